@@ -161,6 +161,7 @@ impl LiveNew for AudioComponentRef {
             module_id: LiveModuleId::from_str(&module_path!()).unwrap(),
             live_type: LiveType::of::<dyn AudioComponent>(),
             fields: Vec::new(),
+            variants: Vec::new(),
             live_ignore: true,
             type_name: LiveId(0)
         }
@@ -1,10 +1,14 @@
 use {
     crate::{
-        decoration::{Decoration, DecorationType},
+        command::{EditorCommand, Motion},
+        decoration::{Decoration, DecorationSet, DecorationType, LineMarkerType},
         layout::{BlockElement, WrappedElement},
         selection::Affinity,
         session::{SelectionMode, CodeSession},
         history::{NewGroup},
+        keymap::Keymap,
+        regex::{self, RegexError},
+        search::SearchOptions,
         settings::Settings,
         str::StrExt,
         text::Position,
@@ -26,6 +30,9 @@ live_design! {
         delimiter_highlight: #f,
         error_decoration: #f00,
         warning_decoration: #0f0,
+        line_added: #587c0c,
+        line_modified: #0c7d9d,
+        line_deleted: #94151b,
         
         unknown: #C0C0C0,
         branch_keyword: #C485BE,
@@ -147,6 +154,31 @@ live_design! {
         draw_decoration: {
           //  draw_depth: 2.0,
         }
+        draw_search_match: {
+            color: #5a4a2080,
+        }
+        draw_remote_selection: {
+            color: #2a5a8a80,
+        }
+        draw_remote_cursor: {
+            color: #4aa0ff,
+        }
+        draw_line_marker: {
+            draw_depth: 1.0,
+        }
+        draw_popup_bg: {
+            draw_depth: 2.0,
+            color: (THEME_COLOR_FLOATING_BG),
+        }
+        draw_popup_selection: {
+            draw_depth: 2.0,
+            color: (THEME_COLOR_CTRL_SELECTED),
+        }
+        draw_popup_text: {
+            draw_depth: 2.0,
+            text_style: <THEME_FONT_CODE> {},
+            color: (THEME_COLOR_TEXT_DEFAULT),
+        }
         draw_selection: {
            // draw_depth: 3.0,
         }
@@ -229,6 +261,13 @@ pub struct CodeEditor {
     #[live] token_colors: TokenColors,
     #[live] draw_indent_guide: DrawIndentGuide,
     #[live] draw_decoration: DrawDecoration,
+    #[live] draw_search_match: DrawColor,
+    #[live] draw_remote_selection: DrawColor,
+    #[live] draw_remote_cursor: DrawColor,
+    #[live] draw_line_marker: DrawColor,
+    #[live] draw_popup_bg: DrawColor,
+    #[live] draw_popup_selection: DrawColor,
+    #[live] draw_popup_text: DrawText,
     #[live] draw_selection: DrawSelection,
     #[live] draw_cursor: DrawColor,
     #[live] draw_cursor_bg: DrawColor,
@@ -250,10 +289,14 @@ pub struct CodeEditor {
     #[live(2usize)] gutter_pad: usize,
         
     #[live(0.5)] blink_speed: f64,
+    #[live(0.5)] hover_delay: f64,
 
     #[animator] animator: Animator,
 
     #[rust] blink_timer: Timer,
+    #[rust] hover_timer: Timer,
+    #[rust] hover_position: Option<Position>,
+    #[rust] popup: Option<EditorPopup>,
 }
 
 enum KeepCursorInView {
@@ -513,11 +556,14 @@ impl CodeEditor {
         if self.show_gutter{
             self.draw_gutter(cx, session);
         }
+        self.draw_search_match_layer(cx, session);
+        self.draw_remote_selection_layer(cx, session);
         self.draw_selection_layer(cx, session);
         self.draw_text_layer(cx, session);
         self.draw_indent_guide_layer(cx, session);
         self.draw_decoration_layer(cx, session);
         self.draw_selection_layer(cx, session);
+        self.draw_popup(cx, session);
 
         // Get the last added selection.
         // Get the normalized cursor position. To go from normalized to screen position, multiply by
@@ -541,6 +587,10 @@ impl CodeEditor {
         cx.set_key_focus(self.scroll_bars.area());
     }
 
+    pub fn has_key_focus(&self, cx: &Cx) -> bool {
+        cx.has_key_focus(self.scroll_bars.area())
+    }
+
     pub fn set_cursor_and_scroll(
         &mut self,
         cx: &mut Cx,
@@ -552,6 +602,218 @@ impl CodeEditor {
         self.redraw(cx);
     }
 
+    /// Searches the session for `pattern` and selects the first match at or after the cursor.
+    /// Returns the number of matches.
+    pub fn find(
+        &mut self,
+        cx: &mut Cx,
+        session: &mut CodeSession,
+        pattern: &str,
+        options: SearchOptions,
+    ) -> Result<usize, RegexError> {
+        let match_count = session.set_search(pattern, options)?;
+        self.keep_cursor_in_view = KeepCursorInView::JumpToPosition;
+        self.redraw(cx);
+        Ok(match_count)
+    }
+
+    pub fn clear_search(&mut self, cx: &mut Cx, session: &mut CodeSession) {
+        session.clear_search();
+        self.redraw(cx);
+    }
+
+    pub fn find_next(&mut self, cx: &mut Cx, session: &mut CodeSession) -> bool {
+        if !session.select_next_match() {
+            return false;
+        }
+        self.keep_cursor_in_view = KeepCursorInView::JumpToPosition;
+        self.redraw(cx);
+        true
+    }
+
+    pub fn find_prev(&mut self, cx: &mut Cx, session: &mut CodeSession) -> bool {
+        if !session.select_prev_match() {
+            return false;
+        }
+        self.keep_cursor_in_view = KeepCursorInView::JumpToPosition;
+        self.redraw(cx);
+        true
+    }
+
+    /// Replaces the selected match and moves on to the next one. Returns whether the text changed.
+    pub fn replace_current(
+        &mut self,
+        cx: &mut Cx,
+        session: &mut CodeSession,
+        replacement: &str,
+    ) -> bool {
+        if self.read_only {
+            return false;
+        }
+        let did_replace = session.replace_current_match(replacement);
+        self.keep_cursor_in_view = KeepCursorInView::JumpToPosition;
+        self.redraw(cx);
+        did_replace
+    }
+
+    /// Replaces every match as a single undo group. Returns the number of replaced matches.
+    pub fn replace_all(
+        &mut self,
+        cx: &mut Cx,
+        session: &mut CodeSession,
+        replacement: &str,
+    ) -> usize {
+        if self.read_only {
+            return 0;
+        }
+        let replace_count = session.replace_all_matches(replacement);
+        self.redraw(cx);
+        replace_count
+    }
+
+    /// Shows `text` in a popup below `position`, in response to `CodeEditorAction::Hover`. The
+    /// text is ignored if the pointer has moved away from `position` in the meantime.
+    pub fn show_hover(&mut self, cx: &mut Cx, position: Position, text: &str) {
+        if self.hover_position != Some(position) {
+            return;
+        }
+        let lines: Vec<_> = text
+            .trim_end()
+            .lines()
+            .take(MAX_POPUP_ROW_COUNT)
+            .map(|line| line.to_string())
+            .collect();
+        if lines.is_empty() {
+            return;
+        }
+        self.popup = Some(EditorPopup::Hover { position, lines });
+        self.redraw(cx);
+    }
+
+    /// Shows a list of completions for the word that ends at `position`, in response to
+    /// `CodeEditorAction::RequestCompletion`. The list is filtered as the word is typed.
+    pub fn show_completions(
+        &mut self,
+        cx: &mut Cx,
+        session: &mut CodeSession,
+        position: Position,
+        items: Vec<CompletionItem>,
+    ) {
+        let start = {
+            let text = session.document().as_text();
+            let Some(line) = text.as_lines().get(position.line_index) else {
+                return;
+            };
+            let Some(before) = line.get(..position.byte_index) else {
+                return;
+            };
+            let word_len: usize = before
+                .chars()
+                .rev()
+                .take_while(|&char| regex::is_word_char(char))
+                .map(|char| char.len_utf8())
+                .sum();
+            Position {
+                line_index: position.line_index,
+                byte_index: position.byte_index - word_len,
+            }
+        };
+        self.popup = Some(EditorPopup::Completion {
+            start,
+            items,
+            visible_items: Vec::new(),
+            selected_index: 0,
+        });
+        self.update_completions(session);
+        self.redraw(cx);
+    }
+
+    pub fn hide_popup(&mut self, cx: &mut Cx) {
+        if self.popup.take().is_some() {
+            self.redraw(cx);
+        }
+    }
+
+    fn cursor_position(&self, session: &CodeSession) -> Position {
+        session.selections()[session.last_added_selection_index().unwrap()]
+            .cursor
+            .position
+    }
+
+    fn update_completions(&mut self, session: &mut CodeSession) {
+        session.handle_changes();
+        let cursor = self.cursor_position(session);
+        let Some(EditorPopup::Completion {
+            start,
+            items,
+            visible_items,
+            selected_index,
+        }) = &mut self.popup
+        else {
+            return;
+        };
+        let text = session.document().as_text();
+        let prefix = if cursor.line_index == start.line_index
+            && cursor.byte_index >= start.byte_index
+        {
+            text.as_lines()[cursor.line_index]
+                .get(start.byte_index..cursor.byte_index)
+                .filter(|prefix| prefix.chars().all(regex::is_word_char))
+                .map(|prefix| prefix.to_lowercase())
+        } else {
+            None
+        };
+        drop(text);
+        if let Some(prefix) = prefix {
+            *visible_items = items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.label.to_lowercase().starts_with(&prefix))
+                .map(|(index, _)| index)
+                .collect();
+            *selected_index = (*selected_index).min(visible_items.len().saturating_sub(1));
+            if !visible_items.is_empty() {
+                return;
+            }
+        }
+        self.popup = None;
+    }
+
+    fn popup_handles_key(&self, key_code: KeyCode) -> bool {
+        match self.popup {
+            Some(EditorPopup::Hover { .. }) => key_code == KeyCode::Escape,
+            Some(EditorPopup::Completion { .. }) => matches!(
+                key_code,
+                KeyCode::ArrowUp
+                    | KeyCode::ArrowDown
+                    | KeyCode::ReturnKey
+                    | KeyCode::Tab
+                    | KeyCode::Escape
+            ),
+            None => false,
+        }
+    }
+
+    fn accept_completion(&mut self, session: &mut CodeSession) -> bool {
+        let Some(EditorPopup::Completion {
+            start,
+            items,
+            visible_items,
+            selected_index,
+        }) = self.popup.take()
+        else {
+            return false;
+        };
+        let Some(&item_index) = visible_items.get(selected_index) else {
+            return false;
+        };
+        let cursor = self.cursor_position(session);
+        session.set_selection(start, Affinity::After, SelectionMode::Simple, NewGroup::No);
+        session.move_to(cursor, Affinity::Before, NewGroup::No);
+        session.paste(items[item_index].insert_text.as_str().into());
+        true
+    }
+
     pub fn reset_font_size(&mut self) {
         self.draw_gutter.text_style.font_size = 9.0;
         self.draw_text.text_style.font_size = 9.0;
@@ -591,6 +853,143 @@ impl CodeEditor {
         }
     }
 
+    /// Runs a command as if its keys were pressed in this editor.
+    pub fn run_command(
+        &mut self,
+        cx: &mut Cx,
+        session: &mut CodeSession,
+        command: EditorCommand,
+    ) -> Vec<CodeEditorAction> {
+        let mut actions = Vec::new();
+        if command.is_edit() && self.read_only {
+            return actions;
+        }
+        let mut moved_cursor = true;
+        match command {
+            EditorCommand::ZoomIn | EditorCommand::ZoomOut | EditorCommand::ResetZoom => {
+                match command {
+                    EditorCommand::ZoomIn => self.increase_font_size(),
+                    EditorCommand::ZoomOut => self.decrease_font_size(),
+                    _ => self.reset_font_size(),
+                }
+                moved_cursor = false;
+            }
+            EditorCommand::ToggleWordWrap => {
+                self.word_wrap = !self.word_wrap;
+                moved_cursor = false;
+            }
+            EditorCommand::FindSelection => {
+                // Search for the selected text, if it fits on a single line.
+                let pattern = session.copy();
+                if !pattern.is_empty() && !pattern.contains('\n') {
+                    let options = session
+                        .search_query()
+                        .as_ref()
+                        .map_or(SearchOptions::default(), |query| query.options());
+                    let options = SearchOptions {
+                        regex: false,
+                        ..options
+                    };
+                    if self.find(cx, session, &pattern, options).is_ok() {
+                        actions.push(CodeEditorAction::SearchDidChange);
+                    }
+                }
+                moved_cursor = false;
+            }
+            EditorCommand::FindNext => {
+                self.find_next(cx, session);
+                moved_cursor = false;
+            }
+            EditorCommand::FindPrevious => {
+                self.find_prev(cx, session);
+                moved_cursor = false;
+            }
+            EditorCommand::GoToDefinition => {
+                actions.push(CodeEditorAction::GoToDefinition(self.cursor_position(session)));
+                moved_cursor = false;
+            }
+            EditorCommand::TriggerCompletion => {
+                actions.push(CodeEditorAction::RequestCompletion(self.cursor_position(session)));
+                moved_cursor = false;
+            }
+            EditorCommand::SelectAll => {
+                let ((cursor, affinity), _is_in_gutter) = self.pick(session, dvec2(0.0, 0.0));
+                session.set_selection(cursor, affinity, SelectionMode::All, NewGroup::Yes);
+                self.reset_cursor_blinker(cx);
+                self.keep_cursor_in_view = KeepCursorInView::Off;
+                moved_cursor = false;
+            }
+            EditorCommand::Move(motion) => self.move_cursors(session, motion, true),
+            EditorCommand::Select(motion) => self.move_cursors(session, motion, false),
+            EditorCommand::NewLine => session.enter(),
+            EditorCommand::Indent => session.indent(),
+            EditorCommand::Outdent => session.outdent(),
+            EditorCommand::Delete => session.delete(),
+            EditorCommand::DeleteWordRight => session.delete_word_right(),
+            EditorCommand::DeleteSubwordRight => session.delete_subword_right(),
+            EditorCommand::DeleteParagraphDown => session.delete_paragraph_down(),
+            EditorCommand::Backspace => session.backspace(),
+            EditorCommand::DeleteWordLeft => session.delete_word_left(),
+            EditorCommand::DeleteSubwordLeft => session.delete_subword_left(),
+            EditorCommand::DeleteParagraphUp => session.delete_paragraph_up(),
+            EditorCommand::DeleteToMatchingDelimiter => session.delete_to_matching_delimiter(),
+            EditorCommand::Undo => {
+                if !session.undo() {
+                    return actions;
+                }
+                cx.redraw_all();
+            }
+            EditorCommand::Redo => {
+                if !session.redo() {
+                    return actions;
+                }
+            }
+            EditorCommand::RedoNextBranch => {
+                if !session.redo_next_branch() {
+                    return actions;
+                }
+            }
+        }
+        if command.is_edit() && command != EditorCommand::TriggerCompletion {
+            actions.push(CodeEditorAction::TextDidChange);
+        }
+        if moved_cursor {
+            self.keep_cursor_in_view = KeepCursorInView::Once;
+            self.reset_cursor_blinker(cx);
+        }
+        self.redraw(cx);
+        actions
+    }
+
+    fn move_cursors(&mut self, session: &mut CodeSession, motion: Motion, reset_anchor: bool) {
+        match motion {
+            Motion::Left => session.move_left(reset_anchor),
+            Motion::Right => session.move_right(reset_anchor),
+            Motion::WordLeft => session.move_word_left(reset_anchor),
+            Motion::WordRight => session.move_word_right(reset_anchor),
+            Motion::SubwordLeft => session.move_subword_left(reset_anchor),
+            Motion::SubwordRight => session.move_subword_right(reset_anchor),
+            Motion::Up => session.move_up(reset_anchor),
+            Motion::Down => session.move_down(reset_anchor),
+            Motion::ParagraphUp => session.move_paragraph_up(reset_anchor),
+            Motion::ParagraphDown => session.move_paragraph_down(reset_anchor),
+            Motion::Home => session.home(reset_anchor),
+            Motion::End => session.end(reset_anchor),
+            Motion::DocumentStart => session.move_to_document_start(reset_anchor),
+            Motion::DocumentEnd => session.move_to_document_end(reset_anchor),
+            Motion::PageUp | Motion::PageDown => {
+                for _ in 0..self.line_end - self.line_start - 3 {
+                    if motion == Motion::PageUp {
+                        session.move_up(reset_anchor);
+                    } else {
+                        session.move_down(reset_anchor);
+                    }
+                }
+            }
+            Motion::MatchingDelimiter => session.move_to_matching_delimiter(reset_anchor),
+        }
+    }
+
     pub fn handle_event(
         &mut self,
         cx: &mut Cx,
@@ -616,8 +1015,42 @@ impl CodeEditor {
             }
             self.blink_timer = cx.start_timeout(self.blink_speed)
         }
+        if self.hover_timer.is_event(event).is_some() {
+            if let Some(position) = self.hover_position {
+                actions.push(CodeEditorAction::Hover(position));
+            }
+        }
         let mut keyboard_moved_cursor = false;
+        let mut popup_handled_key = false;
+        let cursor_before = self.cursor_position(session);
         match event.hits(cx, self.scroll_bars.area()) {
+            Hit::KeyDown(KeyEvent { key_code, .. }) if self.popup_handles_key(key_code) => {
+                popup_handled_key = true;
+                match key_code {
+                    KeyCode::ArrowUp | KeyCode::ArrowDown => {
+                        if let Some(EditorPopup::Completion {
+                            visible_items,
+                            selected_index,
+                            ..
+                        }) = &mut self.popup
+                        {
+                            *selected_index = if key_code == KeyCode::ArrowUp {
+                                selected_index.checked_sub(1).unwrap_or(visible_items.len() - 1)
+                            } else {
+                                (*selected_index + 1) % visible_items.len()
+                            };
+                        }
+                    }
+                    KeyCode::ReturnKey | KeyCode::Tab => {
+                        if self.accept_completion(session) {
+                            keyboard_moved_cursor = true;
+                            actions.push(CodeEditorAction::TextDidChange);
+                        }
+                    }
+                    _ => self.popup = None,
+                }
+                self.redraw(cx);
+            }
             Hit::KeyFocusLost(_) => {
                 self.animator_play(cx, id!(focus.off));
             }
@@ -645,162 +1078,15 @@ impl CodeEditor {
                 }
                 self.redraw(cx);
             }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::Minus,
-                modifiers: KeyModifiers { control, logo, .. },
-                ..
-            }) => {
-                if control || logo {
-                    self.decrease_font_size();
-                    self.redraw(cx);
-                }
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::Key0,
-                modifiers: KeyModifiers { control, logo, .. },
-                ..
-            }) => {
-                if control || logo {
-                    self.reset_font_size();
-                    self.redraw(cx);
-                }
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::Equals,
-                modifiers: KeyModifiers { control, logo, .. },
-                ..
-            }) => {
-                if control || logo {
-                    self.increase_font_size();
-                    self.redraw(cx);
-                }
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::KeyW,
-                modifiers: KeyModifiers { control, logo, .. },
-                ..
-            }) => {
-                if control || logo {
-                    self.word_wrap = !self.word_wrap;
-                    self.redraw(cx);
-                }
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::KeyA,
-                modifiers: KeyModifiers {control, logo, ..},
-                ..
-            }) => {
-                if control || logo {
-                    let ((cursor, affinity), _is_in_gutter) = self.pick(session, dvec2(0.0,0.0));
-                    session.set_selection(
-                        cursor,
-                        affinity,
-                        SelectionMode::All,
-                        NewGroup::Yes
-                    );
-                    self.reset_cursor_blinker(cx);
-                    self.keep_cursor_in_view = KeepCursorInView::Off;
-                    self.redraw(cx);
+            Hit::KeyDown(key_event) => {
+                let command = cx
+                    .global::<Keymap>()
+                    .resolve(&key_event)
+                    .and_then(|id| EditorCommand::from_id(&id));
+                if let Some(command) = command {
+                    actions.extend(self.run_command(cx, session, command));
                 }
             }
-            
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::ArrowLeft,
-                modifiers:
-                    KeyModifiers {
-                        shift,
-                        control,
-                        logo,
-                        ..
-                    },
-                ..
-            }) => {
-                if control || logo {
-                    //session.move_to_start_of_line(!shift);
-                } else {
-                    session.move_left(!shift);
-                }
-                keyboard_moved_cursor = true;
-                self.redraw(cx);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::ArrowRight,
-                modifiers:
-                    KeyModifiers {
-                        shift,
-                        control,
-                        logo,
-                        ..
-                    },
-                ..
-            }) => {
-                if control || logo {
-                    //session.move_to_end_of_line(!shift);
-                } else {
-                    session.move_right(!shift);
-                }
-
-                keyboard_moved_cursor = true;
-                self.redraw(cx);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::ArrowUp,
-                modifiers: KeyModifiers { shift, .. },
-                ..
-            }) => {
-                session.move_up(!shift);
-                keyboard_moved_cursor = true;
-                self.redraw(cx);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::ArrowDown,
-                modifiers: KeyModifiers { shift, .. },
-                ..
-            }) => {
-                session.move_down(!shift);
-                keyboard_moved_cursor = true;
-                self.redraw(cx);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::Home,
-                modifiers: KeyModifiers { shift, .. },
-                ..
-            }) => {
-                session.home(!shift);
-                keyboard_moved_cursor = true;
-                self.redraw(cx);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::End,
-                modifiers: KeyModifiers { shift, .. },
-                ..
-            }) => {
-                session.end(!shift);
-                keyboard_moved_cursor = true;
-                self.redraw(cx);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::PageUp,
-                modifiers: KeyModifiers { shift, .. },
-                ..
-            }) => {
-                for _ in 0..self.line_end - self.line_start - 3 {
-                    session.move_up(!shift);
-                }
-                keyboard_moved_cursor = true;
-                self.redraw(cx);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::PageDown,
-                modifiers: KeyModifiers { shift, .. },
-                ..
-            }) => {
-                for _ in 0..self.line_end - self.line_start - 3 {
-                    session.move_down(!shift);
-                }
-                keyboard_moved_cursor = true;
-                self.redraw(cx);
-            }
             Hit::TextInput(TextInputEvent {
                 ref input,
                 was_paste: false,
@@ -821,53 +1107,6 @@ impl CodeEditor {
                 keyboard_moved_cursor = true;
                 actions.push(CodeEditorAction::TextDidChange);
             }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::ReturnKey,
-                ..
-            }) => {
-                session.enter();
-                self.redraw(cx);
-                keyboard_moved_cursor = true;
-                actions.push(CodeEditorAction::TextDidChange);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::Tab,
-                modifiers: KeyModifiers { shift: false, .. },
-                ..
-            }) if !self.read_only => {
-                session.indent();
-                self.redraw(cx);
-                keyboard_moved_cursor = true;
-                actions.push(CodeEditorAction::TextDidChange);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::Tab,
-                modifiers: KeyModifiers { shift: true, .. },
-                ..
-            }) if !self.read_only=> {
-                session.outdent();
-                self.redraw(cx);
-                keyboard_moved_cursor = true;
-                actions.push(CodeEditorAction::TextDidChange);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::Delete,
-                ..
-            }) if !self.read_only=> {
-                session.delete();
-                self.redraw(cx);
-                keyboard_moved_cursor = true;
-                actions.push(CodeEditorAction::TextDidChange);
-            }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::Backspace,
-                ..
-            }) if !self.read_only=> {
-                session.backspace();
-                self.redraw(cx);
-                keyboard_moved_cursor = true;
-                actions.push(CodeEditorAction::TextDidChange);
-            }
             Hit::TextCopy(ce) => {
                 *ce.response.borrow_mut() = Some(session.copy());
                 keyboard_moved_cursor = true;
@@ -878,40 +1117,6 @@ impl CodeEditor {
                 keyboard_moved_cursor = true;
                 self.redraw(cx);
             }
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::KeyZ,
-                modifiers:
-                    KeyModifiers {
-                        logo: true,
-                        shift: false,
-                        ..
-                    },
-                ..
-            }) if !self.read_only => {
-                if session.undo() {
-                    cx.redraw_all();
-                    actions.push(CodeEditorAction::TextDidChange);
-                    keyboard_moved_cursor = true;
-                }
-            }
-            
-            
-            Hit::KeyDown(KeyEvent {
-                key_code: KeyCode::KeyZ,
-                modifiers:
-                    KeyModifiers {
-                        logo: true,
-                        shift: true,
-                        ..
-                    },
-                ..
-            }) if !self.read_only => {
-                if session.redo() {
-                    self.redraw(cx);
-                    actions.push(CodeEditorAction::TextDidChange);
-                    keyboard_moved_cursor = true;
-                }
-            }
             Hit::FingerDown(FingerDownEvent {
                 abs,
                 tap_count,
@@ -981,8 +1186,34 @@ impl CodeEditor {
                 self.reset_cursor_blinker(cx);
                 self.keep_cursor_in_view = KeepCursorInView::Off;
             }
-            Hit::FingerHoverIn(_) | Hit::FingerHoverOver(_) => {
+            Hit::FingerHoverIn(FingerHoverEvent { abs, .. })
+            | Hit::FingerHoverOver(FingerHoverEvent { abs, .. }) => {
                 cx.set_cursor(MouseCursor::Text);
+                let ((position, _), is_in_gutter) = self.pick(session, abs);
+                let position = if is_in_gutter { None } else { Some(position) };
+                if position != self.hover_position {
+                    self.hover_position = position;
+                    cx.stop_timer(self.hover_timer);
+                    if position.is_some() {
+                        self.hover_timer = cx.start_timeout(self.hover_delay);
+                    }
+                    if let Some(EditorPopup::Hover {
+                        position: hover_position,
+                        ..
+                    }) = self.popup
+                    {
+                        if position.map_or(true, |position| {
+                            !is_in_same_word(session, hover_position, position)
+                        }) {
+                            self.popup = None;
+                            self.redraw(cx);
+                        }
+                    }
+                }
+            }
+            Hit::FingerHoverOut(_) => {
+                cx.stop_timer(self.hover_timer);
+                self.hover_position = None;
             }
             Hit::FingerDown(FingerDownEvent {
                 abs,
@@ -1006,6 +1237,16 @@ impl CodeEditor {
             self.keep_cursor_in_view = KeepCursorInView::Once;
             self.reset_cursor_blinker(cx);
         }
+        if self.popup.is_some() && !popup_handled_key {
+            session.handle_changes();
+            if self.cursor_position(session) != cursor_before {
+                match self.popup {
+                    Some(EditorPopup::Completion { .. }) => self.update_completions(session),
+                    _ => self.popup = None,
+                }
+                self.redraw(cx);
+            }
+        }
         if let KeepCursorInView::Always(abs, next) = &mut self.keep_cursor_in_view {
             if next.is_event(event).is_some() {
                 *next = cx.new_next_frame();
@@ -1022,12 +1263,41 @@ impl CodeEditor {
         let mut line_index = self.line_start;
         let mut origin_y = session.layout().line(self.line_start).y();
         let mut buf = String::new();
+        let line_markers = session.document().line_markers();
+        let mut line_markers = line_markers.iter().peekable();
         for element in session
             .layout()
             .block_elements(self.line_start, self.line_end)
         {
             match element {
                 BlockElement::Line { line, .. } => {
+                    while line_markers
+                        .peek()
+                        .map_or(false, |marker| marker.line_end.max(marker.line_start + 1) <= line_index)
+                    {
+                        line_markers.next();
+                    }
+                    if let Some(marker) = line_markers.peek().filter(|marker| marker.contains_line(line_index)) {
+                        // Markers go in the gap between the line numbers and the text. Deleted
+                        // lines have no line of their own, so they get a notch at the top of the
+                        // line that follows them instead.
+                        let (color, height) = match marker.ty {
+                            LineMarkerType::Added => (self.token_colors.line_added, line.height()),
+                            LineMarkerType::Modified => (self.token_colors.line_modified, line.height()),
+                            LineMarkerType::Deleted => (self.token_colors.line_deleted, 0.25 * line.scale()),
+                        };
+                        self.draw_line_marker.color = color;
+                        self.draw_line_marker.draw_abs(
+                            cx,
+                            Rect {
+                                pos: dvec2(
+                                    self.viewport_rect.pos.x - 0.8 * self.cell_size.x,
+                                    self.gutter_rect.pos.y + origin_y * self.cell_size.y,
+                                ),
+                                size: dvec2(0.35 * self.cell_size.x, height * self.cell_size.y),
+                            },
+                        );
+                    }
                     self.draw_gutter.font_scale = line.scale();
                     buf.clear();
                     match self.gutter_chars{
@@ -1222,9 +1492,119 @@ impl CodeEditor {
         }
     }
 
+    fn draw_popup(&mut self, cx: &mut Cx2d<'_>, session: &CodeSession) {
+        let (position, rows, selected_row) = match &self.popup {
+            Some(EditorPopup::Hover { position, lines }) => (*position, lines.clone(), None),
+            Some(EditorPopup::Completion {
+                start,
+                items,
+                visible_items,
+                selected_index,
+            }) => {
+                // Scroll the list so that the selected item is always visible.
+                let first_row = (selected_index + 1).saturating_sub(MAX_POPUP_ROW_COUNT);
+                let rows = visible_items
+                    .iter()
+                    .skip(first_row)
+                    .take(MAX_POPUP_ROW_COUNT)
+                    .map(|&index| {
+                        let item = &items[index];
+                        match &item.detail {
+                            Some(detail) => format!("{}  {}", item.label, detail),
+                            None => item.label.clone(),
+                        }
+                    })
+                    .collect();
+                (*start, rows, Some(selected_index - first_row))
+            }
+            None => return,
+        };
+        if position.line_index >= session.document().as_text().as_lines().len() {
+            return;
+        }
+        let (x, y) = session
+            .layout()
+            .logical_to_normalized_position(position, Affinity::Before);
+        let column_count = rows
+            .iter()
+            .map(|row| row.column_count())
+            .max()
+            .unwrap_or(0)
+            .min(MAX_POPUP_COLUMN_COUNT);
+        let pad = dvec2(0.5, 0.25) * self.cell_size;
+        let rect = Rect {
+            pos: dvec2(x, y + 1.0) * self.cell_size + self.viewport_rect.pos,
+            size: dvec2(column_count as f64, rows.len() as f64) * self.cell_size + pad * 2.0,
+        };
+        self.draw_popup_bg.draw_abs(cx, rect);
+        for (row_index, row) in rows.iter().enumerate() {
+            let row_pos = rect.pos + pad + dvec2(0.0, row_index as f64 * self.cell_size.y);
+            if selected_row == Some(row_index) {
+                self.draw_popup_selection.draw_abs(
+                    cx,
+                    Rect {
+                        pos: dvec2(rect.pos.x, row_pos.y),
+                        size: dvec2(rect.size.x, self.cell_size.y),
+                    },
+                );
+            }
+            let row: String = row.chars().take(MAX_POPUP_COLUMN_COUNT).collect();
+            self.draw_popup_text.draw_abs(cx, row_pos, &row);
+        }
+    }
+
     fn draw_decoration_layer(&mut self, cx: &mut Cx2d<'_>, session: &CodeSession) {
-        let mut active_decoration = None;
         let decorations = session.document().decorations();
+        self.draw_decorations(cx, session, &decorations);
+    }
+
+    fn draw_search_match_layer(&mut self, cx: &mut Cx2d<'_>, session: &CodeSession) {
+        let search_matches = session.search_matches();
+        self.draw_decorations(cx, session, &search_matches);
+    }
+
+    // Draws the selections and cursors of the other participants in a collaboration session.
+    fn draw_remote_selection_layer(&mut self, cx: &mut Cx2d<'_>, session: &CodeSession) {
+        let remote_selections = session.document().remote_selections().to_vec();
+        if remote_selections.is_empty() {
+            return;
+        }
+        let mut decorations = DecorationSet::new();
+        for selection in &remote_selections {
+            decorations.add_decoration(Decoration::new(
+                0,
+                selection.start(),
+                selection.end(),
+                DecorationType::RemoteSelection,
+            ));
+        }
+        self.draw_decorations(cx, session, &decorations);
+        let line_count = session.document().as_text().as_lines().len();
+        for selection in &remote_selections {
+            let line_index = selection.cursor.line_index;
+            if line_index >= line_count || line_index < self.line_start || line_index >= self.line_end {
+                continue;
+            }
+            let (x, y) = session
+                .layout()
+                .logical_to_normalized_position(selection.cursor, Affinity::Before);
+            self.draw_remote_cursor.draw_abs(
+                cx,
+                Rect {
+                    pos: dvec2(x, y) * self.cell_size + self.viewport_rect.pos - dvec2(1.0, 0.0),
+                    size: dvec2(2.0, self.cell_size.y),
+                },
+            );
+        }
+    }
+
+    fn draw_decorations(
+        &mut self,
+        cx: &mut Cx2d<'_>,
+        session: &CodeSession,
+        decorations: &[Decoration],
+    ) {
+        let mut active_decoration = None;
         let mut decorations = decorations.iter();
         while decorations.as_slice().first().map_or(false, |decoration| {
             decoration.end().line_index < self.line_start
@@ -1487,9 +1867,49 @@ impl CodeEditor {
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, DefaultNone)]
 pub enum CodeEditorAction {
     TextDidChange,
+    SearchDidChange,
+    Hover(Position),
+    GoToDefinition(Position),
+    RequestCompletion(Position),
     None
 }
 
+fn is_in_same_word(session: &CodeSession, a: Position, b: Position) -> bool {
+    if a.line_index != b.line_index {
+        return false;
+    }
+    let text = session.document().as_text();
+    let Some(line) = text.as_lines().get(a.line_index) else {
+        return false;
+    };
+    let (start, end) = (a.byte_index.min(b.byte_index), a.byte_index.max(b.byte_index));
+    line.get(start..end)
+        .map_or(false, |between| between.chars().all(regex::is_word_char))
+}
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub detail: Option<String>,
+    pub insert_text: String,
+}
+
+const MAX_POPUP_ROW_COUNT: usize = 12;
+const MAX_POPUP_COLUMN_COUNT: usize = 100;
+
+enum EditorPopup {
+    Hover {
+        position: Position,
+        lines: Vec<String>,
+    },
+    Completion {
+        start: Position,
+        items: Vec<CompletionItem>,
+        visible_items: Vec<usize>,
+        selected_index: usize,
+    },
+}
+
 struct DrawDecorationLayer<'a> {
     code_editor: &'a mut CodeEditor,
     active_decoration: Option<ActiveDecoration>,
@@ -1656,26 +2076,31 @@ impl<'a> DrawDecorationLayer<'a> {
     ) {
         let start_x = mem::take(&mut self.active_decoration.as_mut().unwrap().start_x);
         let (x, y) = line.grid_to_normalized_position(row_index, column_index);
-        self.code_editor.draw_decoration.color =
-            match self.active_decoration.as_mut().unwrap().decoration.ty {
-                DecorationType::Warning => self.code_editor.token_colors.warning_decoration,
-                DecorationType::Error => self.code_editor.token_colors.error_decoration,
-            };
-
-        self.code_editor.draw_decoration.draw_abs(
-            cx,
-            Rect {
-                pos: DVec2 {
-                    x: start_x,
-                    y: origin_y + y,
-                } * self.code_editor.cell_size
-                    + self.code_editor.viewport_rect.pos,
-                size: DVec2 {
-                    x: x - start_x,
-                    y: line.scale(),
-                } * self.code_editor.cell_size,
-            },
-        );
+        let rect = Rect {
+            pos: DVec2 {
+                x: start_x,
+                y: origin_y + y,
+            } * self.code_editor.cell_size
+                + self.code_editor.viewport_rect.pos,
+            size: DVec2 {
+                x: x - start_x,
+                y: line.scale(),
+            } * self.code_editor.cell_size,
+        };
+        let color = match self.active_decoration.as_mut().unwrap().decoration.ty {
+            DecorationType::Warning => self.code_editor.token_colors.warning_decoration,
+            DecorationType::Error => self.code_editor.token_colors.error_decoration,
+            DecorationType::SearchMatch => {
+                self.code_editor.draw_search_match.draw_abs(cx, rect);
+                return;
+            }
+            DecorationType::RemoteSelection => {
+                self.code_editor.draw_remote_selection.draw_abs(cx, rect);
+                return;
+            }
+        };
+        self.code_editor.draw_decoration.color = color;
+        self.code_editor.draw_decoration.draw_abs(cx, rect);
     }
 }
 
@@ -1970,6 +2395,12 @@ struct TokenColors {
     error_decoration: Vec4,
     #[live]
     warning_decoration: Vec4,
+    #[live]
+    line_added: Vec4,
+    #[live]
+    line_modified: Vec4,
+    #[live]
+    line_deleted: Vec4,
 }
 
 #[derive(Live, LiveHook, LiveRegister)]
@@ -0,0 +1,169 @@
+/// The id and title of a command. Ids are dotted names such as `editor.undo`, which keymaps bind
+/// keys to, and titles are what a command palette shows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CommandInfo {
+    pub id: &'static str,
+    pub title: &'static str,
+}
+
+/// A command that runs in a code editor. See `EDITOR_COMMANDS` for their ids.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EditorCommand {
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    ToggleWordWrap,
+    FindSelection,
+    FindNext,
+    FindPrevious,
+    GoToDefinition,
+    TriggerCompletion,
+    SelectAll,
+    /// Moves every cursor, dropping the selections.
+    Move(Motion),
+    /// Moves every cursor, extending the selections.
+    Select(Motion),
+    NewLine,
+    Indent,
+    Outdent,
+    Delete,
+    DeleteWordRight,
+    DeleteSubwordRight,
+    DeleteParagraphDown,
+    Backspace,
+    DeleteWordLeft,
+    DeleteSubwordLeft,
+    DeleteParagraphUp,
+    DeleteToMatchingDelimiter,
+    Undo,
+    Redo,
+    RedoNextBranch,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Motion {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    SubwordLeft,
+    SubwordRight,
+    Up,
+    Down,
+    ParagraphUp,
+    ParagraphDown,
+    Home,
+    End,
+    DocumentStart,
+    DocumentEnd,
+    PageUp,
+    PageDown,
+    MatchingDelimiter,
+}
+
+impl EditorCommand {
+    pub fn from_id(id: &str) -> Option<Self> {
+        EDITOR_COMMANDS
+            .iter()
+            .find(|(_, info)| info.id == id)
+            .map(|(command, _)| *command)
+    }
+
+    pub fn info(self) -> CommandInfo {
+        EDITOR_COMMANDS
+            .iter()
+            .find(|(command, _)| *command == self)
+            .map(|(_, info)| *info)
+            .unwrap()
+    }
+
+    /// Returns whether this command only runs in an editor that is not read-only.
+    pub fn is_edit(self) -> bool {
+        matches!(
+            self,
+            Self::NewLine
+                | Self::Indent
+                | Self::Outdent
+                | Self::Delete
+                | Self::DeleteWordRight
+                | Self::DeleteSubwordRight
+                | Self::DeleteParagraphDown
+                | Self::Backspace
+                | Self::DeleteWordLeft
+                | Self::DeleteSubwordLeft
+                | Self::DeleteParagraphUp
+                | Self::DeleteToMatchingDelimiter
+                | Self::Undo
+                | Self::Redo
+                | Self::RedoNextBranch
+                | Self::TriggerCompletion
+        )
+    }
+}
+
+const fn info(id: &'static str, title: &'static str) -> CommandInfo {
+    CommandInfo { id, title }
+}
+
+/// Every editor command, with its id and title.
+pub const EDITOR_COMMANDS: &[(EditorCommand, CommandInfo)] = &[
+    (EditorCommand::ZoomIn, info("editor.zoom_in", "Zoom In")),
+    (EditorCommand::ZoomOut, info("editor.zoom_out", "Zoom Out")),
+    (EditorCommand::ResetZoom, info("editor.reset_zoom", "Reset Zoom")),
+    (EditorCommand::ToggleWordWrap, info("editor.toggle_word_wrap", "Toggle Word Wrap")),
+    (EditorCommand::FindSelection, info("editor.find_selection", "Find Selected Text")),
+    (EditorCommand::FindNext, info("editor.find_next", "Find Next")),
+    (EditorCommand::FindPrevious, info("editor.find_previous", "Find Previous")),
+    (EditorCommand::GoToDefinition, info("editor.go_to_definition", "Go to Definition")),
+    (EditorCommand::TriggerCompletion, info("editor.trigger_completion", "Trigger Completion")),
+    (EditorCommand::SelectAll, info("editor.select_all", "Select All")),
+    (EditorCommand::Move(Motion::Left), info("editor.cursor_left", "Cursor Left")),
+    (EditorCommand::Select(Motion::Left), info("editor.select_left", "Select Left")),
+    (EditorCommand::Move(Motion::Right), info("editor.cursor_right", "Cursor Right")),
+    (EditorCommand::Select(Motion::Right), info("editor.select_right", "Select Right")),
+    (EditorCommand::Move(Motion::WordLeft), info("editor.cursor_word_left", "Cursor Word Left")),
+    (EditorCommand::Select(Motion::WordLeft), info("editor.select_word_left", "Select Word Left")),
+    (EditorCommand::Move(Motion::WordRight), info("editor.cursor_word_right", "Cursor Word Right")),
+    (EditorCommand::Select(Motion::WordRight), info("editor.select_word_right", "Select Word Right")),
+    (EditorCommand::Move(Motion::SubwordLeft), info("editor.cursor_subword_left", "Cursor Subword Left")),
+    (EditorCommand::Select(Motion::SubwordLeft), info("editor.select_subword_left", "Select Subword Left")),
+    (EditorCommand::Move(Motion::SubwordRight), info("editor.cursor_subword_right", "Cursor Subword Right")),
+    (EditorCommand::Select(Motion::SubwordRight), info("editor.select_subword_right", "Select Subword Right")),
+    (EditorCommand::Move(Motion::Up), info("editor.cursor_up", "Cursor Up")),
+    (EditorCommand::Select(Motion::Up), info("editor.select_up", "Select Up")),
+    (EditorCommand::Move(Motion::Down), info("editor.cursor_down", "Cursor Down")),
+    (EditorCommand::Select(Motion::Down), info("editor.select_down", "Select Down")),
+    (EditorCommand::Move(Motion::ParagraphUp), info("editor.cursor_paragraph_up", "Cursor Paragraph Up")),
+    (EditorCommand::Select(Motion::ParagraphUp), info("editor.select_paragraph_up", "Select Paragraph Up")),
+    (EditorCommand::Move(Motion::ParagraphDown), info("editor.cursor_paragraph_down", "Cursor Paragraph Down")),
+    (EditorCommand::Select(Motion::ParagraphDown), info("editor.select_paragraph_down", "Select Paragraph Down")),
+    (EditorCommand::Move(Motion::Home), info("editor.cursor_home", "Cursor Home")),
+    (EditorCommand::Select(Motion::Home), info("editor.select_home", "Select to Home")),
+    (EditorCommand::Move(Motion::End), info("editor.cursor_end", "Cursor End")),
+    (EditorCommand::Select(Motion::End), info("editor.select_end", "Select to End")),
+    (EditorCommand::Move(Motion::DocumentStart), info("editor.cursor_document_start", "Cursor to Document Start")),
+    (EditorCommand::Select(Motion::DocumentStart), info("editor.select_document_start", "Select to Document Start")),
+    (EditorCommand::Move(Motion::DocumentEnd), info("editor.cursor_document_end", "Cursor to Document End")),
+    (EditorCommand::Select(Motion::DocumentEnd), info("editor.select_document_end", "Select to Document End")),
+    (EditorCommand::Move(Motion::PageUp), info("editor.cursor_page_up", "Cursor Page Up")),
+    (EditorCommand::Select(Motion::PageUp), info("editor.select_page_up", "Select Page Up")),
+    (EditorCommand::Move(Motion::PageDown), info("editor.cursor_page_down", "Cursor Page Down")),
+    (EditorCommand::Select(Motion::PageDown), info("editor.select_page_down", "Select Page Down")),
+    (EditorCommand::Move(Motion::MatchingDelimiter), info("editor.cursor_matching_delimiter", "Go to Matching Delimiter")),
+    (EditorCommand::Select(Motion::MatchingDelimiter), info("editor.select_matching_delimiter", "Select to Matching Delimiter")),
+    (EditorCommand::NewLine, info("editor.new_line", "New Line")),
+    (EditorCommand::Indent, info("editor.indent", "Indent")),
+    (EditorCommand::Outdent, info("editor.outdent", "Outdent")),
+    (EditorCommand::Delete, info("editor.delete", "Delete")),
+    (EditorCommand::DeleteWordRight, info("editor.delete_word_right", "Delete Word Right")),
+    (EditorCommand::DeleteSubwordRight, info("editor.delete_subword_right", "Delete Subword Right")),
+    (EditorCommand::DeleteParagraphDown, info("editor.delete_paragraph_down", "Delete Paragraph Down")),
+    (EditorCommand::Backspace, info("editor.backspace", "Backspace")),
+    (EditorCommand::DeleteWordLeft, info("editor.delete_word_left", "Delete Word Left")),
+    (EditorCommand::DeleteSubwordLeft, info("editor.delete_subword_left", "Delete Subword Left")),
+    (EditorCommand::DeleteParagraphUp, info("editor.delete_paragraph_up", "Delete Paragraph Up")),
+    (EditorCommand::DeleteToMatchingDelimiter, info("editor.delete_to_matching_delimiter", "Delete to Matching Delimiter")),
+    (EditorCommand::Undo, info("editor.undo", "Undo")),
+    (EditorCommand::Redo, info("editor.redo", "Redo")),
+    (EditorCommand::RedoNextBranch, info("editor.redo_next_branch", "Redo Next Branch")),
+];
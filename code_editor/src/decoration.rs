@@ -7,6 +7,8 @@ use {
 pub enum DecorationType {
     Error,
     Warning,
+    SearchMatch,
+    RemoteSelection,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -94,6 +96,10 @@ impl DecorationSet {
         self.decorations.clear();
     }
 
+    pub fn remove_decorations_with_id(&mut self, id: usize) {
+        self.decorations.retain(|decoration| decoration.id != id);
+    }
+
     pub fn apply_edit(&mut self, edit: &Edit) {
         for decoration in &mut self.decorations {
             *decoration = decoration.apply_edit(edit);
@@ -144,3 +150,86 @@ impl<'a> IntoIterator for &'a DecorationSet {
         self.iter()
     }
 }
+
+/// How a range of lines differs from some earlier version of the text, such as the one in
+/// version control. Line markers are drawn in the gutter, next to the line numbers.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LineMarkerType {
+    Added,
+    Modified,
+    /// Lines were removed between `line_start - 1` and `line_start`. The range is empty.
+    Deleted,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct LineMarker {
+    pub id: usize,
+    pub ty: LineMarkerType,
+    pub line_start: usize,
+    pub line_end: usize,
+}
+
+impl LineMarker {
+    pub fn new(id: usize, line_start: usize, line_end: usize, ty: LineMarkerType) -> Self {
+        Self {
+            id,
+            ty,
+            line_start,
+            line_end: line_end.max(line_start),
+        }
+    }
+
+    pub fn contains_line(self, line_index: usize) -> bool {
+        if self.ty == LineMarkerType::Deleted {
+            return line_index == self.line_start;
+        }
+        (self.line_start..self.line_end).contains(&line_index)
+    }
+
+    pub fn apply_edit(self, edit: &Edit) -> Self {
+        let line_start = Position {
+            line_index: self.line_start,
+            byte_index: 0,
+        }
+        .apply_edit(edit)
+        .line_index;
+        let line_end = Position {
+            line_index: self.line_end,
+            byte_index: 0,
+        }
+        .apply_edit(edit)
+        .line_index;
+        Self {
+            line_start,
+            line_end,
+            ..self
+        }
+    }
+}
+
+/// The selection of another participant in a collaboration session, which is drawn along with its
+/// cursor.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RemoteSelection {
+    pub participant: u64,
+    pub anchor: Position,
+    pub cursor: Position,
+}
+
+impl RemoteSelection {
+    pub fn start(self) -> Position {
+        self.anchor.min(self.cursor)
+    }
+
+    pub fn end(self) -> Position {
+        self.anchor.max(self.cursor)
+    }
+
+    pub fn apply_edit(self, edit: &Edit) -> Self {
+        Self {
+            anchor: self.anchor.apply_edit(edit),
+            cursor: self.cursor.apply_edit(edit),
+            ..self
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use {
     crate::{
         char::CharExt,
-        decoration::{Decoration, DecorationSet},
-        history::{EditKind, History},
+        decoration::{Decoration, DecorationSet, LineMarker, RemoteSelection},
+        grammar::{self, Language},
+        history::{EditKind, History, HistoryMode},
         inlays::{BlockInlay, InlineInlay},
         iter::IteratorExt,
         selection::SelectionSet,
@@ -20,7 +21,7 @@ use {
         iter,
         ops::Range,
         rc::Rc,
-        sync::mpsc::Sender,
+        sync::{mpsc, mpsc::Receiver, mpsc::Sender},
     },
 };
 
@@ -29,6 +30,11 @@ pub struct CodeDocument(Rc<DocumentInner>);
 
 impl CodeDocument {
     pub fn new(text: Text, decorations: DecorationSet) -> Self {
+        Self::new_with_language(text, decorations, grammar::RUST)
+    }
+
+    /// Creates a document that is tokenized with the grammar of `language`.
+    pub fn new_with_language(text: Text, decorations: DecorationSet, language: Language) -> Self {
         let line_count = text.as_lines().len();
         let tokens: Vec<_> = (0..line_count)
             .map(|line| tokenize(&text.as_lines()[line]).collect::<Vec<_>>())
@@ -41,9 +47,12 @@ impl CodeDocument {
                 inline_inlays: (0..line_count).map(|_| Vec::new()).collect(),
                 block_inlays: Vec::new(),
             }),
-            tokenizer: RefCell::new(Tokenizer::new(line_count)),
+            tokenizer: RefCell::new(language.tokenizer(line_count)),
             decorations: RefCell::new(decorations),
+            line_markers: RefCell::new(Vec::new()),
+            remote_selections: RefCell::new(Vec::new()),
             edit_senders: RefCell::new(HashMap::new()),
+            edit_listeners: RefCell::new(Vec::new()),
         }));
         inner.update_indent_state();
         inner.0.tokenizer.borrow_mut().update(
@@ -81,6 +90,18 @@ impl CodeDocument {
         self.update_after_edit(None, None, &edits);
     }
 
+    /// Applies edits that were made elsewhere, such as by another participant in a collaboration
+    /// session. Like `replace`, the edits form an undo group of their own.
+    pub fn apply_remote_edits(&self, edits: &[Edit]) {
+        let mut history = self.0.history.borrow_mut();
+        history.force_new_group();
+        for edit in edits {
+            history.apply_edit(edit.clone());
+        }
+        drop(history);
+        self.update_after_edit(None, None, edits);
+    }
+
     pub fn as_text(&self) -> Ref<'_, Text> {
         Ref::map(self.0.history.borrow(), |history| history.as_text())
     }
@@ -95,6 +116,31 @@ impl CodeDocument {
         })
     }
 
+    pub fn line_markers(&self) -> Ref<'_, [LineMarker]> {
+        Ref::map(self.0.line_markers.borrow(), |line_markers| line_markers.as_slice())
+    }
+
+    /// Replaces the line markers with the given id by `line_markers`.
+    pub fn set_line_markers(&self, id: usize, line_markers: impl IntoIterator<Item = LineMarker>) {
+        let mut markers = self.0.line_markers.borrow_mut();
+        markers.retain(|marker| marker.id != id);
+        markers.extend(line_markers);
+        markers.sort_by_key(|marker| marker.line_start);
+    }
+
+    pub fn remote_selections(&self) -> Ref<'_, [RemoteSelection]> {
+        Ref::map(self.0.remote_selections.borrow(), |selections| selections.as_slice())
+    }
+
+    /// Replaces the selections of the other participants in a collaboration session. They move
+    /// along with later edits until they are replaced again.
+    pub fn set_remote_selections(&self, selections: impl IntoIterator<Item = RemoteSelection>) {
+        let mut remote_selections = self.0.remote_selections.borrow_mut();
+        remote_selections.clear();
+        remote_selections.extend(selections);
+        remote_selections.sort_by_key(|selection| selection.start());
+    }
+
     pub fn edit_selections(
         &self,
         session_id: SessionId,
@@ -210,6 +256,13 @@ impl CodeDocument {
         self.0.decorations.borrow_mut().clear()
     }
 
+    pub fn remove_decorations_with_id(&mut self, id: usize) {
+        self.0
+            .decorations
+            .borrow_mut()
+            .remove_decorations_with_id(id)
+    }
+
     pub fn add_session(
         &mut self,
         session_id: SessionId,
@@ -225,6 +278,14 @@ impl CodeDocument {
         self.0.edit_senders.borrow_mut().remove(&session_id);
     }
 
+    /// Returns a receiver for every batch of edits applied to this document, in the order they
+    /// were applied. Dropping the receiver unregisters the listener.
+    pub fn add_edit_listener(&self) -> Receiver<Vec<Edit>> {
+        let (edit_sender, edit_receiver) = mpsc::channel();
+        self.0.edit_listeners.borrow_mut().push(edit_sender);
+        edit_receiver
+    }
+
     fn autoindent(
         &self,
         line_ranges: &[Range<usize>],
@@ -343,6 +404,32 @@ impl CodeDocument {
         }
     }
 
+    pub fn set_history_mode(&self, mode: HistoryMode) {
+        self.0.history.borrow_mut().set_mode(mode);
+    }
+
+    /// Returns the undo history in a form that can be stored next to the file, and restored with
+    /// `restore_history` when the file is opened again.
+    pub fn history_to_bin(&self) -> Vec<u8> {
+        self.0.history.borrow().to_bin()
+    }
+
+    /// Replaces the undo history with one stored by `history_to_bin`. Returns `false`, and keeps
+    /// the current history, if the stored history was not made for the current text.
+    pub fn restore_history(&self, bytes: &[u8]) -> bool {
+        let mut history = self.0.history.borrow_mut();
+        let Some(mut restored_history) = History::from_bin(history.as_text().clone(), bytes) else {
+            return false;
+        };
+        restored_history.set_mode(history.mode());
+        *history = restored_history;
+        true
+    }
+
+    pub fn select_next_redo_branch(&self) -> bool {
+        self.0.history.borrow_mut().select_next_redo_branch()
+    }
+
     pub fn force_new_group(&self) {
         self.0.history.borrow_mut().force_new_group()
     }
@@ -414,6 +501,24 @@ impl CodeDocument {
             decorations.apply_edit(edit);
         }
         drop(decorations);
+        let mut line_markers = self.0.line_markers.borrow_mut();
+        for edit in edits {
+            for line_marker in line_markers.iter_mut() {
+                *line_marker = line_marker.apply_edit(edit);
+            }
+        }
+        drop(line_markers);
+        let mut remote_selections = self.0.remote_selections.borrow_mut();
+        for edit in edits {
+            for selection in remote_selections.iter_mut() {
+                *selection = selection.apply_edit(edit);
+            }
+        }
+        drop(remote_selections);
+        self.0
+            .edit_listeners
+            .borrow_mut()
+            .retain(|edit_sender| edit_sender.send(edits.to_vec()).is_ok());
         for (&session_id, edit_sender) in &*self.0.edit_senders.borrow() {
             if Some(session_id) == origin_id {
                 edit_sender
@@ -712,7 +817,10 @@ struct DocumentInner {
     layout: RefCell<DocumentLayout>,
     tokenizer: RefCell<Tokenizer>,
     decorations: RefCell<DecorationSet>,
+    line_markers: RefCell<Vec<LineMarker>>,
+    remote_selections: RefCell<Vec<RemoteSelection>>,
     edit_senders: RefCell<HashMap<SessionId, Sender<(Option<SelectionSet>, Vec<Edit>)>>>,
+    edit_listeners: RefCell<Vec<Sender<Vec<Edit>>>>,
 }
 
 fn tokenize(text: &str) -> impl Iterator<Item = Token> + '_ {
@@ -0,0 +1,60 @@
+use {
+    crate::{
+        token::TokenKind,
+        tokenizer::{Cursor, Grammar},
+    },
+};
+
+/// Unified diffs, as produced by `diff -u` and `git diff`. Every line is a single token, whose
+/// kind depends on how the line starts.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DiffGrammar;
+
+impl Grammar for DiffGrammar {
+    type State = ();
+
+    fn next(&self, _state: (), cursor: &mut Cursor) -> ((), TokenKind) {
+        let rest = cursor.rest();
+        let kind = if rest.starts_with("+++") || rest.starts_with("---") {
+            TokenKind::BranchKeyword
+        } else if rest.starts_with("@@") {
+            TokenKind::OtherKeyword
+        } else if rest.starts_with('+') {
+            TokenKind::Typename
+        } else if rest.starts_with('-') {
+            TokenKind::Constant
+        } else if rest.starts_with(' ') || rest.starts_with('\\') {
+            TokenKind::Identifier
+        } else {
+            // `diff --git`, `index` and other extended header lines
+            TokenKind::Comment
+        };
+        cursor.skip_to_end();
+        ((), kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        grammar::{assert_tokens, DIFF},
+        token::TokenKind::*,
+    };
+
+    #[test]
+    fn classifies_lines_by_their_start() {
+        assert_tokens(
+            DIFF,
+            "diff --git a/x b/x\n--- a/x\n+++ b/x\n@@ -1 +1 @@\n-old\n+new\n same",
+            &[
+                &[(Comment, "diff --git a/x b/x")],
+                &[(BranchKeyword, "--- a/x")],
+                &[(BranchKeyword, "+++ b/x")],
+                &[(OtherKeyword, "@@ -1 +1 @@")],
+                &[(Constant, "-old")],
+                &[(Typename, "+new")],
+                &[(Identifier, " same")],
+            ],
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use {
+    super::{block_comment_tail, identifier, identifier_kind, number, punctuator, whitespace},
+    crate::{
+        token::TokenKind,
+        tokenizer::{CharExt, Cursor, Grammar},
+    },
+};
+
+/// GLSL, plus the keywords of the live shader DSL, so shader code pulled out of a
+/// `live_design!` block reads the same as it does inside one.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct GlslGrammar;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum GlslState {
+    #[default]
+    Initial,
+    BlockCommentTail,
+}
+
+impl Grammar for GlslGrammar {
+    type State = GlslState;
+
+    fn next(&self, state: GlslState, cursor: &mut Cursor) -> (GlslState, TokenKind) {
+        if state == GlslState::BlockCommentTail {
+            return block_comment(cursor);
+        }
+        let kind = match (cursor.peek(0), cursor.peek(1)) {
+            ('/', '/') => {
+                cursor.skip_to_end();
+                TokenKind::Comment
+            }
+            ('/', '*') => {
+                cursor.skip(2);
+                return block_comment(cursor);
+            }
+            ('#', _) if cursor.is_at_line_start() => {
+                cursor.skip(1);
+                cursor.skip_while(|char| char.is_whitespace());
+                cursor.skip_while(|char| char.is_identifier_continue());
+                TokenKind::OtherKeyword
+            }
+            ('.', char) if char.is_ascii_digit() => number(cursor),
+            (char, _) if char.is_ascii_digit() => number(cursor),
+            (char, _) if char.is_whitespace() => whitespace(cursor),
+            (char, _) if char.is_identifier_start() => {
+                let string = identifier(cursor);
+                keyword_kind(string).unwrap_or_else(|| identifier_kind(cursor, string))
+            }
+            _ => punctuator(cursor),
+        };
+        (GlslState::Initial, kind)
+    }
+}
+
+fn keyword_kind(string: &str) -> Option<TokenKind> {
+    Some(match string {
+        "if" | "else" | "switch" | "case" | "default" | "return" | "discard" | "match" => {
+            TokenKind::BranchKeyword
+        }
+        "for" | "while" | "do" | "break" | "continue" | "loop" => TokenKind::LoopKeyword,
+        "attribute" | "bool" | "bvec2" | "bvec3" | "bvec4" | "const" | "dmat2" | "dmat3"
+        | "dmat4" | "double" | "dvec2" | "dvec3" | "dvec4" | "false" | "flat" | "float"
+        | "fn" | "highp" | "in" | "inout" | "instance" | "int" | "ivec2" | "ivec3" | "ivec4"
+        | "layout" | "let" | "lowp" | "mat2" | "mat3" | "mat4" | "mediump" | "mut" | "out"
+        | "precision" | "sampler2D" | "sampler3D" | "samplerCube" | "self" | "smooth"
+        | "struct" | "texture" | "true" | "uint" | "uniform" | "uvec2" | "uvec3" | "uvec4"
+        | "varying" | "vec2" | "vec3" | "vec4" | "void" | "f32" | "i32" | "u32" | "Self" => {
+            TokenKind::OtherKeyword
+        }
+        _ => return None,
+    })
+}
+
+fn block_comment(cursor: &mut Cursor) -> (GlslState, TokenKind) {
+    if block_comment_tail(cursor, "*/") {
+        (GlslState::Initial, TokenKind::Comment)
+    } else {
+        (GlslState::BlockCommentTail, TokenKind::Comment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        grammar::{assert_tokens, GLSL},
+        token::TokenKind::*,
+    };
+
+    #[test]
+    fn directives_keywords_and_block_comments() {
+        assert_tokens(
+            GLSL,
+            "#version 300 es\nuniform vec4 color; /* a\nb */ void main() { x = color * 0.5; }",
+            &[
+                &[(OtherKeyword, "#version"), (Number, "300"), (Identifier, "es")],
+                &[
+                    (OtherKeyword, "uniform"),
+                    (OtherKeyword, "vec4"),
+                    (Identifier, "color"),
+                    (Punctuator, ";"),
+                    (Comment, "/* a"),
+                ],
+                &[
+                    (Comment, "b */"),
+                    (OtherKeyword, "void"),
+                    (Function, "main"),
+                    (Delimiter, "("),
+                    (Delimiter, ")"),
+                    (Delimiter, "{"),
+                    (Identifier, "x"),
+                    (Punctuator, "="),
+                    (Identifier, "color"),
+                    (Punctuator, "*"),
+                    (Number, "0.5"),
+                    (Punctuator, ";"),
+                    (Delimiter, "}"),
+                ],
+            ],
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use {
+    super::{block_comment_tail, identifier, identifier_kind, number, punctuator, quoted_tail, whitespace},
+    crate::{
+        token::TokenKind,
+        tokenizer::{CharExt, Cursor, Grammar},
+    },
+};
+
+/// JavaScript, and the TypeScript keywords that don't clash with it.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct JavaScriptGrammar;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum JavaScriptState {
+    #[default]
+    Initial,
+    BlockCommentTail,
+    TemplateStringTail,
+}
+
+impl Grammar for JavaScriptGrammar {
+    type State = JavaScriptState;
+
+    fn next(&self, state: JavaScriptState, cursor: &mut Cursor) -> (JavaScriptState, TokenKind) {
+        match state {
+            JavaScriptState::Initial => {}
+            JavaScriptState::BlockCommentTail => return block_comment(cursor),
+            JavaScriptState::TemplateStringTail => return template_string(cursor),
+        }
+        let kind = match (cursor.peek(0), cursor.peek(1)) {
+            ('/', '/') => {
+                cursor.skip_to_end();
+                TokenKind::Comment
+            }
+            ('/', '*') => {
+                cursor.skip(2);
+                return block_comment(cursor);
+            }
+            ('`', _) => {
+                cursor.skip(1);
+                return template_string(cursor);
+            }
+            (quote @ ('"' | '\''), _) => {
+                cursor.skip(1);
+                quoted_tail(cursor, quote);
+                TokenKind::String
+            }
+            ('.', char) if char.is_ascii_digit() => number(cursor),
+            (char, _) if char.is_ascii_digit() => number(cursor),
+            (char, _) if char.is_whitespace() => whitespace(cursor),
+            (char, _) if char.is_identifier_start() || char == '$' => {
+                let string = identifier(cursor);
+                keyword_kind(string).unwrap_or_else(|| identifier_kind(cursor, string))
+            }
+            _ => punctuator(cursor),
+        };
+        (JavaScriptState::Initial, kind)
+    }
+}
+
+fn keyword_kind(string: &str) -> Option<TokenKind> {
+    Some(match string {
+        "if" | "else" | "switch" | "case" | "default" | "return" | "try" | "catch" | "finally"
+        | "throw" => TokenKind::BranchKeyword,
+        "for" | "while" | "do" | "break" | "continue" => TokenKind::LoopKeyword,
+        "abstract" | "as" | "async" | "await" | "class" | "const" | "constructor" | "declare"
+        | "delete" | "enum" | "export" | "extends" | "false" | "from" | "function" | "get"
+        | "implements" | "import" | "in" | "instanceof" | "interface" | "keyof" | "let"
+        | "new" | "null" | "of" | "private" | "protected" | "public" | "readonly" | "set"
+        | "static" | "super" | "this" | "true" | "type" | "typeof" | "undefined" | "var"
+        | "void" | "yield" => TokenKind::OtherKeyword,
+        _ => return None,
+    })
+}
+
+fn block_comment(cursor: &mut Cursor) -> (JavaScriptState, TokenKind) {
+    if block_comment_tail(cursor, "*/") {
+        (JavaScriptState::Initial, TokenKind::Comment)
+    } else {
+        (JavaScriptState::BlockCommentTail, TokenKind::Comment)
+    }
+}
+
+// Template strings may span lines. Substitutions are highlighted as part of the string.
+fn template_string(cursor: &mut Cursor) -> (JavaScriptState, TokenKind) {
+    if quoted_tail(cursor, '`') {
+        (JavaScriptState::Initial, TokenKind::String)
+    } else {
+        (JavaScriptState::TemplateStringTail, TokenKind::String)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        grammar::{assert_tokens, JAVASCRIPT},
+        token::TokenKind::*,
+    };
+
+    #[test]
+    fn classifies_keywords_and_identifiers() {
+        assert_tokens(
+            JAVASCRIPT,
+            "if ($el) return 'it\\'s' + 0xff;",
+            &[&[
+                (BranchKeyword, "if"),
+                (Delimiter, "("),
+                (Identifier, "$el"),
+                (Delimiter, ")"),
+                (BranchKeyword, "return"),
+                (String, "'it\\'s'"),
+                (Punctuator, "+"),
+                (Number, "0xff"),
+                (Punctuator, ";"),
+            ]],
+        );
+    }
+
+    #[test]
+    fn template_strings_and_block_comments_span_lines() {
+        assert_tokens(
+            JAVASCRIPT,
+            "const s = `a ${b}\nc`; /* x\ny */ f();",
+            &[
+                &[
+                    (OtherKeyword, "const"),
+                    (Identifier, "s"),
+                    (Punctuator, "="),
+                    (String, "`a ${b}"),
+                ],
+                &[(String, "c`"), (Punctuator, ";"), (Comment, "/* x")],
+                &[
+                    (Comment, "y */"),
+                    (Function, "f"),
+                    (Delimiter, "("),
+                    (Delimiter, ")"),
+                    (Punctuator, ";"),
+                ],
+            ],
+        );
+    }
+}
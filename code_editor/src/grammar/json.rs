@@ -0,0 +1,113 @@
+use {
+    super::{block_comment_tail, number, punctuator, quoted_tail, whitespace},
+    crate::{
+        token::TokenKind,
+        tokenizer::{CharExt, Cursor, Grammar},
+    },
+};
+
+/// JSON, with the comments that JSONC and most configuration files allow.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct JsonGrammar;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum JsonState {
+    #[default]
+    Initial,
+    BlockCommentTail,
+}
+
+impl Grammar for JsonGrammar {
+    type State = JsonState;
+
+    fn next(&self, state: JsonState, cursor: &mut Cursor) -> (JsonState, TokenKind) {
+        if state == JsonState::BlockCommentTail {
+            return block_comment(cursor);
+        }
+        let kind = match (cursor.peek(0), cursor.peek(1)) {
+            ('/', '/') => {
+                cursor.skip_to_end();
+                TokenKind::Comment
+            }
+            ('/', '*') => {
+                cursor.skip(2);
+                return block_comment(cursor);
+            }
+            ('"', _) => {
+                cursor.skip(1);
+                quoted_tail(cursor, '"');
+                // object keys are highlighted differently from string values
+                if cursor.rest().trim_start().starts_with(':') {
+                    TokenKind::Identifier
+                } else {
+                    TokenKind::String
+                }
+            }
+            ('-', char) if char.is_ascii_digit() => {
+                cursor.skip(1);
+                number(cursor)
+            }
+            (char, _) if char.is_ascii_digit() => number(cursor),
+            (char, _) if char.is_whitespace() => whitespace(cursor),
+            (char, _) if char.is_identifier_start() => {
+                let start = cursor.index();
+                cursor.skip_while(|char| char.is_identifier_continue());
+                match &cursor.before()[start..] {
+                    "true" | "false" | "null" => TokenKind::OtherKeyword,
+                    _ => TokenKind::Unknown,
+                }
+            }
+            _ => punctuator(cursor),
+        };
+        (JsonState::Initial, kind)
+    }
+}
+
+fn block_comment(cursor: &mut Cursor) -> (JsonState, TokenKind) {
+    if block_comment_tail(cursor, "*/") {
+        (JsonState::Initial, TokenKind::Comment)
+    } else {
+        (JsonState::BlockCommentTail, TokenKind::Comment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        grammar::{assert_tokens, JSON},
+        token::TokenKind::*,
+    };
+
+    #[test]
+    fn keys_values_and_block_comments() {
+        assert_tokens(
+            JSON,
+            "{\"key\": \"välue\", \"n\": -1.5e3, \"ok\": true, /* c\n*/ \"x\": null}",
+            &[
+                &[
+                    (Delimiter, "{"),
+                    (Identifier, "\"key\""),
+                    (Punctuator, ":"),
+                    (String, "\"välue\""),
+                    (Punctuator, ","),
+                    (Identifier, "\"n\""),
+                    (Punctuator, ":"),
+                    (Number, "-1.5e3"),
+                    (Punctuator, ","),
+                    (Identifier, "\"ok\""),
+                    (Punctuator, ":"),
+                    (OtherKeyword, "true"),
+                    (Punctuator, ","),
+                    (Comment, "/* c"),
+                ],
+                &[
+                    (Comment, "*/"),
+                    (Identifier, "\"x\""),
+                    (Punctuator, ":"),
+                    (OtherKeyword, "null"),
+                    (Delimiter, "}"),
+                ],
+            ],
+        );
+    }
+}
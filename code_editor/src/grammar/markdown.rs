@@ -0,0 +1,234 @@
+use {
+    super::{block_comment_tail, whitespace},
+    crate::{
+        token::TokenKind,
+        tokenizer::{Cursor, Grammar},
+    },
+};
+
+/// Markdown. Headings, code and links stand out; prose is left alone.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct MarkdownGrammar;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum MarkdownState {
+    #[default]
+    Initial,
+    FencedCode {
+        fence: char,
+        fence_len: usize,
+    },
+    HtmlCommentTail,
+}
+
+impl Grammar for MarkdownGrammar {
+    type State = MarkdownState;
+
+    fn next(&self, state: MarkdownState, cursor: &mut Cursor) -> (MarkdownState, TokenKind) {
+        match state {
+            MarkdownState::Initial => initial(cursor),
+            MarkdownState::FencedCode { fence, fence_len } => {
+                if cursor.peek(0).is_whitespace() && cursor.index() == 0 {
+                    return (state, whitespace(cursor));
+                }
+                if cursor.is_at_line_start() && fence_len_at(cursor, fence) >= fence_len {
+                    cursor.skip_to_end();
+                    return (MarkdownState::Initial, TokenKind::Comment);
+                }
+                cursor.skip_to_end();
+                (state, TokenKind::String)
+            }
+            MarkdownState::HtmlCommentTail => html_comment(cursor),
+        }
+    }
+}
+
+fn initial(cursor: &mut Cursor) -> (MarkdownState, TokenKind) {
+    let char = cursor.peek(0);
+    if char.is_whitespace() {
+        return (MarkdownState::Initial, whitespace(cursor));
+    }
+    if cursor.is_at_line_start() {
+        if let Some(state) = block_start(cursor) {
+            return state;
+        }
+    }
+    let kind = match (char, cursor.peek(1)) {
+        ('<', '!') if cursor.skip_str("<!--") => return html_comment(cursor),
+        ('`', _) => code_span(cursor),
+        ('<', _) if is_autolink(cursor.rest()) => {
+            cursor.skip_while(|char| char != '>' && char != '\0');
+            cursor.skip(1);
+            TokenKind::Constant
+        }
+        ('\\', _) => {
+            cursor.skip(2);
+            TokenKind::Unknown
+        }
+        ('(', _) if cursor.before().ends_with(']') => {
+            cursor.skip(1);
+            TokenKind::Delimiter
+        }
+        // the destination of a link
+        _ if cursor.before().ends_with("](") && char != ')' => {
+            cursor.skip_while(|char| char != ')' && !char.is_whitespace() && char != '\0');
+            TokenKind::Constant
+        }
+        ('[' | ']' | '(' | ')', _) => {
+            cursor.skip(1);
+            TokenKind::Delimiter
+        }
+        ('*' | '_' | '~', _) => {
+            cursor.skip_while(|other| other == char);
+            TokenKind::Punctuator
+        }
+        (char, _) if char.is_alphanumeric() => {
+            cursor.skip_while(|char| char.is_alphanumeric() || char == '_' || char == '\'');
+            TokenKind::Identifier
+        }
+        _ => {
+            cursor.skip(1);
+            TokenKind::Unknown
+        }
+    };
+    (MarkdownState::Initial, kind)
+}
+
+// Constructs that only mean something at the start of a line.
+fn block_start(cursor: &mut Cursor) -> Option<(MarkdownState, TokenKind)> {
+    let char = cursor.peek(0);
+    let rest = cursor.rest();
+    if char == '`' || char == '~' {
+        let fence_len = fence_len_at(cursor, char);
+        if fence_len >= 3 {
+            cursor.skip_to_end();
+            return Some((MarkdownState::FencedCode { fence: char, fence_len }, TokenKind::Comment));
+        }
+    }
+    if char == '#' {
+        let level = rest.chars().take_while(|char| *char == '#').count();
+        if level <= 6 && matches!(rest[level..].chars().next(), None | Some(' ' | '\t')) {
+            cursor.skip_to_end();
+            return Some((MarkdownState::Initial, TokenKind::BranchKeyword));
+        }
+    }
+    if matches!(char, '-' | '*' | '_') {
+        let rule = rest.trim_end();
+        if rule.chars().filter(|c| *c == char).count() >= 3
+            && rule.chars().all(|c| c == char || c == ' ')
+        {
+            cursor.skip_to_end();
+            return Some((MarkdownState::Initial, TokenKind::Punctuator));
+        }
+    }
+    if char == '>' {
+        cursor.skip(1);
+        return Some((MarkdownState::Initial, TokenKind::Punctuator));
+    }
+    if matches!(char, '-' | '*' | '+') && matches!(cursor.peek(1), ' ' | '\t' | '\0') {
+        cursor.skip(1);
+        return Some((MarkdownState::Initial, TokenKind::OtherKeyword));
+    }
+    if char.is_ascii_digit() {
+        let digits = rest.chars().take_while(|char| char.is_ascii_digit()).count();
+        let mut after = rest[digits..].chars();
+        if matches!(after.next(), Some('.' | ')')) && matches!(after.next(), None | Some(' ' | '\t')) {
+            cursor.skip(digits + 1);
+            return Some((MarkdownState::Initial, TokenKind::OtherKeyword));
+        }
+    }
+    None
+}
+
+fn fence_len_at(cursor: &Cursor, fence: char) -> usize {
+    cursor.rest().chars().take_while(|char| *char == fence).count()
+}
+
+// A code span is closed by a run of exactly as many backticks as opened it.
+fn code_span(cursor: &mut Cursor) -> TokenKind {
+    let len = fence_len_at(cursor, '`');
+    let rest = &cursor.rest()[len..];
+    let mut search = 0;
+    while let Some(index) = rest[search..].find('`') {
+        let start = search + index;
+        let run = rest[start..].chars().take_while(|char| *char == '`').count();
+        if run == len {
+            let span = &cursor.rest()[..len + start + run];
+            cursor.skip_str(span);
+            return TokenKind::String;
+        }
+        search = start + run;
+    }
+    cursor.skip(len);
+    TokenKind::Punctuator
+}
+
+fn is_autolink(rest: &str) -> bool {
+    let Some(end) = rest.find('>') else {
+        return false;
+    };
+    let link = &rest[1..end];
+    !link.contains(char::is_whitespace) && (link.contains("://") || link.starts_with("mailto:"))
+}
+
+fn html_comment(cursor: &mut Cursor) -> (MarkdownState, TokenKind) {
+    if block_comment_tail(cursor, "-->") {
+        (MarkdownState::Initial, TokenKind::Comment)
+    } else {
+        (MarkdownState::HtmlCommentTail, TokenKind::Comment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        grammar::{assert_tokens, MARKDOWN},
+        token::TokenKind::*,
+    };
+
+    #[test]
+    fn headings_lists_and_inline_markup() {
+        assert_tokens(
+            MARKDOWN,
+            "# Title\n- item\n1. one\nSome *text* with `code` and [a link](http://x.y).",
+            &[
+                &[(BranchKeyword, "# Title")],
+                &[(OtherKeyword, "-"), (Identifier, "item")],
+                &[(OtherKeyword, "1."), (Identifier, "one")],
+                &[
+                    (Identifier, "Some"),
+                    (Punctuator, "*"),
+                    (Identifier, "text"),
+                    (Punctuator, "*"),
+                    (Identifier, "with"),
+                    (String, "`code`"),
+                    (Identifier, "and"),
+                    (Delimiter, "["),
+                    (Identifier, "a"),
+                    (Identifier, "link"),
+                    (Delimiter, "]"),
+                    (Delimiter, "("),
+                    (Constant, "http://x.y"),
+                    (Delimiter, ")"),
+                    (Unknown, "."),
+                ],
+            ],
+        );
+    }
+
+    #[test]
+    fn fenced_code_and_html_comments_span_lines() {
+        assert_tokens(
+            MARKDOWN,
+            "```rust\n/* not a comment\n  ``\n```\n<!-- a\ncomment --> ünd",
+            &[
+                &[(Comment, "```rust")],
+                &[(String, "/* not a comment")],
+                &[(String, "``")],
+                &[(Comment, "```")],
+                &[(Comment, "<!-- a")],
+                &[(Comment, "comment -->"), (Identifier, "ünd")],
+            ],
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! The grammars the tokenizer ships with, and the registry that picks one by file extension.
+
+pub mod diff;
+pub mod glsl;
+pub mod javascript;
+pub mod json;
+pub mod markdown;
+pub mod python;
+pub mod rust;
+pub mod toml;
+
+use {
+    crate::{
+        token::TokenKind,
+        tokenizer::{new_tokenizer, CharExt, Cursor, Grammar, Tokenizer},
+    },
+    std::{path::Path, sync::Mutex},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Language {
+    pub name: &'static str,
+    /// Lowercase file extensions, without the leading dot.
+    pub extensions: &'static [&'static str],
+    pub new_tokenizer: fn(usize) -> Tokenizer,
+}
+
+impl Language {
+    pub fn tokenizer(&self, line_count: usize) -> Tokenizer {
+        (self.new_tokenizer)(line_count)
+    }
+
+    /// Finds the language for a file extension. Registered languages take precedence over the
+    /// built-in ones.
+    pub fn for_extension(extension: &str) -> Option<Language> {
+        let extension = extension.to_ascii_lowercase();
+        let matches = |language: &&Language| language.extensions.contains(&extension.as_str());
+        let registered = REGISTERED_LANGUAGES
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(matches)
+            .copied();
+        registered.or_else(|| BUILTIN_LANGUAGES.iter().find(matches).copied())
+    }
+
+    /// Finds the language for a path by its extension, or plain text if there is none.
+    pub fn for_path(path: &str) -> Language {
+        Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Self::for_extension)
+            .unwrap_or(PLAIN_TEXT)
+    }
+}
+
+static REGISTERED_LANGUAGES: Mutex<Vec<Language>> = Mutex::new(Vec::new());
+
+/// Registers a language, overriding any earlier language for the same extensions.
+pub fn register_language(language: Language) {
+    REGISTERED_LANGUAGES.lock().unwrap().push(language);
+}
+
+pub const PLAIN_TEXT: Language = Language {
+    name: "Plain Text",
+    extensions: &["txt"],
+    new_tokenizer: new_tokenizer::<PlainTextGrammar>,
+};
+
+pub const RUST: Language = Language {
+    name: "Rust",
+    extensions: &["rs", "ron"],
+    new_tokenizer: new_tokenizer::<rust::RustGrammar>,
+};
+
+pub const TOML: Language = Language {
+    name: "TOML",
+    extensions: &["toml"],
+    new_tokenizer: new_tokenizer::<toml::TomlGrammar>,
+};
+
+pub const JSON: Language = Language {
+    name: "JSON",
+    extensions: &["json", "jsonc"],
+    new_tokenizer: new_tokenizer::<json::JsonGrammar>,
+};
+
+pub const MARKDOWN: Language = Language {
+    name: "Markdown",
+    extensions: &["md", "markdown"],
+    new_tokenizer: new_tokenizer::<markdown::MarkdownGrammar>,
+};
+
+pub const GLSL: Language = Language {
+    name: "GLSL",
+    extensions: &["glsl", "vert", "frag", "comp", "geom", "tesc", "tese"],
+    new_tokenizer: new_tokenizer::<glsl::GlslGrammar>,
+};
+
+pub const JAVASCRIPT: Language = Language {
+    name: "JavaScript",
+    extensions: &["js", "mjs", "cjs", "jsx", "ts", "mts", "cts", "tsx"],
+    new_tokenizer: new_tokenizer::<javascript::JavaScriptGrammar>,
+};
+
+pub const PYTHON: Language = Language {
+    name: "Python",
+    extensions: &["py", "pyw", "pyi"],
+    new_tokenizer: new_tokenizer::<python::PythonGrammar>,
+};
+
+pub const DIFF: Language = Language {
+    name: "Diff",
+    extensions: &["diff", "patch"],
+    new_tokenizer: new_tokenizer::<diff::DiffGrammar>,
+};
+
+pub const BUILTIN_LANGUAGES: &[Language] =
+    &[PLAIN_TEXT, RUST, TOML, JSON, MARKDOWN, GLSL, JAVASCRIPT, PYTHON, DIFF];
+
+/// Splits text into words and whitespace, without highlighting anything.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PlainTextGrammar;
+
+impl Grammar for PlainTextGrammar {
+    type State = ();
+
+    fn next(&self, _state: (), cursor: &mut Cursor) -> ((), TokenKind) {
+        if cursor.skip_while(|char| char.is_whitespace()) {
+            return ((), TokenKind::Whitespace);
+        }
+        cursor.skip_while(|char| !char.is_whitespace() && char != '\0');
+        ((), TokenKind::Unknown)
+    }
+}
+
+// Helpers for the parts of the grammars that most C-like languages have in common.
+
+fn whitespace(cursor: &mut Cursor) -> TokenKind {
+    cursor.skip_while(|char| char.is_whitespace());
+    TokenKind::Whitespace
+}
+
+/// Skips an identifier, and returns it.
+fn identifier<'a>(cursor: &mut Cursor<'a>) -> &'a str {
+    let start = cursor.index();
+    cursor.skip_while(|char| char.is_identifier_continue() || char == '$');
+    &cursor.before()[start..]
+}
+
+/// Classifies an identifier that isn't a keyword the way the Rust grammar does: all caps is a
+/// constant, capitalized is a type, followed by a parenthesis is a function call.
+fn identifier_kind(cursor: &Cursor, string: &str) -> TokenKind {
+    let mut chars = string.chars();
+    match chars.next() {
+        Some(char) if char.is_uppercase() => match chars.next() {
+            Some(char) if char.is_uppercase() => TokenKind::Constant,
+            _ => TokenKind::Typename,
+        },
+        _ if cursor.peek(0) == '(' => TokenKind::Function,
+        _ => TokenKind::Identifier,
+    }
+}
+
+/// Skips a decimal, hexadecimal, octal or binary number, with an optional fraction, exponent and
+/// suffix.
+fn number(cursor: &mut Cursor) -> TokenKind {
+    let radix = match (cursor.peek(0), cursor.peek(1)) {
+        ('0', 'x' | 'X') => 16,
+        ('0', 'o' | 'O') => 8,
+        ('0', 'b' | 'B') => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        cursor.skip(2);
+        if !cursor.skip_digits(radix) {
+            return TokenKind::Unknown;
+        }
+        cursor.skip_suffix();
+        return TokenKind::Number;
+    }
+    cursor.skip_digits(10);
+    if cursor.peek(0) == '.' && cursor.peek(1).is_ascii_digit() {
+        cursor.skip(1);
+        cursor.skip_digits(10);
+    }
+    if (cursor.peek(0) == 'e' || cursor.peek(0) == 'E')
+        && (cursor.peek(1).is_ascii_digit()
+            || (matches!(cursor.peek(1), '+' | '-') && cursor.peek(2).is_ascii_digit()))
+    {
+        cursor.skip_exponent();
+    }
+    cursor.skip_suffix();
+    TokenKind::Number
+}
+
+/// Skips the rest of a string that is closed by `quote` on the same line, honoring backslash
+/// escapes. Returns false if the line ends first.
+fn quoted_tail(cursor: &mut Cursor, quote: char) -> bool {
+    loop {
+        match cursor.peek(0) {
+            '\0' => return false,
+            '\\' => cursor.skip(2),
+            char if char == quote => {
+                cursor.skip(1);
+                return true;
+            }
+            _ => cursor.skip(1),
+        }
+    }
+}
+
+/// Skips the rest of a block comment that is closed by `end`. Returns false if the line ends
+/// first.
+fn block_comment_tail(cursor: &mut Cursor, end: &str) -> bool {
+    loop {
+        if cursor.skip_str(end) {
+            return true;
+        }
+        if cursor.peek(0) == '\0' {
+            return false;
+        }
+        cursor.skip(1);
+    }
+}
+
+/// Skips a delimiter or a punctuator. Operators made of several characters are split into
+/// single characters, which highlights the same.
+fn punctuator(cursor: &mut Cursor) -> TokenKind {
+    let char = cursor.peek(0);
+    cursor.skip(1);
+    match char {
+        '(' | ')' | '[' | ']' | '{' | '}' => TokenKind::Delimiter,
+        char if char.is_ascii_punctuation() => TokenKind::Punctuator,
+        _ => TokenKind::Unknown,
+    }
+}
+
+/// Tokenizes `text` from scratch, and returns the kind and text of the tokens on each line,
+/// leaving out whitespace. Checks that the tokens cover every line exactly.
+#[cfg(test)]
+fn tokenize(language: Language, text: &str) -> Vec<Vec<(TokenKind, String)>> {
+    use crate::text::Text;
+
+    let text = Text::from(text);
+    let mut tokens = vec![Vec::new(); text.as_lines().len()];
+    language.tokenizer(text.as_lines().len()).update(&text, &mut tokens);
+    text.as_lines()
+        .iter()
+        .zip(tokens)
+        .map(|(line, tokens)| {
+            assert_eq!(tokens.iter().map(|token| token.len).sum::<usize>(), line.len());
+            let mut start = 0;
+            tokens
+                .into_iter()
+                .filter_map(|token| {
+                    let string = &line[start..start + token.len];
+                    start += token.len;
+                    (token.kind != TokenKind::Whitespace).then(|| (token.kind, string.to_string()))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Checks the tokens on each line of `text`, leaving out whitespace.
+#[cfg(test)]
+#[track_caller]
+fn assert_tokens(language: Language, text: &str, expected: &[&[(TokenKind, &str)]]) {
+    let expected: Vec<Vec<(TokenKind, String)>> = expected
+        .iter()
+        .map(|line| line.iter().map(|(kind, string)| (*kind, string.to_string())).collect())
+        .collect();
+    assert_eq!(tokenize(language, text), expected);
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            text::{Change, Length, Position, Text},
+            Token,
+        },
+    };
+
+    #[test]
+    fn finds_languages_by_extension() {
+        assert_eq!(Language::for_path("src/main.rs").name, "Rust");
+        assert_eq!(Language::for_path("Cargo.TOML").name, "TOML");
+        assert_eq!(Language::for_path("shader.frag").name, "GLSL");
+        assert_eq!(Language::for_path("README").name, "Plain Text");
+        assert!(Language::for_extension("unknown").is_none());
+    }
+
+    // Every token has to consume at least one char, or the tokenizer would never finish a line.
+    // These are the inputs where a grammar is most likely to stop short.
+    #[test]
+    fn every_grammar_consumes_unterminated_and_odd_input() {
+        let inputs = [
+            "\\", "\"", "'", "`", "\"\\", "'\\", "r#", "r#\"", "br#", "b'", "/*", "*/", "/", "#",
+            "<!--", "-->", "0x", "0b", "1e", "1e+", ".5", "-", "+1", "[", "[[a", "```", "~~~",
+            "@", "$", "é", "a😀b", "f\"", "rb'", "\"\"\"", "'''", "1979-05-27T07:32:00Z", "12:",
+            "\t", " é ",
+        ];
+        for language in BUILTIN_LANGUAGES {
+            for input in inputs {
+                tokenize(*language, input);
+                tokenize(*language, &format!("{input}\n{input}\n"));
+                tokenize(*language, &format!("x {input} y\n\n{input}"));
+            }
+        }
+    }
+
+    #[test]
+    fn plain_text_splits_words() {
+        assert_tokens(
+            PLAIN_TEXT,
+            "héllo  wörld",
+            &[&[(TokenKind::Unknown, "héllo"), (TokenKind::Unknown, "wörld")]],
+        );
+    }
+
+    #[test]
+    fn retokenizes_the_lines_after_an_edit_that_changes_their_state() {
+        let mut text = Text::from("a\nb\nc");
+        let mut tokenizer = RUST.tokenizer(3);
+        let mut tokens = vec![Vec::new(); 3];
+        tokenizer.update(&text, &mut tokens);
+        let identifier = |len| vec![Token { len, kind: TokenKind::Identifier }];
+        assert_eq!(tokens, vec![identifier(1), identifier(1), identifier(1)]);
+
+        let change = Change::Insert(Position { line_index: 0, byte_index: 0 }, Text::from("/*"));
+        text.apply_change(change.clone());
+        tokenizer.apply_change(&change);
+        tokenizer.update(&text, &mut tokens);
+        let comment = |len| vec![Token { len, kind: TokenKind::Comment }];
+        assert_eq!(tokens, vec![comment(3), comment(1), comment(1)]);
+
+        let change = Change::Delete(
+            Position { line_index: 0, byte_index: 0 },
+            Length { line_count: 0, byte_count: 2 },
+        );
+        text.apply_change(change.clone());
+        tokenizer.apply_change(&change);
+        tokenizer.update(&text, &mut tokens);
+        assert_eq!(tokens, vec![identifier(1), identifier(1), identifier(1)]);
+    }
+}
@@ -0,0 +1,168 @@
+use {
+    super::{identifier, identifier_kind, number, punctuator, quoted_tail, whitespace},
+    crate::{
+        token::TokenKind,
+        tokenizer::{CharExt, Cursor, Grammar},
+    },
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PythonGrammar;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum PythonState {
+    #[default]
+    Initial,
+    TripleQuotedStringTail {
+        quote: char,
+        is_raw: bool,
+    },
+}
+
+impl Grammar for PythonGrammar {
+    type State = PythonState;
+
+    fn next(&self, state: PythonState, cursor: &mut Cursor) -> (PythonState, TokenKind) {
+        if let PythonState::TripleQuotedStringTail { quote, is_raw } = state {
+            return triple_quoted_string(cursor, quote, is_raw);
+        }
+        if let Some(prefix_len) = string_prefix_len(cursor) {
+            let is_raw = cursor.rest()[..prefix_len].contains(['r', 'R']);
+            cursor.skip(prefix_len);
+            let quote = cursor.peek(0);
+            if cursor.peek(1) == quote && cursor.peek(2) == quote {
+                cursor.skip(3);
+                return triple_quoted_string(cursor, quote, is_raw);
+            }
+            cursor.skip(1);
+            quoted_tail(cursor, quote);
+            return (PythonState::Initial, TokenKind::String);
+        }
+        let kind = match (cursor.peek(0), cursor.peek(1)) {
+            ('#', _) => {
+                cursor.skip_to_end();
+                TokenKind::Comment
+            }
+            ('@', char) if char.is_identifier_start() && cursor.is_at_line_start() => {
+                cursor.skip(1);
+                cursor.skip_while(|char| char.is_identifier_continue() || char == '.');
+                TokenKind::Function
+            }
+            ('.', char) if char.is_ascii_digit() => number(cursor),
+            (char, _) if char.is_ascii_digit() => number(cursor),
+            (char, _) if char.is_whitespace() => whitespace(cursor),
+            (char, _) if char.is_identifier_start() => {
+                let string = identifier(cursor);
+                keyword_kind(string).unwrap_or_else(|| identifier_kind(cursor, string))
+            }
+            _ => punctuator(cursor),
+        };
+        (PythonState::Initial, kind)
+    }
+}
+
+fn keyword_kind(string: &str) -> Option<TokenKind> {
+    Some(match string {
+        "if" | "elif" | "else" | "return" | "match" | "case" | "try" | "except" | "finally"
+        | "raise" | "with" => TokenKind::BranchKeyword,
+        "for" | "while" | "break" | "continue" => TokenKind::LoopKeyword,
+        "and" | "as" | "assert" | "async" | "await" | "class" | "def" | "del" | "False"
+        | "from" | "global" | "import" | "in" | "is" | "lambda" | "None" | "nonlocal" | "not"
+        | "or" | "pass" | "self" | "True" | "yield" => TokenKind::OtherKeyword,
+        _ => return None,
+    })
+}
+
+// Returns the length of the prefix (like r, b, f or rb) before the quote that starts a string,
+// if the cursor is at the start of a string.
+fn string_prefix_len(cursor: &Cursor) -> Option<usize> {
+    let rest = cursor.rest();
+    let prefix_len = rest
+        .chars()
+        .take(3)
+        .take_while(|char| matches!(char, 'r' | 'R' | 'b' | 'B' | 'f' | 'F' | 'u' | 'U'))
+        .count();
+    if prefix_len > 2 || (prefix_len > 0 && cursor.before().ends_with(|char: char| char.is_identifier_continue())) {
+        return None;
+    }
+    match rest[prefix_len..].chars().next() {
+        Some('"' | '\'') => Some(prefix_len),
+        _ => None,
+    }
+}
+
+fn triple_quoted_string(cursor: &mut Cursor, quote: char, is_raw: bool) -> (PythonState, TokenKind) {
+    loop {
+        match cursor.peek(0) {
+            '\0' => return (PythonState::TripleQuotedStringTail { quote, is_raw }, TokenKind::String),
+            '\\' if !is_raw => cursor.skip(2),
+            char if char == quote && cursor.peek(1) == quote && cursor.peek(2) == quote => {
+                cursor.skip(3);
+                return (PythonState::Initial, TokenKind::String);
+            }
+            _ => cursor.skip(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        grammar::{assert_tokens, PYTHON},
+        token::TokenKind::*,
+    };
+
+    #[test]
+    fn classifies_keywords_and_decorators() {
+        assert_tokens(
+            PYTHON,
+            "@decorator\ndef f(x):\n    for y in x: break",
+            &[
+                &[(Function, "@decorator")],
+                &[
+                    (OtherKeyword, "def"),
+                    (Function, "f"),
+                    (Delimiter, "("),
+                    (Identifier, "x"),
+                    (Delimiter, ")"),
+                    (Punctuator, ":"),
+                ],
+                &[
+                    (LoopKeyword, "for"),
+                    (Identifier, "y"),
+                    (OtherKeyword, "in"),
+                    (Identifier, "x"),
+                    (Punctuator, ":"),
+                    (LoopKeyword, "break"),
+                ],
+            ],
+        );
+    }
+
+    #[test]
+    fn triple_quoted_strings_span_lines() {
+        assert_tokens(
+            PYTHON,
+            "x = \"\"\"doc\n    more\"\"\" + r'\\d' # note\ny",
+            &[
+                &[(Identifier, "x"), (Punctuator, "="), (String, "\"\"\"doc")],
+                &[
+                    (String, "    more\"\"\""),
+                    (Punctuator, "+"),
+                    (String, "r'\\d'"),
+                    (Comment, "# note"),
+                ],
+                &[(Identifier, "y")],
+            ],
+        );
+    }
+
+    #[test]
+    fn raw_triple_quoted_strings_ignore_escapes() {
+        assert_tokens(
+            PYTHON,
+            "r'''a\\\n''' é",
+            &[&[(String, "r'''a\\")], &[(String, "'''"), (Unknown, "é")]],
+        );
+    }
+}
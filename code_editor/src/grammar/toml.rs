@@ -0,0 +1,180 @@
+use {
+    super::{number, punctuator, quoted_tail, whitespace},
+    crate::{
+        token::TokenKind,
+        tokenizer::{CharExt, Cursor, Grammar},
+    },
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TomlGrammar;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum TomlState {
+    #[default]
+    Initial,
+    MultiLineBasicString,
+    MultiLineLiteralString,
+}
+
+impl Grammar for TomlGrammar {
+    type State = TomlState;
+
+    fn next(&self, state: TomlState, cursor: &mut Cursor) -> (TomlState, TokenKind) {
+        match state {
+            TomlState::Initial => initial(cursor),
+            TomlState::MultiLineBasicString => multi_line_string_tail(cursor, "\"\"\"", state),
+            TomlState::MultiLineLiteralString => multi_line_string_tail(cursor, "'''", state),
+        }
+    }
+}
+
+fn initial(cursor: &mut Cursor) -> (TomlState, TokenKind) {
+    let kind = match cursor.peek(0) {
+        '#' => {
+            cursor.skip_to_end();
+            TokenKind::Comment
+        }
+        '[' if cursor.is_at_line_start() => table_header(cursor),
+        '"' if cursor.skip_str("\"\"\"") => {
+            return multi_line_string_tail(cursor, "\"\"\"", TomlState::MultiLineBasicString)
+        }
+        '\'' if cursor.skip_str("'''") => {
+            return multi_line_string_tail(cursor, "'''", TomlState::MultiLineLiteralString)
+        }
+        '"' => {
+            cursor.skip(1);
+            quoted_tail(cursor, '"');
+            key_or(cursor, TokenKind::String)
+        }
+        '\'' => {
+            cursor.skip(1);
+            cursor.skip_while(|char| char != '\'' && char != '\0');
+            cursor.skip_if(|char| char == '\'');
+            key_or(cursor, TokenKind::String)
+        }
+        '+' | '-' if cursor.peek(1).is_ascii_digit() => {
+            cursor.skip(1);
+            date_or_number(cursor)
+        }
+        char if char.is_ascii_digit() => date_or_number(cursor),
+        char if char.is_whitespace() => whitespace(cursor),
+        char if is_bare_key_char(char) => {
+            let start = cursor.index();
+            cursor.skip_while(is_bare_key_char);
+            match &cursor.before()[start..] {
+                "true" | "false" | "inf" | "nan" => key_or(cursor, TokenKind::OtherKeyword),
+                _ => key_or(cursor, TokenKind::Identifier),
+            }
+        }
+        _ => punctuator(cursor),
+    };
+    (TomlState::Initial, kind)
+}
+
+// `[table]` and `[[array.of.tables]]` as a whole.
+fn table_header(cursor: &mut Cursor) -> TokenKind {
+    cursor.skip_while(|char| char == '[');
+    loop {
+        match cursor.peek(0) {
+            '\0' => break,
+            ']' => {
+                cursor.skip_while(|char| char == ']');
+                break;
+            }
+            '"' => {
+                cursor.skip(1);
+                quoted_tail(cursor, '"');
+            }
+            _ => cursor.skip(1),
+        }
+    }
+    TokenKind::Typename
+}
+
+// Offset date-times like 1979-05-27T07:32:00Z are numbers as far as highlighting goes.
+fn date_or_number(cursor: &mut Cursor) -> TokenKind {
+    let rest = cursor.rest();
+    if rest.len() >= 5 && rest.as_bytes()[4] == b'-' && rest[..4].bytes().all(|b| b.is_ascii_digit()) {
+        cursor.skip_while(|char| char.is_ascii_alphanumeric() || matches!(char, '-' | ':' | '.' | '+'));
+        return TokenKind::Number;
+    }
+    if cursor.peek(2) == ':' {
+        cursor.skip_while(|char| char.is_ascii_digit() || matches!(char, ':' | '.'));
+        return TokenKind::Number;
+    }
+    number(cursor)
+}
+
+fn multi_line_string_tail(cursor: &mut Cursor, end: &str, state: TomlState) -> (TomlState, TokenKind) {
+    loop {
+        if cursor.skip_str(end) {
+            // up to two quotes may directly precede the closing delimiter
+            let quote = end.chars().next().unwrap();
+            cursor.skip_if(|char| char == quote);
+            cursor.skip_if(|char| char == quote);
+            return (TomlState::Initial, TokenKind::String);
+        }
+        match cursor.peek(0) {
+            '\0' => return (state, TokenKind::String),
+            '\\' if state == TomlState::MultiLineBasicString => cursor.skip(2),
+            _ => cursor.skip(1),
+        }
+    }
+}
+
+// A string or word directly followed by `=` or `.` is (part of) a key.
+fn key_or(cursor: &Cursor, kind: TokenKind) -> TokenKind {
+    match cursor.rest().trim_start().chars().next() {
+        Some('=') | Some('.') if cursor.before().trim_start().chars().all(is_key_char) => {
+            TokenKind::Identifier
+        }
+        _ => kind,
+    }
+}
+
+fn is_key_char(char: char) -> bool {
+    is_bare_key_char(char) || matches!(char, '.' | '"' | '\'' | ' ' | '\t')
+}
+
+fn is_bare_key_char(char: char) -> bool {
+    char.is_identifier_continue() || char == '-'
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        grammar::{assert_tokens, TOML},
+        token::TokenKind::*,
+    };
+
+    #[test]
+    fn tables_keys_and_values() {
+        assert_tokens(
+            TOML,
+            "[package]\nname = \"x\" # c\n\"quoted key\" = 'lit'\ndate = 1979-05-27T07:32:00Z\nt = true",
+            &[
+                &[(Typename, "[package]")],
+                &[(Identifier, "name"), (Punctuator, "="), (String, "\"x\""), (Comment, "# c")],
+                &[(Identifier, "\"quoted key\""), (Punctuator, "="), (String, "'lit'")],
+                &[(Identifier, "date"), (Punctuator, "="), (Number, "1979-05-27T07:32:00Z")],
+                &[(Identifier, "t"), (Punctuator, "="), (OtherKeyword, "true")],
+            ],
+        );
+    }
+
+    #[test]
+    fn multi_line_strings_span_lines() {
+        assert_tokens(
+            TOML,
+            "s = \"\"\"one\n\\\"\"\"two\"\"\"\"\nl = '''a\n'''\nx = 1",
+            &[
+                &[(Identifier, "s"), (Punctuator, "="), (String, "\"\"\"one")],
+                &[(String, "\\\"\"\"two\"\"\"\"")],
+                &[(Identifier, "l"), (Punctuator, "="), (String, "'''a")],
+                &[(String, "'''")],
+                &[(Identifier, "x"), (Punctuator, "="), (Number, "1")],
+            ],
+        );
+    }
+}
@@ -1,15 +1,32 @@
-use crate::{
-    selection::SelectionSet,
-    session::SessionId,
-    text::{Edit, Text},
+use {
+    crate::{
+        selection::SelectionSet,
+        session::SessionId,
+        text::{Edit, Text},
+    },
+    makepad_widgets::{makepad_micro_serde::*, LiveId},
 };
 
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct History {
     text: Text,
+    mode: HistoryMode,
     current_desc: Option<GroupDesc>,
-    undo_stack: Stack,
-    redo_stack: Stack,
+    // The selections before the next group, if the next edit should start one. Groups are only
+    // added to the tree once they have an edit in them, so that empty groups don't become undo
+    // steps, or hide the branch that redo would otherwise follow.
+    pending_selections: Option<SelectionSet>,
+    tree: UndoTree,
+}
+
+/// Whether making an edit after an undo discards the undone edits, or keeps them around.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum HistoryMode {
+    #[default]
+    Linear,
+    /// The undone edits are kept as a separate branch of an undo tree. Redo follows the branch
+    /// that was most recently visited, and `select_next_redo_branch` switches to the others.
+    Tree,
 }
 
 #[derive(Clone,Copy)]
@@ -23,12 +40,57 @@ impl History {
         Self::default()
     }
 
+    /// Restores a history that was stored with `to_bin`, if `text` is the text it was stored
+    /// for. Returns `None` if the text changed since then, or if the data is corrupt.
+    pub fn from_bin(text: Text, bytes: &[u8]) -> Option<Self> {
+        let stored = StoredHistory::deserialize_bin(bytes).ok()?;
+        // The tree is only read once its bytes are known to be intact, since a corrupt tree can
+        // still be well-formed, but refer to text that isn't there.
+        if stored.version != StoredHistory::VERSION
+            || stored.text_hash != text_hash(&text)
+            || stored.tree_hash != bytes_hash(&stored.tree)
+        {
+            return None;
+        }
+        let tree = UndoTree::deserialize_bin(&stored.tree).ok()?;
+        if !tree.is_valid() {
+            return None;
+        }
+        Some(Self {
+            text,
+            tree,
+            ..Self::default()
+        })
+    }
+
+    /// Returns the history in a form that can be stored alongside the text, and restored with
+    /// `from_bin`.
+    pub fn to_bin(&self) -> Vec<u8> {
+        let tree = self.tree.serialize_bin();
+        StoredHistory {
+            version: StoredHistory::VERSION,
+            text_hash: text_hash(&self.text),
+            tree_hash: bytes_hash(&tree),
+            tree,
+        }
+        .serialize_bin()
+    }
+
     pub fn as_text(&self) -> &Text {
         &self.text
     }
 
+    pub fn mode(&self) -> HistoryMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: HistoryMode) {
+        self.mode = mode;
+    }
+
     pub fn force_new_group(&mut self) {
         self.current_desc = None;
+        self.pending_selections = Some(SelectionSet::default());
     }
 
     pub fn push_or_extend_group(
@@ -45,16 +107,26 @@ impl History {
             .current_desc
             .map_or(false, |current_desc| current_desc.can_merge_with(desc))
         {
-            self.undo_stack.push_group(selections.clone());
+            self.pending_selections = Some(selections.clone());
             self.current_desc = Some(desc);
         }
     }
 
     pub fn apply_edit(&mut self, edit: Edit) {
+        let selections = self.pending_selections.take().or_else(|| {
+            (self.tree.current == UndoTree::ROOT).then(SelectionSet::default)
+        });
+        if let Some(selections) = selections {
+            if self.mode == HistoryMode::Linear {
+                self.tree.remove_children(self.tree.current);
+            }
+            self.tree.push_node(selections);
+        }
         let inverted_edit = edit.clone().invert(&self.text);
-        self.text.apply_change(edit.change);
-        self.undo_stack.push_edit(inverted_edit);
-        self.redo_stack.clear();
+        self.text.apply_change(edit.change.clone());
+        let node = &mut self.tree.nodes[self.tree.current];
+        node.edits.push(edit);
+        node.inverted_edits.push(inverted_edit);
     }
 
     pub fn undo(
@@ -62,18 +134,23 @@ impl History {
         selections: &SelectionSet,
         edits: &mut Vec<Edit>,
     ) -> Option<SelectionSet> {
-        if let Some(new_selections) = self.undo_stack.pop_group(edits) {
-            self.redo_stack.push_group(selections.clone());
-            for edit in edits {
-                let inverted_edit = edit.clone().invert(&self.text);
-                self.text.apply_change(edit.change.clone());
-                self.redo_stack.push_edit(inverted_edit);
-            }
-            self.current_desc = None;
-            Some(new_selections)
-        } else {
-            None
+        self.pending_selections = None;
+        let index = self.tree.current;
+        if index == UndoTree::ROOT {
+            return None;
+        }
+        let node = &mut self.tree.nodes[index];
+        edits.extend(node.inverted_edits.iter().rev().cloned());
+        node.redo_selections = selections.clone();
+        let new_selections = node.undo_selections.clone();
+        let parent = node.parent;
+        for edit in edits {
+            self.text.apply_change(edit.change.clone());
         }
+        self.tree.nodes[parent].redo_child = Some(index);
+        self.tree.current = parent;
+        self.current_desc = None;
+        Some(new_selections)
     }
 
     pub fn redo(
@@ -81,18 +158,38 @@ impl History {
         selections: &SelectionSet,
         edits: &mut Vec<Edit>,
     ) -> Option<SelectionSet> {
-        if let Some(new_selections) = self.redo_stack.pop_group(edits) {
-            self.undo_stack.push_group(selections.clone());
-            for edit in edits {
-                let inverted_edit = edit.clone().invert(&self.text);
-                self.text.apply_change(edit.change.clone());
-                self.undo_stack.push_edit(inverted_edit);
-            }
-            self.current_desc = None;
-            Some(new_selections)
-        } else {
-            None
+        self.pending_selections = None;
+        let index = self.tree.nodes[self.tree.current].redo_child?;
+        let node = &mut self.tree.nodes[index];
+        edits.extend(node.edits.iter().cloned());
+        node.undo_selections = selections.clone();
+        let new_selections = node.redo_selections.clone();
+        for edit in edits {
+            self.text.apply_change(edit.change.clone());
         }
+        self.tree.current = index;
+        self.current_desc = None;
+        Some(new_selections)
+    }
+
+    /// Returns the number of branches redo can follow from the current state.
+    pub fn redo_branch_count(&self) -> usize {
+        self.tree.nodes[self.tree.current].children.len()
+    }
+
+    /// Makes redo follow the next branch from the current state, wrapping around after the last
+    /// one. Returns `false` if there is no other branch to switch to.
+    pub fn select_next_redo_branch(&mut self) -> bool {
+        let node = &mut self.tree.nodes[self.tree.current];
+        if node.children.len() < 2 {
+            return false;
+        }
+        let position = node
+            .redo_child
+            .and_then(|redo_child| node.children.iter().position(|&child| child == redo_child))
+            .unwrap_or(0);
+        node.redo_child = Some(node.children[(position + 1) % node.children.len()]);
+        true
     }
 
     pub fn into_text(self) -> Text {
@@ -141,42 +238,125 @@ impl GroupDesc {
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
-struct Stack {
-    groups: Vec<Group>,
-    edits: Vec<Edit>,
+// Every node except the root is a group of edits, made to the text of its parent. The current
+// node is the one whose text is the text of the history. In linear mode, the tree is a single
+// chain.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, SerBin, DeBin)]
+struct UndoTree {
+    nodes: Vec<Node>,
+    current: usize,
 }
 
-impl Stack {
-    fn push_group(&mut self, selections: SelectionSet) {
-        self.groups.push(Group {
-            selections,
-            edit_start: self.edits.len(),
+impl UndoTree {
+    const ROOT: usize = 0;
+
+    fn push_node(&mut self, selections: SelectionSet) {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            parent: self.current,
+            undo_selections: selections.clone(),
+            redo_selections: selections,
+            ..Node::default()
+        });
+        let parent = &mut self.nodes[self.current];
+        parent.children.push(index);
+        parent.redo_child = Some(index);
+        self.current = index;
+    }
+
+    // Removes the children of the node at `index`, and everything below them.
+    fn remove_children(&mut self, index: usize) {
+        if self.nodes[index].children.is_empty() {
+            return;
+        }
+        let mut is_removed = vec![false; self.nodes.len()];
+        let mut stack = std::mem::take(&mut self.nodes[index].children);
+        self.nodes[index].redo_child = None;
+        while let Some(child) = stack.pop() {
+            is_removed[child] = true;
+            stack.extend(self.nodes[child].children.iter().copied());
+        }
+        let mut new_indices = Vec::with_capacity(self.nodes.len());
+        let mut new_index = 0;
+        for &is_removed in &is_removed {
+            new_indices.push(new_index);
+            if !is_removed {
+                new_index += 1;
+            }
+        }
+        let mut old_index = 0;
+        self.nodes.retain(|_| {
+            old_index += 1;
+            !is_removed[old_index - 1]
         });
+        for node in &mut self.nodes {
+            node.parent = new_indices[node.parent];
+            for child in &mut node.children {
+                *child = new_indices[*child];
+            }
+            node.redo_child = node.redo_child.map(|child| new_indices[child]);
+        }
+        self.current = new_indices[self.current];
     }
 
-    fn push_edit(&mut self, edit: Edit) {
-        self.edits.push(edit);
+    // Checks that every index in the tree refers to a node, so that a tree read back from storage
+    // can't cause a panic later on.
+    fn is_valid(&self) -> bool {
+        let len = self.nodes.len();
+        len > 0
+            && self.current < len
+            && self.nodes.iter().all(|node| {
+                node.parent < len
+                    && node.edits.len() == node.inverted_edits.len()
+                    && node.children.iter().all(|&child| child < len)
+                    && node.redo_child.map_or(true, |child| child < len)
+            })
     }
+}
 
-    fn pop_group(&mut self, edits: &mut Vec<Edit>) -> Option<SelectionSet> {
-        match self.groups.pop() {
-            Some(group) => {
-                edits.extend(self.edits.drain(group.edit_start..).rev());
-                Some(group.selections)
-            }
-            None => None,
+impl Default for UndoTree {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+            current: Self::ROOT,
         }
     }
+}
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, SerBin, DeBin)]
+struct Node {
+    parent: usize,
+    children: Vec<usize>,
+    redo_child: Option<usize>,
+    // The selections to restore when the group is undone, and when it is redone, respectively.
+    undo_selections: SelectionSet,
+    redo_selections: SelectionSet,
+    edits: Vec<Edit>,
+    inverted_edits: Vec<Edit>,
+}
+
+#[derive(SerBin, DeBin)]
+struct StoredHistory {
+    version: u32,
+    text_hash: u64,
+    tree_hash: u64,
+    // The serialized `UndoTree`.
+    tree: Vec<u8>,
+}
+
+impl StoredHistory {
+    const VERSION: u32 = 2;
+}
 
-    fn clear(&mut self) {
-        self.groups.clear();
-        self.edits.clear();
+fn text_hash(text: &Text) -> u64 {
+    let mut hash = LiveId::from_str("").0;
+    for line in text.as_lines() {
+        hash = LiveId::from_bytes(hash, line.as_bytes(), 0, line.len()).0;
+        hash = LiveId::from_bytes(hash, b"\n", 0, 1).0;
     }
+    hash
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-struct Group {
-    selections: SelectionSet,
-    edit_start: usize,
+fn bytes_hash(bytes: &[u8]) -> u64 {
+    LiveId::from_bytes(LiveId::from_str("").0, bytes, 0, bytes.len()).0
 }
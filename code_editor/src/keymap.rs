@@ -0,0 +1,534 @@
+use {
+    makepad_toml_parser::{parse_toml, Toml},
+    makepad_widgets::*,
+    std::fmt,
+};
+
+/// The default bindings of the code editor. Applications can add their own bindings on top with
+/// `Keymap::load_toml`.
+pub const DEFAULT_KEYMAP: &str = r#"
+[keys]
+"ctrl+=" = "editor.zoom_in"
+"cmd+=" = "editor.zoom_in"
+"ctrl+-" = "editor.zoom_out"
+"cmd+-" = "editor.zoom_out"
+"ctrl+0" = "editor.reset_zoom"
+"cmd+0" = "editor.reset_zoom"
+"ctrl+w" = "editor.toggle_word_wrap"
+"cmd+w" = "editor.toggle_word_wrap"
+"ctrl+f" = "editor.find_selection"
+"cmd+f" = "editor.find_selection"
+"ctrl+g" = "editor.find_next"
+"cmd+g" = "editor.find_next"
+"f3" = "editor.find_next"
+"ctrl+shift+g" = "editor.find_previous"
+"cmd+shift+g" = "editor.find_previous"
+"shift+f3" = "editor.find_previous"
+"f12" = "editor.go_to_definition"
+"ctrl+space" = "editor.trigger_completion"
+"ctrl+a" = "editor.select_all"
+"cmd+a" = "editor.select_all"
+
+"left" = "editor.cursor_left"
+"shift+left" = "editor.select_left"
+"right" = "editor.cursor_right"
+"shift+right" = "editor.select_right"
+"ctrl+left" = "editor.cursor_word_left"
+"alt+left" = "editor.cursor_word_left"
+"ctrl+shift+left" = "editor.select_word_left"
+"alt+shift+left" = "editor.select_word_left"
+"ctrl+right" = "editor.cursor_word_right"
+"alt+right" = "editor.cursor_word_right"
+"ctrl+shift+right" = "editor.select_word_right"
+"alt+shift+right" = "editor.select_word_right"
+"ctrl+alt+left" = "editor.cursor_subword_left"
+"ctrl+alt+shift+left" = "editor.select_subword_left"
+"ctrl+alt+right" = "editor.cursor_subword_right"
+"ctrl+alt+shift+right" = "editor.select_subword_right"
+"up" = "editor.cursor_up"
+"shift+up" = "editor.select_up"
+"down" = "editor.cursor_down"
+"shift+down" = "editor.select_down"
+"ctrl+up" = "editor.cursor_paragraph_up"
+"alt+up" = "editor.cursor_paragraph_up"
+"ctrl+shift+up" = "editor.select_paragraph_up"
+"alt+shift+up" = "editor.select_paragraph_up"
+"ctrl+down" = "editor.cursor_paragraph_down"
+"alt+down" = "editor.cursor_paragraph_down"
+"ctrl+shift+down" = "editor.select_paragraph_down"
+"alt+shift+down" = "editor.select_paragraph_down"
+"home" = "editor.cursor_home"
+"shift+home" = "editor.select_home"
+"end" = "editor.cursor_end"
+"shift+end" = "editor.select_end"
+"ctrl+home" = "editor.cursor_document_start"
+"cmd+up" = "editor.cursor_document_start"
+"ctrl+shift+home" = "editor.select_document_start"
+"cmd+shift+up" = "editor.select_document_start"
+"ctrl+end" = "editor.cursor_document_end"
+"cmd+down" = "editor.cursor_document_end"
+"ctrl+shift+end" = "editor.select_document_end"
+"cmd+shift+down" = "editor.select_document_end"
+"pageup" = "editor.cursor_page_up"
+"shift+pageup" = "editor.select_page_up"
+"pagedown" = "editor.cursor_page_down"
+"shift+pagedown" = "editor.select_page_down"
+"ctrl+m" = "editor.cursor_matching_delimiter"
+"cmd+m" = "editor.cursor_matching_delimiter"
+"ctrl+shift+m" = "editor.select_matching_delimiter"
+"cmd+shift+m" = "editor.select_matching_delimiter"
+
+"enter" = "editor.new_line"
+"shift+enter" = "editor.new_line"
+"tab" = "editor.indent"
+"shift+tab" = "editor.outdent"
+"delete" = "editor.delete"
+"shift+delete" = "editor.delete"
+"ctrl+delete" = "editor.delete_word_right"
+"alt+delete" = "editor.delete_word_right"
+"ctrl+alt+delete" = "editor.delete_subword_right"
+"ctrl+shift+delete" = "editor.delete_paragraph_down"
+"backspace" = "editor.backspace"
+"shift+backspace" = "editor.backspace"
+"ctrl+backspace" = "editor.delete_word_left"
+"alt+backspace" = "editor.delete_word_left"
+"ctrl+alt+backspace" = "editor.delete_subword_left"
+"ctrl+shift+backspace" = "editor.delete_paragraph_up"
+"ctrl+alt+m" = "editor.delete_to_matching_delimiter"
+"cmd+alt+m" = "editor.delete_to_matching_delimiter"
+"cmd+z" = "editor.undo"
+"cmd+shift+z" = "editor.redo"
+"cmd+alt+z" = "editor.redo_next_branch"
+"#;
+
+// The names of keys in a keymap. The first name of a key is the one it is displayed with.
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("a", KeyCode::KeyA), ("b", KeyCode::KeyB), ("c", KeyCode::KeyC), ("d", KeyCode::KeyD),
+    ("e", KeyCode::KeyE), ("f", KeyCode::KeyF), ("g", KeyCode::KeyG), ("h", KeyCode::KeyH),
+    ("i", KeyCode::KeyI), ("j", KeyCode::KeyJ), ("k", KeyCode::KeyK), ("l", KeyCode::KeyL),
+    ("m", KeyCode::KeyM), ("n", KeyCode::KeyN), ("o", KeyCode::KeyO), ("p", KeyCode::KeyP),
+    ("q", KeyCode::KeyQ), ("r", KeyCode::KeyR), ("s", KeyCode::KeyS), ("t", KeyCode::KeyT),
+    ("u", KeyCode::KeyU), ("v", KeyCode::KeyV), ("w", KeyCode::KeyW), ("x", KeyCode::KeyX),
+    ("y", KeyCode::KeyY), ("z", KeyCode::KeyZ),
+    ("0", KeyCode::Key0), ("1", KeyCode::Key1), ("2", KeyCode::Key2), ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4), ("5", KeyCode::Key5), ("6", KeyCode::Key6), ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8), ("9", KeyCode::Key9),
+    ("f1", KeyCode::F1), ("f2", KeyCode::F2), ("f3", KeyCode::F3), ("f4", KeyCode::F4),
+    ("f5", KeyCode::F5), ("f6", KeyCode::F6), ("f7", KeyCode::F7), ("f8", KeyCode::F8),
+    ("f9", KeyCode::F9), ("f10", KeyCode::F10), ("f11", KeyCode::F11), ("f12", KeyCode::F12),
+    ("-", KeyCode::Minus), ("minus", KeyCode::Minus),
+    ("=", KeyCode::Equals), ("equals", KeyCode::Equals),
+    ("`", KeyCode::Backtick), ("backtick", KeyCode::Backtick),
+    ("[", KeyCode::LBracket), ("]", KeyCode::RBracket),
+    (";", KeyCode::Semicolon), ("'", KeyCode::Quote), ("\\", KeyCode::Backslash),
+    (",", KeyCode::Comma), (".", KeyCode::Period), ("/", KeyCode::Slash),
+    ("enter", KeyCode::ReturnKey), ("return", KeyCode::ReturnKey),
+    ("tab", KeyCode::Tab), ("space", KeyCode::Space),
+    ("backspace", KeyCode::Backspace), ("delete", KeyCode::Delete), ("insert", KeyCode::Insert),
+    ("escape", KeyCode::Escape), ("esc", KeyCode::Escape),
+    ("home", KeyCode::Home), ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp), ("pagedown", KeyCode::PageDown),
+    ("up", KeyCode::ArrowUp), ("down", KeyCode::ArrowDown),
+    ("left", KeyCode::ArrowLeft), ("right", KeyCode::ArrowRight),
+];
+
+/// A key together with the exact modifiers that have to be held down for it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyStroke {
+    pub key_code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyStroke {
+    pub fn from_key_event(event: &KeyEvent) -> Self {
+        Self {
+            key_code: event.key_code,
+            modifiers: event.modifiers,
+        }
+    }
+
+    /// Parses a stroke such as `ctrl+shift+p`. The modifiers are `ctrl`, `shift`, `alt` and `cmd`,
+    /// which is the logo key.
+    pub fn parse(string: &str) -> Result<Self, String> {
+        let string = string.to_lowercase();
+        let (modifier_names, key_name) = string.rsplit_once('+').unwrap_or(("", &string));
+        let mut modifiers = KeyModifiers::default();
+        for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            match name {
+                "ctrl" | "control" => modifiers.control = true,
+                "shift" => modifiers.shift = true,
+                "alt" | "option" => modifiers.alt = true,
+                "cmd" | "logo" | "super" | "meta" => modifiers.logo = true,
+                _ => return Err(format!("unknown modifier `{}`", name)),
+            }
+        }
+        let key_code = KEY_NAMES
+            .iter()
+            .find(|(name, _)| *name == key_name)
+            .map(|(_, key_code)| *key_code)
+            .ok_or_else(|| format!("unknown key `{}`", key_name))?;
+        Ok(Self {
+            key_code,
+            modifiers,
+        })
+    }
+}
+
+impl fmt::Display for KeyStroke {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let modifiers = &self.modifiers;
+        for (is_held, name) in [
+            (modifiers.control, "Ctrl+"),
+            (modifiers.alt, "Alt+"),
+            (modifiers.shift, "Shift+"),
+            (modifiers.logo, "Cmd+"),
+        ] {
+            if is_held {
+                f.write_str(name)?;
+            }
+        }
+        let name = KEY_NAMES
+            .iter()
+            .find(|(_, key_code)| *key_code == self.key_code)
+            .map_or("?", |(name, _)| name);
+        let mut chars = name.chars();
+        if let Some(first) = chars.next() {
+            write!(f, "{}{}", first.to_uppercase(), chars.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a sequence of strokes separated by spaces, such as `ctrl+k ctrl+c`.
+pub fn parse_key_sequence(string: &str) -> Result<Vec<KeyStroke>, String> {
+    let strokes = string
+        .split_whitespace()
+        .map(KeyStroke::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    if strokes.is_empty() {
+        return Err("empty key sequence".to_string());
+    }
+    Ok(strokes)
+}
+
+/// Maps sequences of key strokes to command ids.
+///
+/// A sequence of more than one stroke is a chord: its strokes are typed one after the other, and
+/// only the last one runs the command.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyStroke>, String)>,
+    // The strokes of the chord that is being typed.
+    pending: Vec<KeyStroke>,
+    // The time of the last key event that was resolved, and what it resolved to. The same key
+    // event is resolved by both the focused widget and the app, and a chord has to advance once.
+    last_resolved: Option<(f64, Option<String>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self::new();
+        keymap.load_toml(DEFAULT_KEYMAP).unwrap();
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Creates a keymap without any bindings.
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            pending: Vec::new(),
+            last_resolved: None,
+        }
+    }
+
+    /// Binds the sequences in the `[keys]` table of a TOML file, replacing the bindings of
+    /// sequences that are already bound. An empty command removes a binding:
+    ///
+    /// ```toml
+    /// [keys]
+    /// "ctrl+shift+p" = "studio.command_palette"
+    /// "ctrl+k ctrl+c" = "studio.clear_log"
+    /// "cmd+w" = ""
+    /// ```
+    ///
+    /// Entries that fail to parse are skipped, and returned as errors.
+    pub fn load_toml(&mut self, toml: &str) -> Result<(), Vec<String>> {
+        let toml = parse_toml(toml).map_err(|err| vec![format!("{:?}", err)])?;
+        let mut entries: Vec<_> = toml
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix("keys.")?.to_string(), value)))
+            .collect();
+        // Entries come out of the parser in no particular order.
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut errors = Vec::new();
+        for (sequence, value) in entries {
+            let Toml::Str(command, _) = value else {
+                errors.push(format!("binding for `{}` is not a string", sequence));
+                continue;
+            };
+            match parse_key_sequence(&sequence) {
+                Ok(strokes) if command.is_empty() => self.unbind(&strokes),
+                Ok(strokes) => self.bind(strokes, &command),
+                Err(err) => errors.push(format!("cannot bind `{}`: {}", sequence, err)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn bind(&mut self, strokes: Vec<KeyStroke>, command: &str) {
+        self.unbind(&strokes);
+        self.bindings.push((strokes, command.to_string()));
+    }
+
+    pub fn unbind(&mut self, strokes: &[KeyStroke]) {
+        self.bindings.retain(|(bound_strokes, _)| bound_strokes != strokes);
+    }
+
+    /// Returns the sequence that runs a command, for display, if it is bound. Sequences with the
+    /// logo key are preferred on macOS, and avoided elsewhere.
+    pub fn sequence_for_command(&self, command: &str) -> Option<String> {
+        let mut sequences = self
+            .bindings
+            .iter()
+            .filter(|(_, bound_command)| bound_command == command)
+            .map(|(strokes, _)| strokes);
+        let first = sequences.next()?;
+        let uses_logo = |strokes: &Vec<KeyStroke>| strokes.iter().any(|stroke| stroke.modifiers.logo);
+        let strokes = std::iter::once(first)
+            .chain(sequences)
+            .find(|strokes| uses_logo(strokes) == cfg!(target_os = "macos"))
+            .unwrap_or(first);
+        Some(
+            strokes
+                .iter()
+                .map(|stroke| stroke.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+
+    /// Returns whether the first strokes of a chord were typed, and the keymap waits for the rest.
+    pub fn is_chord_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Returns the command that a key event runs, if any. Resolving the same event again returns
+    /// the same result, without advancing chords.
+    pub fn resolve(&mut self, event: &KeyEvent) -> Option<String> {
+        if let Some((time, command)) = &self.last_resolved {
+            if *time == event.time {
+                return command.clone();
+            }
+        }
+        let command = self.resolve_stroke(KeyStroke::from_key_event(event));
+        self.last_resolved = Some((event.time, command.clone()));
+        command
+    }
+
+    fn resolve_stroke(&mut self, stroke: KeyStroke) -> Option<String> {
+        // Modifiers are part of the next stroke, and do not break a chord.
+        if matches!(
+            stroke.key_code,
+            KeyCode::Control | KeyCode::Shift | KeyCode::Alt | KeyCode::Logo
+        ) {
+            return None;
+        }
+        self.pending.push(stroke);
+        if let Some((_, command)) = self
+            .bindings
+            .iter()
+            .find(|(strokes, _)| *strokes == self.pending)
+        {
+            self.pending.clear();
+            return Some(command.clone());
+        }
+        if self
+            .bindings
+            .iter()
+            .any(|(strokes, _)| strokes.starts_with(&self.pending))
+        {
+            return None;
+        }
+        // The stroke does not continue the chord, so it starts over with just this stroke.
+        let is_chord = self.pending.len() > 1;
+        self.pending.clear();
+        if is_chord {
+            self.resolve_stroke(stroke)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::command::EditorCommand};
+
+    fn stroke(key_code: KeyCode, control: bool, shift: bool, alt: bool, logo: bool) -> KeyStroke {
+        KeyStroke {
+            key_code,
+            modifiers: KeyModifiers {
+                control,
+                shift,
+                alt,
+                logo,
+            },
+        }
+    }
+
+    // Resolves a key press at a new time, like a real key event.
+    fn press(keymap: &mut Keymap, time: &mut f64, stroke: &str) -> Option<String> {
+        let stroke = KeyStroke::parse(stroke).unwrap();
+        *time += 1.0;
+        keymap.resolve(&KeyEvent {
+            key_code: stroke.key_code,
+            is_repeat: false,
+            modifiers: stroke.modifiers,
+            time: *time,
+        })
+    }
+
+    #[test]
+    fn parses_strokes() {
+        assert_eq!(
+            KeyStroke::parse("ctrl+shift+p"),
+            Ok(stroke(KeyCode::KeyP, true, true, false, false))
+        );
+        assert_eq!(
+            KeyStroke::parse("Cmd+Option+Z"),
+            Ok(stroke(KeyCode::KeyZ, false, false, true, true))
+        );
+        assert_eq!(KeyStroke::parse("f3"), Ok(stroke(KeyCode::F3, false, false, false, false)));
+        assert_eq!(KeyStroke::parse("-"), Ok(stroke(KeyCode::Minus, false, false, false, false)));
+        assert_eq!(
+            KeyStroke::parse("ctrl+="),
+            Ok(stroke(KeyCode::Equals, true, false, false, false))
+        );
+        assert!(KeyStroke::parse("hyper+a").is_err());
+        assert!(KeyStroke::parse("ctrl+nokey").is_err());
+        assert!(KeyStroke::parse("").is_err());
+    }
+
+    #[test]
+    fn displays_strokes() {
+        let stroke = KeyStroke::parse("shift+ctrl+pageup").unwrap();
+        assert_eq!(stroke.to_string(), "Ctrl+Shift+Pageup");
+        assert_eq!(KeyStroke::parse("cmd+-").unwrap().to_string(), "Cmd+-");
+    }
+
+    #[test]
+    fn parses_sequences() {
+        let strokes = parse_key_sequence("ctrl+k  ctrl+c").unwrap();
+        assert_eq!(strokes, [KeyStroke::parse("ctrl+k").unwrap(), KeyStroke::parse("ctrl+c").unwrap()]);
+        assert!(parse_key_sequence(" ").is_err());
+        assert!(parse_key_sequence("ctrl+k nokey").is_err());
+    }
+
+    #[test]
+    fn matches_modifiers_exactly() {
+        let mut keymap = Keymap::default();
+        let mut time = 0.0;
+        assert_eq!(press(&mut keymap, &mut time, "home").as_deref(), Some("editor.cursor_home"));
+        assert_eq!(
+            press(&mut keymap, &mut time, "ctrl+home").as_deref(),
+            Some("editor.cursor_document_start")
+        );
+        assert_eq!(
+            press(&mut keymap, &mut time, "ctrl+shift+end").as_deref(),
+            Some("editor.select_document_end")
+        );
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+alt+shift+home"), None);
+    }
+
+    #[test]
+    fn default_bindings_run_editor_commands() {
+        let keymap = Keymap::default();
+        for (strokes, command) in &keymap.bindings {
+            assert!(
+                EditorCommand::from_id(command).is_some(),
+                "{:?} is bound to unknown command `{}`",
+                strokes,
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn resolves_chords() {
+        let mut keymap = Keymap::new();
+        keymap
+            .load_toml("[keys]\n\"ctrl+k ctrl+c\" = \"comment\"\n\"ctrl+c\" = \"copy\"\n")
+            .unwrap();
+        let mut time = 0.0;
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+k"), None);
+        assert!(keymap.is_chord_pending());
+        // Holding down a modifier does not break the chord.
+        let modifier_event = KeyEvent {
+            key_code: KeyCode::Control,
+            is_repeat: false,
+            modifiers: KeyModifiers {
+                control: true,
+                ..KeyModifiers::default()
+            },
+            time: 0.5,
+        };
+        assert_eq!(keymap.resolve(&modifier_event), None);
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+c").as_deref(), Some("comment"));
+        assert!(!keymap.is_chord_pending());
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+c").as_deref(), Some("copy"));
+
+        // A stroke that does not continue the chord starts over.
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+k"), None);
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+c").as_deref(), Some("comment"));
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+k"), None);
+        assert_eq!(press(&mut keymap, &mut time, "x"), None);
+        assert!(!keymap.is_chord_pending());
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+k"), None);
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+k"), None);
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+c").as_deref(), Some("comment"));
+    }
+
+    #[test]
+    fn resolving_the_same_event_twice_advances_once() {
+        let mut keymap = Keymap::new();
+        keymap.load_toml("[keys]\n\"ctrl+k ctrl+k\" = \"twice\"\n").unwrap();
+        let event = KeyEvent {
+            key_code: KeyCode::KeyK,
+            is_repeat: false,
+            modifiers: KeyModifiers {
+                control: true,
+                ..KeyModifiers::default()
+            },
+            time: 1.0,
+        };
+        assert_eq!(keymap.resolve(&event), None);
+        assert_eq!(keymap.resolve(&event), None);
+        assert!(keymap.is_chord_pending());
+        assert_eq!(keymap.resolve(&KeyEvent { time: 2.0, ..event }).as_deref(), Some("twice"));
+    }
+
+    #[test]
+    fn user_bindings_override_and_unbind_defaults() {
+        let mut keymap = Keymap::default();
+        let errors = keymap
+            .load_toml(
+                "[keys]\n\"ctrl+g\" = \"user.go_to_line\"\n\"f3\" = \"\"\n\"ctrl+nokey\" = \"x\"\n",
+            )
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("ctrl+nokey"));
+        let mut time = 0.0;
+        assert_eq!(press(&mut keymap, &mut time, "ctrl+g").as_deref(), Some("user.go_to_line"));
+        assert_eq!(press(&mut keymap, &mut time, "f3"), None);
+        assert_eq!(press(&mut keymap, &mut time, "cmd+g").as_deref(), Some("editor.find_next"));
+        assert_eq!(keymap.sequence_for_command("user.go_to_line").as_deref(), Some("Ctrl+G"));
+        assert_eq!(keymap.sequence_for_command("editor.go_to_definition").as_deref(), Some("F12"));
+        assert_eq!(keymap.sequence_for_command("nothing"), None);
+    }
+}
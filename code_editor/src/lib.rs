@@ -3,13 +3,18 @@ use makepad_widgets::*;
 
 pub mod char;
 pub mod code_editor;
+pub mod command;
 pub mod decoration;
 pub mod document;
+pub mod grammar;
 pub mod history;
 pub mod inlays;
 pub mod iter;
+pub mod keymap;
 pub mod layout;
+pub use makepad_regex::regex;
 pub mod selection;
+pub mod search;
 pub mod session;
 pub mod settings;
 pub mod str;
@@ -21,7 +26,8 @@ pub mod wrap;
 pub mod code_view;
 
 pub use self::{
-    code_editor::CodeEditor, document::CodeDocument, history::History, layout::Line,
+    code_editor::CodeEditor, command::EditorCommand, document::CodeDocument, history::History,
+    keymap::Keymap, layout::Line,
     selection::Selection, session::CodeSession, settings::Settings, token::Token, tokenizer::Tokenizer,
 };
 
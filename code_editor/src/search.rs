@@ -0,0 +1,23 @@
+use crate::text::{Position, Text};
+
+pub use makepad_regex::search::{SearchOptions, SearchQuery};
+
+/// Returns the start and end position of every non-empty match of `query` in `text`, in order.
+pub fn find_all(query: &SearchQuery, text: &Text) -> Vec<(Position, Position)> {
+    let mut matches = Vec::new();
+    for (line_index, line) in text.as_lines().iter().enumerate() {
+        for range in query.find_in_line(line) {
+            matches.push((
+                Position {
+                    line_index,
+                    byte_index: range.start,
+                },
+                Position {
+                    line_index,
+                    byte_index: range.end,
+                },
+            ));
+        }
+    }
+    matches
+}
@@ -4,10 +4,11 @@ use {
         str::StrExt,
         text::{Edit, Length, Position},
     },
+    makepad_widgets::makepad_micro_serde::*,
     std::{ops, ops::Deref, slice::Iter},
 };
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Hash, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Hash, Eq, SerBin, DeBin)]
 pub struct Selection {
     pub cursor: Cursor,
     pub anchor: Position,
@@ -116,7 +117,7 @@ impl From<Cursor> for Selection {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, SerBin, DeBin)]
 pub struct SelectionSet {
     selections: Vec<Selection>,
 }
@@ -245,7 +246,7 @@ impl<'a> IntoIterator for &'a SelectionSet {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, SerBin, DeBin)]
 pub struct Cursor {
     pub position: Position,
     pub affinity: Affinity,
@@ -360,6 +361,92 @@ impl Cursor {
         self
     }
 
+    pub fn move_word_left(self, lines: &[String], word_separators: &[char]) -> Self {
+        if self.is_at_start_of_line() {
+            return self.move_left(lines);
+        }
+        let line = &lines[self.position.line_index];
+        let byte_index = find_prev_word_start(&line[..self.position.byte_index], word_separators, false);
+        self.move_to_byte_index(byte_index, Affinity::After)
+    }
+
+    pub fn move_word_right(self, lines: &[String], word_separators: &[char]) -> Self {
+        if self.is_at_end_of_line(lines) {
+            return self.move_right(lines);
+        }
+        let line = &lines[self.position.line_index];
+        let byte_index = self.position.byte_index
+            + find_next_word_end(&line[self.position.byte_index..], word_separators, false);
+        self.move_to_byte_index(byte_index, Affinity::Before)
+    }
+
+    pub fn move_subword_left(self, lines: &[String], word_separators: &[char]) -> Self {
+        if self.is_at_start_of_line() {
+            return self.move_left(lines);
+        }
+        let line = &lines[self.position.line_index];
+        let byte_index = find_prev_word_start(&line[..self.position.byte_index], word_separators, true);
+        self.move_to_byte_index(byte_index, Affinity::After)
+    }
+
+    pub fn move_subword_right(self, lines: &[String], word_separators: &[char]) -> Self {
+        if self.is_at_end_of_line(lines) {
+            return self.move_right(lines);
+        }
+        let line = &lines[self.position.line_index];
+        let byte_index = self.position.byte_index
+            + find_next_word_end(&line[self.position.byte_index..], word_separators, true);
+        self.move_to_byte_index(byte_index, Affinity::Before)
+    }
+
+    /// Moves to the blank line above the current paragraph, or to the start of the text if there
+    /// is none. Lines that only contain whitespace count as blank.
+    pub fn move_paragraph_up(self, lines: &[String]) -> Self {
+        let is_blank = |line_index: usize| lines[line_index].trim().is_empty();
+        let mut line_index = self.position.line_index;
+        while line_index > 0 && is_blank(line_index) {
+            line_index -= 1;
+        }
+        while line_index > 0 && !is_blank(line_index) {
+            line_index -= 1;
+        }
+        if !is_blank(line_index) {
+            return self.move_to_file_start();
+        }
+        Self {
+            position: Position {
+                line_index,
+                byte_index: 0,
+            },
+            affinity: Affinity::Before,
+            preferred_column_index: None,
+        }
+    }
+
+    /// Moves to the blank line below the current paragraph, or to the end of the text if there is
+    /// none.
+    pub fn move_paragraph_down(self, lines: &[String]) -> Self {
+        let is_blank = |line_index: usize| lines[line_index].trim().is_empty();
+        let mut line_index = self.position.line_index;
+        while line_index < lines.len() && is_blank(line_index) {
+            line_index += 1;
+        }
+        while line_index < lines.len() && !is_blank(line_index) {
+            line_index += 1;
+        }
+        if line_index == lines.len() {
+            return self.move_to_file_end(lines);
+        }
+        Self {
+            position: Position {
+                line_index,
+                byte_index: 0,
+            },
+            affinity: Affinity::Before,
+            preferred_column_index: None,
+        }
+    }
+
     pub fn move_to_end_of_line(self, lines: &[String]) -> Self {
         let mut me = self.clone();
         while !me.is_at_end_of_line(lines) {
@@ -530,6 +617,16 @@ impl Cursor {
         }
     }
 
+    fn move_to_byte_index(self, byte_index: usize, affinity: Affinity) -> Self {
+        Self {
+            position: Position {
+                line_index: self.position.line_index,
+                byte_index,
+            },
+            affinity,
+            preferred_column_index: None,
+        }
+    }
 
     pub fn apply_edit(self, edit: &Edit) -> Self {
         Self {
@@ -549,7 +646,7 @@ impl From<Position> for Cursor {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, SerBin, DeBin)]
 pub enum Affinity {
     Before,
     After,
@@ -560,3 +657,114 @@ impl Default for Affinity {
         Self::Before
     }
 }
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CharClass {
+    Whitespace,
+    Separator,
+    Word,
+}
+
+impl CharClass {
+    fn of(char: char, word_separators: &[char]) -> Self {
+        if char.is_whitespace() {
+            Self::Whitespace
+        } else if word_separators.contains(&char) {
+            Self::Separator
+        } else {
+            Self::Word
+        }
+    }
+}
+
+// Returns the byte index of the start of the last word in `string`, skipping any trailing
+// whitespace. A run of separators counts as a word. If `subword` is true, words are further split
+// at underscores and at the humps of camelCase and PascalCase identifiers.
+fn find_prev_word_start(string: &str, word_separators: &[char], subword: bool) -> usize {
+    let chars: Vec<_> = string.char_indices().collect();
+    let mut index = chars.len();
+    let class_at = |index: usize| CharClass::of(chars[index].1, word_separators);
+    while index > 0 && class_at(index - 1) == CharClass::Whitespace {
+        index -= 1;
+    }
+    if index == 0 {
+        return 0;
+    }
+    let class = class_at(index - 1);
+    if class == CharClass::Separator || !subword {
+        while index > 0 && class_at(index - 1) == class {
+            index -= 1;
+        }
+    } else {
+        let is_word_char = |index: usize| class_at(index) == CharClass::Word;
+        while index > 0 && chars[index - 1].1 == '_' {
+            index -= 1;
+        }
+        let end = index;
+        while index > 0
+            && is_word_char(index - 1)
+            && chars[index - 1].1 != '_'
+            && !chars[index - 1].1.is_uppercase()
+        {
+            index -= 1;
+        }
+        if index < end {
+            // A lowercase tail like `Bar` in `fooBar` includes the uppercase letter before it.
+            if index > 0 && chars[index - 1].1.is_uppercase() {
+                index -= 1;
+            }
+        } else {
+            while index > 0 && chars[index - 1].1.is_uppercase() {
+                index -= 1;
+            }
+        }
+    }
+    chars.get(index).map_or(string.len(), |&(byte_index, _)| byte_index)
+}
+
+// Returns the byte index of the end of the first word in `string`, skipping any leading
+// whitespace. See `find_prev_word_start`.
+fn find_next_word_end(string: &str, word_separators: &[char], subword: bool) -> usize {
+    let chars: Vec<_> = string.char_indices().collect();
+    let mut index = 0;
+    let class_at = |index: usize| CharClass::of(chars[index].1, word_separators);
+    while index < chars.len() && class_at(index) == CharClass::Whitespace {
+        index += 1;
+    }
+    if index == chars.len() {
+        return string.len();
+    }
+    let class = class_at(index);
+    if class == CharClass::Separator || !subword {
+        while index < chars.len() && class_at(index) == class {
+            index += 1;
+        }
+    } else {
+        let is_lowercase_char = |index: usize| {
+            class_at(index) == CharClass::Word
+                && chars[index].1 != '_'
+                && !chars[index].1.is_uppercase()
+        };
+        while index < chars.len() && chars[index].1 == '_' {
+            index += 1;
+        }
+        if index < chars.len() && chars[index].1.is_uppercase() {
+            index += 1;
+            if index < chars.len() && chars[index].1.is_uppercase() {
+                // An acronym like `HTTP` in `HTTPServer` ends before the uppercase letter that
+                // starts the next hump.
+                while index < chars.len()
+                    && chars[index].1.is_uppercase()
+                    && !(index + 1 < chars.len() && is_lowercase_char(index + 1))
+                {
+                    index += 1;
+                }
+                return chars.get(index).map_or(string.len(), |&(byte_index, _)| byte_index);
+            }
+        }
+        while index < chars.len() && is_lowercase_char(index) {
+            index += 1;
+        }
+    }
+    chars.get(index).map_or(string.len(), |&(byte_index, _)| byte_index)
+}
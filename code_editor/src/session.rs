@@ -1,9 +1,12 @@
 use {
     crate::{
         char::CharExt,
+        decoration::{Decoration, DecorationSet, DecorationType},
         document::CodeDocument,
         history::{EditKind,NewGroup},
         layout::{BlockElement, Layout, WrappedElement},
+        regex::RegexError,
+        search::{SearchOptions, SearchQuery},
         selection::{Affinity, Cursor, SelectionSet},
         str::StrExt,
         text::{Change, Drift, Edit, Length, Position, Text},
@@ -16,6 +19,7 @@ use {
         collections::HashSet,
         fmt::Write,
         iter, mem,
+        ops::Range,
         rc::Rc,
         sync::{atomic, atomic::AtomicUsize, mpsc, mpsc::Receiver},
     },
@@ -30,6 +34,7 @@ pub struct CodeSession {
     selection_state: RefCell<SelectionState>,
     wrap_column: Cell<Option<usize>>,
     fold_state: RefCell<FoldState>,
+    search_state: RefCell<SearchState>,
     edit_receiver: Receiver<(Option<SelectionSet>, Vec<Edit>)>,
 }
 
@@ -63,6 +68,11 @@ impl CodeSession {
                 folded_lines: HashSet::new(),
                 unfolding_lines: HashSet::new(),
             }),
+            search_state: RefCell::new(SearchState {
+                query: None,
+                line_matches: Vec::new(),
+                matches: DecorationSet::new(),
+            }),
             edit_receiver,
         };
         for line in 0..line_count {
@@ -315,6 +325,111 @@ impl CodeSession {
         });
     }
 
+    pub fn move_to_document_start(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, _| {
+            selection.update_cursor(|_| Cursor::from(Position::zero()))
+        });
+    }
+
+    pub fn move_to_document_end(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            let lines = layout.as_text().as_lines();
+            selection.update_cursor(|_| {
+                Cursor::from(Position {
+                    line_index: lines.len() - 1,
+                    byte_index: lines[lines.len() - 1].len(),
+                })
+            })
+        });
+    }
+
+    pub fn move_word_left(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| {
+                cursor.move_word_left(layout.as_text().as_lines(), &self.settings.word_separators)
+            })
+        });
+    }
+
+    pub fn move_word_right(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| {
+                cursor.move_word_right(layout.as_text().as_lines(), &self.settings.word_separators)
+            })
+        });
+    }
+
+    pub fn move_subword_left(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| {
+                cursor.move_subword_left(layout.as_text().as_lines(), &self.settings.word_separators)
+            })
+        });
+    }
+
+    pub fn move_subword_right(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| {
+                cursor.move_subword_right(layout.as_text().as_lines(), &self.settings.word_separators)
+            })
+        });
+    }
+
+    pub fn move_paragraph_up(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| cursor.move_paragraph_up(layout.as_text().as_lines()))
+        });
+    }
+
+    pub fn move_paragraph_down(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| cursor.move_paragraph_down(layout.as_text().as_lines()))
+        });
+    }
+
+    /// Moves each cursor that is next to a highlighted delimiter to the delimiter that matches it.
+    /// When extending the selection forwards, the closing delimiter is included in it.
+    pub fn move_to_matching_delimiter(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| {
+                move_to_matching_delimiter(cursor, layout.as_text().as_lines(), !reset_anchor)
+            })
+        });
+    }
+
+    pub fn delete_word_left(&self) {
+        self.delete_to(|cursor, lines| cursor.move_word_left(lines, &self.settings.word_separators));
+    }
+
+    pub fn delete_word_right(&self) {
+        self.delete_to(|cursor, lines| cursor.move_word_right(lines, &self.settings.word_separators));
+    }
+
+    pub fn delete_subword_left(&self) {
+        self.delete_to(|cursor, lines| {
+            cursor.move_subword_left(lines, &self.settings.word_separators)
+        });
+    }
+
+    pub fn delete_subword_right(&self) {
+        self.delete_to(|cursor, lines| {
+            cursor.move_subword_right(lines, &self.settings.word_separators)
+        });
+    }
+
+    pub fn delete_paragraph_up(&self) {
+        self.delete_to(|cursor, lines| cursor.move_paragraph_up(lines));
+    }
+
+    pub fn delete_paragraph_down(&self) {
+        self.delete_to(|cursor, lines| cursor.move_paragraph_down(lines));
+    }
+
+    /// Deletes from each cursor up to and including the delimiter that matches the one next to it.
+    pub fn delete_to_matching_delimiter(&self) {
+        self.delete_to(|cursor, lines| move_to_matching_delimiter(cursor, lines, true));
+    }
+
     pub fn insert(&self, text: Text) {
 
         let mut edit_kind = EditKind::Insert;
@@ -818,6 +933,94 @@ impl CodeSession {
             .redo(self.id, &self.selection_state.borrow().selections)
     }
 
+    /// Redoes into the next branch of the undo tree, for documents that keep one. Returns `false`
+    /// if there is no other branch to redo into.
+    pub fn redo_next_branch(&self) -> bool {
+        self.document.select_next_redo_branch() && self.redo()
+    }
+
+    pub fn search_query(&self) -> Ref<'_, Option<SearchQuery>> {
+        Ref::map(self.search_state.borrow(), |search_state| &search_state.query)
+    }
+
+    pub fn search_matches(&self) -> Ref<'_, [Decoration]> {
+        Ref::map(self.search_state.borrow(), |search_state| {
+            search_state.matches.as_decorations()
+        })
+    }
+
+    /// Starts or updates a search, and selects the first match at or after the start of the last
+    /// added selection. Returns the number of matches.
+    pub fn set_search(&self, pattern: &str, options: SearchOptions) -> Result<usize, RegexError> {
+        let query = SearchQuery::new(pattern, options)?;
+        let line_count = self.document.as_text().as_lines().len();
+        let mut search_state = self.search_state.borrow_mut();
+        search_state.query = Some(query);
+        search_state.line_matches = vec![None; line_count];
+        drop(search_state);
+        self.update_search_matches();
+        self.select_match_after(self.last_added_selection().start());
+        Ok(self.search_state.borrow().matches.len())
+    }
+
+    pub fn clear_search(&self) {
+        let mut search_state = self.search_state.borrow_mut();
+        search_state.query = None;
+        search_state.line_matches.clear();
+        search_state.matches.clear();
+    }
+
+    pub fn select_next_match(&self) -> bool {
+        self.select_match_after(self.last_added_selection().end())
+    }
+
+    pub fn select_prev_match(&self) -> bool {
+        let start = self.last_added_selection().start();
+        let search_state = self.search_state.borrow();
+        let Some(search_match) = search_state
+            .matches
+            .iter()
+            .rev()
+            .find(|search_match| search_match.end() <= start)
+            .or_else(|| search_state.matches.last())
+            .copied()
+        else {
+            return false;
+        };
+        drop(search_state);
+        self.select_range(search_match.start(), search_match.end());
+        true
+    }
+
+    /// Replaces the match that is currently selected, and selects the next one. If no match is
+    /// selected, this only selects the next match. Returns whether a match was replaced.
+    pub fn replace_current_match(&mut self, replacement: &str) -> bool {
+        let selection = self.last_added_selection();
+        let current_match = self
+            .search_state
+            .borrow()
+            .matches
+            .iter()
+            .find(|search_match| {
+                search_match.start() == selection.start() && search_match.end() == selection.end()
+            })
+            .copied();
+        let Some(current_match) = current_match else {
+            self.select_next_match();
+            return false;
+        };
+        let replacement_lengths = self.replace_matches(&[current_match], replacement);
+        self.select_match_after(current_match.start() + replacement_lengths[0]);
+        true
+    }
+
+    /// Replaces every match in the document as a single undoable edit. Returns the number of
+    /// matches that were replaced.
+    pub fn replace_all_matches(&mut self, replacement: &str) -> usize {
+        let matches = self.search_state.borrow().matches.to_vec();
+        self.replace_matches(&matches, replacement).len()
+    }
+
     pub fn handle_changes(&mut self) {
         while let Ok((selections, edits)) = self.edit_receiver.try_recv() {
             self.update_after_edit(selections, &edits);
@@ -852,7 +1055,42 @@ impl CodeSession {
         self.document().force_new_group();
     }
 
+    // Deletes each non-empty selection, and otherwise the text between each cursor and the
+    // position `f` would move it to.
+    fn delete_to(&self, mut f: impl FnMut(Cursor, &[String]) -> Cursor) {
+        self.selection_state
+            .borrow_mut()
+            .injected_char_stack
+            .clear();
+        self.document.edit_selections(
+            self.id,
+            EditKind::Delete,
+            &self.selection_state.borrow().selections,
+            &self.settings,
+            |mut editor, position, length| {
+                let (start, length) = if length == Length::zero() {
+                    let target = f(Cursor::from(position), editor.as_text().as_lines()).position;
+                    if target < position {
+                        (target, position - target)
+                    } else {
+                        (position, target - position)
+                    }
+                } else {
+                    (position, length)
+                };
+                if length == Length::zero() {
+                    return;
+                }
+                editor.apply_edit(Edit {
+                    change: Change::Delete(start, length),
+                    drift: Drift::Before,
+                });
+            },
+        );
+    }
+
     fn update_after_edit(&self, selections: Option<SelectionSet>, edits: &[Edit]) {
+        self.invalidate_search_matches(edits);
         for edit in edits {
             match edit.change {
                 Change::Insert(point, ref text) => {
@@ -925,6 +1163,163 @@ impl CodeSession {
         }
         drop(selection_state);
         self.update_highlighted_delimiter_positions();
+        self.update_search_matches();
+    }
+
+    fn last_added_selection(&self) -> Selection {
+        let selection_state = self.selection_state.borrow();
+        selection_state.selections
+            [selection_state.last_added_selection_index.unwrap_or(0)]
+    }
+
+    fn select_range(&self, start: Position, end: Position) {
+        let mut selection_state = self.selection_state.borrow_mut();
+        selection_state.mode = SelectionMode::Simple;
+        selection_state.selections.set_selection(Selection {
+            cursor: Cursor::from(end),
+            anchor: start,
+        });
+        selection_state.last_added_selection_index = Some(0);
+        selection_state.injected_char_stack.clear();
+        drop(selection_state);
+        self.update_highlighted_delimiter_positions();
+        self.document().force_new_group();
+    }
+
+    fn select_match_after(&self, position: Position) -> bool {
+        let search_state = self.search_state.borrow();
+        let Some(search_match) = search_state
+            .matches
+            .iter()
+            .find(|search_match| search_match.start() >= position)
+            .or_else(|| search_state.matches.first())
+            .copied()
+        else {
+            return false;
+        };
+        drop(search_state);
+        self.select_range(search_match.start(), search_match.end());
+        true
+    }
+
+    fn replace_matches(&mut self, matches: &[Decoration], replacement: &str) -> Vec<Length> {
+        let search_state = self.search_state.borrow();
+        let Some(query) = &search_state.query else {
+            return Vec::new();
+        };
+        let text = self.document.as_text();
+        let lines = text.as_lines();
+        let mut selections = SelectionSet::new();
+        let mut replacements = Vec::new();
+        for (index, search_match) in matches.iter().enumerate() {
+            let selection = Selection {
+                cursor: Cursor::from(search_match.end()),
+                anchor: search_match.start(),
+            };
+            if index == 0 {
+                selections.set_selection(selection);
+            } else {
+                selections.add_selection(selection);
+            }
+            replacements.push(Text::from(query.replacement_for(
+                &lines[search_match.start().line_index],
+                search_match.start().byte_index..search_match.end().byte_index,
+                replacement,
+            )));
+        }
+        drop(text);
+        drop(search_state);
+        if replacements.is_empty() {
+            return Vec::new();
+        }
+        let replacement_lengths = replacements.iter().map(|text| text.length()).collect();
+        let mut replacements = replacements.into_iter();
+        // `EditKind::Other` never merges with another group, so all replacements are undone
+        // together, and separately from any surrounding edits.
+        self.document.edit_selections(
+            self.id,
+            EditKind::Other,
+            &selections,
+            &self.settings,
+            |mut editor, position, length| {
+                editor.apply_edit(Edit {
+                    change: Change::Delete(position, length),
+                    drift: Drift::Before,
+                });
+                editor.apply_edit(Edit {
+                    change: Change::Insert(position, replacements.next().unwrap()),
+                    drift: Drift::Before,
+                });
+            },
+        );
+        self.document.force_new_group();
+        self.handle_changes();
+        replacement_lengths
+    }
+
+    // Forgets the matches of every line touched by `edits`, so that `update_search_matches` only
+    // searches those lines again.
+    fn invalidate_search_matches(&self, edits: &[Edit]) {
+        let mut search_state = self.search_state.borrow_mut();
+        if search_state.query.is_none() {
+            return;
+        }
+        let line_matches = &mut search_state.line_matches;
+        for edit in edits {
+            match edit.change {
+                Change::Insert(point, ref text) => {
+                    line_matches[point.line_index] = None;
+                    let line_count = text.length().line_count;
+                    if line_count > 0 {
+                        let line = point.line_index + 1;
+                        line_matches.splice(line..line, (0..line_count).map(|_| None));
+                    }
+                }
+                Change::Delete(start, length) => {
+                    line_matches[start.line_index] = None;
+                    let line_count = length.line_count;
+                    if line_count > 0 {
+                        let start_line = start.line_index + 1;
+                        line_matches.drain(start_line..start_line + line_count);
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_search_matches(&self) {
+        let mut search_state = self.search_state.borrow_mut();
+        let SearchState {
+            query,
+            line_matches,
+            matches,
+        } = &mut *search_state;
+        matches.clear();
+        let Some(query) = query else {
+            return;
+        };
+        let text = self.document.as_text();
+        let mut index = 0;
+        for (line_index, (line, line_matches)) in
+            text.as_lines().iter().zip(line_matches).enumerate()
+        {
+            let ranges = line_matches.get_or_insert_with(|| query.find_in_line(line));
+            for range in ranges {
+                matches.add_decoration(Decoration::new(
+                    index,
+                    Position {
+                        line_index,
+                        byte_index: range.start,
+                    },
+                    Position {
+                        line_index,
+                        byte_index: range.end,
+                    },
+                    DecorationType::SearchMatch,
+                ));
+                index += 1;
+            }
+        }
     }
 
     fn update_y(&self) {
@@ -1053,6 +1448,15 @@ struct SelectionState {
     highlighted_delimiter_positions: HashSet<Position>,
 }
 
+#[derive(Debug)]
+struct SearchState {
+    query: Option<SearchQuery>,
+    /// The byte ranges of the matches in each line, or `None` for lines that were edited since
+    /// they were last searched.
+    line_matches: Vec<Option<Vec<Range<usize>>>>,
+    matches: DecorationSet,
+}
+
 #[derive(Debug)]
 struct FoldState {
     folding_lines: HashSet<usize>,
@@ -1272,6 +1676,33 @@ fn find_highlighted_delimiter_pair(
     None
 }
 
+fn move_to_matching_delimiter(cursor: Cursor, lines: &[String], include_delimiter: bool) -> Cursor {
+    let Some((opening_delimiter_position, closing_delimiter_position)) =
+        find_highlighted_delimiter_pair(lines, cursor.position)
+    else {
+        return cursor;
+    };
+    if cursor.position >= closing_delimiter_position {
+        return Cursor::from(opening_delimiter_position);
+    }
+    if !include_delimiter {
+        return Cursor::from(closing_delimiter_position);
+    }
+    let closing_delimiter = lines[closing_delimiter_position.line_index]
+        [closing_delimiter_position.byte_index..]
+        .chars()
+        .next()
+        .unwrap();
+    Cursor {
+        position: Position {
+            line_index: closing_delimiter_position.line_index,
+            byte_index: closing_delimiter_position.byte_index + closing_delimiter.len_utf8(),
+        },
+        affinity: Affinity::After,
+        preferred_column_index: None,
+    }
+}
+
 fn find_opening_delimiter(
     lines: &[String],
     position: Position,
@@ -1336,3 +1767,337 @@ fn find_closing_delimiter(
         position.byte_index = 0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{decoration::DecorationSet, search},
+    };
+
+    fn session(text: &str) -> CodeSession {
+        CodeSession::new(CodeDocument::new(Text::from(text), DecorationSet::new()))
+    }
+
+    fn position(line_index: usize, byte_index: usize) -> Position {
+        Position {
+            line_index,
+            byte_index,
+        }
+    }
+
+    fn match_positions(session: &CodeSession) -> Vec<(Position, Position)> {
+        session
+            .search_matches()
+            .iter()
+            .map(|search_match| (search_match.start(), search_match.end()))
+            .collect()
+    }
+
+    fn assert_matches_up_to_date(session: &CodeSession) {
+        let query = session.search_query().clone().unwrap();
+        assert_eq!(
+            match_positions(session),
+            search::find_all(&query, &session.document().as_text())
+        );
+    }
+
+    #[test]
+    fn search_finds_matches_in_every_line() {
+        let session = session("foo bar\nbaz foo\n\nfoofoo");
+        assert_eq!(session.set_search("foo", SearchOptions::default()), Ok(4));
+        assert_eq!(
+            match_positions(&session),
+            [
+                (position(0, 0), position(0, 3)),
+                (position(1, 4), position(1, 7)),
+                (position(3, 0), position(3, 3)),
+                (position(3, 3), position(3, 6)),
+            ]
+        );
+        assert_eq!(session.selections()[0].start(), position(0, 0));
+        assert!(session.select_next_match());
+        assert_eq!(session.selections()[0].start(), position(1, 4));
+        assert!(session.select_prev_match());
+        assert_eq!(session.selections()[0].start(), position(0, 0));
+        session.clear_search();
+        assert!(session.search_matches().is_empty());
+    }
+
+    #[test]
+    fn search_respects_options() {
+        let session = session("Foo foo foobar\nx1 y22");
+        let options = SearchOptions {
+            case_sensitive: true,
+            whole_word: true,
+            regex: false,
+        };
+        assert_eq!(session.set_search("foo", options), Ok(1));
+        assert_eq!(session.set_search("foo", SearchOptions::default()), Ok(3));
+        let options = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(session.set_search(r"[a-z]\d+", options), Ok(2));
+        assert!(session.set_search("(", options).is_err());
+    }
+
+    #[test]
+    fn search_matches_follow_edits() {
+        let mut session = session("foo\nbar foo\nbaz\nfoo foo");
+        session.set_search("foo", SearchOptions::default()).unwrap();
+        assert_eq!(session.search_matches().len(), 4);
+
+        // Typing a line break in the middle of a match splits it.
+        session.set_selection(position(1, 5), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        session.insert(Text::from("\n"));
+        session.handle_changes();
+        assert_eq!(session.search_matches().len(), 3);
+        assert_matches_up_to_date(&session);
+
+        // Inserting several lines shifts the matches below them.
+        session.set_selection(position(0, 0), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        session.insert(Text::from("foo\nfoo\n"));
+        session.handle_changes();
+        assert_eq!(session.search_matches().len(), 5);
+        assert_matches_up_to_date(&session);
+
+        // Deleting across lines joins them, which can create a new match.
+        session.set_selection(position(3, 5), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        session.delete();
+        session.handle_changes();
+        assert_eq!(session.document().as_text().as_lines()[3], "bar foo");
+        assert_matches_up_to_date(&session);
+
+        session.undo();
+        session.handle_changes();
+        assert_matches_up_to_date(&session);
+    }
+
+    #[test]
+    fn search_matches_follow_document_replace() {
+        let mut session = session("foo\nfoo");
+        session.set_search("foo", SearchOptions::default()).unwrap();
+        session.document().replace(Text::from("a\nb foo\nc\nfoo foo foo"));
+        session.handle_changes();
+        assert_eq!(session.search_matches().len(), 4);
+        assert_matches_up_to_date(&session);
+    }
+
+    #[test]
+    fn moves_to_document_start_and_end() {
+        let session = session("one\ntwo é");
+        session.set_selection(position(0, 2), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        session.move_to_document_end(false);
+        assert_eq!(session.selections()[0].start(), position(0, 2));
+        assert_eq!(session.selections()[0].end(), position(1, 6));
+        session.move_to_document_start(true);
+        assert!(session.selections()[0].is_empty());
+        assert_eq!(session.selections()[0].cursor.position, position(0, 0));
+    }
+
+    // Repeats a motion from `start` until the cursor stops moving, and returns every position the
+    // cursor visited.
+    fn cursor_positions(
+        session: &CodeSession,
+        start: Position,
+        motion: fn(&CodeSession),
+    ) -> Vec<Position> {
+        session.set_selection(start, Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        let mut positions = Vec::new();
+        let mut position = start;
+        loop {
+            motion(session);
+            let next_position = session.selections()[0].cursor.position;
+            if next_position == position {
+                return positions;
+            }
+            positions.push(next_position);
+            position = next_position;
+        }
+    }
+
+    #[test]
+    fn moves_by_words() {
+        let session = session("let fooBar = café.größe;\n\n  x");
+        assert_eq!(
+            cursor_positions(&session, position(0, 0), |session| session.move_word_right(true)),
+            [
+                position(0, 3),
+                position(0, 10),
+                position(0, 12),
+                position(0, 18),
+                position(0, 19),
+                position(0, 26),
+                position(0, 27),
+                position(1, 0),
+                position(2, 0),
+                position(2, 3),
+            ]
+        );
+        assert_eq!(
+            cursor_positions(&session, position(2, 3), |session| session.move_word_left(true)),
+            [
+                position(2, 2),
+                position(2, 0),
+                position(1, 0),
+                position(0, 27),
+                position(0, 26),
+                position(0, 19),
+                position(0, 18),
+                position(0, 13),
+                position(0, 11),
+                position(0, 4),
+                position(0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn moves_by_subwords() {
+        let session = session("HTTPServer fooBar_baz");
+        assert_eq!(
+            cursor_positions(&session, position(0, 0), |session| session.move_subword_right(true)),
+            [position(0, 4), position(0, 10), position(0, 14), position(0, 17), position(0, 21)]
+        );
+        assert_eq!(
+            cursor_positions(&session, position(0, 21), |session| session.move_subword_left(true)),
+            [position(0, 18), position(0, 14), position(0, 11), position(0, 4), position(0, 0)]
+        );
+    }
+
+    #[test]
+    fn moves_by_paragraphs() {
+        let session = session("a\nb\n\n  \nc\nd\n\né");
+        assert_eq!(
+            cursor_positions(&session, position(0, 0), |session| session.move_paragraph_down(true)),
+            [position(2, 0), position(6, 0), position(7, 2)]
+        );
+        assert_eq!(
+            cursor_positions(&session, position(7, 2), |session| session.move_paragraph_up(true)),
+            [position(6, 0), position(3, 0), position(0, 0)]
+        );
+    }
+
+    #[test]
+    fn motions_stay_put_in_an_empty_document() {
+        let session = session("");
+        let motions: [fn(&CodeSession); 6] = [
+            |session| session.move_word_left(true),
+            |session| session.move_word_right(true),
+            |session| session.move_subword_left(true),
+            |session| session.move_subword_right(true),
+            |session| session.move_paragraph_up(true),
+            |session| session.move_paragraph_down(true),
+        ];
+        for motion in motions {
+            assert!(cursor_positions(&session, position(0, 0), motion).is_empty());
+        }
+    }
+
+    #[test]
+    fn moves_to_matching_delimiters() {
+        let session = session("f(a[é], {\n  b\n})");
+        let matching_delimiter = |position| {
+            session.set_selection(position, Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+            session.move_to_matching_delimiter(true);
+            session.selections()[0].cursor.position
+        };
+        assert_eq!(matching_delimiter(position(0, 1)), position(2, 1));
+        assert_eq!(matching_delimiter(position(2, 1)), position(0, 1));
+        assert_eq!(matching_delimiter(position(0, 3)), position(0, 6));
+        assert_eq!(matching_delimiter(position(0, 6)), position(0, 3));
+        // Without a delimiter next to the cursor there is nothing to match.
+        assert_eq!(matching_delimiter(position(1, 2)), position(1, 2));
+
+        // Extending the selection includes the closing delimiter.
+        session.set_selection(position(0, 9), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        session.move_to_matching_delimiter(false);
+        assert_eq!(session.selections()[0].start(), position(0, 9));
+        assert_eq!(session.selections()[0].end(), position(2, 1));
+    }
+
+    #[test]
+    fn deletes_by_words() {
+        let mut session = session("café.größe(x)");
+        session.set_selection(position(0, 13), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        session.delete_word_left();
+        session.handle_changes();
+        assert_eq!(session.document().as_text().to_string(), "café.(x)");
+        session.set_selection(position(0, 5), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        session.delete_word_right();
+        session.handle_changes();
+        assert_eq!(session.document().as_text().to_string(), "caféx)");
+    }
+
+    // Returns a session for "abc" that has typed "dé" at the end, and its stored undo history.
+    fn session_with_history() -> (CodeSession, Vec<u8>) {
+        let mut session = session("abc");
+        session.set_selection(position(0, 3), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        session.insert(Text::from("dé"));
+        session.handle_changes();
+        let bytes = session.document().history_to_bin();
+        (session, bytes)
+    }
+
+    #[test]
+    fn restores_stored_undo_history() {
+        let (_, bytes) = session_with_history();
+        let mut restored = session("abcdé");
+        assert!(restored.document().restore_history(&bytes));
+        assert!(restored.undo());
+        restored.handle_changes();
+        assert_eq!(restored.document().as_text().to_string(), "abc");
+        assert!(restored.redo());
+        restored.handle_changes();
+        assert_eq!(restored.document().as_text().to_string(), "abcdé");
+    }
+
+    #[test]
+    fn keeps_undo_history_when_the_text_changed_since_it_was_stored() {
+        let (_, bytes) = session_with_history();
+        let mut other = session("abcdx");
+        other.set_selection(position(0, 5), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+        other.insert(Text::from("y"));
+        other.handle_changes();
+        assert!(!other.document().restore_history(&bytes));
+        assert!(other.undo());
+        other.handle_changes();
+        assert_eq!(other.document().as_text().to_string(), "abcdx");
+        assert!(!other.undo());
+    }
+
+    #[test]
+    fn rejects_truncated_and_corrupt_undo_history() {
+        let (mut session, bytes) = session_with_history();
+        for len in 0..bytes.len() {
+            assert!(!session.document().restore_history(&bytes[..len]), "truncated to {len}");
+        }
+        for index in 0..bytes.len() {
+            let mut corrupt_bytes = bytes.clone();
+            corrupt_bytes[index] ^= 0xff;
+            assert!(!session.document().restore_history(&corrupt_bytes), "corrupt at {index}");
+        }
+        // The history the session had is still there.
+        assert!(session.undo());
+        session.handle_changes();
+        assert_eq!(session.document().as_text().to_string(), "abc");
+    }
+
+    #[test]
+    fn replaces_matches() {
+        let mut session = session("me@example\nyou@example");
+        let options = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        session.set_search(r"(\w+)@(\w+)", options).unwrap();
+        assert!(session.replace_current_match("$2:$1"));
+        assert_eq!(session.document().as_text().as_lines()[0], "example:me");
+        assert_eq!(session.search_matches().len(), 1);
+        session.set_search("example", SearchOptions::default()).unwrap();
+        assert_eq!(session.replace_all_matches("x"), 2);
+        assert_eq!(session.document().as_text().to_string(), "x:me\nyou@x");
+        assert!(session.search_matches().is_empty());
+    }
+}
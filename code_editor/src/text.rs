@@ -1,12 +1,15 @@
-use std::{
-    cmp::Ordering,
-    fmt, io,
-    io::BufRead,
-    iter,
-    ops::{Add, AddAssign, Sub, SubAssign},
+use {
+    makepad_widgets::makepad_micro_serde::*,
+    std::{
+        cmp::Ordering,
+        fmt, io,
+        io::BufRead,
+        iter,
+        ops::{Add, AddAssign, Sub, SubAssign},
+    },
 };
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, SerBin, DeBin)]
 pub struct Text {
     lines: Vec<String>,
 }
@@ -174,7 +177,7 @@ impl FromIterator<char> for Text {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, SerBin, DeBin)]
 pub struct Edit {
     pub change: Change,
     pub drift: Drift,
@@ -189,7 +192,7 @@ impl Edit {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, SerBin, DeBin)]
 pub enum Change {
     Insert(Position, Text),
     Delete(Position, Length),
@@ -204,7 +207,7 @@ impl Change {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, SerBin, DeBin)]
 pub struct Position {
     pub line_index: usize,
     pub byte_index: usize,
@@ -279,7 +282,7 @@ impl Sub for Position {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, SerBin, DeBin)]
 pub struct Length {
     pub line_count: usize,
     pub byte_count: usize,
@@ -339,7 +342,7 @@ impl SubAssign for Length {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, SerBin, DeBin)]
 pub enum Drift {
     Before,
     After,
@@ -1,22 +1,92 @@
-use crate::{
-    text::{Change, Text},
-    token::TokenKind,
-    Token,
+use {
+    crate::{
+        grammar::rust::RustGrammar,
+        text::{Change, Text},
+        token::TokenKind,
+        Token,
+    },
+    std::fmt,
 };
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A grammar splits a single line into tokens. Its state carries whatever a construct that spans
+/// several lines, such as a block comment, needs to continue on the next line.
+pub trait Grammar: fmt::Debug + 'static {
+    type State: Clone + Copy + fmt::Debug + Default + Eq;
+
+    /// Consumes the next token from `cursor`, which is never at the end of the line, and returns
+    /// its kind along with the state to continue in.
+    fn next(&self, state: Self::State, cursor: &mut Cursor) -> (Self::State, TokenKind);
+}
+
+/// Tokenizes a document incrementally. The state at the start and end of every line is cached,
+/// so after an edit only the lines that changed, and the lines whose start state changed as a
+/// result, are tokenized again.
+#[derive(Debug)]
 pub struct Tokenizer {
-    state: Vec<Option<(State, State)>>,
+    lines: Box<dyn LineTokenizer>,
 }
 
 impl Tokenizer {
     pub fn new(line_count: usize) -> Self {
+        Self::with_grammar(RustGrammar, line_count)
+    }
+
+    pub fn with_grammar<G: Grammar>(grammar: G, line_count: usize) -> Self {
         Self {
-            state: (0..line_count).map(|_| None).collect(),
+            lines: Box::new(GrammarTokenizer {
+                grammar,
+                state: (0..line_count).map(|_| None).collect(),
+            }),
         }
     }
 
     pub fn apply_change(&mut self, change: &Change) {
+        self.lines.apply_change(change);
+    }
+
+    pub fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]) {
+        self.lines.update(text, tokens);
+    }
+}
+
+/// Creates a tokenizer for a grammar that has a default value. Useful as the constructor of a
+/// [`Language`](crate::grammar::Language).
+pub fn new_tokenizer<G: Grammar + Default>(line_count: usize) -> Tokenizer {
+    Tokenizer::with_grammar(G::default(), line_count)
+}
+
+trait LineTokenizer: fmt::Debug {
+    fn apply_change(&mut self, change: &Change);
+    fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]);
+}
+
+#[derive(Debug)]
+struct GrammarTokenizer<G: Grammar> {
+    grammar: G,
+    state: Vec<Option<(G::State, G::State)>>,
+}
+
+impl<G: Grammar> GrammarTokenizer<G> {
+    fn next_token(&self, state: G::State, cursor: &mut Cursor) -> (G::State, Option<Token>) {
+        if cursor.peek(0) == '\0' {
+            return (state, None);
+        }
+        let start = cursor.index;
+        let (next_state, kind) = self.grammar.next(state, cursor);
+        let end = cursor.index;
+        assert!(start < end);
+        (
+            next_state,
+            Some(Token {
+                len: end - start,
+                kind,
+            }),
+        )
+    }
+}
+
+impl<G: Grammar> LineTokenizer for GrammarTokenizer<G> {
+    fn apply_change(&mut self, change: &Change) {
         match *change {
             Change::Insert(point, ref text) => {
                 self.state[point.line_index] = None;
@@ -38,8 +108,8 @@ impl Tokenizer {
         }
     }
 
-    pub fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]) {
-        let mut state = State::default();
+    fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]) {
+        let mut state = G::State::default();
         for line in 0..text.as_lines().len() {
             match self.state[line] {
                 Some((start_state, end_state)) if state == start_state => {
@@ -50,7 +120,7 @@ impl Tokenizer {
                     let mut new_tokens = Vec::new();
                     let mut cursor = Cursor::new(&text.as_lines()[line]);
                     loop {
-                        let (next_state, token) = state.next(&mut cursor);
+                        let (next_state, token) = self.next_token(state, &mut cursor);
                         state = next_state;
                         match token {
                             Some(token) => new_tokens.push(token),
@@ -65,434 +135,49 @@ impl Tokenizer {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-pub enum State {
-    Initial(InitialState),
-    BlockCommentTail(BlockCommentTailState),
-    DoubleQuotedStringTail(DoubleQuotedStringTailState),
-    RawDoubleQuotedStringTail(RawDoubleQuotedStringTailState),
-}
-
-impl Default for State {
-    fn default() -> State {
-        State::Initial(InitialState)
-    }
-}
-
-impl State {
-    pub fn next(self, cursor: &mut Cursor) -> (State, Option<Token>) {
-        if cursor.peek(0) == '\0' {
-            return (self, None);
-        }
-        let start = cursor.index;
-        let (next_state, kind) = match self {
-            State::Initial(state) => state.next(cursor),
-            State::BlockCommentTail(state) => state.next(cursor),
-            State::DoubleQuotedStringTail(state) => state.next(cursor),
-            State::RawDoubleQuotedStringTail(state) => state.next(cursor),
-        };
-        let end = cursor.index;
-        assert!(start < end);
-        (
-            next_state,
-            Some(Token {
-                len: end - start,
-                kind,
-            }),
-        )
-    }
+#[derive(Debug)]
+pub struct Cursor<'a> {
+    string: &'a str,
+    index: usize,
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-pub struct InitialState;
-
-impl InitialState {
-    fn next(self, cursor: &mut Cursor<'_>) -> (State, TokenKind) {
-        match (cursor.peek(0), cursor.peek(1), cursor.peek(2)) {
-            ('r', '#', '"') | ('r', '#', '#') => self.raw_string(cursor),
-            ('b', 'r', '"') | ('b', 'r', '#') => self.raw_byte_string(cursor),
-            ('/', '/', _) => self.line_comment(cursor),
-            ('/', '*', _) => self.block_comment(cursor),
-            ('b', '\'', _) => self.byte(cursor),
-            ('b', '"', _) => self.byte_string(cursor),
-            ('!', '=', _)
-            | ('%', '=', _)
-            | ('&', '&', _)
-            | ('&', '=', _)
-            | ('*', '=', _)
-            | ('+', '=', _)
-            | ('-', '=', _)
-            | ('-', '>', _)
-            | ('.', '.', _)
-            | ('/', '=', _)
-            | (':', ':', _)
-            | ('<', '<', _)
-            | ('<', '=', _)
-            | ('=', '=', _)
-            | ('=', '>', _)
-            | ('>', '=', _)
-            | ('>', '>', _)
-            | ('^', '=', _)
-            | ('|', '=', _)
-            | ('|', '|', _) => {
-                cursor.skip(2);
-                (State::Initial(InitialState), TokenKind::Punctuator)
-            }
-            ('\'', _, _) => self.char_or_lifetime(cursor),
-            ('"', _, _) => self.string(cursor),
-            ('(', _, _) => {
-                cursor.skip(1);
-                (State::Initial(InitialState), TokenKind::Delimiter)
-            }
-            (')', _, _) => {
-                cursor.skip(1);
-                (State::Initial(InitialState), TokenKind::Delimiter)
-            }
-            ('[', _, _) => {
-                cursor.skip(1);
-                (State::Initial(InitialState), TokenKind::Delimiter)
-            }
-            (']', _, _) => {
-                cursor.skip(1);
-                (State::Initial(InitialState), TokenKind::Delimiter)
-            }
-            ('{', _, _) => {
-                cursor.skip(1);
-                (State::Initial(InitialState), TokenKind::Delimiter)
-            }
-            ('}', _, _) => {
-                cursor.skip(1);
-                (State::Initial(InitialState), TokenKind::Delimiter)
-            }
-            ('.', char, _) if char.is_digit(10) => self.number(cursor),
-            ('!', _, _)
-            | ('#', _, _)
-            | ('$', _, _)
-            | ('%', _, _)
-            | ('&', _, _)
-            | ('*', _, _)
-            | ('+', _, _)
-            | (',', _, _)
-            | ('-', _, _)
-            | ('.', _, _)
-            | ('/', _, _)
-            | (':', _, _)
-            | (';', _, _)
-            | ('<', _, _)
-            | ('=', _, _)
-            | ('>', _, _)
-            | ('?', _, _)
-            | ('@', _, _)
-            | ('^', _, _)
-            | ('_', _, _)
-            | ('|', _, _) => {
-                cursor.skip(1);
-                (State::Initial(InitialState), TokenKind::Punctuator)
-            }
-            (char, _, _) if char.is_identifier_start() => self.identifier_or_keyword(cursor),
-            (char, _, _) if char.is_digit(10) => self.number(cursor),
-            (char, _, _) if char.is_whitespace() => self.whitespace(cursor),
-            _ => {
-                cursor.skip(1);
-                (State::Initial(InitialState), TokenKind::Unknown)
-            }
-        }
-    }
-
-    fn line_comment(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0) == '/' && cursor.peek(1) == '/');
-        cursor.skip(2);
-        while cursor.skip_if(|ch| ch != '\0') {}
-        (State::Initial(InitialState), TokenKind::Comment)
-    }
-
-    fn block_comment(self, cursor: &mut Cursor<'_>) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0) == '/' && cursor.peek(1) == '*');
-        cursor.skip(2);
-        BlockCommentTailState { depth: 0 }.next(cursor)
-    }
-
-    fn identifier_or_keyword(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0).is_identifier_start());
-        let start = cursor.index;
-        cursor.skip(1);
-        while cursor.skip_if(|char| char.is_identifier_continue()) {}
-        let end = cursor.index;
-        let string = &cursor.string[start..end];
-        (
-            State::Initial(InitialState),
-            match string {
-                "else" | "if" | "match" | "return" => TokenKind::BranchKeyword,
-                "break" | "continue" | "for" | "loop" | "while" => TokenKind::LoopKeyword,
-                "Self" | "as" | "async" | "await" | "const" | "crate" | "dyn" | "enum"
-                | "extern" | "false" | "fn" | "impl" | "in" | "let" | "mod" | "move" | "mut"
-                | "pub" | "ref" | "self" | "static" | "struct" | "super" | "trait" | "true"
-                | "type" | "unsafe" | "use" | "where" | "usize" | "isize" | "u8" | "u16"
-                | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" | "vec2" | "vec3" | "vec4"
-                | "bool" | "f32" | "f64" => TokenKind::OtherKeyword,
-                _ => {
-                    let mut chars = string.chars();
-                    if chars.next().unwrap().is_uppercase() {
-                        match chars.next() {
-                            Some(char) if char.is_uppercase() => TokenKind::Constant,
-                            _ => TokenKind::Typename,
-                        }
-                    } else if cursor.peek(0) == '(' {
-                        TokenKind::Function
-                    } else {
-                        TokenKind::Identifier
-                    }
-                }
-            },
-        )
-    }
-
-    fn number(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        match (cursor.peek(0), cursor.peek(1)) {
-            ('0', 'b') => {
-                cursor.skip(2);
-                if !cursor.skip_digits(2) {
-                    return (State::Initial(InitialState), TokenKind::Unknown);
-                }
-                return (State::Initial(InitialState), TokenKind::Number);
-            }
-            ('0', 'o') => {
-                cursor.skip(2);
-                if !cursor.skip_digits(8) {
-                    return (State::Initial(InitialState), TokenKind::Unknown);
-                }
-                return (State::Initial(InitialState), TokenKind::Number);
-            }
-            ('0', 'x') => {
-                cursor.skip(2);
-                if !cursor.skip_digits(16) {
-                    return (State::Initial(InitialState), TokenKind::Unknown);
-                }
-                return (State::Initial(InitialState), TokenKind::Number);
-            }
-            _ => {
-                cursor.skip_digits(10);
-                match cursor.peek(0) {
-                    '.' if cursor.peek(1) != '.' && !cursor.peek(0).is_identifier_start() => {
-                        cursor.skip(1);
-                        if cursor.skip_digits(10) {
-                            if cursor.peek(0) == 'E' || cursor.peek(0) == 'e' {
-                                if !cursor.skip_exponent() {
-                                    return (State::Initial(InitialState), TokenKind::Unknown);
-                                }
-                            }
-                        }
-                        cursor.skip_suffix();
-                        return (State::Initial(InitialState), TokenKind::Number);
-                    }
-                    'E' | 'e' => {
-                        if !cursor.skip_exponent() {
-                            return (State::Initial(InitialState), TokenKind::Unknown);
-                        }
-                        cursor.skip_suffix();
-                        return (State::Initial(InitialState), TokenKind::Number);
-                    }
-                    _ => {
-                        cursor.skip_suffix();
-                        return (State::Initial(InitialState), TokenKind::Number);
-                    }
-                }
-            }
-        };
-    }
-
-    fn char_or_lifetime(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        if cursor.peek(1).is_identifier_start() && cursor.peek(2) != '\'' {
-            debug_assert!(cursor.peek(0) == '\'');
-            cursor.skip(2);
-            while cursor.skip_if(|ch| ch.is_identifier_continue()) {}
-            if cursor.peek(0) == '\'' {
-                cursor.skip(1);
-                cursor.skip_suffix();
-                (State::Initial(InitialState), TokenKind::String)
-            } else {
-                (State::Initial(InitialState), TokenKind::String)
-            }
-        } else {
-            self.single_quoted_string(cursor)
-        }
-    }
-
-    fn byte(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0) == 'b');
-        cursor.skip(1);
-        self.single_quoted_string(cursor)
-    }
-
-    fn string(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        self.double_quoted_string(cursor)
-    }
-
-    fn byte_string(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0) == 'b');
-        cursor.skip(1);
-        self.double_quoted_string(cursor)
-    }
-
-    fn raw_string(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0) == 'r');
-        cursor.skip(1);
-        self.raw_double_quoted_string(cursor)
-    }
-
-    fn raw_byte_string(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0) == 'b' && cursor.peek(1) == 'r');
-        cursor.skip(2);
-        self.raw_double_quoted_string(cursor)
-    }
-
-    fn single_quoted_string(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0) == '\'');
-        cursor.skip(1);
-        loop {
-            match (cursor.peek(0), cursor.peek(1)) {
-                ('\'', _) => {
-                    cursor.skip(1);
-                    cursor.skip_suffix();
-                    break;
-                }
-                ('\0', _) => return (State::Initial(InitialState), TokenKind::Unknown),
-                ('\\', '\'') | ('\\', '\\') => cursor.skip(2),
-                _ => cursor.skip(1),
-            }
-        }
-        (State::Initial(InitialState), TokenKind::String)
+impl<'a> Cursor<'a> {
+    pub fn new(string: &'a str) -> Self {
+        Cursor { string, index: 0 }
     }
 
-    fn double_quoted_string(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0) == '"');
-        cursor.skip(1);
-        DoubleQuotedStringTailState.next(cursor)
+    /// The byte index of the cursor in the line.
+    pub fn index(&self) -> usize {
+        self.index
     }
 
-    fn raw_double_quoted_string(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        let mut start_hash_count = 0;
-        while cursor.skip_if(|ch| ch == '#') {
-            start_hash_count += 1;
-        }
-        RawDoubleQuotedStringTailState { start_hash_count }.next(cursor)
+    /// The part of the line that comes before the cursor.
+    pub fn before(&self) -> &'a str {
+        &self.string[..self.index]
     }
 
-    fn whitespace(self, cursor: &mut Cursor) -> (State, TokenKind) {
-        debug_assert!(cursor.peek(0).is_whitespace());
-        cursor.skip(1);
-        while cursor.skip_if(|char| char.is_whitespace()) {}
-        (State::Initial(InitialState), TokenKind::Whitespace)
+    /// The part of the line that comes after the cursor.
+    pub fn rest(&self) -> &'a str {
+        &self.string[self.index..]
     }
-}
-
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-pub struct BlockCommentTailState {
-    depth: usize,
-}
 
-impl BlockCommentTailState {
-    fn next(self, cursor: &mut Cursor<'_>) -> (State, TokenKind) {
-        let mut state = self;
-        loop {
-            match (cursor.peek(0), cursor.peek(1)) {
-                ('/', '*') => {
-                    cursor.skip(2);
-                    state.depth += 1;
-                }
-                ('*', '/') => {
-                    cursor.skip(2);
-                    if state.depth == 0 {
-                        break (State::Initial(InitialState), TokenKind::Comment);
-                    }
-                    state.depth -= 1;
-                }
-                ('\0', _) => {
-                    break (State::BlockCommentTail(state), TokenKind::Comment);
-                }
-                _ => cursor.skip(1),
-            }
-        }
+    /// Returns true if there is nothing but whitespace before the cursor.
+    pub fn is_at_line_start(&self) -> bool {
+        self.before().chars().all(|char| char.is_whitespace())
     }
-}
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-pub struct DoubleQuotedStringTailState;
-
-impl DoubleQuotedStringTailState {
-    fn next(self, cursor: &mut Cursor<'_>) -> (State, TokenKind) {
-        loop {
-            match (cursor.peek(0), cursor.peek(1)) {
-                ('"', _) => {
-                    cursor.skip(1);
-                    cursor.skip_suffix();
-                    break (State::Initial(InitialState), TokenKind::String);
-                }
-                ('\0', _) => {
-                    break (
-                        State::DoubleQuotedStringTail(DoubleQuotedStringTailState),
-                        TokenKind::String,
-                    );
-                }
-                ('\\', '"') | ('\\', '\\') => cursor.skip(2),
-                _ => cursor.skip(1),
-            }
-        }
-    }
-}
-
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-pub struct RawDoubleQuotedStringTailState {
-    start_hash_count: usize,
-}
-
-impl RawDoubleQuotedStringTailState {
-    fn next(self, cursor: &mut Cursor<'_>) -> (State, TokenKind) {
-        loop {
-            match cursor.peek(0) {
-                '"' => {
-                    cursor.skip(1);
-                    let mut end_hash_count = 0;
-                    while end_hash_count < self.start_hash_count && cursor.skip_if(|ch| ch == '#') {
-                        end_hash_count += 1;
-                    }
-                    if end_hash_count == self.start_hash_count {
-                        cursor.skip_suffix();
-                        break (State::Initial(InitialState), TokenKind::String);
-                    }
-                }
-                '\0' => {
-                    break (State::RawDoubleQuotedStringTail(self), TokenKind::String);
-                }
-                _ => cursor.skip(1),
-            }
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct Cursor<'a> {
-    string: &'a str,
-    index: usize,
-}
-
-impl<'a> Cursor<'a> {
-    pub fn new(string: &'a str) -> Self {
-        Cursor { string, index: 0 }
-    }
-
-    fn peek(&self, index: usize) -> char {
+    pub fn peek(&self, index: usize) -> char {
         self.string[self.index..].chars().nth(index).unwrap_or('\0')
     }
 
-    fn skip(&mut self, count: usize) {
+    pub fn skip(&mut self, count: usize) {
         self.index = self.string[self.index..]
             .char_indices()
             .nth(count)
             .map_or(self.string.len(), |(index, _)| self.index + index);
     }
 
-    fn skip_if<P>(&mut self, predicate: P) -> bool
+    pub fn skip_if<P>(&mut self, predicate: P) -> bool
     where
         P: FnOnce(char) -> bool,
     {
@@ -504,7 +189,30 @@ impl<'a> Cursor<'a> {
         }
     }
 
-    fn skip_exponent(&mut self) -> bool {
+    pub fn skip_while<P>(&mut self, mut predicate: P) -> bool
+    where
+        P: FnMut(char) -> bool,
+    {
+        let start = self.index;
+        while self.skip_if(&mut predicate) {}
+        self.index > start
+    }
+
+    /// Skips `prefix` if the rest of the line starts with it.
+    pub fn skip_str(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.index += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn skip_to_end(&mut self) {
+        self.index = self.string.len();
+    }
+
+    pub fn skip_exponent(&mut self) -> bool {
         debug_assert!(self.peek(0) == 'E' || self.peek(0) == 'e');
         self.skip(1);
         if self.peek(0) == '+' || self.peek(0) == '-' {
@@ -513,7 +221,7 @@ impl<'a> Cursor<'a> {
         self.skip_digits(10)
     }
 
-    fn skip_digits(&mut self, radix: u32) -> bool {
+    pub fn skip_digits(&mut self, radix: u32) -> bool {
         let mut has_skip_digits = false;
         loop {
             match self.peek(0) {
@@ -530,7 +238,7 @@ impl<'a> Cursor<'a> {
         has_skip_digits
     }
 
-    fn skip_suffix(&mut self) -> bool {
+    pub fn skip_suffix(&mut self) -> bool {
         if self.peek(0).is_identifier_start() {
             self.skip(1);
             while self.skip_if(|char| char.is_identifier_continue()) {}
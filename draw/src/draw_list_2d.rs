@@ -30,6 +30,7 @@ impl LiveNew for DrawList2d {
             live_type: LiveType::of::<Self>(),
             live_ignore: true,
             fields: Vec::new(),
+            variants: Vec::new(),
             type_name: id_lut!(View)
         }
     }
@@ -33,6 +33,7 @@ impl LiveNew for Overlay {
             live_type: LiveType::of::<Self>(),
             live_ignore: true,
             fields: Vec::new(),
+            variants: Vec::new(),
             type_name: id_lut!(Overlay)
         }
     }
@@ -792,7 +792,7 @@ fn draw_macroblock_yuv420(image: &mut ImageBuffer, x0: usize, y0: usize, width:
     }
 }
 
-fn draw_macroblock_yuv422_normal(image: &mut ImageBuffer, x0: usize, y0: usize, width: usize, height: usize, coeffs: &[i32]) {
+fn draw_macroblock_yuv422(image: &mut ImageBuffer, x0: usize, y0: usize, width: usize, height: usize, coeffs: &[i32]) {
     for i in 0..height {
         for k in 0..width {
             let by = k >> 3;
@@ -806,66 +806,6 @@ fn draw_macroblock_yuv422_normal(image: &mut ImageBuffer, x0: usize, y0: usize,
     }
 }
 
-#[cfg(feature="nightly")]
-fn draw_macroblock_yuv422(image: &mut ImageBuffer, x0: usize, y0: usize, width: usize, height: usize, coeffs: &[i32]) {
-    if width & 3 != 0 {
-        return draw_macroblock_yuv422_normal(image, x0, y0, width, height, coeffs);
-    }
-    return draw_macroblock_yuv422_simd(image, x0, y0, width, height, coeffs);
-}
-
-#[cfg(not(feature="nightly"))]
-fn draw_macroblock_yuv422(image: &mut ImageBuffer, x0: usize, y0: usize, width: usize, height: usize, coeffs: &[i32]) {
-    draw_macroblock_yuv422_normal(image, x0, y0, width, height, coeffs)
-}
-
-#[cfg(feature="nightly")]
-fn draw_macroblock_yuv422_simd(image: &mut ImageBuffer, x0: usize, y0: usize, width: usize, height: usize, coeffs: &[i32]) {
-    use std::simd::*;
-
-    fn draw_rgb_simd(image: &mut ImageBuffer, px: usize, py: usize, r: i32x4, g: i32x4, b: i32x4) {
-        for i in 0..4 {
-            image.data[py * image.width + px + i] = 0xFF000000 | ((r[i] as u32) << 16) | ((g[i] as u32) << 8) | (b[i] as u32);
-        }
-    }
-
-    fn i32x4v(a: i32, b: i32, c: i32, d: i32) -> i32x4 {i32x4::from_array([a, b, c, d])}
-    fn i32x4s(a: i32) -> i32x4 {i32x4::from_array([a; 4])}
-
-    fn draw_yuv_simd(image: &mut ImageBuffer, px: usize, py: usize, y: i32x4, u: i32x4, v: i32x4) {
-        // ok lets simd this.
-        let r = ((y << i32x4s(8)) + i32x4s(359) * v) >> i32x4s(8);
-        let g = ((y << i32x4s(8)) - i32x4s(88) * u - i32x4s(183) * v) >> i32x4s(8);
-        let b = ((y << i32x4s(8)) + i32x4s(454) * u) >> i32x4s(8);
-        draw_rgb_simd(image, px, py, r.clamp(i32x4s(0), i32x4s(255)), g.clamp(i32x4s(0), i32x4s(255)), b.clamp(i32x4s(0), i32x4s(255)));
-    }
-
-    for i in 0..height {
-        for k in (0..width).step_by(4) {
-            let k0 = k;
-            let hk0 = k0 >> 1;
-            let by0 = k0 >> 3;
-            let sk0 = k0 & 7;
-            let k1 = k + 1;
-            let hk1 = k1 >> 1;
-            let by1 = k1 >> 3;
-            let sk1 = k1 & 7;
-            let k2 = k + 2;
-            let hk2 = k2 >> 1;
-            let by2 = k2 >> 3;
-            let sk2 = k2 & 7;
-            let k3 = k + 3;
-            let hk3 = k3 >> 1;
-            let by3 = k3 >> 3;
-            let sk3 = k3 & 7;
-            let y = i32x4v(coeffs[by0 * 64 + i * 8 + sk0], coeffs[by1 * 64 + i * 8 + sk1], coeffs[by2 * 64 + i * 8 + sk2], coeffs[by3 * 64 + i * 8 + sk3]) + i32x4s(128);
-            let u = i32x4v(coeffs[128 + i * 8 + hk0], coeffs[128 + i * 8 + hk1], coeffs[128 + i * 8 + hk2], coeffs[128 + i * 8 + hk3]);
-            let v = i32x4v(coeffs[192 + i * 8 + hk0], coeffs[192 + i * 8 + hk1], coeffs[192 + i * 8 + hk2], coeffs[192 + i * 8 + hk3]);
-            draw_yuv_simd(image, x0 + k, y0 + i, y, u, v);
-        }
-    }
-}
-
 fn draw_macroblock_yuv440(image: &mut ImageBuffer, x0: usize, y0: usize, width: usize, height: usize, coeffs: &[i32]) {
     for i in 0..height {
         for k in 0..width {
@@ -913,8 +853,8 @@ pub fn test(src: &[u8]) -> Option<(usize, usize)> {
         let length = from_be16(&src[sp + 2..sp + 4]) as usize;
         match marker {
             0xFFC0 | 0xFFC1 | 0xFFC2 => {
-                let width = from_be16(&src[sp + 5..sp + 7]) as usize;
-                let height = from_be16(&src[sp + 7..sp + 9]) as usize;
+                let height = from_be16(&src[sp + 5..sp + 7]) as usize;
+                let width = from_be16(&src[sp + 7..sp + 9]) as usize;
                 let components = src[sp + 9];
                 if (components == 1) || (components == 3) { // does not support RGBA or CMYK JPEGs
                     return Some((width, height));
@@ -1235,6 +1175,298 @@ pub fn decode(src: &[u8]) -> Result<ImageBuffer, String> {
     Err("Invalid JPEG 11".to_string())
 }
 
-pub fn encode(_image: &ImageBuffer) -> Result<Vec<u8>, String> {
-    Err("not implemented yet".to_string())
+// encoder tables from ITU T.81 annex K, quantization tables in natural order
+const LUMINANCE_QUANT: [u8; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61,
+    12, 12, 14, 19, 26, 58, 60, 55,
+    14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62,
+    18, 22, 37, 56, 68, 109, 103, 77,
+    24, 35, 55, 64, 81, 104, 113, 92,
+    49, 64, 78, 87, 103, 121, 120, 101,
+    72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+const CHROMINANCE_QUANT: [u8; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99,
+    18, 21, 26, 66, 99, 99, 99, 99,
+    24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+// natural index of each zigzag position
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10,
+    17, 24, 32, 25, 18, 11, 4, 5,
+    12, 19, 26, 33, 40, 48, 41, 34,
+    27, 20, 13, 6, 7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36,
+    29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46,
+    53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+const DC_LUMINANCE_BITS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+const DC_LUMINANCE_VALUES: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+const DC_CHROMINANCE_BITS: [u8; 16] = [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0];
+const DC_CHROMINANCE_VALUES: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+const AC_LUMINANCE_BITS: [u8; 16] = [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7D];
+const AC_LUMINANCE_VALUES: [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07,
+    0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xA1, 0x08, 0x23, 0x42, 0xB1, 0xC1, 0x15, 0x52, 0xD1, 0xF0,
+    0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0A, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x25, 0x26, 0x27, 0x28,
+    0x29, 0x2A, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+    0x4A, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6A, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+    0x8A, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7,
+    0xA8, 0xA9, 0xAA, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xB9, 0xBA, 0xC2, 0xC3, 0xC4, 0xC5,
+    0xC6, 0xC7, 0xC8, 0xC9, 0xCA, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xD9, 0xDA, 0xE1, 0xE2,
+    0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xEA, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8,
+    0xF9, 0xFA,
+];
+
+const AC_CHROMINANCE_BITS: [u8; 16] = [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77];
+const AC_CHROMINANCE_VALUES: [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71,
+    0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xA1, 0xB1, 0xC1, 0x09, 0x23, 0x33, 0x52, 0xF0,
+    0x15, 0x62, 0x72, 0xD1, 0x0A, 0x16, 0x24, 0x34, 0xE1, 0x25, 0xF1, 0x17, 0x18, 0x19, 0x1A, 0x26,
+    0x27, 0x28, 0x29, 0x2A, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+    0x49, 0x4A, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+    0x69, 0x6A, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+    0x88, 0x89, 0x8A, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0xA2, 0xA3, 0xA4, 0xA5,
+    0xA6, 0xA7, 0xA8, 0xA9, 0xAA, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xB9, 0xBA, 0xC2, 0xC3,
+    0xC4, 0xC5, 0xC6, 0xC7, 0xC8, 0xC9, 0xCA, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xD9, 0xDA,
+    0xE2, 0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xEA, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8,
+    0xF9, 0xFA,
+];
+
+// (code, length) for every symbol of a huffman table
+struct CodeTable {
+    codes: [(u16, u8); 256],
+}
+
+impl CodeTable {
+    fn new(bits: &[u8; 16], values: &[u8]) -> CodeTable {
+        let mut codes = [(0u16, 0u8); 256];
+        let mut code = 0u16;
+        let mut k = 0;
+        for (i, &count) in bits.iter().enumerate() {
+            for _ in 0..count {
+                codes[values[k] as usize] = (code, (i + 1) as u8);
+                code += 1;
+                k += 1;
+            }
+            code <<= 1;
+        }
+        CodeTable {
+            codes,
+        }
+    }
+}
+
+struct Writer {
+    dst: Vec<u8>,
+    cache: u32,
+    bits: u32,
+}
+
+impl Writer {
+    fn put(&mut self, value: u32, length: u8) {
+        self.cache = (self.cache << length) | (value & ((1 << length) - 1));
+        self.bits += length as u32;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            let b = ((self.cache >> self.bits) & 255) as u8;
+            self.dst.push(b);
+            if b == 0xFF {
+                self.dst.push(0x00);
+            }
+        }
+    }
+    
+    fn put_code(&mut self, table: &CodeTable, symbol: u8) {
+        let (code, length) = table.codes[symbol as usize];
+        self.put(code as u32, length);
+    }
+    
+    // fill the last byte with 1 bits
+    fn flush(&mut self) {
+        if self.bits > 0 {
+            self.put(0x7F, (8 - self.bits) as u8);
+        }
+    }
+}
+
+// category and extra bits of a coefficient
+fn make_cat(value: i32) -> (u8, u32) {
+    let magnitude = value.unsigned_abs();
+    let cat = (32 - magnitude.leading_zeros()) as u8;
+    let bits = if value < 0 {(value - 1) as u32} else {value as u32};
+    (cat, bits)
+}
+
+fn make_quant(base: &[u8; 64], quality: u8) -> [u8; 64] {
+    let quality = clamp(quality as i32, 1, 100);
+    let scale = if quality < 50 {5000 / quality} else {200 - quality * 2};
+    let mut table = [0u8; 64];
+    for (q, b) in table.iter_mut().zip(base.iter()) {
+        *q = clamp(((*b as i32) * scale + 50) / 100, 1, 255) as u8;
+    }
+    table
+}
+
+fn fdct(block: &mut [f32; 64]) {
+    let mut cos = [[0f32; 8]; 8];
+    for (u, row) in cos.iter_mut().enumerate() {
+        let cu = if u == 0 {FC4} else {1.0};
+        for (x, c) in row.iter_mut().enumerate() {
+            *c = 0.5 * cu * ((((2 * x + 1) * u) as f32) * std::f32::consts::PI / 16.0).cos();
+        }
+    }
+    let mut temp = [0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            temp[y * 8 + u] = (0..8).map( | x | cos[u][x] * block[y * 8 + x]).sum();
+        }
+    }
+    for v in 0..8 {
+        for u in 0..8 {
+            block[v * 8 + u] = (0..8).map( | y | cos[v][y] * temp[y * 8 + u]).sum();
+        }
+    }
+}
+
+fn pack_block(writer: &mut Writer, block: &mut [f32; 64], qtable: &[u8; 64], dcht: &CodeTable, acht: &CodeTable, dc: &mut i32) {
+    fdct(block);
+    let mut coeffs = [0i32; 64];
+    for (k, &n) in ZIGZAG.iter().enumerate() {
+        coeffs[k] = (block[n] / (qtable[n] as f32)).round() as i32;
+    }
+    
+    let (cat, bits) = make_cat(coeffs[0] - *dc);
+    *dc = coeffs[0];
+    writer.put_code(dcht, cat);
+    if cat > 0 {
+        writer.put(bits, cat);
+    }
+    
+    let mut run = 0;
+    for &coeff in &coeffs[1..] {
+        if coeff == 0 {
+            run += 1;
+            continue;
+        }
+        while run >= 16 {
+            writer.put_code(acht, 0xF0);
+            run -= 16;
+        }
+        let (cat, bits) = make_cat(coeff);
+        writer.put_code(acht, (run << 4) | cat);
+        writer.put(bits, cat);
+        run = 0;
+    }
+    if run > 0 {
+        writer.put_code(acht, 0x00);
+    }
+}
+
+fn push_segment(dst: &mut Vec<u8>, marker: u16, data: &[u8]) {
+    dst.extend_from_slice(&marker.to_be_bytes());
+    dst.extend_from_slice(&((data.len() + 2) as u16).to_be_bytes());
+    dst.extend_from_slice(data);
+}
+
+// baseline JFIF with 4:2:0 subsampling, quality runs from 1 (smallest) to 100 (best)
+pub fn encode(image: &ImageBuffer, quality: u8) -> Result<Vec<u8>, String> {
+    if (image.width == 0) || (image.height == 0) || (image.width >= 65536) || (image.height >= 65536) {
+        return Err("Invalid image size".to_string());
+    }
+    if image.data.len() < image.width * image.height {
+        return Err("Image data too small".to_string());
+    }
+    let yqtable = make_quant(&LUMINANCE_QUANT, quality);
+    let cqtable = make_quant(&CHROMINANCE_QUANT, quality);
+    
+    let mut dst: Vec<u8> = vec![0xFF, 0xD8];
+    push_segment(&mut dst, 0xFFE0, &[b'J', b'F', b'I', b'F', 0, 1, 1, 0, 0, 1, 0, 1, 0, 0]);
+    for (n, qtable) in [&yqtable, &cqtable].iter().enumerate() {
+        let mut data = vec![n as u8];
+        data.extend(ZIGZAG.iter().map( | k | qtable[*k]));
+        push_segment(&mut dst, 0xFFDB, &data);
+    }
+    let mut sof = vec![8];
+    sof.extend_from_slice(&(image.height as u16).to_be_bytes());
+    sof.extend_from_slice(&(image.width as u16).to_be_bytes());
+    sof.extend_from_slice(&[3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    push_segment(&mut dst, 0xFFC0, &sof);
+    for (d, bits, values) in [
+        (0x00, &DC_LUMINANCE_BITS, &DC_LUMINANCE_VALUES[..]),
+        (0x10, &AC_LUMINANCE_BITS, &AC_LUMINANCE_VALUES[..]),
+        (0x01, &DC_CHROMINANCE_BITS, &DC_CHROMINANCE_VALUES[..]),
+        (0x11, &AC_CHROMINANCE_BITS, &AC_CHROMINANCE_VALUES[..]),
+    ] {
+        let mut data = vec![d];
+        data.extend_from_slice(bits);
+        data.extend_from_slice(values);
+        push_segment(&mut dst, 0xFFC4, &data);
+    }
+    push_segment(&mut dst, 0xFFDA, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+    
+    let ydcht = CodeTable::new(&DC_LUMINANCE_BITS, &DC_LUMINANCE_VALUES);
+    let yacht = CodeTable::new(&AC_LUMINANCE_BITS, &AC_LUMINANCE_VALUES);
+    let cdcht = CodeTable::new(&DC_CHROMINANCE_BITS, &DC_CHROMINANCE_VALUES);
+    let cacht = CodeTable::new(&AC_CHROMINANCE_BITS, &AC_CHROMINANCE_VALUES);
+    let mut writer = Writer {
+        dst,
+        cache: 0,
+        bits: 0,
+    };
+    let mut dc = [0i32; 3];
+    let mbwidth = image.width.div_ceil(16);
+    let mbheight = image.height.div_ceil(16);
+    for i in 0..mbheight {
+        for k in 0..mbwidth {
+            // convert the macroblock to YCbCr, repeating the edge pixels past the image border
+            let mut y = [0f32; 256];
+            let mut u = [0f32; 256];
+            let mut v = [0f32; 256];
+            for by in 0..16 {
+                let py = (i * 16 + by).min(image.height - 1);
+                for bx in 0..16 {
+                    let px = (k * 16 + bx).min(image.width - 1);
+                    let c = image.data[py * image.width + px];
+                    let r = ((c >> 16) & 255) as f32;
+                    let g = ((c >> 8) & 255) as f32;
+                    let b = (c & 255) as f32;
+                    y[by * 16 + bx] = 0.299 * r + 0.587 * g + 0.114 * b - 128.0;
+                    u[by * 16 + bx] = -0.168736 * r - 0.331264 * g + 0.5 * b;
+                    v[by * 16 + bx] = 0.5 * r - 0.418688 * g - 0.081312 * b;
+                }
+            }
+            for n in 0..4 {
+                let mut block = [0f32; 64];
+                for (p, value) in block.iter_mut().enumerate() {
+                    *value = y[((n >> 1) * 8 + (p >> 3)) * 16 + (n & 1) * 8 + (p & 7)];
+                }
+                pack_block(&mut writer, &mut block, &yqtable, &ydcht, &yacht, &mut dc[0]);
+            }
+            for (c, plane) in [&u, &v].iter().enumerate() {
+                let mut block = [0f32; 64];
+                for (p, value) in block.iter_mut().enumerate() {
+                    let s = (p >> 3) * 32 + (p & 7) * 2;
+                    *value = 0.25 * (plane[s] + plane[s + 1] + plane[s + 16] + plane[s + 17]);
+                }
+                pack_block(&mut writer, &mut block, &cqtable, &cdcht, &cacht, &mut dc[c + 1]);
+            }
+        }
+    }
+    writer.flush();
+    let mut dst = writer.dst;
+    dst.extend_from_slice(&[0xFF, 0xD9]);
+    Ok(dst)
 }
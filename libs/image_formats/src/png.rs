@@ -1039,6 +1039,117 @@ pub fn decode(src: &[u8]) -> Result<ImageBuffer, String> {
     }
 }
 
-pub fn encode(_image: &ImageBuffer) -> Result<Vec<u8>, String> {
-    Err("not implemented".to_string())
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = (a as i16) + (b as i16) - (c as i16);
+    let pa = (p - (a as i16)).abs();
+    let pb = (p - (b as i16)).abs();
+    let pc = (p - (c as i16)).abs();
+    if (pa <= pb) && (pa <= pc) {
+        a
+    }
+    else if pb <= pc {
+        b
+    }
+    else {
+        c
+    }
+}
+
+// filter every row with each of the five filters and keep the one with the smallest
+// sum of absolute (signed) values, the usual heuristic from the PNG spec
+fn filter(src: &[u8], height: usize, stride: usize, bpp: usize) -> Vec<u8> {
+    let mut dst: Vec<u8> = Vec::with_capacity((stride + 1) * height);
+    let zero: Vec<u8> = vec![0; stride];
+    let mut candidate: Vec<u8> = vec![0; stride];
+    let mut best: Vec<u8> = vec![0; stride];
+    for y in 0..height {
+        let row = &src[y * stride..(y + 1) * stride];
+        let prev = if y > 0 {&src[(y - 1) * stride..y * stride]} else {&zero[..]};
+        let mut best_type = 0u8;
+        let mut best_sum = usize::MAX;
+        for ftype in 0..5u8 {
+            for x in 0..stride {
+                let a = if x >= bpp {row[x - bpp]} else {0};
+                let b = prev[x];
+                let c = if x >= bpp {prev[x - bpp]} else {0};
+                candidate[x] = match ftype {
+                    0 => row[x],
+                    1 => row[x].wrapping_sub(a),
+                    2 => row[x].wrapping_sub(b),
+                    3 => row[x].wrapping_sub((((a as u16) + (b as u16)) >> 1) as u8),
+                    _ => row[x].wrapping_sub(paeth(a, b, c)),
+                };
+            }
+            let sum: usize = candidate.iter().map( | v | (*v as i8).unsigned_abs() as usize).sum();
+            if sum < best_sum {
+                best_sum = sum;
+                best_type = ftype;
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+        dst.push(best_type);
+        dst.extend_from_slice(&best);
+    }
+    dst
+}
+
+fn crc32(data: &[&[u8]]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if (c & 1) != 0 {0xEDB88320 ^ (c >> 1)} else {c >> 1};
+        }
+        *entry = c;
+    }
+    let mut crc = 0xFFFFFFFFu32;
+    for part in data {
+        for b in part.iter() {
+            crc = table[((crc ^ (*b as u32)) & 255) as usize] ^ (crc >> 8);
+        }
+    }
+    crc ^ 0xFFFFFFFF
+}
+
+fn push_chunk(dst: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    dst.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    dst.extend_from_slice(chunk_type);
+    dst.extend_from_slice(data);
+    dst.extend_from_slice(&crc32(&[chunk_type, data]).to_be_bytes());
+}
+
+// writes 8 bit RGB, or RGBA if any pixel is not fully opaque
+pub fn encode(image: &ImageBuffer) -> Result<Vec<u8>, String> {
+    if (image.width == 0) || (image.height == 0) || (image.width >= 65536) || (image.height >= 65536) {
+        return Err("Invalid image size".to_string());
+    }
+    if image.data.len() < image.width * image.height {
+        return Err("Image data too small".to_string());
+    }
+    let pixels = &image.data[0..image.width * image.height];
+    let has_alpha = pixels.iter().any( | c | (c >> 24) != 255);
+    let (itype, bpp) = if has_alpha {(TYPE_RGBA8, 4)} else {(TYPE_RGB8, 3)};
+    let stride = image.width * bpp;
+    let mut raw_data: Vec<u8> = Vec::with_capacity(stride * image.height);
+    for c in pixels {
+        raw_data.push(((c >> 16) & 255) as u8);
+        raw_data.push(((c >> 8) & 255) as u8);
+        raw_data.push((c & 255) as u8);
+        if has_alpha {
+            raw_data.push((c >> 24) as u8);
+        }
+    }
+    let filtered_data = filter(&raw_data, image.height, stride, bpp);
+    let zipped_data = makepad_miniz::compress_to_vec_zlib(&filtered_data, 6);
+    
+    let mut dst: Vec<u8> = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+    let mut ihdr: Vec<u8> = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(image.width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(image.height as u32).to_be_bytes());
+    ihdr.extend_from_slice(&itype.to_be_bytes());
+    ihdr.extend_from_slice(&[0, 0, 0]); // compression, filter, interlace
+    push_chunk(&mut dst, b"IHDR", &ihdr);
+    push_chunk(&mut dst, b"IDAT", &zipped_data);
+    push_chunk(&mut dst, b"IEND", &[]);
+    Ok(dst)
 }
\ No newline at end of file
@@ -0,0 +1,65 @@
+use makepad_image_formats::*;
+
+fn gradient(width: usize, height: usize, alpha: bool) -> ImageBuffer {
+    let mut image = ImageBuffer::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let r = (x * 255 / width) as u32;
+            let g = (y * 255 / height) as u32;
+            let b = ((x + y) * 127 / (width + height)) as u32;
+            let a = if alpha {((x * 7 + y) & 255) as u32} else {255};
+            image.data[y * width + x] = (a << 24) | (r << 16) | (g << 8) | b;
+        }
+    }
+    image
+}
+
+#[test]
+fn png_roundtrip() {
+    for (width, height, alpha) in [(1, 1, false), (37, 23, false), (64, 48, true), (300, 5, true)] {
+        let image = gradient(width, height, alpha);
+        let data = png::encode(&image).unwrap();
+        assert_eq!(png::test(&data), Some((width, height)));
+        let decoded = png::decode(&data).unwrap();
+        assert_eq!((decoded.width, decoded.height), (width, height));
+        assert!(decoded.data == image.data);
+    }
+    // a smooth image compresses well
+    let data = png::encode(&gradient(256, 256, false)).unwrap();
+    assert!(data.len() < 256 * 256 * 3 / 4);
+    assert!(png::encode(&ImageBuffer::new(0, 4)).is_err());
+}
+
+fn mean_error(a: &ImageBuffer, b: &ImageBuffer) -> f32 {
+    let mut total = 0;
+    for (ca, cb) in a.data.iter().zip(b.data.iter()) {
+        for shift in [0, 8, 16] {
+            total += (((ca >> shift) & 255) as i32 - ((cb >> shift) & 255) as i32).abs();
+        }
+    }
+    total as f32 / (a.data.len() * 3) as f32
+}
+
+#[test]
+fn jpeg_roundtrip() {
+    for (width, height) in [(1, 1), (16, 16), (37, 23), (200, 130)] {
+        let image = gradient(width, height, false);
+        let data = jpeg::encode(&image, 90).unwrap();
+        assert_eq!(jpeg::test(&data), Some((width, height)));
+        let decoded = jpeg::decode(&data).unwrap();
+        assert_eq!((decoded.width, decoded.height), (width, height));
+        let error = mean_error(&image, &decoded);
+        assert!(error < 5.0, "{}x{} mean error {}", width, height, error);
+    }
+}
+
+#[test]
+fn jpeg_quality() {
+    let image = gradient(128, 128, false);
+    let low = jpeg::encode(&image, 10).unwrap();
+    let high = jpeg::encode(&image, 95).unwrap();
+    assert!(low.len() < high.len());
+    let low_error = mean_error(&image, &jpeg::decode(&low).unwrap());
+    let high_error = mean_error(&image, &jpeg::decode(&high).unwrap());
+    assert!(high_error < low_error);
+}
@@ -1,5 +1,22 @@
 use proc_macro::{TokenStream};
 use makepad_micro_proc_macro::*;
+use crate::serde_attrs::*;
+
+// the binary format has no keys, so only #[skip] changes what is written
+fn de_bin_fields(tb: &mut TokenBuilder, fields: &[(StructField, FieldAttrs)]) {
+    tb.add("{");
+    for (field, attrs) in fields {
+        tb.ident(&field.name).add(":");
+        if attrs.skip {
+            attrs.default_value(tb);
+        }
+        else {
+            tb.add("DeBin :: de_bin ( o , d ) ?");
+        }
+        tb.add(",");
+    }
+    tb.add("}");
+}
 
 pub fn derive_ser_bin_impl(input: TokenStream) -> TokenStream {
     let mut parser = TokenParser::new(input);
@@ -24,8 +41,14 @@ pub fn derive_ser_bin_impl(input: TokenStream) -> TokenStream {
                 }
             }
             else if let Some(fields) = parser.eat_all_struct_fields(){ 
-                for field in fields{
-                    tb.add("self .").ident(&field.name).add(". ser_bin ( s ) ;");
+                let fields = match parse_fields(fields) {
+                    Ok(fields) => fields,
+                    Err(err) => return err
+                };
+                for (field, attrs) in fields{
+                    if !attrs.skip {
+                        tb.add("self .").ident(&field.name).add(". ser_bin ( s ) ;");
+                    }
                 }
             }
             else{
@@ -65,13 +88,17 @@ pub fn derive_ser_bin_impl(input: TokenStream) -> TokenStream {
                         tb.add("}");
                     }
                     else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for field in fields.iter(){
-                            tb.ident(&field.name).add(",");
-                        }
-                        tb.add("} => {").suf_u16(index).add(". ser_bin ( s ) ;");
-                        for field in fields{
-                            tb.ident(&field.name).add(". ser_bin ( s ) ;");
+                        let fields = match parse_fields(fields) {
+                            Ok(fields) => fields,
+                            Err(err) => return err
+                        };
+                        tb.add("Self ::").ident(&variant);
+                        bind_fields(&mut tb, &fields);
+                        tb.add("=> {").suf_u16(index).add(". ser_bin ( s ) ;");
+                        for (field, attrs) in fields{
+                            if !attrs.skip {
+                                tb.ident(&field.name).add(". ser_bin ( s ) ;");
+                            }
                         }
                         tb.add("}");
                     }
@@ -122,11 +149,10 @@ pub fn derive_de_bin_impl(input: TokenStream) -> TokenStream {
                 tb.add(")");
             }
             else if let Some(fields) = parser.eat_all_struct_fields(){ 
-                tb.add("{");
-                for field in fields{
-                    tb.ident(&field.name).add(": DeBin :: de_bin ( o , d ) ? ,");
+                match parse_fields(fields) {
+                    Ok(fields) => de_bin_fields(&mut tb, &fields),
+                    Err(err) => return err
                 }
-                tb.add("}");
             }
             else{
                 return parser.unexpected()
@@ -165,11 +191,11 @@ pub fn derive_de_bin_impl(input: TokenStream) -> TokenStream {
                         tb.add(")");
                     }
                     else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.ident(&variant).add("{");
-                        for field in fields.iter(){
-                            tb.ident(&field.name).add(": DeBin :: de_bin ( o , d ) ? ,");
+                        tb.ident(&variant);
+                        match parse_fields(fields) {
+                            Ok(fields) => de_bin_fields(&mut tb, &fields),
+                            Err(err) => return err
                         }
-                        tb.add("}");
                     }
                     else if parser.is_punct_alone(',') || parser.is_eot(){ // bare variant
                         tb.ident(&variant);
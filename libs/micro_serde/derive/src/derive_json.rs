@@ -1,16 +1,203 @@
 use proc_macro::TokenStream;
 use makepad_micro_proc_macro::*;
+use crate::serde_attrs::*;
+
+// writes the bound fields into an object that is already open
+fn ser_json_fields(tb: &mut TokenBuilder, fields: &[(StructField, FieldAttrs)]) {
+    for (field, attrs) in fields {
+        if attrs.skip {
+            continue;
+        }
+        if attrs.flatten {
+            tb.add("s . flatten ( d + 1 ,").ident(&field.name).add(") ;");
+        }
+        else if is_option(&field.ty) {
+            tb.add("if let Some ( t ) =").ident(&field.name).add("{");
+            tb.add("s . field ( d + 1 ,").string(&attrs.key(field)).add(") ;");
+            tb.add("t . ser_json ( d + 1 , s ) ; }");
+        }
+        else {
+            tb.add("s . field ( d + 1 ,").string(&attrs.key(field)).add(") ;");
+            tb.ident(&field.name).add(". ser_json ( d + 1 , s ) ;");
+        }
+    }
+}
+
+// writes the bound tuple fields n0, n1.. as an array, a single field is written as is when newtype_inline
+fn ser_json_tuple(tb: &mut TokenBuilder, len: usize, newtype_inline: bool) {
+    if len == 1 && newtype_inline {
+        tb.add("n0 . ser_json ( d , s ) ;");
+        return
+    }
+    tb.add("s . out . push (").chr('[').add(") ;");
+    for i in 0..len {
+        tb.ident(&format!("n{}", i)).add(". ser_json ( d , s ) ;");
+        if i != len - 1 {
+            tb.add("s . out . push (").chr(',').add(") ;");
+        }
+    }
+    tb.add("s . out . push (").chr(']').add(") ;");
+}
+
+// emits a block parsing an object into the struct or variant at path. The tag key of an internally tagged enum is skipped
+fn de_json_fields(tb: &mut TokenBuilder, path: &str, fields: &[(StructField, FieldAttrs)], tag: Option<&str>) -> Result<(), TokenStream> {
+    let flatten = fields.iter().filter( | (_, attrs) | attrs.flatten && !attrs.skip).count();
+    if flatten > 1 {
+        return Err(error("Only one field can be #[flatten]"))
+    }
+    tb.add("{ s . curly_open ( i ) ? ;");
+    for (field, attrs) in fields {
+        if !attrs.skip && !attrs.flatten {
+            tb.add("let mut").ident(&format!("_{}", field.name)).add("= None ;");
+        }
+    }
+    if flatten > 0 {
+        tb.add("let mut flat_fields = std :: collections :: HashMap :: new ( ) ;");
+    }
+    tb.add("while let Some ( _ ) = s . next_str ( ) {");
+    tb.add("match s . strbuf . as_ref ( ) {");
+    for (field, attrs) in fields {
+        if !attrs.skip && !attrs.flatten {
+            tb.string(&attrs.key(field)).add("=> { s . next_colon ( i ) ? ;");
+            tb.ident(&format!("_{}", field.name)).add("= Some ( DeJson :: de_json ( s , i ) ? ) ; } ,");
+        }
+    }
+    if let Some(tag) = tag {
+        tb.string(tag).add("=> { s . next_colon ( i ) ? ; s . string ( i ) ? ; } ,");
+    }
+    if flatten > 0 {
+        tb.add("_ => { let k = s . as_string ( ) ? ; s . next_colon ( i ) ? ;");
+        tb.add("flat_fields . insert ( k , JsonValue :: de_json ( s , i ) ? ) ; }");
+    }
+    else {
+        tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . strbuf ) )");
+    }
+    tb.add("} ; s . eat_comma_curly ( i ) ? ;");
+    tb.add("} ; s . curly_close ( i ) ? ;");
+
+    tb.add(path).add("{");
+    for (field, attrs) in fields {
+        tb.ident(&field.name).add(":");
+        if attrs.skip {
+            attrs.default_value(tb);
+        }
+        else if attrs.flatten {
+            tb.add("s . de_flat ( flat_fields ) ?");
+        }
+        else {
+            tb.add("if let Some ( t ) =").ident(&format!("_{}", field.name)).add("{ t } else");
+            if attrs.default.is_some() {
+                tb.add("{");
+                attrs.default_value(tb);
+                tb.add("}");
+            }
+            else if is_option(&field.ty) {
+                tb.add("{ None }");
+            }
+            else {
+                tb.add("{ return std :: result :: Result :: Err ( s . err_nf (").string(&attrs.key(field)).add(") ) }");
+            }
+        }
+        tb.add(",");
+    }
+    tb.add("} }");
+    Ok(())
+}
+
+// parses an array into the tuple struct or variant at path, a single field is parsed as is when newtype_inline
+fn de_json_tuple(tb: &mut TokenBuilder, path: &str, len: usize, newtype_inline: bool) {
+    if len == 1 && newtype_inline {
+        tb.add(path).add("( DeJson :: de_json ( s , i ) ? )");
+        return
+    }
+    tb.add("{ s . block_open ( i ) ? ;");
+    tb.add("let r =").add(path).add("(");
+    for _ in 0..len {
+        tb.add("{ let r = DeJson :: de_json ( s , i ) ? ; s . eat_comma_block ( i ) ? ; r } ,");
+    }
+    tb.add(") ;");
+    tb.add("s . block_close ( i ) ? ; r }");
+}
+
+fn de_json_null(tb: &mut TokenBuilder, path: &str) {
+    tb.add("{ if s . tok != DeJsonTok :: Null { return std :: result :: Result :: Err ( s . err_token (").string("null").add(") ) }");
+    tb.add("s . next_tok ( i ) ? ;").add(path).add("}");
+}
+
+enum VariantKind {
+    Unit,
+    Tuple(usize),
+    Named(Vec<(StructField, FieldAttrs)>),
+}
+
+struct Variant {
+    ident: String,
+    name: String,
+    kind: VariantKind,
+}
+
+impl Variant {
+    fn path(&self) -> String {
+        format!("Self :: {}", self.ident)
+    }
+
+    fn pattern(&self, tb: &mut TokenBuilder) {
+        tb.add("Self ::").ident(&self.ident);
+        match &self.kind {
+            VariantKind::Unit => (),
+            VariantKind::Tuple(len) => {
+                tb.add("(");
+                for i in 0..*len {
+                    tb.ident(&format!("n{}", i)).add(",");
+                }
+                tb.add(")");
+            }
+            VariantKind::Named(fields) => bind_fields(tb, fields),
+        }
+    }
+}
+
+fn parse_variants(parser: &mut TokenParser) -> Result<Vec<Variant>, TokenStream> {
+    if !parser.open_brace() {
+        return Err(parser.unexpected())
+    }
+    let mut variants = Vec::new();
+    while !parser.eat_eot() {
+        let attrs = parser.eat_attributes();
+        if let Some(ident) = parser.eat_any_ident() {
+            let kind = if let Some(types) = parser.eat_all_types() {
+                VariantKind::Tuple(types.len())
+            }
+            else if let Some(fields) = parser.eat_all_struct_fields() { // named variant
+                VariantKind::Named(parse_fields(fields)?)
+            }
+            else if parser.is_punct_alone(',') || parser.is_eot() { // bare variant
+                VariantKind::Unit
+            }
+            else {
+                return Err(parser.unexpected())
+            };
+            let name = variant_name(&attrs, &ident)?;
+            variants.push(Variant {ident, name, kind});
+            parser.eat_punct_alone(',');
+        }
+        else {
+            return Err(parser.unexpected())
+        }
+    }
+    Ok(variants)
+}
 
 pub fn derive_ser_json_impl(input: TokenStream) -> TokenStream {
 
     let mut parser = TokenParser::new(input);
     let mut tb = TokenBuilder::new();
-    
-    parser.eat_attributes();
+
+    let main_attrs = parser.eat_attributes();
     parser.eat_ident("pub");
     if parser.eat_ident("struct"){
         if let Some(name) = parser.eat_any_ident(){
-            
+
             let generic = parser.eat_generic();
             let types = parser.eat_all_types();
             let where_clause = parser.eat_where_clause(Some("SerJson"));
@@ -18,7 +205,7 @@ pub fn derive_ser_json_impl(input: TokenStream) -> TokenStream {
             tb.add("impl").stream(generic.clone());
             tb.add("SerJson for").ident(&name).stream(generic).stream(where_clause);
             tb.add("{ fn ser_json ( & self , d : usize , s : & mut SerJsonState ) {");
-            
+
             if let Some(types) = types{
                 tb.add("s . out . push (").chr('[').add(") ;");
                 for i in 0..types.len(){
@@ -30,27 +217,16 @@ pub fn derive_ser_json_impl(input: TokenStream) -> TokenStream {
                 tb.add("s . out . push (").chr(']').add(") ;");
             }
             else if let Some(fields) = parser.eat_all_struct_fields(){
-                let fields_len = fields.len();
-                tb.add("s . st_pre ( ) ;");
                 // named struct
-                for (i, field) in fields.into_iter().enumerate() {
-                    if field.ty.into_iter().next().unwrap().to_string() == "Option"{
-                        tb.add("if let Some ( t ) = ").add("& self .").ident(&field.name).add("{");
-                        tb.add("s . field ( d + 1 ,").string(&field.name).add(") ;");
-                        tb.add("t . ser_json ( d + 1 , s ) ;");
-                        if i != fields_len - 1 {
-                            tb.add("s . conl ( ) ;");
-                        }
-                        tb.add("} ;");
-                    }
-                    else{
-                        tb.add("s . field ( d + 1 ,").string(&field.name).add(" ) ;");
-                        tb.add("self .").ident(&field.name).add(". ser_json ( d + 1 , s ) ;");
-                        if i != fields_len - 1 {
-                            tb.add("s . conl ( ) ;");
-                        }
-                    }
-                }
+                let fields = match parse_fields(fields) {
+                    Ok(fields) => fields,
+                    Err(err) => return err
+                };
+                tb.add("let Self");
+                bind_fields(&mut tb, &fields);
+                tb.add("= self ;");
+                tb.add("s . st_pre ( ) ;");
+                ser_json_fields(&mut tb, &fields);
                 tb.add("s . st_post ( d ) ;");
             }
             else{
@@ -64,90 +240,96 @@ pub fn derive_ser_json_impl(input: TokenStream) -> TokenStream {
         if let Some(name) = parser.eat_any_ident(){
             let generic = parser.eat_generic();
             let where_clause = parser.eat_where_clause(Some("SerJson"));
+            let repr = match EnumRepr::parse(&main_attrs) {
+                Ok(repr) => repr,
+                Err(err) => return err
+            };
+            let variants = match parse_variants(&mut parser) {
+                Ok(variants) => variants,
+                Err(err) => return err
+            };
 
             tb.add("impl").stream(generic.clone());
             tb.add("SerJson for").ident(&name).stream(generic).stream(where_clause);
             tb.add("{ fn ser_json ( & self , d : usize , s : & mut SerJsonState ) {");
-            tb.add("s . out . push (").chr('{').add(") ;");
-            tb.add("match self {");
-            
-            if !parser.open_brace(){
-                return parser.unexpected()
+            if let EnumRepr::External = repr {
+                tb.add("s . out . push (").chr('{').add(") ;");
             }
+            tb.add("match self {");
 
-            while !parser.eat_eot(){
-                // parse ident
-                parser.eat_attributes();
-                if let Some(variant) = parser.eat_any_ident(){
-                    if let Some(types) = parser.eat_all_types(){
-                        
-                        tb.add("Self ::").ident(&variant).add("(");
-                        for i in 0..types.len(){
-                            tb.ident(&format!("n{}", i)).add(",");
-                        }
-                        tb.add(") => {");
-                        tb.add("s . label (").string(&variant).add(") ;");
+            for variant in &variants {
+                variant.pattern(&mut tb);
+                tb.add("=> {");
+                match (&repr, &variant.kind) {
+                    (EnumRepr::External, VariantKind::Unit) => {
+                        tb.add("s . label (").string(&variant.name).add(") ;");
+                        tb.add("s . out . push_str (").string(":[]").add(") ;");
+                    }
+                    (EnumRepr::External, VariantKind::Tuple(len)) => {
+                        tb.add("s . label (").string(&variant.name).add(") ;");
+                        tb.add("s . out . push (").chr(':').add(") ;");
+                        ser_json_tuple(&mut tb, *len, false);
+                    }
+                    (EnumRepr::External, VariantKind::Named(fields)) => {
+                        tb.add("s . label (").string(&variant.name).add(") ;");
                         tb.add("s . out . push (").chr(':').add(") ;");
-                        tb.add("s . out . push (").chr('[').add(") ;");
-                        
-                        for i in 0..types.len(){
-                            tb.ident(&format!("n{}", i)).add(". ser_json ( d , s ) ;");
-                            if i != types.len() - 1{
-                                tb.add("s . out . push (").chr(',').add(") ;");
+                        tb.add("s . st_pre ( ) ;");
+                        ser_json_fields(&mut tb, fields);
+                        tb.add("s . st_post ( d ) ;");
+                    }
+                    (EnumRepr::Internal(tag), kind) => {
+                        tb.add("s . st_pre ( ) ;");
+                        tb.add("s . field ( d + 1 ,").string(tag).add(") ;");
+                        tb.add("s . label (").string(&variant.name).add(") ;");
+                        match kind {
+                            VariantKind::Unit => (),
+                            VariantKind::Tuple(1) => {
+                                tb.add("s . flatten ( d + 1 , n0 ) ;");
+                            }
+                            VariantKind::Tuple(_) => {
+                                return error("Internally tagged enums cannot have tuple variants with more than one field")
                             }
+                            VariantKind::Named(fields) => ser_json_fields(&mut tb, fields),
                         }
-                        tb.add("s . out . push (").chr(']').add(") ;");
-                        tb.add("}");
+                        tb.add("s . st_post ( d ) ;");
                     }
-                    else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for field in fields.iter(){
-                            tb.ident(&field.name).add(",");
-                        }
-                        tb.add("} => {");
-                        
-                        tb.add("s . label (").string(&variant).add(") ;");
-                        tb.add("s . out . push (").chr(':').add(") ;");
+                    (EnumRepr::Adjacent(tag, content), kind) => {
                         tb.add("s . st_pre ( ) ;");
-
-                        let fields_len = fields.len();
-                        for (i, field) in fields.into_iter().enumerate() {
-                            let field_strip = if let Some(v) = field.name.strip_prefix("_"){v}else{&field.name};
-                            if field.ty.into_iter().next().unwrap().to_string() == "Option"{
-                                tb.add("if let Some ( t ) = ").ident(&field.name).add("{");
-                                tb.add("s . field ( d + 1 ,").string(&field_strip).add(") ;");
-                                tb.add("t . ser_json ( d + 1 , s ) ;");
-                                if i != fields_len - 1 {
-                                    tb.add("s . conl ( ) ;");
-                                }
-                                tb.add("} ;");
+                        tb.add("s . field ( d + 1 ,").string(tag).add(") ;");
+                        tb.add("s . label (").string(&variant.name).add(") ;");
+                        match kind {
+                            VariantKind::Unit => (),
+                            VariantKind::Tuple(len) => {
+                                tb.add("s . field ( d + 1 ,").string(content).add(") ;");
+                                ser_json_tuple(&mut tb, *len, true);
                             }
-                            else{
-                                tb.add("s . field ( d + 1 ,").string(&field_strip).add(" ) ;");
-                                tb.ident(&field.name).add(". ser_json ( d + 1 , s ) ;");
-                                if i != fields_len - 1 {
-                                    tb.add("s . conl ( ) ;");
-                                }
+                            VariantKind::Named(fields) => {
+                                tb.add("s . field ( d + 1 ,").string(content).add(") ;");
+                                tb.add("s . st_pre ( ) ;");
+                                ser_json_fields(&mut tb, fields);
+                                tb.add("s . st_post ( d + 1 ) ;");
                             }
                         }
-                        tb.add("s . st_post ( d ) ; }");
+                        tb.add("s . st_post ( d ) ;");
                     }
-                    else if parser.is_punct_alone(',') || parser.is_eot(){ // bare variant
-                        tb.add("Self ::").ident(&variant).add("=> {");
-                        tb.add("s . label (").string(&variant).add(") ;");
-                        tb.add("s . out . push_str (").string(":[]").add(") ; }");
+                    (EnumRepr::Untagged, VariantKind::Unit) => {
+                        tb.add("s . out . push_str (").string("null").add(") ;");
                     }
-                    else{
-                        return parser.unexpected();
+                    (EnumRepr::Untagged, VariantKind::Tuple(len)) => {
+                        ser_json_tuple(&mut tb, *len, true);
+                    }
+                    (EnumRepr::Untagged, VariantKind::Named(fields)) => {
+                        tb.add("s . st_pre ( ) ;");
+                        ser_json_fields(&mut tb, fields);
+                        tb.add("s . st_post ( d ) ;");
                     }
-                    parser.eat_punct_alone(',');
-                }
-                else{
-                    return parser.unexpected()
                 }
+                tb.add("}");
             }
             tb.add("}");
-            tb.add("s . out . push (").chr('}').add(") ;");
+            if let EnumRepr::External = repr {
+                tb.add("s . out . push (").chr('}').add(") ;");
+            }
             tb.add("} } ;");
             return tb.end();
         }
@@ -158,8 +340,8 @@ pub fn derive_ser_json_impl(input: TokenStream) -> TokenStream {
 pub fn derive_de_json_impl(input: TokenStream) -> TokenStream {
     let mut parser = TokenParser::new(input);
     let mut tb = TokenBuilder::new();
-    
-    parser.eat_attributes();
+
+    let main_attrs = parser.eat_attributes();
     parser.eat_ident("pub");
     if parser.eat_ident("struct"){
         if let Some(name) = parser.eat_any_ident(){
@@ -173,135 +355,170 @@ pub fn derive_de_json_impl(input: TokenStream) -> TokenStream {
             tb.add("-> std :: result :: Result < Self ,  DeJsonErr > { ");
 
             if let Some(types) = types{
-                tb.add("s . block_open ( i ) ? ;");
-                tb.add("let r = Self");
-                tb.add("(");
-                for _ in 0..types.len(){
-                     tb.add("{ let r = DeJson :: de_json ( s , i ) ? ; s . eat_comma_block ( i ) ? ; r } ,");
-                }
-                tb.add(") ;");
-                tb.add("s . block_close ( i ) ? ;");
-                tb.add("std :: result :: Result :: Ok ( r )");
+                tb.add("std :: result :: Result :: Ok (");
+                de_json_tuple(&mut tb, "Self", types.len(), false);
+                tb.add(")");
             }
-            else if let Some(fields) = parser.eat_all_struct_fields(){ 
-                tb.add("s . curly_open ( i ) ? ;");
-                for field in &fields{
-                    tb.add("let mut").ident(&format!("_{}",field.name)).add("= None ;");
-                }
-                tb.add("while let Some ( _ ) = s . next_str ( ) {");
-                tb.add("match s . strbuf . as_ref ( ) {");
-                for field in &fields{
-                    let field_strip = if let Some(v) = field.name.strip_prefix("_"){v}else{&field.name};
-                    tb.string(&field_strip).add("=> { s . next_colon ( i ) ? ;");
-                    tb.ident(&format!("_{}",field.name)).add("= Some (DeJson :: de_json ( s , i ) ? ) ; } ,");
-                }
-                tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . strbuf ) )");
-                tb.add("} ; s . eat_comma_curly ( i ) ? ;");
-                tb.add("} ; s . curly_close ( i ) ? ;");
-                
-                tb.add("std :: result :: Result :: Ok ( Self {");
-                for field in fields{
-                    tb.ident(&field.name).add(":");
-                    if field.ty.into_iter().next().unwrap().to_string() == "Option"{
-                        tb.add("if let Some ( t ) =").ident(&format!("_{}",field.name));
-                        tb.add("{ t } else { None } ,");
-                    }
-                    else{
-                        tb.add("if let Some ( t ) =").ident(&format!("_{}",field.name));
-                        tb.add("{ t } else { return Err ( s . err_nf (");
-                        tb.string(&field.name).add(") ) } ,");
-                    }
+            else if let Some(fields) = parser.eat_all_struct_fields(){
+                let fields = match parse_fields(fields) {
+                    Ok(fields) => fields,
+                    Err(err) => return err
+                };
+                tb.add("std :: result :: Result :: Ok (");
+                if let Err(err) = de_json_fields(&mut tb, "Self", &fields, None) {
+                    return err
                 }
-                tb.add("} )");
+                tb.add(")");
             }
             else{
                 return parser.unexpected()
             }
-            tb.add("} } ;"); 
+            tb.add("} } ;");
             return tb.end();
         }
     }
     else if parser.eat_ident("enum"){
-        
+
         if let Some(name) = parser.eat_any_ident(){
             let generic = parser.eat_generic();
             let where_clause = parser.eat_where_clause(Some("DeJson"));
+            let repr = match EnumRepr::parse(&main_attrs) {
+                Ok(repr) => repr,
+                Err(err) => return err
+            };
+            let variants = match parse_variants(&mut parser) {
+                Ok(variants) => variants,
+                Err(err) => return err
+            };
 
             tb.add("impl").stream(generic.clone());
             tb.add("DeJson for").ident(&name).stream(generic).stream(where_clause);
             tb.add("{ fn de_json ( s : & mut  DeJsonState , i : & mut std :: str :: Chars )");
             tb.add("-> std :: result :: Result < Self , DeJsonErr > { ");
-            tb.add("s . curly_open ( i ) ? ;");
-            tb.add("let _ = s . string ( i ) ? ;");
-            tb.add("s . colon ( i ) ? ;");
-            tb.add("let r = std :: result :: Result :: Ok ( match s . strbuf . as_ref ( ) {");
-            
-            if !parser.open_brace(){
-                return parser.unexpected()
-            }
-            while !parser.eat_eot(){
-                // parse ident
-                parser.eat_attributes();
-                if let Some(variant) = parser.eat_any_ident(){
-                    tb.string(&variant).add("=> {");
-                    if let Some(types) = parser.eat_all_types(){
-                        
-                        tb.add("s . block_open ( i ) ? ;");
-                        tb.add("let r = Self ::").ident(&variant).add("(");
-                        for _ in 0..types.len(){
-                            tb.add("{ let r = DeJson :: de_json ( s , i ) ? ; s . eat_comma_block ( i ) ? ; r } ,");
-                        }
-                        tb.add(") ;");
-                        tb.add("s . block_close ( i ) ? ; r");
-                    }
-                    else if let Some(fields) = parser.eat_all_struct_fields(){ // named variant
-                        tb.add("s . curly_open ( i ) ? ;");
-                        for field in &fields{
-                            tb.add("let mut").ident(&format!("_{}",field.name)).add("= None ;");
-                        }
-                        tb.add("while let Some ( _ ) = s . next_str ( ) {");
-                        tb.add("match s . strbuf . as_ref ( ) {");
-                        for field in &fields{
-                            tb.string(&field.name).add("=> { s . next_colon ( i ) ? ;");
-                            tb.ident(&format!("_{}",field.name)).add("= Some (DeJson :: de_json ( s , i ) ? ) ; } ,");
-                        }
-                        tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . strbuf ) )");
-                        tb.add("} s . eat_comma_curly ( i ) ? ;");
-                        tb.add("} s . curly_close ( i ) ? ;");
-                        
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for field in fields{
-                            tb.ident(&field.name).add(":");
-                            if field.ty.into_iter().next().unwrap().to_string() == "Option"{
-                                tb.add("if let Some ( t ) =").ident(&format!("_{}",field.name));
-                                tb.add("{ t } else { None } ,");
-                            }
-                            else{
-                                tb.add("if let Some ( t ) =").ident(&format!("_{}",field.name));
-                                tb.add("{ t } else { return Err ( s . err_nf (");
-                                tb.string(&field.name).add(") ) } ,");
-                            }
-                        }
-                        tb.add("}");
-                    }
-                    else if parser.is_punct_alone(',') || parser.is_eot(){ // bare variant
-                        tb.add("s . block_open ( i ) ? ; s . block_close ( i ) ? ; Self ::").ident(&variant);
-                    }
-                    else{
-                        return parser.unexpected();
-                    }
-                    
-                    tb.add("}");
-                    parser.eat_punct_alone(',');
-                }
-                else{
-                    return parser.unexpected()
-                }
+
+            let result = match &repr {
+                EnumRepr::External => de_json_external(&mut tb, &variants),
+                EnumRepr::Internal(tag) => de_json_internal(&mut tb, &variants, tag),
+                EnumRepr::Adjacent(tag, content) => de_json_adjacent(&mut tb, &variants, tag, content),
+                EnumRepr::Untagged => de_json_untagged(&mut tb, &variants, &name),
+            };
+            if let Err(err) = result {
+                return err
             }
-            tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . strbuf ) )");
-            tb.add("} ) ; s . curly_close ( i ) ? ; r } }");
+            tb.add("} } ;");
             return tb.end();
         }
     }
     parser.unexpected()
 }
+
+// {"Variant":[...]}
+fn de_json_external(tb: &mut TokenBuilder, variants: &[Variant]) -> Result<(), TokenStream> {
+    tb.add("s . curly_open ( i ) ? ;");
+    tb.add("let _ = s . string ( i ) ? ;");
+    tb.add("s . colon ( i ) ? ;");
+    tb.add("let r = std :: result :: Result :: Ok ( match s . strbuf . as_ref ( ) {");
+    for variant in variants {
+        tb.string(&variant.name).add("=> {");
+        match &variant.kind {
+            VariantKind::Unit => {
+                tb.add("s . block_open ( i ) ? ; s . block_close ( i ) ? ;").add(&variant.path());
+            }
+            VariantKind::Tuple(len) => de_json_tuple(tb, &variant.path(), *len, false),
+            VariantKind::Named(fields) => de_json_fields(tb, &variant.path(), fields, None)?,
+        }
+        tb.add("}");
+    }
+    tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . strbuf ) )");
+    tb.add("} ) ; s . curly_close ( i ) ? ; r");
+    Ok(())
+}
+
+// {"tag":"Variant", ...fields}
+fn de_json_internal(tb: &mut TokenBuilder, variants: &[Variant], tag: &str) -> Result<(), TokenStream> {
+    tb.add("let tag = s . peek_tag ( i ,").string(tag).add(") ? ;");
+    tb.add("std :: result :: Result :: Ok ( match tag . as_ref ( ) {");
+    for variant in variants {
+        tb.string(&variant.name).add("=> {");
+        match &variant.kind {
+            VariantKind::Unit => de_json_fields(tb, &variant.path(), &[], Some(tag))?,
+            VariantKind::Tuple(1) => {
+                tb.add("s . curly_open ( i ) ? ;");
+                tb.add("let mut flat_fields = std :: collections :: HashMap :: new ( ) ;");
+                tb.add("while let Some ( _ ) = s . next_str ( ) {");
+                tb.add("let k = s . as_string ( ) ? ; s . next_colon ( i ) ? ;");
+                tb.add("if k ==").string(tag).add("{ s . string ( i ) ? ; }");
+                tb.add("else { flat_fields . insert ( k , JsonValue :: de_json ( s , i ) ? ) ; }");
+                tb.add("s . eat_comma_curly ( i ) ? ;");
+                tb.add("} ; s . curly_close ( i ) ? ;");
+                tb.add(&variant.path()).add("( s . de_flat ( flat_fields ) ? )");
+            }
+            VariantKind::Tuple(_) => {
+                return Err(error("Internally tagged enums cannot have tuple variants with more than one field"))
+            }
+            VariantKind::Named(fields) => de_json_fields(tb, &variant.path(), fields, Some(tag))?,
+        }
+        tb.add("}");
+    }
+    tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( & tag ) )");
+    tb.add("} )");
+    Ok(())
+}
+
+// {"tag":"Variant", "content":...}
+fn de_json_adjacent(tb: &mut TokenBuilder, variants: &[Variant], tag: &str, content: &str) -> Result<(), TokenStream> {
+    tb.add("let tag = s . peek_tag ( i ,").string(tag).add(") ? ;");
+    tb.add("let mut r = None ;");
+    tb.add("s . curly_open ( i ) ? ;");
+    tb.add("while let Some ( _ ) = s . next_str ( ) {");
+    tb.add("match s . strbuf . as_ref ( ) {");
+    tb.string(tag).add("=> { s . next_colon ( i ) ? ; s . string ( i ) ? ; } ,");
+    tb.string(content).add("=> { s . next_colon ( i ) ? ; r = Some ( match tag . as_ref ( ) {");
+    for variant in variants {
+        tb.string(&variant.name).add("=>");
+        match &variant.kind {
+            VariantKind::Unit => de_json_null(tb, &variant.path()),
+            VariantKind::Tuple(len) => de_json_tuple(tb, &variant.path(), *len, true),
+            VariantKind::Named(fields) => de_json_fields(tb, &variant.path(), fields, None)?,
+        }
+        tb.add(",");
+    }
+    tb.add("_ => return std :: result :: Result :: Err ( s . err_enum ( & tag ) )");
+    tb.add("} ) ; } ,");
+    tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . strbuf ) )");
+    tb.add("} ; s . eat_comma_curly ( i ) ? ;");
+    tb.add("} ; s . curly_close ( i ) ? ;");
+    // unit variants can leave out the content
+    tb.add("if let Some ( r ) = r { return std :: result :: Result :: Ok ( r ) }");
+    tb.add("match tag . as_ref ( ) {");
+    for variant in variants {
+        tb.string(&variant.name).add("=>");
+        if let VariantKind::Unit = variant.kind {
+            tb.add("std :: result :: Result :: Ok (").add(&variant.path()).add(") ,");
+        }
+        else {
+            tb.add("std :: result :: Result :: Err ( s . err_nf (").string(content).add(") ) ,");
+        }
+    }
+    tb.add("_ => std :: result :: Result :: Err ( s . err_enum ( & tag ) )");
+    tb.add("}");
+    Ok(())
+}
+
+// tries every variant in order on a copy of the parser state, the first one that parses wins
+fn de_json_untagged(tb: &mut TokenBuilder, variants: &[Variant], name: &str) -> Result<(), TokenStream> {
+    for variant in variants {
+        tb.add("{ let mut s2 = s . clone ( ) ; let mut i2 = i . clone ( ) ;");
+        tb.add("let r = ( | s : & mut DeJsonState , i : & mut std :: str :: Chars |");
+        tb.add("-> std :: result :: Result < Self , DeJsonErr > { std :: result :: Result :: Ok (");
+        match &variant.kind {
+            VariantKind::Unit => de_json_null(tb, &variant.path()),
+            VariantKind::Tuple(len) => de_json_tuple(tb, &variant.path(), *len, true),
+            VariantKind::Named(fields) => de_json_fields(tb, &variant.path(), fields, None)?,
+        }
+        tb.add(") } ) ( & mut s2 , & mut i2 ) ;");
+        tb.add("if let std :: result :: Result :: Ok ( r ) = r { * s = s2 ; * i = i2 ; return std :: result :: Result :: Ok ( r ) } }");
+    }
+    tb.add("std :: result :: Result :: Err ( s . err_msg (").string(&format!("Data did not match any variant of {}", name)).add(") )");
+    Ok(())
+}
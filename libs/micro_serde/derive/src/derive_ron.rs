@@ -1,5 +1,83 @@
 use makepad_micro_proc_macro::*;
 use proc_macro::TokenStream;
+use crate::serde_attrs::*;
+
+// ron has its own enum syntax, so only the field options and variant renames apply here
+fn parse_ron_fields(fields: Vec<StructField>) -> Result<Vec<(StructField, FieldAttrs)>, TokenStream> {
+    let fields = parse_fields(fields)?;
+    if fields.iter().any( | (_, attrs) | attrs.flatten) {
+        return Err(error("#[flatten] is not supported by SerRon and DeRon"))
+    }
+    Ok(fields)
+}
+
+// unlike json, ron keys are idents and keep their leading _
+fn ron_key(field: &StructField, attrs: &FieldAttrs) -> String {
+    attrs.rename.clone().unwrap_or_else( || field.name.clone())
+}
+
+// writes the bound fields into a struct that is already open
+fn ser_ron_fields(tb: &mut TokenBuilder, fields: &[(StructField, FieldAttrs)]) {
+    for (field, attrs) in fields {
+        if attrs.skip {
+            continue;
+        }
+        if is_option(&field.ty) {
+            tb.add("if let Some ( t ) =").ident(&field.name).add("{");
+            tb.add("s . field ( d + 1 ,").string(&ron_key(field, attrs)).add(") ;");
+            tb.add("t . ser_ron ( d + 1 , s ) ; s . conl ( ) ; } ;");
+        }
+        else {
+            tb.add("s . field ( d + 1 ,").string(&ron_key(field, attrs)).add(" ) ;");
+            tb.ident(&field.name).add(". ser_ron ( d + 1 , s ) ; s . conl ( ) ;");
+        }
+    }
+}
+
+// emits a block parsing a struct into path
+fn de_ron_fields(tb: &mut TokenBuilder, path: &str, fields: &[(StructField, FieldAttrs)]) {
+    tb.add("{ s . paren_open ( i ) ? ;");
+    for (field, attrs) in fields {
+        if !attrs.skip {
+            tb.add("let mut").ident(&format!("_{}", field.name)).add("= None ;");
+        }
+    }
+    tb.add("while let Some ( _ ) = s . next_ident ( ) {");
+    tb.add("match s . identbuf . as_ref ( ) {");
+    for (field, attrs) in fields {
+        if !attrs.skip {
+            tb.string(&ron_key(field, attrs)).add("=> { s . next_colon ( i ) ? ;");
+            tb.ident(&format!("_{}", field.name)).add("= Some ( DeRon :: de_ron ( s , i ) ? ) ; } ,");
+        }
+    }
+    tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . identbuf ) )");
+    tb.add("} ; s . eat_comma_paren ( i ) ? ;");
+    tb.add("} ; s . paren_close ( i ) ? ;");
+
+    tb.add(path).add("{");
+    for (field, attrs) in fields {
+        tb.ident(&field.name).add(":");
+        if attrs.skip {
+            attrs.default_value(tb);
+        }
+        else {
+            tb.add("if let Some ( t ) =").ident(&format!("_{}", field.name)).add("{ t } else");
+            if attrs.default.is_some() {
+                tb.add("{");
+                attrs.default_value(tb);
+                tb.add("}");
+            }
+            else if is_option(&field.ty) {
+                tb.add("{ None }");
+            }
+            else {
+                tb.add("{ return Err ( s . err_nf (").string(&ron_key(field, attrs)).add(") ) }");
+            }
+        }
+        tb.add(",");
+    }
+    tb.add("} }");
+}
 
 pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
     let mut parser = TokenParser::new(input);
@@ -31,26 +109,16 @@ pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
                 }
                 tb.add("s.out.push(").chr(')').add(");");
             } else if let Some(fields) = parser.eat_all_struct_fields() {
-                tb.add("s.st_pre( ) ;");
-
                 // named struct
-                for field in fields {
-                    if field.ty.into_iter().next().unwrap().to_string() == "Option" {
-                        tb.add("if let Some ( t ) = ")
-                            .add("& self .")
-                            .ident(&field.name)
-                            .add("{");
-                        tb.add("s.field ( d + 1 ,").string(&field.name).add(") ;");
-                        tb.add("t.ser_ron ( d + 1 , s ) ; s . conl ( ) ; } ;");
-                    } else {
-                        tb.add("s . field ( d + 1 ,")
-                            .string(&field.name)
-                            .add(" ) ;");
-                        tb.add("self .")
-                            .ident(&field.name)
-                            .add(". ser_ron ( d + 1 , s ) ; s . conl ( ) ;");
-                    }
-                }
+                let fields = match parse_ron_fields(fields) {
+                    Ok(fields) => fields,
+                    Err(err) => return err,
+                };
+                tb.add("let Self");
+                bind_fields(&mut tb, &fields);
+                tb.add("= self ;");
+                tb.add("s.st_pre( ) ;");
+                ser_ron_fields(&mut tb, &fields);
                 tb.add("s . st_post ( d ) ;");
             } else {
                 return parser.unexpected();
@@ -77,15 +145,19 @@ pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
 
             while !parser.eat_eot() {
                 // parse ident
-                parser.eat_attributes();
+                let attrs = parser.eat_attributes();
                 if let Some(variant) = parser.eat_any_ident() {
+                    let label = match variant_name(&attrs, &variant) {
+                        Ok(label) => label,
+                        Err(err) => return err,
+                    };
                     if let Some(types) = parser.eat_all_types() {
                         tb.add("Self ::").ident(&variant).add("(");
                         for i in 0..types.len() {
                             tb.ident(&format!("n{}", i)).add(",");
                         }
                         tb.add(") => {");
-                        tb.add("s . out . push_str (").string(&variant).add(") ;");
+                        tb.add("s . out . push_str (").string(&label).add(") ;");
                         tb.add("s . out . push (").chr('(').add(") ;");
 
                         for i in 0..types.len() {
@@ -98,34 +170,22 @@ pub fn derive_ser_ron_impl(input: TokenStream) -> TokenStream {
                         tb.add("}");
                     } else if let Some(fields) = parser.eat_all_struct_fields() {
                         // named variant
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for field in fields.iter() {
-                            tb.ident(&field.name).add(",");
-                        }
-                        tb.add("} => {");
+                        let fields = match parse_ron_fields(fields) {
+                            Ok(fields) => fields,
+                            Err(err) => return err,
+                        };
+                        tb.add("Self ::").ident(&variant);
+                        bind_fields(&mut tb, &fields);
+                        tb.add("=> {");
 
-                        tb.add("s . out . push_str (").string(&variant).add(") ;");
+                        tb.add("s . out . push_str (").string(&label).add(") ;");
                         tb.add("s . st_pre ( ) ;");
-
-                        for field in fields {
-                            if field.ty.into_iter().next().unwrap().to_string() == "Option" {
-                                tb.add("if ").ident(&field.name).add(". is_some ( ) {");
-                                tb.add("s . field ( d + 1 ,").string(&field.name).add(") ;");
-                                tb.ident(&field.name)
-                                    .add(" . ser_ron ( d + 1 , s ) ; s . conl ( ) ; } ;");
-                            } else {
-                                tb.add("s . field ( d + 1 ,")
-                                    .string(&field.name)
-                                    .add(" ) ;");
-                                tb.ident(&field.name)
-                                    .add(". ser_ron ( d + 1 , s ) ; s . conl ( ) ;");
-                            }
-                        }
+                        ser_ron_fields(&mut tb, &fields);
                         tb.add("s . st_post ( d ) ; }");
                     } else if parser.is_punct_alone(',') || parser.is_eot() {
                         // bare variant
                         tb.add("Self ::").ident(&variant).add("=> {");
-                        tb.add("s . out . push_str (").string(&variant).add(") ; }");
+                        tb.add("s . out . push_str (").string(&label).add(") ; }");
                     } else {
                         return parser.unexpected();
                     }
@@ -172,40 +232,13 @@ pub fn derive_de_ron_impl(input: TokenStream) -> TokenStream {
                 tb.add("s . paren_close ( i ) ? ;");
                 tb.add("std :: result :: Result :: Ok ( r ) ");
             } else if let Some(fields) = parser.eat_all_struct_fields() {
-                tb.add("s . paren_open ( i ) ? ;");
-                for field in &fields {
-                    tb.add("let mut")
-                        .ident(&format!("_{}", field.name))
-                        .add("= None ;");
-                }
-                tb.add("while let Some ( _ ) = s . next_ident ( ) {");
-                tb.add("match s . identbuf . as_ref ( ) {");
-                for field in &fields {
-                    tb.string(&field.name).add("=> { s . next_colon ( i ) ? ;");
-                    tb.ident(&format!("_{}", field.name))
-                        .add("= Some ( DeRon :: de_ron ( s , i ) ? ) ; } ,");
-                }
-                tb.add(
-                    "_ => return std :: result :: Result :: Err ( s . err_exp ( & s . identbuf ) )",
-                );
-                tb.add("} ; s . eat_comma_paren ( i ) ? ;");
-                tb.add("} ; s . paren_close ( i ) ? ;");
-
-                tb.add("std :: result :: Result :: Ok ( Self {");
-                for field in fields {
-                    tb.ident(&field.name).add(":");
-                    if field.ty.into_iter().next().unwrap().to_string() == "Option" {
-                        tb.add("if let Some ( t ) =")
-                            .ident(&format!("_{}", field.name));
-                        tb.add("{ t } else { None } ,");
-                    } else {
-                        tb.add("if let Some ( t ) =")
-                            .ident(&format!("_{}", field.name));
-                        tb.add("{ t } else { return Err ( s . err_nf (");
-                        tb.string(&field.name).add(") ) } ,");
-                    }
-                }
-                tb.add("} )");
+                let fields = match parse_ron_fields(fields) {
+                    Ok(fields) => fields,
+                    Err(err) => return err,
+                };
+                tb.add("std :: result :: Result :: Ok (");
+                de_ron_fields(&mut tb, "Self", &fields);
+                tb.add(")");
             } else {
                 return parser.unexpected();
             }
@@ -232,9 +265,13 @@ pub fn derive_de_ron_impl(input: TokenStream) -> TokenStream {
             }
             while !parser.eat_eot() {
                 // parse ident
-                parser.eat_attributes();
+                let attrs = parser.eat_attributes();
                 if let Some(variant) = parser.eat_any_ident() {
-                    tb.string(&variant).add("=> {");
+                    let label = match variant_name(&attrs, &variant) {
+                        Ok(label) => label,
+                        Err(err) => return err,
+                    };
+                    tb.string(&label).add("=> {");
                     if let Some(types) = parser.eat_all_types() {
                         tb.add("s . paren_open ( i ) ? ;");
                         tb.add("let r = Self ::").ident(&variant).add("(");
@@ -245,38 +282,11 @@ pub fn derive_de_ron_impl(input: TokenStream) -> TokenStream {
                         tb.add("s . paren_close ( i ) ? ; r");
                     } else if let Some(fields) = parser.eat_all_struct_fields() {
                         // named variant
-                        tb.add("s . paren_open ( i ) ? ;");
-                        for field in &fields {
-                            tb.add("let mut")
-                                .ident(&format!("_{}", field.name))
-                                .add("= None ;");
-                        }
-                        tb.add("while let Some ( _ ) = s . next_ident ( ) {");
-                        tb.add("match s . identbuf . as_ref ( ) {");
-                        for field in &fields {
-                            tb.string(&field.name).add("=> { s . next_colon ( i ) ? ;");
-                            tb.ident(&format!("_{}", field.name))
-                                .add("= Some ( DeRon :: de_ron ( s , i ) ? ) ; } ,");
-                        }
-                        tb.add("_ => return std :: result :: Result :: Err ( s . err_exp ( & s . strbuf ) )");
-                        tb.add("} ; s . eat_comma_paren ( i ) ? ;");
-                        tb.add("} ; s . paren_close ( i ) ? ;");
-
-                        tb.add("Self ::").ident(&variant).add("{");
-                        for field in fields {
-                            tb.ident(&field.name).add(":");
-                            if field.ty.into_iter().next().unwrap().to_string() == "Option" {
-                                tb.add("if let Some ( t ) =")
-                                    .ident(&format!("_{}", field.name));
-                                tb.add("{ t } else { None } ,");
-                            } else {
-                                tb.add("if let Some ( t ) =")
-                                    .ident(&format!("_{}", field.name));
-                                tb.add("{ t } else { return Err ( s . err_nf (");
-                                tb.string(&field.name).add(") ) } ,");
-                            }
-                        }
-                        tb.add("}");
+                        let fields = match parse_ron_fields(fields) {
+                            Ok(fields) => fields,
+                            Err(err) => return err,
+                        };
+                        de_ron_fields(&mut tb, &format!("Self :: {}", variant), &fields);
                     } else if parser.is_punct_alone(',') || parser.is_eot() {
                         // bare variant
                         tb.add("Self ::").ident(&variant);
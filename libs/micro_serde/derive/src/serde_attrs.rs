@@ -0,0 +1,140 @@
+use proc_macro::{TokenStream, TokenTree};
+use makepad_micro_proc_macro::*;
+
+// the per field options shared by all the derives
+//   #[rename = "key"]    use a different key than the field name
+//   #[default]           use Default::default() when the key is missing
+//   #[default(expr)]     use expr when the key is missing
+//   #[skip]              never serialize, deserialize as the default
+//   #[flatten]           inline the fields of this value into the parent object (json only)
+pub struct FieldAttrs {
+    pub rename: Option<String>,
+    pub default: Option<Option<TokenStream>>,
+    pub skip: bool,
+    pub flatten: bool,
+}
+
+impl FieldAttrs {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self, TokenStream> {
+        let mut ret = Self {rename: None, default: None, skip: false, flatten: false};
+        for attr in attrs {
+            match attr.name.as_ref() {
+                "rename" => ret.rename = Some(attr_string(attr)?),
+                "default" => ret.default = Some(attr.args.clone().filter( | args | !args.is_empty())),
+                "skip" => ret.skip = true,
+                "flatten" => ret.flatten = true,
+                _ => ()
+            }
+        }
+        Ok(ret)
+    }
+
+    // the key used in the serialized output, a leading _ is stripped so keywords can be used as keys
+    pub fn key(&self, field: &StructField) -> String {
+        if let Some(rename) = &self.rename {
+            return rename.clone()
+        }
+        if let Some(v) = field.name.strip_prefix('_') {v.to_string()} else {field.name.clone()}
+    }
+
+    // emits the value used for a missing or skipped field
+    pub fn default_value(&self, tb: &mut TokenBuilder) {
+        if let Some(Some(expr)) = &self.default {
+            tb.add("{").stream(Some(expr.clone())).add("}");
+        }
+        else {
+            tb.add("std :: default :: Default :: default ( )");
+        }
+    }
+}
+
+// how an enum is represented in json
+//   default               {"Variant":[...]}
+//   #[tag = "t"]          {"t":"Variant", ...fields}
+//   #[tag = "t"] #[content = "c"]  {"t":"Variant", "c":...}
+//   #[untagged]           just the content of the variant
+pub enum EnumRepr {
+    External,
+    Internal(String),
+    Adjacent(String, String),
+    Untagged,
+}
+
+impl EnumRepr {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self, TokenStream> {
+        let mut tag = None;
+        let mut content = None;
+        let mut untagged = false;
+        for attr in attrs {
+            match attr.name.as_ref() {
+                "tag" => tag = Some(attr_string(attr)?),
+                "content" => content = Some(attr_string(attr)?),
+                "untagged" => untagged = true,
+                _ => ()
+            }
+        }
+        match (tag, content, untagged) {
+            (None, None, false) => Ok(EnumRepr::External),
+            (Some(tag), None, false) => Ok(EnumRepr::Internal(tag)),
+            (Some(tag), Some(content), false) => Ok(EnumRepr::Adjacent(tag, content)),
+            (None, None, true) => Ok(EnumRepr::Untagged),
+            (None, Some(_), false) => Err(error("#[content] needs a #[tag]")),
+            _ => Err(error("#[untagged] cannot be combined with #[tag] or #[content]"))
+        }
+    }
+}
+
+// the name used for an enum variant, which can be changed with #[rename = "..."]
+pub fn variant_name(attrs: &[Attribute], variant: &str) -> Result<String, TokenStream> {
+    for attr in attrs {
+        if attr.name == "rename" {
+            return attr_string(attr)
+        }
+    }
+    Ok(variant.to_string())
+}
+
+pub fn is_option(ty: &TokenStream) -> bool {
+    // the last path segment before the generic arguments, so std::option::Option<T> works too
+    let mut last = None;
+    for tt in ty.clone() {
+        match tt {
+            TokenTree::Ident(ident) => last = Some(ident.to_string()),
+            TokenTree::Punct(punct) if punct.as_char() == '<' => break,
+            _ => ()
+        }
+    }
+    last.as_deref() == Some("Option")
+}
+
+fn attr_string(attr: &Attribute) -> Result<String, TokenStream> {
+    if let Some(args) = &attr.args {
+        if let Some(TokenTree::Literal(lit)) = args.clone().into_iter().next() {
+            let lit = lit.to_string();
+            if let Some(s) = lit.strip_prefix('"').and_then( | s | s.strip_suffix('"')) {
+                return Ok(s.to_string())
+            }
+        }
+    }
+    Err(error(&format!("#[{}] expects a string, like #[{} = \"name\"]", attr.name, attr.name)))
+}
+
+pub fn parse_fields(fields: Vec<StructField>) -> Result<Vec<(StructField, FieldAttrs)>, TokenStream> {
+    let mut ret = Vec::new();
+    for field in fields {
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        ret.push((field, attrs));
+    }
+    Ok(ret)
+}
+
+// a pattern binding every serialized field of a struct or variant by name
+pub fn bind_fields(tb: &mut TokenBuilder, fields: &[(StructField, FieldAttrs)]) {
+    tb.add("{");
+    for (field, attrs) in fields {
+        if !attrs.skip {
+            tb.ident(&field.name).add(",");
+        }
+    }
+    tb.add(".. }");
+}
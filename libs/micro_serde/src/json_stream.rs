@@ -0,0 +1,683 @@
+use std::collections::HashMap;
+use crate::serde_json::{DeJsonErr, JsonValue, SerJson, SerJsonState};
+
+// Pull parsing and streaming writing of json, for documents that are too large to build
+// a tree for or that arrive in pieces. Whitespace separated top level values are read
+// one after another, so newline delimited streams work too.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonEvent {
+    ObjectStart,
+    ObjectEnd,
+    ArrayStart,
+    ArrayEnd,
+    Key(String),
+    String(String),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Nest {
+    Array,
+    Object,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Expect {
+    Value,
+    ArrayFirst,
+    ObjectFirst,
+    Key,
+    Colon,
+    Comma,
+}
+
+enum Step {
+    Event(JsonEvent),
+    NeedMore,
+    End,
+}
+
+// the state machine shared by both readers. It never consumes a partial token, so when the
+// input runs out halfway it can be called again once more input is there
+struct JsonParser {
+    stack: Vec<Nest>,
+    expect: Expect,
+    line: usize,
+    col: usize,
+    // a string that ran out of input: what it decoded to so far, and the offset from its
+    // opening quote to continue lexing at, so a long streamed string isn't lexed over again
+    partial_string: Option<(Vec<u8>, usize)>,
+}
+
+impl JsonParser {
+    fn new() -> Self {
+        Self {stack: Vec::new(), expect: Expect::Value, line: 0, col: 0, partial_string: None}
+    }
+
+    fn err(&self, msg: &str) -> DeJsonErr {
+        DeJsonErr {msg: msg.to_string(), line: self.line, col: self.col}
+    }
+
+    fn is_complete(&self) -> bool {
+        self.stack.is_empty() && self.expect == Expect::Value
+    }
+
+    fn consume(&mut self, input: &[u8], pos: &mut usize, end: usize) {
+        for &b in &input[*pos..end] {
+            if b == b'\n' {
+                self.line += 1;
+                self.col = 0;
+            }
+            else if b & 0xc0 != 0x80 {
+                self.col += 1;
+            }
+        }
+        *pos = end;
+    }
+
+    fn after_value(&mut self) {
+        self.expect = if self.stack.is_empty() {Expect::Value} else {Expect::Comma};
+    }
+
+    fn close(&mut self, input: &[u8], pos: &mut usize, event: JsonEvent) -> Step {
+        self.consume(input, pos, *pos + 1);
+        self.stack.pop();
+        self.after_value();
+        Step::Event(event)
+    }
+
+    fn step(&mut self, input: &[u8], pos: &mut usize, finished: bool) -> Result<Step, DeJsonErr> {
+        loop {
+            let mut end = *pos;
+            while end < input.len() && matches!(input[end], b' ' | b'\t' | b'\n' | b'\r') {
+                end += 1;
+            }
+            self.consume(input, pos, end);
+            if *pos >= input.len() {
+                if !finished {
+                    return Ok(Step::NeedMore)
+                }
+                if self.is_complete() {
+                    return Ok(Step::End)
+                }
+                return Err(self.err("Unexpected end of input"))
+            }
+            let c = input[*pos];
+            match self.expect {
+                Expect::Colon => {
+                    if c != b':' {
+                        return Err(self.err("Expected :"))
+                    }
+                    self.consume(input, pos, *pos + 1);
+                    self.expect = Expect::Value;
+                }
+                Expect::Comma => match (c, self.stack.last()) {
+                    (b',', Some(Nest::Array)) => {
+                        self.consume(input, pos, *pos + 1);
+                        self.expect = Expect::Value;
+                    }
+                    (b',', Some(Nest::Object)) => {
+                        self.consume(input, pos, *pos + 1);
+                        self.expect = Expect::Key;
+                    }
+                    (b']', Some(Nest::Array)) => return Ok(self.close(input, pos, JsonEvent::ArrayEnd)),
+                    (b'}', Some(Nest::Object)) => return Ok(self.close(input, pos, JsonEvent::ObjectEnd)),
+                    _ => return Err(self.err("Expected , or a closing bracket"))
+                },
+                Expect::ObjectFirst if c == b'}' => return Ok(self.close(input, pos, JsonEvent::ObjectEnd)),
+                Expect::ArrayFirst if c == b']' => return Ok(self.close(input, pos, JsonEvent::ArrayEnd)),
+                Expect::ObjectFirst | Expect::Key => {
+                    if c != b'"' {
+                        return Err(self.err("Expected a key"))
+                    }
+                    return match self.lex_string(input, *pos, finished) ? {
+                        Some((key, end)) => {
+                            self.consume(input, pos, end);
+                            self.expect = Expect::Colon;
+                            Ok(Step::Event(JsonEvent::Key(key)))
+                        }
+                        None => Ok(Step::NeedMore)
+                    }
+                }
+                Expect::Value | Expect::ArrayFirst => return self.value(input, pos, finished)
+            }
+        }
+    }
+
+    fn value(&mut self, input: &[u8], pos: &mut usize, finished: bool) -> Result<Step, DeJsonErr> {
+        let lexed = match input[*pos] {
+            b'{' => {
+                self.consume(input, pos, *pos + 1);
+                self.stack.push(Nest::Object);
+                self.expect = Expect::ObjectFirst;
+                return Ok(Step::Event(JsonEvent::ObjectStart))
+            }
+            b'[' => {
+                self.consume(input, pos, *pos + 1);
+                self.stack.push(Nest::Array);
+                self.expect = Expect::ArrayFirst;
+                return Ok(Step::Event(JsonEvent::ArrayStart))
+            }
+            b'"' => self.lex_string(input, *pos, finished) ?.map( | (s, end) | (JsonEvent::String(s), end)),
+            b'-' | b'0'..=b'9' => self.lex_number(input, *pos, finished) ?,
+            b't' => self.lex_literal(input, *pos, finished, "true", JsonEvent::Bool(true)) ?,
+            b'f' => self.lex_literal(input, *pos, finished, "false", JsonEvent::Bool(false)) ?,
+            b'n' => self.lex_literal(input, *pos, finished, "null", JsonEvent::Null) ?,
+            _ => return Err(self.err("Unexpected character"))
+        };
+        if let Some((event, end)) = lexed {
+            self.consume(input, pos, end);
+            self.after_value();
+            return Ok(Step::Event(event))
+        }
+        Ok(Step::NeedMore)
+    }
+
+    fn incomplete<T>(&self, finished: bool, what: &str) -> Result<Option<T>, DeJsonErr> {
+        if finished {
+            return Err(self.err(&format!("Unexpected end of input in {}", what)))
+        }
+        Ok(None)
+    }
+
+    fn lex_string(&mut self, input: &[u8], start: usize, finished: bool) -> Result<Option<(String, usize)>, DeJsonErr> {
+        let (mut out, offset) = self.partial_string.take().unwrap_or((Vec::new(), 1));
+        let mut i = start + offset;
+        loop {
+            if i >= input.len() {
+                return self.incomplete_string(finished, out, i - start)
+            }
+            match input[i] {
+                b'"' => {
+                    return match String::from_utf8(out) {
+                        Ok(s) => Ok(Some((s, i + 1))),
+                        Err(_) => Err(self.err("Invalid utf8 in string"))
+                    }
+                }
+                b'\\' => {
+                    if i + 1 >= input.len() {
+                        return self.incomplete_string(finished, out, i - start)
+                    }
+                    let c = match input[i + 1] {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            if i + 6 > input.len() {
+                                return self.incomplete_string(finished, out, i - start)
+                            }
+                            let high = self.hex4(&input[i + 2..i + 6]) ?;
+                            let c = match high {
+                                0xd800..=0xdbff => {
+                                    // a surrogate pair is written as two escapes
+                                    let low = &input[i + 6..input.len().min(i + 12)];
+                                    if (!low.is_empty() && low[0] != b'\\') || (low.len() > 1 && low[1] != b'u') {
+                                        return Err(self.err("Unpaired surrogate in string"))
+                                    }
+                                    if low.len() < 6 {
+                                        return self.incomplete_string(finished, out, i - start)
+                                    }
+                                    let low = self.hex4(&low[2..6]) ?;
+                                    if !(0xdc00..=0xdfff).contains(&low) {
+                                        return Err(self.err("Unpaired surrogate in string"))
+                                    }
+                                    i += 6;
+                                    char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).unwrap()
+                                }
+                                0xdc00..=0xdfff => return Err(self.err("Unpaired surrogate in string")),
+                                _ => char::from_u32(high).unwrap()
+                            };
+                            push_char(&mut out, c);
+                            i += 6;
+                            continue;
+                        }
+                        _ => return Err(self.err("Invalid escape in string"))
+                    };
+                    push_char(&mut out, c);
+                    i += 2;
+                }
+                c if c < 0x20 => return Err(self.err("Control character in string")),
+                c => {
+                    out.push(c);
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    // keeps what the string decoded to so far, to continue from the first byte not used yet
+    fn incomplete_string(&mut self, finished: bool, out: Vec<u8>, offset: usize) -> Result<Option<(String, usize)>, DeJsonErr> {
+        if !finished {
+            self.partial_string = Some((out, offset));
+        }
+        self.incomplete(finished, "string")
+    }
+
+    fn hex4(&self, digits: &[u8]) -> Result<u32, DeJsonErr> {
+        let mut v = 0;
+        for &d in digits {
+            let n = match d {
+                b'0'..=b'9' => d - b'0',
+                b'a'..=b'f' => d - b'a' + 10,
+                b'A'..=b'F' => d - b'A' + 10,
+                _ => return Err(self.err("Invalid \\u escape in string"))
+            };
+            v = (v << 4) | n as u32;
+        }
+        Ok(v)
+    }
+
+    fn lex_number(&self, input: &[u8], start: usize, finished: bool) -> Result<Option<(JsonEvent, usize)>, DeJsonErr> {
+        let mut end = start;
+        while end < input.len() && matches!(input[end], b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') {
+            end += 1;
+        }
+        if end >= input.len() && !finished {
+            // the number might continue in the next chunk
+            return Ok(None)
+        }
+        let text = std::str::from_utf8(&input[start..end]).unwrap();
+        if !is_json_number(text) {
+            return Err(self.err(&format!("Invalid number {}", text)))
+        }
+        let event = if text.contains(['.', 'e', 'E']) {
+            JsonEvent::F64(text.parse().unwrap())
+        }
+        else if text.starts_with('-') {
+            text.parse().map(JsonEvent::I64).unwrap_or_else( | _ | JsonEvent::F64(text.parse().unwrap()))
+        }
+        else {
+            text.parse().map(JsonEvent::U64).unwrap_or_else( | _ | JsonEvent::F64(text.parse().unwrap()))
+        };
+        Ok(Some((event, end)))
+    }
+
+    fn lex_literal(&self, input: &[u8], start: usize, finished: bool, literal: &str, event: JsonEvent) -> Result<Option<(JsonEvent, usize)>, DeJsonErr> {
+        let literal = literal.as_bytes();
+        let available = &input[start..input.len().min(start + literal.len())];
+        if available != &literal[..available.len()] {
+            return Err(self.err("Unexpected character"))
+        }
+        if available.len() < literal.len() {
+            return self.incomplete(finished, "literal")
+        }
+        Ok(Some((event, start + literal.len())))
+    }
+}
+
+fn push_char(out: &mut Vec<u8>, c: char) {
+    let mut buf = [0u8; 4];
+    out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+}
+
+// -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?
+fn is_json_number(text: &str) -> bool {
+    let b = text.as_bytes();
+    let mut i = 0;
+    let digits = | i: &mut usize | {
+        let start = *i;
+        while *i < b.len() && b[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+    if i < b.len() && b[i] == b'-' {
+        i += 1;
+    }
+    let int_start = i;
+    let n = digits(&mut i);
+    if n == 0 || (n > 1 && b[int_start] == b'0') {
+        return false
+    }
+    if i < b.len() && b[i] == b'.' {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false
+        }
+    }
+    if i < b.len() && (b[i] == b'e' || b[i] == b'E') {
+        i += 1;
+        if i < b.len() && (b[i] == b'+' || b[i] == b'-') {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false
+        }
+    }
+    i == b.len()
+}
+
+// pull parser over a complete document
+pub struct JsonReader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    parser: JsonParser,
+    failed: bool,
+}
+
+impl<'a> JsonReader<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {input: input.as_bytes(), pos: 0, parser: JsonParser::new(), failed: false}
+    }
+
+    // the next event, or None at the end of the input
+    pub fn next_event(&mut self) -> Result<Option<JsonEvent>, DeJsonErr> {
+        match self.parser.step(self.input, &mut self.pos, true) {
+            Ok(Step::Event(event)) => Ok(Some(event)),
+            Ok(Step::NeedMore) | Ok(Step::End) => Ok(None),
+            Err(err) => {
+                self.failed = true;
+                Err(err)
+            }
+        }
+    }
+
+    // the number of objects and arrays we are in
+    pub fn depth(&self) -> usize {
+        self.parser.stack.len()
+    }
+
+    // reads the next value as a whole, for picking a part out of a larger document
+    pub fn read_value(&mut self) -> Result<JsonValue, DeJsonErr> {
+        let first = self.expect_event() ?;
+        self.read_value_from(first)
+    }
+
+    fn expect_event(&mut self) -> Result<JsonEvent, DeJsonErr> {
+        match self.next_event() ? {
+            Some(event) => Ok(event),
+            None => Err(self.parser.err("Unexpected end of input"))
+        }
+    }
+
+    fn read_value_from(&mut self, first: JsonEvent) -> Result<JsonValue, DeJsonErr> {
+        Ok(match first {
+            JsonEvent::ObjectStart => {
+                let mut h = HashMap::new();
+                loop {
+                    match self.expect_event() ? {
+                        JsonEvent::ObjectEnd => break,
+                        JsonEvent::Key(key) => {
+                            let first = self.expect_event() ?;
+                            h.insert(key, self.read_value_from(first) ?);
+                        }
+                        _ => return Err(self.parser.err("Expected a key"))
+                    }
+                }
+                JsonValue::Object(h)
+            }
+            JsonEvent::ArrayStart => {
+                let mut out = Vec::new();
+                loop {
+                    match self.expect_event() ? {
+                        JsonEvent::ArrayEnd => break,
+                        first => out.push(self.read_value_from(first) ?)
+                    }
+                }
+                JsonValue::Array(out)
+            }
+            JsonEvent::String(v) => JsonValue::String(v),
+            JsonEvent::U64(v) => JsonValue::U64(v),
+            JsonEvent::I64(v) => JsonValue::I64(v),
+            JsonEvent::F64(v) => JsonValue::F64(v),
+            JsonEvent::Bool(v) => JsonValue::Bool(v),
+            JsonEvent::Null => JsonValue::Null,
+            JsonEvent::Key(_) | JsonEvent::ObjectEnd | JsonEvent::ArrayEnd => return Err(self.parser.err("Expected a value"))
+        })
+    }
+
+    // skips over the next value, including everything nested in it
+    pub fn skip_value(&mut self) -> Result<(), DeJsonErr> {
+        let depth = self.depth();
+        loop {
+            match self.next_event() ? {
+                Some(JsonEvent::Key(_)) => (),
+                Some(_) if self.depth() == depth => return Ok(()),
+                Some(_) => (),
+                None => return Err(self.parser.err("Unexpected end of input"))
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for JsonReader<'a> {
+    type Item = Result<JsonEvent, DeJsonErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None
+        }
+        self.next_event().transpose()
+    }
+}
+
+// pull parser over input that arrives in chunks, for instance a streaming http body.
+// Chunks can split the input anywhere, also inside a string or a multibyte character
+pub struct JsonChunkReader {
+    buf: Vec<u8>,
+    pos: usize,
+    finished: bool,
+    parser: JsonParser,
+}
+
+impl Default for JsonChunkReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JsonChunkReader {
+    pub fn new() -> Self {
+        Self {buf: Vec::new(), pos: 0, finished: false, parser: JsonParser::new()}
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        // drop what we already parsed so the buffer only holds the unread tail
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        self.buf.extend_from_slice(chunk);
+    }
+
+    // marks the end of the input, after which a truncated document is an error
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    // the next event, or None when the input fed so far is used up
+    pub fn next_event(&mut self) -> Result<Option<JsonEvent>, DeJsonErr> {
+        match self.parser.step(&self.buf, &mut self.pos, self.finished) ? {
+            Step::Event(event) => Ok(Some(event)),
+            Step::NeedMore | Step::End => Ok(None)
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.parser.stack.len()
+    }
+
+    // true when we are in between top level values
+    pub fn is_complete(&self) -> bool {
+        self.parser.is_complete()
+    }
+}
+
+// streaming writer, commas and colons are put in automatically. Call take to hand off
+// what was written so far, for instance to send it out before the document is done
+pub struct JsonWriter {
+    pub out: String,
+    stack: Vec<(Nest, bool)>,
+    after_key: bool,
+    top_level_values: usize,
+}
+
+impl Default for JsonWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JsonWriter {
+    pub fn new() -> Self {
+        Self {out: String::new(), stack: Vec::new(), after_key: false, top_level_values: 0}
+    }
+
+    pub fn take(&mut self) -> String {
+        std::mem::take(&mut self.out)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn before_value(&mut self) {
+        match self.stack.last_mut() {
+            Some((Nest::Array, has_items)) => {
+                if *has_items {
+                    self.out.push(',');
+                }
+                *has_items = true;
+            }
+            Some((Nest::Object, _)) => {
+                if !self.after_key {
+                    panic!("JsonWriter: a value in an object needs a key first");
+                }
+                self.after_key = false;
+            }
+            None => {
+                // top level values go on their own line
+                if self.top_level_values > 0 {
+                    self.out.push('\n');
+                }
+                self.top_level_values += 1;
+            }
+        }
+    }
+
+    fn end(&mut self, nest: Nest, c: char) {
+        match self.stack.pop() {
+            Some((top, _)) if top == nest && !self.after_key => self.out.push(c),
+            _ => panic!("JsonWriter: unbalanced {}", c)
+        }
+    }
+
+    pub fn begin_object(&mut self) {
+        self.before_value();
+        self.out.push('{');
+        self.stack.push((Nest::Object, false));
+    }
+
+    pub fn end_object(&mut self) {
+        self.end(Nest::Object, '}');
+    }
+
+    pub fn begin_array(&mut self) {
+        self.before_value();
+        self.out.push('[');
+        self.stack.push((Nest::Array, false));
+    }
+
+    pub fn end_array(&mut self) {
+        self.end(Nest::Array, ']');
+    }
+
+    pub fn key(&mut self, key: &str) {
+        match self.stack.last_mut() {
+            Some((Nest::Object, has_items)) if !self.after_key => {
+                if *has_items {
+                    self.out.push(',');
+                }
+                *has_items = true;
+            }
+            _ => panic!("JsonWriter: keys can only be written in an object")
+        }
+        write_json_string(&mut self.out, key);
+        self.out.push(':');
+        self.after_key = true;
+    }
+
+    pub fn string(&mut self, v: &str) {
+        self.before_value();
+        write_json_string(&mut self.out, v);
+    }
+
+    pub fn u64(&mut self, v: u64) {
+        self.before_value();
+        self.out.push_str(&v.to_string());
+    }
+
+    pub fn i64(&mut self, v: i64) {
+        self.before_value();
+        self.out.push_str(&v.to_string());
+    }
+
+    pub fn f64(&mut self, v: f64) {
+        self.before_value();
+        if v.is_finite() {
+            self.out.push_str(&v.to_string());
+        }
+        else {
+            self.out.push_str("null");
+        }
+    }
+
+    pub fn bool(&mut self, v: bool) {
+        self.before_value();
+        self.out.push_str(if v {"true"} else {"false"});
+    }
+
+    pub fn null(&mut self) {
+        self.before_value();
+        self.out.push_str("null");
+    }
+
+    // writes a whole value with its SerJson implementation
+    pub fn value<T>(&mut self, v: &T) where T: SerJson + ?Sized {
+        self.before_value();
+        let mut s = SerJsonState {out: std::mem::take(&mut self.out)};
+        v.ser_json(0, &mut s);
+        self.out = s.out;
+    }
+
+    // writes an event from a reader, so a document can be filtered while it streams through
+    pub fn event(&mut self, event: &JsonEvent) {
+        match event {
+            JsonEvent::ObjectStart => self.begin_object(),
+            JsonEvent::ObjectEnd => self.end_object(),
+            JsonEvent::ArrayStart => self.begin_array(),
+            JsonEvent::ArrayEnd => self.end_array(),
+            JsonEvent::Key(v) => self.key(v),
+            JsonEvent::String(v) => self.string(v),
+            JsonEvent::U64(v) => self.u64(*v),
+            JsonEvent::I64(v) => self.i64(*v),
+            JsonEvent::F64(v) => self.f64(*v),
+            JsonEvent::Bool(v) => self.bool(*v),
+            JsonEvent::Null => self.null(),
+        }
+    }
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+}
@@ -7,4 +7,7 @@ mod serde_json;
 pub use crate::serde_json::*;
 
 mod serde_ron;
-pub use crate::serde_ron::*;
\ No newline at end of file
+pub use crate::serde_ron::*;
+
+mod json_stream;
+pub use crate::json_stream::*;
\ No newline at end of file
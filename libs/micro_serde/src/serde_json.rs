@@ -15,6 +15,10 @@ impl SerJsonState {
     }
     
     pub fn field(&mut self, d: usize, field: &str) {
+        // every field but the first one directly follows a value
+        if !self.out.ends_with('{') {
+            self.conl();
+        }
         self.indent(d);
         self.out.push('"');
         self.out.push_str(field);
@@ -41,6 +45,22 @@ impl SerJsonState {
         self.out.push('}');
     }
     
+    // writes the fields of an object value into the object we are writing, used by #[flatten]
+    pub fn flatten<T>(&mut self, d: usize, value: &T) where T: SerJson + ?Sized {
+        let mut s = SerJsonState {
+            out: String::new()
+        };
+        value.ser_json(d, &mut s);
+        if let Some(fields) = s.out.strip_prefix('{').and_then( | v | v.strip_suffix('}')) {
+            if !fields.is_empty() {
+                if !self.out.ends_with('{') {
+                    self.conl();
+                }
+                self.out.push_str(fields);
+            }
+        }
+    }
+    
 }
 
 pub trait SerJson {
@@ -71,7 +91,7 @@ pub trait DeJson: Sized {
     DeJsonErr>;
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 #[derive(Default)]
 pub enum DeJsonTok {
     Str,
@@ -93,7 +113,7 @@ pub enum DeJsonTok {
     Eof
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct DeJsonState {
     pub cur: char,
     pub tok: DeJsonTok,
@@ -165,6 +185,29 @@ impl DeJsonState {
         DeJsonErr{msg:format!("Cannot parse {} ", what), line:self.line, col:self.col}
     }
     
+    // looks ahead for the string value of key in the object at the cursor, without consuming anything
+    pub fn peek_tag(&self, i: &Chars, key: &str) -> Result<String, DeJsonErr> {
+        let mut s = self.clone();
+        let mut i = i.clone();
+        s.curly_open(&mut i) ?;
+        while s.next_str().is_some() {
+            let k = s.as_string() ?;
+            s.next_colon(&mut i) ?;
+            if k == key {
+                return s.as_string()
+            }
+            JsonValue::de_json(&mut s, &mut i) ?;
+            s.eat_comma_curly(&mut i) ?;
+        }
+        Err(self.err_nf(key))
+    }
+    
+    // deserializes the keys an object did not know itself, used by #[flatten]
+    pub fn de_flat<T>(&self, fields: HashMap<String, JsonValue>) -> Result<T, DeJsonErr> where T: DeJson {
+        let json = JsonValue::Object(fields).serialize_json();
+        T::deserialize_json(&json).map_err( | e | self.err_msg(&e.msg))
+    }
+    
     pub fn eat_comma_block(&mut self, i: &mut Chars) -> Result<(), DeJsonErr> {
         match self.tok {
             DeJsonTok::Comma => {
@@ -926,15 +969,14 @@ impl<K, V> SerJson for HashMap<K, V> where K: SerJson,
 V: SerJson {
     fn ser_json(&self, d: usize, s: &mut SerJsonState) {
         s.out.push('{');
-        let last = self.len() - 1;
         for (index, (k, v)) in self.iter().enumerate() {
+            if index != 0 {
+                s.conl();
+            }
             s.indent(d + 1);
             k.ser_json(d + 1, s);
             s.out.push(':');
             v.ser_json(d + 1, s);
-            if index != last{
-                s.conl();
-            }
         }
         s.indent(d);
         s.out.push('}');
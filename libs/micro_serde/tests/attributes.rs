@@ -0,0 +1,182 @@
+use makepad_micro_serde::*;
+use std::collections::HashMap;
+
+fn default_model() -> String {
+    "gpt".to_string()
+}
+
+#[derive(SerJson, DeJson, SerRon, DeRon, SerBin, DeBin, Debug, PartialEq)]
+struct Request {
+    #[rename = "type"]
+    kind: String,
+    #[default(default_model())]
+    model: String,
+    #[default]
+    max_tokens: u32,
+    #[skip]
+    cached: u32,
+    stop: Option<Vec<String>>,
+}
+
+#[test]
+fn rename_default_skip() {
+    let request = Request {kind: "chat".into(), model: "gpt".into(), max_tokens: 4, cached: 7, stop: None};
+    let json = request.serialize_json();
+    assert_eq!(json, r#"{"type":"chat","model":"gpt","max_tokens":4}"#);
+
+    let parsed = Request::deserialize_json(r#"{"type":"chat"}"#).unwrap();
+    assert_eq!(parsed, Request {kind: "chat".into(), model: "gpt".into(), max_tokens: 0, cached: 0, stop: None});
+    assert!(Request::deserialize_json(r#"{"model":"x"}"#).is_err());
+    assert!(Request::deserialize_json(r#"{"type":"chat","cached":1}"#).is_err());
+
+    let ron = request.serialize_ron();
+    let parsed = Request::deserialize_ron(&ron).unwrap();
+    assert_eq!(parsed.kind, "chat");
+    assert_eq!(parsed.cached, 0);
+
+    let bin = request.serialize_bin();
+    let parsed = Request::deserialize_bin(&bin).unwrap();
+    assert_eq!(parsed.max_tokens, 4);
+    assert_eq!(parsed.cached, 0);
+}
+
+#[test]
+fn option_fields() {
+    let with_stop = Request {kind: "a".into(), model: "b".into(), max_tokens: 1, cached: 0, stop: Some(vec!["x".into()])};
+    let json = with_stop.serialize_json();
+    assert_eq!(json, r#"{"type":"a","model":"b","max_tokens":1,"stop":["x"]}"#);
+    assert_eq!(Request::deserialize_json(&json).unwrap(), with_stop);
+    let parsed = Request::deserialize_json(r#"{"type":"a","stop":null}"#).unwrap();
+    assert_eq!(parsed.stop, None);
+}
+
+#[derive(SerJson, DeJson, Debug, PartialEq)]
+struct Usage {
+    prompt_tokens: u32,
+    total_tokens: u32,
+}
+
+#[derive(SerJson, DeJson, Debug, PartialEq)]
+struct Response {
+    id: String,
+    #[flatten]
+    usage: Usage,
+}
+
+#[derive(SerJson, DeJson, Debug)]
+struct Extra {
+    id: String,
+    #[flatten]
+    rest: HashMap<String, JsonValue>,
+}
+
+#[test]
+fn flatten() {
+    let response = Response {id: "r".into(), usage: Usage {prompt_tokens: 1, total_tokens: 2}};
+    let json = response.serialize_json();
+    assert_eq!(json, r#"{"id":"r","prompt_tokens":1,"total_tokens":2}"#);
+    let parsed = Response::deserialize_json(r#"{"total_tokens":2,"id":"r","prompt_tokens":1}"#).unwrap();
+    assert_eq!(parsed, response);
+    assert!(Response::deserialize_json(r#"{"id":"r","prompt_tokens":1}"#).is_err());
+
+    let extra = Extra::deserialize_json(r#"{"id":"x","a":1,"b":"c"}"#).unwrap();
+    assert_eq!(extra.rest.len(), 2);
+    assert_eq!(extra.rest.get("b").and_then( | v | v.string()).map( | v | v.as_str()), Some("c"));
+    let empty = Extra {id: "x".into(), rest: HashMap::new()};
+    assert_eq!(empty.serialize_json(), r#"{"id":"x"}"#);
+}
+
+#[derive(SerJson, DeJson, Debug, PartialEq)]
+#[tag = "type"]
+enum Internal {
+    #[rename = "text"]
+    Text {text: String},
+    Usage(Usage),
+    Stop,
+}
+
+#[test]
+fn internally_tagged() {
+    let text = Internal::Text {text: "hi".into()};
+    assert_eq!(text.serialize_json(), r#"{"type":"text","text":"hi"}"#);
+    assert_eq!(Internal::deserialize_json(r#"{"text":"hi","type":"text"}"#).unwrap(), text);
+
+    let usage = Internal::Usage(Usage {prompt_tokens: 1, total_tokens: 2});
+    let json = usage.serialize_json();
+    assert_eq!(json, r#"{"type":"Usage","prompt_tokens":1,"total_tokens":2}"#);
+    assert_eq!(Internal::deserialize_json(&json).unwrap(), usage);
+
+    assert_eq!(Internal::Stop.serialize_json(), r#"{"type":"Stop"}"#);
+    assert_eq!(Internal::deserialize_json(r#"{"type":"Stop"}"#).unwrap(), Internal::Stop);
+    assert!(Internal::deserialize_json(r#"{"type":"Other"}"#).is_err());
+    assert!(Internal::deserialize_json(r#"{"text":"hi"}"#).is_err());
+}
+
+#[derive(SerJson, DeJson, Debug, PartialEq)]
+#[tag = "t"]
+#[content = "c"]
+enum Adjacent {
+    Value(u32),
+    Pair(u32, String),
+    Point {x: i32, y: i32},
+    Empty,
+}
+
+#[test]
+fn adjacently_tagged() {
+    let cases = [
+        (Adjacent::Value(1), r#"{"t":"Value","c":1}"#),
+        (Adjacent::Pair(1, "a".into()), r#"{"t":"Pair","c":[1,"a"]}"#),
+        (Adjacent::Point {x: 1, y: -2}, r#"{"t":"Point","c":{"x":1,"y":-2}}"#),
+        (Adjacent::Empty, r#"{"t":"Empty"}"#),
+    ];
+    for (value, json) in cases {
+        assert_eq!(value.serialize_json(), json);
+        assert_eq!(Adjacent::deserialize_json(json).unwrap(), value);
+    }
+    assert_eq!(Adjacent::deserialize_json(r#"{"c":5,"t":"Value"}"#).unwrap(), Adjacent::Value(5));
+    assert!(Adjacent::deserialize_json(r#"{"t":"Value"}"#).is_err());
+}
+
+#[derive(SerJson, DeJson, Debug, PartialEq)]
+#[untagged]
+enum Untagged {
+    Number(u32),
+    Text(String),
+    Point {x: i32, y: i32},
+    Nothing,
+}
+
+#[test]
+fn untagged() {
+    let cases = [
+        (Untagged::Number(3), r#"3"#),
+        (Untagged::Text("a".into()), r#""a""#),
+        (Untagged::Point {x: 1, y: 2}, r#"{"x":1,"y":2}"#),
+        (Untagged::Nothing, r#"null"#),
+    ];
+    for (value, json) in cases {
+        assert_eq!(value.serialize_json(), json);
+        assert_eq!(Untagged::deserialize_json(json).unwrap(), value);
+    }
+    let list: Vec<Untagged> = DeJson::deserialize_json(r#"[1,"b",{"x":3,"y":4},null]"#).unwrap();
+    assert_eq!(list.len(), 4);
+    assert_eq!(list[2], Untagged::Point {x: 3, y: 4});
+    assert!(Untagged::deserialize_json(r#"true"#).is_err());
+}
+
+#[derive(SerJson, DeJson, SerRon, DeRon, Debug, PartialEq)]
+enum External {
+    #[rename = "one"]
+    One,
+    Two {#[default] a: u32, b: u32},
+}
+
+#[test]
+fn external_rename() {
+    assert_eq!(External::One.serialize_json(), r#"{"one":[]}"#);
+    assert_eq!(External::deserialize_json(r#"{"one":[]}"#).unwrap(), External::One);
+    assert_eq!(External::deserialize_json(r#"{"Two":{"b":2}}"#).unwrap(), External::Two {a: 0, b: 2});
+    let ron = External::Two {a: 1, b: 2}.serialize_ron();
+    assert_eq!(External::deserialize_ron(&ron).unwrap(), External::Two {a: 1, b: 2});
+}
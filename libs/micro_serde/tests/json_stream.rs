@@ -0,0 +1,181 @@
+use makepad_micro_serde::*;
+
+fn events(json: &str) -> Vec<JsonEvent> {
+    JsonReader::new(json).collect::<Result<Vec<_>, _>>().unwrap()
+}
+
+#[test]
+fn reader_events() {
+    use JsonEvent::*;
+    assert_eq!(events(r#" {"a": [1, -2, 3.5, 1e2], "b": {"c": null, "d": true}, "e": "x\ny\u00e9\ud83d\ude00"} "#), vec![
+        ObjectStart,
+        Key("a".into()), ArrayStart, U64(1), I64(-2), F64(3.5), F64(100.0), ArrayEnd,
+        Key("b".into()), ObjectStart, Key("c".into()), Null, Key("d".into()), Bool(true), ObjectEnd,
+        Key("e".into()), String("x\nyé😀".into()),
+        ObjectEnd,
+    ]);
+    assert_eq!(events("[] {} 7"), vec![ArrayStart, ArrayEnd, ObjectStart, ObjectEnd, U64(7)]);
+    assert_eq!(events("18446744073709551616"), vec![F64(18446744073709551616.0)]);
+}
+
+#[test]
+fn reader_errors() {
+    for json in [r#"{"a" 1}"#, "[1,]", "[1 2]", "{1:2}", "01", "[", r#""abc"#, "tru", "[}", "\"\\x\""] {
+        assert!(JsonReader::new(json).any( | e | e.is_err()), "{} should fail", json);
+    }
+    let err = JsonReader::new("[1,\n  x]").find_map( | e | e.err()).unwrap();
+    assert_eq!((err.line, err.col), (1, 2));
+}
+
+#[test]
+fn reader_pull() {
+    let mut reader = JsonReader::new(r#"{"id":"a","big":{"x":[1,2,{"y":3}]},"choices":[{"delta":{"content":"hi"}}]}"#);
+    assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::ObjectStart));
+    let mut content = None;
+    while let Some(event) = reader.next_event().unwrap() {
+        match event {
+            JsonEvent::Key(key) if key == "choices" => {
+                let choices = reader.read_value().unwrap();
+                if let JsonValue::Array(choices) = choices {
+                    content = choices[0].key("delta").and_then( | d | d.key("content")).and_then( | c | c.string()).cloned();
+                }
+            }
+            JsonEvent::Key(_) => reader.skip_value().unwrap(),
+            JsonEvent::ObjectEnd => break,
+            _ => panic!("unexpected event")
+        }
+    }
+    assert_eq!(content.as_deref(), Some("hi"));
+    assert_eq!(reader.depth(), 0);
+    assert_eq!(reader.next_event().unwrap(), None);
+}
+
+#[test]
+fn chunk_reader() {
+    let json = r#"{"text":"héllo \"wörld\" 😀","n":[12345,-6.5e-1,true,false,null]}"#;
+    let expected = events(json);
+    // every possible split of the input into single bytes
+    let mut reader = JsonChunkReader::new();
+    let mut got = Vec::new();
+    for b in json.as_bytes() {
+        reader.feed(&[*b]);
+        while let Some(event) = reader.next_event().unwrap() {
+            got.push(event);
+        }
+    }
+    reader.finish();
+    while let Some(event) = reader.next_event().unwrap() {
+        got.push(event);
+    }
+    assert_eq!(got, expected);
+    assert!(reader.is_complete());
+
+    // a number at the end of a chunk is only complete once we know nothing follows
+    let mut reader = JsonChunkReader::new();
+    reader.feed(b"12");
+    assert_eq!(reader.next_event().unwrap(), None);
+    reader.feed(b"3 ");
+    assert_eq!(reader.next_event().unwrap(), Some(JsonEvent::U64(123)));
+
+    let mut reader = JsonChunkReader::new();
+    reader.feed(b"{\"a\":");
+    while reader.next_event().unwrap().is_some() {}
+    reader.finish();
+    assert!(reader.next_event().is_err());
+}
+
+#[test]
+fn surrogates() {
+    assert_eq!(events(r#""😀𝄞""#), vec![JsonEvent::String("😀𝄞".into())]);
+    for (json, msg) in [
+        // a high surrogate followed by something other than a low one
+        (r#""\ud83dA""#, "Unpaired surrogate in string"),
+        (r#""\ud83d\ud83d""#, "Unpaired surrogate in string"),
+        // a high surrogate on its own
+        (r#""\ud83dx""#, "Unpaired surrogate in string"),
+        (r#""\ud83d""#, "Unpaired surrogate in string"),
+        (r#""\ud83d\n""#, "Unpaired surrogate in string"),
+        // a low surrogate on its own
+        (r#""\ude00""#, "Unpaired surrogate in string"),
+        (r#""x\udfff\ud83d""#, "Unpaired surrogate in string"),
+        (r#""\ud83d\ude0""#, "Invalid \\u escape in string"),
+    ] {
+        let err = JsonReader::new(json).find_map( | e | e.err());
+        assert_eq!(err.map( | e | e.msg), Some(msg.to_string()), "{}", json);
+    }
+
+    // the same errors when the escapes arrive split over chunks
+    for json in [r#""\ud83dA""#, r#""\ud83dx""#, r#""\ude00""#] {
+        let mut reader = JsonChunkReader::new();
+        let mut failed = false;
+        for b in json.as_bytes() {
+            reader.feed(&[*b]);
+            if reader.next_event().is_err() {
+                failed = true;
+                break
+            }
+        }
+        assert!(failed, "{} should fail", json);
+    }
+}
+
+#[test]
+fn chunk_reader_long_string() {
+    // the string is only lexed once however it is split, so this stays fast
+    let text = "abc\\u00e9\\ud83d\\ude00\\n".repeat(50000);
+    let json = format!("[\"{}\"]", text);
+    let mut reader = JsonChunkReader::new();
+    let mut got = Vec::new();
+    for chunk in json.as_bytes().chunks(7) {
+        reader.feed(chunk);
+        while let Some(event) = reader.next_event().unwrap() {
+            got.push(event);
+        }
+    }
+    assert_eq!(got, vec![
+        JsonEvent::ArrayStart,
+        JsonEvent::String("abcé😀\n".repeat(50000)),
+        JsonEvent::ArrayEnd,
+    ]);
+}
+
+#[test]
+fn writer() {
+    let mut w = JsonWriter::new();
+    w.begin_object();
+    w.key("a");
+    w.begin_array();
+    w.u64(1);
+    w.i64(-2);
+    w.f64(0.5);
+    w.f64(f64::NAN);
+    w.end_array();
+    let head = w.take();
+    w.key("b\"");
+    w.string("line\n\u{1}");
+    w.key("c");
+    w.value(&vec![true, false]);
+    w.key("d");
+    w.null();
+    w.end_object();
+    w.bool(true);
+    let json = format!("{}{}", head, w.out);
+    assert_eq!(json, "{\"a\":[1,-2,0.5,null],\"b\\\"\":\"line\\n\\u0001\",\"c\":[true,false],\"d\":null}\ntrue");
+    assert_eq!(w.depth(), 0);
+
+    // piping reader events through a writer gives back the same document
+    let input = r#"{"x":[1,{"y":"z"},[]],"w":{}}"#;
+    let mut w = JsonWriter::new();
+    for event in JsonReader::new(input) {
+        w.event(&event.unwrap());
+    }
+    assert_eq!(w.out, input);
+}
+
+#[test]
+#[should_panic]
+fn writer_needs_key() {
+    let mut w = JsonWriter::new();
+    w.begin_object();
+    w.u64(1);
+}
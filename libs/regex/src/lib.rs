@@ -0,0 +1,7 @@
+pub mod regex;
+pub mod search;
+
+pub use self::{
+    regex::{Captures, Regex, RegexError},
+    search::{SearchOptions, SearchQuery},
+};
@@ -0,0 +1,837 @@
+use std::{fmt, ops::Range};
+
+/// A small regular expression engine, used by search.
+///
+/// Supports literals, `.`, character classes (`[a-z]`, `[^...]`), the escapes `\d \w \s \D \W \S`
+/// and `\b \B`, the anchors `^ $`, capturing and non-capturing (`(?:...)`) groups, alternation and
+/// the greedy and lazy quantifiers `* + ? {m} {m,} {m,n}`. Patterns are matched against a single
+/// line at a time.
+///
+/// Patterns are compiled to a small program that is run as a Pike VM: all alternatives are
+/// tried in lockstep in a single pass over the haystack, so matching never backtracks or
+/// recurses, and takes time proportional to the length of the haystack times the size of the
+/// program.
+#[derive(Clone, Debug)]
+pub struct Regex {
+    program: Vec<Inst>,
+    group_count: usize,
+    case_insensitive: bool,
+}
+
+/// The largest program a pattern may compile to. Counted quantifiers copy their operand, so
+/// something like `(a{1000}){1000}` is rejected rather than compiled.
+const MAX_PROGRAM_LEN: usize = 100_000;
+
+impl Regex {
+    pub fn new(pattern: &str, case_insensitive: bool) -> Result<Self, RegexError> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            index: 0,
+            group_count: 0,
+        };
+        let node = parser.parse_alternation()?;
+        if parser.index < parser.chars.len() {
+            return Err(parser.error("unmatched `)`"));
+        }
+        Ok(Self {
+            program: Compiler::compile(&node)?,
+            group_count: parser.group_count,
+            case_insensitive,
+        })
+    }
+
+    /// Creates a regex that matches `string` literally.
+    pub fn literal(string: &str, case_insensitive: bool) -> Self {
+        let node = Node::Concat(string.chars().map(Node::Char).collect());
+        Self {
+            // A literal has no quantifiers, so it can't exceed the program limit.
+            program: Compiler::compile(&node).unwrap(),
+            group_count: 0,
+            case_insensitive,
+        }
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.group_count
+    }
+
+    /// Returns the captures of the leftmost match in `haystack` that starts at or after the byte
+    /// index `start`.
+    pub fn find_at(&self, haystack: &str, start: usize) -> Option<Captures> {
+        self.run(haystack, start, false)
+    }
+
+    /// Returns the captures of a match in `haystack` that starts exactly at the byte index
+    /// `start`.
+    pub fn match_at(&self, haystack: &str, start: usize) -> Option<Captures> {
+        self.run(haystack, start, true)
+    }
+
+    fn run(&self, haystack: &str, start: usize, is_anchored: bool) -> Option<Captures> {
+        let mut threads = Threads::new(self.program.len());
+        let mut next_threads = Threads::new(self.program.len());
+        let mut matched = None;
+        let mut index = start;
+        loop {
+            // Threads that start further to the right have a lower priority than the ones already
+            // running, and once something matched, no later start can be leftmost anymore.
+            if matched.is_none() && (index == start || !is_anchored) {
+                let slots = vec![None; 2 * (self.group_count + 1)];
+                self.add_thread(&mut threads, 0, haystack, index, slots);
+            }
+            // In an unanchored search, a start that failed right away doesn't end the search.
+            if threads.list.is_empty() && (matched.is_some() || is_anchored) {
+                break;
+            }
+            let char = haystack[index..].chars().next();
+            for (pc, slots) in threads.list.drain(..) {
+                let next_index = index + char.map_or(0, char::len_utf8);
+                match self.program[pc] {
+                    Inst::Match => {
+                        matched = Some(slots);
+                        // Every thread after this one has a lower priority.
+                        break;
+                    }
+                    Inst::Char(expected) => {
+                        if char.is_some_and(|char| self.chars_eq(expected, char)) {
+                            self.add_thread(&mut next_threads, pc + 1, haystack, next_index, slots);
+                        }
+                    }
+                    Inst::Any => {
+                        if char.is_some() {
+                            self.add_thread(&mut next_threads, pc + 1, haystack, next_index, slots);
+                        }
+                    }
+                    Inst::Class(ref class) => {
+                        if char.is_some_and(|char| class.matches(char, self.case_insensitive)) {
+                            self.add_thread(&mut next_threads, pc + 1, haystack, next_index, slots);
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            let Some(char) = char else {
+                break;
+            };
+            index += char.len_utf8();
+            std::mem::swap(&mut threads, &mut next_threads);
+            next_threads.clear();
+        }
+        let slots = matched?;
+        Some(Captures {
+            groups: slots
+                .chunks(2)
+                .map(|slots| Some(slots[0]?..slots[1]?))
+                .collect(),
+        })
+    }
+
+    /// Follows the jumps, splits, saves and assertions from `pc` at `index`, and adds each thread
+    /// that ends up on an instruction that consumes a char, or on `Match`, to `threads` in
+    /// priority order.
+    fn add_thread(
+        &self,
+        threads: &mut Threads,
+        pc: usize,
+        haystack: &str,
+        index: usize,
+        slots: Slots,
+    ) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((pc, mut slots)) = stack.pop() {
+            // A pc that was already reached at this index was reached by a thread with a higher
+            // priority. This is also what stops empty loops like `(a*)*` from running forever.
+            if std::mem::replace(&mut threads.is_visited[pc], true) {
+                continue;
+            }
+            match self.program[pc] {
+                Inst::Jump(target) => stack.push((target, slots)),
+                Inst::Split(first, second) => {
+                    stack.push((second, slots.clone()));
+                    stack.push((first, slots));
+                }
+                Inst::Save(slot) => {
+                    slots[slot] = Some(index);
+                    stack.push((pc + 1, slots));
+                }
+                Inst::LineStart => {
+                    if index == 0 {
+                        stack.push((pc + 1, slots));
+                    }
+                }
+                Inst::LineEnd => {
+                    if index == haystack.len() {
+                        stack.push((pc + 1, slots));
+                    }
+                }
+                Inst::WordBoundary { is_negated } => {
+                    let is_word_before = haystack[..index]
+                        .chars()
+                        .next_back()
+                        .is_some_and(is_word_char);
+                    let is_word_after = haystack[index..].chars().next().is_some_and(is_word_char);
+                    if (is_word_before != is_word_after) != is_negated {
+                        stack.push((pc + 1, slots));
+                    }
+                }
+                Inst::Char(_) | Inst::Any | Inst::Class(_) | Inst::Match => {
+                    threads.list.push((pc, slots))
+                }
+            }
+        }
+    }
+
+    fn chars_eq(&self, expected: char, actual: char) -> bool {
+        expected == actual || self.case_insensitive && fold_case(expected) == fold_case(actual)
+    }
+}
+
+/// The byte ranges matched by a regex and each of its capturing groups.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Captures {
+    groups: Vec<Option<Range<usize>>>,
+}
+
+impl Captures {
+    pub fn range(&self) -> Range<usize> {
+        self.groups[0].clone().unwrap()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Range<usize>> {
+        self.groups.get(index).cloned().flatten()
+    }
+
+    /// Expands `replacement` for this match, replacing `$0` through `$9` with the text of the
+    /// corresponding group. A literal `$` can be written as `$$`.
+    pub fn expand(&self, haystack: &str, replacement: &str) -> String {
+        let mut string = String::new();
+        let mut chars = replacement.chars().peekable();
+        while let Some(char) = chars.next() {
+            if char != '$' {
+                string.push(char);
+                continue;
+            }
+            match chars.peek().copied() {
+                Some('$') => {
+                    chars.next();
+                    string.push('$');
+                }
+                Some(digit @ '0'..='9') => {
+                    chars.next();
+                    if let Some(range) = self.get(digit as usize - '0' as usize) {
+                        string.push_str(&haystack[range]);
+                    }
+                }
+                _ => string.push('$'),
+            }
+        }
+        string
+    }
+}
+
+/// The start and end index of each group, group `i` uses slots `2 * i` and `2 * i + 1`.
+type Slots = Vec<Option<usize>>;
+
+/// The threads of the Pike VM at a single index into the haystack, in priority order.
+struct Threads {
+    list: Vec<(usize, Slots)>,
+    is_visited: Vec<bool>,
+}
+
+impl Threads {
+    fn new(program_len: usize) -> Self {
+        Self {
+            list: Vec::new(),
+            is_visited: vec![false; program_len],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.is_visited.fill(false);
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    LineStart,
+    LineEnd,
+    WordBoundary {
+        is_negated: bool,
+    },
+    /// Records the current index in a slot.
+    Save(usize),
+    /// Continues at both targets, preferring the first.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn compile(node: &Node) -> Result<Vec<Inst>, RegexError> {
+        let mut compiler = Compiler {
+            program: vec![Inst::Save(0)],
+        };
+        compiler.compile_node(node)?;
+        compiler.program.push(Inst::Save(1));
+        compiler.program.push(Inst::Match);
+        Ok(compiler.program)
+    }
+
+    fn compile_node(&mut self, node: &Node) -> Result<(), RegexError> {
+        match *node {
+            Node::Empty => {}
+            Node::Char(char) => self.program.push(Inst::Char(char)),
+            Node::Any => self.program.push(Inst::Any),
+            Node::Class(ref class) => self.program.push(Inst::Class(class.clone())),
+            Node::LineStart => self.program.push(Inst::LineStart),
+            Node::LineEnd => self.program.push(Inst::LineEnd),
+            Node::WordBoundary { is_negated } => {
+                self.program.push(Inst::WordBoundary { is_negated })
+            }
+            Node::Group {
+                ref node,
+                group_index,
+            } => match group_index {
+                Some(group_index) => {
+                    self.program.push(Inst::Save(2 * group_index));
+                    self.compile_node(node)?;
+                    self.program.push(Inst::Save(2 * group_index + 1));
+                }
+                None => self.compile_node(node)?,
+            },
+            Node::Concat(ref nodes) => {
+                for node in nodes {
+                    self.compile_node(node)?;
+                }
+            }
+            Node::Alternation(ref nodes) => {
+                let mut jumps = Vec::new();
+                let (last, nodes) = nodes.split_last().unwrap();
+                for node in nodes {
+                    let split = self.push_placeholder();
+                    self.compile_node(node)?;
+                    jumps.push(self.push_placeholder());
+                    self.program[split] = Inst::Split(split + 1, self.program.len());
+                }
+                self.compile_node(last)?;
+                for jump in jumps {
+                    self.program[jump] = Inst::Jump(self.program.len());
+                }
+            }
+            Node::Repeat {
+                ref node,
+                min,
+                max,
+                is_greedy,
+            } => {
+                for _ in 0..min {
+                    self.compile_repeated_node(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.push_placeholder();
+                        self.compile_repeated_node(node)?;
+                        self.program.push(Inst::Jump(split));
+                        self.program[split] = self.split(split + 1, self.program.len(), is_greedy);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in min..max {
+                            splits.push(self.push_placeholder());
+                            self.compile_repeated_node(node)?;
+                        }
+                        for split in splits {
+                            self.program[split] =
+                                self.split(split + 1, self.program.len(), is_greedy);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_repeated_node(&mut self, node: &Node) -> Result<(), RegexError> {
+        if self.program.len() > MAX_PROGRAM_LEN {
+            return Err(RegexError {
+                index: 0,
+                message: "pattern too large",
+            });
+        }
+        self.compile_node(node)
+    }
+
+    fn push_placeholder(&mut self) -> usize {
+        self.program.push(Inst::Match);
+        self.program.len() - 1
+    }
+
+    fn split(&self, repeat: usize, exit: usize, is_greedy: bool) -> Inst {
+        if is_greedy {
+            Inst::Split(repeat, exit)
+        } else {
+            Inst::Split(exit, repeat)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RegexError {
+    pub index: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at character {}", self.message, self.index)
+    }
+}
+
+impl std::error::Error for RegexError {}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    LineStart,
+    LineEnd,
+    WordBoundary {
+        is_negated: bool,
+    },
+    Group {
+        node: Box<Node>,
+        group_index: Option<usize>,
+    },
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        is_greedy: bool,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Class {
+    is_negated: bool,
+    items: Vec<ClassItem>,
+}
+
+impl Class {
+    fn matches(&self, char: char, case_insensitive: bool) -> bool {
+        let matches = self.items.iter().any(|item| {
+            item.matches(char)
+                || case_insensitive
+                    && (char.to_lowercase().any(|char| item.matches(char))
+                        || char.to_uppercase().any(|char| item.matches(char)))
+        });
+        matches != self.is_negated
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ClassItem {
+    Range(char, char),
+    Digit { is_negated: bool },
+    Word { is_negated: bool },
+    Space { is_negated: bool },
+}
+
+impl ClassItem {
+    fn matches(self, char: char) -> bool {
+        match self {
+            Self::Range(start, end) => (start..=end).contains(&char),
+            Self::Digit { is_negated } => char.is_ascii_digit() != is_negated,
+            Self::Word { is_negated } => is_word_char(char) != is_negated,
+            Self::Space { is_negated } => char.is_whitespace() != is_negated,
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    group_count: usize,
+}
+
+impl Parser {
+    fn parse_alternation(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = vec![self.parse_concat()?];
+        while self.peek() == Some('|') {
+            self.index += 1;
+            nodes.push(self.parse_concat()?);
+        }
+        Ok(if nodes.len() == 1 {
+            nodes.pop().unwrap()
+        } else {
+            Node::Alternation(nodes)
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = Vec::new();
+        while let Some(char) = self.peek() {
+            if char == '|' || char == ')' {
+                break;
+            }
+            let node = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(node)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn parse_quantifier(&mut self, node: Node) -> Result<Node, RegexError> {
+        let start = self.index;
+        let (min, max) = match self.peek() {
+            Some('*') => {
+                self.index += 1;
+                (0, None)
+            }
+            Some('+') => {
+                self.index += 1;
+                (1, None)
+            }
+            Some('?') => {
+                self.index += 1;
+                (0, Some(1))
+            }
+            Some('{') => match self.parse_counted_quantifier() {
+                Some((min, max)) => (min, max),
+                None => {
+                    // Not a valid quantifier, so treat the `{` as a literal.
+                    self.index = start;
+                    return Ok(node);
+                }
+            },
+            _ => return Ok(node),
+        };
+        if matches!(
+            node,
+            Node::Empty | Node::LineStart | Node::LineEnd | Node::WordBoundary { .. }
+        ) {
+            return Err(RegexError {
+                index: start,
+                message: "nothing to repeat",
+            });
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err(RegexError {
+                index: start,
+                message: "invalid repetition count",
+            });
+        }
+        let is_greedy = if self.peek() == Some('?') {
+            self.index += 1;
+            false
+        } else {
+            true
+        };
+        let node = Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            is_greedy,
+        };
+        if matches!(self.peek(), Some('*' | '+' | '?')) {
+            return Err(self.error("nothing to repeat"));
+        }
+        Ok(node)
+    }
+
+    fn parse_counted_quantifier(&mut self) -> Option<(usize, Option<usize>)> {
+        self.index += 1;
+        let min = self.parse_number()?;
+        let max = if self.peek() == Some(',') {
+            self.index += 1;
+            if self.peek() == Some('}') {
+                None
+            } else {
+                Some(self.parse_number()?)
+            }
+        } else {
+            Some(min)
+        };
+        if self.peek() != Some('}') {
+            return None;
+        }
+        self.index += 1;
+        Some((min, max))
+    }
+
+    fn parse_number(&mut self) -> Option<usize> {
+        let start = self.index;
+        while self.peek().is_some_and(|char| char.is_ascii_digit()) {
+            self.index += 1;
+        }
+        self.chars[start..self.index]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, RegexError> {
+        let char = self.next().unwrap();
+        Ok(match char {
+            '.' => Node::Any,
+            '^' => Node::LineStart,
+            '$' => Node::LineEnd,
+            '(' => {
+                let group_index = if self.chars[self.index..].starts_with(&['?', ':']) {
+                    self.index += 2;
+                    None
+                } else {
+                    self.group_count += 1;
+                    Some(self.group_count)
+                };
+                let node = self.parse_alternation()?;
+                if self.next() != Some(')') {
+                    return Err(self.error("unclosed group"));
+                }
+                Node::Group {
+                    node: Box::new(node),
+                    group_index,
+                }
+            }
+            '[' => Node::Class(self.parse_class()?),
+            '*' | '+' | '?' => {
+                return Err(RegexError {
+                    index: self.index - 1,
+                    message: "nothing to repeat",
+                })
+            }
+            '\\' => match self.next() {
+                Some('b') => Node::WordBoundary { is_negated: false },
+                Some('B') => Node::WordBoundary { is_negated: true },
+                Some(char) => match class_escape(char) {
+                    Some(item) => Node::Class(Class {
+                        is_negated: false,
+                        items: vec![item],
+                    }),
+                    None => Node::Char(char_escape(char)),
+                },
+                None => return Err(self.error("trailing `\\`")),
+            },
+            char => Node::Char(char),
+        })
+    }
+
+    fn parse_class(&mut self) -> Result<Class, RegexError> {
+        let is_negated = if self.peek() == Some('^') {
+            self.index += 1;
+            true
+        } else {
+            false
+        };
+        let mut items = Vec::new();
+        let mut is_first = true;
+        loop {
+            let char = match self.next() {
+                Some(']') if !is_first => break,
+                Some(char) => char,
+                None => return Err(self.error("unclosed character class")),
+            };
+            is_first = false;
+            let start = if char == '\\' {
+                let Some(char) = self.next() else {
+                    return Err(self.error("unclosed character class"));
+                };
+                if let Some(item) = class_escape(char) {
+                    items.push(item);
+                    continue;
+                }
+                char_escape(char)
+            } else {
+                char
+            };
+            if self.peek() == Some('-')
+                && self
+                    .chars
+                    .get(self.index + 1)
+                    .is_some_and(|&char| char != ']')
+            {
+                self.index += 1;
+                let end = match self.next().unwrap() {
+                    '\\' => match self.next() {
+                        Some(char) => char_escape(char),
+                        None => return Err(self.error("unclosed character class")),
+                    },
+                    char => char,
+                };
+                if end < start {
+                    return Err(self.error("invalid character class range"));
+                }
+                items.push(ClassItem::Range(start, end));
+            } else {
+                items.push(ClassItem::Range(start, start));
+            }
+        }
+        Ok(Class { is_negated, items })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.index += 1;
+        Some(char)
+    }
+
+    fn error(&self, message: &'static str) -> RegexError {
+        RegexError {
+            index: self.index,
+            message,
+        }
+    }
+}
+
+pub fn is_word_char(char: char) -> bool {
+    char.is_alphanumeric() || char == '_'
+}
+
+fn fold_case(char: char) -> char {
+    char.to_lowercase().next().unwrap_or(char)
+}
+
+fn class_escape(char: char) -> Option<ClassItem> {
+    Some(match char {
+        'd' => ClassItem::Digit { is_negated: false },
+        'D' => ClassItem::Digit { is_negated: true },
+        'w' => ClassItem::Word { is_negated: false },
+        'W' => ClassItem::Word { is_negated: true },
+        's' => ClassItem::Space { is_negated: false },
+        'S' => ClassItem::Space { is_negated: true },
+        _ => return None,
+    })
+}
+
+fn char_escape(char: char) -> char {
+    match char {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        char => char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, haystack: &str) -> Option<Range<usize>> {
+        Some(
+            Regex::new(pattern, false)
+                .unwrap()
+                .find_at(haystack, 0)?
+                .range(),
+        )
+    }
+
+    #[test]
+    fn finds_leftmost_match() {
+        assert_eq!(find("b+", "aabbbc"), Some(2..5));
+        assert_eq!(find("a|ab", "ab"), Some(0..1));
+        assert_eq!(find("ab|a", "ab"), Some(0..2));
+        assert_eq!(find("a+?", "aaa"), Some(0..1));
+        assert_eq!(find("a{2,3}", "aaaa"), Some(0..3));
+        assert_eq!(find("a{2,3}?", "aaaa"), Some(0..2));
+        assert_eq!(find("^b", "ab"), None);
+        assert_eq!(find("b$", "ab"), Some(1..2));
+        assert_eq!(find(r"\bfoo\b", "foobar foo"), Some(7..10));
+        assert_eq!(find("[^a-c]", "abcd"), Some(3..4));
+        assert_eq!(find("é.", "aébc"), Some(1..4));
+        assert_eq!(find("x", "abc"), None);
+    }
+
+    #[test]
+    fn matches_case_insensitively() {
+        let regex = Regex::new("hello [a-z]+", true).unwrap();
+        assert_eq!(regex.find_at("Say HELLO World", 0).unwrap().range(), 4..15);
+        assert_eq!(
+            Regex::literal("ÄB", true)
+                .find_at("xäb", 0)
+                .unwrap()
+                .range(),
+            1..4
+        );
+    }
+
+    #[test]
+    fn captures_groups() {
+        let regex = Regex::new(r"(\w+)@(\w+)(?:\.(com))?", false).unwrap();
+        let haystack = "mail me@example now";
+        let captures = regex.find_at(haystack, 0).unwrap();
+        assert_eq!(captures.range(), 5..15);
+        assert_eq!(captures.get(1), Some(5..7));
+        assert_eq!(captures.get(2), Some(8..15));
+        assert_eq!(captures.get(3), None);
+        assert_eq!(captures.expand(haystack, "$2 at $1 $$"), "example at me $");
+        // A repeated group captures its last iteration.
+        let captures = Regex::new("(a|b)+", false)
+            .unwrap()
+            .find_at("abab", 0)
+            .unwrap();
+        assert_eq!(captures.get(1), Some(3..4));
+    }
+
+    #[test]
+    fn matches_at_exact_start() {
+        let regex = Regex::new("b+", false).unwrap();
+        assert_eq!(regex.match_at("abb", 0), None);
+        assert_eq!(regex.match_at("abb", 1).unwrap().range(), 1..3);
+        assert_eq!(regex.find_at("abbab", 3).unwrap().range(), 4..5);
+    }
+
+    #[test]
+    fn terminates_on_empty_loops() {
+        assert_eq!(find("(a?)*", "aa"), Some(0..2));
+        assert_eq!(find("(a*)*b", "aab"), Some(0..3));
+        assert_eq!(find("(|a)+b", "ab"), Some(0..2));
+    }
+
+    #[test]
+    fn matches_long_lines_without_overflowing_the_stack() {
+        let haystack = format!("a{}", "x".repeat(20_000));
+        assert_eq!(find("a.*", &haystack), Some(0..20_001));
+        assert_eq!(find("(?:x|y)+$", &haystack), Some(1..20_001));
+    }
+
+    #[test]
+    fn matches_in_linear_time() {
+        // These take exponential or quadratic time with a backtracking matcher.
+        let haystack = "a".repeat(10_000);
+        assert_eq!(find("(a*)*b", &haystack), None);
+        assert_eq!(find("(a|aa)+b", &haystack), None);
+        assert_eq!(find("a*b", &haystack), None);
+        assert_eq!(find("a*b", &format!("{}b", haystack)), Some(0..10_001));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(Regex::new("(a", false).is_err());
+        assert!(Regex::new("a)", false).is_err());
+        assert!(Regex::new("*a", false).is_err());
+        assert!(Regex::new("[b-a]", false).is_err());
+        assert_eq!(
+            Regex::new("(a{1000}){1000}", false).unwrap_err().message,
+            "pattern too large"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use {
+    crate::regex::{self, Captures, Regex, RegexError},
+    std::ops::Range,
+};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub regex: bool,
+}
+
+/// A compiled search query. Matches never span more than a single line.
+#[derive(Clone, Debug)]
+pub struct SearchQuery {
+    pattern: String,
+    options: SearchOptions,
+    regex: Regex,
+}
+
+impl SearchQuery {
+    pub fn new(pattern: &str, options: SearchOptions) -> Result<Self, RegexError> {
+        let regex = if options.regex {
+            Regex::new(pattern, !options.case_sensitive)?
+        } else {
+            Regex::literal(pattern, !options.case_sensitive)
+        };
+        Ok(Self {
+            pattern: pattern.to_string(),
+            options,
+            regex,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn options(&self) -> SearchOptions {
+        self.options
+    }
+
+    /// Returns the byte range of every non-empty match in `line`, in order.
+    pub fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        if self.pattern.is_empty() {
+            return ranges;
+        }
+        let mut byte_index = 0;
+        while byte_index <= line.len() {
+            let Some(captures) = self.regex.find_at(line, byte_index) else {
+                break;
+            };
+            let range = captures.range();
+            if !range.is_empty() && self.is_whole_word(line, range.clone()) {
+                byte_index = range.end;
+                ranges.push(range);
+            } else {
+                match line[range.start..].chars().next() {
+                    Some(char) => byte_index = range.start + char.len_utf8(),
+                    None => break,
+                }
+            }
+        }
+        ranges
+    }
+
+    /// Returns the text that should replace the match at `range` in `line`. In regex mode, `$0`
+    /// through `$9` in `replacement` refer to the groups of the match.
+    pub fn replacement_for(&self, line: &str, range: Range<usize>, replacement: &str) -> String {
+        if !self.options.regex {
+            return replacement.to_string();
+        }
+        match self.captures_at(line, range) {
+            Some(captures) => captures.expand(line, replacement),
+            None => replacement.to_string(),
+        }
+    }
+
+    /// Replaces every match in `line`, and returns the new line and the number of replaced
+    /// matches.
+    pub fn replace_in_line(&self, line: &str, replacement: &str) -> (String, usize) {
+        let ranges = self.find_in_line(line);
+        let mut new_line = String::new();
+        let mut byte_index = 0;
+        for range in &ranges {
+            new_line.push_str(&line[byte_index..range.start]);
+            new_line.push_str(&self.replacement_for(line, range.clone(), replacement));
+            byte_index = range.end;
+        }
+        new_line.push_str(&line[byte_index..]);
+        (new_line, ranges.len())
+    }
+
+    fn captures_at(&self, line: &str, range: Range<usize>) -> Option<Captures> {
+        let captures = self.regex.match_at(line, range.start)?;
+        if captures.range() == range {
+            return Some(captures);
+        }
+        None
+    }
+
+    fn is_whole_word(&self, line: &str, range: Range<usize>) -> bool {
+        if !self.options.whole_word {
+            return true;
+        }
+        let is_word_before = line[..range.start]
+            .chars()
+            .next_back()
+            .is_some_and(regex::is_word_char);
+        let is_word_after = line[range.end..]
+            .chars()
+            .next()
+            .is_some_and(regex::is_word_char);
+        !is_word_before && !is_word_after
+    }
+}
@@ -89,6 +89,41 @@ pub fn shell_env_cap_split(env: &[(&str, &str)], cwd: &Path, cmd: &str, args: &[
     (stdout, stderr, r.status.success())
 }
 
+pub fn shell_env_cap_split_stdin(env: &[(&str, &str)], cwd: &Path, cmd: &str, args: &[&str], stdin: &str) -> (String, String, bool) {
+    let mut cmd_build = Command::new(cmd);
+        
+    cmd_build.args(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .current_dir(cwd);
+            
+    for (key, value) in env {
+        cmd_build.env(key, value);
+    }
+    let child = cmd_build.spawn(); 
+    if let Err(e) = child{
+        return ("".to_string(),format!("Cannot start process {}", e), false);
+    }
+    let mut child = child.unwrap();
+    // write from a thread, so a process that fills its output pipes before reading all of its
+    // input can't deadlock us
+    let mut child_stdin = child.stdin.take().expect("stdin cannot be taken!");
+    let stdin = stdin.to_string();
+    let stdin_thread = std::thread::spawn(move || {
+        let _ = child_stdin.write_all(stdin.as_bytes());
+    });
+    let r = child.wait_with_output();
+    let _ = stdin_thread.join();
+    if let Err(e) = r{
+        return ("".to_string(),format!("Wait with output failed for process {}", e), false);
+    }
+    let r = r.unwrap();
+    let stderr = std::str::from_utf8(&r.stderr).unwrap_or("could not decode utf8").to_string();
+    let stdout = std::str::from_utf8(&r.stdout).unwrap_or("could not decode utf8").to_string();
+    (stdout, stderr, r.status.success())
+}
+
 pub fn shell_env_filter(start:&str, minus:Vec<String>, env: &[(&str, &str)], cwd: &Path, cmd: &str,  args: &[&str]) -> Result<(), String> {
 
     let mut cmd_build = Command::new(cmd);
@@ -4,7 +4,8 @@ use {
         code,
         code::{
             BinOpInfo, BlockType, CompiledCode, InstrSlot, InstrVisitor, LoadInfo, MemArg,
-            StoreInfo, UnOpInfo, UncompiledCode,
+            SimdLaneOpInfo, SimdMemLaneOpInfo, SimdMemOpInfo, SimdOpInfo, StoreInfo, UnOpInfo,
+            UncompiledCode,
         },
         decode::DecodeError,
         exec,
@@ -28,6 +29,7 @@ pub(crate) struct Compiler {
     blocks: Vec<Block>,
     opds: Vec<Opd>,
     fixup_idxs: Vec<usize>,
+    fuel_idxs: Vec<usize>,
 }
 
 impl Compiler {
@@ -38,6 +40,7 @@ impl Compiler {
             blocks: Vec::new(),
             opds: Vec::new(),
             fixup_idxs: Vec::new(),
+            fuel_idxs: Vec::new(),
         }
     }
 
@@ -54,6 +57,7 @@ impl Compiler {
         self.blocks.clear();
         self.opds.clear();
         self.fixup_idxs.clear();
+        self.fuel_idxs.clear();
 
         let type_ = func.type_(store);
         let locals = &mut self.locals;
@@ -78,6 +82,8 @@ impl Compiler {
             blocks: &mut self.blocks,
             opds: &mut self.opds,
             fixup_idxs: &mut self.fixup_idxs,
+            fuel_idxs: &mut self.fuel_idxs,
+            consume_fuel: store.engine().config().consume_fuel,
             first_param_result_stack_idx: -(type_.call_frame_size() as isize),
             first_temp_stack_idx: local_count,
             max_stack_height: local_count,
@@ -97,9 +103,11 @@ impl Compiler {
                 .mem(0)
                 .map(|mem| mem.to_unguarded(store.id())),
         );
+        compile.emit_consume_fuel();
 
         let mut decoder = Decoder::new(&code.expr);
         while !compile.blocks.is_empty() {
+            compile.count_instr();
             code::decode_instr(&mut decoder, &mut self.label_idxs, &mut compile).unwrap();
         }
 
@@ -110,6 +118,12 @@ impl Compiler {
         }
         compile.emit(exec::return_ as ThreadedInstr);
 
+        // Now that we know the maximum stack height, we can patch it into each `consume_fuel`
+        // instruction, which needs it to save the call frame when execution is suspended.
+        for fuel_idx in compile.fuel_idxs.iter().copied() {
+            compile.code[fuel_idx + 1] = compile.max_stack_height;
+        }
+
         let mut code: AliasableBox<[InstrSlot]> = AliasableBox::from_box(Box::from(compile.code));
         for fixup_idx in compile.fixup_idxs.drain(..) {
             code[fixup_idx] += code.as_ptr() as usize;
@@ -138,6 +152,8 @@ struct Compile<'a> {
     blocks: &'a mut Vec<Block>,
     opds: &'a mut Vec<Opd>,
     fixup_idxs: &'a mut Vec<usize>,
+    fuel_idxs: &'a mut Vec<usize>,
+    consume_fuel: bool,
     first_param_result_stack_idx: isize,
     first_temp_stack_idx: usize,
     max_stack_height: usize,
@@ -254,6 +270,7 @@ impl<'a> Compile<'a> {
 
     /// Pushes a block with the given kind and type on stack.
     fn push_block(&mut self, kind: BlockKind, type_: FuncType) {
+        let fuel_idx = self.blocks.last().and_then(|block| block.fuel_idx);
         self.blocks.push(Block {
             kind,
             type_,
@@ -262,6 +279,7 @@ impl<'a> Compile<'a> {
             first_instr_idx: self.code.len(),
             first_hole_idx: None,
             else_hole_idx: None,
+            fuel_idx,
         });
 
         // Push the inputs of the block on the stack.
@@ -497,6 +515,39 @@ impl<'a> Compile<'a> {
             UnguardedVal::F64(val) => self.emit(val),
             UnguardedVal::FuncRef(val) => self.emit(val),
             UnguardedVal::ExternRef(val) => self.emit(val),
+            // A `v128` does not fit in a single slot, so we emit it as two halves.
+            UnguardedVal::V128(val) => {
+                self.emit(val as u64);
+                self.emit((val >> 64) as u64);
+            }
+        }
+    }
+
+    /// Emits a SIMD operation, and then emits its inputs and pops them from the stack.
+    ///
+    /// SIMD operations read all their inputs from the stack, so we first need to ensure that none
+    /// of the inputs is an immediate or register operand.
+    fn emit_simd_op_and_pop_inputs(&mut self, info: SimdOpInfo) {
+        let input_count = info.input_types.len();
+        for opd_depth in 0..input_count {
+            self.ensure_opd_not_imm(opd_depth);
+            self.ensure_opd_not_reg(opd_depth);
+        }
+        self.emit(info.instr);
+        for opd_depth in (0..input_count).rev() {
+            self.emit_stack_offset(self.opd_stack_idx(opd_depth));
+        }
+        for _ in 0..input_count {
+            self.pop_opd();
+        }
+    }
+
+    /// Pushes the output of a SIMD operation onto the stack (if it has one), and emits its stack
+    /// offset.
+    fn push_simd_op_output(&mut self, info: SimdOpInfo) {
+        if let Some(output_type) = info.output_type {
+            self.push_opd(output_type);
+            self.emit_stack_offset(self.opd_stack_idx(0));
         }
     }
 
@@ -542,6 +593,36 @@ impl<'a> Compile<'a> {
         self.fixup_idxs.push(self.code.len());
         self.emit(instr_idx * mem::size_of::<InstrSlot>());
     }
+
+    // Methods for fuel metering.
+
+    /// Emits a `consume_fuel` instruction for the current block, if fuel consumption is enabled.
+    ///
+    /// The instruction consumes fuel for every instruction in the current block that is compiled
+    /// after it, up to the end of the block, including those in any nested blocks that do not
+    /// emit their own `consume_fuel` instruction. We only emit this at the start of a function
+    /// and at the start of a loop, so that any unbounded execution has to pass through it.
+    fn emit_consume_fuel(&mut self) {
+        if !self.consume_fuel {
+            return;
+        }
+        self.emit(exec::consume_fuel as ThreadedInstr);
+        let fuel_idx = self.code.len();
+        // The amount of fuel to consume. This is incremented by `count_instr`.
+        self.emit(0usize);
+        // The maximum stack height. This is patched at the end of compilation.
+        self.emit(0usize);
+        self.fuel_idxs.push(fuel_idx);
+        self.block_mut(0).fuel_idx = Some(fuel_idx);
+    }
+
+    /// Adds the cost of a single instruction to the `consume_fuel` instruction for the current
+    /// block, if it has one.
+    fn count_instr(&mut self) {
+        if let Some(fuel_idx) = self.block(0).fuel_idx {
+            self.code[fuel_idx] += 1;
+        }
+    }
 }
 
 impl<'a> InstrVisitor for Compile<'a> {
@@ -613,6 +694,10 @@ impl<'a> InstrVisitor for Compile<'a> {
 
         self.push_block(BlockKind::Loop, type_);
 
+        // Every iteration of the loop consumes fuel, which ensures that loops terminate once we run
+        // out of fuel.
+        self.emit_consume_fuel();
+
         Ok(())
     }
 
@@ -1151,6 +1236,17 @@ impl<'a> InstrVisitor for Compile<'a> {
 
         let type_ = type_.unwrap_or_else(|| self.opd(1).type_);
 
+        // `v128` values are never stored in a register, so we compile a `select` on `v128` values
+        // as a SIMD operation, which reads its inputs from and writes its output to the stack.
+        if type_ == ValType::V128 {
+            return self.visit_simd_op(SimdOpInfo {
+                _name: "select_v128",
+                input_types: &[ValType::V128, ValType::V128, ValType::I32],
+                output_type: Some(ValType::V128),
+                instr: exec::select_v128,
+            });
+        }
+
         // The `select` instruction does not have any _{sri}{sri}i variants.
         //
         // For instance, the following sequence of instructions:
@@ -2049,6 +2145,137 @@ impl<'a> InstrVisitor for Compile<'a> {
 
         Ok(())
     }
+
+    // Vector instructions
+
+    /// Compiles a `v128.const` instruction.
+    fn visit_v128_const(&mut self, val: u128) -> Result<(), DecodeError> {
+        // Skip this instruction if it is unreachable.
+        if self.block(0).is_unreachable {
+            return Ok(());
+        }
+
+        // Push the output onto the stack and set its value.
+        //
+        // SIMD operations cannot read their inputs from an immediate, so we preserve the constant
+        // on the stack right away.
+        self.push_opd(ValType::V128);
+        self.opd_mut(0).val = Some(UnguardedVal::V128(val));
+        self.preserve_imm_opd(0);
+
+        Ok(())
+    }
+
+    /// Compiles an `i8x16.shuffle` instruction.
+    fn visit_i8x16_shuffle(&mut self, lane_idxs: [u8; 16]) -> Result<(), DecodeError> {
+        // Skip this instruction if it is unreachable.
+        if self.block(0).is_unreachable {
+            return Ok(());
+        }
+
+        let info = SimdOpInfo {
+            _name: "i8x16_shuffle",
+            input_types: &[ValType::V128, ValType::V128],
+            output_type: Some(ValType::V128),
+            instr: exec::i8x16_shuffle,
+        };
+
+        // Emit the instruction, and then emit the inputs and pop them from the stack.
+        self.emit_simd_op_and_pop_inputs(info);
+
+        // Emit the lane indices, as two halves.
+        let lane_idxs = u128::from_le_bytes(lane_idxs);
+        self.emit(lane_idxs as u64);
+        self.emit((lane_idxs >> 64) as u64);
+
+        // Push the output onto the stack and emit its stack offset.
+        self.push_simd_op_output(info);
+
+        Ok(())
+    }
+
+    /// Compiles a SIMD operation.
+    fn visit_simd_op(&mut self, info: SimdOpInfo) -> Result<(), DecodeError> {
+        // Skip this instruction if it is unreachable.
+        if self.block(0).is_unreachable {
+            return Ok(());
+        }
+
+        // Emit the instruction, and then emit the inputs and pop them from the stack.
+        self.emit_simd_op_and_pop_inputs(info);
+
+        // Push the output onto the stack and emit its stack offset.
+        self.push_simd_op_output(info);
+
+        Ok(())
+    }
+
+    /// Compiles a SIMD operation with a lane index.
+    fn visit_simd_lane_op(
+        &mut self,
+        lane_idx: u8,
+        info: SimdLaneOpInfo,
+    ) -> Result<(), DecodeError> {
+        // Skip this instruction if it is unreachable.
+        if self.block(0).is_unreachable {
+            return Ok(());
+        }
+
+        // Emit the instruction, and then emit the inputs and pop them from the stack.
+        self.emit_simd_op_and_pop_inputs(info.op);
+
+        // Emit the lane index.
+        self.emit(lane_idx);
+
+        // Push the output onto the stack and emit its stack offset.
+        self.push_simd_op_output(info.op);
+
+        Ok(())
+    }
+
+    /// Compiles a SIMD load or store operation.
+    fn visit_simd_mem_op(&mut self, arg: MemArg, info: SimdMemOpInfo) -> Result<(), DecodeError> {
+        // Skip this instruction if it is unreachable.
+        if self.block(0).is_unreachable {
+            return Ok(());
+        }
+
+        // Emit the instruction, and then emit the inputs and pop them from the stack.
+        self.emit_simd_op_and_pop_inputs(info.op);
+
+        // Emit the static offset.
+        self.emit(arg.offset);
+
+        // Push the output (if any) onto the stack and emit its stack offset.
+        self.push_simd_op_output(info.op);
+
+        Ok(())
+    }
+
+    /// Compiles a SIMD load or store operation with a lane index.
+    fn visit_simd_mem_lane_op(
+        &mut self,
+        arg: MemArg,
+        lane_idx: u8,
+        info: SimdMemLaneOpInfo,
+    ) -> Result<(), DecodeError> {
+        // Skip this instruction if it is unreachable.
+        if self.block(0).is_unreachable {
+            return Ok(());
+        }
+
+        // Emit the instruction, and then emit the inputs and pop them from the stack.
+        self.emit_simd_op_and_pop_inputs(info.op);
+
+        // Emit the static offset and the lane index.
+        self.emit(arg.offset);
+        self.emit(lane_idx);
+
+        // Push the output (if any) onto the stack and emit its stack offset.
+        self.push_simd_op_output(info.op);
+
+        Ok(())
+    }
 }
 
 /// A local on the stack.
@@ -2077,6 +2304,8 @@ struct Block {
     else_hole_idx: Option<usize>,
     // The index of the first hole for this block.
     first_hole_idx: Option<usize>,
+    // The index of the fuel cost of the `consume_fuel` instruction for this block, if any.
+    fuel_idx: Option<usize>,
 }
 
 impl Block {
@@ -2425,6 +2654,9 @@ fn select_select(
         // variant of this instruction that can handle this case.
         | (_, OpdKind::Reg, OpdKind::Reg, _)
         | (_, _, _, OpdKind::Imm) => panic!("no suitable instruction found"),
+
+        // A `select` on `v128` values is compiled as a SIMD operation instead.
+        (ValType::V128, _, _, _) => panic!("no suitable instruction found"),
     }
 }
 
@@ -2436,6 +2668,7 @@ fn select_global_get(type_: ValType) -> ThreadedInstr {
         ValType::F64 => exec::global_get_f64,
         ValType::FuncRef => exec::global_get_func_ref,
         ValType::ExternRef => exec::global_get_extern_ref,
+        ValType::V128 => exec::global_get_v128,
     }
 }
 
@@ -2459,6 +2692,11 @@ fn select_global_set(type_: ValType, kind: OpdKind) -> ThreadedInstr {
         (ValType::ExternRef, OpdKind::Stack) => exec::global_set_extern_ref_s,
         (ValType::ExternRef, OpdKind::Reg) => exec::global_set_extern_ref_r,
         (ValType::ExternRef, OpdKind::Imm) => exec::global_set_extern_ref_i,
+        (ValType::V128, OpdKind::Stack) => exec::global_set_v128_s,
+
+        // `v128` values are never stored in a register, and are always preserved on the stack
+        // when they are created as an immediate.
+        (ValType::V128, OpdKind::Reg | OpdKind::Imm) => panic!("no suitable instruction found"),
     }
 }
 
@@ -2585,6 +2823,7 @@ fn select_copy_imm_to_stack(type_: ValType) -> ThreadedInstr {
         ValType::F64 => exec::copy_imm_to_stack_f64,
         ValType::FuncRef => exec::copy_imm_to_stack_func_ref,
         ValType::ExternRef => exec::copy_imm_to_stack_extern_ref,
+        ValType::V128 => exec::copy_imm_to_stack_v128,
     }
 }
 
@@ -2596,6 +2835,7 @@ fn select_copy_stack(type_: ValType) -> ThreadedInstr {
         ValType::F64 => exec::copy_stack_f64,
         ValType::FuncRef => exec::copy_stack_func_ref,
         ValType::ExternRef => exec::copy_stack_extern_ref,
+        ValType::V128 => exec::copy_stack_v128,
     }
 }
 
@@ -2607,5 +2847,6 @@ fn select_copy_reg_to_stack(type_: ValType) -> ThreadedInstr {
         ValType::F64 => exec::copy_reg_to_stack_f64,
         ValType::FuncRef => exec::copy_reg_to_stack_func_ref,
         ValType::ExternRef => exec::copy_reg_to_stack_extern_ref,
+        ValType::V128 => panic!("no suitable instruction found"),
     }
 }
@@ -21,14 +21,25 @@ pub struct Engine {
 impl Engine {
     /// Creates a new [`Engine`].
     pub fn new() -> Engine {
+        Engine::with_config(EngineConfig::default())
+    }
+
+    /// Creates a new [`Engine`] with the given [`EngineConfig`].
+    pub fn with_config(config: EngineConfig) -> Engine {
         Engine {
             inner: Arc::new(EngineInner {
+                config,
                 validators: Mutex::new(Pool::new()),
                 compilers: Mutex::new(Pool::new()),
             }),
         }
     }
 
+    /// Returns the [`EngineConfig`] of this [`Engine`].
+    pub fn config(&self) -> EngineConfig {
+        self.inner.config
+    }
+
     pub(crate) fn validate(
         &self,
         type_: &FuncType,
@@ -61,8 +72,35 @@ impl Default for Engine {
     }
 }
 
+/// Configuration for an [`Engine`].
+#[derive(Clone, Copy, Debug, Default)]
+#[non_exhaustive]
+pub struct EngineConfig {
+    /// Whether Wasm code consumes fuel while it executes.
+    ///
+    /// If enabled, each function entry and each loop iteration consumes fuel from the
+    /// [`Store`] in which the code executes, one unit per instruction, and execution traps with
+    /// [`Trap::OutOfFuel`](crate::Trap::OutOfFuel) once the [`Store`] runs out of fuel. This has a
+    /// small runtime cost, so it is disabled by default.
+    pub consume_fuel: bool,
+}
+
+impl EngineConfig {
+    /// Creates a new [`EngineConfig`] with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables fuel consumption.
+    pub fn consume_fuel(mut self, consume_fuel: bool) -> Self {
+        self.consume_fuel = consume_fuel;
+        self
+    }
+}
+
 #[derive(Debug)]
 struct EngineInner {
+    config: EngineConfig,
     validators: Mutex<Pool<Validator>>,
     compilers: Mutex<Pool<Compiler>>,
 }
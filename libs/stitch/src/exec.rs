@@ -11,15 +11,16 @@ use {
         func::{Func, FuncEntity, UnguardedFunc},
         func_ref::UnguardedFuncRef,
         global::UnguardedGlobal,
-        mem::UnguardedMem,
+        mem::{MemEntity, UnguardedMem},
         ops::*,
+        simd::*,
         stack::{Stack, StackGuard, StackSlot},
-        store::{Handle, Store, UnguardedInternedFuncType},
+        store::{Handle, Store, StoreId, UnguardedHandle, UnguardedInternedFuncType},
         table::UnguardedTable,
         trap::Trap,
         val::{UnguardedVal, Val},
     },
-    std::{hint, mem, ptr},
+    std::{hint, mem, ptr, slice},
 };
 
 /// A `ThreadedInstr` is a subroutine that executes a single WebAssembly instruction.
@@ -77,7 +78,7 @@ pub(crate) type Sp = *mut StackSlot;
 pub(crate) type Md = *mut u8;
 
 /// The memory size register (`Ms`) stores the size of the current [`Memory`].
-pub(crate) type Ms = u32;
+pub(crate) type Ms = u64;
 
 /// The integer register (`Ix`) stores temporary values of integral type.
 pub(crate) type Ix = u64;
@@ -105,6 +106,9 @@ pub(crate) struct Context<'a> {
     pub(crate) ix: Ix,
     pub(crate) sx: Sx,
     pub(crate) dx: Dx,
+    // The end of the current call frame, which is where the stack in use ends. This is only set
+    // when execution is suspended.
+    pub(crate) frame_end: Sp,
 
     // A mutable reference to the store in which we're executing.
     pub(crate) store: &'a mut Store,
@@ -158,6 +162,23 @@ pub(crate) fn exec(
     args: &[Val],
     results: &mut [Val],
 ) -> Result<(), Error> {
+    match exec_resumable(store, func, args, results)? {
+        Some(_) => Err(Trap::OutOfFuel)?,
+        None => Ok(()),
+    }
+}
+
+/// Executes the given [`Func`] with the given arguments, suspending execution if the [`Store`]
+/// runs out of fuel.
+///
+/// Returns the [`SuspendedExec`] if execution was suspended. Otherwise, the results are written to
+/// the `results` slice.
+pub(crate) fn exec_resumable(
+    store: &mut Store,
+    func: Func,
+    args: &[Val],
+    results: &mut [Val],
+) -> Result<Option<SuspendedExec>, Error> {
     // Lock the stack for the current thread.
     let mut stack = Stack::lock();
 
@@ -194,15 +215,19 @@ pub(crate) fn exec(
             };
 
             // Create a trampoline for the [`WasmFuncEntity`].
-            let mut trampoline = [
+            //
+            // The trampoline is boxed, because the call frame for the function stores a pointer
+            // into it, which needs to remain valid if execution is suspended.
+            let mut trampoline = Box::new([
                 call as InstrSlot,
                 code.code.as_mut_ptr() as InstrSlot,
                 type_.call_frame_size() * mem::size_of::<StackSlot>(),
                 stop as InstrSlot,
-            ];
+            ]);
 
             // Create an execution context.
-            let mut context = Context {
+            let call_depth = store.call_depth;
+            let context = Context {
                 ip: trampoline.as_mut_ptr(),
                 sp: stack.ptr(),
                 md: ptr::null_mut(),
@@ -210,68 +235,212 @@ pub(crate) fn exec(
                 ix: 0,
                 sx: 0.0,
                 dx: 0.0,
+                frame_end: ptr::null_mut(),
                 store,
                 stack: Some(stack),
                 error: None,
             };
+            run(context, trampoline, ptr, call_depth, results)
+        }
+        FuncEntity::Host(func) => {
+            // Set the stack pointer to the end of the call frame.
+            stack.set_ptr(unsafe { ptr.add(type_.call_frame_size()) });
 
-            // Main interpreter loop
-            loop {
-                match ControlFlow::from_bits(unsafe {
-                    next_instr(
-                        context.ip,
-                        context.sp,
-                        context.md,
-                        context.ms,
-                        context.ix,
-                        context.sx,
-                        context.dx,
-                        &mut context as *mut _,
-                    )
-                })
-                .unwrap()
-                {
-                    ControlFlow::Stop => {
-                        stack = context.stack.take().unwrap();
+            // Call the [`HostTrampoline`] of the [`HostFuncEntity`].
+            stack = func.trampoline().clone().call(store, stack)?;
+
+            // Reset the stack to the start of the call frame.
+            stack.set_ptr(ptr);
 
-                        // Reset the stack to the start of the call frame.
-                        stack.set_ptr(ptr);
+            // Copy the results from the stack.
+            read_results(store, ptr, results);
 
-                        break;
-                    }
-                    ControlFlow::Trap(trap) => {
-                        stack = context.stack.take().unwrap();
+            Ok(None)
+        }
+    }
+}
 
-                        // Reset the stack to the start of the call frame.
-                        stack.set_ptr(ptr);
+/// Resumes the given [`SuspendedExec`].
+///
+/// Returns the [`SuspendedExec`] if execution was suspended again. Otherwise, the results are
+/// written to the `results` slice.
+///
+/// # Panics
+///
+/// - If the [`SuspendedExec`] was not suspended in the given [`Store`].
+/// - If the stack for the current thread is not at the same height as when the [`SuspendedExec`]
+///   was suspended.
+pub(crate) fn resume(
+    store: &mut Store,
+    state: SuspendedExec,
+    results: &mut [Val],
+) -> Result<Option<SuspendedExec>, Error> {
+    assert_eq!(state.store_id, store.id(), "store mismatch");
 
-                        return Err(trap)?;
-                    }
-                    ControlFlow::Error => {
-                        stack = context.stack.take().unwrap();
+    // Lock the stack for the current thread.
+    let mut stack = Stack::lock();
+    let ptr = state.frame_start;
+    assert_eq!(
+        stack.ptr(),
+        ptr,
+        "execution resumed at a different stack height"
+    );
+
+    // Restore the stack in use from when execution was suspended.
+    unsafe { ptr::copy_nonoverlapping(state.slots.as_ptr(), ptr, state.slots.len()) };
+
+    // Memories may have been grown while execution was suspended, so we update the pointers to
+    // their data in both the memory registers and the saved call frames.
+    let mut md = state.md;
+    let mut ms = state.ms;
+    for (mut mem, old_data) in state.mems.iter().copied() {
+        let bytes = unsafe { mem.as_mut() }.bytes_mut();
+        let new_data = bytes.as_mut_ptr();
+        let new_size = bytes.len() as Ms;
+        if md == old_data {
+            md = new_data;
+            ms = new_size;
+        }
+        unsafe { update_mem_in_call_frames(state.sp, ptr, old_data, new_data, new_size) };
+    }
 
-                        // Reset the stack to the start of the call frame.
-                        stack.set_ptr(ptr);
+    let call_depth = store.call_depth;
+    store.call_depth += state.call_depth;
+    let context = Context {
+        ip: state.ip,
+        sp: state.sp,
+        md,
+        ms,
+        ix: state.ix,
+        sx: state.sx,
+        dx: state.dx,
+        frame_end: ptr::null_mut(),
+        store,
+        stack: Some(stack),
+        error: None,
+    };
+    run(context, state.trampoline, ptr, call_depth, results)
+}
 
-                        return Err(context.error.take().unwrap());
-                    }
-                }
+/// The state of an execution that was suspended because its [`Store`] ran out of fuel.
+#[derive(Debug)]
+pub(crate) struct SuspendedExec {
+    store_id: StoreId,
+    // The trampoline that the outermost call frame returns to.
+    trampoline: Box<[InstrSlot; 4]>,
+    // The virtual registers.
+    ip: Ip,
+    sp: Sp,
+    md: Md,
+    ms: Ms,
+    ix: Ix,
+    sx: Sx,
+    dx: Dx,
+    // The start of the outermost call frame.
+    frame_start: Sp,
+    // A copy of the stack in use, starting at `frame_start`.
+    slots: Vec<StackSlot>,
+    // The number of calls in progress.
+    call_depth: usize,
+    // The memories that are referred to by the memory registers or a saved call frame, together
+    // with the pointer to their data at the time execution was suspended.
+    mems: Vec<(UnguardedHandle<MemEntity>, Md)>,
+}
+
+/// Runs the interpreter loop for the given execution context until execution stops, traps, or is
+/// suspended.
+///
+/// `frame_start` is the start of the outermost call frame, and `call_depth` is the call depth of
+/// the [`Store`] before execution started, which is restored after execution.
+fn run(
+    mut context: Context,
+    trampoline: Box<[InstrSlot; 4]>,
+    frame_start: Sp,
+    call_depth: usize,
+    results: &mut [Val],
+) -> Result<Option<SuspendedExec>, Error> {
+    let control_flow = ControlFlow::from_bits(unsafe {
+        next_instr(
+            context.ip,
+            context.sp,
+            context.md,
+            context.ms,
+            context.ix,
+            context.sx,
+            context.dx,
+            &mut context as *mut _,
+        )
+    })
+    .unwrap();
+
+    // If a host function returned an error, it has already released the stack, so we need to lock
+    // it again.
+    let mut stack = context.stack.take().unwrap_or_else(Stack::lock);
+    let store = context.store;
+
+    // Save the stack in use if execution was suspended.
+    let suspended = if let ControlFlow::Trap(Trap::OutOfFuel) = control_flow {
+        let slots = unsafe {
+            slice::from_raw_parts(
+                frame_start,
+                context.frame_end.offset_from(frame_start) as usize,
+            )
+        }
+        .to_vec();
+        let mut mems: Vec<(UnguardedHandle<MemEntity>, Md)> = Vec::new();
+        let mut add_mem = |md: Md| {
+            if md.is_null() || mems.iter().any(|&(_, data)| data == md) {
+                return;
+            }
+            if let Some(mem) = store.find_mem(md) {
+                mems.push((mem, md));
+            }
+        };
+        add_mem(context.md);
+        let mut sp = context.sp;
+        while sp != frame_start {
+            unsafe {
+                add_mem(*sp.offset(-2).cast());
+                sp = *sp.offset(-3).cast();
             }
         }
-        FuncEntity::Host(func) => {
-            // Set the stack pointer to the end of the call frame.
-            stack.set_ptr(unsafe { ptr.add(type_.call_frame_size()) });
+        Some(SuspendedExec {
+            store_id: store.id(),
+            trampoline,
+            ip: context.ip,
+            sp: context.sp,
+            md: context.md,
+            ms: context.ms,
+            ix: context.ix,
+            sx: context.sx,
+            dx: context.dx,
+            frame_start,
+            slots,
+            call_depth: store.call_depth - call_depth,
+            mems,
+        })
+    } else {
+        None
+    };
 
-            // Call the [`HostTrampoline`] of the [`HostFuncEntity`].
-            stack = func.trampoline().clone().call(store, stack)?;
+    // Reset the stack to the start of the call frame.
+    stack.set_ptr(frame_start);
+    store.call_depth = call_depth;
 
-            // Reset the stack to the start of the call frame.
-            stack.set_ptr(ptr);
+    match control_flow {
+        ControlFlow::Stop => {
+            // Copy the results from the stack.
+            read_results(store, frame_start, results);
+            Ok(None)
         }
+        ControlFlow::Trap(Trap::OutOfFuel) => Ok(suspended),
+        ControlFlow::Trap(trap) => Err(trap)?,
+        ControlFlow::Error => Err(context.error.take().unwrap()),
     }
+}
 
-    // Copy the results from the stack.
-    let mut ptr = stack.ptr();
+/// Reads the results of a call from the stack, starting at the given pointer.
+fn read_results(store: &Store, mut ptr: Sp, results: &mut [Val]) {
     for result in results.iter_mut() {
         unsafe {
             *result = Val::from_unguarded(
@@ -281,8 +450,28 @@ pub(crate) fn exec(
             ptr = ptr.add(1);
         }
     }
+}
 
-    Ok(())
+/// Updates the memory data pointer and size in each call frame on the stack, from the call frame
+/// ending at `sp` down to `frame_start`, that refers to the memory data at `old_data`.
+///
+/// # Safety
+///
+/// The stack from `frame_start` to `sp` must consist of valid call frames.
+pub(crate) unsafe fn update_mem_in_call_frames(
+    mut sp: Sp,
+    frame_start: Sp,
+    old_data: Md,
+    new_data: Md,
+    new_size: Ms,
+) {
+    while sp != frame_start {
+        if *sp.offset(-2).cast::<Md>() == old_data {
+            *sp.offset(-2).cast() = new_data;
+            *sp.offset(-1).cast() = new_size;
+        }
+        sp = *sp.offset(-3).cast();
+    }
 }
 
 // Helper macros
@@ -520,6 +709,8 @@ threaded_instr!(return_(
     dx: Dx,
     cx: Cx,
 ) -> ControlFlowBits {
+    (*(*cx).store).call_depth -= 1;
+
     // Restore call frame from stack.
     let old_sp = sp;
     let ip = *old_sp.offset(-4).cast();
@@ -595,7 +786,7 @@ threaded_instr!(call_host(
     if let Some(mut mem) = mem {
         let data = mem.as_mut().bytes_mut();
         md = data.as_mut_ptr();
-        ms = data.len() as u32;
+        ms = data.len() as Ms;
     } else {
         md = ptr::null_mut();
         ms = 0;
@@ -674,7 +865,7 @@ threaded_instr!(call_indirect(
             if let Some(mut mem) = mem {
                 let data = mem.as_mut().bytes_mut();
                 md = data.as_mut_ptr();
-                ms = data.len() as u32;
+                ms = data.len() as Ms;
             } else {
                 md = ptr::null_mut();
                 ms = 0;
@@ -1345,6 +1536,7 @@ global_get!(global_get_f32, f32);
 global_get!(global_get_f64, f64);
 global_get!(global_get_func_ref, UnguardedFuncRef);
 global_get!(global_get_extern_ref, UnguardedExternRef);
+global_get!(global_get_v128, u128);
 
 macro_rules! global_set {
     ($global_set_s:ident, $global_set_r:ident, $global_set_i:ident, $T:ty) => {
@@ -1442,6 +1634,32 @@ global_set!(
     UnguardedExternRef
 );
 
+// `v128` values are never stored in a register or an immediate, so only a stack variant exists.
+threaded_instr!(global_set_v128_s(
+    ip: Ip,
+    sp: Sp,
+    md: Md,
+    ms: Ms,
+    ix: Ix,
+    sx: Sx,
+    dx: Dx,
+    cx: Cx,
+) -> ControlFlowBits {
+    // Read operands
+    let (val, ip): (u128, _) = read_stack(ip, sp);
+    let (mut global, ip): (UnguardedGlobal, _) = read_imm(ip);
+
+    // Perform operation
+    global
+        .as_mut()
+        .downcast_mut::<u128>()
+        .unwrap_unchecked()
+        .set(val);
+
+    // Execute next instruction
+    next_instr(ip, sp, md, ms, ix, sx, dx, cx)
+});
+
 // Table instructions
 
 macro_rules! table_get {
@@ -2044,7 +2262,7 @@ macro_rules! load {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$T>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$T>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let mut bytes = [0u8; mem::size_of::<$T>()];
@@ -2074,7 +2292,7 @@ macro_rules! load {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$T>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$T>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let mut bytes = [0u8; mem::size_of::<$T>()];
@@ -2104,7 +2322,7 @@ macro_rules! load {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$T>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$T>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let mut bytes = [0u8; mem::size_of::<$T>()];
@@ -2150,7 +2368,7 @@ macro_rules! store {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$U>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$U>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let bytes = (x as $U).to_le_bytes();
@@ -2177,7 +2395,7 @@ macro_rules! store {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$U>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$U>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let bytes = (x as $U).to_le_bytes();
@@ -2204,7 +2422,7 @@ macro_rules! store {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$U>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$U>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let bytes = (x as $U).to_le_bytes();
@@ -2231,7 +2449,7 @@ macro_rules! store {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$U>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$U>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let bytes = (x as $U).to_le_bytes();
@@ -2258,7 +2476,7 @@ macro_rules! store {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$U>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$U>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let bytes = (x as $U).to_le_bytes();
@@ -2285,7 +2503,7 @@ macro_rules! store {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$U>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$U>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let bytes = (x as $U).to_le_bytes();
@@ -2312,7 +2530,7 @@ macro_rules! store {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$U>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$U>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let bytes = (x as $U).to_le_bytes();
@@ -2339,7 +2557,7 @@ macro_rules! store {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$U>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$U>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let bytes = (x as $U).to_le_bytes();
@@ -2387,7 +2605,7 @@ macro_rules! store_float {
 
             // Perform operation
             let offset = dyn_offset as u64 + static_offset as u64;
-            if offset + mem::size_of::<$U>() as u64 > ms as u64 {
+            if offset + mem::size_of::<$U>() as u64 > ms {
                 return ControlFlow::Trap(Trap::MemAccessOutOfBounds).to_bits();
             }
             let bytes = (x as $U).to_le_bytes();
@@ -2561,6 +2779,13 @@ threaded_instr!(memory_grow(
     let (count, ip): (u32, _) = read_stack(ip, sp);
     let (mut mem, ip): (UnguardedMem, _) = read_imm(ip);
 
+    // Check that the memory limit of the store is not exceeded.
+    if let Some(max_mem_pages) = (*(*cx).store).limits.max_mem_pages {
+        if count > max_mem_pages.saturating_sub(mem.as_ref().size()) {
+            return ControlFlow::Trap(Trap::MemLimitExceeded).to_bits();
+        }
+    }
+
     // Perform operation
     (*cx).stack.as_mut().unwrap_unchecked().set_ptr(sp);
     let old_size = mem
@@ -2569,7 +2794,7 @@ threaded_instr!(memory_grow(
         .unwrap_or(u32::MAX);
     let bytes = mem.as_mut().bytes_mut();
     let md = bytes.as_mut_ptr();
-    let ms = bytes.len() as u32;
+    let ms = bytes.len() as Ms;
 
     // Write result
     let ip = write_stack(ip, sp, old_size);
@@ -3817,6 +4042,569 @@ un_op!(
     <u64 as Trunc<f64>>::trunc_sat
 );
 
+// Vector instructions
+
+/// A helper macro for defining a `ThreadedInstr` for a SIMD operation.
+///
+/// SIMD operations read all their inputs from the stack, followed by any immediates, and write
+/// their output (if any) to the stack.
+macro_rules! simd_op {
+    (
+        $name:ident($($x:ident: $T:ty),+ $(; $($imm:ident: $I:ty),+)?) -> $U:ty $body:block
+    ) => {
+        threaded_instr!($name(
+            ip: Ip,
+            sp: Sp,
+            md: Md,
+            ms: Ms,
+            ix: Ix,
+            sx: Sx,
+            dx: Dx,
+            cx: Cx,
+        ) -> ControlFlowBits {
+            // Read operands
+            $(
+                let ($x, ip) = read_stack::<<$T as SimdVal>::Repr>(ip, sp);
+                let $x = <$T>::from_repr($x);
+            )+
+            $($(
+                let ($imm, ip): ($I, _) = read_imm(ip);
+            )+)?
+
+            // Perform operation
+            let y: $U = $body;
+
+            // Write result
+            let ip = write_stack(ip, sp, y.to_repr());
+
+            // Execute next instruction
+            next_instr(ip, sp, md, ms, ix, sx, dx, cx)
+        });
+    };
+}
+
+/// A helper macro for defining a `ThreadedInstr` for a SIMD operation that loads from memory.
+macro_rules! simd_load_op {
+    (
+        $name:ident(
+            $mem:ident,
+            $($x:ident: $T:ty),+;
+            $($imm:ident: $I:ty),+
+        ) -> $U:ty $body:block
+    ) => {
+        threaded_instr!($name(
+            ip: Ip,
+            sp: Sp,
+            md: Md,
+            ms: Ms,
+            ix: Ix,
+            sx: Sx,
+            dx: Dx,
+            cx: Cx,
+        ) -> ControlFlowBits {
+            // Read operands
+            $(
+                let ($x, ip) = read_stack::<<$T as SimdVal>::Repr>(ip, sp);
+                let $x = <$T>::from_repr($x);
+            )+
+            $(
+                let ($imm, ip): ($I, _) = read_imm(ip);
+            )+
+
+            // Perform operation
+            let $mem: &[u8] = slice::from_raw_parts(md, ms as usize);
+            let y: $U = r#try!($body);
+
+            // Write result
+            let ip = write_stack(ip, sp, y.to_repr());
+
+            // Execute next instruction
+            next_instr(ip, sp, md, ms, ix, sx, dx, cx)
+        });
+    };
+}
+
+/// A helper macro for defining a `ThreadedInstr` for a SIMD operation that stores to memory.
+macro_rules! simd_store_op {
+    (
+        $name:ident(
+            $mem:ident,
+            $($x:ident: $T:ty),+;
+            $($imm:ident: $I:ty),+
+        ) $body:block
+    ) => {
+        threaded_instr!($name(
+            ip: Ip,
+            sp: Sp,
+            md: Md,
+            ms: Ms,
+            ix: Ix,
+            sx: Sx,
+            dx: Dx,
+            cx: Cx,
+        ) -> ControlFlowBits {
+            // Read operands
+            $(
+                let ($x, ip) = read_stack::<<$T as SimdVal>::Repr>(ip, sp);
+                let $x = <$T>::from_repr($x);
+            )+
+            $(
+                let ($imm, ip): ($I, _) = read_imm(ip);
+            )+
+
+            // Perform operation
+            let $mem: &mut [u8] = slice::from_raw_parts_mut(md, ms as usize);
+            r#try!($body);
+
+            // Execute next instruction
+            next_instr(ip, sp, md, ms, ix, sx, dx, cx)
+        });
+    };
+}
+
+threaded_instr!(i8x16_shuffle(
+    ip: Ip,
+    sp: Sp,
+    md: Md,
+    ms: Ms,
+    ix: Ix,
+    sx: Sx,
+    dx: Dx,
+    cx: Cx,
+) -> ControlFlowBits {
+    // Read operands
+    let (x, ip) = read_stack(ip, sp);
+    let (y, ip) = read_stack(ip, sp);
+    let (lane_idxs_lo, ip): (u64, _) = read_imm(ip);
+    let (lane_idxs_hi, ip): (u64, _) = read_imm(ip);
+
+    // Perform operation
+    let lanes: [u8; 32] = concat(<[u8; 16]>::from_repr(x), <[u8; 16]>::from_repr(y));
+    let lane_idxs = <[u8; 16]>::from_repr(lane_idxs_lo as u128 | (lane_idxs_hi as u128) << 64);
+    let z = lane_idxs.map(|lane_idx| lanes[lane_idx as usize]);
+
+    // Write result
+    let ip = write_stack(ip, sp, z.to_repr());
+
+    // Execute next instruction
+    next_instr(ip, sp, md, ms, ix, sx, dx, cx)
+});
+
+simd_op!(select_v128(x0: u128, x1: u128, cond: i32) -> u128 {
+    if cond != 0 {
+        x0
+    } else {
+        x1
+    }
+});
+
+simd_load_op!(v128_load(mem, dyn_offset: u32; static_offset: u32) -> u128 {
+    load(mem, dyn_offset, static_offset).map(u128::from_le_bytes)
+});
+simd_load_op!(v128_load8x8_s(mem, dyn_offset: u32; static_offset: u32) -> [i16; 8] {
+    load_zero::<8>(mem, dyn_offset, static_offset).map(|x| extend_low(<[i8; 16]>::from_repr(x)))
+});
+simd_load_op!(v128_load8x8_u(mem, dyn_offset: u32; static_offset: u32) -> [i16; 8] {
+    load_zero::<8>(mem, dyn_offset, static_offset).map(|x| extend_low(<[u8; 16]>::from_repr(x)))
+});
+simd_load_op!(v128_load16x4_s(mem, dyn_offset: u32; static_offset: u32) -> [i32; 4] {
+    load_zero::<8>(mem, dyn_offset, static_offset).map(|x| extend_low(<[i16; 8]>::from_repr(x)))
+});
+simd_load_op!(v128_load16x4_u(mem, dyn_offset: u32; static_offset: u32) -> [i32; 4] {
+    load_zero::<8>(mem, dyn_offset, static_offset).map(|x| extend_low(<[u16; 8]>::from_repr(x)))
+});
+simd_load_op!(v128_load32x2_s(mem, dyn_offset: u32; static_offset: u32) -> [i64; 2] {
+    load_zero::<8>(mem, dyn_offset, static_offset).map(|x| extend_low(<[i32; 4]>::from_repr(x)))
+});
+simd_load_op!(v128_load32x2_u(mem, dyn_offset: u32; static_offset: u32) -> [i64; 2] {
+    load_zero::<8>(mem, dyn_offset, static_offset).map(|x| extend_low(<[u32; 4]>::from_repr(x)))
+});
+simd_load_op!(v128_load8_splat(mem, dyn_offset: u32; static_offset: u32) -> [u8; 16] {
+    load(mem, dyn_offset, static_offset).map(|bytes| [u8::from_le_bytes(bytes); 16])
+});
+simd_load_op!(v128_load16_splat(mem, dyn_offset: u32; static_offset: u32) -> [u16; 8] {
+    load(mem, dyn_offset, static_offset).map(|bytes| [u16::from_le_bytes(bytes); 8])
+});
+simd_load_op!(v128_load32_splat(mem, dyn_offset: u32; static_offset: u32) -> [u32; 4] {
+    load(mem, dyn_offset, static_offset).map(|bytes| [u32::from_le_bytes(bytes); 4])
+});
+simd_load_op!(v128_load64_splat(mem, dyn_offset: u32; static_offset: u32) -> [u64; 2] {
+    load(mem, dyn_offset, static_offset).map(|bytes| [u64::from_le_bytes(bytes); 2])
+});
+simd_store_op!(v128_store(mem, dyn_offset: u32, x: u128; static_offset: u32) {
+    store(mem, dyn_offset, static_offset, x.to_le_bytes())
+});
+simd_op!(i8x16_swizzle(x: [u8; 16], y: [u8; 16]) -> [u8; 16] {
+    y.map(|i| x.get(i as usize).copied().unwrap_or(0))
+});
+simd_op!(i8x16_splat(x: i32) -> [i8; 16] { [x as i8; 16] });
+simd_op!(i16x8_splat(x: i32) -> [i16; 8] { [x as i16; 8] });
+simd_op!(i32x4_splat(x: i32) -> [i32; 4] { [x; 4] });
+simd_op!(i64x2_splat(x: i64) -> [i64; 2] { [x; 2] });
+simd_op!(f32x4_splat(x: f32) -> [f32; 4] { [x; 4] });
+simd_op!(f64x2_splat(x: f64) -> [f64; 2] { [x; 2] });
+simd_op!(i8x16_extract_lane_s(x: [i8; 16]; lane_idx: u8) -> i32 { x[lane_idx as usize] as i32 });
+simd_op!(i8x16_extract_lane_u(x: [u8; 16]; lane_idx: u8) -> i32 { x[lane_idx as usize] as i32 });
+simd_op!(i8x16_replace_lane(x: [i8; 16], y: i32; lane_idx: u8) -> [i8; 16] {
+    replace_lane(x, lane_idx, y as i8)
+});
+simd_op!(i16x8_extract_lane_s(x: [i16; 8]; lane_idx: u8) -> i32 { x[lane_idx as usize] as i32 });
+simd_op!(i16x8_extract_lane_u(x: [u16; 8]; lane_idx: u8) -> i32 { x[lane_idx as usize] as i32 });
+simd_op!(i16x8_replace_lane(x: [i16; 8], y: i32; lane_idx: u8) -> [i16; 8] {
+    replace_lane(x, lane_idx, y as i16)
+});
+simd_op!(i32x4_extract_lane(x: [i32; 4]; lane_idx: u8) -> i32 { x[lane_idx as usize] });
+simd_op!(i32x4_replace_lane(x: [i32; 4], y: i32; lane_idx: u8) -> [i32; 4] {
+    replace_lane(x, lane_idx, y)
+});
+simd_op!(i64x2_extract_lane(x: [i64; 2]; lane_idx: u8) -> i64 { x[lane_idx as usize] });
+simd_op!(i64x2_replace_lane(x: [i64; 2], y: i64; lane_idx: u8) -> [i64; 2] {
+    replace_lane(x, lane_idx, y)
+});
+simd_op!(f32x4_extract_lane(x: [f32; 4]; lane_idx: u8) -> f32 { x[lane_idx as usize] });
+simd_op!(f32x4_replace_lane(x: [f32; 4], y: f32; lane_idx: u8) -> [f32; 4] {
+    replace_lane(x, lane_idx, y)
+});
+simd_op!(f64x2_extract_lane(x: [f64; 2]; lane_idx: u8) -> f64 { x[lane_idx as usize] });
+simd_op!(f64x2_replace_lane(x: [f64; 2], y: f64; lane_idx: u8) -> [f64; 2] {
+    replace_lane(x, lane_idx, y)
+});
+simd_op!(i8x16_eq(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { compare(x, y, |x, y| x == y) });
+simd_op!(i8x16_ne(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { compare(x, y, |x, y| x != y) });
+simd_op!(i8x16_lt_s(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { compare(x, y, |x, y| x < y) });
+simd_op!(i8x16_lt_u(x: [u8; 16], y: [u8; 16]) -> [i8; 16] { compare(x, y, |x, y| x < y) });
+simd_op!(i8x16_gt_s(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { compare(x, y, |x, y| x > y) });
+simd_op!(i8x16_gt_u(x: [u8; 16], y: [u8; 16]) -> [i8; 16] { compare(x, y, |x, y| x > y) });
+simd_op!(i8x16_le_s(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { compare(x, y, |x, y| x <= y) });
+simd_op!(i8x16_le_u(x: [u8; 16], y: [u8; 16]) -> [i8; 16] { compare(x, y, |x, y| x <= y) });
+simd_op!(i8x16_ge_s(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { compare(x, y, |x, y| x >= y) });
+simd_op!(i8x16_ge_u(x: [u8; 16], y: [u8; 16]) -> [i8; 16] { compare(x, y, |x, y| x >= y) });
+simd_op!(i16x8_eq(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { compare(x, y, |x, y| x == y) });
+simd_op!(i16x8_ne(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { compare(x, y, |x, y| x != y) });
+simd_op!(i16x8_lt_s(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { compare(x, y, |x, y| x < y) });
+simd_op!(i16x8_lt_u(x: [u16; 8], y: [u16; 8]) -> [i16; 8] { compare(x, y, |x, y| x < y) });
+simd_op!(i16x8_gt_s(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { compare(x, y, |x, y| x > y) });
+simd_op!(i16x8_gt_u(x: [u16; 8], y: [u16; 8]) -> [i16; 8] { compare(x, y, |x, y| x > y) });
+simd_op!(i16x8_le_s(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { compare(x, y, |x, y| x <= y) });
+simd_op!(i16x8_le_u(x: [u16; 8], y: [u16; 8]) -> [i16; 8] { compare(x, y, |x, y| x <= y) });
+simd_op!(i16x8_ge_s(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { compare(x, y, |x, y| x >= y) });
+simd_op!(i16x8_ge_u(x: [u16; 8], y: [u16; 8]) -> [i16; 8] { compare(x, y, |x, y| x >= y) });
+simd_op!(i32x4_eq(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { compare(x, y, |x, y| x == y) });
+simd_op!(i32x4_ne(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { compare(x, y, |x, y| x != y) });
+simd_op!(i32x4_lt_s(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { compare(x, y, |x, y| x < y) });
+simd_op!(i32x4_lt_u(x: [u32; 4], y: [u32; 4]) -> [i32; 4] { compare(x, y, |x, y| x < y) });
+simd_op!(i32x4_gt_s(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { compare(x, y, |x, y| x > y) });
+simd_op!(i32x4_gt_u(x: [u32; 4], y: [u32; 4]) -> [i32; 4] { compare(x, y, |x, y| x > y) });
+simd_op!(i32x4_le_s(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { compare(x, y, |x, y| x <= y) });
+simd_op!(i32x4_le_u(x: [u32; 4], y: [u32; 4]) -> [i32; 4] { compare(x, y, |x, y| x <= y) });
+simd_op!(i32x4_ge_s(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { compare(x, y, |x, y| x >= y) });
+simd_op!(i32x4_ge_u(x: [u32; 4], y: [u32; 4]) -> [i32; 4] { compare(x, y, |x, y| x >= y) });
+simd_op!(f32x4_eq(x: [f32; 4], y: [f32; 4]) -> [i32; 4] { compare(x, y, |x, y| x == y) });
+simd_op!(f32x4_ne(x: [f32; 4], y: [f32; 4]) -> [i32; 4] { compare(x, y, |x, y| x != y) });
+simd_op!(f32x4_lt(x: [f32; 4], y: [f32; 4]) -> [i32; 4] { compare(x, y, |x, y| x < y) });
+simd_op!(f32x4_gt(x: [f32; 4], y: [f32; 4]) -> [i32; 4] { compare(x, y, |x, y| x > y) });
+simd_op!(f32x4_le(x: [f32; 4], y: [f32; 4]) -> [i32; 4] { compare(x, y, |x, y| x <= y) });
+simd_op!(f32x4_ge(x: [f32; 4], y: [f32; 4]) -> [i32; 4] { compare(x, y, |x, y| x >= y) });
+simd_op!(f64x2_eq(x: [f64; 2], y: [f64; 2]) -> [i64; 2] { compare(x, y, |x, y| x == y) });
+simd_op!(f64x2_ne(x: [f64; 2], y: [f64; 2]) -> [i64; 2] { compare(x, y, |x, y| x != y) });
+simd_op!(f64x2_lt(x: [f64; 2], y: [f64; 2]) -> [i64; 2] { compare(x, y, |x, y| x < y) });
+simd_op!(f64x2_gt(x: [f64; 2], y: [f64; 2]) -> [i64; 2] { compare(x, y, |x, y| x > y) });
+simd_op!(f64x2_le(x: [f64; 2], y: [f64; 2]) -> [i64; 2] { compare(x, y, |x, y| x <= y) });
+simd_op!(f64x2_ge(x: [f64; 2], y: [f64; 2]) -> [i64; 2] { compare(x, y, |x, y| x >= y) });
+simd_op!(v128_not(x: u128) -> u128 { !x });
+simd_op!(v128_and(x: u128, y: u128) -> u128 { x & y });
+simd_op!(v128_andnot(x: u128, y: u128) -> u128 { x & !y });
+simd_op!(v128_or(x: u128, y: u128) -> u128 { x | y });
+simd_op!(v128_xor(x: u128, y: u128) -> u128 { x ^ y });
+simd_op!(v128_bitselect(x: u128, y: u128, z: u128) -> u128 { x & z | y & !z });
+simd_op!(v128_any_true(x: u128) -> i32 { (x != 0) as i32 });
+simd_load_op!(v128_load8_lane(
+    mem,
+    dyn_offset: u32,
+    x: [u8; 16];
+    static_offset: u32,
+    lane_idx: u8
+) -> [u8; 16] {
+    load(mem, dyn_offset, static_offset)
+        .map(|bytes| replace_lane(x, lane_idx, u8::from_le_bytes(bytes)))
+});
+simd_load_op!(v128_load16_lane(
+    mem,
+    dyn_offset: u32,
+    x: [u16; 8];
+    static_offset: u32,
+    lane_idx: u8
+) -> [u16; 8] {
+    load(mem, dyn_offset, static_offset)
+        .map(|bytes| replace_lane(x, lane_idx, u16::from_le_bytes(bytes)))
+});
+simd_load_op!(v128_load32_lane(
+    mem,
+    dyn_offset: u32,
+    x: [u32; 4];
+    static_offset: u32,
+    lane_idx: u8
+) -> [u32; 4] {
+    load(mem, dyn_offset, static_offset)
+        .map(|bytes| replace_lane(x, lane_idx, u32::from_le_bytes(bytes)))
+});
+simd_load_op!(v128_load64_lane(
+    mem,
+    dyn_offset: u32,
+    x: [u64; 2];
+    static_offset: u32,
+    lane_idx: u8
+) -> [u64; 2] {
+    load(mem, dyn_offset, static_offset)
+        .map(|bytes| replace_lane(x, lane_idx, u64::from_le_bytes(bytes)))
+});
+simd_store_op!(v128_store8_lane(
+    mem,
+    dyn_offset: u32,
+    x: [u8; 16];
+    static_offset: u32,
+    lane_idx: u8
+) {
+    store(mem, dyn_offset, static_offset, x[lane_idx as usize].to_le_bytes())
+});
+simd_store_op!(v128_store16_lane(
+    mem,
+    dyn_offset: u32,
+    x: [u16; 8];
+    static_offset: u32,
+    lane_idx: u8
+) {
+    store(mem, dyn_offset, static_offset, x[lane_idx as usize].to_le_bytes())
+});
+simd_store_op!(v128_store32_lane(
+    mem,
+    dyn_offset: u32,
+    x: [u32; 4];
+    static_offset: u32,
+    lane_idx: u8
+) {
+    store(mem, dyn_offset, static_offset, x[lane_idx as usize].to_le_bytes())
+});
+simd_store_op!(v128_store64_lane(
+    mem,
+    dyn_offset: u32,
+    x: [u64; 2];
+    static_offset: u32,
+    lane_idx: u8
+) {
+    store(mem, dyn_offset, static_offset, x[lane_idx as usize].to_le_bytes())
+});
+simd_load_op!(v128_load32_zero(mem, dyn_offset: u32; static_offset: u32) -> u128 {
+    load_zero::<4>(mem, dyn_offset, static_offset)
+});
+simd_load_op!(v128_load64_zero(mem, dyn_offset: u32; static_offset: u32) -> u128 {
+    load_zero::<8>(mem, dyn_offset, static_offset)
+});
+simd_op!(f32x4_demote_f64x2_zero(x: [f64; 2]) -> [f32; 4] { [x[0] as f32, x[1] as f32, 0.0, 0.0] });
+simd_op!(f64x2_promote_low_f32x4(x: [f32; 4]) -> [f64; 2] { [x[0] as f64, x[1] as f64] });
+simd_op!(i8x16_abs(x: [i8; 16]) -> [i8; 16] { x.map(i8::wrapping_abs) });
+simd_op!(i8x16_neg(x: [i8; 16]) -> [i8; 16] { x.map(i8::wrapping_neg) });
+simd_op!(i8x16_popcnt(x: [u8; 16]) -> [u8; 16] { x.map(|x| x.count_ones() as u8) });
+simd_op!(i8x16_all_true(x: [i8; 16]) -> i32 { x.into_iter().all(|x| x != 0) as i32 });
+simd_op!(i8x16_bitmask(x: [i8; 16]) -> i32 { bitmask(x) });
+simd_op!(i8x16_narrow_i16x8_s(x: [i16; 8], y: [i16; 8]) -> [i8; 16] {
+    concat(x, y).map(|x: i16| x.clamp(i8::MIN as i16, i8::MAX as i16) as i8)
+});
+simd_op!(i8x16_narrow_i16x8_u(x: [i16; 8], y: [i16; 8]) -> [u8; 16] {
+    concat(x, y).map(|x: i16| x.clamp(u8::MIN as i16, u8::MAX as i16) as u8)
+});
+simd_op!(f32x4_ceil(x: [f32; 4]) -> [f32; 4] { x.map(f32::ceil) });
+simd_op!(f32x4_floor(x: [f32; 4]) -> [f32; 4] { x.map(f32::floor) });
+simd_op!(f32x4_trunc(x: [f32; 4]) -> [f32; 4] { x.map(f32::trunc) });
+simd_op!(f32x4_nearest(x: [f32; 4]) -> [f32; 4] { x.map(f32::round_ties_even) });
+simd_op!(i8x16_shl(x: [i8; 16], y: i32) -> [i8; 16] { x.map(|x| x.wrapping_shl(y as u32)) });
+simd_op!(i8x16_shr_s(x: [i8; 16], y: i32) -> [i8; 16] { x.map(|x| x.wrapping_shr(y as u32)) });
+simd_op!(i8x16_shr_u(x: [u8; 16], y: i32) -> [u8; 16] { x.map(|x| x.wrapping_shr(y as u32)) });
+simd_op!(i8x16_add(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { zip(x, y, i8::wrapping_add) });
+simd_op!(i8x16_add_sat_s(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { zip(x, y, i8::saturating_add) });
+simd_op!(i8x16_add_sat_u(x: [u8; 16], y: [u8; 16]) -> [u8; 16] { zip(x, y, u8::saturating_add) });
+simd_op!(i8x16_sub(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { zip(x, y, i8::wrapping_sub) });
+simd_op!(i8x16_sub_sat_s(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { zip(x, y, i8::saturating_sub) });
+simd_op!(i8x16_sub_sat_u(x: [u8; 16], y: [u8; 16]) -> [u8; 16] { zip(x, y, u8::saturating_sub) });
+simd_op!(f64x2_ceil(x: [f64; 2]) -> [f64; 2] { x.map(f64::ceil) });
+simd_op!(f64x2_floor(x: [f64; 2]) -> [f64; 2] { x.map(f64::floor) });
+simd_op!(i8x16_min_s(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { zip(x, y, i8::min) });
+simd_op!(i8x16_min_u(x: [u8; 16], y: [u8; 16]) -> [u8; 16] { zip(x, y, u8::min) });
+simd_op!(i8x16_max_s(x: [i8; 16], y: [i8; 16]) -> [i8; 16] { zip(x, y, i8::max) });
+simd_op!(i8x16_max_u(x: [u8; 16], y: [u8; 16]) -> [u8; 16] { zip(x, y, u8::max) });
+simd_op!(f64x2_trunc(x: [f64; 2]) -> [f64; 2] { x.map(f64::trunc) });
+simd_op!(i8x16_avgr_u(x: [u8; 16], y: [u8; 16]) -> [u8; 16] {
+    zip(x, y, |x, y| ((x as u16 + y as u16 + 1) >> 1) as u8)
+});
+simd_op!(i16x8_extadd_pairwise_i8x16_s(x: [i8; 16]) -> [i16; 8] {
+    pairwise(x, |x, y| x as i16 + y as i16)
+});
+simd_op!(i16x8_extadd_pairwise_i8x16_u(x: [u8; 16]) -> [i16; 8] {
+    pairwise(x, |x, y| x as i16 + y as i16)
+});
+simd_op!(i32x4_extadd_pairwise_i16x8_s(x: [i16; 8]) -> [i32; 4] {
+    pairwise(x, |x, y| x as i32 + y as i32)
+});
+simd_op!(i32x4_extadd_pairwise_i16x8_u(x: [u16; 8]) -> [i32; 4] {
+    pairwise(x, |x, y| x as i32 + y as i32)
+});
+simd_op!(i16x8_abs(x: [i16; 8]) -> [i16; 8] { x.map(i16::wrapping_abs) });
+simd_op!(i16x8_neg(x: [i16; 8]) -> [i16; 8] { x.map(i16::wrapping_neg) });
+simd_op!(i16x8_q15mulr_sat_s(x: [i16; 8], y: [i16; 8]) -> [i16; 8] {
+    zip(x, y, |x, y| {
+        ((x as i32 * y as i32 + 0x4000) >> 15).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+    })
+});
+simd_op!(i16x8_all_true(x: [i16; 8]) -> i32 { x.into_iter().all(|x| x != 0) as i32 });
+simd_op!(i16x8_bitmask(x: [i16; 8]) -> i32 { bitmask(x) });
+simd_op!(i16x8_narrow_i32x4_s(x: [i32; 4], y: [i32; 4]) -> [i16; 8] {
+    concat(x, y).map(|x: i32| x.clamp(i16::MIN as i32, i16::MAX as i32) as i16)
+});
+simd_op!(i16x8_narrow_i32x4_u(x: [i32; 4], y: [i32; 4]) -> [u16; 8] {
+    concat(x, y).map(|x: i32| x.clamp(u16::MIN as i32, u16::MAX as i32) as u16)
+});
+simd_op!(i16x8_extend_low_i8x16_s(x: [i8; 16]) -> [i16; 8] { extend_low(x) });
+simd_op!(i16x8_extend_high_i8x16_s(x: [i8; 16]) -> [i16; 8] { extend_high(x) });
+simd_op!(i16x8_extend_low_i8x16_u(x: [u8; 16]) -> [i16; 8] { extend_low(x) });
+simd_op!(i16x8_extend_high_i8x16_u(x: [u8; 16]) -> [i16; 8] { extend_high(x) });
+simd_op!(i16x8_shl(x: [i16; 8], y: i32) -> [i16; 8] { x.map(|x| x.wrapping_shl(y as u32)) });
+simd_op!(i16x8_shr_s(x: [i16; 8], y: i32) -> [i16; 8] { x.map(|x| x.wrapping_shr(y as u32)) });
+simd_op!(i16x8_shr_u(x: [u16; 8], y: i32) -> [u16; 8] { x.map(|x| x.wrapping_shr(y as u32)) });
+simd_op!(i16x8_add(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { zip(x, y, i16::wrapping_add) });
+simd_op!(i16x8_add_sat_s(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { zip(x, y, i16::saturating_add) });
+simd_op!(i16x8_add_sat_u(x: [u16; 8], y: [u16; 8]) -> [u16; 8] { zip(x, y, u16::saturating_add) });
+simd_op!(i16x8_sub(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { zip(x, y, i16::wrapping_sub) });
+simd_op!(i16x8_sub_sat_s(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { zip(x, y, i16::saturating_sub) });
+simd_op!(i16x8_sub_sat_u(x: [u16; 8], y: [u16; 8]) -> [u16; 8] { zip(x, y, u16::saturating_sub) });
+simd_op!(f64x2_nearest(x: [f64; 2]) -> [f64; 2] { x.map(f64::round_ties_even) });
+simd_op!(i16x8_mul(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { zip(x, y, i16::wrapping_mul) });
+simd_op!(i16x8_min_s(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { zip(x, y, i16::min) });
+simd_op!(i16x8_min_u(x: [u16; 8], y: [u16; 8]) -> [u16; 8] { zip(x, y, u16::min) });
+simd_op!(i16x8_max_s(x: [i16; 8], y: [i16; 8]) -> [i16; 8] { zip(x, y, i16::max) });
+simd_op!(i16x8_max_u(x: [u16; 8], y: [u16; 8]) -> [u16; 8] { zip(x, y, u16::max) });
+simd_op!(i16x8_avgr_u(x: [u16; 8], y: [u16; 8]) -> [u16; 8] {
+    zip(x, y, |x, y| ((x as u32 + y as u32 + 1) >> 1) as u16)
+});
+simd_op!(i16x8_extmul_low_i8x16_s(x: [i8; 16], y: [i8; 16]) -> [i16; 8] {
+    zip(extend_low(x), extend_low(y), i16::wrapping_mul)
+});
+simd_op!(i16x8_extmul_high_i8x16_s(x: [i8; 16], y: [i8; 16]) -> [i16; 8] {
+    zip(extend_high(x), extend_high(y), i16::wrapping_mul)
+});
+simd_op!(i16x8_extmul_low_i8x16_u(x: [u8; 16], y: [u8; 16]) -> [i16; 8] {
+    zip(extend_low(x), extend_low(y), i16::wrapping_mul)
+});
+simd_op!(i16x8_extmul_high_i8x16_u(x: [u8; 16], y: [u8; 16]) -> [i16; 8] {
+    zip(extend_high(x), extend_high(y), i16::wrapping_mul)
+});
+simd_op!(i32x4_abs(x: [i32; 4]) -> [i32; 4] { x.map(i32::wrapping_abs) });
+simd_op!(i32x4_neg(x: [i32; 4]) -> [i32; 4] { x.map(i32::wrapping_neg) });
+simd_op!(i32x4_all_true(x: [i32; 4]) -> i32 { x.into_iter().all(|x| x != 0) as i32 });
+simd_op!(i32x4_bitmask(x: [i32; 4]) -> i32 { bitmask(x) });
+simd_op!(i32x4_extend_low_i16x8_s(x: [i16; 8]) -> [i32; 4] { extend_low(x) });
+simd_op!(i32x4_extend_high_i16x8_s(x: [i16; 8]) -> [i32; 4] { extend_high(x) });
+simd_op!(i32x4_extend_low_i16x8_u(x: [u16; 8]) -> [i32; 4] { extend_low(x) });
+simd_op!(i32x4_extend_high_i16x8_u(x: [u16; 8]) -> [i32; 4] { extend_high(x) });
+simd_op!(i32x4_shl(x: [i32; 4], y: i32) -> [i32; 4] { x.map(|x| x.wrapping_shl(y as u32)) });
+simd_op!(i32x4_shr_s(x: [i32; 4], y: i32) -> [i32; 4] { x.map(|x| x.wrapping_shr(y as u32)) });
+simd_op!(i32x4_shr_u(x: [u32; 4], y: i32) -> [u32; 4] { x.map(|x| x.wrapping_shr(y as u32)) });
+simd_op!(i32x4_add(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { zip(x, y, i32::wrapping_add) });
+simd_op!(i32x4_sub(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { zip(x, y, i32::wrapping_sub) });
+simd_op!(i32x4_mul(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { zip(x, y, i32::wrapping_mul) });
+simd_op!(i32x4_min_s(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { zip(x, y, i32::min) });
+simd_op!(i32x4_min_u(x: [u32; 4], y: [u32; 4]) -> [u32; 4] { zip(x, y, u32::min) });
+simd_op!(i32x4_max_s(x: [i32; 4], y: [i32; 4]) -> [i32; 4] { zip(x, y, i32::max) });
+simd_op!(i32x4_max_u(x: [u32; 4], y: [u32; 4]) -> [u32; 4] { zip(x, y, u32::max) });
+simd_op!(i32x4_dot_i16x8_s(x: [i16; 8], y: [i16; 8]) -> [i32; 4] {
+    pairwise(zip(x, y, |x, y| x as i32 * y as i32), i32::wrapping_add)
+});
+simd_op!(i32x4_extmul_low_i16x8_s(x: [i16; 8], y: [i16; 8]) -> [i32; 4] {
+    zip(extend_low(x), extend_low(y), i32::wrapping_mul)
+});
+simd_op!(i32x4_extmul_high_i16x8_s(x: [i16; 8], y: [i16; 8]) -> [i32; 4] {
+    zip(extend_high(x), extend_high(y), i32::wrapping_mul)
+});
+simd_op!(i32x4_extmul_low_i16x8_u(x: [u16; 8], y: [u16; 8]) -> [i32; 4] {
+    zip(extend_low(x), extend_low(y), i32::wrapping_mul)
+});
+simd_op!(i32x4_extmul_high_i16x8_u(x: [u16; 8], y: [u16; 8]) -> [i32; 4] {
+    zip(extend_high(x), extend_high(y), i32::wrapping_mul)
+});
+simd_op!(i64x2_abs(x: [i64; 2]) -> [i64; 2] { x.map(i64::wrapping_abs) });
+simd_op!(i64x2_neg(x: [i64; 2]) -> [i64; 2] { x.map(i64::wrapping_neg) });
+simd_op!(i64x2_all_true(x: [i64; 2]) -> i32 { x.into_iter().all(|x| x != 0) as i32 });
+simd_op!(i64x2_bitmask(x: [i64; 2]) -> i32 { bitmask(x) });
+simd_op!(i64x2_extend_low_i32x4_s(x: [i32; 4]) -> [i64; 2] { extend_low(x) });
+simd_op!(i64x2_extend_high_i32x4_s(x: [i32; 4]) -> [i64; 2] { extend_high(x) });
+simd_op!(i64x2_extend_low_i32x4_u(x: [u32; 4]) -> [i64; 2] { extend_low(x) });
+simd_op!(i64x2_extend_high_i32x4_u(x: [u32; 4]) -> [i64; 2] { extend_high(x) });
+simd_op!(i64x2_shl(x: [i64; 2], y: i32) -> [i64; 2] { x.map(|x| x.wrapping_shl(y as u32)) });
+simd_op!(i64x2_shr_s(x: [i64; 2], y: i32) -> [i64; 2] { x.map(|x| x.wrapping_shr(y as u32)) });
+simd_op!(i64x2_shr_u(x: [u64; 2], y: i32) -> [u64; 2] { x.map(|x| x.wrapping_shr(y as u32)) });
+simd_op!(i64x2_add(x: [i64; 2], y: [i64; 2]) -> [i64; 2] { zip(x, y, i64::wrapping_add) });
+simd_op!(i64x2_sub(x: [i64; 2], y: [i64; 2]) -> [i64; 2] { zip(x, y, i64::wrapping_sub) });
+simd_op!(i64x2_mul(x: [i64; 2], y: [i64; 2]) -> [i64; 2] { zip(x, y, i64::wrapping_mul) });
+simd_op!(i64x2_eq(x: [i64; 2], y: [i64; 2]) -> [i64; 2] { compare(x, y, |x, y| x == y) });
+simd_op!(i64x2_ne(x: [i64; 2], y: [i64; 2]) -> [i64; 2] { compare(x, y, |x, y| x != y) });
+simd_op!(i64x2_lt_s(x: [i64; 2], y: [i64; 2]) -> [i64; 2] { compare(x, y, |x, y| x < y) });
+simd_op!(i64x2_gt_s(x: [i64; 2], y: [i64; 2]) -> [i64; 2] { compare(x, y, |x, y| x > y) });
+simd_op!(i64x2_le_s(x: [i64; 2], y: [i64; 2]) -> [i64; 2] { compare(x, y, |x, y| x <= y) });
+simd_op!(i64x2_ge_s(x: [i64; 2], y: [i64; 2]) -> [i64; 2] { compare(x, y, |x, y| x >= y) });
+simd_op!(i64x2_extmul_low_i32x4_s(x: [i32; 4], y: [i32; 4]) -> [i64; 2] {
+    zip(extend_low(x), extend_low(y), i64::wrapping_mul)
+});
+simd_op!(i64x2_extmul_high_i32x4_s(x: [i32; 4], y: [i32; 4]) -> [i64; 2] {
+    zip(extend_high(x), extend_high(y), i64::wrapping_mul)
+});
+simd_op!(i64x2_extmul_low_i32x4_u(x: [u32; 4], y: [u32; 4]) -> [i64; 2] {
+    zip(extend_low(x), extend_low(y), i64::wrapping_mul)
+});
+simd_op!(i64x2_extmul_high_i32x4_u(x: [u32; 4], y: [u32; 4]) -> [i64; 2] {
+    zip(extend_high(x), extend_high(y), i64::wrapping_mul)
+});
+simd_op!(f32x4_abs(x: [f32; 4]) -> [f32; 4] { x.map(f32::abs) });
+simd_op!(f32x4_neg(x: [f32; 4]) -> [f32; 4] { x.map(|x| -x) });
+simd_op!(f32x4_sqrt(x: [f32; 4]) -> [f32; 4] { x.map(f32::sqrt) });
+simd_op!(f32x4_add(x: [f32; 4], y: [f32; 4]) -> [f32; 4] { zip(x, y, |x, y| x + y) });
+simd_op!(f32x4_sub(x: [f32; 4], y: [f32; 4]) -> [f32; 4] { zip(x, y, |x, y| x - y) });
+simd_op!(f32x4_mul(x: [f32; 4], y: [f32; 4]) -> [f32; 4] { zip(x, y, |x, y| x * y) });
+simd_op!(f32x4_div(x: [f32; 4], y: [f32; 4]) -> [f32; 4] { zip(x, y, |x, y| x / y) });
+simd_op!(f32x4_min(x: [f32; 4], y: [f32; 4]) -> [f32; 4] { zip(x, y, fmin) });
+simd_op!(f32x4_max(x: [f32; 4], y: [f32; 4]) -> [f32; 4] { zip(x, y, fmax) });
+simd_op!(f32x4_pmin(x: [f32; 4], y: [f32; 4]) -> [f32; 4] {
+    zip(x, y, |x, y| if y < x { y } else { x })
+});
+simd_op!(f32x4_pmax(x: [f32; 4], y: [f32; 4]) -> [f32; 4] {
+    zip(x, y, |x, y| if x < y { y } else { x })
+});
+simd_op!(f64x2_abs(x: [f64; 2]) -> [f64; 2] { x.map(f64::abs) });
+simd_op!(f64x2_neg(x: [f64; 2]) -> [f64; 2] { x.map(|x| -x) });
+simd_op!(f64x2_sqrt(x: [f64; 2]) -> [f64; 2] { x.map(f64::sqrt) });
+simd_op!(f64x2_add(x: [f64; 2], y: [f64; 2]) -> [f64; 2] { zip(x, y, |x, y| x + y) });
+simd_op!(f64x2_sub(x: [f64; 2], y: [f64; 2]) -> [f64; 2] { zip(x, y, |x, y| x - y) });
+simd_op!(f64x2_mul(x: [f64; 2], y: [f64; 2]) -> [f64; 2] { zip(x, y, |x, y| x * y) });
+simd_op!(f64x2_div(x: [f64; 2], y: [f64; 2]) -> [f64; 2] { zip(x, y, |x, y| x / y) });
+simd_op!(f64x2_min(x: [f64; 2], y: [f64; 2]) -> [f64; 2] { zip(x, y, fmin) });
+simd_op!(f64x2_max(x: [f64; 2], y: [f64; 2]) -> [f64; 2] { zip(x, y, fmax) });
+simd_op!(f64x2_pmin(x: [f64; 2], y: [f64; 2]) -> [f64; 2] {
+    zip(x, y, |x, y| if y < x { y } else { x })
+});
+simd_op!(f64x2_pmax(x: [f64; 2], y: [f64; 2]) -> [f64; 2] {
+    zip(x, y, |x, y| if x < y { y } else { x })
+});
+simd_op!(i32x4_trunc_sat_f32x4_s(x: [f32; 4]) -> [i32; 4] { x.map(|x| x as i32) });
+simd_op!(i32x4_trunc_sat_f32x4_u(x: [f32; 4]) -> [u32; 4] { x.map(|x| x as u32) });
+simd_op!(f32x4_convert_i32x4_s(x: [i32; 4]) -> [f32; 4] { x.map(|x| x as f32) });
+simd_op!(f32x4_convert_i32x4_u(x: [u32; 4]) -> [f32; 4] { x.map(|x| x as f32) });
+simd_op!(i32x4_trunc_sat_f64x2_s_zero(x: [f64; 2]) -> [i32; 4] {
+    [x[0] as i32, x[1] as i32, 0, 0]
+});
+simd_op!(i32x4_trunc_sat_f64x2_u_zero(x: [f64; 2]) -> [u32; 4] {
+    [x[0] as u32, x[1] as u32, 0, 0]
+});
+simd_op!(f64x2_convert_low_i32x4_s(x: [i32; 4]) -> [f64; 2] { [x[0] as f64, x[1] as f64] });
+simd_op!(f64x2_convert_low_i32x4_u(x: [u32; 4]) -> [f64; 2] { [x[0] as f64, x[1] as f64] });
+
 // Miscellaneous instructions
 
 macro_rules! copy_imm_to_stack {
@@ -3850,6 +4638,28 @@ copy_imm_to_stack!(copy_imm_to_stack_f64, f64);
 copy_imm_to_stack!(copy_imm_to_stack_func_ref, UnguardedFuncRef);
 copy_imm_to_stack!(copy_imm_to_stack_extern_ref, UnguardedExternRef);
 
+// A `v128` immediate does not fit in a single slot, so it is split into two halves.
+threaded_instr!(copy_imm_to_stack_v128(
+    ip: Ip,
+    sp: Sp,
+    md: Md,
+    ms: Ms,
+    ix: Ix,
+    sx: Sx,
+    dx: Dx,
+    cx: Cx,
+) -> ControlFlowBits {
+    // Read immediate value
+    let (x_lo, ip): (u64, _) = read_imm(ip);
+    let (x_hi, ip): (u64, _) = read_imm(ip);
+
+    // Write value to stack
+    let ip = write_stack(ip, sp, x_lo as u128 | (x_hi as u128) << 64);
+
+    // Execute next instruction
+    next_instr(ip, sp, md, ms, ix, sx, dx, cx)
+});
+
 macro_rules! copy_stack {
     ($copy_stack_t:ident, $T:ty) => {
         threaded_instr!($copy_stack_t(
@@ -3880,6 +4690,7 @@ copy_stack!(copy_stack_f32, f32);
 copy_stack!(copy_stack_f64, f64);
 copy_stack!(copy_stack_func_ref, UnguardedFuncRef);
 copy_stack!(copy_stack_extern_ref, UnguardedExternRef);
+copy_stack!(copy_stack_v128, u128);
 
 macro_rules! copy_reg_to_stack {
     ($copy_reg_to_stack_t:ident, $T:ty) => {
@@ -3974,6 +4785,17 @@ threaded_instr!(enter(
         return ControlFlow::Trap(Trap::StackOverflow).to_bits();
     }
 
+    // Check that the call depth limit of the store is not exceeded.
+    let store = &mut *(*cx).store;
+    if store
+        .limits
+        .max_call_depth
+        .is_some_and(|max_call_depth| store.call_depth >= max_call_depth)
+    {
+        return ControlFlow::Trap(Trap::CallDepthExceeded).to_bits();
+    }
+    store.call_depth += 1;
+
     // Initialize the locals for this function to their default values.
     ptr::write_bytes(sp, 0, code.local_count);
 
@@ -3982,7 +4804,7 @@ threaded_instr!(enter(
     if let Some(mut mem) = mem {
         let data = mem.as_mut().bytes_mut();
         md = data.as_mut_ptr();
-        ms = data.len() as u32;
+        ms = data.len() as Ms;
     } else {
         md = ptr::null_mut();
         ms = 0;
@@ -3992,6 +4814,39 @@ threaded_instr!(enter(
     next_instr(ip, sp, md, ms, ix, sx, dx, cx)
 });
 
+threaded_instr!(consume_fuel(
+    ip: Ip,
+    sp: Sp,
+    md: Md,
+    ms: Ms,
+    ix: Ix,
+    sx: Sx,
+    dx: Dx,
+    cx: Cx,
+) -> ControlFlowBits {
+    let (cost, ip_next): (usize, _) = read_imm(ip);
+    let (frame_height, ip_next): (usize, _) = read_imm(ip_next);
+
+    // If the store does not have enough fuel, save the registers so that execution can be resumed
+    // at this instruction once the store has been refueled.
+    let store = &mut *(*cx).store;
+    if store.fuel < cost as u64 {
+        (*cx).ip = ip.offset(-1);
+        (*cx).sp = sp;
+        (*cx).md = md;
+        (*cx).ms = ms;
+        (*cx).ix = ix;
+        (*cx).sx = sx;
+        (*cx).dx = dx;
+        (*cx).frame_end = sp.add(frame_height);
+        return ControlFlow::Trap(Trap::OutOfFuel).to_bits();
+    }
+    store.fuel -= cost as u64;
+
+    // Execute the next instruction.
+    next_instr(ip_next, sp, md, ms, ix, sx, dx, cx)
+});
+
 // Helper functions
 
 /// Executes the next instruction.
@@ -42,19 +42,51 @@ impl Func {
     /// - If the actual result count does not match the expected result count.
     /// - If the argument types do not match the expected parameter types.
     pub fn call(self, store: &mut Store, args: &[Val], results: &mut [Val]) -> Result<(), Error> {
+        self.check_args_and_results(store, args, results)?;
+        exec::exec(store, self, args, results)
+    }
+
+    /// Calls this [`Func`] with the given arguments, suspending the call if the [`Store`] runs out
+    /// of fuel.
+    ///
+    /// If the call finishes, the results are written to the `results` slice. Otherwise, a
+    /// [`SuspendedCall`] is returned, which can be resumed after adding more fuel to the [`Store`].
+    ///
+    /// # Errors
+    ///
+    /// - If the argument count does not match the expected parameter count.
+    /// - If the actual result count does not match the expected result count.
+    /// - If the argument types do not match the expected parameter types.
+    pub fn call_resumable(
+        self,
+        store: &mut Store,
+        args: &[Val],
+        results: &mut [Val],
+    ) -> Result<ResumableCall, Error> {
+        self.check_args_and_results(store, args, results)?;
+        let state = exec::exec_resumable(store, self, args, results)?;
+        Ok(ResumableCall::new(self, state))
+    }
+
+    fn check_args_and_results(
+        self,
+        store: &Store,
+        args: &[Val],
+        results: &[Val],
+    ) -> Result<(), FuncError> {
         let type_ = self.type_(store);
         if args.len() != type_.params().len() {
-            return Err(FuncError::ParamCountMismatch)?;
+            return Err(FuncError::ParamCountMismatch);
         }
         if results.len() != type_.results().len() {
-            return Err(FuncError::ResultCountMismatch)?;
+            return Err(FuncError::ResultCountMismatch);
         }
         for (arg, param_type) in args.iter().zip(type_.params().iter().copied()) {
             if arg.type_() != param_type {
-                return Err(FuncError::ParamTypeMismatch)?;
+                return Err(FuncError::ParamTypeMismatch);
             }
         }
-        exec::exec(store, self, args, results)
+        Ok(())
     }
 
     /// Creates a new Wasm function from its raw parts.
@@ -145,7 +177,7 @@ impl FuncType {
     /// [`FuncType`] [] -> [`ValType`?].
     pub(crate) fn from_val_type(type_: Option<ValType>) -> FuncType {
         thread_local! {
-            static TYPES: [FuncType; 7] = [
+            static TYPES: [FuncType; 8] = [
                 FuncType::new(vec![], vec![]),
                 FuncType::new(vec![], vec![ValType::I32]),
                 FuncType::new(vec![], vec![ValType::I64]),
@@ -153,6 +185,7 @@ impl FuncType {
                 FuncType::new(vec![], vec![ValType::F64]),
                 FuncType::new(vec![], vec![ValType::FuncRef]),
                 FuncType::new(vec![], vec![ValType::ExternRef]),
+                FuncType::new(vec![], vec![ValType::V128]),
             ];
         }
 
@@ -164,6 +197,7 @@ impl FuncType {
             Some(ValType::F64) => types[4].clone(),
             Some(ValType::FuncRef) => types[5].clone(),
             Some(ValType::ExternRef) => types[6].clone(),
+            Some(ValType::V128) => types[7].clone(),
         })
     }
 
@@ -213,6 +247,62 @@ impl fmt::Display for FuncError {
 
 impl error::Error for FuncError {}
 
+/// The outcome of [`Func::call_resumable`] or [`SuspendedCall::resume`].
+#[derive(Debug)]
+pub enum ResumableCall {
+    /// The call finished, and its results were written to the `results` slice.
+    Finished,
+    /// The [`Store`] ran out of fuel before the call could finish.
+    OutOfFuel(SuspendedCall),
+}
+
+impl ResumableCall {
+    fn new(func: Func, state: Option<exec::SuspendedExec>) -> Self {
+        match state {
+            Some(state) => Self::OutOfFuel(SuspendedCall { func, state }),
+            None => Self::Finished,
+        }
+    }
+}
+
+/// A call that was suspended because its [`Store`] ran out of fuel.
+#[derive(Debug)]
+pub struct SuspendedCall {
+    func: Func,
+    state: exec::SuspendedExec,
+}
+
+impl SuspendedCall {
+    /// Returns the [`Func`] that was called.
+    pub fn func(&self) -> Func {
+        self.func
+    }
+
+    /// Resumes this [`SuspendedCall`].
+    ///
+    /// The [`Store`] should be given more fuel with [`Store::add_fuel`] or [`Store::set_fuel`]
+    /// before resuming, otherwise the call is suspended again immediately.
+    ///
+    /// If the call finishes, the results are written to the `results` slice.
+    ///
+    /// # Errors
+    ///
+    /// If the actual result count does not match the expected result count.
+    ///
+    /// # Panics
+    ///
+    /// - If this [`SuspendedCall`] was not suspended in the given [`Store`].
+    /// - If this [`SuspendedCall`] is resumed while another call is in progress on the same
+    ///   thread.
+    pub fn resume(self, store: &mut Store, results: &mut [Val]) -> Result<ResumableCall, Error> {
+        if results.len() != self.func.type_(store).results().len() {
+            return Err(FuncError::ResultCountMismatch)?;
+        }
+        let state = exec::resume(store, self.state, results)?;
+        Ok(ResumableCall::new(self.func, state))
+    }
+}
+
 /// The representation of a [`Func`] in a [`Store`].
 #[derive(Debug)]
 pub enum FuncEntity {
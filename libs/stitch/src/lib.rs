@@ -24,20 +24,22 @@ mod mem;
 mod module;
 mod ops;
 mod ref_;
+mod simd;
 mod stack;
 mod store;
 mod table;
 mod trap;
 mod val;
 mod validate;
+mod wasi;
 
 pub use self::{
     decode::DecodeError,
-    engine::Engine,
+    engine::{Engine, EngineConfig},
     error::Error,
     extern_ref::ExternRef,
     extern_val::{ExternType, ExternVal},
-    func::{Func, FuncError, FuncType},
+    func::{Func, FuncError, FuncType, ResumableCall, SuspendedCall},
     func_ref::FuncRef,
     global::{Global, GlobalError, GlobalType, Mut},
     instance::{Instance, InstanceExports},
@@ -46,7 +48,9 @@ pub use self::{
     mem::{Mem, MemError, MemType},
     module::{Module, ModuleExports, ModuleImports},
     ref_::{Ref, RefType},
-    store::Store,
+    store::{Store, StoreLimits},
     table::{Table, TableError, TableType},
+    trap::Trap,
     val::{Val, ValType},
+    wasi::{Wasi, WasiConfig},
 };
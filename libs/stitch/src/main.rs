@@ -1,18 +1,43 @@
 use {
-    makepad_stitch::{Engine, Linker, Module, Store, Val, ValType},
-    std::{env, fs},
+    makepad_stitch::{Engine, Linker, Module, Store, Val, ValType, Wasi, WasiConfig},
+    std::{env, fs, process},
 };
 
 fn main() {
-    let mut args = env::args();
+    let mut args = env::args().peekable();
     args.next().unwrap();
+    let mut dirs = Vec::new();
+    while args.peek().map(|arg| arg.as_str()) == Some("--dir") {
+        args.next().unwrap();
+        dirs.push(args.next().unwrap());
+    }
     let wasm_file = args.next().unwrap();
-    let func_name = args.next().unwrap();
-    let args: Vec<_> = args.collect();
     let engine = Engine::new();
     let mut store = Store::new(engine);
-    let bytes = fs::read(wasm_file).unwrap();
+    let bytes = fs::read(&wasm_file).unwrap();
     let module = Module::new(store.engine(), &bytes).unwrap();
+
+    // Modules that import WASI functions are run as WASI commands, with the remaining arguments
+    // passed to the program.
+    if module
+        .imports()
+        .any(|((module, _), _)| module == "wasi_snapshot_preview1")
+    {
+        let mut config = WasiConfig::new().arg(wasm_file).args(args);
+        for dir in dirs {
+            let (host_path, guest_path) = dir.split_once("::").unwrap_or((&dir, &dir));
+            config = config.preopened_dir(host_path, guest_path);
+        }
+        let wasi = Wasi::new(config);
+        let mut linker = Linker::new();
+        wasi.define(&mut store, &mut linker);
+        let instance = linker.instantiate(&mut store, &module).unwrap();
+        let code = wasi.start(&mut store, &instance).unwrap();
+        process::exit(code);
+    }
+
+    let func_name = args.next().unwrap();
+    let args: Vec<_> = args.collect();
     let linker = Linker::new();
     let instance = linker.instantiate(&mut store, &module).unwrap();
     let func = instance.exported_func(&func_name).unwrap();
@@ -41,6 +66,7 @@ fn parse_val(type_: ValType, string: &str) -> Val {
         ValType::I64 => string.parse::<i64>().unwrap().into(),
         ValType::F32 => string.parse::<f32>().unwrap().into(),
         ValType::F64 => string.parse::<f64>().unwrap().into(),
+        ValType::V128 => string.parse::<u128>().unwrap().into(),
         ValType::FuncRef => unimplemented!(),
         ValType::ExternRef => unimplemented!(),
     }
@@ -52,6 +78,7 @@ fn print_val(val: Val) {
         Val::I64(val) => println!("{}", val),
         Val::F32(val) => println!("{}", val),
         Val::F64(val) => println!("{}", val),
+        Val::V128(val) => println!("{}", val),
         Val::FuncRef(_) => unimplemented!(),
         Val::ExternRef(_) => unimplemented!(),
     }
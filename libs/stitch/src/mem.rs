@@ -2,6 +2,7 @@ use {
     crate::{
         data::{Data, DataEntity},
         decode::{Decode, DecodeError, Decoder},
+        exec,
         limits::Limits,
         stack::Stack,
         store::{Handle, HandlePair, Store, StoreId, UnguardedHandle},
@@ -207,14 +208,13 @@ impl MemEntity {
         self.bytes
             .resize((new_size as usize).checked_mul(PAGE_SIZE).unwrap(), 0);
         let new_data = self.bytes.as_mut_ptr();
-        let mut ptr = stack.ptr();
-        while ptr != stack.base_ptr() {
-            ptr = *ptr.offset(-3).cast();
-            if *ptr.offset(-2).cast::<*mut u8>() == old_data {
-                *ptr.offset(-2).cast() = new_data;
-                *ptr.offset(-1).cast() = new_size;
-            }
-        }
+        exec::update_mem_in_call_frames(
+            stack.ptr(),
+            stack.base_ptr(),
+            old_data,
+            new_data,
+            self.bytes.len() as u64,
+        );
         Ok(old_size)
     }
 
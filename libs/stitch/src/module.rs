@@ -291,6 +291,13 @@ impl Module {
             initer.push_table(Table::new(store, type_, Ref::null(type_.elem)).unwrap());
         }
         for type_ in self.internal_memories() {
+            if store
+                .limits()
+                .max_mem_pages
+                .is_some_and(|max_mem_pages| type_.limits.min > max_mem_pages)
+            {
+                return Err(Trap::MemLimitExceeded)?;
+            }
             initer.push_mem(Mem::new(store, type_));
         }
         for ((type_, _), init_val) in self.internal_globals().zip(global_init_vals) {
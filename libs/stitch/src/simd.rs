@@ -0,0 +1,244 @@
+//! Helpers for executing SIMD instructions.
+//!
+//! A `v128` is represented as a `u128`, and stored on the stack in little-endian lane order. SIMD
+//! instructions operate on a `v128` by viewing it as an array of lanes of the appropriate type.
+
+use {
+    crate::{ops::FloatOps, trap::Trap},
+    std::{array, fmt},
+};
+
+/// A value that can be read from or written to the stack by a SIMD instruction.
+pub(crate) trait SimdVal: Copy {
+    /// The type of the value as it is stored on the stack.
+    type Repr: Copy + fmt::Debug;
+
+    fn from_repr(repr: Self::Repr) -> Self;
+
+    fn to_repr(self) -> Self::Repr;
+}
+
+macro_rules! impl_simd_val_for_scalar {
+    ($T:ty) => {
+        impl SimdVal for $T {
+            type Repr = $T;
+
+            fn from_repr(repr: Self::Repr) -> Self {
+                repr
+            }
+
+            fn to_repr(self) -> Self::Repr {
+                self
+            }
+        }
+    };
+}
+
+impl_simd_val_for_scalar!(i32);
+impl_simd_val_for_scalar!(u32);
+impl_simd_val_for_scalar!(i64);
+impl_simd_val_for_scalar!(f32);
+impl_simd_val_for_scalar!(f64);
+impl_simd_val_for_scalar!(u128);
+
+macro_rules! impl_simd_val_for_lanes {
+    ($T:ty, $N:literal) => {
+        impl SimdVal for [$T; $N] {
+            type Repr = u128;
+
+            fn from_repr(repr: Self::Repr) -> Self {
+                const SIZE: usize = 16 / $N;
+
+                let bytes = repr.to_le_bytes();
+                array::from_fn(|i| {
+                    <$T>::from_le_bytes(bytes[i * SIZE..][..SIZE].try_into().unwrap())
+                })
+            }
+
+            fn to_repr(self) -> Self::Repr {
+                const SIZE: usize = 16 / $N;
+
+                let mut bytes = [0; 16];
+                for (i, lane) in self.into_iter().enumerate() {
+                    bytes[i * SIZE..][..SIZE].copy_from_slice(&lane.to_le_bytes());
+                }
+                u128::from_le_bytes(bytes)
+            }
+        }
+    };
+}
+
+impl_simd_val_for_lanes!(i8, 16);
+impl_simd_val_for_lanes!(u8, 16);
+impl_simd_val_for_lanes!(i16, 8);
+impl_simd_val_for_lanes!(u16, 8);
+impl_simd_val_for_lanes!(i32, 4);
+impl_simd_val_for_lanes!(u32, 4);
+impl_simd_val_for_lanes!(i64, 2);
+impl_simd_val_for_lanes!(u64, 2);
+impl_simd_val_for_lanes!(f32, 4);
+impl_simd_val_for_lanes!(f64, 2);
+
+/// A lane type that can be used as the result of a lane-wise comparison.
+pub(crate) trait Mask {
+    /// Returns a lane with all bits set if `cond` is `true`, or all bits cleared otherwise.
+    fn mask(cond: bool) -> Self;
+}
+
+macro_rules! impl_mask {
+    ($T:ty) => {
+        impl Mask for $T {
+            fn mask(cond: bool) -> Self {
+                -(cond as $T)
+            }
+        }
+    };
+}
+
+impl_mask!(i8);
+impl_mask!(i16);
+impl_mask!(i32);
+impl_mask!(i64);
+
+/// Applies `f` to each pair of corresponding lanes of `x` and `y`.
+pub(crate) fn zip<T, U, const N: usize>(x: [T; N], y: [T; N], f: impl Fn(T, T) -> U) -> [U; N]
+where
+    T: Copy,
+{
+    array::from_fn(|i| f(x[i], y[i]))
+}
+
+/// Compares each pair of corresponding lanes of `x` and `y` with `f`, and returns a mask.
+pub(crate) fn compare<T, U, const N: usize>(
+    x: [T; N],
+    y: [T; N],
+    f: impl Fn(T, T) -> bool,
+) -> [U; N]
+where
+    T: Copy,
+    U: Mask,
+{
+    zip(x, y, |x, y| U::mask(f(x, y)))
+}
+
+/// Returns a bitmask with one bit for each lane of `x`, which is set if the lane is negative.
+pub(crate) fn bitmask<T, const N: usize>(x: [T; N]) -> i32
+where
+    T: Default + PartialOrd,
+{
+    x.into_iter().enumerate().fold(0, |mask, (i, lane)| {
+        mask | ((lane < T::default()) as i32) << i
+    })
+}
+
+/// Applies `f` to each pair of adjacent lanes of `x`.
+pub(crate) fn pairwise<T, U, const N: usize, const M: usize>(
+    x: [T; N],
+    f: impl Fn(T, T) -> U,
+) -> [U; M]
+where
+    T: Copy,
+{
+    debug_assert_eq!(N, 2 * M);
+    array::from_fn(|i| f(x[2 * i], x[2 * i + 1]))
+}
+
+/// Returns a copy of `x` with the lane at `lane_idx` replaced by `val`.
+pub(crate) fn replace_lane<T, const N: usize>(mut x: [T; N], lane_idx: u8, val: T) -> [T; N] {
+    x[lane_idx as usize] = val;
+    x
+}
+
+/// Widens the lower half of the lanes of `x`.
+pub(crate) fn extend_low<T, U, const N: usize, const M: usize>(x: [T; N]) -> [U; M]
+where
+    T: Copy,
+    U: From<T>,
+{
+    debug_assert_eq!(N, 2 * M);
+    array::from_fn(|i| U::from(x[i]))
+}
+
+/// Widens the upper half of the lanes of `x`.
+pub(crate) fn extend_high<T, U, const N: usize, const M: usize>(x: [T; N]) -> [U; M]
+where
+    T: Copy,
+    U: From<T>,
+{
+    debug_assert_eq!(N, 2 * M);
+    array::from_fn(|i| U::from(x[i + M]))
+}
+
+/// Returns the minimum of `x` and `y`, with the semantics of the scalar `min` instruction.
+pub(crate) fn fmin<T>(x: T, y: T) -> T
+where
+    T: FloatOps,
+{
+    match x.min(y) {
+        Ok(val) => val,
+        Err(_) => unreachable!(),
+    }
+}
+
+/// Returns the maximum of `x` and `y`, with the semantics of the scalar `max` instruction.
+pub(crate) fn fmax<T>(x: T, y: T) -> T
+where
+    T: FloatOps,
+{
+    match x.max(y) {
+        Ok(val) => val,
+        Err(_) => unreachable!(),
+    }
+}
+
+/// Returns the lanes of `x` followed by the lanes of `y`.
+pub(crate) fn concat<T, const N: usize, const M: usize>(x: [T; N], y: [T; N]) -> [T; M]
+where
+    T: Copy,
+{
+    debug_assert_eq!(2 * N, M);
+    array::from_fn(|i| if i < N { x[i] } else { y[i - N] })
+}
+
+/// Reads `N` bytes from `mem` at the given dynamic and static offset.
+pub(crate) fn load<const N: usize>(
+    mem: &[u8],
+    dyn_offset: u32,
+    static_offset: u32,
+) -> Result<[u8; N], Trap> {
+    (dyn_offset as usize)
+        .checked_add(static_offset as usize)
+        .and_then(|offset| mem.get(offset..))
+        .and_then(|bytes| bytes.get(..N))
+        .map(|bytes| bytes.try_into().unwrap())
+        .ok_or(Trap::MemAccessOutOfBounds)
+}
+
+/// Writes `N` bytes to `mem` at the given dynamic and static offset.
+pub(crate) fn store<const N: usize>(
+    mem: &mut [u8],
+    dyn_offset: u32,
+    static_offset: u32,
+    bytes: [u8; N],
+) -> Result<(), Trap> {
+    (dyn_offset as usize)
+        .checked_add(static_offset as usize)
+        .and_then(|offset| mem.get_mut(offset..))
+        .and_then(|mem| mem.get_mut(..N))
+        .ok_or(Trap::MemAccessOutOfBounds)?
+        .copy_from_slice(&bytes);
+    Ok(())
+}
+
+/// Reads `N` bytes from `mem` at the given dynamic and static offset, and zero-extends them to a
+/// `v128`.
+pub(crate) fn load_zero<const N: usize>(
+    mem: &[u8],
+    dyn_offset: u32,
+    static_offset: u32,
+) -> Result<u128, Trap> {
+    let bytes: [u8; N] = load(mem, dyn_offset, static_offset)?;
+    let mut padded_bytes = [0; 16];
+    padded_bytes[..N].copy_from_slice(&bytes);
+    Ok(u128::from_le_bytes(padded_bytes))
+}
@@ -70,7 +70,8 @@ impl Drop for StackGuard {
     }
 }
 
-pub(crate) type StackSlot = u64;
+// A stack slot is large enough to hold a value of any type, including `v128`.
+pub(crate) type StackSlot = u128;
 
 thread_local! {
     static STACK: Cell<Option<Stack>> = Cell::new(Some(Stack::new()));
@@ -31,6 +31,9 @@ pub struct Store {
     elems: Vec<AliasableBox<ElemEntity>>,
     datas: Vec<AliasableBox<DataEntity>>,
     externs: Vec<AliasableBox<ExternEntity>>,
+    pub(crate) fuel: u64,
+    pub(crate) call_depth: usize,
+    pub(crate) limits: StoreLimits,
 }
 
 impl Store {
@@ -47,6 +50,9 @@ impl Store {
             elems: Vec::new(),
             datas: Vec::new(),
             externs: Vec::new(),
+            fuel: 0,
+            call_depth: 0,
+            limits: StoreLimits::default(),
         }
     }
 
@@ -54,6 +60,36 @@ impl Store {
         &self.engine
     }
 
+    /// Returns the amount of fuel left in this [`Store`].
+    ///
+    /// Fuel is only consumed if fuel consumption is enabled in the [`EngineConfig`] of the
+    /// [`Engine`] for this [`Store`].
+    ///
+    /// [`EngineConfig`]: crate::EngineConfig
+    pub fn fuel(&self) -> u64 {
+        self.fuel
+    }
+
+    /// Sets the amount of fuel left in this [`Store`].
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = fuel;
+    }
+
+    /// Adds the given amount of fuel to this [`Store`].
+    pub fn add_fuel(&mut self, fuel: u64) {
+        self.fuel = self.fuel.saturating_add(fuel);
+    }
+
+    /// Returns the [`StoreLimits`] of this [`Store`].
+    pub fn limits(&self) -> StoreLimits {
+        self.limits
+    }
+
+    /// Sets the [`StoreLimits`] of this [`Store`].
+    pub fn set_limits(&mut self, limits: StoreLimits) {
+        self.limits = limits;
+    }
+
     pub(crate) fn id(&self) -> StoreId {
         self.id
     }
@@ -96,6 +132,38 @@ impl Store {
         handle
     }
 
+    /// Returns the [`MemEntity`] in this [`Store`] whose bytes start at the given pointer, if
+    /// any.
+    pub(crate) fn find_mem(&self, data: *const u8) -> Option<UnguardedHandle<MemEntity>> {
+        self.mems
+            .iter()
+            .find(|mem| mem.bytes().as_ptr() == data)
+            .map(AliasableBox::as_raw)
+    }
+
+    /// Returns the index of the given [`MemEntity`] in this [`Store`].
+    ///
+    /// Unlike a [`Handle`], a [`MemIndex`] is plain data, so host state that has to be `Send`
+    /// can keep it and look up the [`MemEntity`] with [`Store::mem_at`] on each call.
+    pub(crate) fn mem_index(&self, mem: Handle<MemEntity>) -> MemIndex {
+        let mem = mem.to_unguarded(self.id);
+        let index = self
+            .mems
+            .iter()
+            .position(|entity| AliasableBox::as_raw(entity) == mem)
+            .unwrap();
+        MemIndex {
+            index,
+            store_id: self.id,
+        }
+    }
+
+    /// Returns a [`Handle`] to the [`MemEntity`] at the given [`MemIndex`] in this [`Store`].
+    pub(crate) fn mem_at(&self, index: MemIndex) -> Handle<MemEntity> {
+        assert_eq!(self.id, index.store_id, "store mismatch");
+        unsafe { Handle::from_unguarded(AliasableBox::as_raw(&self.mems[index.index]), self.id) }
+    }
+
     /// Inserts the given [`GlobalEntity`] into this [`Store`].
     ///
     /// Returns a [`Handle`] to the inserted [`GlobalEntity`].
@@ -138,6 +206,20 @@ impl Store {
     }
 }
 
+/// Limits on the resources that Wasm code executing in a [`Store`] can use.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct StoreLimits {
+    /// The maximum number of nested Wasm function calls.
+    ///
+    /// Exceeding this traps with [`Trap::CallDepthExceeded`](crate::Trap::CallDepthExceeded).
+    pub max_call_depth: Option<usize>,
+    /// The maximum size of each memory, in number of pages.
+    ///
+    /// Instantiating a module with a larger memory, or growing a memory beyond this size with
+    /// `memory.grow`, traps with [`Trap::MemLimitExceeded`](crate::Trap::MemLimitExceeded).
+    pub max_mem_pages: Option<u32>,
+}
+
 /// A unique identifier for a [`Store`].
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct StoreId(usize);
@@ -156,6 +238,13 @@ impl StoreId {
     }
 }
 
+/// The index of a [`MemEntity`] in the [`Store`] that owns it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct MemIndex {
+    index: usize,
+    store_id: StoreId,
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub(crate) struct InternedFuncType {
     type_: UnguardedInternedFuncType,
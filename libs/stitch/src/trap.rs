@@ -11,6 +11,9 @@ pub enum Trap {
     TableAccessOutOfBounds,
     MemAccessOutOfBounds,
     StackOverflow,
+    OutOfFuel,
+    CallDepthExceeded,
+    MemLimitExceeded,
 }
 
 impl Trap {
@@ -25,6 +28,9 @@ impl Trap {
             6 => Some(Self::TableAccessOutOfBounds),
             7 => Some(Self::MemAccessOutOfBounds),
             8 => Some(Self::StackOverflow),
+            9 => Some(Self::OutOfFuel),
+            10 => Some(Self::CallDepthExceeded),
+            11 => Some(Self::MemLimitExceeded),
             _ => None,
         }
     }
@@ -46,6 +52,9 @@ impl fmt::Display for Trap {
             Self::TableAccessOutOfBounds => write!(f, "table access out of bounds"),
             Self::MemAccessOutOfBounds => write!(f, "memory access out of bounds"),
             Self::StackOverflow => write!(f, "stack overflow"),
+            Self::OutOfFuel => write!(f, "out of fuel"),
+            Self::CallDepthExceeded => write!(f, "call depth exceeded"),
+            Self::MemLimitExceeded => write!(f, "memory limit exceeded"),
         }
     }
 }
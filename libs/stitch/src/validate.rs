@@ -2,8 +2,8 @@ use {
     crate::{
         code,
         code::{
-            BinOpInfo, BlockType, InstrVisitor, LoadInfo, MemArg, StoreInfo, UnOpInfo,
-            UncompiledCode,
+            BinOpInfo, BlockType, InstrVisitor, LoadInfo, MemArg, SimdLaneOpInfo,
+            SimdMemLaneOpInfo, SimdMemOpInfo, SimdOpInfo, StoreInfo, UnOpInfo, UncompiledCode,
         },
         decode::DecodeError,
         func::FuncType,
@@ -344,7 +344,9 @@ impl<'a> InstrVisitor for Validation<'a> {
             self.pop_opd()?.check(ValType::I32)?;
             let input_type_1 = self.pop_opd()?;
             let input_type_0 = self.pop_opd()?;
-            if !(input_type_0.is_num() && input_type_1.is_num()) {
+            if !(input_type_0.is_num() && input_type_1.is_num()
+                || input_type_0.is_vec() && input_type_1.is_vec())
+            {
                 return Err(DecodeError::new("type mismatch"));
             }
             if let OpdType::ValType(input_type_1) = input_type_1 {
@@ -561,6 +563,67 @@ impl<'a> InstrVisitor for Validation<'a> {
         }
         Ok(())
     }
+
+    // Vector instructions
+    fn visit_v128_const(&mut self, _val: u128) -> Result<(), Self::Error> {
+        self.push_opd(ValType::V128);
+        Ok(())
+    }
+
+    fn visit_i8x16_shuffle(&mut self, lane_idxs: [u8; 16]) -> Result<(), Self::Error> {
+        if lane_idxs.iter().any(|&lane_idx| lane_idx >= 32) {
+            return Err(DecodeError::new("invalid lane index"));
+        }
+        self.pop_opd()?.check(ValType::V128)?;
+        self.pop_opd()?.check(ValType::V128)?;
+        self.push_opd(ValType::V128);
+        Ok(())
+    }
+
+    fn visit_simd_op(&mut self, info: SimdOpInfo) -> Result<(), Self::Error> {
+        for input_type in info.input_types.iter().rev().copied() {
+            self.pop_opd()?.check(input_type)?;
+        }
+        if let Some(output_type) = info.output_type {
+            self.push_opd(output_type);
+        }
+        Ok(())
+    }
+
+    fn visit_simd_lane_op(
+        &mut self,
+        lane_idx: u8,
+        info: SimdLaneOpInfo,
+    ) -> Result<(), Self::Error> {
+        if lane_idx >= info.lane_count {
+            return Err(DecodeError::new("invalid lane index"));
+        }
+        self.visit_simd_op(info.op)
+    }
+
+    fn visit_simd_mem_op(&mut self, arg: MemArg, info: SimdMemOpInfo) -> Result<(), Self::Error> {
+        if arg.align > info.max_align {
+            return Err(DecodeError::new("alignment too large"));
+        }
+        self.module.memory(0)?;
+        self.visit_simd_op(info.op)
+    }
+
+    fn visit_simd_mem_lane_op(
+        &mut self,
+        arg: MemArg,
+        lane_idx: u8,
+        info: SimdMemLaneOpInfo,
+    ) -> Result<(), Self::Error> {
+        if arg.align > info.max_align {
+            return Err(DecodeError::new("alignment too large"));
+        }
+        if lane_idx >= info.lane_count {
+            return Err(DecodeError::new("invalid lane index"));
+        }
+        self.module.memory(0)?;
+        self.visit_simd_op(info.op)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -619,6 +682,13 @@ impl OpdType {
         }
     }
 
+    fn is_vec(self) -> bool {
+        match self {
+            OpdType::ValType(type_) => type_.is_vec(),
+            _ => true,
+        }
+    }
+
     fn is_ref(self) -> bool {
         match self {
             OpdType::ValType(type_) => type_.is_ref(),
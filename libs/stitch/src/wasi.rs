@@ -0,0 +1,1356 @@
+use {
+    crate::{
+        error::Error,
+        func::Func,
+        instance::Instance,
+        linker::{InstantiateError, Linker},
+        mem::Mem,
+        store::{MemIndex, Store},
+    },
+    std::{
+        collections::{hash_map::RandomState, BTreeMap},
+        fmt, fs,
+        hash::{BuildHasher, Hasher},
+        io::{self, Read, Seek, SeekFrom, Write},
+        path::{Component, Path, PathBuf},
+        sync::{Arc, Mutex},
+        thread,
+        time::{Instant, SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// The name of the module from which WASI preview 1 functions are imported.
+const WASI_MODULE_NAME: &str = "wasi_snapshot_preview1";
+
+/// The configuration for a [`Wasi`] context.
+pub struct WasiConfig {
+    args: Vec<String>,
+    envs: Vec<(String, String)>,
+    preopened_dirs: Vec<(PathBuf, String)>,
+    stdin: Box<dyn Read + Send>,
+    stdout: Box<dyn Write + Send>,
+    stderr: Box<dyn Write + Send>,
+}
+
+impl WasiConfig {
+    /// Creates a new [`WasiConfig`] without arguments, environment variables, or preopened
+    /// directories, that inherits the standard streams of the host.
+    pub fn new() -> Self {
+        Self {
+            args: Vec::new(),
+            envs: Vec::new(),
+            preopened_dirs: Vec::new(),
+            stdin: Box::new(io::stdin()),
+            stdout: Box::new(io::stdout()),
+            stderr: Box::new(io::stderr()),
+        }
+    }
+
+    /// Appends a command line argument.
+    ///
+    /// By convention, the first argument is the name of the program.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Appends the given command line arguments.
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args.extend(args.into_iter().map(|arg| arg.into()));
+        self
+    }
+
+    /// Sets an environment variable.
+    pub fn env(mut self, key: impl Into<String>, val: impl Into<String>) -> Self {
+        self.envs.push((key.into(), val.into()));
+        self
+    }
+
+    /// Makes the directory at `host_path` available to the Wasm program as `guest_path`.
+    ///
+    /// The Wasm program cannot access any files outside its preopened directories.
+    pub fn preopened_dir(
+        mut self,
+        host_path: impl Into<PathBuf>,
+        guest_path: impl Into<String>,
+    ) -> Self {
+        self.preopened_dirs
+            .push((host_path.into(), guest_path.into()));
+        self
+    }
+
+    /// Sets the stream from which the Wasm program reads its standard input.
+    pub fn stdin(mut self, stdin: impl Read + Send + 'static) -> Self {
+        self.stdin = Box::new(stdin);
+        self
+    }
+
+    /// Sets the stream to which the Wasm program writes its standard output.
+    pub fn stdout(mut self, stdout: impl Write + Send + 'static) -> Self {
+        self.stdout = Box::new(stdout);
+        self
+    }
+
+    /// Sets the stream to which the Wasm program writes its standard error.
+    pub fn stderr(mut self, stderr: impl Write + Send + 'static) -> Self {
+        self.stderr = Box::new(stderr);
+        self
+    }
+}
+
+impl Default for WasiConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for WasiConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasiConfig")
+            .field("args", &self.args)
+            .field("envs", &self.envs)
+            .field("preopened_dirs", &self.preopened_dirs)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A WASI preview 1 context.
+///
+/// A [`Wasi`] context provides the functions in the `wasi_snapshot_preview1` module, which can
+/// be defined on a [`Linker`] with [`Wasi::define`]. Since these functions need to access the
+/// memory of the Wasm program, the context must be bound to that memory after instantiation,
+/// either with [`Wasi::set_mem`], or by running the program with [`Wasi::start`].
+///
+/// The context only remembers which memory of the [`Store`] it is bound to, and looks it up in
+/// the [`Store`] passed to each function call.
+#[derive(Clone, Debug)]
+pub struct Wasi {
+    state: Arc<Mutex<WasiState>>,
+}
+
+impl Wasi {
+    /// Creates a new [`Wasi`] context with the given [`WasiConfig`].
+    pub fn new(config: WasiConfig) -> Self {
+        let mut fds = BTreeMap::new();
+        fds.insert(0, FdEntry::Stdin);
+        fds.insert(1, FdEntry::Stdout);
+        fds.insert(2, FdEntry::Stderr);
+        for (fd, (host_path, guest_path)) in (3..).zip(config.preopened_dirs) {
+            let root = host_path.canonicalize().unwrap_or(host_path);
+            fds.insert(
+                fd,
+                FdEntry::Dir {
+                    path: root.clone(),
+                    root,
+                    preopened_path: Some(guest_path),
+                },
+            );
+        }
+        Self {
+            state: Arc::new(Mutex::new(WasiState {
+                args: config.args,
+                envs: config
+                    .envs
+                    .into_iter()
+                    .map(|(key, val)| format!("{}={}", key, val))
+                    .collect(),
+                fds,
+                stdin: config.stdin,
+                stdout: config.stdout,
+                stderr: config.stderr,
+                mem: None,
+                start_time: Instant::now(),
+            })),
+        }
+    }
+
+    /// Defines the functions in the `wasi_snapshot_preview1` module on the given [`Linker`].
+    pub fn define(&self, store: &mut Store, linker: &mut Linker) {
+        macro_rules! define {
+            ($($name:ident($($arg:ident: $T:ty),*);)*) => {
+                $(
+                    let state = self.state.clone();
+                    let func = Func::wrap(store, move |store: &mut Store, $($arg: $T),*| -> u32 {
+                        let mut state = state.lock().unwrap();
+                        match state.$name(store, $($arg),*) {
+                            Ok(()) => ERRNO_SUCCESS.0 as u32,
+                            Err(errno) => errno.0 as u32,
+                        }
+                    });
+                    linker.define(WASI_MODULE_NAME, stringify!($name), func);
+                )*
+            };
+        }
+
+        define! {
+            args_get(argv: u32, argv_buf: u32);
+            args_sizes_get(argc_ptr: u32, argv_buf_size_ptr: u32);
+            environ_get(environ: u32, environ_buf: u32);
+            environ_sizes_get(environc_ptr: u32, environ_buf_size_ptr: u32);
+            clock_res_get(id: u32, res_ptr: u32);
+            clock_time_get(id: u32, precision: u64, time_ptr: u32);
+            fd_advise(fd: u32, offset: u64, len: u64, advice: u32);
+            fd_allocate(fd: u32, offset: u64, len: u64);
+            fd_close(fd: u32);
+            fd_datasync(fd: u32);
+            fd_fdstat_get(fd: u32, stat_ptr: u32);
+            fd_fdstat_set_flags(fd: u32, flags: u32);
+            fd_fdstat_set_rights(fd: u32, rights_base: u64, rights_inheriting: u64);
+            fd_filestat_get(fd: u32, stat_ptr: u32);
+            fd_filestat_set_size(fd: u32, size: u64);
+            fd_filestat_set_times(fd: u32, atim: u64, mtim: u64, fst_flags: u32);
+            fd_pread(fd: u32, iovs: u32, iovs_len: u32, offset: u64, nread_ptr: u32);
+            fd_prestat_get(fd: u32, prestat_ptr: u32);
+            fd_prestat_dir_name(fd: u32, path: u32, path_len: u32);
+            fd_pwrite(fd: u32, iovs: u32, iovs_len: u32, offset: u64, nwritten_ptr: u32);
+            fd_read(fd: u32, iovs: u32, iovs_len: u32, nread_ptr: u32);
+            fd_readdir(fd: u32, buf: u32, buf_len: u32, cookie: u64, bufused_ptr: u32);
+            fd_renumber(fd: u32, to: u32);
+            fd_seek(fd: u32, offset: i64, whence: u32, newoffset_ptr: u32);
+            fd_sync(fd: u32);
+            fd_tell(fd: u32, offset_ptr: u32);
+            fd_write(fd: u32, iovs: u32, iovs_len: u32, nwritten_ptr: u32);
+            path_create_directory(fd: u32, path: u32, path_len: u32);
+            path_filestat_get(fd: u32, flags: u32, path: u32, path_len: u32, stat_ptr: u32);
+            path_filestat_set_times(
+                fd: u32,
+                flags: u32,
+                path: u32,
+                path_len: u32,
+                atim: u64,
+                mtim: u64,
+                fst_flags: u32
+            );
+            path_link(
+                old_fd: u32,
+                old_flags: u32,
+                old_path: u32,
+                old_path_len: u32,
+                new_fd: u32,
+                new_path: u32,
+                new_path_len: u32
+            );
+            path_open(
+                fd: u32,
+                dirflags: u32,
+                path: u32,
+                path_len: u32,
+                oflags: u32,
+                rights_base: u64,
+                rights_inheriting: u64,
+                fdflags: u32,
+                fd_ptr: u32
+            );
+            path_readlink(
+                fd: u32,
+                path: u32,
+                path_len: u32,
+                buf: u32,
+                buf_len: u32,
+                bufused_ptr: u32
+            );
+            path_remove_directory(fd: u32, path: u32, path_len: u32);
+            path_rename(
+                fd: u32,
+                old_path: u32,
+                old_path_len: u32,
+                new_fd: u32,
+                new_path: u32,
+                new_path_len: u32
+            );
+            path_symlink(
+                old_path: u32,
+                old_path_len: u32,
+                fd: u32,
+                new_path: u32,
+                new_path_len: u32
+            );
+            path_unlink_file(fd: u32, path: u32, path_len: u32);
+            poll_oneoff(in_: u32, out: u32, nsubscriptions: u32, nevents_ptr: u32);
+            proc_raise(sig: u32);
+            random_get(buf: u32, buf_len: u32);
+            sched_yield();
+            sock_accept(fd: u32, flags: u32, fd_ptr: u32);
+            sock_recv(
+                fd: u32,
+                ri_data: u32,
+                ri_data_len: u32,
+                ri_flags: u32,
+                ro_datalen_ptr: u32,
+                ro_flags_ptr: u32
+            );
+            sock_send(
+                fd: u32,
+                si_data: u32,
+                si_data_len: u32,
+                si_flags: u32,
+                so_datalen_ptr: u32
+            );
+            sock_shutdown(fd: u32, how: u32);
+        }
+
+        // `proc_exit` does not return, so we unwind out of the Wasm program with an error instead.
+        let state = self.state.clone();
+        let func = Func::wrap(
+            store,
+            move |_store: &mut Store, code: u32| -> Result<(), Error> {
+                let mut state = state.lock().unwrap();
+                let _ = state.stdout.flush();
+                let _ = state.stderr.flush();
+                Err(Error::Exit(code as i32))
+            },
+        );
+        linker.define(WASI_MODULE_NAME, "proc_exit", func);
+    }
+
+    /// Binds this [`Wasi`] context to the memory of the Wasm program.
+    ///
+    /// # Panics
+    ///
+    /// If the given [`Mem`] does not belong to the given [`Store`].
+    pub fn set_mem(&self, store: &Store, mem: Mem) {
+        self.state.lock().unwrap().mem = Some(store.mem_index(mem.0));
+    }
+
+    /// Runs the given instance of a WASI command.
+    ///
+    /// Binds this [`Wasi`] context to the memory exported as `memory`, and then calls the
+    /// function exported as `_start`.
+    ///
+    /// Returns the exit code of the program.
+    ///
+    /// # Errors
+    ///
+    /// - If the instance does not export a function named `_start`.
+    /// - If the program traps.
+    pub fn start(&self, store: &mut Store, instance: &Instance) -> Result<i32, Error> {
+        if let Some(mem) = instance.exported_mem("memory") {
+            self.set_mem(store, mem);
+        }
+        let Some(start) = instance.exported_func("_start") else {
+            return Err(InstantiateError::DefNotFound)?;
+        };
+        let result = match start.call(store, &[], &mut []) {
+            Ok(()) => Ok(0),
+            Err(Error::Exit(code)) => Ok(code),
+            Err(error) => Err(error),
+        };
+        let mut state = self.state.lock().unwrap();
+        let _ = state.stdout.flush();
+        let _ = state.stderr.flush();
+        result
+    }
+}
+
+struct WasiState {
+    args: Vec<String>,
+    envs: Vec<String>,
+    fds: BTreeMap<u32, FdEntry>,
+    stdin: Box<dyn Read + Send>,
+    stdout: Box<dyn Write + Send>,
+    stderr: Box<dyn Write + Send>,
+    mem: Option<MemIndex>,
+    start_time: Instant,
+}
+
+// The methods here mirror the signatures of the corresponding WASI functions.
+#[allow(clippy::too_many_arguments)]
+impl WasiState {
+    fn args_get(&mut self, store: &mut Store, argv: u32, argv_buf: u32) -> Result<(), Errno> {
+        write_strings(self.bytes(store)?, &self.args, argv, argv_buf)
+    }
+
+    fn args_sizes_get(
+        &mut self,
+        store: &mut Store,
+        argc_ptr: u32,
+        argv_buf_size_ptr: u32,
+    ) -> Result<(), Errno> {
+        write_string_sizes(self.bytes(store)?, &self.args, argc_ptr, argv_buf_size_ptr)
+    }
+
+    fn environ_get(
+        &mut self,
+        store: &mut Store,
+        environ: u32,
+        environ_buf: u32,
+    ) -> Result<(), Errno> {
+        write_strings(self.bytes(store)?, &self.envs, environ, environ_buf)
+    }
+
+    fn environ_sizes_get(
+        &mut self,
+        store: &mut Store,
+        environc_ptr: u32,
+        environ_buf_size_ptr: u32,
+    ) -> Result<(), Errno> {
+        write_string_sizes(
+            self.bytes(store)?,
+            &self.envs,
+            environc_ptr,
+            environ_buf_size_ptr,
+        )
+    }
+
+    fn clock_res_get(&mut self, store: &mut Store, id: u32, res_ptr: u32) -> Result<(), Errno> {
+        if id > CLOCK_THREAD_CPUTIME_ID {
+            return Err(ERRNO_INVAL);
+        }
+        write_u64(self.bytes(store)?, res_ptr, 1_000)
+    }
+
+    fn clock_time_get(
+        &mut self,
+        store: &mut Store,
+        id: u32,
+        _precision: u64,
+        time_ptr: u32,
+    ) -> Result<(), Errno> {
+        let time = match id {
+            CLOCK_REALTIME_ID => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|_| ERRNO_INVAL)?,
+            CLOCK_MONOTONIC_ID | CLOCK_PROCESS_CPUTIME_ID | CLOCK_THREAD_CPUTIME_ID => {
+                self.start_time.elapsed()
+            }
+            _ => return Err(ERRNO_INVAL),
+        };
+        write_u64(self.bytes(store)?, time_ptr, time.as_nanos() as u64)
+    }
+
+    fn fd_advise(
+        &mut self,
+        _store: &mut Store,
+        fd: u32,
+        _offset: u64,
+        _len: u64,
+        _advice: u32,
+    ) -> Result<(), Errno> {
+        // Advice is only a hint, so we can safely ignore it.
+        self.fd(fd)?;
+        Ok(())
+    }
+
+    fn fd_allocate(
+        &mut self,
+        _store: &mut Store,
+        _fd: u32,
+        _offset: u64,
+        _len: u64,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn fd_close(&mut self, _store: &mut Store, fd: u32) -> Result<(), Errno> {
+        self.fds.remove(&fd).ok_or(ERRNO_BADF)?;
+        Ok(())
+    }
+
+    fn fd_datasync(&mut self, _store: &mut Store, fd: u32) -> Result<(), Errno> {
+        self.sync(fd, true)
+    }
+
+    fn fd_fdstat_get(&mut self, store: &mut Store, fd: u32, stat_ptr: u32) -> Result<(), Errno> {
+        let (filetype, flags) = match self.fd(fd)? {
+            FdEntry::Stdin | FdEntry::Stdout | FdEntry::Stderr => (FILETYPE_CHARACTER_DEVICE, 0),
+            FdEntry::Dir { .. } => (FILETYPE_DIRECTORY, 0),
+            FdEntry::File { append, .. } => (
+                FILETYPE_REGULAR_FILE,
+                if *append { FDFLAGS_APPEND } else { 0 },
+            ),
+        };
+        let bytes = self.bytes(store)?;
+        write_bytes(bytes, stat_ptr, &[0; 24])?;
+        write_u8(bytes, stat_ptr, filetype)?;
+        write_u16(bytes, stat_ptr + 2, flags)?;
+        write_u64(bytes, stat_ptr + 8, RIGHTS_ALL)?;
+        write_u64(bytes, stat_ptr + 16, RIGHTS_ALL)
+    }
+
+    fn fd_fdstat_set_flags(
+        &mut self,
+        _store: &mut Store,
+        fd: u32,
+        _flags: u32,
+    ) -> Result<(), Errno> {
+        self.fd(fd)?;
+        Err(ERRNO_NOSYS)
+    }
+
+    fn fd_fdstat_set_rights(
+        &mut self,
+        _store: &mut Store,
+        fd: u32,
+        _rights_base: u64,
+        _rights_inheriting: u64,
+    ) -> Result<(), Errno> {
+        // We don't track rights, so there is nothing to restrict.
+        self.fd(fd)?;
+        Ok(())
+    }
+
+    fn fd_filestat_get(&mut self, store: &mut Store, fd: u32, stat_ptr: u32) -> Result<(), Errno> {
+        let stat = match self.fd(fd)? {
+            FdEntry::Stdin | FdEntry::Stdout | FdEntry::Stderr => Filestat {
+                filetype: FILETYPE_CHARACTER_DEVICE,
+                ..Filestat::default()
+            },
+            FdEntry::Dir { path, .. } => Filestat::from_metadata(&fs::metadata(path)?),
+            FdEntry::File { file, .. } => Filestat::from_metadata(&file.metadata()?),
+        };
+        stat.write(self.bytes(store)?, stat_ptr)
+    }
+
+    fn fd_filestat_set_size(
+        &mut self,
+        _store: &mut Store,
+        fd: u32,
+        size: u64,
+    ) -> Result<(), Errno> {
+        match self.fd_mut(fd)? {
+            FdEntry::File { file, .. } => file.set_len(size).map_err(Errno::from),
+            _ => Err(ERRNO_INVAL),
+        }
+    }
+
+    fn fd_filestat_set_times(
+        &mut self,
+        _store: &mut Store,
+        _fd: u32,
+        _atim: u64,
+        _mtim: u64,
+        _fst_flags: u32,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn fd_pread(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        offset: u64,
+        nread_ptr: u32,
+    ) -> Result<(), Errno> {
+        let bytes = self.bytes(store)?;
+        let FdEntry::File { file, .. } = self.fd_mut(fd)? else {
+            return Err(ERRNO_SPIPE);
+        };
+        let old_offset = file.stream_position()?;
+        file.seek(SeekFrom::Start(offset))?;
+        let result = read_iovs(bytes, file, iovs, iovs_len);
+        file.seek(SeekFrom::Start(old_offset))?;
+        write_u32(bytes, nread_ptr, result?)
+    }
+
+    fn fd_prestat_get(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        prestat_ptr: u32,
+    ) -> Result<(), Errno> {
+        let Some(FdEntry::Dir {
+            preopened_path: Some(preopened_path),
+            ..
+        }) = self.fds.get(&fd)
+        else {
+            return Err(ERRNO_BADF);
+        };
+        let len = preopened_path.len() as u32;
+        let bytes = self.bytes(store)?;
+        write_bytes(bytes, prestat_ptr, &[0; 8])?;
+        write_u8(bytes, prestat_ptr, PREOPENTYPE_DIR)?;
+        write_u32(bytes, prestat_ptr + 4, len)
+    }
+
+    fn fd_prestat_dir_name(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(), Errno> {
+        let Some(FdEntry::Dir {
+            preopened_path: Some(preopened_path),
+            ..
+        }) = self.fds.get(&fd)
+        else {
+            return Err(ERRNO_BADF);
+        };
+        let name = preopened_path.as_bytes();
+        if name.len() > path_len as usize {
+            return Err(ERRNO_NAMETOOLONG);
+        }
+        write_bytes(self.bytes(store)?, path, name)
+    }
+
+    fn fd_pwrite(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        offset: u64,
+        nwritten_ptr: u32,
+    ) -> Result<(), Errno> {
+        let bytes = self.bytes(store)?;
+        let FdEntry::File { file, .. } = self.fd_mut(fd)? else {
+            return Err(ERRNO_SPIPE);
+        };
+        let old_offset = file.stream_position()?;
+        file.seek(SeekFrom::Start(offset))?;
+        let result = write_iovs(bytes, file, iovs, iovs_len);
+        file.seek(SeekFrom::Start(old_offset))?;
+        write_u32(bytes, nwritten_ptr, result?)
+    }
+
+    fn fd_read(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        nread_ptr: u32,
+    ) -> Result<(), Errno> {
+        let bytes = self.bytes(store)?;
+        let nread = read_iovs(bytes, self.reader(fd)?, iovs, iovs_len)?;
+        write_u32(bytes, nread_ptr, nread)
+    }
+
+    fn fd_readdir(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        buf: u32,
+        buf_len: u32,
+        cookie: u64,
+        bufused_ptr: u32,
+    ) -> Result<(), Errno> {
+        let FdEntry::Dir { path, .. } = self.fd(fd)? else {
+            return Err(ERRNO_NOTDIR);
+        };
+        let mut entries = vec![
+            (".".to_string(), FILETYPE_DIRECTORY),
+            ("..".to_string(), FILETYPE_DIRECTORY),
+        ];
+        let mut children = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let filetype = entry
+                .file_type()
+                .map(filetype_from_file_type)
+                .unwrap_or(FILETYPE_UNKNOWN);
+            children.push((entry.file_name().to_string_lossy().into_owned(), filetype));
+        }
+        children.sort();
+        entries.extend(children);
+
+        // Entries are written back to back, each consisting of a 24-byte header followed by its
+        // name. If the buffer is too small, the last entry is truncated, which tells the caller
+        // to call us again with a larger buffer.
+        let mut dirents = Vec::new();
+        for (idx, (name, filetype)) in entries.iter().enumerate().skip(cookie as usize) {
+            if dirents.len() >= buf_len as usize {
+                break;
+            }
+            dirents.extend_from_slice(&(idx as u64 + 1).to_le_bytes());
+            dirents.extend_from_slice(&0u64.to_le_bytes());
+            dirents.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            dirents.extend_from_slice(&[*filetype, 0, 0, 0]);
+            dirents.extend_from_slice(name.as_bytes());
+        }
+        dirents.truncate(buf_len as usize);
+        let bytes = self.bytes(store)?;
+        write_bytes(bytes, buf, &dirents)?;
+        write_u32(bytes, bufused_ptr, dirents.len() as u32)
+    }
+
+    fn fd_renumber(&mut self, _store: &mut Store, fd: u32, to: u32) -> Result<(), Errno> {
+        self.fd(to)?;
+        let entry = self.fds.remove(&fd).ok_or(ERRNO_BADF)?;
+        self.fds.insert(to, entry);
+        Ok(())
+    }
+
+    fn fd_seek(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        offset: i64,
+        whence: u32,
+        newoffset_ptr: u32,
+    ) -> Result<(), Errno> {
+        let FdEntry::File { file, .. } = self.fd_mut(fd)? else {
+            return Err(ERRNO_SPIPE);
+        };
+        let pos = match whence {
+            WHENCE_SET => SeekFrom::Start(u64::try_from(offset).map_err(|_| ERRNO_INVAL)?),
+            WHENCE_CUR => SeekFrom::Current(offset),
+            WHENCE_END => SeekFrom::End(offset),
+            _ => return Err(ERRNO_INVAL),
+        };
+        let new_offset = file.seek(pos)?;
+        write_u64(self.bytes(store)?, newoffset_ptr, new_offset)
+    }
+
+    fn fd_sync(&mut self, _store: &mut Store, fd: u32) -> Result<(), Errno> {
+        self.sync(fd, false)
+    }
+
+    fn fd_tell(&mut self, store: &mut Store, fd: u32, offset_ptr: u32) -> Result<(), Errno> {
+        let FdEntry::File { file, .. } = self.fd_mut(fd)? else {
+            return Err(ERRNO_SPIPE);
+        };
+        let offset = file.stream_position()?;
+        write_u64(self.bytes(store)?, offset_ptr, offset)
+    }
+
+    fn fd_write(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        iovs: u32,
+        iovs_len: u32,
+        nwritten_ptr: u32,
+    ) -> Result<(), Errno> {
+        let bytes = self.bytes(store)?;
+        let nwritten = write_iovs(bytes, self.writer(fd)?, iovs, iovs_len)?;
+        write_u32(bytes, nwritten_ptr, nwritten)
+    }
+
+    fn path_create_directory(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(), Errno> {
+        let path = self.resolve_path(store, fd, path, path_len)?;
+        fs::create_dir(path)?;
+        Ok(())
+    }
+
+    fn path_filestat_get(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        flags: u32,
+        path: u32,
+        path_len: u32,
+        stat_ptr: u32,
+    ) -> Result<(), Errno> {
+        let path = self.resolve_path(store, fd, path, path_len)?;
+        let metadata = if flags & LOOKUPFLAGS_SYMLINK_FOLLOW != 0 {
+            fs::metadata(path)?
+        } else {
+            fs::symlink_metadata(path)?
+        };
+        Filestat::from_metadata(&metadata).write(self.bytes(store)?, stat_ptr)
+    }
+
+    fn path_filestat_set_times(
+        &mut self,
+        _store: &mut Store,
+        _fd: u32,
+        _flags: u32,
+        _path: u32,
+        _path_len: u32,
+        _atim: u64,
+        _mtim: u64,
+        _fst_flags: u32,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn path_link(
+        &mut self,
+        _store: &mut Store,
+        _old_fd: u32,
+        _old_flags: u32,
+        _old_path: u32,
+        _old_path_len: u32,
+        _new_fd: u32,
+        _new_path: u32,
+        _new_path_len: u32,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn path_open(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        _dirflags: u32,
+        path: u32,
+        path_len: u32,
+        oflags: u32,
+        rights_base: u64,
+        _rights_inheriting: u64,
+        fdflags: u32,
+        fd_ptr: u32,
+    ) -> Result<(), Errno> {
+        let root = match self.fd(fd)? {
+            FdEntry::Dir { root, .. } => root.clone(),
+            _ => return Err(ERRNO_NOTDIR),
+        };
+        let path = self.resolve_path(store, fd, path, path_len)?;
+        let oflags = oflags as u16;
+        let fdflags = fdflags as u16;
+        let is_dir = path.is_dir();
+        let entry =
+            if oflags & OFLAGS_DIRECTORY != 0 || is_dir && rights_base & RIGHTS_FD_WRITE == 0 {
+                if !is_dir {
+                    return Err(if path.exists() {
+                        ERRNO_NOTDIR
+                    } else {
+                        ERRNO_NOENT
+                    });
+                }
+                FdEntry::Dir {
+                    path,
+                    root,
+                    preopened_path: None,
+                }
+            } else {
+                let append = fdflags & FDFLAGS_APPEND != 0;
+                let write = rights_base & RIGHTS_FD_WRITE != 0
+                    || append
+                    || oflags & (OFLAGS_CREAT | OFLAGS_TRUNC) != 0;
+                let read = rights_base & RIGHTS_FD_READ != 0 || !write;
+                let create = oflags & OFLAGS_CREAT != 0;
+                let exclusive = oflags & OFLAGS_EXCL != 0;
+                let file = fs::OpenOptions::new()
+                    .read(read)
+                    .write(write && !append)
+                    .append(append)
+                    .create(create && !exclusive)
+                    .create_new(create && exclusive)
+                    .truncate(oflags & OFLAGS_TRUNC != 0)
+                    .open(&path)?;
+                FdEntry::File { file, append }
+            };
+        let new_fd = (3..).find(|fd| !self.fds.contains_key(fd)).unwrap();
+        write_u32(self.bytes(store)?, fd_ptr, new_fd)?;
+        self.fds.insert(new_fd, entry);
+        Ok(())
+    }
+
+    fn path_readlink(
+        &mut self,
+        _store: &mut Store,
+        _fd: u32,
+        _path: u32,
+        _path_len: u32,
+        _buf: u32,
+        _buf_len: u32,
+        _bufused_ptr: u32,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn path_remove_directory(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(), Errno> {
+        let path = self.resolve_path(store, fd, path, path_len)?;
+        fs::remove_dir(path)?;
+        Ok(())
+    }
+
+    fn path_rename(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        old_path: u32,
+        old_path_len: u32,
+        new_fd: u32,
+        new_path: u32,
+        new_path_len: u32,
+    ) -> Result<(), Errno> {
+        let old_path = self.resolve_path(store, fd, old_path, old_path_len)?;
+        let new_path = self.resolve_path(store, new_fd, new_path, new_path_len)?;
+        fs::rename(old_path, new_path)?;
+        Ok(())
+    }
+
+    fn path_symlink(
+        &mut self,
+        _store: &mut Store,
+        _old_path: u32,
+        _old_path_len: u32,
+        _fd: u32,
+        _new_path: u32,
+        _new_path_len: u32,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn path_unlink_file(
+        &mut self,
+        store: &mut Store,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<(), Errno> {
+        let path = self.resolve_path(store, fd, path, path_len)?;
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    fn poll_oneoff(
+        &mut self,
+        _store: &mut Store,
+        _in: u32,
+        _out: u32,
+        _nsubscriptions: u32,
+        _nevents_ptr: u32,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn proc_raise(&mut self, _store: &mut Store, _sig: u32) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn random_get(&mut self, store: &mut Store, buf: u32, buf_len: u32) -> Result<(), Errno> {
+        let buf = slice_mut(self.bytes(store)?, buf, buf_len)?;
+        fill_random(buf);
+        Ok(())
+    }
+
+    fn sched_yield(&mut self, _store: &mut Store) -> Result<(), Errno> {
+        thread::yield_now();
+        Ok(())
+    }
+
+    fn sock_accept(
+        &mut self,
+        _store: &mut Store,
+        _fd: u32,
+        _flags: u32,
+        _fd_ptr: u32,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn sock_recv(
+        &mut self,
+        _store: &mut Store,
+        _fd: u32,
+        _ri_data: u32,
+        _ri_data_len: u32,
+        _ri_flags: u32,
+        _ro_datalen_ptr: u32,
+        _ro_flags_ptr: u32,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn sock_send(
+        &mut self,
+        _store: &mut Store,
+        _fd: u32,
+        _si_data: u32,
+        _si_data_len: u32,
+        _si_flags: u32,
+        _so_datalen_ptr: u32,
+    ) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    fn sock_shutdown(&mut self, _store: &mut Store, _fd: u32, _how: u32) -> Result<(), Errno> {
+        Err(ERRNO_NOSYS)
+    }
+
+    // Helper methods
+
+    /// Returns the bytes of the memory of the Wasm program.
+    fn bytes<'a>(&self, store: &'a mut Store) -> Result<&'a mut [u8], Errno> {
+        let mem = Mem(store.mem_at(self.mem.ok_or(ERRNO_FAULT)?));
+        Ok(mem.bytes_mut(store))
+    }
+
+    fn fd(&self, fd: u32) -> Result<&FdEntry, Errno> {
+        self.fds.get(&fd).ok_or(ERRNO_BADF)
+    }
+
+    fn fd_mut(&mut self, fd: u32) -> Result<&mut FdEntry, Errno> {
+        self.fds.get_mut(&fd).ok_or(ERRNO_BADF)
+    }
+
+    fn sync(&mut self, fd: u32, data_only: bool) -> Result<(), Errno> {
+        match self.fds.get_mut(&fd).ok_or(ERRNO_BADF)? {
+            FdEntry::Stdin | FdEntry::Dir { .. } => {}
+            FdEntry::Stdout => self.stdout.flush()?,
+            FdEntry::Stderr => self.stderr.flush()?,
+            FdEntry::File { file, .. } if data_only => file.sync_data()?,
+            FdEntry::File { file, .. } => file.sync_all()?,
+        }
+        Ok(())
+    }
+
+    fn reader(&mut self, fd: u32) -> Result<&mut dyn Read, Errno> {
+        match self.fds.get_mut(&fd).ok_or(ERRNO_BADF)? {
+            FdEntry::Stdin => Ok(&mut self.stdin),
+            FdEntry::File { file, .. } => Ok(file),
+            FdEntry::Dir { .. } => Err(ERRNO_ISDIR),
+            FdEntry::Stdout | FdEntry::Stderr => Err(ERRNO_BADF),
+        }
+    }
+
+    fn writer(&mut self, fd: u32) -> Result<&mut dyn Write, Errno> {
+        match self.fds.get_mut(&fd).ok_or(ERRNO_BADF)? {
+            FdEntry::Stdout => Ok(&mut self.stdout),
+            FdEntry::Stderr => Ok(&mut self.stderr),
+            FdEntry::File { file, .. } => Ok(file),
+            FdEntry::Dir { .. } => Err(ERRNO_ISDIR),
+            FdEntry::Stdin => Err(ERRNO_BADF),
+        }
+    }
+
+    /// Resolves the path in the memory of the Wasm program, relative to the directory with the
+    /// given file descriptor, to a path on the host.
+    ///
+    /// Fails with `ERRNO_NOTCAPABLE` if the resolved path is outside the preopened directory that
+    /// the directory was opened from, including via symbolic links.
+    fn resolve_path(
+        &self,
+        store: &mut Store,
+        fd: u32,
+        path: u32,
+        path_len: u32,
+    ) -> Result<PathBuf, Errno> {
+        let FdEntry::Dir {
+            path: dir_path,
+            root,
+            ..
+        } = self.fd(fd)?
+        else {
+            return Err(ERRNO_NOTDIR);
+        };
+        let bytes = self.bytes(store)?;
+        let path = std::str::from_utf8(slice(bytes, path, path_len)?).map_err(|_| ERRNO_ILSEQ)?;
+        let mut resolved_path = dir_path.clone();
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(name) => resolved_path.push(name),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !resolved_path.pop() {
+                        return Err(ERRNO_NOTCAPABLE);
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => return Err(ERRNO_NOTCAPABLE),
+            }
+        }
+        if !resolved_path.starts_with(root) {
+            return Err(ERRNO_NOTCAPABLE);
+        }
+
+        // Check that symbolic links don't lead outside the preopened directory, by resolving the
+        // longest prefix of the path that exists.
+        let mut existing_path = resolved_path.as_path();
+        loop {
+            if let Ok(canonical_path) = existing_path.canonicalize() {
+                if !canonical_path.starts_with(root) {
+                    return Err(ERRNO_NOTCAPABLE);
+                }
+                break;
+            }
+            match existing_path.parent() {
+                Some(parent) => existing_path = parent,
+                None => break,
+            }
+        }
+        Ok(resolved_path)
+    }
+}
+
+impl fmt::Debug for WasiState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasiState")
+            .field("args", &self.args)
+            .field("envs", &self.envs)
+            .field("fds", &self.fds)
+            .field("mem", &self.mem)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+enum FdEntry {
+    Stdin,
+    Stdout,
+    Stderr,
+    Dir {
+        path: PathBuf,
+        // The preopened directory that this directory was opened from.
+        root: PathBuf,
+        // The path under which the Wasm program sees this directory, if it was preopened.
+        preopened_path: Option<String>,
+    },
+    File {
+        file: fs::File,
+        append: bool,
+    },
+}
+
+#[derive(Debug, Default)]
+struct Filestat {
+    dev: u64,
+    ino: u64,
+    filetype: u8,
+    nlink: u64,
+    size: u64,
+    atim: u64,
+    mtim: u64,
+    ctim: u64,
+}
+
+impl Filestat {
+    fn from_metadata(metadata: &fs::Metadata) -> Self {
+        fn nanos(time: io::Result<SystemTime>) -> u64 {
+            time.ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |duration| duration.as_nanos() as u64)
+        }
+
+        #[cfg(unix)]
+        let (dev, ino, nlink) = {
+            use std::os::unix::fs::MetadataExt;
+
+            (metadata.dev(), metadata.ino(), metadata.nlink())
+        };
+        #[cfg(not(unix))]
+        let (dev, ino, nlink) = (0, 0, 1);
+        Self {
+            dev,
+            ino,
+            filetype: filetype_from_file_type(metadata.file_type()),
+            nlink,
+            size: metadata.len(),
+            atim: nanos(metadata.accessed()),
+            mtim: nanos(metadata.modified()),
+            ctim: nanos(metadata.created()),
+        }
+    }
+
+    fn write(&self, bytes: &mut [u8], ptr: u32) -> Result<(), Errno> {
+        write_bytes(bytes, ptr, &[0; 64])?;
+        write_u64(bytes, ptr, self.dev)?;
+        write_u64(bytes, ptr + 8, self.ino)?;
+        write_u8(bytes, ptr + 16, self.filetype)?;
+        write_u64(bytes, ptr + 24, self.nlink)?;
+        write_u64(bytes, ptr + 32, self.size)?;
+        write_u64(bytes, ptr + 40, self.atim)?;
+        write_u64(bytes, ptr + 48, self.mtim)?;
+        write_u64(bytes, ptr + 56, self.ctim)
+    }
+}
+
+/// A WASI error number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Errno(u16);
+
+impl From<io::Error> for Errno {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => ERRNO_NOENT,
+            io::ErrorKind::PermissionDenied => ERRNO_ACCES,
+            io::ErrorKind::AlreadyExists => ERRNO_EXIST,
+            io::ErrorKind::InvalidInput => ERRNO_INVAL,
+            io::ErrorKind::NotADirectory => ERRNO_NOTDIR,
+            io::ErrorKind::IsADirectory => ERRNO_ISDIR,
+            io::ErrorKind::DirectoryNotEmpty => ERRNO_NOTEMPTY,
+            io::ErrorKind::Unsupported => ERRNO_NOSYS,
+            _ => ERRNO_IO,
+        }
+    }
+}
+
+const ERRNO_SUCCESS: Errno = Errno(0);
+const ERRNO_ACCES: Errno = Errno(2);
+const ERRNO_BADF: Errno = Errno(8);
+const ERRNO_EXIST: Errno = Errno(20);
+const ERRNO_FAULT: Errno = Errno(21);
+const ERRNO_ILSEQ: Errno = Errno(25);
+const ERRNO_INVAL: Errno = Errno(28);
+const ERRNO_IO: Errno = Errno(29);
+const ERRNO_ISDIR: Errno = Errno(31);
+const ERRNO_NAMETOOLONG: Errno = Errno(37);
+const ERRNO_NOENT: Errno = Errno(44);
+const ERRNO_NOSYS: Errno = Errno(52);
+const ERRNO_NOTDIR: Errno = Errno(54);
+const ERRNO_NOTEMPTY: Errno = Errno(55);
+const ERRNO_SPIPE: Errno = Errno(70);
+const ERRNO_NOTCAPABLE: Errno = Errno(76);
+
+const CLOCK_REALTIME_ID: u32 = 0;
+const CLOCK_MONOTONIC_ID: u32 = 1;
+const CLOCK_PROCESS_CPUTIME_ID: u32 = 2;
+const CLOCK_THREAD_CPUTIME_ID: u32 = 3;
+
+const FILETYPE_UNKNOWN: u8 = 0;
+const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+const FILETYPE_DIRECTORY: u8 = 3;
+const FILETYPE_REGULAR_FILE: u8 = 4;
+const FILETYPE_SYMBOLIC_LINK: u8 = 7;
+
+const FDFLAGS_APPEND: u16 = 1 << 0;
+
+const LOOKUPFLAGS_SYMLINK_FOLLOW: u32 = 1 << 0;
+
+const OFLAGS_CREAT: u16 = 1 << 0;
+const OFLAGS_DIRECTORY: u16 = 1 << 1;
+const OFLAGS_EXCL: u16 = 1 << 2;
+const OFLAGS_TRUNC: u16 = 1 << 3;
+
+const RIGHTS_FD_READ: u64 = 1 << 1;
+const RIGHTS_FD_WRITE: u64 = 1 << 6;
+const RIGHTS_ALL: u64 = (1 << 30) - 1;
+
+const PREOPENTYPE_DIR: u8 = 0;
+
+const WHENCE_SET: u32 = 0;
+const WHENCE_CUR: u32 = 1;
+const WHENCE_END: u32 = 2;
+
+fn filetype_from_file_type(file_type: fs::FileType) -> u8 {
+    if file_type.is_dir() {
+        FILETYPE_DIRECTORY
+    } else if file_type.is_file() {
+        FILETYPE_REGULAR_FILE
+    } else if file_type.is_symlink() {
+        FILETYPE_SYMBOLIC_LINK
+    } else {
+        FILETYPE_UNKNOWN
+    }
+}
+
+/// Writes the given strings to memory as null-terminated strings starting at `buf`, and a pointer
+/// to each string to the array at `ptrs`.
+fn write_strings(bytes: &mut [u8], strings: &[String], ptrs: u32, buf: u32) -> Result<(), Errno> {
+    let mut ptr = buf;
+    for (idx, string) in strings.iter().enumerate() {
+        write_u32(bytes, ptrs + 4 * idx as u32, ptr)?;
+        write_bytes(bytes, ptr, string.as_bytes())?;
+        ptr += string.len() as u32;
+        write_u8(bytes, ptr, 0)?;
+        ptr += 1;
+    }
+    Ok(())
+}
+
+/// Writes the number of given strings to `count_ptr`, and the size of the buffer required to
+/// hold them, as null-terminated strings, to `buf_size_ptr`.
+fn write_string_sizes(
+    bytes: &mut [u8],
+    strings: &[String],
+    count_ptr: u32,
+    buf_size_ptr: u32,
+) -> Result<(), Errno> {
+    let buf_size: usize = strings.iter().map(|string| string.len() + 1).sum();
+    write_u32(bytes, count_ptr, strings.len() as u32)?;
+    write_u32(bytes, buf_size_ptr, buf_size as u32)
+}
+
+/// Reads from the given reader into the I/O vectors at `iovs`, and returns the number of bytes
+/// read.
+fn read_iovs(
+    bytes: &mut [u8],
+    reader: &mut dyn Read,
+    iovs: u32,
+    iovs_len: u32,
+) -> Result<u32, Errno> {
+    let mut nread = 0;
+    for idx in 0..iovs_len {
+        let buf = read_u32(bytes, iovs + 8 * idx)?;
+        let buf_len = read_u32(bytes, iovs + 8 * idx + 4)?;
+        let buf = slice_mut(bytes, buf, buf_len)?;
+        let n = reader.read(buf)?;
+        nread += n as u32;
+        if n < buf.len() {
+            break;
+        }
+    }
+    Ok(nread)
+}
+
+/// Writes the I/O vectors at `iovs` to the given writer, and returns the number of bytes written.
+fn write_iovs(
+    bytes: &mut [u8],
+    writer: &mut dyn Write,
+    iovs: u32,
+    iovs_len: u32,
+) -> Result<u32, Errno> {
+    let mut nwritten = 0;
+    for idx in 0..iovs_len {
+        let buf = read_u32(bytes, iovs + 8 * idx)?;
+        let buf_len = read_u32(bytes, iovs + 8 * idx + 4)?;
+        writer.write_all(slice(bytes, buf, buf_len)?)?;
+        nwritten += buf_len;
+    }
+    Ok(nwritten)
+}
+
+fn fill_random(buf: &mut [u8]) {
+    if fs::File::open("/dev/urandom")
+        .and_then(|mut file| file.read_exact(buf))
+        .is_ok()
+    {
+        return;
+    }
+
+    // Fall back to the randomly seeded hasher from the standard library.
+    let state = RandomState::new();
+    for (idx, chunk) in buf.chunks_mut(8).enumerate() {
+        let mut hasher = state.build_hasher();
+        hasher.write_usize(idx);
+        chunk.copy_from_slice(&hasher.finish().to_le_bytes()[..chunk.len()]);
+    }
+}
+
+fn slice(bytes: &[u8], ptr: u32, len: u32) -> Result<&[u8], Errno> {
+    bytes
+        .get(ptr as usize..)
+        .and_then(|bytes| bytes.get(..len as usize))
+        .ok_or(ERRNO_FAULT)
+}
+
+fn slice_mut(bytes: &mut [u8], ptr: u32, len: u32) -> Result<&mut [u8], Errno> {
+    bytes
+        .get_mut(ptr as usize..)
+        .and_then(|bytes| bytes.get_mut(..len as usize))
+        .ok_or(ERRNO_FAULT)
+}
+
+fn read_u32(bytes: &[u8], ptr: u32) -> Result<u32, Errno> {
+    Ok(u32::from_le_bytes(
+        slice(bytes, ptr, 4)?.try_into().unwrap(),
+    ))
+}
+
+fn write_bytes(bytes: &mut [u8], ptr: u32, data: &[u8]) -> Result<(), Errno> {
+    slice_mut(bytes, ptr, data.len() as u32)?.copy_from_slice(data);
+    Ok(())
+}
+
+fn write_u8(bytes: &mut [u8], ptr: u32, val: u8) -> Result<(), Errno> {
+    write_bytes(bytes, ptr, &[val])
+}
+
+fn write_u16(bytes: &mut [u8], ptr: u32, val: u16) -> Result<(), Errno> {
+    write_bytes(bytes, ptr, &val.to_le_bytes())
+}
+
+fn write_u32(bytes: &mut [u8], ptr: u32, val: u32) -> Result<(), Errno> {
+    write_bytes(bytes, ptr, &val.to_le_bytes())
+}
+
+fn write_u64(bytes: &mut [u8], ptr: u32, val: u64) -> Result<(), Errno> {
+    write_bytes(bytes, ptr, &val.to_le_bytes())
+}
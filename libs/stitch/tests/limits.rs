@@ -0,0 +1,173 @@
+use {
+    makepad_stitch::{
+        Engine, EngineConfig, Error, Instance, Linker, Module, ResumableCall, Store, StoreLimits,
+        Trap, Val,
+    },
+    wast::{
+        parser::{self, ParseBuffer},
+        Wat,
+    },
+};
+
+const SUM: &str = r#"
+    (module
+        (func (export "sum") (param $n i32) (result i32) (local $acc i32)
+            (loop $loop
+                (local.set $acc (i32.add (local.get $acc) (local.get $n)))
+                (br_if $loop (local.tee $n (i32.sub (local.get $n) (i32.const 1))))
+            )
+            (local.get $acc)
+        )
+    )
+"#;
+
+const REC: &str = r#"
+    (module
+        (func $rec (export "rec") (param $n i32) (result i32)
+            (if (result i32) (i32.eqz (local.get $n))
+                (then (i32.const 0))
+                (else (i32.add (call $rec (i32.sub (local.get $n) (i32.const 1))) (i32.const 1)))
+            )
+        )
+    )
+"#;
+
+const MEM: &str = r#"
+    (module
+        (memory 1)
+        (func $grow (export "grow") (param $n i32) (result i32)
+            (memory.grow (local.get $n))
+        )
+        (func (export "grow_then_store") (result i32)
+            (drop (call $grow (i32.const 1)))
+            (i32.store (i32.const 65536) (i32.const 42))
+            (i32.load (i32.const 65536))
+        )
+    )
+"#;
+
+fn instantiate(store: &mut Store, wat: &str) -> Result<Instance, Error> {
+    let buf = ParseBuffer::new(wat).unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    let bytes = wat.encode().unwrap();
+    let module = Module::new(store.engine(), &bytes)?;
+    Linker::new().instantiate(store, &module)
+}
+
+#[test]
+fn fuel() {
+    let mut store = Store::new(Engine::with_config(EngineConfig::new().consume_fuel(true)));
+    let instance = instantiate(&mut store, SUM).unwrap();
+    let sum = instance.exported_func("sum").unwrap();
+    let mut results = [Val::I32(0)];
+    assert!(matches!(
+        sum.call(&mut store, &[Val::I32(100)], &mut results),
+        Err(Error::Trap(Trap::OutOfFuel))
+    ));
+    store.set_fuel(1_000);
+    sum.call(&mut store, &[Val::I32(10)], &mut results).unwrap();
+    assert_eq!(results[0], Val::I32(55));
+    assert!(store.fuel() < 1_000);
+}
+
+#[test]
+fn fuel_resume() {
+    let mut store = Store::new(Engine::with_config(EngineConfig::new().consume_fuel(true)));
+    let instance = instantiate(&mut store, SUM).unwrap();
+    let sum = instance.exported_func("sum").unwrap();
+    let mut results = [Val::I32(0)];
+    store.set_fuel(50);
+    let mut call = sum
+        .call_resumable(&mut store, &[Val::I32(100)], &mut results)
+        .unwrap();
+    let mut suspend_count = 0;
+    while let ResumableCall::OutOfFuel(suspended_call) = call {
+        suspend_count += 1;
+
+        // Other calls can be made while a call is suspended.
+        let mut other_results = [Val::I32(0)];
+        store.set_fuel(1_000);
+        sum.call(&mut store, &[Val::I32(10)], &mut other_results)
+            .unwrap();
+        assert_eq!(other_results[0], Val::I32(55));
+
+        store.set_fuel(50);
+        call = suspended_call.resume(&mut store, &mut results).unwrap();
+    }
+    assert!(suspend_count > 1);
+    assert_eq!(results[0], Val::I32(5050));
+}
+
+#[test]
+fn fuel_resume_nested_calls() {
+    let mut store = Store::new(Engine::with_config(EngineConfig::new().consume_fuel(true)));
+    let instance = instantiate(&mut store, REC).unwrap();
+    let rec = instance.exported_func("rec").unwrap();
+    let mut results = [Val::I32(0)];
+    let mut call = rec
+        .call_resumable(&mut store, &[Val::I32(500)], &mut results)
+        .unwrap();
+    while let ResumableCall::OutOfFuel(suspended_call) = call {
+        store.add_fuel(7);
+        call = suspended_call.resume(&mut store, &mut results).unwrap();
+    }
+    assert_eq!(results[0], Val::I32(500));
+}
+
+#[test]
+fn call_depth() {
+    let mut store = Store::new(Engine::new());
+    let instance = instantiate(&mut store, REC).unwrap();
+    let rec = instance.exported_func("rec").unwrap();
+    store.set_limits(StoreLimits {
+        max_call_depth: Some(100),
+        ..StoreLimits::default()
+    });
+    let mut results = [Val::I32(0)];
+    rec.call(&mut store, &[Val::I32(99)], &mut results).unwrap();
+    assert_eq!(results[0], Val::I32(99));
+    assert!(matches!(
+        rec.call(&mut store, &[Val::I32(100)], &mut results),
+        Err(Error::Trap(Trap::CallDepthExceeded))
+    ));
+    rec.call(&mut store, &[Val::I32(99)], &mut results).unwrap();
+    assert_eq!(results[0], Val::I32(99));
+}
+
+#[test]
+fn mem_pages() {
+    let mut store = Store::new(Engine::new());
+    let instance = instantiate(&mut store, MEM).unwrap();
+    let grow = instance.exported_func("grow").unwrap();
+    store.set_limits(StoreLimits {
+        max_mem_pages: Some(4),
+        ..StoreLimits::default()
+    });
+    let mut results = [Val::I32(0)];
+    grow.call(&mut store, &[Val::I32(3)], &mut results).unwrap();
+    assert_eq!(results[0], Val::I32(1));
+    assert!(matches!(
+        grow.call(&mut store, &[Val::I32(1)], &mut results),
+        Err(Error::Trap(Trap::MemLimitExceeded))
+    ));
+
+    let mut store = Store::new(Engine::new());
+    store.set_limits(StoreLimits {
+        max_mem_pages: Some(0),
+        ..StoreLimits::default()
+    });
+    assert!(matches!(
+        instantiate(&mut store, MEM),
+        Err(Error::Trap(Trap::MemLimitExceeded))
+    ));
+}
+
+#[test]
+fn mem_grow_in_callee() {
+    let mut store = Store::new(Engine::new());
+    let instance = instantiate(&mut store, MEM).unwrap();
+    let grow_then_store = instance.exported_func("grow_then_store").unwrap();
+    let mut results = [Val::I32(0)];
+    grow_then_store.call(&mut store, &[], &mut results).unwrap();
+    assert_eq!(results[0], Val::I32(42));
+}
@@ -0,0 +1,207 @@
+use {
+    makepad_stitch::{Engine, Linker, Module, Store, Wasi, WasiConfig},
+    std::{
+        io::{self, Write},
+        path::Path,
+        sync::{Arc, Mutex},
+    },
+    wast::{
+        parser::{self, ParseBuffer},
+        Wat,
+    },
+};
+
+const HELLO: &str = r#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "\10\00\00\00\0e\00\00\00")
+        (data (i32.const 16) "Hello, world!\n")
+        (func (export "_start")
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+        )
+    )
+"#;
+
+const ARGS: &str = r#"
+    (module
+        (import "wasi_snapshot_preview1" "args_sizes_get"
+            (func $args_sizes_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "args_get"
+            (func $args_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "environ_sizes_get"
+            (func $environ_sizes_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "environ_get"
+            (func $environ_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (func (export "_start")
+            ;; Write the argument buffer to stdout.
+            (drop (call $args_sizes_get (i32.const 0) (i32.const 12)))
+            (drop (call $args_get (i32.const 256) (i32.const 1024)))
+            (i32.store (i32.const 16) (i32.const 1024))
+            (i32.store (i32.const 20) (i32.load (i32.const 12)))
+            (drop (call $fd_write (i32.const 1) (i32.const 16) (i32.const 1) (i32.const 8)))
+
+            ;; Write the environment buffer to stdout.
+            (drop (call $environ_sizes_get (i32.const 0) (i32.const 12)))
+            (drop (call $environ_get (i32.const 256) (i32.const 1024)))
+            (i32.store (i32.const 20) (i32.load (i32.const 12)))
+            (drop (call $fd_write (i32.const 1) (i32.const 16) (i32.const 1) (i32.const 8)))
+        )
+    )
+"#;
+
+// Opens the file whose path is passed as the first argument after the program name, relative to
+// the first preopened directory, and copies it to stdout. Exits with the error number if the file
+// cannot be opened.
+const CAT: &str = r#"
+    (module
+        (import "wasi_snapshot_preview1" "args_get"
+            (func $args_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "args_sizes_get"
+            (func $args_sizes_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open"
+            (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_read"
+            (func $fd_read (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "proc_exit"
+            (func $proc_exit (param i32)))
+        (memory (export "memory") 1)
+        (func (export "_start") (local $errno i32) (local $path i32)
+            (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+            (drop (call $args_get (i32.const 256) (i32.const 1024)))
+            (local.set $path (i32.load (i32.const 260)))
+            (local.set $errno
+                (call $path_open
+                    (i32.const 3)
+                    (i32.const 0)
+                    (local.get $path)
+                    (i32.sub
+                        (i32.add (i32.const 1024) (i32.load (i32.const 4)))
+                        (i32.add (local.get $path) (i32.const 1))
+                    )
+                    (i32.const 0)
+                    (i64.const 2)
+                    (i64.const 0)
+                    (i32.const 0)
+                    (i32.const 8)
+                )
+            )
+            (if (local.get $errno) (then (call $proc_exit (local.get $errno))))
+            (i32.store (i32.const 16) (i32.const 4096))
+            (i32.store (i32.const 20) (i32.const 4096))
+            (drop (call $fd_read (i32.load (i32.const 8)) (i32.const 16) (i32.const 1) (i32.const 12)))
+            (i32.store (i32.const 20) (i32.load (i32.const 12)))
+            (drop (call $fd_write (i32.const 1) (i32.const 16) (i32.const 1) (i32.const 12)))
+            (call $proc_exit (i32.const 0))
+        )
+    )
+"#;
+
+#[derive(Clone, Debug, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn to_vec(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn run(wat: &str, config: WasiConfig) -> (i32, Vec<u8>) {
+    let stdout = Output::default();
+    let wasi = Wasi::new(config.stdout(stdout.clone()));
+    let mut store = Store::new(Engine::new());
+    let mut linker = Linker::new();
+    wasi.define(&mut store, &mut linker);
+    let buf = ParseBuffer::new(wat).unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    let bytes = wat.encode().unwrap();
+    let module = Module::new(store.engine(), &bytes).unwrap();
+    let instance = linker.instantiate(&mut store, &module).unwrap();
+    let code = wasi.start(&mut store, &instance).unwrap();
+    (code, stdout.to_vec())
+}
+
+fn fixtures_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/wasi"))
+}
+
+#[test]
+fn hello() {
+    let (code, stdout) = run(HELLO, WasiConfig::new());
+    assert_eq!(code, 0);
+    assert_eq!(stdout, b"Hello, world!\n");
+}
+
+#[test]
+fn args_and_env() {
+    let (code, stdout) = run(
+        ARGS,
+        WasiConfig::new().arg("prog").arg("arg").env("KEY", "val"),
+    );
+    assert_eq!(code, 0);
+    assert_eq!(stdout, b"prog\0arg\0KEY=val\0");
+}
+
+#[test]
+fn read_file() {
+    let (code, stdout) = run(
+        CAT,
+        WasiConfig::new()
+            .arg("cat")
+            .arg("hello.txt")
+            .preopened_dir(fixtures_dir(), "/fixtures"),
+    );
+    assert_eq!(code, 0);
+    assert_eq!(stdout, b"Hello from a fixture!\n");
+}
+
+#[test]
+fn read_missing_file() {
+    let (code, stdout) = run(
+        CAT,
+        WasiConfig::new()
+            .arg("cat")
+            .arg("missing.txt")
+            .preopened_dir(fixtures_dir(), "/fixtures"),
+    );
+    assert_eq!(code, 44);
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn sandbox() {
+    for path in ["../wasi.rs", "/etc/passwd", "subdir/../../wasi.rs"] {
+        let (code, stdout) = run(
+            CAT,
+            WasiConfig::new()
+                .arg("cat")
+                .arg(path)
+                .preopened_dir(fixtures_dir(), "/fixtures"),
+        );
+        assert_eq!(code, 76, "{}", path);
+        assert!(stdout.is_empty());
+    }
+}
+
+#[test]
+fn no_preopened_dir() {
+    let (code, _) = run(CAT, WasiConfig::new().arg("cat").arg("hello.txt"));
+    assert_eq!(code, 8);
+}
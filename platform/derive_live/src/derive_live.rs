@@ -424,6 +424,10 @@ fn derive_live_impl_inner(parser: &mut TokenParser, tb: &mut TokenBuilder) -> Re
                 }
                 tb.add("});");
             }
+            else if field.attrs.iter().any( | a | a.name == "walk" || a.name == "layout") {
+                // the splatted fields are set directly on the struct
+                tb.add("fields.extend(<").stream(Some(field.ty.clone())).add("as LiveNew>::live_type_info(cx).fields);");
+            }
         }
         tb.add("        LiveTypeInfo{");
         tb.add("            module_id: LiveModuleId::from_str(&module_path!()).unwrap(),");
@@ -431,6 +435,7 @@ fn derive_live_impl_inner(parser: &mut TokenParser, tb: &mut TokenBuilder) -> Re
         let live_ignore = main_attribs.iter().any( | attr | attr.name == "live_ignore");
         tb.add("            live_ignore: ").ident(if live_ignore {"true"} else {"false"}).add(",");
         tb.add("            fields,");
+        tb.add("            variants: Vec::new(),");
         
         tb.add("            type_name: LiveId::from_str_with_lut(").string(&struct_name).add(").unwrap()");
         tb.add("        }");
@@ -578,6 +583,15 @@ fn derive_live_impl_inner(parser: &mut TokenParser, tb: &mut TokenBuilder) -> Re
         tb.add("            module_id: LiveModuleId::from_str(&module_path!()).unwrap(),");
         tb.add("            live_type: LiveType::of::<Self>(),");
         tb.add("            fields: Vec::new(),");
+        tb.add("            variants: vec![");
+        for item in &items {
+            tb.add("(LiveId::from_str_with_lut(").string(&item.name).add(").unwrap(), LiveVariantKind::").ident(match item.kind {
+                EnumKind::Bare => "Bare",
+                EnumKind::Tuple(_) => "Tuple",
+                EnumKind::Named(_) => "Named",
+            }).add("),");
+        }
+        tb.add("            ],");
         let live_ignore = main_attribs.iter().any( | attr | attr.name == "live_ignore");
         tb.add("            live_ignore: ").ident(if live_ignore {"true"} else {"false"}).add(",");
         tb.add("            type_name: LiveId::from_str_with_lut(").string(&enum_name).add(").unwrap(),");
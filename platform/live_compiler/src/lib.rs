@@ -10,12 +10,16 @@ pub mod live_node_vec;
 pub mod live_document;
 pub mod live_registry; 
 pub mod live_expander;
+pub mod live_lint;
 pub mod live_ptr;
 pub mod live_eval;
+pub mod live_eval_fns;
+pub mod live_media;
 pub mod live_component;
 pub mod live_node_cbor;
 //pub mod live_node_cbor;
 pub mod live_node_reader;
+pub mod live_format;
 
 pub use makepad_math;
 pub use makepad_derive_live;
@@ -37,6 +41,16 @@ pub use {
         },
         live_eval::{
             live_eval_value,
+            live_eval_media_condition,
+        },
+        live_eval_fns::{
+            LiveEvalFn,
+            LiveEvalFns,
+        },
+        live_lint::LiveLinter,
+        live_media::{
+            LiveMedia,
+            LIVE_MEDIA_PLATFORMS,
         },
         live_registry::{
             LiveFileChange,
@@ -79,6 +93,7 @@ pub use {
             LiveTypeInfo,
             LiveTypeField,
             LiveFieldKind,
+            LiveVariantKind,
             LiveBinOp,
             LiveUnOp,
             LiveNodeOrigin,
@@ -99,6 +114,12 @@ pub use {
             LiveError,
             LiveFileError
         },
-        live_document::{LiveOriginal, LiveExpanded}
+        live_document::{LiveOriginal, LiveExpanded},
+        live_format::{
+            LiveFormatOptions,
+            LiveFormatError,
+            format_live_design,
+            format_live_design_in_rust
+        }
     }
 };
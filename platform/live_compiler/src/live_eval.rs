@@ -10,6 +10,7 @@ pub use {
         live_error::{LiveError},
         live_node_vec::*,
         live_registry::{LiveRegistry,LiveScopeTarget},
+        live_parser::LiveParser,
         live_node::*
     }
 };
@@ -47,6 +48,14 @@ impl LiveError {
         Self::eval_error(origin, index, nodes, format!("cant find target: {}", id))
     }
     
+    fn eval_error_member_undefined(origin: LiveErrorOrigin, index: usize, nodes: &[LiveNode], member: LiveId, a: LiveValue)->Self {
+        Self::eval_error(origin, index, nodes, format!("Member {} undefined for {:?}", member, a))
+    }
+    
+    fn eval_error_fn_call(origin: LiveErrorOrigin, index: usize, nodes: &[LiveNode], ident: LiveId, message: &str)->Self {
+        Self::eval_error(origin, index, nodes, format!("Cannot call fn {}: {}", ident, message))
+    }
+    
     fn eval_error(origin: LiveErrorOrigin, index: usize, nodes: &[LiveNode], message: String)->Self{
         LiveError {
             origin,
@@ -57,6 +66,23 @@ impl LiveError {
 }
 
 pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &[LiveNode], scope_nodes: &[LiveNode]) -> Result<LiveValue,LiveError> {
+    eval_value(live_registry, index, nodes, scope_nodes, &[], 0)
+}
+
+/// Evaluates the condition of the `@media` block at `index` against `LiveRegistry::media`
+pub fn live_eval_media_condition(live_registry: &LiveRegistry, index: usize, nodes: &[LiveNode], scope_nodes: &[LiveNode]) -> Result<bool,LiveError> {
+    let mut expr_index = index + 1;
+    match eval_value(live_registry, &mut expr_index, nodes, scope_nodes, &live_registry.media.locals(), 0)? {
+        LiveValue::Bool(v) => Ok(v),
+        v => Err(LiveError::eval_error(live_error_origin!(), index, nodes, format!("@media condition should be a bool but got {:?}", v)))
+    }
+}
+
+// the most nested calls to live `fn`s, so a recursive one gives an error instead of a stack overflow
+const MAX_EVAL_CALL_DEPTH: usize = 16;
+
+// locals are the arguments and let bindings of the live `fn` being evaluated
+fn eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &[LiveNode], scope_nodes: &[LiveNode], locals: &[(LiveId, LiveValue)], depth: usize) -> Result<LiveValue,LiveError> {
     let v = &nodes[*index].value;
     Ok(match v {
         LiveValue::Str(_) |
@@ -97,10 +123,13 @@ pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &
         },
         LiveValue::Expr=>{
             *index += 1;
-            return live_eval_value(live_registry, index, nodes, scope_nodes)
+            return eval_value(live_registry, index, nodes, scope_nodes, locals, depth)
         }
         LiveValue::Id(id) => { // look it up from start on up
             *index += 1;
+            if let Some((_, value)) = locals.iter().rev().find(|(local, _)| local == id) {
+                return Ok(value.clone())
+            }
             if let LiveValue::Root {id_resolve} = &scope_nodes[0].value {
                 // lets find the id
                 if let Some(ptr) = id_resolve.get(&id){
@@ -108,11 +137,11 @@ pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &
                         LiveScopeTarget::LivePtr(ptr)=>{
                             let doc = live_registry.ptr_to_doc(*ptr);
                             let mut index = ptr.index as usize;
-                            return live_eval_value(live_registry, &mut index, &doc.nodes, &doc.nodes)
+                            return eval_value(live_registry, &mut index, &doc.nodes, &doc.nodes, &[], depth)
                         }
                         LiveScopeTarget::LocalPtr(ptr)=>{
                             let mut index = *ptr; 
-                            return live_eval_value(live_registry, &mut index, &scope_nodes, &scope_nodes)
+                            return eval_value(live_registry, &mut index, scope_nodes, scope_nodes, &[], depth)
                         }
                     }
                 }
@@ -121,7 +150,7 @@ pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &
         },
         LiveValue::ExprUnOp(op) => {
             *index += 1;
-            let a = live_eval_value(live_registry, index, nodes, scope_nodes)?;
+            let a = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
             match op {
                 LiveUnOp::Not => match a {
                     LiveValue::Bool(va) => LiveValue::Bool(!va),
@@ -138,8 +167,8 @@ pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &
             *index += 1;
             match ident {
                 live_id!(pow) if *args == 2 => {
-                    let a = live_eval_value(live_registry, index, nodes, scope_nodes)?;
-                    let b = live_eval_value(live_registry, index, nodes, scope_nodes)?;
+                    let a = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+                    let b = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
                     if let LiveValue::Float64(va) = a {
                         if let LiveValue::Float64(vb) = b {
                             // ok so how do we blend this eh.
@@ -148,8 +177,8 @@ pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &
                     }
                 }
                 live_id!(blend) if *args == 2 => {
-                    let a = live_eval_value(live_registry, index, nodes, scope_nodes)?;
-                    let b = live_eval_value(live_registry, index, nodes, scope_nodes)?;
+                    let a = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+                    let b = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
                     if let Some(va) = a.as_vec4() {
                         if let Some(vb) = b.as_vec4() {
                             // ok so how do we blend this eh.
@@ -163,9 +192,9 @@ pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &
                     }
                 }
                 live_id!(mix) if *args == 3 => {
-                    let a = live_eval_value(live_registry, index, nodes, scope_nodes)?;
-                    let b = live_eval_value(live_registry, index, nodes, scope_nodes)?;
-                    let c = live_eval_value(live_registry, index, nodes, scope_nodes)?;
+                    let a = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+                    let b = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+                    let c = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
                     
                     if let Some(va) = a.as_vec4() {
                         if let Some(vb) = b.as_vec4() {
@@ -192,10 +221,10 @@ pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &
                     }
                 }
                 live_id!(hsvmod) if *args == 4 => {
-                    let orig = live_eval_value(live_registry, index, nodes, scope_nodes)?;
-                    let hmod = live_eval_value(live_registry, index, nodes, scope_nodes)?;
-                    let smod = live_eval_value(live_registry, index, nodes, scope_nodes)?;
-                    let vmod = live_eval_value(live_registry, index, nodes, scope_nodes)?;
+                    let orig = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+                    let hmod = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+                    let smod = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+                    let vmod = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
                     if let Some(vorig) = orig.as_vec4() {
                         if let LiveValue::Float64(hm) = hmod {
                             if let LiveValue::Float64(sm) = smod {
@@ -213,15 +242,63 @@ pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &
                         }
                     }
                 }
-                _ => {}
+                live_id!(cond) if *args == 3 => {
+                    // only evaluate the branch we take, so live fns can recurse
+                    let cond = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+                    if let LiveValue::Bool(cond) = cond {
+                        if cond {
+                            let v = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+                            *index = skip_expr(*index, nodes);
+                            return Ok(v)
+                        }
+                        *index = skip_expr(*index, nodes);
+                        return eval_value(live_registry, index, nodes, scope_nodes, locals, depth)
+                    }
+                }
+                _ => {
+                    let call_index = *index - 1;
+                    let mut values = Vec::with_capacity(*args);
+                    for _ in 0..*args {
+                        values.push(eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?);
+                    }
+                    if let Some((fn_node, fn_scope_nodes)) = find_live_fn(live_registry, scope_nodes, *ident) {
+                        return eval_live_fn(live_registry, call_index, nodes, fn_node, fn_scope_nodes, values, depth)
+                    }
+                    if let Some(eval_fn) = live_registry.eval_fns.get(*ident) {
+                        return eval_fn(&values).map_err(|message| LiveError::eval_error(
+                            live_error_origin!(), call_index, nodes, format!("{}: {}", ident, message)
+                        ))
+                    }
+                }
             }
                         
             return Err(LiveError::eval_error_expression_call_not_implemented(live_error_origin!(), *index, nodes, *ident, *args))
         }
+        LiveValue::ExprMember(member) => {
+            let member_index = *index;
+            *index += 1;
+            let v = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+            let components = match &v {
+                LiveValue::Vec2(c) => vec![c.x, c.y],
+                LiveValue::Vec3(c) => vec![c.x, c.y, c.z],
+                v => v.as_vec4().map(|c| vec![c.x, c.y, c.z, c.w]).unwrap_or_default()
+            };
+            let component = match member {
+                live_id!(x) | live_id!(r) => 0,
+                live_id!(y) | live_id!(g) => 1,
+                live_id!(z) | live_id!(b) => 2,
+                live_id!(w) | live_id!(a) => 3,
+                _ => usize::MAX
+            };
+            match components.get(component) {
+                Some(c) => LiveValue::Float64(*c as f64),
+                None => return Err(LiveError::eval_error_member_undefined(live_error_origin!(), member_index, nodes, *member, v))
+            }
+        }
         LiveValue::ExprBinOp(op) => {
             *index += 1;
-            let a = live_eval_value(live_registry, index, nodes, scope_nodes)?;
-            let b = live_eval_value(live_registry, index, nodes, scope_nodes)?;
+            let a = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
+            let b = eval_value(live_registry, index, nodes, scope_nodes, locals, depth)?;
             
             match op {
                 LiveBinOp::Or => match a {
@@ -502,3 +579,63 @@ pub fn live_eval_value(live_registry: &LiveRegistry, index: &mut usize, nodes: &
         }
     })
 }
+
+// returns the index after the expression that starts at index
+fn skip_expr(index: usize, nodes: &[LiveNode]) -> usize {
+    let children = match &nodes[index].value {
+        LiveValue::ExprBinOp(_) => 2,
+        LiveValue::ExprUnOp(_) | LiveValue::ExprMember(_) => 1,
+        LiveValue::ExprCall {args, ..} => *args,
+        LiveValue::Expr => return nodes.skip_node(index),
+        _ => 0
+    };
+    let mut index = index + 1;
+    for _ in 0..children {
+        index = skip_expr(index, nodes);
+    }
+    index
+}
+
+// finds a `fn` in the root scope, with the nodes of the document it is defined in
+fn find_live_fn<'a>(live_registry: &'a LiveRegistry, scope_nodes: &'a [LiveNode], ident: LiveId) -> Option<(&'a LiveNode, &'a [LiveNode])> {
+    let LiveValue::Root {id_resolve} = &scope_nodes[0].value else {
+        return None
+    };
+    let (node, nodes) = match id_resolve.get(&ident)? {
+        LiveScopeTarget::LivePtr(ptr) => {
+            let doc = live_registry.ptr_to_doc(*ptr);
+            (&doc.nodes[ptr.index as usize], &doc.nodes[..])
+        }
+        LiveScopeTarget::LocalPtr(ptr) => (&scope_nodes[*ptr], scope_nodes)
+    };
+    node.value.is_dsl().then_some((node, nodes))
+}
+
+fn eval_live_fn(live_registry: &LiveRegistry, call_index: usize, nodes: &[LiveNode], fn_node: &LiveNode, fn_scope_nodes: &[LiveNode], args: Vec<LiveValue>, depth: usize) -> Result<LiveValue, LiveError> {
+    let LiveValue::DSL {token_start, token_count, ..} = fn_node.value else {
+        return Err(LiveError::eval_error_fn_call(live_error_origin!(), call_index, nodes, fn_node.id, "not a fn"))
+    };
+    if depth >= MAX_EVAL_CALL_DEPTH {
+        return Err(LiveError::eval_error_fn_call(live_error_origin!(), call_index, nodes, fn_node.id, "calls nest too deep"))
+    }
+    let token_id = fn_node.origin.token_id().unwrap();
+    let origin_doc = live_registry.token_id_to_origin_doc(token_id);
+    let mut parser = LiveParser::new(
+        origin_doc.get_tokens(token_start as usize, token_count as usize),
+        &[],
+        token_id.file_id().unwrap()
+    );
+    parser.token_index = token_start as usize;
+    let fn_def = parser.expect_eval_fn_def()?;
+    if fn_def.params.len() != args.len() {
+        return Err(LiveError::eval_error_fn_call(live_error_origin!(), call_index, nodes, fn_node.id, &format!(
+            "expected {} arguments but got {}", fn_def.params.len(), args.len()
+        )))
+    }
+    let mut locals: Vec<(LiveId, LiveValue)> = fn_def.params.into_iter().zip(args).collect();
+    for (ident, expr_nodes) in fn_def.lets {
+        let value = eval_value(live_registry, &mut 0, &expr_nodes, fn_scope_nodes, &locals, depth + 1)?;
+        locals.push((ident, value));
+    }
+    eval_value(live_registry, &mut 0, &fn_def.ret, fn_scope_nodes, &locals, depth + 1)
+}
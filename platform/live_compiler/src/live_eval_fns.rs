@@ -0,0 +1,221 @@
+use {
+    std::collections::HashMap,
+    crate::{
+        makepad_math::*,
+        makepad_live_id::*,
+        live_node::LiveValue,
+    }
+};
+
+/// A Rust function that live value expressions can call by name. It gets the evaluated
+/// arguments and returns the value, or a message that is reported at the call.
+pub type LiveEvalFn = fn(&[LiveValue]) -> Result<LiveValue, String>;
+
+/// The Rust functions live value expressions can call, on top of the builtins `pow`, `blend`,
+/// `mix` and `hsvmod`. A `fn` with the same name in the live document scope takes precedence.
+/// Hues are in the 0..1 range, like `Vec4::to_hsva` and the shader library use.
+pub struct LiveEvalFns(HashMap<LiveId, LiveEvalFn>);
+
+impl Default for LiveEvalFns {
+    fn default() -> Self {
+        let mut fns = Self(HashMap::new());
+        fns.register(live_id!(min), eval_min);
+        fns.register(live_id!(max), eval_max);
+        fns.register(live_id!(clamp), eval_clamp);
+        fns.register(live_id!(abs), eval_abs);
+        fns.register(live_id!(darken), eval_darken);
+        fns.register(live_id!(lighten), eval_lighten);
+        fns.register(live_id!(saturate), eval_saturate);
+        fns.register(live_id!(desaturate), eval_desaturate);
+        fns.register(live_id!(fade), eval_fade);
+        fns.register(live_id!(hsv), eval_hsv);
+        fns.register(live_id!(hsva), eval_hsva);
+        fns.register(live_id!(hsl), eval_hsl);
+        fns.register(live_id!(hsla), eval_hsla);
+        fns.register(live_id!(to_hsv), eval_to_hsv);
+        fns.register(live_id!(to_hsl), eval_to_hsl);
+        fns.register(live_id!(srgb_to_linear), eval_srgb_to_linear);
+        fns.register(live_id!(linear_to_srgb), eval_linear_to_srgb);
+        fns
+    }
+}
+
+impl LiveEvalFns {
+    /// Adds a function, replacing any earlier one with the same name. Register before the
+    /// documents that call it are expanded.
+    pub fn register(&mut self, ident: LiveId, eval_fn: LiveEvalFn) {
+        self.0.insert(ident, eval_fn);
+    }
+
+    pub fn get(&self, ident: LiveId) -> Option<LiveEvalFn> {
+        self.0.get(&ident).cloned()
+    }
+}
+
+fn expect_args(args: &[LiveValue], count: usize) -> Result<(), String> {
+    if args.len() != count {
+        return Err(format!("expected {} arguments but got {}", count, args.len()))
+    }
+    Ok(())
+}
+
+fn float_arg(args: &[LiveValue], index: usize) -> Result<f32, String> {
+    match &args[index] {
+        LiveValue::Float64(v) => Ok(*v as f32),
+        LiveValue::Int64(v) => Ok(*v as f32),
+        v => Err(format!("expected a number for argument {} but got {:?}", index + 1, v))
+    }
+}
+
+fn color_arg(args: &[LiveValue], index: usize) -> Result<Vec4, String> {
+    args[index].as_vec4().ok_or_else( || format!("expected a color for argument {} but got {:?}", index + 1, args[index]))
+}
+
+fn number_op(args: &[LiveValue], op: fn(f64, f64) -> f64) -> Result<LiveValue, String> {
+    expect_args(args, 2) ?;
+    match (&args[0], &args[1]) {
+        (LiveValue::Int64(a), LiveValue::Int64(b)) => Ok(LiveValue::Int64(op(*a as f64, *b as f64) as i64)),
+        (a, b) => match (a.as_float(), b.as_float()) {
+            (Some(a), Some(b)) => Ok(LiveValue::Float64(op(a, b))),
+            _ => Err(format!("expected two numbers but got {:?} and {:?}", a, b))
+        }
+    }
+}
+
+fn eval_min(args: &[LiveValue]) -> Result<LiveValue, String> {
+    number_op(args, f64::min)
+}
+
+fn eval_max(args: &[LiveValue]) -> Result<LiveValue, String> {
+    number_op(args, f64::max)
+}
+
+fn eval_clamp(args: &[LiveValue]) -> Result<LiveValue, String> {
+    expect_args(args, 3) ?;
+    let lower = eval_max(&args[0..2]) ?;
+    eval_min(&[lower, args[2].clone()])
+}
+
+fn eval_abs(args: &[LiveValue]) -> Result<LiveValue, String> {
+    expect_args(args, 1) ?;
+    match &args[0] {
+        LiveValue::Int64(v) => Ok(LiveValue::Int64(v.abs())),
+        LiveValue::Float64(v) => Ok(LiveValue::Float64(v.abs())),
+        v => Err(format!("expected a number but got {:?}", v))
+    }
+}
+
+fn rgb_to_hsl(c: Vec4) -> Vec4 {
+    let max = c.x.max(c.y).max(c.z);
+    let min = c.x.min(c.y).min(c.z);
+    let l = (max + min) * 0.5;
+    let d = max - min;
+    if d == 0.0 {
+        return vec4(0.0, 0.0, l, c.w)
+    }
+    let s = d / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == c.x {
+        ((c.y - c.z) / d).rem_euclid(6.0)
+    }
+    else if max == c.y {
+        (c.z - c.x) / d + 2.0
+    }
+    else {
+        (c.x - c.y) / d + 4.0
+    };
+    vec4(h / 6.0, s, l, c.w)
+}
+
+fn hsl_to_rgb(hsl: Vec4) -> Vec4 {
+    let c = (1.0 - (2.0 * hsl.z - 1.0).abs()) * hsl.y;
+    let h = hsl.x.rem_euclid(1.0) * 6.0;
+    let x = c * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match h as usize {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = hsl.z - c * 0.5;
+    vec4(r + m, g + m, b + m, hsl.w)
+}
+
+fn adjust_hsl(args: &[LiveValue], adjust: fn(&mut Vec4, f32)) -> Result<LiveValue, String> {
+    expect_args(args, 2) ?;
+    let mut hsl = rgb_to_hsl(color_arg(args, 0) ?);
+    adjust(&mut hsl, float_arg(args, 1) ?);
+    hsl.y = hsl.y.clamp(0.0, 1.0);
+    hsl.z = hsl.z.clamp(0.0, 1.0);
+    Ok(LiveValue::Vec4(hsl_to_rgb(hsl)))
+}
+
+fn eval_darken(args: &[LiveValue]) -> Result<LiveValue, String> {
+    adjust_hsl(args, | hsl, amount | hsl.z -= amount)
+}
+
+fn eval_lighten(args: &[LiveValue]) -> Result<LiveValue, String> {
+    adjust_hsl(args, | hsl, amount | hsl.z += amount)
+}
+
+fn eval_saturate(args: &[LiveValue]) -> Result<LiveValue, String> {
+    adjust_hsl(args, | hsl, amount | hsl.y += amount)
+}
+
+fn eval_desaturate(args: &[LiveValue]) -> Result<LiveValue, String> {
+    adjust_hsl(args, | hsl, amount | hsl.y -= amount)
+}
+
+fn eval_fade(args: &[LiveValue]) -> Result<LiveValue, String> {
+    expect_args(args, 2) ?;
+    let mut c = color_arg(args, 0) ?;
+    c.w = float_arg(args, 1) ?;
+    Ok(LiveValue::Vec4(c))
+}
+
+fn eval_hsv(args: &[LiveValue]) -> Result<LiveValue, String> {
+    expect_args(args, 3) ?;
+    Ok(LiveValue::Vec4(Vec4::from_hsva(vec4(float_arg(args, 0) ?, float_arg(args, 1) ?, float_arg(args, 2) ?, 1.0))))
+}
+
+fn eval_hsva(args: &[LiveValue]) -> Result<LiveValue, String> {
+    expect_args(args, 4) ?;
+    let mut c = Vec4::from_hsva(vec4(float_arg(args, 0) ?, float_arg(args, 1) ?, float_arg(args, 2) ?, 1.0));
+    c.w = float_arg(args, 3) ?;
+    Ok(LiveValue::Vec4(c))
+}
+
+fn eval_hsl(args: &[LiveValue]) -> Result<LiveValue, String> {
+    expect_args(args, 3) ?;
+    Ok(LiveValue::Vec4(hsl_to_rgb(vec4(float_arg(args, 0) ?, float_arg(args, 1) ?, float_arg(args, 2) ?, 1.0))))
+}
+
+fn eval_hsla(args: &[LiveValue]) -> Result<LiveValue, String> {
+    expect_args(args, 4) ?;
+    Ok(LiveValue::Vec4(hsl_to_rgb(vec4(float_arg(args, 0) ?, float_arg(args, 1) ?, float_arg(args, 2) ?, float_arg(args, 3) ?))))
+}
+
+fn eval_to_hsv(args: &[LiveValue]) -> Result<LiveValue, String> {
+    expect_args(args, 1) ?;
+    Ok(LiveValue::Vec4(color_arg(args, 0) ?.to_hsva()))
+}
+
+fn eval_to_hsl(args: &[LiveValue]) -> Result<LiveValue, String> {
+    expect_args(args, 1) ?;
+    Ok(LiveValue::Vec4(rgb_to_hsl(color_arg(args, 0) ?)))
+}
+
+fn map_rgb(args: &[LiveValue], map: fn(f32) -> f32) -> Result<LiveValue, String> {
+    expect_args(args, 1) ?;
+    let c = color_arg(args, 0) ?;
+    Ok(LiveValue::Vec4(vec4(map(c.x), map(c.y), map(c.z), c.w)))
+}
+
+fn eval_srgb_to_linear(args: &[LiveValue]) -> Result<LiveValue, String> {
+    map_rgb(args, | v | if v <= 0.04045 {v / 12.92} else {((v + 0.055) / 1.055).powf(2.4)})
+}
+
+fn eval_linear_to_srgb(args: &[LiveValue]) -> Result<LiveValue, String> {
+    map_rgb(args, | v | if v <= 0.0031308 {v * 12.92} else {1.055 * v.powf(1.0 / 2.4) - 0.055})
+}
@@ -7,7 +7,7 @@ use {
         makepad_live_tokenizer::{live_error_origin, LiveErrorOrigin},
         live_ptr::{LiveFileId, LivePtr, LiveFileGeneration},
         live_error::{LiveError},
-        live_eval::live_eval_value,
+        live_eval::{live_eval_value, live_eval_media_condition},
         live_document::{LiveOriginal, LiveExpanded},
         live_node::{LiveValue, LiveNode, LiveFieldKind, LivePropType},
         live_node_vec::{LiveNodeSliceApi, LiveNodeVecApi},
@@ -56,6 +56,8 @@ impl<'a> LiveExpander<'a> {
         let mut current_parent = vec![(LiveId(0), 0usize)];
         let mut in_index = 1;
         let mut lazy_define_value = None;
+        // the close nodes of the @media blocks we are in, which don't close a parent
+        let mut media_closes = Vec::new();
         loop {
             
             if let Some((node_id, ptr)) = lazy_define_value.take() {
@@ -74,10 +76,34 @@ impl<'a> LiveExpander<'a> {
             match in_value {
                 
                 LiveValue::Close => {
-                    current_parent.pop();
+                    if media_closes.last() == Some(&in_index) {
+                        media_closes.pop();
+                    }
+                    else {
+                        current_parent.pop();
+                    }
                     in_index += 1;
                     continue;
                 }
+                LiveValue::Media => {
+                    // the properties of a block whose condition holds are expanded as if they
+                    // were written in its place, otherwise the block is left out
+                    let close_index = in_doc.nodes.skip_node(in_index) - 1;
+                    match live_eval_media_condition(self.live_registry, in_index, &in_doc.nodes, &out_doc.nodes) {
+                        Ok(true) => {
+                            media_closes.push(close_index);
+                            in_index = in_doc.nodes.skip_node(in_index + 1);
+                        }
+                        Ok(false) => {
+                            in_index = close_index + 1;
+                        }
+                        Err(e) => {
+                            self.errors.push(e);
+                            in_index = close_index + 1;
+                        }
+                    }
+                    continue;
+                }
                 LiveValue::Import(live_import) => {
                     // lets verify it points anywhere
                     let mut found = false;
@@ -0,0 +1,822 @@
+use {
+    std::fmt,
+    crate::{
+        span::TextPos,
+        makepad_live_tokenizer::{
+            full_token::{Delim, FullToken},
+            tokenizer::{Cursor, State},
+        },
+    },
+};
+
+/// A `{..}` property value that was written on a single line stays on that line if it has at most
+/// this many properties, none of which are blocks themselves. Think `align: {x: 0.5, y: 0.5}`.
+const MAX_INLINE_PROPERTIES: usize = 4;
+
+const KEYWORDS: [&str; 12] = [
+    "if", "else", "for", "in", "while", "loop", "return", "let", "mut", "match", "break", "continue",
+];
+
+#[derive(Clone, Debug)]
+pub struct LiveFormatOptions {
+    /// One level of indentation, such as four spaces or a tab.
+    pub indent: String,
+    /// A block or array that was written on a single line is only kept on it if it fits this width.
+    pub max_line_width: usize,
+}
+
+impl Default for LiveFormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: "    ".to_string(),
+            max_line_width: 100,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct LiveFormatError {
+    pub pos: TextPos,
+    pub message: String,
+}
+
+impl fmt::Display for LiveFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{} - {}", self.pos.line + 1, self.pos.column + 1, self.message)
+    }
+}
+
+impl fmt::Debug for LiveFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Formats the body of a `live_design!` block: every property goes on a line of its own, nested
+/// blocks are indented by one level, and comments and single blank lines are kept where they are.
+///
+/// The result is checked to consist of exactly the same tokens as `source`, so formatting never
+/// changes what the code means. Formatting a formatted body gives the same body again.
+pub fn format_live_design(source: &str, options: &LiveFormatOptions) -> Result<String, LiveFormatError> {
+    format_body(source, options, "", 0)
+}
+
+/// Formats every `live_design!` block in a Rust file with `format_live_design`, indenting the body
+/// relative to the line the macro is on. Everything outside the blocks is left as it is.
+pub fn format_live_design_in_rust(source: &str, options: &LiveFormatOptions) -> Result<String, LiveFormatError> {
+    const MACRO: &str = "live_design!";
+    let mut out = String::new();
+    let mut copied = 0;
+    let mut search = 0;
+    while let Some(found) = source[search..].find(MACRO) {
+        let start = search + found;
+        search = start + MACRO.len();
+        let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line_prefix = &source[line_start..start];
+        // Skip mentions of the macro in comments, and macros that merely end in the same name.
+        if line_prefix.contains("//")
+            || line_prefix.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+        {
+            continue;
+        }
+        let after = &source[search..];
+        let open = search + after.len() - after.trim_start().len();
+        if !source[open..].starts_with(['{', '(']) {
+            continue;
+        }
+        let close = find_matching_close(source, open)?;
+        let base_indent: String = line_prefix
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        let body = format_body(&source[open + 1..close], options, &base_indent, 1).map_err(|error| {
+            LiveFormatError {
+                pos: offset_pos(source, open + 1, error.pos),
+                message: error.message,
+            }
+        })?;
+        out.push_str(&source[copied..open + 1]);
+        if !body.is_empty() {
+            out.push('\n');
+            out.push_str(&body);
+            out.push_str(&base_indent);
+        }
+        copied = close;
+        search = close;
+    }
+    out.push_str(&source[copied..]);
+    Ok(out)
+}
+
+fn format_body(
+    source: &str,
+    options: &LiveFormatOptions,
+    base_indent: &str,
+    indent: usize,
+) -> Result<String, LiveFormatError> {
+    let tokens = tokenize(source)?;
+    let mut formatter = Formatter {
+        tokens: &tokens,
+        index: 0,
+        options,
+        base_indent,
+        out: String::new(),
+        line: String::new(),
+        line_indent: indent,
+        indent,
+        pending: Break::None,
+        prev: None,
+        before_prev: None,
+    };
+    formatter.document();
+    let out = formatter.out;
+
+    // The formatter only ever changes whitespace, and this makes sure of it.
+    let formatted = tokenize(&out).map_err(|error| LiveFormatError {
+        pos: error.pos,
+        message: format!("formatting produced code that does not tokenize: {}", error.message),
+    })?;
+    for index in 0..tokens.len().max(formatted.len()) {
+        match (tokens.get(index), formatted.get(index)) {
+            (Some(a), Some(b)) if a.text.trim_end() == b.text.trim_end() => {}
+            (token, _) => {
+                return Err(LiveFormatError {
+                    pos: token.or(tokens.last()).map_or(TextPos::default(), |token| token.pos),
+                    message: "formatting would change the code here, so it was left alone".to_string(),
+                })
+            }
+        }
+    }
+    Ok(out)
+}
+
+struct Token {
+    kind: FullToken,
+    text: String,
+    pos: TextPos,
+    newlines_before: usize,
+}
+
+/// Splits `source` into all tokens other than whitespace, and checks that its delimiters match.
+fn tokenize(source: &str) -> Result<Vec<Token>, LiveFormatError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut scratch = String::new();
+    let mut cursor = Cursor::new(&chars, &mut scratch);
+    let mut state = State::default();
+    let mut tokens = Vec::new();
+    let mut delims: Vec<(Delim, TextPos)> = Vec::new();
+    let mut pos = TextPos::default();
+    let mut start = 0;
+    let mut newlines = 0;
+    loop {
+        let (next_state, token) = state.next(&mut cursor);
+        state = next_state;
+        let Some(token) = token else {
+            break;
+        };
+        let token_pos = pos;
+        let end = start + token.len;
+        for c in &chars[start..end] {
+            if *c == '\n' {
+                pos.line += 1;
+                pos.column = 0;
+            } else {
+                pos.column += 1;
+            }
+        }
+        let text: String = chars[start..end].iter().collect();
+        start = end;
+        match token.token {
+            FullToken::Whitespace => {
+                newlines += text.matches('\n').count();
+                continue;
+            }
+            FullToken::Open(delim) => delims.push((delim, token_pos)),
+            FullToken::Close(delim) => match delims.pop() {
+                Some((open, _)) if open == delim => {}
+                _ => {
+                    return Err(LiveFormatError {
+                        pos: token_pos,
+                        message: format!("unexpected `{}`", text),
+                    })
+                }
+            },
+            _ => {}
+        }
+        tokens.push(Token {
+            kind: token.token,
+            text,
+            pos: token_pos,
+            newlines_before: newlines,
+        });
+        newlines = 0;
+    }
+    if state != State::default() {
+        return Err(LiveFormatError {
+            pos,
+            message: "unterminated comment or string".to_string(),
+        });
+    }
+    if let Some((_, pos)) = delims.pop() {
+        return Err(LiveFormatError {
+            pos,
+            message: "unclosed delimiter".to_string(),
+        });
+    }
+    Ok(tokens)
+}
+
+/// Returns the byte index of the delimiter that closes the one at byte index `open`.
+fn find_matching_close(source: &str, open: usize) -> Result<usize, LiveFormatError> {
+    let chars: Vec<char> = source[open..].chars().collect();
+    let mut scratch = String::new();
+    let mut cursor = Cursor::new(&chars, &mut scratch);
+    let mut state = State::default();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut byte_index = open;
+    while let (next_state, Some(token)) = state.next(&mut cursor) {
+        state = next_state;
+        let end = start + token.len;
+        match token.token {
+            FullToken::Open(_) => depth += 1,
+            FullToken::Close(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(byte_index);
+                }
+            }
+            _ => {}
+        }
+        byte_index += chars[start..end].iter().map(|c| c.len_utf8()).sum::<usize>();
+        start = end;
+    }
+    Err(LiveFormatError {
+        pos: offset_pos(source, open, TextPos::default()),
+        message: "unclosed live_design! block".to_string(),
+    })
+}
+
+/// Turns a position in the part of `source` that starts at byte index `start` into a position in
+/// `source`.
+fn offset_pos(source: &str, start: usize, pos: TextPos) -> TextPos {
+    let before = &source[..start];
+    let line = before.matches('\n').count() as u32;
+    if pos.line > 0 {
+        return TextPos {
+            line: line + pos.line,
+            column: pos.column,
+        };
+    }
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    TextPos {
+        line,
+        column: before[line_start..].chars().count() as u32 + pos.column,
+    }
+}
+
+/// What goes between the last token that was written and the next one.
+#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+enum Break {
+    None,
+    Space,
+    Newline,
+    BlankLine,
+}
+
+struct Formatter<'a> {
+    tokens: &'a [Token],
+    index: usize,
+    options: &'a LiveFormatOptions,
+    base_indent: &'a str,
+    out: String,
+    line: String,
+    line_indent: usize,
+    indent: usize,
+    pending: Break,
+    prev: Option<&'a Token>,
+    before_prev: Option<&'a Token>,
+}
+
+impl<'a> Formatter<'a> {
+    fn document(&mut self) {
+        while self.peek(0).is_some() {
+            self.body(false);
+            // The body stops at closing delimiters, which can not occur on their own here as the
+            // tokenizer checked they match, but make sure we always move on anyway.
+            if self.peek(0).is_some() {
+                self.newline();
+                self.emit();
+            }
+        }
+        self.flush_comments();
+        self.finish_line();
+    }
+
+    fn body(&mut self, inline: bool) {
+        let mut first = true;
+        while let Some(token) = self.peek(0) {
+            if token.kind.is_close() {
+                break;
+            }
+            if !first {
+                if inline {
+                    self.space();
+                } else {
+                    self.newline();
+                }
+            }
+            first = false;
+            self.item();
+            if self.peek_is(0, ",") || self.peek_is(0, ";") {
+                self.emit();
+            }
+        }
+    }
+
+    fn item(&mut self) {
+        let Some(token) = self.peek(0) else {
+            return;
+        };
+        let next_is_ident = self.peek(1).is_some_and(|next| next.kind.is_ident());
+        match token.kind {
+            FullToken::Ident(_) if next_is_ident => match token.text.as_str() {
+                "fn" => self.function(),
+                "import" => self.import(),
+                // Things like `instance hover: 0.0`.
+                _ => {
+                    self.emit();
+                    self.space();
+                    self.property();
+                }
+            },
+            FullToken::Ident(_) => self.property(),
+            FullToken::Punct(_) if token.text == "@" => self.media(),
+            _ => self.value(),
+        }
+    }
+
+    /// An `@media(width < 600) {..}` block.
+    fn media(&mut self) {
+        self.emit();
+        if self.peek(0).is_some_and(|token| token.kind.is_ident()) {
+            self.emit();
+        }
+        if self.peek_is(0, "(") {
+            self.expression();
+        }
+        if self.peek_is(0, "{") {
+            self.space();
+            self.block(false);
+        }
+    }
+
+    fn property(&mut self) {
+        self.emit();
+        if self.peek_is(0, ":") {
+            self.emit();
+            self.space();
+            self.value();
+        } else if self.peek_is(0, "=") {
+            self.space();
+            self.emit();
+            self.space();
+            self.value();
+        }
+    }
+
+    fn value(&mut self) {
+        let Some(token) = self.peek(0) else {
+            return;
+        };
+        let next = self.peek(1).map(|next| next.text.as_str());
+        match token.kind {
+            FullToken::Close(_) => {}
+            FullToken::Punct(_) if token.text == "<" => self.class(),
+            FullToken::Punct(_) if token.text == "-" => {
+                self.emit();
+                self.value();
+            }
+            FullToken::Open(Delim::Brace) if next == Some("{") => self.rust_type(),
+            FullToken::Open(Delim::Brace) => self.block(true),
+            FullToken::Open(Delim::Bracket) => self.array(),
+            FullToken::Open(Delim::Paren) => self.expression(),
+            FullToken::Ident(_) if token.text == "struct" && next == Some("{") => {
+                self.emit();
+                self.space();
+                self.block(false);
+            }
+            FullToken::Ident(_) => {
+                self.emit();
+                while (self.peek_is(0, ".") || self.peek_is(0, "::"))
+                    && self.peek(1).is_some_and(|next| next.kind.is_ident())
+                {
+                    self.emit();
+                    self.emit();
+                }
+                if self.peek_is(0, "(") {
+                    self.expression();
+                } else if self.peek_is(0, "{") {
+                    self.space();
+                    self.block(true);
+                }
+            }
+            _ => self.emit(),
+        }
+    }
+
+    /// A `<Name> {..}` value.
+    fn class(&mut self) {
+        self.emit();
+        if self.peek(0).is_some_and(|token| token.kind.is_ident()) {
+            self.emit();
+        }
+        while let Some(token) = self.peek(0) {
+            if token.text == ">" {
+                self.emit();
+                break;
+            }
+            if token.kind.is_open() || token.kind.is_close() {
+                break;
+            }
+            // Design info, as in `<View dx:10.0 dy:-5.0>`.
+            if token.kind.is_ident() || self.prev.is_some_and(|prev| prev.text == ",") {
+                self.space();
+            }
+            self.emit();
+        }
+        if self.peek_is(0, "{") {
+            self.space();
+            self.block(false);
+        }
+    }
+
+    /// A `{{RustType}} {..}` value.
+    fn rust_type(&mut self) {
+        if !self.peek(2).is_some_and(|token| token.kind.is_ident())
+            || !self.peek_is(3, "}")
+            || !self.peek_is(4, "}")
+        {
+            return self.block(true);
+        }
+        for _ in 0..5 {
+            self.emit();
+        }
+        if self.peek_is(0, "{") {
+            self.space();
+            self.block(false);
+        }
+    }
+
+    /// A `{..}` block, which can only stay on a single line if `can_inline`, as the bodies of
+    /// components always get a line per property.
+    fn block(&mut self, can_inline: bool) {
+        let inline = can_inline && self.fits_inline();
+        self.emit();
+        if self.next_is_close() {
+            self.emit();
+            return;
+        }
+        if inline {
+            self.body(true);
+            self.emit();
+            return;
+        }
+        self.indent += 1;
+        self.newline();
+        self.body(false);
+        self.flush_comments();
+        self.indent -= 1;
+        self.newline();
+        self.emit();
+    }
+
+    fn array(&mut self) {
+        let inline = self.fits_inline();
+        self.emit();
+        if self.next_is_close() {
+            self.emit();
+            return;
+        }
+        if !inline {
+            self.indent += 1;
+            self.newline();
+        }
+        let mut first = true;
+        while let Some(token) = self.peek(0) {
+            if token.kind.is_close() {
+                break;
+            }
+            if !first {
+                if inline {
+                    self.space();
+                } else {
+                    self.newline();
+                }
+            }
+            first = false;
+            self.value();
+            if self.peek_is(0, ",") {
+                self.emit();
+            }
+        }
+        if !inline {
+            self.flush_comments();
+            self.indent -= 1;
+            self.newline();
+        }
+        self.emit();
+    }
+
+    /// Anything in parentheses, such as the arguments of `vec4(..)` or a `(a + b)` expression,
+    /// which stays on one line.
+    fn expression(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.peek(0) {
+            self.emit_spaced();
+            match token.kind {
+                FullToken::Open(_) => depth += 1,
+                FullToken::Close(_) => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+
+    fn import(&mut self) {
+        self.emit();
+        while let Some(token) = self.peek(0) {
+            if token.kind.is_close() || token.text == ";" || token.text == "," {
+                break;
+            }
+            // An import without a semicolon ends at the end of its line.
+            if token.newlines_before > 0 && self.prev.is_none_or(|prev| prev.text != "::") {
+                break;
+            }
+            self.emit_spaced();
+        }
+    }
+
+    /// A shader function, of which the body is laid out with one statement per line.
+    fn function(&mut self) {
+        self.emit();
+        while let Some(token) = self.peek(0) {
+            if token.kind.is_close() {
+                return;
+            }
+            if token.text == "{" {
+                self.code_block();
+                return;
+            }
+            if token.kind.is_open() {
+                self.expression();
+            } else {
+                self.emit_spaced();
+            }
+        }
+    }
+
+    fn code_block(&mut self) {
+        self.emit_spaced();
+        if self.next_is_close() {
+            self.emit();
+            return;
+        }
+        self.indent += 1;
+        while let Some(token) = self.peek(0) {
+            if token.kind.is_close() {
+                break;
+            }
+            self.newline();
+            self.statement();
+        }
+        self.flush_comments();
+        self.indent -= 1;
+        self.newline();
+        self.emit();
+    }
+
+    fn statement(&mut self) {
+        let start = self.index;
+        let mut depth = 0;
+        while let Some(token) = self.peek(0) {
+            if token.text == "{" {
+                self.code_block();
+                let continues = self
+                    .peek(0)
+                    .is_some_and(|next| next.kind.is_close() || matches!(next.text.as_str(), "else" | ";" | "," | "."));
+                if depth == 0 && !continues {
+                    return;
+                }
+                continue;
+            }
+            if token.kind.is_close() && depth == 0 {
+                return;
+            }
+            // Shaders allow leaving out the semicolon at the end of a line.
+            let ends_line = self.prev.is_some_and(|prev| {
+                prev.kind.is_ident() || prev.kind.is_close() || prev.kind.is_value_type()
+            });
+            if self.index > start && depth == 0 && ends_line && token.kind.is_ident() && token.newlines_before > 0 {
+                return;
+            }
+            self.emit_spaced();
+            match token.kind {
+                FullToken::Open(_) => depth += 1,
+                FullToken::Close(_) => depth -= 1,
+                _ => {}
+            }
+            if token.text == ";" && depth == 0 {
+                return;
+            }
+        }
+    }
+
+    /// Returns whether the block or array that starts at the next token was written on a single
+    /// line, is simple enough to stay on it, and fits in the maximum line width.
+    fn fits_inline(&self) -> bool {
+        let Some(start) = (self.index..self.tokens.len()).find(|index| !self.tokens[*index].kind.is_comment()) else {
+            return false;
+        };
+        let mut width = self.line_width() + 1;
+        let mut depth = 0;
+        let mut properties = 0;
+        for (index, token) in self.tokens[start..].iter().enumerate() {
+            if index > 0 && (token.kind.is_comment() || token.newlines_before > 0) {
+                return false;
+            }
+            width += token.text.chars().count() + 1;
+            match token.kind {
+                FullToken::Open(delim) => {
+                    if depth > 0 && delim == Delim::Brace {
+                        return false;
+                    }
+                    depth += 1;
+                }
+                FullToken::Close(_) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return width <= self.options.max_line_width && properties <= MAX_INLINE_PROPERTIES;
+                    }
+                }
+                FullToken::Punct(_) if depth == 1 && (token.text == ":" || token.text == "=") => {
+                    properties += 1;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    fn line_width(&self) -> usize {
+        self.base_indent.chars().count()
+            + self.line_indent * self.options.indent.chars().count()
+            + self.line.chars().count()
+    }
+
+    /// Returns the `n`th token from here that is not a comment.
+    fn peek(&self, n: usize) -> Option<&'a Token> {
+        let tokens = self.tokens;
+        tokens[self.index..].iter().filter(|token| !token.kind.is_comment()).nth(n)
+    }
+
+    fn peek_is(&self, n: usize, text: &str) -> bool {
+        self.peek(n).is_some_and(|token| token.text == text)
+    }
+
+    fn next_is_close(&self) -> bool {
+        self.tokens.get(self.index).is_some_and(|token| token.kind.is_close())
+    }
+
+    fn space(&mut self) {
+        self.pending = self.pending.max(Break::Space);
+    }
+
+    fn newline(&mut self) {
+        self.pending = self.pending.max(Break::Newline);
+    }
+
+    /// Writes the next token, with a space before it if it needs one in an expression.
+    fn emit_spaced(&mut self) {
+        if let (Some(prev), Some(next)) = (self.prev, self.peek(0)) {
+            if space_between(self.before_prev, prev, next) {
+                self.space();
+            }
+        }
+        self.emit();
+    }
+
+    /// Writes the comments before the next token, and then the token itself.
+    fn emit(&mut self) {
+        self.flush_comments();
+        let tokens = self.tokens;
+        if let Some(token) = tokens.get(self.index) {
+            self.index += 1;
+            self.write(token);
+            self.before_prev = self.prev;
+            self.prev = Some(token);
+        }
+    }
+
+    fn flush_comments(&mut self) {
+        let tokens = self.tokens;
+        while let Some(token) = tokens.get(self.index) {
+            if !token.kind.is_comment() {
+                break;
+            }
+            self.index += 1;
+            if token.newlines_before == 0 && !self.line.is_empty() {
+                // A comment after something on the same line stays on that line.
+                self.line.push(' ');
+                self.line.push_str(&token.text);
+            } else {
+                self.newline();
+                self.write(token);
+            }
+            if tokens.get(self.index).is_none_or(|next| next.newlines_before > 0) {
+                self.newline();
+            } else {
+                self.space();
+            }
+        }
+    }
+
+    fn write(&mut self, token: &Token) {
+        let mut pending = self.pending;
+        if pending >= Break::Newline && token.newlines_before > 1 && !token.kind.is_close() {
+            pending = Break::BlankLine;
+        }
+        match pending {
+            Break::None => {}
+            Break::Space => {
+                if !self.line.is_empty() {
+                    self.line.push(' ');
+                }
+            }
+            Break::Newline => self.finish_line(),
+            Break::BlankLine => {
+                self.finish_line();
+                // Blank lines at the start of a block add nothing.
+                if !self.out.is_empty()
+                    && !self.out.ends_with("\n\n")
+                    && !self.out.trim_end().ends_with(['{', '[', '('])
+                {
+                    self.out.push('\n');
+                }
+            }
+        }
+        if self.line.is_empty() {
+            self.line_indent = self.indent;
+        }
+        self.line.push_str(&token.text);
+        self.pending = Break::None;
+    }
+
+    fn finish_line(&mut self) {
+        if self.line.is_empty() {
+            return;
+        }
+        self.out.push_str(self.base_indent);
+        for _ in 0..self.line_indent {
+            self.out.push_str(&self.options.indent);
+        }
+        self.out.push_str(self.line.trim_end());
+        self.out.push('\n');
+        self.line.clear();
+    }
+}
+
+/// Returns whether there is a space between `prev` and `next` in an expression, in the way
+/// `rustfmt` would put it.
+fn space_between(before_prev: Option<&Token>, prev: &Token, next: &Token) -> bool {
+    let is_keyword = |token: &Token| KEYWORDS.contains(&token.text.as_str());
+    if prev.kind.is_open() || next.kind.is_close() {
+        return false;
+    }
+    if matches!(next.text.as_str(), "," | ";" | ":" | "." | "::" | "..") {
+        return false;
+    }
+    if matches!(prev.text.as_str(), "." | "::" | "..") {
+        return false;
+    }
+    if matches!(prev.text.as_str(), "," | ";" | ":") {
+        return true;
+    }
+    if next.kind.is_open_delim(Delim::Paren) || next.kind.is_open_delim(Delim::Bracket) {
+        // Calls and indexing.
+        if prev.kind.is_close() || prev.kind.is_ident() && !is_keyword(prev) {
+            return false;
+        }
+    }
+    if matches!(prev.text.as_str(), "-" | "!") {
+        let is_unary = before_prev.is_none_or(|before_prev| {
+            before_prev.kind.is_open() || before_prev.kind.is_punct() || is_keyword(before_prev)
+        });
+        if is_unary {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,268 @@
+use {
+    std::{
+        any::TypeId,
+        sync::Arc,
+    },
+    crate::{
+        makepad_math::*,
+        makepad_live_id::*,
+        makepad_live_tokenizer::{live_error_origin, LiveErrorOrigin},
+        live_error::{LiveError, LiveErrorSpan},
+        live_expander::LiveExpander,
+        live_node::{LiveValue, LiveNode, LiveTypeInfo, LiveTypeField, LiveFieldKind, LivePropType, LiveVariantKind},
+        live_node_vec::LiveNodeSliceApi,
+        live_registry::LiveRegistry,
+        span::TextSpan,
+    }
+};
+
+/// Checks expanded documents against the `LiveTypeInfo` of the Rust types they apply to.
+/// It reports the properties a `#[derive(Live)]` struct would otherwise silently ignore:
+/// unknown fields, values of the wrong kind, unknown enum variants and unresolved clones.
+pub struct LiveLinter<'a> {
+    pub live_registry: &'a LiveRegistry,
+    pub errors: &'a mut Vec<LiveError>,
+    reported: Vec<TextSpan>,
+}
+
+impl<'a> LiveLinter<'a> {
+    /// Spans that already have an error in `errors` are not reported again
+    pub fn new(live_registry: &'a LiveRegistry, errors: &'a mut Vec<LiveError>) -> Self {
+        let reported = errors.iter().filter_map( | error | Self::error_text_span(live_registry, &error.span)).collect();
+        Self {
+            live_registry,
+            errors,
+            reported
+        }
+    }
+
+    fn error_text_span(live_registry: &LiveRegistry, span: &LiveErrorSpan) -> Option<TextSpan> {
+        match span {
+            LiveErrorSpan::Text(text_span) => Some(*text_span),
+            LiveErrorSpan::Token(token_span) => {
+                let file_id = token_span.token_id.file_id() ?;
+                Some(live_registry.live_files[file_id.to_index()].original.token_id_to_span(token_span.token_id))
+            }
+        }
+    }
+
+    pub fn lint(&mut self, nodes: &[LiveNode]) {
+        if !nodes.is_empty() {
+            self.lint_children(nodes, 0, None);
+        }
+    }
+
+    fn lint_children(&mut self, nodes: &[LiveNode], parent_index: usize, parent_type: Option<&LiveTypeInfo>) {
+        let mut child = nodes.first_child(parent_index);
+        while let Some(index) = child {
+            self.lint_node(nodes, index, parent_type);
+            child = nodes.next_child(index);
+        }
+    }
+
+    fn lint_node(&mut self, nodes: &[LiveNode], index: usize, parent_type: Option<&LiveTypeInfo>) {
+        let node = &nodes[index];
+
+        let field = if let Some(parent_type) = parent_type {
+            if node.origin.has_prop_type(LivePropType::Field) && !node.origin.node_has_prefix() {
+                match Self::find_field(parent_type, node.id) {
+                    FindField::Found(field) => Some(field),
+                    FindField::Unchecked => None,
+                    FindField::NotFound => {
+                        self.report(node, format!("{} has no field {}", parent_type.type_name, node.id));
+                        return
+                    }
+                }
+            }
+            else {
+                None
+            }
+        }
+        else {
+            None
+        };
+
+        if let LiveValue::Clone {clone, ..} = &node.value {
+            if !LiveExpander::is_baseclass(*clone) {
+                self.report(node, format!("Can't find live definition of {} did you forget to call live_design for it?", clone));
+            }
+        }
+
+        if let Some(field) = field {
+            self.lint_field_value(node, field);
+        }
+
+        match &node.value {
+            LiveValue::Class {live_type, ..} => {
+                let live_registry = self.live_registry;
+                let live_type_info = live_registry.live_type_infos.get(live_type);
+                self.lint_children(nodes, index, live_type_info);
+            }
+            LiveValue::Object => {
+                self.lint_children(nodes, index, field.map( | field | &field.live_type_info));
+            }
+            LiveValue::Expr | LiveValue::DSL {..} => (),
+            value if value.is_open() => {
+                self.lint_children(nodes, index, None);
+            }
+            _ => ()
+        }
+    }
+
+    fn find_field(live_type_info: &LiveTypeInfo, id: LiveId) -> FindField<'_> {
+        if live_type_info.live_ignore || live_type_info.fields.is_empty() {
+            return FindField::Unchecked
+        }
+        if let Some(field) = live_type_info.fields.iter().find( | field | field.id == id) {
+            return FindField::Found(field)
+        }
+        // unknown names are applied to the deref field
+        if let Some(field) = live_type_info.fields.iter().find( | field | field.live_field_kind == LiveFieldKind::Deref) {
+            return Self::find_field(&field.live_type_info, id)
+        }
+        FindField::NotFound
+    }
+
+    fn lint_field_value(&mut self, node: &LiveNode, field: &LiveTypeField) {
+        let live_type_info = &field.live_type_info;
+        let is_option = field.live_field_kind == LiveFieldKind::LiveOption;
+        match &node.value {
+            LiveValue::BareEnum(variant) if is_option && *variant == live_id!(None) => (),
+            LiveValue::Id(variant) | LiveValue::BareEnum(variant) |
+            LiveValue::TupleEnum(variant) | LiveValue::NamedEnum(variant) if !live_type_info.variants.is_empty() => {
+                let kind = match &node.value {
+                    LiveValue::TupleEnum(_) => LiveVariantKind::Tuple,
+                    LiveValue::NamedEnum(_) => LiveVariantKind::Named,
+                    _ => LiveVariantKind::Bare
+                };
+                match live_type_info.variants.iter().find( | (id, _) | id == variant) {
+                    None => {
+                        self.report(node, format!("{} has no variant {} for field {}", live_type_info.type_name, variant, node.id));
+                    }
+                    Some((_, variant_kind)) if *variant_kind != kind => {
+                        self.report(node, format!(
+                            "{}::{} is a {} variant but field {} gives it as a {} variant",
+                            live_type_info.type_name,
+                            variant,
+                            variant_kind_name(*variant_kind),
+                            node.id,
+                            variant_kind_name(kind)
+                        ));
+                    }
+                    _ => ()
+                }
+            }
+            value => if let Some(expected) = expected_value_kinds(live_type_info.live_type) {
+                if !expected.accepts(value) {
+                    self.report(node, format!(
+                        "Field {} expects {} but got {}",
+                        node.id,
+                        expected.name,
+                        value_kind_name(value)
+                    ));
+                }
+            }
+        }
+    }
+
+    fn report(&mut self, node: &LiveNode, message: String) {
+        let token_id = if let Some(token_id) = node.origin.token_id() {token_id} else {return};
+        let file_id = if let Some(file_id) = token_id.file_id() {file_id} else {return};
+        let span = self.live_registry.live_files[file_id.to_index()].original.token_id_to_span(token_id);
+        if self.reported.contains(&span) {
+            return
+        }
+        self.reported.push(span);
+        self.errors.push(LiveError {
+            origin: live_error_origin!(),
+            span: span.into(),
+            message
+        });
+    }
+}
+
+enum FindField<'a> {
+    Found(&'a LiveTypeField),
+    Unchecked,
+    NotFound
+}
+
+struct ValueKinds {
+    name: &'static str,
+    accepts: fn(&LiveValue) -> bool
+}
+
+impl ValueKinds {
+    fn accepts(&self, value: &LiveValue) -> bool {
+        // arrays animate, expressions evaluate at apply time and DSL blocks are skipped
+        matches!(value, LiveValue::Array | LiveValue::Expr | LiveValue::DSL {..} | LiveValue::None) || (self.accepts)(value)
+    }
+}
+
+fn is_number(value: &LiveValue) -> bool {
+    matches!(value, LiveValue::Float32(_) | LiveValue::Float64(_) | LiveValue::Int64(_) | LiveValue::Uint64(_))
+}
+
+fn expected_value_kinds(live_type: TypeId) -> Option<ValueKinds> {
+    let (name, accepts): (_, fn(&LiveValue) -> bool) = if [
+        TypeId::of::<f32>(),
+        TypeId::of::<f64>(),
+        TypeId::of::<i64>(),
+        TypeId::of::<u64>(),
+        TypeId::of::<i32>(),
+        TypeId::of::<u32>(),
+        TypeId::of::<usize>()
+    ].contains(&live_type) {
+        ("a number", is_number)
+    }
+    else if live_type == TypeId::of::<bool>() {
+        ("a bool", | v | matches!(v, LiveValue::Bool(_) | LiveValue::Int64(_) | LiveValue::Uint64(_)))
+    }
+    else if live_type == TypeId::of::<Vec2>() || live_type == TypeId::of::<DVec2>() {
+        ("a vec2", | v | is_number(v) || matches!(v, LiveValue::Vec2(_)))
+    }
+    else if live_type == TypeId::of::<Vec3>() {
+        ("a vec3", | v | is_number(v) || matches!(v, LiveValue::Vec2(_) | LiveValue::Vec3(_)))
+    }
+    else if live_type == TypeId::of::<Vec4>() {
+        ("a vec4 or color", | v | is_number(v) || matches!(v, LiveValue::Vec2(_) | LiveValue::Vec3(_) | LiveValue::Vec4(_) | LiveValue::Color(_)))
+    }
+    else if live_type == TypeId::of::<String>() || live_type == TypeId::of::<Arc<String>>() {
+        ("a string", | v | matches!(v, LiveValue::Str(_) | LiveValue::String(_) | LiveValue::InlineString(_)))
+    }
+    else if live_type == TypeId::of::<LiveId>() {
+        ("an id", | v | matches!(v, LiveValue::Id(_) | LiveValue::BareEnum(_)))
+    }
+    else {
+        return None
+    };
+    Some(ValueKinds {name, accepts})
+}
+
+fn variant_kind_name(kind: LiveVariantKind) -> &'static str {
+    match kind {
+        LiveVariantKind::Bare => "bare",
+        LiveVariantKind::Tuple => "tuple",
+        LiveVariantKind::Named => "named",
+    }
+}
+
+fn value_kind_name(value: &LiveValue) -> String {
+    match value {
+        LiveValue::Str(_) | LiveValue::String(_) | LiveValue::InlineString(_) => "a string".to_string(),
+        LiveValue::Dependency(_) => "a dependency".to_string(),
+        LiveValue::Bool(_) => "a bool".to_string(),
+        LiveValue::Int64(_) | LiveValue::Uint64(_) | LiveValue::Float32(_) | LiveValue::Float64(_) => "a number".to_string(),
+        LiveValue::Color(_) => "a color".to_string(),
+        LiveValue::Vec2(_) => "a vec2".to_string(),
+        LiveValue::Vec3(_) => "a vec3".to_string(),
+        LiveValue::Vec4(_) => "a vec4".to_string(),
+        LiveValue::Id(id) => format!("the id {}", id),
+        LiveValue::IdPath(_) => "an id path".to_string(),
+        LiveValue::BareEnum(variant) | LiveValue::TupleEnum(variant) | LiveValue::NamedEnum(variant) => format!("the enum variant {}", variant),
+        LiveValue::Object => "an object".to_string(),
+        LiveValue::Clone {clone, ..} => format!("a <{}>", clone),
+        LiveValue::Class {..} | LiveValue::Deref {..} => "a class".to_string(),
+        _ => format!("{:?}", value)
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{
+    makepad_live_id::*,
+    live_node::LiveValue,
+};
+
+/// The platforms an `@media` condition can test for, each one is a bool that is true when
+/// running on it, as in `@media(android || ios) {..}`
+pub const LIVE_MEDIA_PLATFORMS: [LiveId; 8] = [
+    live_id!(windows),
+    live_id!(macos),
+    live_id!(linux),
+    live_id!(ios),
+    live_id!(android),
+    live_id!(openharmony),
+    live_id!(web),
+    live_id!(unknown),
+];
+
+/// The values `@media(..)` conditions are evaluated against. A document like
+/// `<View> {flow: Right, @media(width < 600) {flow: Down}}` only gets the properties of a block
+/// while its condition holds, see `LiveRegistry::update_media`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiveMedia {
+    /// Size of the main window in logical pixels
+    pub width: f64,
+    pub height: f64,
+    pub dpi_factor: f64,
+    /// One of `LIVE_MEDIA_PLATFORMS`
+    pub platform: LiveId,
+}
+
+impl Default for LiveMedia {
+    fn default() -> Self {
+        Self {
+            width: 800.0,
+            height: 600.0,
+            dpi_factor: 1.0,
+            platform: live_id!(unknown),
+        }
+    }
+}
+
+impl LiveMedia {
+    pub fn locals(&self) -> Vec<(LiveId, LiveValue)> {
+        let mut locals = vec![
+            (live_id!(width), LiveValue::Float64(self.width)),
+            (live_id!(height), LiveValue::Float64(self.height)),
+            (live_id!(dpi_factor), LiveValue::Float64(self.dpi_factor)),
+        ];
+        for platform in LIVE_MEDIA_PLATFORMS {
+            locals.push((platform, LiveValue::Bool(platform == self.platform)));
+        }
+        locals
+    }
+}
@@ -103,6 +103,8 @@ pub enum LiveValue {
     Clone{clone:LiveId, design_info:LiveDesignInfoIndex},
     Deref{live_type: LiveType, clone:LiveId, design_info:LiveDesignInfoIndex},
     Class {live_type: LiveType, class_parent: LivePtr, design_info:LiveDesignInfoIndex},
+    // an @media block, the condition Expr is its first child. only in original documents
+    Media,
     Close,
     
     // shader code and other DSLs
@@ -426,7 +428,9 @@ pub struct LiveTypeInfo {
     pub type_name: LiveId,
     pub module_id: LiveModuleId,
     pub live_ignore: bool,
-    pub fields: Vec<LiveTypeField>
+    pub fields: Vec<LiveTypeField>,
+    /// The variants of an enum, empty for other types
+    pub variants: Vec<(LiveId, LiveVariantKind)>
 }
 
 #[derive(Clone, Debug)]
@@ -436,6 +440,13 @@ pub struct LiveTypeField {
     pub live_field_kind: LiveFieldKind
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LiveVariantKind {
+    Bare,
+    Tuple,
+    Named
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum LiveFieldKind {
     Calc,
@@ -513,6 +524,7 @@ impl LiveValue {
             Self::Clone {..} | // subnodes including this one
             Self::Class {..} | 
             Self::Deref {..} | 
+            Self::Media |
             Self::Root {..} => true, // subnodes including this one
             _ => false
         }
@@ -746,6 +758,7 @@ impl LiveValue {
             
             Self::DSL {..} => 31,
             Self::Import {..} => 32,
+            Self::Media => 33,
             //Self::Registry {..} => 30,
         }
     }
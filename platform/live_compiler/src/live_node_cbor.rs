@@ -387,6 +387,9 @@ impl<T> LiveNodeSliceToCbor for T where T: AsRef<[LiveNode]> {
                 LiveValue::Root{..} => {
                     return Err("Cannot serialise LiveValue::Registry".into())
                 }
+                LiveValue::Media => {
+                    return Err("Cannot serialise LiveValue::Media".into())
+                }
                 LiveValue::IdPath(..) => {
                     return Err("Cannot serialise LiveValue::IdPath".into())
                 }                
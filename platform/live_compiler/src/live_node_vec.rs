@@ -751,6 +751,10 @@ impl<T> LiveNodeSliceApi for T where T: AsRef<[LiveNode]> {
                     writeln!(f, "{}{} <Root>", node.id, pt).unwrap();
                     stack_depth += 1;
                 }, // subnodes including this one                
+                LiveValue::Media => {
+                    writeln!(f, "{}{} <Media>", node.id, pt).unwrap();
+                    stack_depth += 1;
+                },
                 LiveValue::Close => {
                     if stack_depth == 0 {
                         writeln!(f, "<CloseMisaligned> {}", node.id).unwrap();
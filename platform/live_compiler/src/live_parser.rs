@@ -856,6 +856,11 @@ impl<'a> LiveParser<'a> {
     
     fn expect_live_class(&mut self, root: bool, prop_id: LiveId, ld: &mut LiveOriginal) -> Result<(), LiveError> {
         let mut nameless_id = 1;
+        self.expect_live_class_body(root, prop_id, &mut nameless_id, ld)
+    }
+    
+    // an @media block numbers its nameless children on from those of the class it is in
+    fn expect_live_class_body(&mut self, root: bool, prop_id: LiveId, nameless_id: &mut u64, ld: &mut LiveOriginal) -> Result<(), LiveError> {
         while self.peek_token() != LiveToken::Eof {
             match self.peek_token() {
                 LiveToken::Close(Delim::Brace) => {
@@ -880,12 +885,30 @@ impl<'a> LiveParser<'a> {
                     self.expect_token(LiveToken::Open(Delim::Brace))?;
                     ld.nodes.push(LiveNode {
                         origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Instance),
-                        id: LiveId(nameless_id),
+                        id: LiveId(*nameless_id),
                         value: LiveValue::Clone{clone:ident, design_info}
                     });
-                    nameless_id += 1;
+                    *nameless_id += 1;
                     self.expect_live_class(false, prop_id, ld) ?;
                 }
+                LiveToken::Punct(live_id!(@))=>{ // @media(condition) {props}
+                    let token_id = self.get_token_id();
+                    self.skip_token();
+                    let ident = self.expect_ident()?;
+                    if ident != live_id!(media) {
+                        return Err(self.error(format!("Unexpected @{}, expected @media", ident), live_error_origin!()))
+                    }
+                    ld.nodes.push(LiveNode {
+                        origin: LiveNodeOrigin::from_token_id(token_id),
+                        id: live_id!(media),
+                        value: LiveValue::Media
+                    });
+                    let origin = LiveNodeOrigin::from_token_id(self.get_token_id());
+                    self.expect_expression(live_id!(media), origin, ld) ?;
+                    self.expect_token(LiveToken::Open(Delim::Brace))?;
+                    self.expect_live_class_body(false, live_id!(media), nameless_id, ld) ?;
+                    self.accept_optional_delim();
+                }
                 LiveToken::Ident(prop_id) => {
                     let token_id = self.get_token_id();
                     self.skip_token();
@@ -1000,82 +1023,7 @@ impl<'a> LiveParser<'a> {
             value: LiveValue::Expr 
         });
         
-        fn recur_walk(expr: Expr, ld: &mut LiveOriginal) {
-            match expr {
-                Expr::Bin {token_id, op, left_expr, right_expr} => {
-                    ld.nodes.push(LiveNode {
-                        origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
-                        id: LiveId::empty(),
-                        value: LiveValue::ExprBinOp(op)
-                    });
-                    recur_walk(*left_expr, ld);
-                    recur_walk(*right_expr, ld);
-                }
-                Expr::Un {token_id, op, expr} => {
-                    ld.nodes.push(LiveNode {
-                        origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
-                        id: LiveId::empty(),
-                        value: LiveValue::ExprUnOp(op)
-                    });
-                    recur_walk(*expr, ld);
-                }
-                Expr::Call {token_id, ident, arg_exprs} => {
-                    ld.nodes.push(LiveNode {
-                        origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
-                        id: LiveId::empty(),
-                        value: LiveValue::ExprCall {ident, args: arg_exprs.len()}
-                    });
-                    for arg in arg_exprs {
-                        recur_walk(arg, ld);
-                    }
-                }
-                Expr::Member {token_id, ident, expr} => {
-                    ld.nodes.push(LiveNode {
-                        origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
-                        id: LiveId::empty(),
-                        value: LiveValue::ExprMember(ident)
-                    });
-                    recur_walk(*expr, ld);
-                }
-                Expr::Var {token_id, ident} => {
-                    ld.nodes.push(LiveNode {
-                        origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
-                        id: LiveId::empty(),
-                        value: LiveValue::Id(ident)
-                    });
-                }
-                Expr::Bool {token_id, v} => {
-                    ld.nodes.push(LiveNode {
-                        origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
-                        id: LiveId::empty(),
-                        value: LiveValue::Bool(v)
-                    });
-                }
-                Expr::Int {token_id, v} => {
-                    ld.nodes.push(LiveNode {
-                        origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
-                        id: LiveId::empty(),
-                        value: LiveValue::Int64(v)
-                    });
-                }
-                Expr::Float {token_id, v} => {
-                    ld.nodes.push(LiveNode {
-                        origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
-                        id: LiveId::empty(),
-                        value: LiveValue::Float64(v)
-                    });
-                }
-                Expr::Color {token_id, v} => {
-                    ld.nodes.push(LiveNode {
-                        origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
-                        id: LiveId::empty(),
-                        value: LiveValue::Color(v)
-                    });
-                }
-            }
-        }
-        
-        recur_walk(expr, ld);
+        push_expr_nodes(expr, &mut ld.nodes);
         
         ld.nodes.push(LiveNode {
             origin: LiveNodeOrigin::from_token_id(self.get_token_id()),
@@ -1287,7 +1235,139 @@ impl<'a> LiveParser<'a> {
         Ok(arg_exprs)
     }
     
+    // parses a `fn` that live value expressions can call. The body may only hold `let`
+    // bindings and a final (optionally `return`ed) expression, which keeps it valid shader code
+    pub(crate) fn expect_eval_fn_def(&mut self) -> Result<LiveEvalFnDef, LiveError> {
+        self.expect_token(LiveToken::Ident(live_id!(fn))) ?;
+        self.expect_ident() ?;
+        self.expect_token(LiveToken::Open(Delim::Paren)) ?;
+        let mut params = Vec::new();
+        if !self.accept_token(LiveToken::Close(Delim::Paren)) {
+            loop {
+                params.push(self.expect_ident() ?);
+                if self.accept_token(LiveToken::Punct(live_id!(:))) {
+                    self.expect_ident() ?;
+                }
+                if !self.accept_token(LiveToken::Punct(live_id!(,))) {
+                    break;
+                }
+            }
+            self.expect_token(LiveToken::Close(Delim::Paren)) ?;
+        }
+        if self.accept_token(LiveToken::Punct(live_id!(->))) {
+            self.expect_ident() ?;
+        }
+        self.expect_token(LiveToken::Open(Delim::Brace)) ?;
+        let mut lets = Vec::new();
+        while self.accept_token(LiveToken::Ident(live_id!(let))) {
+            let ident = self.expect_ident() ?;
+            if self.accept_token(LiveToken::Punct(live_id!(:))) {
+                self.expect_ident() ?;
+            }
+            self.expect_token(LiveToken::Punct(live_id!(=))) ?;
+            lets.push((ident, self.expect_eval_fn_expr() ?));
+            self.accept_token(LiveToken::Punct(live_id!(;)));
+        }
+        self.accept_token(LiveToken::Ident(live_id!(return)));
+        let ret = self.expect_eval_fn_expr() ?;
+        self.accept_token(LiveToken::Punct(live_id!(;)));
+        if !self.accept_token(LiveToken::Close(Delim::Brace)) {
+            return Err(self.error("Functions called from live values can only contain let statements and a return expression".to_string(), live_error_origin!()))
+        }
+        Ok(LiveEvalFnDef {params, lets, ret})
+    }
     
+    fn expect_eval_fn_expr(&mut self) -> Result<Vec<LiveNode>, LiveError> {
+        let origin = LiveNodeOrigin::from_token_id(self.get_token_id());
+        let expr = self.expect_expr() ?;
+        let mut nodes = vec![LiveNode {origin, id: LiveId::empty(), value: LiveValue::Expr}];
+        push_expr_nodes(expr, &mut nodes);
+        nodes.push(LiveNode {origin: LiveNodeOrigin::from_token_id(self.get_token_id()), id: LiveId::empty(), value: LiveValue::Close});
+        Ok(nodes)
+    }
+}
+
+/// A `fn` from a live document in the form live value expressions can evaluate. Each
+/// expression is stored as an `Expr` ... `Close` node range, like an expression property.
+pub(crate) struct LiveEvalFnDef {
+    pub params: Vec<LiveId>,
+    pub lets: Vec<(LiveId, Vec<LiveNode>)>,
+    pub ret: Vec<LiveNode>,
+}
+
+fn push_expr_nodes(expr: Expr, nodes: &mut Vec<LiveNode>) {
+    match expr {
+        Expr::Bin {token_id, op, left_expr, right_expr} => {
+            nodes.push(LiveNode {
+                origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
+                id: LiveId::empty(),
+                value: LiveValue::ExprBinOp(op)
+            });
+            push_expr_nodes(*left_expr, nodes);
+            push_expr_nodes(*right_expr, nodes);
+        }
+        Expr::Un {token_id, op, expr} => {
+            nodes.push(LiveNode {
+                origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
+                id: LiveId::empty(),
+                value: LiveValue::ExprUnOp(op)
+            });
+            push_expr_nodes(*expr, nodes);
+        }
+        Expr::Call {token_id, ident, arg_exprs} => {
+            nodes.push(LiveNode {
+                origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
+                id: LiveId::empty(),
+                value: LiveValue::ExprCall {ident, args: arg_exprs.len()}
+            });
+            for arg in arg_exprs {
+                push_expr_nodes(arg, nodes);
+            }
+        }
+        Expr::Member {token_id, ident, expr} => {
+            nodes.push(LiveNode {
+                origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
+                id: LiveId::empty(),
+                value: LiveValue::ExprMember(ident)
+            });
+            push_expr_nodes(*expr, nodes);
+        }
+        Expr::Var {token_id, ident} => {
+            nodes.push(LiveNode {
+                origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
+                id: LiveId::empty(),
+                value: LiveValue::Id(ident)
+            });
+        }
+        Expr::Bool {token_id, v} => {
+            nodes.push(LiveNode {
+                origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
+                id: LiveId::empty(),
+                value: LiveValue::Bool(v)
+            });
+        }
+        Expr::Int {token_id, v} => {
+            nodes.push(LiveNode {
+                origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
+                id: LiveId::empty(),
+                value: LiveValue::Int64(v)
+            });
+        }
+        Expr::Float {token_id, v} => {
+            nodes.push(LiveNode {
+                origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
+                id: LiveId::empty(),
+                value: LiveValue::Float64(v)
+            });
+        }
+        Expr::Color {token_id, v} => {
+            nodes.push(LiveNode {
+                origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Nameless),
+                id: LiveId::empty(),
+                value: LiveValue::Color(v)
+            });
+        }
+    }
 }
 
 #[derive(Debug)]
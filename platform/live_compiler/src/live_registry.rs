@@ -8,14 +8,18 @@ use {
         live_error::{LiveError, LiveErrorSpan, LiveFileError},
         live_parser::LiveParser,
         live_document::{LiveOriginal, LiveExpanded},
-        live_node::{LiveNodeOrigin, LiveNode, LiveValue, LiveType, LiveTypeInfo, LiveIdAsProp, LiveDesignInfo, LiveDesignInfoIndex},
+        live_node::{LiveNodeOrigin, LiveNode, LiveValue, LiveType, LiveTypeInfo, LiveIdAsProp, LivePropType, LiveDesignInfo, LiveDesignInfoIndex},
         /*live_node_reader::{LiveNodeMutReader},*/
         live_node_vec::{LiveNodeSliceApi, /*LiveNodeVecApi*/},
         live_ptr::{LiveFileId, LivePtr, LiveModuleId, LiveFileGeneration},
         live_token::{LiveToken, LiveTokenId, TokenWithSpan},
         span::{TextSpan, TextPos},
         live_expander::{LiveExpander},
-        live_component::{LiveComponentRegistries}
+        live_lint::LiveLinter,
+        live_component::{LiveComponentRegistries},
+        live_eval::live_eval_media_condition,
+        live_eval_fns::LiveEvalFns,
+        live_media::LiveMedia,
     }
 };
 
@@ -34,6 +38,9 @@ pub struct LiveFile {
     pub original: LiveOriginal,
     pub next_original: Option<LiveOriginal>,
     pub expanded: LiveExpanded,
+    /// The module overriding this one's values and the original nodes without the overrides,
+    /// see `LiveRegistry::rebind_module_values`
+    pub (crate) rebound: Option<(LiveModuleId, Vec<LiveNode>)>,
     
     pub live_type_infos: Vec<LiveTypeInfo>,
 }
@@ -46,6 +53,9 @@ pub struct LiveRegistry {
     //pub ignore_no_dsl: HashSet<LiveId>,
     pub main_module: Option<LiveTypeInfo>,
     pub components: LiveComponentRegistries,
+    pub eval_fns: LiveEvalFns,
+    /// What `@media` conditions are evaluated against, set it with `update_media`
+    pub media: LiveMedia,
     pub package_root: Option<String>
 }
 
@@ -58,6 +68,8 @@ impl Default for LiveRegistry {
             live_files: Vec::new(),
             live_type_infos: Default::default(),
             components: LiveComponentRegistries::default(),
+            eval_fns: LiveEvalFns::default(),
+            media: LiveMedia::default(),
             package_root: None
         }
     }
@@ -681,6 +693,7 @@ impl LiveRegistry {
     
     pub fn process_file_changes(&mut self, changes: Vec<LiveFileChange>, errors:&mut Vec<LiveError >){
         let mut any_changes = false;
+        let mut changed_modules = Vec::new();
         for change in changes {
             if let Some(file_id) = self.file_name_to_file_id(&change.file_name){
                 let module_id = self.file_id_to_module_id(file_id).unwrap();
@@ -705,7 +718,11 @@ impl LiveRegistry {
                                     }
                                 }
                                 any_changes = true;
+                                changed_modules.push(module_id);
                                 ld.tokens = new_tokens;
+                                if let Some((_, unbound_nodes)) = &mut live_file.rebound {
+                                    *unbound_nodes = ld.nodes.clone();
+                                }
                                 live_file.original = ld;
                                 live_file.reexpand = true;
                                 live_file.generation.next_gen();
@@ -718,8 +735,114 @@ impl LiveRegistry {
         if any_changes{
             // try to re-expand
             self.expand_all_documents(errors);
+            // the overrides come from the expanded values, so rebind once those are up to date
+            let mut any_rebound = false;
+            for file_index in 0..self.live_files.len() {
+                if let Some((with_module_id, _)) = &self.live_files[file_index].rebound {
+                    if changed_modules.contains(with_module_id) || changed_modules.contains(&self.live_files[file_index].module_id) {
+                        self.rebind_file(LiveFileId::new(file_index));
+                        any_rebound = true;
+                    }
+                }
+            }
+            if any_rebound {
+                self.expand_all_documents(errors);
+            }
         }
     }
+    
+    /// Overrides the root level values of `module_id` with the plain values of the same name
+    /// in `with_module_id`, or restores its own values when that is `None`. Expressions using
+    /// the overridden values are evaluated again, in `module_id` and in the documents importing
+    /// it, which is how a theme module swaps its constants for those of another theme.
+    pub fn rebind_module_values(&mut self, module_id: LiveModuleId, with_module_id: Option<LiveModuleId>, errors: &mut Vec<LiveError>) {
+        let file_id = if let Some(file_id) = self.module_id_to_file_id.get(&module_id) {
+            *file_id
+        }
+        else {
+            return
+        };
+        let live_file = &mut self.live_files[file_id.to_index()];
+        if let Some((_, unbound_nodes)) = live_file.rebound.take() {
+            live_file.original.nodes = unbound_nodes;
+        }
+        if let Some(with_module_id) = with_module_id {
+            live_file.rebound = Some((with_module_id, live_file.original.nodes.clone()));
+            self.rebind_file(file_id);
+        }
+        let live_file = &mut self.live_files[file_id.to_index()];
+        live_file.reexpand = true;
+        live_file.generation.next_gen();
+        self.expand_all_documents(errors);
+    }
+    
+    /// Sets the values `@media` conditions are evaluated against. The documents in which that
+    /// changes the outcome of a condition are expanded again, as are the ones depending on them.
+    /// Returns whether anything was expanded.
+    pub fn update_media(&mut self, media: LiveMedia, errors: &mut Vec<LiveError>) -> bool {
+        if media == self.media {
+            return false
+        }
+        let media_files: Vec<usize> = (0..self.live_files.len())
+            .filter( | index | self.live_files[*index].original.nodes.iter().any( | node | node.value == LiveValue::Media))
+            .collect();
+        let before: Vec<Vec<bool>> = media_files.iter().map( | index | self.media_conditions(*index)).collect();
+        self.media = media;
+        let mut changed = false;
+        for (index, before) in media_files.into_iter().zip(before) {
+            if self.media_conditions(index) != before {
+                let live_file = &mut self.live_files[index];
+                live_file.reexpand = true;
+                live_file.generation.next_gen();
+                changed = true;
+            }
+        }
+        if changed {
+            self.expand_all_documents(errors);
+        }
+        changed
+    }
+    
+    // the outcome of every @media condition in a file, the expander reports the errors
+    fn media_conditions(&self, file_index: usize) -> Vec<bool> {
+        let live_file = &self.live_files[file_index];
+        let nodes = &live_file.original.nodes;
+        (0..nodes.len()).filter( | index | nodes[*index].value == LiveValue::Media).map( | index | {
+            live_eval_media_condition(self, index, nodes, &live_file.expanded.nodes).unwrap_or(false)
+        }).collect()
+    }
+    
+    fn rebind_file(&mut self, file_id: LiveFileId) {
+        let (with_module_id, mut nodes) = if let Some((with_module_id, unbound_nodes)) = &self.live_files[file_id.to_index()].rebound {
+            (*with_module_id, unbound_nodes.clone())
+        }
+        else {
+            return
+        };
+        if let Some(with_file_id) = self.module_id_to_file_id.get(&with_module_id) {
+            let with_nodes = &self.live_files[with_file_id.to_index()].expanded.nodes;
+            let mut with_child = with_nodes.first_child(0);
+            while let Some(with_index) = with_child {
+                with_child = with_nodes.next_child(with_index);
+                let with_node = &with_nodes[with_index];
+                if with_node.value.is_open() || with_node.value.is_dsl() || !with_node.origin.has_prop_type(LivePropType::Instance) {
+                    continue
+                }
+                if let Some(index) = nodes.child_by_name(0, with_node.id.as_instance()) {
+                    let end = nodes.skip_node(index);
+                    let origin = nodes[index].origin;
+                    nodes.splice(index..end, Some(LiveNode {
+                        origin,
+                        id: with_node.id,
+                        value: with_node.value.clone()
+                    }));
+                }
+            }
+        }
+        let live_file = &mut self.live_files[file_id.to_index()];
+        live_file.original.nodes = nodes;
+        live_file.reexpand = true;
+    }
 
     pub fn register_live_file(
         &mut self,
@@ -811,7 +934,8 @@ impl LiveRegistry {
             live_type_infos,
             original,
             next_original: None,
-            expanded: LiveExpanded::new()
+            expanded: LiveExpanded::new(),
+            rebound: None,
         };
         self.module_id_to_file_id.insert(own_module_id, file_id);
         
@@ -915,6 +1039,16 @@ impl LiveRegistry {
             std::mem::swap(&mut out_doc, &mut self.live_files[file_id.to_index()].expanded);
         }
     }
+    
+    /// Checks the expanded documents against the field lists of the Rust types they apply to,
+    /// see `LiveLinter`. Run it after `expand_all_documents`, passing the same errors so the
+    /// spans it already reported are skipped.
+    pub fn lint_all_documents(&self, errors: &mut Vec<LiveError>) {
+        let mut linter = LiveLinter::new(self, errors);
+        for live_file in &self.live_files {
+            linter.lint(&live_file.expanded.nodes);
+        }
+    }
 }
 
 #[derive(Debug)]
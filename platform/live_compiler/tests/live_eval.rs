@@ -0,0 +1,97 @@
+use makepad_live_compiler::{*, makepad_live_id::*, makepad_math::*};
+
+fn expand(files: &[(&str, &str)], setup: impl FnOnce(&mut LiveRegistry)) -> (LiveRegistry, Vec<LiveError>) {
+    let mut live_registry = LiveRegistry::default();
+    setup(&mut live_registry);
+    for (module_path, source) in files {
+        live_registry.register_live_file(
+            &format!("{}.rs", module_path),
+            "",
+            LiveModuleId::from_str(module_path).unwrap(),
+            source.to_string(),
+            vec![],
+            TextPos::default()
+        ).map_err(|e| e.message).unwrap();
+    }
+    let mut errors = Vec::new();
+    live_registry.expand_all_documents(&mut errors);
+    (live_registry, errors)
+}
+
+fn value(live_registry: &LiveRegistry, module_path: &str, id: LiveId) -> LiveValue {
+    let file_id = live_registry.module_id_to_file_id[&LiveModuleId::from_str(module_path).unwrap()];
+    let nodes = &live_registry.live_files[file_id.to_index()].expanded.nodes;
+    let index = nodes.child_by_name(0, id.as_instance()).unwrap();
+    nodes[index].value.clone()
+}
+
+fn eval(source: &str) -> LiveValue {
+    let (live_registry, errors) = expand(&[("test::a", source)], |_| {});
+    assert!(errors.is_empty(), "{:?}", errors);
+    value(&live_registry, "test::a", live_id!(v))
+}
+
+fn eval_error(source: &str) -> String {
+    let (_, errors) = expand(&[("test::a", source)], |_| {});
+    errors[0].message.clone()
+}
+
+#[test]
+fn calls_live_fns() {
+    assert_eq!(eval("fn double(x: float) -> float { return x * 2.0 }\nv = (double(3.0) + 1)"), LiveValue::Float64(7.0));
+}
+
+#[test]
+fn calls_live_fns_with_let_bindings_and_members() {
+    assert_eq!(
+        eval("fn luma(c: vec4) -> float { let w = 0.5; return c.r * w + c.g * w }\nv = (luma(#ff0000))"),
+        LiveValue::Float64(0.5)
+    );
+}
+
+#[test]
+fn calls_recursive_live_fns() {
+    assert_eq!(eval("fn fact(n) { return n <= 1 ? 1 : n * fact(n - 1) }\nv = (fact(5))"), LiveValue::Int64(120));
+    assert_eq!(eval_error("fn forever(n) { return forever(n) }\nv = (forever(5))"), "Cannot call fn forever: calls nest too deep");
+}
+
+#[test]
+fn calls_live_fns_from_imported_modules() {
+    let (live_registry, errors) = expand(&[
+        ("test::theme", "BASE = 8.0\nfn rem(x) { return x * BASE }"),
+        ("test::a", "import test::theme::*;\nv = (rem(2))"),
+    ], |_| {});
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(value(&live_registry, "test::a", live_id!(v)), LiveValue::Float64(16.0));
+}
+
+#[test]
+fn checks_live_fn_arguments() {
+    assert_eq!(eval_error("fn double(x) { return x * 2.0 }\nv = (double(1, 2))"), "Cannot call fn double: expected 1 arguments but got 2");
+    assert_eq!(eval_error("fn double(x) { let y = x; y = 2; return y }\nv = (double(1))").contains("can only contain let statements"), true);
+}
+
+#[test]
+fn calls_registered_rust_fns() {
+    fn triple(args: &[LiveValue]) -> Result<LiveValue, String> {
+        Ok(LiveValue::Float64(args[0].as_float().ok_or("expected a number") ? * 3.0))
+    }
+    let (live_registry, errors) = expand(&[("test::a", "v = (triple(2) + 1)")], |live_registry| {
+        live_registry.eval_fns.register(live_id!(triple), triple);
+    });
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(value(&live_registry, "test::a", live_id!(v)), LiveValue::Float64(7.0));
+}
+
+#[test]
+fn calls_standard_fns() {
+    assert_eq!(eval("v = (clamp(5, 0, 3))"), LiveValue::Int64(3));
+    assert_eq!(eval("v = (min(1.5, 2))"), LiveValue::Float64(1.5));
+    assert_eq!(eval("v = (max(1.5, 2))"), LiveValue::Float64(2.0));
+    assert_eq!(eval("v = (fade(#ff0000, 0.5))"), LiveValue::Vec4(vec4(1.0, 0.0, 0.0, 0.5)));
+    assert_eq!(eval("v = (darken(#ff0000, 0.25))"), LiveValue::Vec4(vec4(0.5, 0.0, 0.0, 1.0)));
+    assert_eq!(eval("v = (lighten(#ff0000, 0.25))"), LiveValue::Vec4(vec4(1.0, 0.5, 0.5, 1.0)));
+    assert_eq!(eval("v = (hsl(0.0, 1.0, 0.5))"), LiveValue::Vec4(vec4(1.0, 0.0, 0.0, 1.0)));
+    assert_eq!(eval("v = (to_hsl(#ff0000).z)"), LiveValue::Float64(0.5));
+    assert_eq!(eval_error("v = (min(#ff0000, 2))"), "min: expected two numbers but got Color(4278190335) and Int64(2)");
+}
@@ -0,0 +1,99 @@
+use makepad_live_compiler::{format_live_design, format_live_design_in_rust, LiveFormatOptions};
+
+fn format(source: &str) -> String {
+    format_live_design(source, &LiveFormatOptions::default()).unwrap()
+}
+
+#[test]
+fn puts_one_property_per_line() {
+    assert_eq!(
+        format("Button = <View> { width: Fill, height: Fit draw_bg: { color: #f00, radius: 2.0 } }"),
+        "Button = <View> {\n    width: Fill,\n    height: Fit\n    draw_bg: {color: #f00, radius: 2.0}\n}\n"
+    );
+}
+
+#[test]
+fn normalizes_indentation() {
+    assert_eq!(
+        format("App = {{App}}{\n  ui: <Window>{\n\t\t\tbody = <View>{}\n      }\n}"),
+        "App = {{App}} {\n    ui: <Window> {\n        body = <View> {}\n    }\n}\n"
+    );
+}
+
+#[test]
+fn keeps_short_single_line_blocks() {
+    assert_eq!(
+        format("Label = <View> {\nalign: {x: 0.5, y: 0.5},\npadding: { left: 10, right: 10 }\n}"),
+        "Label = <View> {\n    align: {x: 0.5, y: 0.5},\n    padding: {left: 10, right: 10}\n}\n"
+    );
+}
+
+#[test]
+fn keeps_comments_and_blank_lines() {
+    assert_eq!(
+        format("// The header\nA = <View> { // Trailing\n\n\n    // Leading\n    width: 100.0, /* Inline */ height: 50.0\n    /* Last */\n}"),
+        "// The header\nA = <View> { // Trailing\n\n    // Leading\n    width: 100.0, /* Inline */\n    height: 50.0\n    /* Last */\n}\n"
+    );
+}
+
+#[test]
+fn formats_values() {
+    assert_eq!(
+        format("a: -1.0, b: vec4( 1.0,0.0 , -0.5,1 ), c: [x,y], d: (THEME_SPACE * 2.0), e: Inherit(Down), f: dep(\"crate://self/a.png\")"),
+        "a: -1.0,\nb: vec4(1.0, 0.0, -0.5, 1),\nc: [x, y],\nd: (THEME_SPACE * 2.0),\ne: Inherit(Down),\nf: dep(\"crate://self/a.png\")\n"
+    );
+}
+
+#[test]
+fn formats_shader_functions() {
+    assert_eq!(
+        format("draw_bg: { instance hover: 0.0\nfn pixel(self)->vec4{ let sdf = Sdf2d::viewport(self.pos*self.rect_size); if self.hover > 0.5 { return #f00 } else { sdf.box(1.,1.,2.,2.,-1.); } return sdf.result } }"),
+        "draw_bg: {\n    instance hover: 0.0\n    fn pixel(self) -> vec4 {\n        let sdf = Sdf2d::viewport(self.pos * self.rect_size);\n        if self.hover > 0.5 {\n            return #f00\n        } else {\n            sdf.box(1., 1., 2., 2., -1.);\n        }\n        return sdf.result\n    }\n}\n"
+    );
+}
+
+#[test]
+fn ends_statements_without_semicolons_at_the_end_of_the_line() {
+    assert_eq!(
+        format("fn pixel(self) -> vec4 {\n    let a = 1.0\n    let b = a\n        + 2.0\n    return vec4(b)\n}"),
+        "fn pixel(self) -> vec4 {\n    let a = 1.0\n    let b = a + 2.0\n    return vec4(b)\n}\n"
+    );
+}
+
+#[test]
+fn formats_imports() {
+    assert_eq!(
+        format("import makepad_draw::shader::std::*;\nimport crate::app_ui::AppUI;"),
+        "import makepad_draw::shader::std::*;\nimport crate::app_ui::AppUI;\n"
+    );
+}
+
+#[test]
+fn formats_media_blocks() {
+    assert_eq!(
+        format("A = <View> { flow: Right @media( width<600 &&!web ){flow: Down} }"),
+        "A = <View> {\n    flow: Right\n    @media(width < 600 && !web) {\n        flow: Down\n    }\n}\n"
+    );
+}
+
+#[test]
+fn is_idempotent() {
+    let source = "import a::*;\nA = <View> {\n  // Comment\n  flow: Down, spacing: 5\n  align: {x: 1.0}\n  fn pixel(self) -> vec4 { return mix(#f00, #0f0, self.pos.x) }\n  items: [<Button> {text: \"a\"}, <Button> {}]\n}";
+    let once = format(source);
+    assert_eq!(format(&once), once);
+}
+
+#[test]
+fn rejects_unbalanced_delimiters() {
+    let error = format_live_design("A = <View> {\n    b: (1.0\n}", &LiveFormatOptions::default()).unwrap_err();
+    assert_eq!(error.to_string(), "3:1 - unexpected `}`");
+}
+
+#[test]
+fn formats_blocks_in_rust_files() {
+    let source = "use makepad_widgets::*;\n\nmod app {\n    live_design!{\n    A = <View>{ width: Fill }\n    }\n}\n\n// live_design!{ not this one }\n";
+    assert_eq!(
+        format_live_design_in_rust(source, &LiveFormatOptions::default()).unwrap(),
+        "use makepad_widgets::*;\n\nmod app {\n    live_design!{\n        A = <View> {\n            width: Fill\n        }\n    }\n}\n\n// live_design!{ not this one }\n"
+    );
+}
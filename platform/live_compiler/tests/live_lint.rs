@@ -0,0 +1,117 @@
+use makepad_live_compiler::{*, makepad_live_id::*, makepad_math::*};
+use std::any::TypeId;
+
+struct Button;
+struct DrawThing;
+struct DrawVars;
+struct Flow;
+
+fn type_info(live_type: TypeId, name: &str, live_ignore: bool) -> LiveTypeInfo {
+    LiveTypeInfo {
+        live_type,
+        type_name: LiveId::from_str_with_lut(name).unwrap(),
+        module_id: LiveModuleId::from_str("test::a").unwrap(),
+        live_ignore,
+        fields: Vec::new(),
+        variants: Vec::new(),
+    }
+}
+
+fn field(name: &str, live_type_info: LiveTypeInfo, live_field_kind: LiveFieldKind) -> LiveTypeField {
+    LiveTypeField {
+        id: LiveId::from_str_with_lut(name).unwrap(),
+        live_type_info,
+        live_field_kind
+    }
+}
+
+fn draw_thing_type_info() -> LiveTypeInfo {
+    let mut draw_thing = type_info(TypeId::of::<DrawThing>(), "DrawThing", false);
+    draw_thing.fields = vec![
+        field("draw_vars", type_info(TypeId::of::<DrawVars>(), "DrawVars", true), LiveFieldKind::Deref),
+        field("color", type_info(TypeId::of::<Vec4>(), "Vec4", true), LiveFieldKind::Live),
+    ];
+    draw_thing
+}
+
+fn button_type_info() -> LiveTypeInfo {
+    let mut flow = type_info(TypeId::of::<Flow>(), "Flow", true);
+    flow.variants = vec![
+        (live_id!(Right), LiveVariantKind::Bare),
+        (live_id!(Down), LiveVariantKind::Bare),
+        (live_id!(Grid), LiveVariantKind::Named),
+    ];
+    let mut button = type_info(TypeId::of::<Button>(), "Button", false);
+    button.fields = vec![
+        field("text", type_info(TypeId::of::<String>(), "String", true), LiveFieldKind::Live),
+        field("width", type_info(TypeId::of::<f64>(), "f64", true), LiveFieldKind::Live),
+        field("label", type_info(TypeId::of::<String>(), "String", true), LiveFieldKind::LiveOption),
+        field("flow", flow, LiveFieldKind::Live),
+        field("draw", draw_thing_type_info(), LiveFieldKind::Live),
+    ];
+    button
+}
+
+fn lint(source: &str) -> Vec<String> {
+    let mut live_registry = LiveRegistry::default();
+    live_registry.register_live_file(
+        "test/a.rs",
+        "",
+        LiveModuleId::from_str("test::a").unwrap(),
+        format!("DrawThing = {{{{DrawThing}}}} {{}}\n{}", source),
+        vec![draw_thing_type_info(), button_type_info()],
+        TextPos::default()
+    ).map_err(|e| e.message).unwrap();
+    let mut errors = Vec::new();
+    live_registry.expand_all_documents(&mut errors);
+    live_registry.lint_all_documents(&mut errors);
+    errors.into_iter().map(|e| e.message).collect()
+}
+
+#[test]
+fn accepts_matching_documents() {
+    let errors = lint(r#"
+        Button = {{Button}} {
+            text: "hi"
+            width: 10
+            label: None
+            flow: Down
+            draw: {color: #f00, shader_uniform: 1.0}
+        }
+        Other = <Button> {width: 2.0, label: "label", flow: Grid {}}
+    "#);
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn reports_unknown_fields() {
+    assert_eq!(lint("Button = {{Button}} {widht: 10.0}"), vec!["Button has no field widht"]);
+    // DrawThing derefs to the live_ignore DrawVars, which takes any shader input
+    assert_eq!(lint("Button = {{Button}} {draw: {colr: #f00}}"), Vec::<String>::new());
+}
+
+#[test]
+fn reports_unknown_fields_in_clones() {
+    assert_eq!(lint("Button = {{Button}} {}\nOther = <Button> {titel: \"x\"}"), vec!["Button has no field titel"]);
+}
+
+#[test]
+fn reports_wrong_value_kinds() {
+    assert_eq!(lint("Button = {{Button}} {text: 5.0, width: \"wide\"}"), vec![
+        "Field text expects a string but got a number",
+        "Field width expects a number but got a string",
+    ]);
+    assert_eq!(lint("Button = {{Button}} {draw: {color: \"red\"}}"), vec!["Field color expects a vec4 or color but got a string"]);
+}
+
+#[test]
+fn reports_unknown_enum_variants() {
+    assert_eq!(lint("Button = {{Button}} {flow: Sideways}"), vec!["Flow has no variant Sideways for field flow"]);
+    assert_eq!(lint("Button = {{Button}} {flow: Down(1)}"), vec!["Flow::Down is a bare variant but field flow gives it as a tuple variant"]);
+}
+
+#[test]
+fn reports_unresolved_clones_once() {
+    let errors = lint("Button = {{Button}} {}\nOther = <Missing> {}");
+    assert_eq!(errors, vec!["Can't find live definition of Missing did you forget to call live_design for it?"]);
+}
@@ -0,0 +1,89 @@
+use makepad_live_compiler::{*, makepad_live_id::*};
+
+const APP: &str = "
+    BREAKPOINT = 600
+    Panel = <View> {
+        flow: Right
+        spacing: 10.0
+        <Label> {}
+        @media(width < BREAKPOINT) {
+            flow: Down
+            <Button> {}
+            @media(dpi_factor >= 2.0) {spacing: 5.0}
+        }
+        @media(android || ios) {padding: 4.0}
+    }
+";
+
+fn registry(media: LiveMedia) -> LiveRegistry {
+    let mut live_registry = LiveRegistry::default();
+    live_registry.media = media;
+    for (module_path, source) in [("test::base", "View = {}\nLabel = {}\nButton = {}"), ("test::app", &format!("import test::base::*;\n{}", APP)[..])] {
+        live_registry.register_live_file(
+            &format!("{}.rs", module_path),
+            "",
+            LiveModuleId::from_str(module_path).unwrap(),
+            source.to_string(),
+            vec![],
+            TextPos::default()
+        ).map_err(|e| e.message).unwrap();
+    }
+    let mut errors = Vec::new();
+    live_registry.expand_all_documents(&mut errors);
+    assert!(errors.is_empty(), "{:?}", errors);
+    live_registry
+}
+
+fn media(width: f64, dpi_factor: f64, platform: LiveId) -> LiveMedia {
+    LiveMedia {width, height: 600.0, dpi_factor, platform}
+}
+
+fn panel(live_registry: &LiveRegistry, id: LiveId) -> Option<LiveValue> {
+    let file_id = live_registry.module_id_to_file_id[&LiveModuleId::from_str("test::app").unwrap()];
+    let nodes = &live_registry.live_files[file_id.to_index()].expanded.nodes;
+    let panel = nodes.child_by_name(0, live_id!(Panel).as_instance()).unwrap();
+    nodes.child_by_name(panel, id.as_field()).or_else(|| nodes.child_by_name(panel, id.as_instance())).map(|index| nodes[index].value.clone())
+}
+
+#[test]
+fn applies_blocks_whose_condition_holds() {
+    let live_registry = registry(media(1200.0, 1.0, live_id!(macos)));
+    assert_eq!(panel(&live_registry, live_id!(flow)), Some(LiveValue::BareEnum(live_id!(Right))));
+    assert_eq!(panel(&live_registry, live_id!(padding)), None);
+    assert_eq!(panel(&live_registry, LiveId(2)), None);
+
+    let live_registry = registry(media(400.0, 2.0, live_id!(android)));
+    assert_eq!(panel(&live_registry, live_id!(flow)), Some(LiveValue::BareEnum(live_id!(Down))));
+    assert_eq!(panel(&live_registry, live_id!(spacing)), Some(LiveValue::Float64(5.0)));
+    assert_eq!(panel(&live_registry, live_id!(padding)), Some(LiveValue::Float64(4.0)));
+    // nameless children of a block are numbered on from the ones before it
+    assert!(panel(&live_registry, LiveId(1)).is_some());
+    assert!(panel(&live_registry, LiveId(2)).is_some());
+}
+
+#[test]
+fn expands_again_when_a_condition_changes() {
+    let mut live_registry = registry(media(1200.0, 1.0, live_id!(macos)));
+    let mut errors = Vec::new();
+    assert!(!live_registry.update_media(media(1000.0, 1.0, live_id!(macos)), &mut errors));
+
+    assert!(live_registry.update_media(media(500.0, 1.0, live_id!(macos)), &mut errors));
+    assert_eq!(panel(&live_registry, live_id!(flow)), Some(LiveValue::BareEnum(live_id!(Down))));
+    assert_eq!(panel(&live_registry, live_id!(spacing)), Some(LiveValue::Float64(10.0)));
+
+    assert!(live_registry.update_media(media(700.0, 1.0, live_id!(macos)), &mut errors));
+    assert_eq!(panel(&live_registry, live_id!(flow)), Some(LiveValue::BareEnum(live_id!(Right))));
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn reports_bad_media_blocks() {
+    let mut live_registry = LiveRegistry::default();
+    let error = live_registry.register_live_file("test/a.rs", "", LiveModuleId::from_str("test::a").unwrap(), "@screen(width < 600) {}".to_string(), vec![], TextPos::default()).unwrap_err();
+    assert_eq!(error.message, "Unexpected @screen, expected @media");
+
+    live_registry.register_live_file("test/b.rs", "", LiveModuleId::from_str("test::b").unwrap(), "@media(width) {a: 1.0}".to_string(), vec![], TextPos::default()).map_err(|e| e.message).unwrap();
+    let mut errors = Vec::new();
+    live_registry.expand_all_documents(&mut errors);
+    assert_eq!(errors[0].message, "@media condition should be a bool but got Float64(800.0)");
+}
@@ -0,0 +1,78 @@
+use makepad_live_compiler::{*, makepad_live_id::*, makepad_math::*};
+
+const DARK: &str = "W = #fff\nB = #000\nFG = (mix(B, W, 0.75))\nSIZE = 8.0";
+const LIGHT: &str = "W = #000\nB = #fff\nEXTRA = 1.0";
+const APP: &str = "import test::dark::*;\nv = (FG)\nsize = (SIZE)";
+
+fn registry() -> LiveRegistry {
+    let mut live_registry = LiveRegistry::default();
+    for (module_path, source) in [("test::dark", DARK), ("test::light", LIGHT), ("test::app", APP)] {
+        live_registry.register_live_file(
+            &format!("{}.rs", module_path),
+            "",
+            LiveModuleId::from_str(module_path).unwrap(),
+            source.to_string(),
+            vec![],
+            TextPos::default()
+        ).map_err(|e| e.message).unwrap();
+    }
+    let mut errors = Vec::new();
+    live_registry.expand_all_documents(&mut errors);
+    assert!(errors.is_empty(), "{:?}", errors);
+    live_registry
+}
+
+fn value(live_registry: &LiveRegistry, module_path: &str, id: LiveId) -> LiveValue {
+    let file_id = live_registry.module_id_to_file_id[&LiveModuleId::from_str(module_path).unwrap()];
+    let nodes = &live_registry.live_files[file_id.to_index()].expanded.nodes;
+    let index = nodes.child_by_name(0, id.as_instance()).unwrap();
+    nodes[index].value.clone()
+}
+
+fn rebind(live_registry: &mut LiveRegistry, with_module_path: Option<&str>) {
+    let mut errors = Vec::new();
+    live_registry.rebind_module_values(
+        LiveModuleId::from_str("test::dark").unwrap(),
+        with_module_path.map(|v| LiveModuleId::from_str(v).unwrap()),
+        &mut errors
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn rebinds_module_values_into_importers() {
+    let mut live_registry = registry();
+    assert_eq!(value(&live_registry, "test::app", live_id!(v)), LiveValue::Vec4(vec4(0.75, 0.75, 0.75, 1.0)));
+
+    rebind(&mut live_registry, Some("test::light"));
+    assert_eq!(value(&live_registry, "test::dark", live_id!(W)), LiveValue::Color(0x000000ff));
+    assert_eq!(value(&live_registry, "test::app", live_id!(v)), LiveValue::Vec4(vec4(0.25, 0.25, 0.25, 1.0)));
+    assert_eq!(value(&live_registry, "test::app", live_id!(size)), LiveValue::Float64(8.0));
+    let dark = live_registry.module_id_to_file_id[&LiveModuleId::from_str("test::dark").unwrap()];
+    assert!(live_registry.live_files[dark.to_index()].expanded.nodes.child_by_name(0, live_id!(EXTRA).as_instance()).is_none());
+
+    rebind(&mut live_registry, None);
+    assert_eq!(value(&live_registry, "test::app", live_id!(v)), LiveValue::Vec4(vec4(0.75, 0.75, 0.75, 1.0)));
+}
+
+#[test]
+fn keeps_rebinding_across_live_edits() {
+    let mut live_registry = registry();
+    rebind(&mut live_registry, Some("test::light"));
+
+    let mut errors = Vec::new();
+    live_registry.process_file_changes(vec![LiveFileChange {
+        file_name: "test::light.rs".to_string(),
+        content: "live_design!{W = #000\nB = #888}".to_string()
+    }], &mut errors);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(value(&live_registry, "test::dark", live_id!(B)), LiveValue::Color(0x888888ff));
+
+    live_registry.process_file_changes(vec![LiveFileChange {
+        file_name: "test::dark.rs".to_string(),
+        content: "live_design!{W = #fff\nB = #000\nFG = (mix(B, W, 0.75))\nSIZE = 10.0}".to_string()
+    }], &mut errors);
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert_eq!(value(&live_registry, "test::dark", live_id!(B)), LiveValue::Color(0x888888ff));
+    assert_eq!(value(&live_registry, "test::app", live_id!(size)), LiveValue::Float64(10.0));
+}
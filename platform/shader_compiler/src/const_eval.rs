@@ -95,13 +95,14 @@ impl ConstEvaluator {
         expr_if_true: &Expr,
         expr_if_false: &Expr,
     ) -> Option<Val> {
-        let val = self.try_const_eval_expr(expr)?;
-        let val_if_true = self.try_const_eval_expr(expr_if_true)?;
-        let val_if_false = self.try_const_eval_expr(expr_if_false)?;
-        Some(if val.to_bool().unwrap() {
-            val_if_true
+        // evaluate all three so each subexpression gets its const_val set
+        let val = self.try_const_eval_expr(expr);
+        let val_if_true = self.try_const_eval_expr(expr_if_true);
+        let val_if_false = self.try_const_eval_expr(expr_if_false);
+        Some(if val?.to_bool().unwrap() {
+            val_if_true?
         } else {
-            val_if_false
+            val_if_false?
         })
     }
 
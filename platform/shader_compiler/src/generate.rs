@@ -77,6 +77,36 @@ pub trait BackendWriter {
     fn write_ty_lit(&self, string: &mut String, ty_lit: TyLit);
     fn write_builtin_call_ident(&self, string: &mut String, ident: Ident, arg_exprs: &[Expr]);
     
+    // the defaults below produce C-style syntax, shared by glsl, hlsl and metal
+    
+    fn needs_select_for_cond_expr(&self) -> bool {
+        false
+    }
+    
+    // assignments are statements instead of expressions, and bare expressions need a phony assignment
+    fn assign_is_stmt(&self) -> bool {
+        false
+    }
+    
+    // inout params are passed as pointers, which need an explicit & at the callsite and * on use
+    fn inout_is_pointer(&self) -> bool {
+        false
+    }
+    
+    // params can't be assigned to, so the fn body starts with mutable copies
+    fn params_are_immutable(&self) -> bool {
+        false
+    }
+    
+    fn write_local_var_prefix(&self, _string: &mut String) {
+    }
+    
+    fn write_fn_def_head(&self, string: &mut String, ident: &dyn fmt::Display, return_ty: &Ty) {
+        self.write_var_decl(string, "", false, false, ident, return_ty);
+    }
+    
+    fn write_fn_def_tail(&self, _string: &mut String, _return_ty: &Ty) {
+    }
 }
 
 pub struct BlockGenerator<'a> {
@@ -288,9 +318,12 @@ impl<'a> BlockGenerator<'a> {
         } else {
             -1
         };
+        write!(self.string, "for (").unwrap();
+        self.backend_writer.write_local_var_prefix(self.string);
+        self.backend_writer.write_var_decl(self.string, "", false, false, &DisplayVarName(ident, ScopeSymShadow(0)), &Ty::Int);
         write!(
             self.string,
-            "for (int {0} = {1}; {0} {2} {3}; {0} {4} {5}) ",
+            " = {1}; {0} {2} {3}; {0} {4} {5}) ",
             &DisplayVarName(ident, ScopeSymShadow(0)),
             if from <= to {from} else {from - 1},
             if from <= to {"<"} else {">="},
@@ -361,6 +394,7 @@ impl<'a> BlockGenerator<'a> {
         expr: &Option<Expr>,
         shadow: &Cell<Option<ScopeSymShadow >>
     ) {
+        self.backend_writer.write_local_var_prefix(self.string);
         self.backend_writer.write_var_decl(
             &mut self.string,
             "",
@@ -390,7 +424,28 @@ impl<'a> BlockGenerator<'a> {
         writeln!(self.string).unwrap();
     }
     
-    fn generate_expr_stmt(&mut self, _span: TokenSpan, expr: &Expr) {
+    fn generate_expr_stmt(&mut self, span: TokenSpan, expr: &Expr) {
+        if self.backend_writer.assign_is_stmt() {
+            match &expr.kind {
+                ExprKind::Bin {op, left_expr, right_expr, ..} if op.is_assign() => {
+                    // chained assignments become one statement per assignment
+                    if let ExprKind::Bin {op: right_op, left_expr: right_left_expr, ..} = &right_expr.kind {
+                        if right_op.is_assign() {
+                            self.generate_expr_stmt(span, right_expr);
+                            writeln!(self.string).unwrap();
+                            self.write_indent();
+                            self.generate_expr(left_expr);
+                            write!(self.string, " {} ", op).unwrap();
+                            self.generate_expr(right_left_expr);
+                            writeln!(self.string, ";").unwrap();
+                            return
+                        }
+                    }
+                }
+                ExprKind::MethodCall {..} | ExprKind::PlainCall {..} => (),
+                _ => write!(self.string, "_ = ").unwrap()
+            }
+        }
         self.generate_expr(expr);
         writeln!(self.string, ";").unwrap();
     }
@@ -569,6 +624,16 @@ impl<'a> ExprGenerator<'a> {
         expr_if_true: &Expr,
         expr_if_false: &Expr,
     ) {
+        if self.backend_writer.needs_select_for_cond_expr() {
+            write!(self.string, "select(").unwrap();
+            self.generate_expr(expr_if_false);
+            write!(self.string, ", ").unwrap();
+            self.generate_expr(expr_if_true);
+            write!(self.string, ", ").unwrap();
+            self.generate_expr(expr);
+            write!(self.string, ")").unwrap();
+            return
+        }
         write!(self.string, "(").unwrap();
         self.generate_expr(expr);
         write!(self.string, " ? ").unwrap();
@@ -653,6 +718,13 @@ impl<'a> ExprGenerator<'a> {
             }
         }
         
+        if op.is_assign() && self.backend_writer.assign_is_stmt() {
+            self.generate_expr(left_expr);
+            write!(self.string, " {} ", op).unwrap();
+            self.generate_expr(right_expr);
+            return
+        }
+        
         write!(self.string, "(").unwrap();
         self.generate_expr(left_expr);
         write!(self.string, " {} ", op).unwrap();
@@ -714,7 +786,7 @@ impl<'a> ExprGenerator<'a> {
             )).unwrap();
             
             let mut sep = "";
-            for arg_expr in arg_exprs {
+            for (arg_index, arg_expr) in arg_exprs.iter().enumerate() {
                 // check if the args is a closure, ifso skip it
                 match arg_expr.ty.borrow().as_ref().unwrap(){
                     Ty::ClosureDef(_)=>{
@@ -724,7 +796,7 @@ impl<'a> ExprGenerator<'a> {
                 }
                 
                 write!(self.string, "{}", sep).unwrap();
-                self.generate_expr(arg_expr);
+                self.generate_arg_expr(fn_def, arg_exprs.len(), arg_index, arg_expr);
                 sep = ", ";
             }
             // and now the closed over values
@@ -751,9 +823,9 @@ impl<'a> ExprGenerator<'a> {
         else {
             write!(self.string, "{}_{} (", fn_def.fn_ptr, fn_def.ident).unwrap();
             let mut sep = "";
-            for arg_expr in arg_exprs {
+            for (arg_index, arg_expr) in arg_exprs.iter().enumerate() {
                 write!(self.string, "{}", sep).unwrap();
-                self.generate_expr(arg_expr);
+                self.generate_arg_expr(fn_def, arg_exprs.len(), arg_index, arg_expr);
                 sep = ", ";
             }
 
@@ -763,6 +835,17 @@ impl<'a> ExprGenerator<'a> {
         }
     }
     
+    fn generate_arg_expr(&mut self, fn_def: &FnDef, arg_count: usize, arg_index: usize, arg_expr: &Expr) {
+        if self.backend_writer.inout_is_pointer() {
+            // drawshader methods get called without their self arg
+            let param_index = arg_index + fn_def.params.len() - arg_count;
+            if fn_def.params[param_index].is_inout {
+                write!(self.string, "&").unwrap();
+            }
+        }
+        self.generate_expr(arg_expr);
+    }
+    
     fn generate_field_expr(&mut self, _span: TokenSpan, expr: &Expr, field_ident: Ident, ty:&Ty) {
         match expr.ty.borrow().as_ref() {
             Some(Ty::DrawShader(_)) => {
@@ -925,11 +1008,13 @@ impl<'a> ExprGenerator<'a> {
     fn generate_var_expr(&mut self, _span: TokenSpan, kind: &Cell<Option<VarKind >>, _ty: &Option<Ty>) {
         // ok so we have a few varkinds
         match kind.get().unwrap() {
-            VarKind::Local {ident, shadow} => {
-                write!(self.string, "{}", DisplayVarName(ident, shadow)).unwrap();
-            }
-            VarKind::MutLocal {ident, shadow} => {
-                write!(self.string, "{}", DisplayVarName(ident, shadow)).unwrap();
+            VarKind::Local {ident, shadow} | VarKind::MutLocal {ident, shadow} => {
+                if self.backend_writer.inout_is_pointer() && self.is_inout_param(ident, shadow) {
+                    write!(self.string, "(*{})", DisplayVarName(ident, shadow)).unwrap();
+                }
+                else {
+                    write!(self.string, "{}", DisplayVarName(ident, shadow)).unwrap();
+                }
             }
             VarKind::LiveValue(value_node_ptr) => {
                 // this is a live value.. also prefix needed
@@ -939,6 +1024,15 @@ impl<'a> ExprGenerator<'a> {
         }
     }
     
+    fn is_inout_param(&self, ident: Ident, shadow: ScopeSymShadow) -> bool {
+        if let Some(fn_def) = self.fn_def {
+            return fn_def.params.iter().any( | param | {
+                param.is_inout && param.ident == ident && param.shadow.get() == Some(shadow)
+            })
+        }
+        false
+    }
+    
     fn generate_lit_expr(&mut self, _span: TokenSpan, lit: Lit) {
         write!(self.string, "{}", lit).unwrap();
     }
@@ -963,50 +1057,75 @@ pub struct FnDefGenerator<'a> {
 impl<'a> FnDefGenerator<'a> {
     pub fn generate_fn_def(&mut self) {
         
-        self.backend_writer.write_var_decl(
+        self.backend_writer.write_fn_def_head(
             &mut self.string,
-            "",
-            false,
-            false,
             &DisplayFnName(self.fn_def.fn_ptr, self.fn_def.ident), // here we must expand IdentPath to something
             self.fn_def.return_ty.borrow().as_ref().unwrap()
         );
         write!(self.string, "(").unwrap();
         let mut sep = "";
+        let mut param_vars = Vec::new();
         for param in &self.fn_def.params {
             if !param.shadow.get().is_none() {
+                let var_name = DisplayVarName(param.ident, param.shadow.get().unwrap());
                 if self.backend_writer.write_var_decl(
                     &mut self.string,
                     sep,
                     param.is_inout,
                     false,
-                    &DisplayVarName(param.ident, param.shadow.get().unwrap()),
+                    &var_name,
                     param.ty_expr.ty.borrow().as_ref().unwrap(),
                 ) {
                     sep = ", ";
+                    if !param.is_inout {
+                        param_vars.push(var_name);
+                    }
                 }
             }
         }
         self.backend_writer.write_fn_def_hidden_params(self.string, self.fn_def.hidden_args.borrow().as_ref().unwrap(), sep);
-        write!(self.string, ") ").unwrap();
-        self.generate_block(&self.fn_def.block);
+        write!(self.string, ")").unwrap();
+        self.backend_writer.write_fn_def_tail(self.string, self.fn_def.return_ty.borrow().as_ref().unwrap());
+        write!(self.string, " ").unwrap();
+        generate_fn_body_with_param_vars(self.string, self.backend_writer, &param_vars, | string | {
+            BlockGenerator {
+                shader_registry: self.shader_registry,
+                closure_site_info: None,
+                //env: self.env,
+                fn_def: self.fn_def,
+                backend_writer: self.backend_writer,
+                const_table_offset: self.const_table_offset,
+                indent_level: 0,
+                string,
+            }
+            .generate_block(&self.fn_def.block)
+        });
         writeln!(self.string).unwrap();
         //self.visited.insert(self.decl.ident_path);
     }
     
-    fn generate_block(&mut self, block: &Block) {
-        BlockGenerator {
-            shader_registry: self.shader_registry,
-            closure_site_info: None,
-            //env: self.env,
-            fn_def: self.fn_def,
-            backend_writer: self.backend_writer,
-            const_table_offset: self.const_table_offset,
-            indent_level: 0,
-            string: self.string,
-        }
-        .generate_block(block)
+}
+
+// backends with immutable params get the block wrapped in a scope that shadows them with mutable copies
+fn generate_fn_body_with_param_vars(
+    string: &mut String,
+    backend_writer: &dyn BackendWriter,
+    param_vars: &[DisplayVarName],
+    generate_block: impl FnOnce(&mut String)
+) {
+    if !backend_writer.params_are_immutable() || param_vars.is_empty() {
+        generate_block(string);
+        return
     }
+    // params live in the outermost scope of the fn, so the copies need a scope of their own
+    write!(string, "{{\n{{\n").unwrap();
+    for param_var in param_vars {
+        write!(string, "    ").unwrap();
+        backend_writer.write_local_var_prefix(string);
+        write!(string, "{0} = {0};\n", param_var).unwrap();
+    }
+    generate_block(string);
+    write!(string, "\n}}\n}}").unwrap();
 }
 
 pub struct FnDefWithClosureArgsGenerator<'a> {
@@ -1080,11 +1199,8 @@ impl<'a> FnDefWithClosureArgsGenerator<'a> {
     
     pub fn generate_fn_def_with_closure_args(&mut self) {
         
-        self.backend_writer.write_var_decl(
+        self.backend_writer.write_fn_def_head(
             &mut self.string,
-            "",
-            false,
-            false,
             &DisplayFnNameWithClosureArgs(
                 self.closure_site_info.site_index,
                 self.call_def.fn_ptr,
@@ -1094,17 +1210,22 @@ impl<'a> FnDefWithClosureArgsGenerator<'a> {
         );
         write!(self.string, "(").unwrap();
         let mut sep = "";
+        let mut param_vars = Vec::new();
         for param in &self.fn_def.params {
             if !param.shadow.get().is_none() {
+                let var_name = DisplayVarName(param.ident, param.shadow.get().unwrap());
                 if self.backend_writer.write_var_decl(
                     &mut self.string,
                     sep,
                     param.is_inout,
                     false,
-                    &DisplayVarName(param.ident, param.shadow.get().unwrap()),
+                    &var_name,
                     param.ty_expr.ty.borrow().as_ref().unwrap(),
                 ) {
                     sep = ", ";
+                    if !param.is_inout {
+                        param_vars.push(var_name);
+                    }
                 }
             }
         }
@@ -1128,29 +1249,28 @@ impl<'a> FnDefWithClosureArgsGenerator<'a> {
         merged_hidden_args.extend(self.call_def.hidden_args.borrow().as_ref().unwrap().iter().cloned());
         self.backend_writer.write_fn_def_hidden_params(self.string, &merged_hidden_args, sep);
         
-        write!(self.string, ") ").unwrap();
+        write!(self.string, ")").unwrap();
+        self.backend_writer.write_fn_def_tail(self.string, self.fn_def.return_ty.borrow().as_ref().unwrap());
+        write!(self.string, " ").unwrap();
         // alright so here the block is generated.. however
         // we need to know the names and the closed-over-args passthrough
-        self.generate_block(&self.fn_def.block);
-        
+        generate_fn_body_with_param_vars(self.string, self.backend_writer, &param_vars, | string | {
+            BlockGenerator {
+                shader_registry: self.shader_registry,
+                closure_site_info: Some(self.closure_site_info.clone()),
+                //env: self.env,
+                fn_def: self.fn_def,
+                backend_writer: self.backend_writer,
+                const_table_offset: self.const_table_offset,
+                indent_level: 0,
+                string,
+            }
+            .generate_block(&self.fn_def.block)
+        });
         
         writeln!(self.string).unwrap();
         //self.visited.insert(self.decl.ident_path);
     }
-    
-    fn generate_block(&mut self, block: &Block) {
-        BlockGenerator {
-            shader_registry: self.shader_registry,
-            closure_site_info: Some(self.closure_site_info.clone()),
-            //env: self.env,
-            fn_def: self.fn_def,
-            backend_writer: self.backend_writer,
-            const_table_offset: self.const_table_offset,
-            indent_level: 0,
-            string: self.string,
-        }
-        .generate_block(block)
-    }
 }
 
 pub struct ClosureDefGenerator<'a> {
@@ -1170,14 +1290,12 @@ impl<'a> ClosureDefGenerator<'a> {
         let fn_param = &self.fn_def.params[self.closure_site_arg.param_index];
         
         let mut sep = "";
+        let mut param_vars = Vec::new();
         
-        if let TyExprKind::ClosureDecl {params, return_ty, ..} = &fn_param.ty_expr.kind {
+        let return_ty = if let TyExprKind::ClosureDecl {params, return_ty, ..} = &fn_param.ty_expr.kind {
             
-            self.backend_writer.write_var_decl(
+            self.backend_writer.write_fn_def_head(
                 &mut self.string,
-                "",
-                false,
-                false,
                 &DisplayClosureName(self.call_def.fn_ptr, self.closure_site_arg.closure_def_index), // here we must expand IdentPath to something
                 return_ty.borrow().as_ref().unwrap(),
             );
@@ -1188,32 +1306,39 @@ impl<'a> ClosureDefGenerator<'a> {
                 // lets fetch the name of this thing
                 let closure_param = &self.closure_def.params[param_index];
                 let shadow = closure_param.shadow.get().unwrap();
+                let var_name = DisplayVarName(closure_param.ident, shadow);
                 if self.backend_writer.write_var_decl(
                     &mut self.string,
                     sep,
                     param.is_inout,
                     false,
-                    &DisplayVarName(closure_param.ident, shadow),
+                    &var_name,
                     param.ty_expr.ty.borrow().as_ref().unwrap(),
                 ) {
                     sep = ", ";
+                    if !param.is_inout {
+                        param_vars.push(var_name);
+                    }
                 }
             }
+            return_ty.borrow().clone().unwrap()
         }
         else {
             panic!()
-        }
+        };
         
         for sym in self.closure_def.closed_over_syms.borrow().as_ref().unwrap() {
+            let var_name = DisplayVarName(sym.ident, sym.shadow);
             if self.backend_writer.write_var_decl(
                 &mut self.string,
                 sep,
                 false,
                 false,
-                &DisplayVarName(sym.ident, sym.shadow),
+                &var_name,
                 &sym.ty,
             ) {
                 sep = ", ";
+                param_vars.push(var_name);
             }
         } 
 
@@ -1222,38 +1347,38 @@ impl<'a> ClosureDefGenerator<'a> {
         merged_hidden_args.extend(self.call_def.hidden_args.borrow().as_ref().unwrap().iter().cloned());
         self.backend_writer.write_fn_def_hidden_params(self.string, &merged_hidden_args, sep);
         
-        writeln!(self.string, ") {{").unwrap();
+        write!(self.string, ")").unwrap();
+        self.backend_writer.write_fn_def_tail(self.string, &return_ty);
         
         match &self.closure_def.kind {
             ClosureDefKind::Expr(expr) => {
+                writeln!(self.string, " {{").unwrap();
                 write!(self.string, "    return ").unwrap();
                 self.generate_expr(expr);
                 writeln!(self.string, ";").unwrap();
                 writeln!(self.string, "}}").unwrap();
             }
             ClosureDefKind::Block(block) => {
-                self.generate_block(block);
+                write!(self.string, " ").unwrap();
+                generate_fn_body_with_param_vars(self.string, self.backend_writer, &param_vars, | string | {
+                    BlockGenerator {
+                        shader_registry: self.shader_registry,
+                        closure_site_info: None,
+                        //env: self.env,
+                        fn_def: self.fn_def,
+                        backend_writer: self.backend_writer,
+                        const_table_offset: self.const_table_offset,
+                        indent_level: 0,
+                        string,
+                    }
+                    .generate_block(block)
+                });
                 writeln!(self.string).unwrap();
             }
         }
         //self.visited.insert(self.decl.ident_path);
     }
     
-    fn generate_block(&mut self, block: &Block) {
-        BlockGenerator {
-            shader_registry: self.shader_registry,
-            closure_site_info: None,
-            //env: self.env,
-            fn_def: self.fn_def,
-            backend_writer: self.backend_writer,
-            const_table_offset: self.const_table_offset,
-            indent_level: 0,
-            string: self.string,
-        }
-        .generate_block(block)
-    }
-    
-    
     fn generate_expr(&mut self, expr: &Expr) {
         ExprGenerator {
             shader_registry: self.shader_registry,
@@ -0,0 +1,992 @@
+use {
+    std::{
+        fmt::Write,
+        fmt,
+        collections::{BTreeMap, BTreeSet}
+    },
+    crate::{
+        makepad_live_id::*,
+        shader_ast::*,
+        generate::*,
+        shader_registry::ShaderRegistry,
+    }
+};
+
+// Binding layout of the generated shader:
+// @group(0) @binding(0)   const_table, array<vec4<f32>, N>
+// @group(0) @binding(1)   live_table, array<vec4<f32>, N>
+// @group(0) @binding(2+i) the uniform blocks in fields_as_uniform_blocks order as {block}_table
+// @group(1) @binding(i)   the textures in field order, followed by a single sampler
+// All tables are the same float slots as the glsl backend uses, padded to a multiple of 4.
+// Geometries are packed into vec4 locations starting at 0, instances follow right after.
+
+pub struct WgslGeneratedShader{
+    pub wgsl: String,
+    pub fields_as_uniform_blocks:BTreeMap<Ident, Vec<(usize, Ident) >>
+}
+
+pub fn generate_shader(draw_shader_def: &DrawShaderDef, const_table:&DrawShaderConstTable, shader_registry: &ShaderRegistry) -> WgslGeneratedShader {
+    let mut string = String::new();
+    let fields_as_uniform_blocks = draw_shader_def.fields_as_uniform_blocks();
+
+    let mut cons_fns = BTreeSet::new();
+    for fn_iter in draw_shader_def.all_fns.borrow().iter() {
+        let fn_def = shader_registry.all_fns.get(fn_iter).unwrap();
+        for (ty_lit, param_tys) in fn_def.constructor_fn_deps.borrow().as_ref().unwrap() {
+            if needs_cons_fn(*ty_lit, param_tys) {
+                cons_fns.insert(cons_fn_name(*ty_lit, param_tys));
+            }
+        }
+    }
+
+    DrawShaderGenerator {
+        draw_shader_def,
+        shader_registry,
+        const_table,
+        string: &mut string,
+        fields_as_uniform_blocks: &fields_as_uniform_blocks,
+        backend_writer: &WgslBackendWriter {draw_shader_def, cons_fns}
+    }
+    .generate_shader();
+    WgslGeneratedShader{
+        wgsl:string,
+        fields_as_uniform_blocks
+    }
+}
+
+struct DrawShaderGenerator<'a> {
+    draw_shader_def: &'a DrawShaderDef,
+    shader_registry: &'a ShaderRegistry,
+    string: &'a mut String,
+    fields_as_uniform_blocks: &'a BTreeMap<Ident, Vec<(usize, Ident) >>,
+    backend_writer: &'a dyn BackendWriter,
+    const_table: &'a DrawShaderConstTable
+}
+
+impl<'a> DrawShaderGenerator<'a> {
+    fn generate_shader(&mut self) {
+        let mut all_constructor_fns = BTreeSet::new();
+        let mut all_builtins = BTreeSet::new();
+
+        for fn_iter in self.draw_shader_def.all_fns.borrow().iter() {
+            let fn_def = self.shader_registry.all_fns.get(fn_iter).unwrap();
+            all_constructor_fns.extend(fn_def.constructor_fn_deps.borrow().as_ref().unwrap().iter().cloned());
+            all_builtins.extend(fn_def.builtin_deps.borrow().as_ref().unwrap().iter().cloned());
+        }
+
+        // derivatives are used from non uniform control flow all over the place
+        if all_builtins.contains(&Ident(live_id!(dFdx))) || all_builtins.contains(&Ident(live_id!(dFdy))) {
+            writeln!(self.string, "diagnostic(off, derivative_uniformity);").unwrap();
+        }
+
+        self.generate_struct_defs();
+        self.generate_table_decls();
+        self.generate_texture_decls();
+        self.generate_private_decls();
+        self.generate_geometry_struct();
+        self.generate_instance_struct();
+        self.generate_varying_struct();
+
+        if all_builtins.contains(&Ident(live_id!(sample2d))) {
+            writeln!(self.string, "fn sample2d(tex: texture_2d<f32>, pos: vec2<f32>) -> vec4<f32> {{ return textureSampleLevel(tex, default_sampler, pos, 0.0); }}").unwrap();
+        }
+        if all_builtins.contains(&Ident(live_id!(sample2d_rt))) {
+            writeln!(self.string, "fn sample2d_rt(tex: texture_2d<f32>, pos: vec2<f32>) -> vec4<f32> {{ return textureSampleLevel(tex, default_sampler, pos, 0.0); }}").unwrap();
+        }
+        if all_builtins.contains(&Ident(live_id!(sample2dOES))) {
+            writeln!(self.string, "fn sample2dOES(tex: texture_external, pos: vec2<f32>) -> vec4<f32> {{ return textureSampleBaseClampToEdge(tex, default_sampler, pos); }}").unwrap();
+        }
+        self.generate_builtin_polyfills(&all_builtins);
+
+        for (ty_lit, ref param_tys) in all_constructor_fns {
+            self.generate_cons_fn(ty_lit, param_tys);
+        }
+
+        let all_fns = self.draw_shader_def.all_fns.borrow();
+        for fn_iter in all_fns.iter().rev() {
+            let const_table_offset = self.const_table.offsets.get(fn_iter).cloned();
+            let fn_def = self.shader_registry.all_fns.get(fn_iter).unwrap();
+            if fn_def.has_closure_args() {
+                for call_iter in all_fns.iter().rev() {
+                    // any function that depends on us, will have the closures we need
+                    let call_def = self.shader_registry.all_fns.get(call_iter).unwrap();
+                    if call_def.callees.borrow().as_ref().unwrap().contains(&fn_iter) {
+                        FnDefWithClosureArgsGenerator::generate_fn_def_with_all_closures(
+                            &mut self.string,
+                            self.shader_registry,
+                            fn_def,
+                            call_def,
+                            self.backend_writer,
+                            const_table_offset
+                        );
+                    }
+                }
+                continue
+            }
+            FnDefGenerator {
+                fn_def,
+                const_table_offset,
+                shader_registry: self.shader_registry,
+                backend_writer: self.backend_writer,
+                string: self.string,
+            }
+            .generate_fn_def()
+        }
+        self.generate_vertex_main();
+        self.generate_pixel_main();
+    }
+
+    fn generate_struct_defs(&mut self) {
+        // we have all the structs already from analyse
+        for struct_ptr in self.draw_shader_def.all_structs.borrow().iter().rev() {
+            let struct_def = self.shader_registry.structs.get(struct_ptr).unwrap();
+            writeln!(self.string, "struct {} {{", struct_ptr).unwrap();
+            for field in &struct_def.fields {
+                write!(self.string, "    ").unwrap();
+                self.write_var_decl(&DisplayStructField(field.ident), field.ty_expr.ty.borrow().as_ref().unwrap(),);
+                writeln!(self.string, ",").unwrap();
+            }
+            writeln!(self.string, "}}").unwrap();
+        }
+    }
+
+    fn generate_table_decls(&mut self) {
+        if self.const_table.table.len()>0 {
+            writeln!(self.string, "@group(0) @binding(0) var<uniform> const_table: array<vec4<f32>, {}>;", vec4_table_len(self.const_table.table.len())).unwrap();
+        }
+
+        let live_slots = self.calc_live_slots();
+        if live_slots > 0 {
+            writeln!(self.string, "@group(0) @binding(1) var<uniform> live_table: array<vec4<f32>, {}>;", vec4_table_len(live_slots)).unwrap();
+        }
+
+        for (binding, (ident, vec)) in self.fields_as_uniform_blocks.iter().enumerate() {
+            let mut slots = 0;
+            for (index, _item) in vec {
+                let field = &self.draw_shader_def.fields[*index];
+                slots += field.ty_expr.ty.borrow().as_ref().unwrap().slots();
+            }
+            writeln!(self.string, "@group(0) @binding({}) var<uniform> {}_table: array<vec4<f32>, {}>;", binding + 2, ident, vec4_table_len(slots)).unwrap();
+        }
+    }
+
+    fn generate_texture_decls(&mut self) {
+        let mut binding = 0;
+        for field in &self.draw_shader_def.fields {
+            if let DrawShaderFieldKind::Texture {..} = field.kind {
+                write!(self.string, "@group(1) @binding({}) var ", binding).unwrap();
+                self.write_var_decl(&DisplayDsIdent(field.ident), field.ty_expr.ty.borrow().as_ref().unwrap());
+                writeln!(self.string, ";").unwrap();
+                binding += 1;
+            }
+        }
+        if binding > 0 {
+            writeln!(self.string, "@group(1) @binding({}) var default_sampler: sampler;", binding).unwrap();
+        }
+    }
+
+    fn generate_private_decls(&mut self) {
+        for (live_ref, ty) in self.draw_shader_def.all_live_refs.borrow().iter() {
+            write!(self.string, "var<private> ").unwrap();
+            self.write_var_decl(live_ref, ty);
+            writeln!(self.string, ";").unwrap();
+        }
+        for field in &self.draw_shader_def.fields {
+            match field.kind {
+                DrawShaderFieldKind::Texture {..} => (),
+                DrawShaderFieldKind::Instance {..} if field.ident == Ident(LiveId(0)) => (),
+                _ => {
+                    write!(self.string, "var<private> ").unwrap();
+                    self.write_var_decl(&DisplayDsIdent(field.ident), field.ty_expr.ty.borrow().as_ref().unwrap());
+                    writeln!(self.string, ";").unwrap();
+                }
+            }
+        }
+    }
+
+    fn generate_geometry_struct(&mut self) {
+        let slots = self.compute_packed_geometries_slots();
+        if slots > 0 {
+            writeln!(self.string, "struct Geometries {{").unwrap();
+            self.generate_packed_var_decls("packed_geometry", slots, 0);
+            writeln!(self.string, "}}").unwrap();
+        }
+    }
+
+    fn generate_instance_struct(&mut self) {
+        let slots = self.compute_packed_instances_slots();
+        if slots > 0 {
+            let first_location = packed_var_count(self.compute_packed_geometries_slots());
+            writeln!(self.string, "struct Instances {{").unwrap();
+            self.generate_packed_var_decls("packed_instance", slots, first_location);
+            writeln!(self.string, "}}").unwrap();
+        }
+    }
+
+    fn generate_varying_struct(&mut self) {
+        writeln!(self.string, "struct Varyings {{").unwrap();
+        writeln!(self.string, "    @builtin(position) position: vec4<f32>,").unwrap();
+        self.generate_packed_var_decls("packed_varying", self.compute_packed_varyings_slots(), 0);
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_packed_var_decls(&mut self, packed_var_name: &str, packed_vars_size: usize, first_location: usize) {
+        for packed_var_index in 0..packed_var_count(packed_vars_size) {
+            writeln!(
+                self.string,
+                "    @location({}) {}_{}: {},",
+                first_location + packed_var_index,
+                packed_var_name,
+                packed_var_index,
+                match (packed_vars_size - packed_var_index * 4).min(4) {
+                    1 => "f32",
+                    2 => "vec2<f32>",
+                    3 => "vec3<f32>",
+                    _ => "vec4<f32>",
+                }
+            ).unwrap();
+        }
+    }
+
+    fn generate_builtin_polyfills(&mut self, all_builtins: &BTreeSet<Ident>) {
+        let mut polyfills = BTreeMap::new();
+        for ident in all_builtins {
+            if let Some(builtin) = self.shader_registry.builtins.get(ident) {
+                for (arg_tys, return_ty) in &builtin.return_tys {
+                    if builtin_needs_polyfill(*ident, arg_tys) {
+                        let name = DisplayBuiltinPolyfill(*ident, arg_tys).to_string();
+                        polyfills.insert(name, (*ident, arg_tys.clone(), return_ty.clone()));
+                    }
+                }
+            }
+        }
+        for (name, (ident, arg_tys, return_ty)) in polyfills {
+            write!(self.string, "fn {}(", name).unwrap();
+            let mut sep = "";
+            for (index, arg_ty) in arg_tys.iter().enumerate() {
+                self.backend_writer.write_var_decl(self.string, sep, false, false, &DisplaConstructorArg(index), arg_ty);
+                sep = ", ";
+            }
+            write!(self.string, ") -> ").unwrap();
+            self.write_ty_lit(return_ty.maybe_ty_lit().unwrap());
+            writeln!(self.string, " {{").unwrap();
+            match ident {
+                Ident(live_id!(mod)) => {
+                    writeln!(self.string, "    return x0 - x1 * floor(x0 / x1);").unwrap();
+                }
+                Ident(live_id!(reflect)) => {
+                    writeln!(self.string, "    return x0 - 2.0 * x1 * x0 * x1;").unwrap();
+                }
+                Ident(live_id!(refract)) => {
+                    writeln!(self.string, "    let k = 1.0 - x2 * x2 * (1.0 - x1 * x0 * x1 * x0);").unwrap();
+                    writeln!(self.string, "    if k < 0.0 {{ return 0.0; }}").unwrap();
+                    writeln!(self.string, "    return x2 * x0 - (x2 * x1 * x0 + sqrt(k)) * x1;").unwrap();
+                }
+                Ident(live_id!(faceforward)) => {
+                    writeln!(self.string, "    return select(-x0, x0, x2 * x1 < 0.0);").unwrap();
+                }
+                Ident(live_id!(dot)) => {
+                    writeln!(self.string, "    return x0 * x1;").unwrap();
+                }
+                Ident(live_id!(normalize)) => {
+                    writeln!(self.string, "    return sign(x0);").unwrap();
+                }
+                Ident(live_id!(equal)) => writeln!(self.string, "    return x0 == x1;").unwrap(),
+                Ident(live_id!(notEqual)) => writeln!(self.string, "    return x0 != x1;").unwrap(),
+                Ident(live_id!(lessThan)) => writeln!(self.string, "    return x0 < x1;").unwrap(),
+                Ident(live_id!(lessThanEqual)) => writeln!(self.string, "    return x0 <= x1;").unwrap(),
+                Ident(live_id!(greaterThan)) => writeln!(self.string, "    return x0 > x1;").unwrap(),
+                Ident(live_id!(greaterThanEqual)) => writeln!(self.string, "    return x0 >= x1;").unwrap(),
+                Ident(live_id!(not)) => writeln!(self.string, "    return !x0;").unwrap(),
+                Ident(live_id!(matrixCompMult)) => {
+                    write!(self.string, "    return ").unwrap();
+                    self.write_ty_lit(return_ty.maybe_ty_lit().unwrap());
+                    write!(self.string, "(").unwrap();
+                    let mut sep = "";
+                    for col in 0..mat_size(&return_ty) {
+                        write!(self.string, "{}x0[{1}] * x1[{1}]", sep, col).unwrap();
+                        sep = ", ";
+                    }
+                    writeln!(self.string, ");").unwrap();
+                }
+                Ident(live_id!(inverse)) => {
+                    self.generate_inverse_mat4_body();
+                }
+                _ => {
+                    // mixed scalar and vector args, splat the scalars to the return type
+                    write!(self.string, "    return {}(", ident).unwrap();
+                    let mut sep = "";
+                    for (index, arg_ty) in arg_tys.iter().enumerate() {
+                        if *arg_ty == return_ty {
+                            write!(self.string, "{}x{}", sep, index).unwrap();
+                        }
+                        else {
+                            write!(self.string, "{}", sep).unwrap();
+                            self.write_ty_lit(return_ty.maybe_ty_lit().unwrap());
+                            write!(self.string, "(x{})", index).unwrap();
+                        }
+                        sep = ", ";
+                    }
+                    writeln!(self.string, ");").unwrap();
+                }
+            }
+            writeln!(self.string, "}}").unwrap();
+        }
+    }
+
+    fn generate_inverse_mat4_body(&mut self) {
+        for col in 0..4 {
+            for row in 0..4 {
+                writeln!(self.string, "    let a{0}{1} = x0[{0}][{1}];", col, row).unwrap();
+            }
+        }
+        writeln!(self.string, "    let b00 = a00 * a11 - a01 * a10;").unwrap();
+        writeln!(self.string, "    let b01 = a00 * a12 - a02 * a10;").unwrap();
+        writeln!(self.string, "    let b02 = a00 * a13 - a03 * a10;").unwrap();
+        writeln!(self.string, "    let b03 = a01 * a12 - a02 * a11;").unwrap();
+        writeln!(self.string, "    let b04 = a01 * a13 - a03 * a11;").unwrap();
+        writeln!(self.string, "    let b05 = a02 * a13 - a03 * a12;").unwrap();
+        writeln!(self.string, "    let b06 = a20 * a31 - a21 * a30;").unwrap();
+        writeln!(self.string, "    let b07 = a20 * a32 - a22 * a30;").unwrap();
+        writeln!(self.string, "    let b08 = a20 * a33 - a23 * a30;").unwrap();
+        writeln!(self.string, "    let b09 = a21 * a32 - a22 * a31;").unwrap();
+        writeln!(self.string, "    let b10 = a21 * a33 - a23 * a31;").unwrap();
+        writeln!(self.string, "    let b11 = a22 * a33 - a23 * a32;").unwrap();
+        writeln!(self.string, "    let det = b00 * b11 - b01 * b10 + b02 * b09 + b03 * b08 - b04 * b07 + b05 * b06;").unwrap();
+        writeln!(self.string, "    return mat4x4<f32>(").unwrap();
+        writeln!(self.string, "        a11 * b11 - a12 * b10 + a13 * b09, a02 * b10 - a01 * b11 - a03 * b09, a31 * b05 - a32 * b04 + a33 * b03, a22 * b04 - a21 * b05 - a23 * b03,").unwrap();
+        writeln!(self.string, "        a12 * b08 - a10 * b11 - a13 * b07, a00 * b11 - a02 * b08 + a03 * b07, a32 * b02 - a30 * b05 - a33 * b01, a20 * b05 - a22 * b02 + a23 * b01,").unwrap();
+        writeln!(self.string, "        a10 * b10 - a11 * b08 + a13 * b06, a01 * b08 - a00 * b10 - a03 * b06, a30 * b04 - a31 * b02 + a33 * b00, a21 * b02 - a20 * b04 - a23 * b00,").unwrap();
+        writeln!(self.string, "        a11 * b07 - a10 * b09 - a12 * b06, a00 * b09 - a01 * b07 + a02 * b06, a31 * b01 - a30 * b03 - a32 * b00, a20 * b03 - a21 * b01 + a22 * b00").unwrap();
+        writeln!(self.string, "    ) * (1.0 / det);").unwrap();
+    }
+
+    fn generate_cons_fn(&mut self, ty_lit: TyLit, param_tys: &[Ty]) {
+        if !needs_cons_fn(ty_lit, param_tys) {
+            return
+        }
+        write!(self.string, "fn {}(", cons_fn_name(ty_lit, param_tys)).unwrap();
+        let mut sep = "";
+        for (index, param_ty) in param_tys.iter().enumerate() {
+            self.backend_writer.write_var_decl(self.string, sep, false, false, &DisplaConstructorArg(index), param_ty);
+            sep = ", ";
+        }
+        write!(self.string, ") -> ").unwrap();
+        self.write_ty_lit(ty_lit);
+        write!(self.string, " {{\n    return ").unwrap();
+        self.write_ty_lit(ty_lit);
+        write!(self.string, "(").unwrap();
+
+        let ty = ty_lit.to_ty();
+        let (elem_ty, slots) = ty_elem_and_slots(&ty);
+        let mut sep = "";
+        match (&ty, param_tys) {
+            (Ty::Mat2 | Ty::Mat3 | Ty::Mat4, [Ty::Bool | Ty::Int | Ty::Float]) => {
+                let size = mat_size(&ty);
+                for col in 0..size {
+                    for row in 0..size {
+                        if col == row {
+                            write!(self.string, "{}f32(x0)", sep).unwrap();
+                        }
+                        else {
+                            write!(self.string, "{}0.0", sep).unwrap();
+                        }
+                        sep = ", ";
+                    }
+                }
+            }
+            (Ty::Mat2 | Ty::Mat3 | Ty::Mat4, [param_ty @ (Ty::Mat2 | Ty::Mat3 | Ty::Mat4)]) => {
+                let dst_size = mat_size(&ty);
+                let src_size = mat_size(param_ty);
+                for col in 0..dst_size {
+                    for row in 0..dst_size {
+                        if row < src_size && col < src_size {
+                            write!(self.string, "{}x0[{}][{}]", sep, col, row).unwrap();
+                        }
+                        else {
+                            write!(self.string, "{}{}", sep, if col == row {"1.0"} else {"0.0"}).unwrap();
+                        }
+                        sep = ", ";
+                    }
+                }
+            }
+            (_, [param_ty @ (Ty::Bool | Ty::Int | Ty::Float)]) => {
+                write_elem_conversion(self.string, &elem_ty, param_ty, &"x0");
+            }
+            _ => {
+                // flatten all the args and take as many components as we need
+                let mut written = 0;
+                'outer: for (index, param_ty) in param_tys.iter().enumerate() {
+                    let (param_elem_ty, param_slots) = ty_elem_and_slots(param_ty);
+                    for slot in 0..param_slots {
+                        if written == slots {
+                            break 'outer;
+                        }
+                        write!(self.string, "{}", sep).unwrap();
+                        let mut component = String::new();
+                        write_slot_access(&mut component, &format!("x{}", index), param_ty, slot);
+                        write_elem_conversion(self.string, &elem_ty, &param_elem_ty, &component);
+                        sep = ", ";
+                        written += 1;
+                    }
+                }
+            }
+        }
+        writeln!(self.string, ");\n}}").unwrap();
+    }
+
+    fn generate_vertex_main(&mut self) {
+        let packed_geometries_slots = self.compute_packed_geometries_slots();
+        let packed_instances_slots = self.compute_packed_instances_slots();
+        let packed_varyings_slots = self.compute_packed_varyings_slots();
+
+        writeln!(self.string, "@vertex").unwrap();
+        write!(self.string, "fn vertex_main(").unwrap();
+        let mut sep = "";
+        if packed_geometries_slots > 0 {
+            write!(self.string, "geometries: Geometries").unwrap();
+            sep = ", ";
+        }
+        if packed_instances_slots > 0 {
+            write!(self.string, "{}instances: Instances", sep).unwrap();
+        }
+        writeln!(self.string, ") -> Varyings {{").unwrap();
+
+        self.generate_uniform_block_unpack();
+        self.generate_live_unpack();
+
+        let mut geometry_unpacker = VarPacker::new("geometries.packed_geometry", packed_geometries_slots, self.string);
+        for field in &self.draw_shader_def.fields {
+            if let DrawShaderFieldKind::Geometry {..} = field.kind {
+                geometry_unpacker.unpack_var(field.ident, field.ty_expr.ty.borrow().as_ref().unwrap());
+            }
+        }
+
+        let mut instance_unpacker = VarPacker::new("instances.packed_instance", packed_instances_slots, self.string);
+        for field in &self.draw_shader_def.fields {
+            if let DrawShaderFieldKind::Instance {..} = field.kind {
+                if field.ident == Ident(LiveId(0)) {
+                    instance_unpacker.skip_var(field.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                else {
+                    instance_unpacker.unpack_var(field.ident, field.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+            }
+        }
+
+        writeln!(self.string, "    var varyings: Varyings;").unwrap();
+        let vertex_def = self.shader_registry.draw_shader_method_decl_from_ident(self.draw_shader_def, Ident(live_id!(vertex))).unwrap();
+        writeln!(self.string, "    varyings.position = {}();", DisplayFnName(vertex_def.fn_ptr, vertex_def.ident)).unwrap();
+
+        let mut varying_packer = VarPacker::new("varyings.packed_varying", packed_varyings_slots, self.string);
+        for field in &self.draw_shader_def.fields {
+            match &field.kind {
+                DrawShaderFieldKind::Geometry {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    varying_packer.pack_var(field.ident, field.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                DrawShaderFieldKind::Instance {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    varying_packer.pack_var(field.ident, field.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                DrawShaderFieldKind::Varying {..} => {
+                    varying_packer.pack_var(field.ident, field.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                _ => {}
+            }
+        }
+        writeln!(self.string, "    return varyings;").unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_pixel_main(&mut self) {
+        let packed_varyings_slots = self.compute_packed_varyings_slots();
+
+        writeln!(self.string, "@fragment").unwrap();
+        writeln!(self.string, "fn fragment_main(varyings: Varyings) -> @location(0) vec4<f32> {{").unwrap();
+
+        self.generate_uniform_block_unpack();
+        self.generate_live_unpack();
+
+        let mut varying_unpacker = VarPacker::new("varyings.packed_varying", packed_varyings_slots, self.string);
+        for field in &self.draw_shader_def.fields {
+            match &field.kind {
+                DrawShaderFieldKind::Geometry {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    varying_unpacker.unpack_var(field.ident, field.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                DrawShaderFieldKind::Instance {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    varying_unpacker.unpack_var(field.ident, field.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                DrawShaderFieldKind::Varying {..} => {
+                    varying_unpacker.unpack_var(field.ident, field.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                _ => {}
+            }
+        }
+
+        let pixel_def = self.shader_registry.draw_shader_method_decl_from_ident(self.draw_shader_def, Ident(live_id!(pixel))).unwrap();
+        writeln!(self.string, "    return {}();", DisplayFnName(pixel_def.fn_ptr, pixel_def.ident)).unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_uniform_block_unpack(&mut self) {
+        for (ident, vec) in self.fields_as_uniform_blocks {
+            let table = format!("{}_table", ident);
+            let mut slots = 0;
+            for (index, _item) in vec {
+                let field = &self.draw_shader_def.fields[*index];
+                let ty = field.ty_expr.ty.borrow();
+                self.write_uniform_ty_unpack(&DisplayDsIdent(field.ident), ty.as_ref().unwrap(), &table, slots);
+                slots += ty.as_ref().unwrap().slots();
+            }
+        }
+    }
+
+    fn generate_live_unpack(&mut self) {
+        let mut slots = 0;
+        for (live_ref, ty) in self.draw_shader_def.all_live_refs.borrow().iter() {
+            self.write_uniform_ty_unpack(live_ref, ty, "live_table", slots);
+            slots += ty.slots();
+        }
+    }
+
+    fn write_uniform_ty_unpack(&mut self, ident: &dyn fmt::Display, ty: &Ty, table: &str, offset: usize) {
+        let (elem_ty, slots) = ty_elem_and_slots(ty);
+        for slot in 0..slots {
+            write!(self.string, "    ").unwrap();
+            write_slot_access(self.string, ident, ty, slot);
+            write!(self.string, " = ").unwrap();
+            let mut table_slot = String::new();
+            write!(table_slot, "{}", table).unwrap();
+            write_vec4_table_index(&mut table_slot, offset + slot);
+            write_elem_conversion(self.string, &elem_ty, &Ty::Float, &table_slot);
+            writeln!(self.string, ";").unwrap();
+        }
+    }
+
+    fn compute_packed_geometries_slots(&self) -> usize {
+        let mut slots = 0;
+        for field in &self.draw_shader_def.fields {
+            if let DrawShaderFieldKind::Geometry {..} = field.kind {
+                slots += field.ty_expr.ty.borrow().as_ref().unwrap().slots();
+            }
+        }
+        slots
+    }
+
+    fn compute_packed_instances_slots(&self) -> usize {
+        let mut slots = 0;
+        for field in &self.draw_shader_def.fields {
+            if let DrawShaderFieldKind::Instance {..} = field.kind {
+                slots += field.ty_expr.ty.borrow().as_ref().unwrap().slots();
+            }
+        }
+        slots
+    }
+
+    fn compute_packed_varyings_slots(&self) -> usize {
+        let mut slots = 0;
+        for field in &self.draw_shader_def.fields {
+            match &field.kind {
+                DrawShaderFieldKind::Geometry {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    slots += field.ty_expr.ty.borrow().as_ref().unwrap().slots();
+                }
+                DrawShaderFieldKind::Instance {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    slots += field.ty_expr.ty.borrow().as_ref().unwrap().slots();
+                }
+                DrawShaderFieldKind::Varying {..} => {
+                    slots += field.ty_expr.ty.borrow().as_ref().unwrap().slots();
+                }
+                _ => {}
+            }
+        }
+        slots
+    }
+
+    pub fn calc_live_slots(&self) -> usize {
+        let mut slots = 0;
+        for (_, ty) in self.draw_shader_def.all_live_refs.borrow().iter() {
+            slots += ty.slots();
+        }
+        slots
+    }
+
+    fn write_var_decl(&mut self, ident: &dyn fmt::Display, ty: &Ty) {
+        self.backend_writer.write_var_decl(&mut self.string, "", false, false, ident, ty);
+    }
+
+    fn write_ty_lit(&mut self, ty_lit: TyLit) {
+        self.backend_writer.write_ty_lit(&mut self.string, ty_lit);
+    }
+}
+
+// wgsl can't assign to swizzles, so we pack and unpack one slot at a time
+struct VarPacker<'a> {
+    packed_var_name: &'a str,
+    packed_vars_size: usize,
+    slot: usize,
+    string: &'a mut String,
+}
+
+impl<'a> VarPacker<'a> {
+    fn new(packed_var_name: &'a str, packed_vars_size: usize, string: &'a mut String) -> VarPacker<'a> {
+        VarPacker {
+            packed_var_name,
+            packed_vars_size,
+            slot: 0,
+            string
+        }
+    }
+
+    fn pack_var(&mut self, ident: Ident, ty: &Ty) {
+        let (elem_ty, slots) = ty_elem_and_slots(ty);
+        for slot in 0..slots {
+            write!(self.string, "    ").unwrap();
+            write_packed_slot(self.string, self.packed_var_name, self.packed_vars_size, self.slot);
+            write!(self.string, " = ").unwrap();
+            let mut component = String::new();
+            write_slot_access(&mut component, &DisplayDsIdent(ident), ty, slot);
+            write_elem_conversion(self.string, &Ty::Float, &elem_ty, &component);
+            writeln!(self.string, ";").unwrap();
+            self.slot += 1;
+        }
+    }
+
+    fn unpack_var(&mut self, ident: Ident, ty: &Ty) {
+        let (elem_ty, slots) = ty_elem_and_slots(ty);
+        for slot in 0..slots {
+            write!(self.string, "    ").unwrap();
+            write_slot_access(self.string, &DisplayDsIdent(ident), ty, slot);
+            write!(self.string, " = ").unwrap();
+            let mut packed_slot = String::new();
+            write_packed_slot(&mut packed_slot, self.packed_var_name, self.packed_vars_size, self.slot);
+            write_elem_conversion(self.string, &elem_ty, &Ty::Float, &packed_slot);
+            writeln!(self.string, ";").unwrap();
+            self.slot += 1;
+        }
+    }
+
+    fn skip_var(&mut self, ty: &Ty) {
+        self.slot += ty.slots();
+    }
+}
+
+fn write_packed_slot(string: &mut String, packed_var_name: &str, packed_vars_size: usize, slot: usize) {
+    let packed_var_index = slot >> 2;
+    write!(string, "{}_{}", packed_var_name, packed_var_index).unwrap();
+    if (packed_vars_size - (packed_var_index << 2)).min(4) > 1 {
+        write!(string, ".{}", ["x", "y", "z", "w"][slot & 3]).unwrap();
+    }
+}
+
+fn packed_var_count(packed_vars_size: usize) -> usize {
+    (packed_vars_size + 3) >> 2
+}
+
+fn vec4_table_len(slots: usize) -> usize {
+    ((slots + 3) >> 2).max(1)
+}
+
+fn write_vec4_table_index(string: &mut String, index: usize) {
+    write!(string, "[{}].{}", index >> 2, ["x", "y", "z", "w"][index & 3]).unwrap();
+}
+
+fn mat_size(ty: &Ty) -> usize {
+    match ty {
+        Ty::Mat2 => 2,
+        Ty::Mat3 => 3,
+        Ty::Mat4 => 4,
+        _ => panic!("{} is not a matrix", ty),
+    }
+}
+
+// returns the scalar type of each slot, enums are floats
+fn ty_elem_and_slots(ty: &Ty) -> (Ty, usize) {
+    match ty {
+        Ty::Bool | Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 => (Ty::Bool, ty.slots()),
+        Ty::Int | Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4 => (Ty::Int, ty.slots()),
+        Ty::Float | Ty::Vec2 | Ty::Vec3 | Ty::Vec4 | Ty::Mat2 | Ty::Mat3 | Ty::Mat4 | Ty::Enum(_) => (Ty::Float, ty.slots()),
+        _ => panic!("{} can't be split into slots", ty),
+    }
+}
+
+fn write_slot_access(string: &mut String, ident: &dyn fmt::Display, ty: &Ty, slot: usize) {
+    match ty {
+        Ty::Bool | Ty::Int | Ty::Float | Ty::Enum(_) => write!(string, "{}", ident).unwrap(),
+        Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+            let size = mat_size(ty);
+            write!(string, "{}[{}][{}]", ident, slot / size, slot % size).unwrap()
+        }
+        _ => write!(string, "{}.{}", ident, ["x", "y", "z", "w"][slot]).unwrap(),
+    }
+}
+
+fn write_elem_conversion(string: &mut String, dst_ty: &Ty, src_ty: &Ty, value: &dyn fmt::Display) {
+    match (dst_ty, src_ty) {
+        (Ty::Bool, Ty::Bool) | (Ty::Int, Ty::Int) | (Ty::Float, Ty::Float) => write!(string, "{}", value),
+        (Ty::Bool, _) => write!(string, "({} > 0.5)", value),
+        (Ty::Int, _) => write!(string, "i32({})", value),
+        (_, Ty::Bool) => write!(string, "select(0.0, 1.0, {})", value),
+        _ => write!(string, "f32({})", value),
+    }.unwrap()
+}
+
+fn cons_fn_name(ty_lit: TyLit, param_tys: &[Ty]) -> String {
+    let mut cons_name = format!("consfn_{}", ty_lit);
+    for param_ty in param_tys {
+        write!(cons_name, "_{}", param_ty).unwrap();
+    }
+    cons_name
+}
+
+// wgsl constructors only splat same-typed scalars, convert same-sized vectors and don't truncate
+fn needs_cons_fn(ty_lit: TyLit, param_tys: &[Ty]) -> bool {
+    let ty = ty_lit.to_ty();
+    let is_scalar = | ty: &Ty | matches!(ty, Ty::Bool | Ty::Int | Ty::Float);
+    match &ty {
+        Ty::Bool | Ty::Int | Ty::Float => !(param_tys.len() == 1 && is_scalar(&param_tys[0])),
+        Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+            let size = mat_size(&ty);
+            if param_tys.len() == 1 {
+                return param_tys[0] != ty
+            }
+            let column_ty = match size {2 => Ty::Vec2, 3 => Ty::Vec3, _ => Ty::Vec4};
+            !(param_tys.len() == size * size && param_tys.iter().all( | param_ty | *param_ty == Ty::Float)
+                || param_tys.len() == size && param_tys.iter().all( | param_ty | *param_ty == column_ty))
+        }
+        _ => {
+            let (elem_ty, slots) = ty_elem_and_slots(&ty);
+            if param_tys.len() == 1 {
+                let (param_elem_ty, param_slots) = ty_elem_and_slots(&param_tys[0]);
+                if is_scalar(&param_tys[0]) {
+                    return param_elem_ty != elem_ty
+                }
+                return param_slots != slots || matches!(param_tys[0], Ty::Mat2 | Ty::Mat3 | Ty::Mat4)
+            }
+            let mut total_slots = 0;
+            for param_ty in param_tys {
+                if matches!(param_ty, Ty::Mat2 | Ty::Mat3 | Ty::Mat4) {
+                    return true
+                }
+                let (param_elem_ty, param_slots) = ty_elem_and_slots(param_ty);
+                if param_elem_ty != elem_ty {
+                    return true
+                }
+                total_slots += param_slots;
+            }
+            total_slots != slots
+        }
+    }
+}
+
+fn builtin_needs_polyfill(ident: Ident, arg_tys: &[Ty]) -> bool {
+    match ident {
+        Ident(live_id!(mod)) |
+        Ident(live_id!(equal)) |
+        Ident(live_id!(notEqual)) |
+        Ident(live_id!(lessThan)) |
+        Ident(live_id!(lessThanEqual)) |
+        Ident(live_id!(greaterThan)) |
+        Ident(live_id!(greaterThanEqual)) |
+        Ident(live_id!(not)) |
+        Ident(live_id!(matrixCompMult)) |
+        Ident(live_id!(inverse)) => true,
+        Ident(live_id!(clamp)) |
+        Ident(live_id!(min)) |
+        Ident(live_id!(max)) |
+        Ident(live_id!(step)) |
+        Ident(live_id!(smoothstep)) => arg_tys.iter().any( | arg_ty | *arg_ty != arg_tys[0]),
+        Ident(live_id!(dot)) |
+        Ident(live_id!(normalize)) |
+        Ident(live_id!(reflect)) |
+        Ident(live_id!(refract)) |
+        Ident(live_id!(faceforward)) => arg_tys.first() == Some(&Ty::Float),
+        _ => false
+    }
+}
+
+pub struct DisplayBuiltinPolyfill<'a>(pub Ident, pub &'a [Ty]);
+impl<'a> fmt::Display for DisplayBuiltinPolyfill<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "builtin_{}", self.0)?;
+        for ty in self.1 {
+            write!(f, "_{}", ty)?;
+        }
+        fmt::Result::Ok(())
+    }
+}
+
+struct WgslBackendWriter<'a> {
+    pub draw_shader_def: &'a DrawShaderDef,
+    pub cons_fns: BTreeSet<String>,
+}
+
+impl<'a> BackendWriter for WgslBackendWriter<'a> {
+
+    fn get_struct_cons_type(&self) -> StructConsType {
+        StructConsType::Paren
+    }
+
+    fn needs_mul_fn_for_matrix_multiplication(&self) -> bool {
+        false
+    }
+
+    fn needs_unpack_for_matrix_multiplication(&self) -> bool {
+        false
+    }
+
+    fn const_table_is_vec4(&self) -> bool {
+        true
+    }
+
+    fn enum_is_float(&self) -> bool {
+        true
+    }
+
+    fn use_cons_fn(&self, what: &str) -> bool {
+        self.cons_fns.contains(what)
+    }
+
+    fn needs_select_for_cond_expr(&self) -> bool {
+        true
+    }
+
+    fn assign_is_stmt(&self) -> bool {
+        true
+    }
+
+    fn inout_is_pointer(&self) -> bool {
+        true
+    }
+
+    fn params_are_immutable(&self) -> bool {
+        true
+    }
+
+    fn write_local_var_prefix(&self, string: &mut String) {
+        write!(string, "var ").unwrap();
+    }
+
+    fn write_fn_def_head(&self, string: &mut String, ident: &dyn fmt::Display, _return_ty: &Ty) {
+        write!(string, "fn {}", ident).unwrap();
+    }
+
+    fn write_fn_def_tail(&self, string: &mut String, return_ty: &Ty) {
+        if *return_ty != Ty::Void {
+            write!(string, " -> ").unwrap();
+            self.write_ty(string, return_ty);
+        }
+    }
+
+    fn write_var_decl(
+        &self,
+        string: &mut String,
+        sep: &'static str,
+        is_inout: bool,
+        _is_packed: bool,
+        ident: &dyn fmt::Display,
+        ty: &Ty,
+    ) -> bool {
+        match ty {
+            Ty::DrawShader(_) | Ty::ClosureDef {..} | Ty::ClosureDecl => {
+                return false
+            }
+            _ => ()
+        }
+        write!(string, "{}{}: ", sep, ident).unwrap();
+        if is_inout {
+            write!(string, "ptr<function, ").unwrap();
+            self.write_ty(string, ty);
+            write!(string, ">").unwrap();
+        }
+        else {
+            self.write_ty(string, ty);
+        }
+        true
+    }
+
+    fn write_call_expr_hidden_args(&self, _string: &mut String, _hidden_args: &BTreeSet<HiddenArgKind >, _sep: &str) {
+    }
+
+    fn write_fn_def_hidden_params(&self, _string: &mut String, _hidden_args: &BTreeSet<HiddenArgKind >, _sep: &str) {
+    }
+
+    fn generate_live_value_prefix(&self, _string: &mut String) {
+    }
+
+    fn generate_draw_shader_field_expr(&self, string: &mut String, field_ident: Ident, _ty: &Ty) {
+        debug_assert!(self.draw_shader_def.find_field(field_ident).is_some());
+        write!(string, "{}", &DisplayDsIdent(field_ident)).unwrap();
+    }
+
+    fn write_ty_lit(&self, string: &mut String, ty_lit: TyLit) {
+        write!(
+            string,
+            "{}",
+            match ty_lit {
+                TyLit::Bool => "bool",
+                TyLit::Int => "i32",
+                TyLit::Float => "f32",
+                TyLit::Bvec2 => "vec2<bool>",
+                TyLit::Bvec3 => "vec3<bool>",
+                TyLit::Bvec4 => "vec4<bool>",
+                TyLit::Ivec2 => "vec2<i32>",
+                TyLit::Ivec3 => "vec3<i32>",
+                TyLit::Ivec4 => "vec4<i32>",
+                TyLit::Vec2 => "vec2<f32>",
+                TyLit::Vec3 => "vec3<f32>",
+                TyLit::Vec4 => "vec4<f32>",
+                TyLit::Mat2 => "mat2x2<f32>",
+                TyLit::Mat3 => "mat3x3<f32>",
+                TyLit::Mat4 => "mat4x4<f32>",
+                TyLit::Texture2D => "texture_2d<f32>",
+                TyLit::TextureOES => "texture_external",
+            }
+        )
+            .unwrap();
+    }
+
+    fn write_builtin_call_ident(&self, string: &mut String, ident: Ident, arg_exprs: &[Expr]) {
+        let arg_tys: Vec<Ty> = arg_exprs.iter().map( | arg_expr | arg_expr.ty.borrow().clone().unwrap()).collect();
+        if builtin_needs_polyfill(ident, &arg_tys) {
+            write!(string, "{}", DisplayBuiltinPolyfill(ident, &arg_tys)).unwrap();
+            return
+        }
+        match ident {
+            Ident(live_id!(atan)) if arg_exprs.len() == 2 => {
+                write!(string, "atan2").unwrap();
+            }
+            Ident(live_id!(dFdx)) => {
+                write!(string, "dpdx").unwrap();
+            }
+            Ident(live_id!(dFdy)) => {
+                write!(string, "dpdy").unwrap();
+            }
+            Ident(live_id!(inversesqrt)) => {
+                write!(string, "inverseSqrt").unwrap();
+            }
+            Ident(live_id!(faceforward)) => {
+                write!(string, "faceForward").unwrap();
+            }
+            _ => {
+                write!(string, "{}", ident).unwrap();
+            }
+        }
+    }
+}
+
+impl<'a> WgslBackendWriter<'a> {
+    fn write_ty(&self, string: &mut String, ty: &Ty) {
+        match ty {
+            Ty::Void => write!(string, "void").unwrap(),
+            Ty::Array {elem_ty, len} => {
+                write!(string, "array<").unwrap();
+                self.write_ty(string, elem_ty);
+                write!(string, ", {}>", len).unwrap();
+            }
+            Ty::Struct(struct_ptr) => write!(string, "{}", struct_ptr).unwrap(),
+            Ty::Enum(_) => write!(string, "f32").unwrap(),
+            Ty::Texture2D => self.write_ty_lit(string, TyLit::Texture2D),
+            Ty::TextureOES => self.write_ty_lit(string, TyLit::TextureOES),
+            _ => self.write_ty_lit(string, ty.maybe_ty_lit().unwrap()),
+        }
+    }
+}
@@ -0,0 +1,1070 @@
+use {
+    std::{collections::HashMap, rc::Rc},
+    crate::{
+        makepad_live_compiler::*,
+        makepad_live_compiler::makepad_math::*,
+        makepad_live_id::*,
+        shader_ast::*,
+        shader_registry::ShaderRegistry,
+        swizzle::Swizzle,
+    }
+};
+
+// Runs the functions of an analysed draw shader on the CPU, so shaders can be tested without a gpu.
+// The AST is type checked, so a type mismatch here is a compiler bug and panics. Only things that
+// depend on the inputs, like a missing texture or method, are returned as an error.
+// There are no neighbouring pixels to compare with, so dFdx and dFdy always return zero.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShaderValue {
+    Void,
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Bvec(Vec<bool>),
+    Ivec(Vec<i32>),
+    Vec(Vec<f32>),
+    // column major, like Mat4
+    Mat(Vec<f32>),
+    Array(Vec<ShaderValue>),
+    Struct(Vec<(Ident, ShaderValue)>),
+    Texture(Ident),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CpuTexture {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec4>,
+}
+
+pub struct ShaderInterpreter<'a> {
+    pub live_registry: &'a LiveRegistry,
+    pub shader_registry: &'a ShaderRegistry,
+    pub draw_shader_def: &'a DrawShaderDef,
+    pub fields: HashMap<Ident, ShaderValue>,
+    pub textures: HashMap<Ident, Rc<CpuTexture>>,
+}
+
+#[derive(Clone)]
+struct Closure<'a> {
+    fn_def: &'a FnDef,
+    closure_def_index: ClosureDefIndex,
+    locals: HashMap<(Ident, ScopeSymShadow), ShaderValue>,
+    closure_args: Vec<(usize, Closure<'a>)>,
+}
+
+struct Frame<'a> {
+    // for closures this is the fn the closure is defined in
+    fn_def: &'a FnDef,
+    locals: HashMap<(Ident, ScopeSymShadow), ShaderValue>,
+    closure_args: Vec<(usize, Closure<'a>)>,
+}
+
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(ShaderValue),
+}
+
+impl<'a> ShaderInterpreter<'a> {
+    pub fn new(live_registry: &'a LiveRegistry, shader_registry: &'a ShaderRegistry, draw_shader_def: &'a DrawShaderDef) -> Self {
+        let mut fields = HashMap::new();
+        for field in &draw_shader_def.fields {
+            let ty = field.ty_expr.ty.borrow().as_ref().unwrap().clone();
+            let zero = ShaderValue::zero(&ty, shader_registry);
+            let var_def_ptr = match &field.kind {
+                DrawShaderFieldKind::Geometry {var_def_ptr, ..} |
+                DrawShaderFieldKind::Instance {var_def_ptr, ..} |
+                DrawShaderFieldKind::Uniform {var_def_ptr, ..} => *var_def_ptr,
+                DrawShaderFieldKind::Texture {..} => {
+                    fields.insert(field.ident, ShaderValue::Texture(field.ident));
+                    continue;
+                }
+                DrawShaderFieldKind::Varying {..} => None,
+            };
+            let value = if let Some(var_def_ptr) = var_def_ptr {
+                ShaderValue::from_live_ptr(live_registry, var_def_ptr.0, &zero).unwrap_or(zero)
+            }
+            else {
+                zero
+            };
+            fields.insert(field.ident, value);
+        }
+        Self {
+            live_registry,
+            shader_registry,
+            draw_shader_def,
+            fields,
+            textures: HashMap::new(),
+        }
+    }
+
+    pub fn set_field(&mut self, id: LiveId, value: impl Into<ShaderValue>) {
+        self.fields.insert(Ident(id), value.into());
+    }
+
+    pub fn field(&self, id: LiveId) -> Option<&ShaderValue> {
+        self.fields.get(&Ident(id))
+    }
+
+    pub fn set_texture(&mut self, id: LiveId, texture: impl Into<Rc<CpuTexture>>) {
+        self.textures.insert(Ident(id), texture.into());
+    }
+
+    pub fn vertex(&mut self) -> Result<Vec4, LiveError> {
+        Ok(self.call_method(live_id!(vertex), Vec::new()) ?.to_vec4().unwrap())
+    }
+
+    pub fn pixel(&mut self) -> Result<Vec4, LiveError> {
+        Ok(self.call_method(live_id!(pixel), Vec::new()) ?.to_vec4().unwrap())
+    }
+
+    // calls a draw shader method, args are without self
+    pub fn call_method(&mut self, id: LiveId, args: Vec<ShaderValue>) -> Result<ShaderValue, LiveError> {
+        let fn_def = self.shader_registry.draw_shader_method_decl_from_ident(self.draw_shader_def, Ident(id)).ok_or_else( || LiveError {
+            origin: live_error_origin!(),
+            span: TokenSpan::default().into(),
+            message: format!("draw shader has no method `{}`", id),
+        }) ?;
+        let mut all_args = vec![ShaderValue::Void];
+        all_args.extend(args);
+        if all_args.len() != fn_def.params.len() {
+            return Err(LiveError {
+                origin: live_error_origin!(),
+                span: fn_def.span.into(),
+                message: format!("method `{}` expects {} arguments, got {}", id, fn_def.params.len() - 1, all_args.len() - 1),
+            })
+        }
+        let (value, _) = self.call_fn(fn_def, all_args, Vec::new()) ?;
+        Ok(value)
+    }
+
+    fn call_fn(&mut self, fn_def: &'a FnDef, args: Vec<ShaderValue>, closure_args: Vec<(usize, Closure<'a>)>) -> Result<(ShaderValue, Frame<'a>), LiveError> {
+        let mut frame = Frame {
+            fn_def,
+            locals: HashMap::new(),
+            closure_args,
+        };
+        for (param, arg) in fn_def.params.iter().zip(args) {
+            if let Some(shadow) = param.shadow.get() {
+                frame.locals.insert((param.ident, shadow), arg);
+            }
+        }
+        let value = match self.exec_block(&mut frame, &fn_def.block) ? {
+            Flow::Return(value) => value,
+            _ => ShaderValue::Void
+        };
+        Ok((value, frame))
+    }
+
+    fn exec_block(&mut self, frame: &mut Frame<'a>, block: &'a Block) -> Result<Flow, LiveError> {
+        for stmt in &block.stmts {
+            match self.exec_stmt(frame, stmt) ? {
+                Flow::Next => (),
+                flow => return Ok(flow)
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn exec_stmt(&mut self, frame: &mut Frame<'a>, stmt: &'a Stmt) -> Result<Flow, LiveError> {
+        match *stmt {
+            Stmt::Break {..} => Ok(Flow::Break),
+            Stmt::Continue {..} => Ok(Flow::Continue),
+            Stmt::For {
+                ident,
+                ref from_expr,
+                ref to_expr,
+                ref step_expr,
+                ref block,
+                ..
+            } => self.exec_for_stmt(frame, ident, from_expr, to_expr, step_expr, block),
+            Stmt::If {
+                ref expr,
+                ref block_if_true,
+                ref block_if_false,
+                ..
+            } => {
+                if self.eval_expr(frame, expr) ?.to_bool().unwrap() {
+                    self.exec_block(frame, block_if_true)
+                }
+                else if let Some(block_if_false) = block_if_false {
+                    self.exec_block(frame, block_if_false)
+                }
+                else {
+                    Ok(Flow::Next)
+                }
+            }
+            Stmt::Match {
+                ref expr,
+                ref matches,
+                ..
+            } => {
+                // enums are floats on the gpu, so compare them the same way
+                let value = self.eval_expr(frame, expr) ?.to_f32().unwrap();
+                for match_item in matches {
+                    if (value - match_item.enum_value.get().unwrap() as f32).abs() < 0.5 {
+                        return self.exec_block(frame, &match_item.block)
+                    }
+                }
+                Ok(Flow::Next)
+            }
+            Stmt::Let {
+                ref ty,
+                ref shadow,
+                ident,
+                ref expr,
+                ..
+            } => {
+                let value = if let Some(expr) = expr {
+                    self.eval_expr(frame, expr) ?
+                }
+                else {
+                    ShaderValue::zero(ty.borrow().as_ref().unwrap(), self.shader_registry)
+                };
+                frame.locals.insert((ident, shadow.get().unwrap()), value);
+                Ok(Flow::Next)
+            }
+            Stmt::Return {ref expr, ..} => {
+                let value = if let Some(expr) = expr {
+                    self.eval_expr(frame, expr) ?
+                }
+                else {
+                    ShaderValue::Void
+                };
+                Ok(Flow::Return(value))
+            }
+            Stmt::Block {ref block, ..} => self.exec_block(frame, block),
+            Stmt::Expr {ref expr, ..} => {
+                self.eval_expr(frame, expr) ?;
+                Ok(Flow::Next)
+            }
+        }
+    }
+
+    fn exec_for_stmt(
+        &mut self,
+        frame: &mut Frame<'a>,
+        ident: Ident,
+        from_expr: &'a Expr,
+        to_expr: &'a Expr,
+        step_expr: &'a Option<Expr>,
+        block: &'a Block
+    ) -> Result<Flow, LiveError> {
+        let from = self.eval_expr(frame, from_expr) ?.to_int().unwrap();
+        let to = self.eval_expr(frame, to_expr) ?.to_int().unwrap();
+        let step = if let Some(step_expr) = step_expr {
+            self.eval_expr(frame, step_expr) ?.to_int().unwrap()
+        } else if from < to {
+            1
+        } else {
+            -1
+        };
+        // same bounds as the generated code
+        let mut index = if from <= to {from} else {from - 1};
+        while if from <= to {index < to} else {index >= to} {
+            // the generators also always name the loop variable with shadow 0
+            frame.locals.insert((ident, ScopeSymShadow(0)), ShaderValue::Int(index));
+            match self.exec_block(frame, block) ? {
+                Flow::Break => break,
+                Flow::Return(value) => return Ok(Flow::Return(value)),
+                Flow::Next | Flow::Continue => ()
+            }
+            index += step;
+        }
+        Ok(Flow::Next)
+    }
+
+    fn eval_expr(&mut self, frame: &mut Frame<'a>, expr: &'a Expr) -> Result<ShaderValue, LiveError> {
+        if let Some(Some(val)) = expr.const_val.borrow().as_ref() {
+            return Ok(ShaderValue::from_val(val))
+        }
+        match expr.kind {
+            ExprKind::Cond {
+                ref expr,
+                ref expr_if_true,
+                ref expr_if_false,
+                ..
+            } => {
+                if self.eval_expr(frame, expr) ?.to_bool().unwrap() {
+                    self.eval_expr(frame, expr_if_true)
+                }
+                else {
+                    self.eval_expr(frame, expr_if_false)
+                }
+            }
+            ExprKind::Bin {
+                op,
+                ref left_expr,
+                ref right_expr,
+                ..
+            } => self.eval_bin_expr(frame, op, left_expr, right_expr),
+            ExprKind::Un {op, ref expr, ..} => {
+                let value = self.eval_expr(frame, expr) ?;
+                Ok(match op {
+                    UnOp::Not => ShaderValue::Bool(!value.to_bool().unwrap()),
+                    UnOp::Neg => map_floats(&[&value], | x | -x[0]),
+                })
+            }
+            ExprKind::Field {
+                ref expr,
+                field_ident,
+                ..
+            } => {
+                if let Some(Ty::DrawShader(_)) = expr.ty.borrow().as_ref() {
+                    return Ok(self.fields.get(&field_ident).cloned().unwrap_or_else( || panic!("draw shader field {} not found", field_ident)))
+                }
+                let value = self.eval_expr(frame, expr) ?;
+                Ok(match value {
+                    ShaderValue::Struct(fields) => fields.into_iter().find( | (ident, _) | *ident == field_ident).unwrap().1,
+                    value => value.swizzle(&Swizzle::parse(field_ident).unwrap())
+                })
+            }
+            ExprKind::Index {
+                ref expr,
+                ref index_expr,
+                ..
+            } => {
+                let value = self.eval_expr(frame, expr) ?;
+                let index = self.eval_expr(frame, index_expr) ?.to_int().unwrap();
+                Ok(value.index(index as usize))
+            }
+            ExprKind::MethodCall {
+                ident,
+                ref arg_exprs,
+                ..
+            } => {
+                let fn_def = match arg_exprs[0].ty.borrow().as_ref().unwrap() {
+                    Ty::Struct(struct_ptr) => self.shader_registry.struct_method_decl_from_ident(
+                        self.shader_registry.structs.get(struct_ptr).unwrap(),
+                        ident
+                    ).unwrap(),
+                    Ty::DrawShader(shader_ptr) => self.shader_registry.draw_shader_method_decl_from_ident(
+                        self.shader_registry.draw_shader_defs.get(shader_ptr).unwrap(),
+                        ident
+                    ).unwrap(),
+                    _ => panic!()
+                };
+                self.eval_call_expr(frame, fn_def, arg_exprs)
+            }
+            ExprKind::PlainCall {
+                fn_ptr,
+                ref arg_exprs,
+                ref param_index,
+                ..
+            } => {
+                if let Some(param_index) = param_index.get() {
+                    self.eval_closure_call_expr(frame, param_index, arg_exprs)
+                }
+                else {
+                    let fn_def = self.shader_registry.all_fns.get(&fn_ptr.unwrap()).unwrap();
+                    self.eval_call_expr(frame, fn_def, arg_exprs)
+                }
+            }
+            ExprKind::BuiltinCall {
+                span,
+                ident,
+                ref arg_exprs,
+            } => {
+                let mut args = Vec::new();
+                for arg_expr in arg_exprs {
+                    args.push(self.eval_expr(frame, arg_expr) ?);
+                }
+                self.call_builtin(span, ident, &args)
+            }
+            // closures are picked up by the call they are passed to
+            ExprKind::ClosureDef(_) => Ok(ShaderValue::Void),
+            ExprKind::ConsCall {
+                ty_lit,
+                ref arg_exprs,
+                ..
+            } => {
+                let mut args = Vec::new();
+                for arg_expr in arg_exprs {
+                    args.push(self.eval_expr(frame, arg_expr) ?);
+                }
+                Ok(ShaderValue::construct(ty_lit, &args))
+            }
+            ExprKind::StructCons {
+                struct_ptr,
+                ref args,
+                ..
+            } => {
+                let struct_def = self.shader_registry.structs.get(&struct_ptr).unwrap();
+                let mut fields = Vec::new();
+                for field in &struct_def.fields {
+                    let (_, arg_expr) = args.iter().find( | (ident, _) | *ident == field.ident).unwrap();
+                    fields.push((field.ident, self.eval_expr(frame, arg_expr) ?));
+                }
+                Ok(ShaderValue::Struct(fields))
+            }
+            ExprKind::Var {ref kind, ..} => Ok(match kind.get().unwrap() {
+                VarKind::Local {ident, shadow} | VarKind::MutLocal {ident, shadow} => {
+                    frame.locals.get(&(ident, shadow)).cloned().unwrap_or(ShaderValue::Void)
+                }
+                VarKind::LiveValue(value_ptr) => {
+                    let zero = ShaderValue::zero(expr.ty.borrow().as_ref().unwrap(), self.shader_registry);
+                    ShaderValue::from_live_ptr(self.live_registry, value_ptr.0, &zero).unwrap_or(zero)
+                }
+            }),
+            ExprKind::Lit {lit, ..} => Ok(ShaderValue::from_val(&lit.to_val())),
+        }
+    }
+
+    fn eval_bin_expr(&mut self, frame: &mut Frame<'a>, op: BinOp, left_expr: &'a Expr, right_expr: &'a Expr) -> Result<ShaderValue, LiveError> {
+        let op = match op {
+            BinOp::Assign => {
+                let value = self.eval_expr(frame, right_expr) ?;
+                self.assign(frame, left_expr, value.clone()) ?;
+                return Ok(value)
+            }
+            BinOp::AddAssign => BinOp::Add,
+            BinOp::SubAssign => BinOp::Sub,
+            BinOp::MulAssign => BinOp::Mul,
+            BinOp::DivAssign => BinOp::Div,
+            BinOp::Or => {
+                let left = self.eval_expr(frame, left_expr) ?.to_bool().unwrap();
+                return Ok(ShaderValue::Bool(left || self.eval_expr(frame, right_expr) ?.to_bool().unwrap()))
+            }
+            BinOp::And => {
+                let left = self.eval_expr(frame, left_expr) ?.to_bool().unwrap();
+                return Ok(ShaderValue::Bool(left && self.eval_expr(frame, right_expr) ?.to_bool().unwrap()))
+            }
+            op => {
+                let left = self.eval_expr(frame, left_expr) ?;
+                let right = self.eval_expr(frame, right_expr) ?;
+                return Ok(eval_bin_op(op, &left, &right))
+            }
+        };
+        let left = self.eval_expr(frame, left_expr) ?;
+        let right = self.eval_expr(frame, right_expr) ?;
+        let value = eval_bin_op(op, &left, &right);
+        self.assign(frame, left_expr, value.clone()) ?;
+        Ok(value)
+    }
+
+    fn assign(&mut self, frame: &mut Frame<'a>, expr: &'a Expr, value: ShaderValue) -> Result<(), LiveError> {
+        match expr.kind {
+            ExprKind::Var {ref kind, ..} => match kind.get().unwrap() {
+                VarKind::Local {ident, shadow} | VarKind::MutLocal {ident, shadow} => {
+                    frame.locals.insert((ident, shadow), value);
+                }
+                VarKind::LiveValue(_) => panic!()
+            },
+            ExprKind::Field {
+                expr: ref base_expr,
+                field_ident,
+                ..
+            } => {
+                if let Some(Ty::DrawShader(_)) = base_expr.ty.borrow().as_ref() {
+                    self.fields.insert(field_ident, value);
+                    return Ok(())
+                }
+                let mut base = self.eval_expr(frame, base_expr) ?;
+                if let ShaderValue::Struct(fields) = &mut base {
+                    fields.iter_mut().find( | (ident, _) | *ident == field_ident).unwrap().1 = value;
+                }
+                else {
+                    base.set_swizzle(&Swizzle::parse(field_ident).unwrap(), &value);
+                }
+                self.assign(frame, base_expr, base) ?;
+            }
+            ExprKind::Index {
+                expr: ref base_expr,
+                ref index_expr,
+                ..
+            } => {
+                let mut base = self.eval_expr(frame, base_expr) ?;
+                let index = self.eval_expr(frame, index_expr) ?.to_int().unwrap();
+                base.set_index(index as usize, value);
+                self.assign(frame, base_expr, base) ?;
+            }
+            _ => panic!()
+        }
+        Ok(())
+    }
+
+    fn eval_call_expr(&mut self, frame: &mut Frame<'a>, fn_def: &'a FnDef, arg_exprs: &'a [Expr]) -> Result<ShaderValue, LiveError> {
+        let mut args = Vec::new();
+        let mut closure_args = Vec::new();
+        for (arg_index, arg_expr) in arg_exprs.iter().enumerate() {
+            if let ExprKind::ClosureDef(closure_def_index) = arg_expr.kind {
+                // closed over values are passed by value, so a copy of the locals will do
+                closure_args.push((arg_index, Closure {
+                    fn_def: frame.fn_def,
+                    closure_def_index,
+                    locals: frame.locals.clone(),
+                    closure_args: frame.closure_args.clone(),
+                }));
+                args.push(ShaderValue::Void);
+            }
+            else {
+                args.push(self.eval_expr(frame, arg_expr) ?);
+            }
+        }
+        let (value, callee_frame) = self.call_fn(fn_def, args, closure_args) ?;
+        // write the inout params back to where they came from
+        for (param, arg_expr) in fn_def.params.iter().zip(arg_exprs) {
+            if !param.is_inout {
+                continue;
+            }
+            if let Some(value) = param.shadow.get().and_then( | shadow | callee_frame.locals.get(&(param.ident, shadow))) {
+                self.assign(frame, arg_expr, value.clone()) ?;
+            }
+        }
+        Ok(value)
+    }
+
+    fn eval_closure_call_expr(&mut self, frame: &mut Frame<'a>, param_index: usize, arg_exprs: &'a [Expr]) -> Result<ShaderValue, LiveError> {
+        let closure = frame.closure_args.iter().find( | (index, _) | *index == param_index).unwrap().1.clone();
+        let closure_def = &closure.fn_def.closure_defs[closure.closure_def_index.0];
+        let mut closure_frame = Frame {
+            fn_def: closure.fn_def,
+            locals: closure.locals,
+            closure_args: closure.closure_args,
+        };
+        for (param, arg_expr) in closure_def.params.iter().zip(arg_exprs) {
+            let value = self.eval_expr(frame, arg_expr) ?;
+            closure_frame.locals.insert((param.ident, param.shadow.get().unwrap()), value);
+        }
+        match &closure_def.kind {
+            ClosureDefKind::Expr(expr) => self.eval_expr(&mut closure_frame, expr),
+            ClosureDefKind::Block(block) => match self.exec_block(&mut closure_frame, block) ? {
+                Flow::Return(value) => Ok(value),
+                _ => Ok(ShaderValue::Void)
+            }
+        }
+    }
+
+    fn call_builtin(&self, span: TokenSpan, ident: Ident, args: &[ShaderValue]) -> Result<ShaderValue, LiveError> {
+        let a = | index: usize | &args[index];
+        Ok(match ident.0 {
+            live_id!(abs) => map_floats(&[a(0)], | x | x[0].abs()),
+            live_id!(acos) => map_floats(&[a(0)], | x | x[0].acos()),
+            live_id!(asin) => map_floats(&[a(0)], | x | x[0].asin()),
+            live_id!(atan) => if args.len() == 2 {
+                map_floats(&[a(0), a(1)], | x | x[0].atan2(x[1]))
+            }
+            else {
+                map_floats(&[a(0)], | x | x[0].atan())
+            },
+            live_id!(ceil) => map_floats(&[a(0)], | x | x[0].ceil()),
+            live_id!(cos) => map_floats(&[a(0)], | x | x[0].cos()),
+            live_id!(degrees) => map_floats(&[a(0)], | x | x[0].to_degrees()),
+            live_id!(exp) => map_floats(&[a(0)], | x | x[0].exp()),
+            live_id!(exp2) => map_floats(&[a(0)], | x | x[0].exp2()),
+            live_id!(floor) => map_floats(&[a(0)], | x | x[0].floor()),
+            live_id!(fract) => map_floats(&[a(0)], | x | x[0] - x[0].floor()),
+            live_id!(inversesqrt) => map_floats(&[a(0)], | x | 1.0 / x[0].sqrt()),
+            live_id!(log) => map_floats(&[a(0)], | x | x[0].ln()),
+            live_id!(log2) => map_floats(&[a(0)], | x | x[0].log2()),
+            live_id!(radians) => map_floats(&[a(0)], | x | x[0].to_radians()),
+            live_id!(sign) => map_floats(&[a(0)], | x | if x[0] > 0.0 {1.0} else if x[0] < 0.0 {-1.0} else {0.0}),
+            live_id!(sin) => map_floats(&[a(0)], | x | x[0].sin()),
+            live_id!(sqrt) => map_floats(&[a(0)], | x | x[0].sqrt()),
+            live_id!(tan) => map_floats(&[a(0)], | x | x[0].tan()),
+            live_id!(clamp) => map_floats(&[a(0), a(1), a(2)], | x | x[0].max(x[1]).min(x[2])),
+            live_id!(max) => map_floats(&[a(0), a(1)], | x | x[0].max(x[1])),
+            live_id!(min) => map_floats(&[a(0), a(1)], | x | x[0].min(x[1])),
+            live_id!(mix) => map_floats(&[a(0), a(1), a(2)], | x | x[0] + (x[1] - x[0]) * x[2]),
+            live_id!(mod) => map_floats(&[a(0), a(1)], | x | x[0] - x[1] * (x[0] / x[1]).floor()),
+            live_id!(pow) => map_floats(&[a(0), a(1)], | x | x[0].powf(x[1])),
+            live_id!(step) => map_floats(&[a(0), a(1)], | x | if x[1] < x[0] {0.0} else {1.0}),
+            live_id!(smoothstep) => map_floats(&[a(0), a(1), a(2)], | x | {
+                let t = ((x[2] - x[0]) / (x[1] - x[0])).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            }),
+            live_id!(length) => ShaderValue::Float(dot(a(0), a(0)).sqrt()),
+            live_id!(distance) => {
+                let d = map_floats(&[a(0), a(1)], | x | x[0] - x[1]);
+                ShaderValue::Float(dot(&d, &d).sqrt())
+            }
+            live_id!(dot) => ShaderValue::Float(dot(a(0), a(1))),
+            live_id!(normalize) => {
+                let len = ShaderValue::Float(dot(a(0), a(0)).sqrt());
+                map_floats(&[a(0), &len], | x | x[0] / x[1])
+            }
+            live_id!(cross) => {
+                let (l, r) = (a(0).to_floats(), a(1).to_floats());
+                ShaderValue::Vec(vec![
+                    l[1] * r[2] - r[1] * l[2],
+                    l[2] * r[0] - r[2] * l[0],
+                    l[0] * r[1] - r[0] * l[1]
+                ])
+            }
+            live_id!(faceforward) => if dot(a(2), a(1)) < 0.0 {
+                a(0).clone()
+            }
+            else {
+                map_floats(&[a(0)], | x | -x[0])
+            },
+            live_id!(reflect) => {
+                let d = ShaderValue::Float(2.0 * dot(a(1), a(0)));
+                map_floats(&[a(0), a(1), &d], | x | x[0] - x[2] * x[1])
+            }
+            live_id!(refract) => {
+                let eta = a(2).to_f32().unwrap();
+                let n_dot_i = dot(a(1), a(0));
+                let k = 1.0 - eta * eta * (1.0 - n_dot_i * n_dot_i);
+                if k < 0.0 {
+                    map_floats(&[a(0)], | _ | 0.0)
+                }
+                else {
+                    let f = ShaderValue::Float(eta * n_dot_i + k.sqrt());
+                    map_floats(&[a(0), a(1), &f], | x | eta * x[0] - x[2] * x[1])
+                }
+            }
+            live_id!(all) => ShaderValue::Bool(a(0).to_floats().iter().all( | x | *x != 0.0)),
+            live_id!(any) => ShaderValue::Bool(a(0).to_floats().iter().any( | x | *x != 0.0)),
+            live_id!(not) => compare_floats(a(0), a(0), | x, _ | x == 0.0),
+            live_id!(equal) => compare_floats(a(0), a(1), | x, y | x == y),
+            live_id!(notEqual) => compare_floats(a(0), a(1), | x, y | x != y),
+            live_id!(lessThan) => compare_floats(a(0), a(1), | x, y | x < y),
+            live_id!(lessThanEqual) => compare_floats(a(0), a(1), | x, y | x <= y),
+            live_id!(greaterThan) => compare_floats(a(0), a(1), | x, y | x > y),
+            live_id!(greaterThanEqual) => compare_floats(a(0), a(1), | x, y | x >= y),
+            live_id!(matrixCompMult) => map_floats(&[a(0), a(1)], | x | x[0] * x[1]),
+            live_id!(transpose) => {
+                let m = a(0).to_floats();
+                let n = mat_dim(m.len());
+                ShaderValue::Mat((0..n * n).map( | i | m[(i % n) * n + i / n]).collect())
+            }
+            live_id!(inverse) => ShaderValue::Mat(mat_inverse(&a(0).to_floats())),
+            live_id!(dFdx) | live_id!(dFdy) => map_floats(&[a(0)], | _ | 0.0),
+            live_id!(sample2d) | live_id!(sample2d_rt) | live_id!(sample2dOES) => {
+                let texture_ident = match a(0) {
+                    ShaderValue::Texture(ident) => *ident,
+                    _ => panic!()
+                };
+                let texture = self.textures.get(&texture_ident).ok_or_else( || LiveError {
+                    origin: live_error_origin!(),
+                    span: span.into(),
+                    message: format!("texture `{}` is not set", texture_ident),
+                }) ?;
+                texture.sample(a(1).to_vec2().unwrap()).into()
+            }
+            _ => return Err(LiveError {
+                origin: live_error_origin!(),
+                span: span.into(),
+                message: format!("builtin `{}` is not supported by the interpreter", ident),
+            })
+        })
+    }
+}
+
+impl CpuTexture {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Vec4::default(); width * height]
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Vec4>) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        Self {width, height, pixels}
+    }
+
+    // clamps to the edge, row 0 is the top of the image
+    pub fn pixel(&self, x: isize, y: isize) -> Vec4 {
+        let x = x.max(0).min(self.width as isize - 1) as usize;
+        let y = y.max(0).min(self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    // bilinear filtered, like the default sampler on the gpu
+    pub fn sample(&self, pos: Vec2) -> Vec4 {
+        if self.width == 0 || self.height == 0 {
+            return Vec4::default()
+        }
+        let x = pos.x * self.width as f32 - 0.5;
+        let y = pos.y * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+        let top = self.pixel(x0, y0) * (1.0 - fx) + self.pixel(x0 + 1, y0) * fx;
+        let bottom = self.pixel(x0, y0 + 1) * (1.0 - fx) + self.pixel(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+impl ShaderValue {
+    pub fn zero(ty: &Ty, shader_registry: &ShaderRegistry) -> Self {
+        match ty {
+            Ty::Void | Ty::DrawShader(_) | Ty::ClosureDef(_) | Ty::ClosureDecl => Self::Void,
+            Ty::Bool => Self::Bool(false),
+            Ty::Int | Ty::Enum(_) => Self::Int(0),
+            Ty::Float => Self::Float(0.0),
+            Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 => Self::Bvec(vec![false; ty.slots()]),
+            Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4 => Self::Ivec(vec![0; ty.slots()]),
+            Ty::Vec2 | Ty::Vec3 | Ty::Vec4 => Self::Vec(vec![0.0; ty.slots()]),
+            Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => Self::Mat(vec![0.0; ty.slots()]),
+            Ty::Texture2D | Ty::TextureOES => Self::Texture(Ident::default()),
+            Ty::Array {elem_ty, len} => Self::Array((0..*len).map( | _ | Self::zero(elem_ty, shader_registry)).collect()),
+            Ty::Struct(struct_ptr) => Self::Struct(
+                shader_registry.structs.get(struct_ptr).unwrap().fields.iter().map( | field | {
+                    (field.ident, Self::zero(field.ty_expr.ty.borrow().as_ref().unwrap(), shader_registry))
+                }).collect()
+            ),
+        }
+    }
+
+    pub fn from_val(val: &Val) -> Self {
+        match val {
+            Val::Bool(v) => Self::Bool(*v),
+            Val::Int(v) => Self::Int(*v),
+            Val::Float(v) => Self::Float(*v),
+            Val::Vec4(v) => (*v).into(),
+        }
+    }
+
+    // reads a live value into a value shaped like zero
+    pub fn from_live_ptr(live_registry: &LiveRegistry, live_ptr: LivePtr, zero: &ShaderValue) -> Option<Self> {
+        let (nodes, index) = live_registry.ptr_to_nodes_index(live_ptr);
+        let value = match &nodes[index].value {
+            LiveValue::Expr => live_eval_value(live_registry, &mut (index + 1), nodes, nodes).ok() ?,
+            value => value.clone()
+        };
+        let floats = match value {
+            LiveValue::Bool(v) => vec![if v {1.0} else {0.0}],
+            LiveValue::Int64(v) => vec![v as f32],
+            LiveValue::Float32(v) => vec![v],
+            LiveValue::Float64(v) => vec![v as f32],
+            LiveValue::Color(v) => {
+                let v = Vec4::from_u32(v);
+                vec![v.x, v.y, v.z, v.w]
+            }
+            LiveValue::Vec2(v) => vec![v.x, v.y],
+            LiveValue::Vec3(v) => vec![v.x, v.y, v.z],
+            LiveValue::Vec4(v) => vec![v.x, v.y, v.z, v.w],
+            _ => return None
+        };
+        let len = zero.to_floats().len();
+        if len == 0 {
+            return None
+        }
+        if floats.len() == 1 {
+            Some(zero.with_floats(&vec![floats[0]; len]))
+        }
+        else if floats.len() == len {
+            Some(zero.with_floats(&floats))
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn to_bool(&self) -> Option<bool> {
+        match *self {
+            Self::Bool(v) => Some(v),
+            _ => None
+        }
+    }
+
+    pub fn to_int(&self) -> Option<i32> {
+        match *self {
+            Self::Int(v) => Some(v),
+            Self::Float(v) => Some(v as i32),
+            _ => None
+        }
+    }
+
+    pub fn to_f32(&self) -> Option<f32> {
+        match *self {
+            Self::Int(v) => Some(v as f32),
+            Self::Float(v) => Some(v),
+            _ => None
+        }
+    }
+
+    pub fn to_vec2(&self) -> Option<Vec2> {
+        match self {
+            Self::Vec(v) if v.len() == 2 => Some(vec2(v[0], v[1])),
+            _ => None
+        }
+    }
+
+    pub fn to_vec3(&self) -> Option<Vec3> {
+        match self {
+            Self::Vec(v) if v.len() == 3 => Some(vec3(v[0], v[1], v[2])),
+            _ => None
+        }
+    }
+
+    pub fn to_vec4(&self) -> Option<Vec4> {
+        match self {
+            Self::Vec(v) if v.len() == 4 => Some(vec4(v[0], v[1], v[2], v[3])),
+            _ => None
+        }
+    }
+
+    pub fn to_mat4(&self) -> Option<Mat4> {
+        match self {
+            Self::Mat(v) if v.len() == 16 => {
+                let mut m = Mat4::default();
+                m.v.copy_from_slice(v);
+                Some(m)
+            }
+            _ => None
+        }
+    }
+
+    // all scalar components as floats, matrices column major
+    pub fn to_floats(&self) -> Vec<f32> {
+        match self {
+            Self::Bool(v) => vec![if *v {1.0} else {0.0}],
+            Self::Int(v) => vec![*v as f32],
+            Self::Float(v) => vec![*v],
+            Self::Bvec(v) => v.iter().map( | v | if *v {1.0} else {0.0}).collect(),
+            Self::Ivec(v) => v.iter().map( | v | *v as f32).collect(),
+            Self::Vec(v) | Self::Mat(v) => v.clone(),
+            _ => Vec::new()
+        }
+    }
+
+    // a value of the same scalar kind as self with these components,
+    // scalars and vectors are sized by the number of floats
+    pub fn with_floats(&self, floats: &[f32]) -> Self {
+        match self {
+            Self::Bool(_) | Self::Bvec(_) => if floats.len() == 1 {
+                Self::Bool(floats[0] != 0.0)
+            }
+            else {
+                Self::Bvec(floats.iter().map( | v | *v != 0.0).collect())
+            },
+            Self::Int(_) | Self::Ivec(_) => if floats.len() == 1 {
+                Self::Int(floats[0] as i32)
+            }
+            else {
+                Self::Ivec(floats.iter().map( | v | *v as i32).collect())
+            },
+            Self::Float(_) | Self::Vec(_) => if floats.len() == 1 {
+                Self::Float(floats[0])
+            }
+            else {
+                Self::Vec(floats.to_vec())
+            },
+            Self::Mat(_) => Self::Mat(floats.to_vec()),
+            _ => panic!()
+        }
+    }
+
+    fn swizzle(&self, swizzle: &Swizzle) -> Self {
+        let floats = self.to_floats();
+        let picked: Vec<f32> = swizzle.iter().map( | index | floats[*index]).collect();
+        self.with_floats(&picked)
+    }
+
+    fn set_swizzle(&mut self, swizzle: &Swizzle, value: &ShaderValue) {
+        let mut floats = self.to_floats();
+        let values = value.to_floats();
+        for (value_index, index) in swizzle.iter().enumerate() {
+            floats[*index] = values[value_index];
+        }
+        *self = self.with_floats(&floats);
+    }
+
+    fn index(&self, index: usize) -> Self {
+        match self {
+            Self::Array(elems) => elems[index].clone(),
+            Self::Mat(v) => {
+                let n = mat_dim(v.len());
+                Self::Vec(v[index * n..(index + 1) * n].to_vec())
+            }
+            value => value.with_floats(&[value.to_floats()[index]])
+        }
+    }
+
+    fn set_index(&mut self, index: usize, value: ShaderValue) {
+        match self {
+            Self::Array(elems) => elems[index] = value,
+            Self::Mat(v) => {
+                let n = mat_dim(v.len());
+                v[index * n..(index + 1) * n].copy_from_slice(&value.to_floats());
+            }
+            _ => {
+                let mut floats = self.to_floats();
+                floats[index] = value.to_floats()[0];
+                *self = self.with_floats(&floats);
+            }
+        }
+    }
+
+    fn construct(ty_lit: TyLit, args: &[ShaderValue]) -> Self {
+        let zero = match ty_lit {
+            TyLit::Bool => Self::Bool(false),
+            TyLit::Int => Self::Int(0),
+            TyLit::Float => Self::Float(0.0),
+            TyLit::Bvec2 | TyLit::Bvec3 | TyLit::Bvec4 => Self::Bvec(vec![false; ty_lit.to_ty().slots()]),
+            TyLit::Ivec2 | TyLit::Ivec3 | TyLit::Ivec4 => Self::Ivec(vec![0; ty_lit.to_ty().slots()]),
+            TyLit::Vec2 | TyLit::Vec3 | TyLit::Vec4 => Self::Vec(vec![0.0; ty_lit.to_ty().slots()]),
+            TyLit::Mat2 | TyLit::Mat3 | TyLit::Mat4 => Self::Mat(vec![0.0; ty_lit.to_ty().slots()]),
+            TyLit::Texture2D | TyLit::TextureOES => panic!(),
+        };
+        let len = zero.to_floats().len();
+        if let Self::Mat(_) = zero {
+            let n = mat_dim(len);
+            let mut floats = vec![0.0; len];
+            match args {
+                // a scalar fills the diagonal
+                [arg] if arg.to_floats().len() == 1 => {
+                    for i in 0..n {
+                        floats[i * n + i] = arg.to_floats()[0];
+                    }
+                    return zero.with_floats(&floats)
+                }
+                // a matrix of another size is cut off or extended with identity
+                [Self::Mat(m)] => {
+                    let k = mat_dim(m.len());
+                    for c in 0..n {
+                        for r in 0..n {
+                            floats[c * n + r] = if c < k && r < k {m[c * k + r]} else if c == r {1.0} else {0.0};
+                        }
+                    }
+                    return zero.with_floats(&floats)
+                }
+                _ => ()
+            }
+        }
+        let floats: Vec<f32> = args.iter().flat_map( | arg | arg.to_floats()).collect();
+        if floats.len() == 1 {
+            zero.with_floats(&vec![floats[0]; len])
+        }
+        else {
+            zero.with_floats(&floats[0..len])
+        }
+    }
+}
+
+impl From<bool> for ShaderValue {
+    fn from(v: bool) -> Self {Self::Bool(v)}
+}
+
+impl From<i32> for ShaderValue {
+    fn from(v: i32) -> Self {Self::Int(v)}
+}
+
+impl From<f32> for ShaderValue {
+    fn from(v: f32) -> Self {Self::Float(v)}
+}
+
+impl From<Vec2> for ShaderValue {
+    fn from(v: Vec2) -> Self {Self::Vec(vec![v.x, v.y])}
+}
+
+impl From<Vec3> for ShaderValue {
+    fn from(v: Vec3) -> Self {Self::Vec(vec![v.x, v.y, v.z])}
+}
+
+impl From<Vec4> for ShaderValue {
+    fn from(v: Vec4) -> Self {Self::Vec(vec![v.x, v.y, v.z, v.w])}
+}
+
+impl From<Mat4> for ShaderValue {
+    fn from(v: Mat4) -> Self {Self::Mat(v.v.to_vec())}
+}
+
+fn mat_dim(len: usize) -> usize {
+    match len {
+        4 => 2,
+        9 => 3,
+        16 => 4,
+        _ => panic!()
+    }
+}
+
+// applies f per component, scalar args are repeated for vector args
+fn map_floats(args: &[&ShaderValue], f: impl Fn(&[f32]) -> f32) -> ShaderValue {
+    let shape = args.iter().find( | arg | arg.to_floats().len() > 1).unwrap_or(&args[0]);
+    let arg_floats: Vec<Vec<f32 >> = args.iter().map( | arg | arg.to_floats()).collect();
+    let len = shape.to_floats().len();
+    let mut x = vec![0.0; args.len()];
+    let mut out = Vec::new();
+    for i in 0..len {
+        for (arg_index, floats) in arg_floats.iter().enumerate() {
+            x[arg_index] = if floats.len() == 1 {floats[0]} else {floats[i]};
+        }
+        out.push(f(&x));
+    }
+    shape.with_floats(&out)
+}
+
+fn compare_floats(left: &ShaderValue, right: &ShaderValue, f: impl Fn(f32, f32) -> bool) -> ShaderValue {
+    let floats = left.to_floats().iter().zip(right.to_floats()).map( | (l, r) | if f(*l, r) {1.0} else {0.0}).collect::<Vec<_ >> ();
+    ShaderValue::Bool(false).with_floats(&floats)
+}
+
+fn dot(left: &ShaderValue, right: &ShaderValue) -> f32 {
+    left.to_floats().iter().zip(right.to_floats()).map( | (l, r) | l * r).sum()
+}
+
+fn eval_bin_op(op: BinOp, left: &ShaderValue, right: &ShaderValue) -> ShaderValue {
+    match op {
+        BinOp::Eq => ShaderValue::Bool(left == right),
+        BinOp::Ne => ShaderValue::Bool(left != right),
+        BinOp::Lt => ShaderValue::Bool(left.to_f32().unwrap() < right.to_f32().unwrap()),
+        BinOp::Le => ShaderValue::Bool(left.to_f32().unwrap() <= right.to_f32().unwrap()),
+        BinOp::Gt => ShaderValue::Bool(left.to_f32().unwrap() > right.to_f32().unwrap()),
+        BinOp::Ge => ShaderValue::Bool(left.to_f32().unwrap() >= right.to_f32().unwrap()),
+        BinOp::Add => map_floats(&[left, right], | x | x[0] + x[1]),
+        BinOp::Sub => map_floats(&[left, right], | x | x[0] - x[1]),
+        BinOp::Div => map_floats(&[left, right], | x | x[0] / x[1]),
+        BinOp::Mul => match (left, right) {
+            (ShaderValue::Mat(l), ShaderValue::Mat(r)) => {
+                let n = mat_dim(l.len());
+                ShaderValue::Mat((0..n * n).map( | i | {
+                    let (c, r_) = (i / n, i % n);
+                    (0..n).map( | k | l[k * n + r_] * r[c * n + k]).sum()
+                }).collect())
+            }
+            (ShaderValue::Mat(m), ShaderValue::Vec(v)) => {
+                let n = v.len();
+                ShaderValue::Vec((0..n).map( | r | (0..n).map( | c | m[c * n + r] * v[c]).sum()).collect())
+            }
+            (ShaderValue::Vec(v), ShaderValue::Mat(m)) => {
+                let n = v.len();
+                ShaderValue::Vec((0..n).map( | c | (0..n).map( | r | v[r] * m[c * n + r]).sum()).collect())
+            }
+            _ => map_floats(&[left, right], | x | x[0] * x[1]),
+        },
+        _ => panic!()
+    }
+}
+
+// gauss-jordan elimination on a column major matrix
+fn mat_inverse(m: &[f32]) -> Vec<f32> {
+    let n = mat_dim(m.len());
+    let mut a = m.to_vec();
+    let mut inv = vec![0.0; n * n];
+    for i in 0..n {
+        inv[i * n + i] = 1.0;
+    }
+    for col in 0..n {
+        let pivot = (col..n).max_by( | x, y | a[col * n + x].abs().partial_cmp(&a[col * n + y].abs()).unwrap()).unwrap();
+        if a[col * n + pivot] == 0.0 {
+            return vec![0.0; n * n];
+        }
+        for c in 0..n {
+            a.swap(c * n + col, c * n + pivot);
+            inv.swap(c * n + col, c * n + pivot);
+        }
+        let d = a[col * n + col];
+        for c in 0..n {
+            a[c * n + col] /= d;
+            inv[c * n + col] /= d;
+        }
+        for r in 0..n {
+            if r != col {
+                let f = a[col * n + r];
+                for c in 0..n {
+                    a[c * n + r] -= f * a[c * n + col];
+                    inv[c * n + r] -= f * inv[c * n + col];
+                }
+            }
+        }
+    }
+    inv
+}
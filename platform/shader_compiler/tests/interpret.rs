@@ -0,0 +1,242 @@
+use makepad_shader_compiler::makepad_live_compiler::*;
+use makepad_shader_compiler::makepad_live_compiler::makepad_math::*;
+use makepad_shader_compiler::makepad_live_id::*;
+use makepad_shader_compiler::shader_registry::{ShaderRegistry, DrawShaderQuery};
+use makepad_shader_compiler::shader_ast::{DrawShaderPtr, ShaderTy};
+use makepad_shader_compiler::interpret::{ShaderInterpreter, ShaderValue, CpuTexture};
+
+// copies of the DrawQuad and DrawColor shaders in the draw crate
+const DRAW_QUAD_SOURCE: &'static str = r#"
+    DrawQuad = {{DrawQuad}} {
+        geometry: {{GeometryQuad2D}} {}
+        varying pos: vec2
+
+        fn clip_and_transform_vertex(self, rect_pos:vec2, rect_size:vec2) -> vec4 {
+            let clipped: vec2 = clamp(
+                self.geom_pos * rect_size + rect_pos,
+                self.draw_clip.xy,
+                self.draw_clip.zw
+            )
+            self.pos = (clipped - rect_pos) / rect_size
+            return self.camera_projection * (self.camera_view * (self.view_transform * vec4(
+                clipped.x,
+                clipped.y,
+                self.draw_depth + self.draw_zbias,
+                1.
+            )))
+        }
+
+        fn vertex(self) -> vec4 {
+            return self.clip_and_transform_vertex(self.rect_pos, self.rect_size)
+        }
+
+        fn pixel(self) -> vec4 {
+            return #f0f
+        }
+    }
+"#;
+
+const DRAW_COLOR_SOURCE: &'static str = r#"
+    DrawColor = {{DrawColor}} {
+        fn pixel(self) -> vec4 {
+            return vec4(self.color.rgb*self.color.a, self.color.a);
+        }
+    }
+"#;
+
+const DRAW_TEST_SOURCE: &'static str = r#"
+    SCALE = 2.0
+    TINT = #0f0
+
+    Acc = struct {
+        field sum: float
+        field steps: float
+
+        fn add(inout self, x: float) {
+            self.sum += x;
+            self.steps = self.steps + 1.0;
+        }
+    }
+
+    DrawTest = {{DrawTest}} {
+        geometry: {{GeometryQuad2D}} {}
+        uniform gain: 3.0
+        texture tex: texture2d
+
+        fn apply(self, x: float, f: fn(v: float) -> float) -> float {
+            return f(x);
+        }
+
+        fn sum_to(self, n: float) -> float {
+            let acc = Acc {sum: 0.0, steps: 0.0};
+            for i in 0..8 {
+                if n <= float(i) {
+                    break;
+                }
+                acc.add(float(i));
+            }
+            return acc.sum + acc.steps;
+        }
+
+        fn vertex(self) -> vec4 {
+            return vec4(self.geom_pos, 0.0, 1.0);
+        }
+
+        fn pixel(self) -> vec4 {
+            let k = self.gain * SCALE;
+            let v = vec4(0.25);
+            v.xy = vec2(self.apply(0.5, | x | x * k), self.sum_to(4.0));
+            v.z = v.y > 5.0 ? clamp(v.x, 0.0, 1.0) : 0.0;
+            let m = mat2(2.0);
+            v.w = (m * vec2(0.25, 0.0)).x;
+            return v * sample2d(self.tex, vec2(0.5, 0.5)) + TINT * 0.0;
+        }
+    }
+"#;
+
+struct DrawQuad;
+struct DrawColor;
+struct DrawTest;
+struct GeometryQuad2D;
+
+fn live_type_info<T: 'static>(type_name: LiveId, module_id: LiveModuleId, fields: Vec<LiveTypeField>) -> LiveTypeInfo {
+    LiveTypeInfo {
+        live_type: LiveType::of::<T>(),
+        type_name,
+        module_id,
+        live_ignore: false,
+        fields,
+        variants: Vec::new()
+    }
+}
+
+fn register(live_registry: &mut LiveRegistry, file_name: &str, module_id: LiveModuleId, source: &str, live_type_infos: Vec<LiveTypeInfo>) {
+    if let Err(err) = live_registry.register_live_file(
+        file_name,
+        "",
+        module_id,
+        source.to_string(),
+        live_type_infos,
+        TextPos::default()
+    ) {
+        panic!("{}", err);
+    }
+}
+
+fn analyse(live_registry: &LiveRegistry, shader_registry: &mut ShaderRegistry, module_id: LiveModuleId, name: LiveId, has_color: bool) -> DrawShaderPtr {
+    let draw_shader_ptr = DrawShaderPtr(live_registry.module_id_and_name_to_ptr(module_id, name).unwrap());
+    let result = shader_registry.analyse_draw_shader(live_registry, draw_shader_ptr, | _, _, span, draw_shader_query, _, draw_shader_def | {
+        match draw_shader_query {
+            DrawShaderQuery::DrawShader => {
+                draw_shader_def.add_instance(live_id!(rect_pos), ShaderTy::Vec2, span, LiveFieldKind::Calc);
+                draw_shader_def.add_instance(live_id!(rect_size), ShaderTy::Vec2, span, LiveFieldKind::Calc);
+                draw_shader_def.add_instance(live_id!(draw_clip), ShaderTy::Vec4, span, LiveFieldKind::Calc);
+                draw_shader_def.add_instance(live_id!(draw_depth), ShaderTy::Float, span, LiveFieldKind::Live);
+                if has_color {
+                    draw_shader_def.add_instance(LiveId(0), ShaderTy::Float, span, LiveFieldKind::Calc);
+                    draw_shader_def.add_instance(live_id!(color), ShaderTy::Vec4, span, LiveFieldKind::Live);
+                }
+            }
+            DrawShaderQuery::Geometry => {
+                draw_shader_def.add_geometry(live_id!(geom_pos), ShaderTy::Vec2, span);
+            }
+        }
+    });
+    if let Err(err) = result {
+        panic!("{}", live_registry.live_error_to_live_file_error(err));
+    }
+    draw_shader_ptr
+}
+
+fn draw_quad_registry() -> LiveRegistry {
+    let mut live_registry = LiveRegistry::default();
+    let draw_quad_module = LiveModuleId::from_str("test::draw_quad").unwrap();
+    let draw_color_module = LiveModuleId::from_str("test::draw_color").unwrap();
+    let draw_quad_info = live_type_info::<DrawQuad>(live_id!(DrawQuad), draw_quad_module, Vec::new());
+    register(&mut live_registry, "draw_quad.rs", draw_quad_module, DRAW_QUAD_SOURCE, vec![
+        draw_quad_info.clone(),
+        live_type_info::<GeometryQuad2D>(live_id!(GeometryQuad2D), draw_quad_module, Vec::new()),
+    ]);
+    register(&mut live_registry, "draw_color.rs", draw_color_module, DRAW_COLOR_SOURCE, vec![
+        live_type_info::<DrawColor>(live_id!(DrawColor), draw_color_module, vec![LiveTypeField {
+            id: live_id!(draw_super),
+            live_type_info: draw_quad_info,
+            live_field_kind: LiveFieldKind::Deref
+        }]),
+    ]);
+    let mut errors = Vec::new();
+    live_registry.expand_all_documents(&mut errors);
+    assert!(errors.is_empty(), "{:?}", errors);
+    live_registry
+}
+
+fn set_rect(interpreter: &mut ShaderInterpreter, geom_pos: Vec2) {
+    interpreter.set_field(live_id!(camera_projection), Mat4::default());
+    interpreter.set_field(live_id!(camera_view), Mat4::default());
+    interpreter.set_field(live_id!(view_transform), Mat4::default());
+    interpreter.set_field(live_id!(rect_pos), vec2(10.0, 20.0));
+    interpreter.set_field(live_id!(rect_size), vec2(100.0, 50.0));
+    interpreter.set_field(live_id!(draw_clip), vec4(0.0, 0.0, 60.0, 1000.0));
+    interpreter.set_field(live_id!(draw_depth), 1.0);
+    interpreter.set_field(live_id!(geom_pos), geom_pos);
+}
+
+#[test]
+fn draw_quad() {
+    let live_registry = draw_quad_registry();
+    let mut shader_registry = ShaderRegistry::new(true);
+    let module_id = LiveModuleId::from_str("test::draw_quad").unwrap();
+    let draw_shader_ptr = analyse(&live_registry, &mut shader_registry, module_id, live_id!(DrawQuad), false);
+    let draw_shader_def = shader_registry.draw_shader_defs.get(&draw_shader_ptr).unwrap();
+
+    let mut interpreter = ShaderInterpreter::new(&live_registry, &shader_registry, draw_shader_def);
+    set_rect(&mut interpreter, vec2(1.0, 1.0));
+    // the corner is clipped to draw_clip.zw on x
+    assert_eq!(interpreter.vertex().unwrap(), vec4(60.0, 70.0, 1.0, 1.0));
+    assert_eq!(interpreter.field(live_id!(pos)).unwrap().to_vec2(), Some(vec2(0.5, 1.0)));
+    assert_eq!(interpreter.pixel().unwrap(), vec4(1.0, 0.0, 1.0, 1.0));
+}
+
+#[test]
+fn draw_color() {
+    let live_registry = draw_quad_registry();
+    let mut shader_registry = ShaderRegistry::new(true);
+    let module_id = LiveModuleId::from_str("test::draw_color").unwrap();
+    let draw_shader_ptr = analyse(&live_registry, &mut shader_registry, module_id, live_id!(DrawColor), true);
+    let draw_shader_def = shader_registry.draw_shader_defs.get(&draw_shader_ptr).unwrap();
+
+    let mut interpreter = ShaderInterpreter::new(&live_registry, &shader_registry, draw_shader_def);
+    set_rect(&mut interpreter, vec2(0.0, 0.0));
+    interpreter.set_field(live_id!(color), vec4(1.0, 0.5, 0.25, 0.5));
+    assert_eq!(interpreter.vertex().unwrap(), vec4(10.0, 20.0, 1.0, 1.0));
+    assert_eq!(interpreter.pixel().unwrap(), vec4(0.5, 0.25, 0.125, 0.5));
+}
+
+#[test]
+fn draw_test() {
+    let mut live_registry = LiveRegistry::default();
+    let module_id = LiveModuleId::from_str("test::draw_test").unwrap();
+    register(&mut live_registry, "draw_test.rs", module_id, DRAW_TEST_SOURCE, vec![
+        live_type_info::<DrawTest>(live_id!(DrawTest), module_id, Vec::new()),
+        live_type_info::<GeometryQuad2D>(live_id!(GeometryQuad2D), module_id, Vec::new()),
+    ]);
+    let mut errors = Vec::new();
+    live_registry.expand_all_documents(&mut errors);
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let mut shader_registry = ShaderRegistry::new(true);
+    let draw_shader_ptr = analyse(&live_registry, &mut shader_registry, module_id, live_id!(DrawTest), false);
+    let draw_shader_def = shader_registry.draw_shader_defs.get(&draw_shader_ptr).unwrap();
+
+    let mut interpreter = ShaderInterpreter::new(&live_registry, &shader_registry, draw_shader_def);
+    assert_eq!(interpreter.field(live_id!(gain)), Some(&ShaderValue::Float(3.0)));
+    assert!(interpreter.pixel().is_err());
+
+    interpreter.set_texture(live_id!(tex), CpuTexture::from_pixels(2, 1, vec![vec4(1.0, 1.0, 1.0, 1.0), vec4(0.0, 1.0, 0.0, 1.0)]));
+    // 0.5 * 3 * 2, 0+1+2+3 plus 4 steps, clamped x, mat2(2) * 0.25, all times the filtered texel
+    assert_eq!(interpreter.pixel().unwrap(), vec4(3.0 * 0.5, 10.0, 1.0 * 0.5, 0.5));
+    assert_eq!(
+        interpreter.call_method(live_id!(sum_to), vec![ShaderValue::Float(2.0)]).unwrap(),
+        ShaderValue::Float(3.0)
+    );
+}
@@ -0,0 +1,139 @@
+use makepad_shader_compiler::makepad_live_compiler::*;
+use makepad_shader_compiler::makepad_live_id::*;
+use makepad_shader_compiler::shader_registry::{ShaderRegistry, DrawShaderQuery};
+use makepad_shader_compiler::shader_ast::{DrawShaderPtr, ShaderTy};
+use makepad_shader_compiler::generate_wgsl;
+
+const SOURCE: &'static str = r#"
+    SCALE = 2.0
+
+    Shape = struct {
+        field pos: vec2
+        field dist: float
+        field result: vec4
+
+        fn viewport(pos: vec2) -> Self {
+            return Self {pos: pos, dist: 1e+20, result: vec4(0.)}
+        }
+
+        fn circle(inout self, x: float, y: float, r: float) {
+            let c = self.pos - vec2(x, y);
+            self.dist = min(self.dist, length(c) - r);
+        }
+
+        fn fill(inout self, color: vec4) -> vec4 {
+            let f = clamp(-self.dist, 0.0, 1.0);
+            self.result = mix(self.result, color, f);
+            self.dist = self.dist = 1e+20;
+            return self.result;
+        }
+    }
+
+    DrawTest = {{DrawTest}} {
+        geometry: {{GeometryTest}} {}
+        varying pos: vec2
+        uniform radius: 4.0
+        texture tex: texture2d
+
+        fn wobble(self, x: float, steps: float) -> float {
+            for i in 0..4 {
+                x += sin(float(i) * x);
+            }
+            x = mod(x, 2.0);
+            if steps > 2.0 {
+                x *= 0.5;
+            }
+            else {
+                x = step(0.5, x);
+            }
+            return smoothstep(0.0, 1.0, x) + steps;
+        }
+
+        fn vertex(self) -> vec4 {
+            let pos = self.geom_pos * self.rect_size + self.rect_pos;
+            self.pos = self.geom_pos;
+            return self.camera_projection * (self.camera_view * (self.view_transform * self.transform * vec4(pos, self.draw_depth, 1.)));
+        }
+
+        fn pixel(self) -> vec4 {
+            let sdf = Shape::viewport(self.pos * self.rect_size);
+            sdf.circle(self.radius, self.radius, self.radius * SCALE);
+            let color = sample2d(self.tex, self.pos) * self.color;
+            let w = self.wobble(dFdx(self.pos.x), 3.0);
+            color.a = max(color.a, w > 1.0 ? 1.0 : 0.0);
+            return sdf.fill(mix(color, #f00, clamp(self.pos, 0.0, 1.0).x));
+        }
+    }
+"#;
+
+struct DrawTest;
+struct GeometryTest;
+
+fn live_type_info<T: 'static>(type_name: LiveId, module_id: LiveModuleId) -> LiveTypeInfo {
+    LiveTypeInfo {
+        live_type: LiveType::of::<T>(),
+        type_name,
+        module_id,
+        live_ignore: false,
+        fields: Vec::new(),
+        variants: Vec::new()
+    }
+}
+
+#[test]
+fn generate_wgsl() {
+    let module_id = LiveModuleId::from_str("test::wgsl").unwrap();
+    let mut live_registry = LiveRegistry::default();
+    if let Err(err) = live_registry.register_live_file(
+        "wgsl.rs",
+        "",
+        module_id,
+        SOURCE.to_string(),
+        vec![
+            live_type_info::<DrawTest>(live_id!(DrawTest), module_id),
+            live_type_info::<GeometryTest>(live_id!(GeometryTest), module_id),
+        ],
+        TextPos::default()
+    ) {
+        panic!("{}", err);
+    }
+    let mut errors = Vec::new();
+    live_registry.expand_all_documents(&mut errors);
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let draw_shader_ptr = DrawShaderPtr(live_registry.module_id_and_name_to_ptr(module_id, live_id!(DrawTest)).unwrap());
+    let mut shader_registry = ShaderRegistry::new(true);
+    let result = shader_registry.analyse_draw_shader(&live_registry, draw_shader_ptr, | _, _, span, draw_shader_query, _, draw_shader_def | {
+        match draw_shader_query {
+            DrawShaderQuery::DrawShader => {
+                draw_shader_def.add_instance(live_id!(rect_pos), ShaderTy::Vec2, span, LiveFieldKind::Live);
+                draw_shader_def.add_instance(live_id!(rect_size), ShaderTy::Vec2, span, LiveFieldKind::Live);
+                draw_shader_def.add_instance(live_id!(draw_depth), ShaderTy::Float, span, LiveFieldKind::Live);
+                draw_shader_def.add_instance(LiveId(0), ShaderTy::Float, span, LiveFieldKind::Calc);
+                draw_shader_def.add_instance(live_id!(transform), ShaderTy::Mat4, span, LiveFieldKind::Live);
+                draw_shader_def.add_instance(live_id!(color), ShaderTy::Vec4, span, LiveFieldKind::Live);
+            }
+            DrawShaderQuery::Geometry => {
+                draw_shader_def.add_geometry(live_id!(geom_pos), ShaderTy::Vec2, span);
+            }
+        }
+    });
+    if let Err(err) = result {
+        panic!("{}", live_registry.live_error_to_live_file_error(err));
+    }
+
+    let const_table = shader_registry.compute_const_table(draw_shader_ptr);
+    let draw_shader_def = shader_registry.draw_shader_defs.get(&draw_shader_ptr).unwrap();
+    let shader = generate_wgsl::generate_shader(draw_shader_def, &const_table, &shader_registry);
+
+    let module = match naga::front::wgsl::parse_str(&shader.wgsl) {
+        Ok(module) => module,
+        Err(err) => panic!("{}\n{}", err.emit_to_string(&shader.wgsl), shader.wgsl)
+    };
+    let mut validator = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all());
+    if let Err(err) = validator.validate(&module) {
+        panic!("{}\n{}", err.emit_to_string(&shader.wgsl), shader.wgsl);
+    }
+    assert!(module.entry_points.iter().any( | entry_point | entry_point.name == "vertex_main"));
+    assert!(module.entry_points.iter().any( | entry_point | entry_point.name == "fragment_main"));
+}
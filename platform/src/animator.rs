@@ -11,6 +11,7 @@ use {
             LiveTypeField,
             LivePropType,
             LiveFieldKind,
+            LiveVariantKind,
             LiveModuleId,
             LiveType,
             LiveNode,
@@ -557,6 +558,7 @@ impl LiveNew for Animator {
             live_type: LiveType::of::<Self>(),
             live_ignore: true,
             fields: Vec::new(),
+            variants: Vec::new(),
             type_name: id_lut!(States)
         }
     }
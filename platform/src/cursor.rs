@@ -5,6 +5,7 @@ use {
         makepad_live_compiler::{
             LiveValue,
             LiveTypeInfo,
+            LiveVariantKind,
             LiveModuleId,
             LiveType,
             LiveId,
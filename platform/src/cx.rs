@@ -14,7 +14,8 @@ use {
         action::{ActionSendSync,ACTION_SENDER_GLOBAL},
         makepad_live_compiler::{
             LiveRegistry,
-            LiveFileChange
+            LiveFileChange,
+            LiveModuleId
         },
         makepad_shader_compiler::ShaderRegistry,
         draw_shader::CxDrawShaders,
@@ -97,6 +98,7 @@ pub struct Cx {
 
     pub (crate) live_file_change_receiver: std::sync::mpsc::Receiver<Vec<LiveFileChange>>,
     pub (crate) live_file_change_sender: std::sync::mpsc::Sender<Vec<LiveFileChange >>,
+    pub (crate) live_rebinds: Vec<(LiveModuleId, Option<LiveModuleId>)>,
     
     pub (crate) action_receiver: std::sync::mpsc::Receiver<ActionSendSync>,
     
@@ -173,6 +175,7 @@ pub enum OsType {
     OpenHarmony(OpenHarmonyParams),
     LinuxWindow (LinuxWindowParams),
     LinuxDirect,
+    LinuxHeadless,
     Web(WebParams)
 }
 
@@ -189,6 +192,7 @@ impl OsType {
             OsType::Ios=>true,
             OsType::Android(_) => true,
             OsType::LinuxDirect=> true,
+            OsType::LinuxHeadless=> true,
             _=> false
         }
     }
@@ -278,6 +282,7 @@ impl Cx {
             
             live_file_change_receiver,
             live_file_change_sender,
+            live_rebinds: Vec::new(),
             action_receiver,
             
             shader_registry: ShaderRegistry::new(true),
@@ -44,6 +44,10 @@ pub trait CxOsApi {
     
     fn default_window_size(&self)->DVec2{dvec2(800.,600.)}
     
+    /// Whether the OS appearance is set to dark mode, `None` where the platform doesn't say or hasn't answered yet.
+    /// Cheap to call, platforms that have to ask another process cache the answer.
+    fn prefers_dark_mode(&self) -> Option<bool> {None}
+    
     /*
     fn web_socket_open(&mut self, url: String, rec: WebSocketAutoReconnect) -> WebSocket;
     fn web_socket_send(&mut self, socket: WebSocket, data: Vec<u8>);*/
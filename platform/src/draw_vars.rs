@@ -110,6 +110,7 @@ impl LiveNew for DrawVars {
             live_type: std::any::TypeId::of::<Self>(),
             live_ignore: true,
             fields: Vec::new(),
+            variants: Vec::new(),
             type_name: id_lut!(DrawVars)
         }
     }
@@ -66,6 +66,7 @@ pub use makepad_objc_sys;
 pub use ::windows as windows;
 
 pub use makepad_futures;
+pub use makepad_image_formats;
  
 pub use {
     makepad_shader_compiler,
@@ -99,6 +100,7 @@ pub use {
         LiveTypeInfo,
         LiveTypeField,
         LiveFieldKind,
+        LiveVariantKind,
         LiveComponentInfo,
         LiveComponentRegistry,
         LivePropType,
@@ -15,13 +15,20 @@ use {
             LivePtr,
             /*LiveTokenId,*/
             LiveFileId,
+            LiveEvalFn,
+            LiveRegistry,
+            LiveMedia,
         },
-        studio::{StudioToAppVec,StudioToApp},
+        makepad_live_id::live_id,
+        makepad_math::DVec2,
+        cx_api::CxOsApi,
+        event::{Event, WindowGeomChangeEvent},
+        studio::{StudioToAppVec,StudioToApp,AppToStudio,StudioLogItem},
+        log::LogLevel,
         web_socket::WebSocketMessage,
         makepad_live_compiler::LiveTypeInfo,
         /*makepad_math::*,*/
-        cx::Cx,
-        cx::CxDependency,
+        cx::{Cx, CxDependency, OsType},
     },
 };
 
@@ -262,10 +269,14 @@ impl Cx {
         while let Ok(changes) = self.live_file_change_receiver.try_recv(){
             all_changes.extend(changes);
         }
-        if all_changes.len()>0{
+        if all_changes.len()>0 || self.live_rebinds.len()>0{
             let mut live_registry = self.live_registry.borrow_mut();
             let mut errs = Vec::new();
             live_registry.process_file_changes(all_changes, &mut errs);
+            for (module_id, with_module_id) in self.live_rebinds.drain(..){
+                live_registry.rebind_module_values(module_id, with_module_id, &mut errs);
+            }
+            let lints = Self::live_lint(&live_registry, &mut errs);
             for err in errs {
                 
                 // alright we need to output the correct error
@@ -284,6 +295,7 @@ impl Cx {
                 }
                 error!("check_live_file_watcher: Error expanding live file {}", err);
             }
+            Self::report_live_lint(&live_registry, lints);
             self.draw_shaders.reset_for_live_reload();
             true
         }
@@ -292,10 +304,27 @@ impl Cx {
         }
     }
     
+    /// Overrides the root values of the `module_id` document with those of `with_module_id`, or
+    /// restores them for `None`. The documents are re-expanded and re-applied like on a live
+    /// edit, with an `Event::LiveEdit` followed by a redraw of everything.
+    pub fn rebind_live_module(&mut self, module_id: LiveModuleId, with_module_id: Option<LiveModuleId>) {
+        self.live_rebinds.retain( | (v, _) | *v != module_id);
+        self.live_rebinds.push((module_id, with_module_id));
+    }
+    
+    /// Makes a Rust function callable from live value expressions, like `(my_fn(1.0))`.
+    /// Call it from a crate's `live_design` before the documents using it are expanded.
+    pub fn register_live_eval_fn(&mut self, ident: LiveId, eval_fn: LiveEvalFn) {
+        self.live_registry.borrow_mut().eval_fns.register(ident, eval_fn);
+    }
+    
     // ok so now what. now we should run the expansion
     pub fn live_expand(&mut self) {
         let mut errs = Vec::new();
+        // the main window doesn't exist yet, handle_live_media_change corrects this once it does
+        let media = self.live_media(self.default_window_size(), 1.0);
         let mut live_registry = self.live_registry.borrow_mut();
+        live_registry.media = media;
         /* 
         for file in &live_registry.live_files {
             log!("{}. {}", file.module_id.0, file.module_id.1);        // lets expand the f'er
@@ -303,6 +332,7 @@ impl Cx {
         //let dt = crate::profile_start();
         
         live_registry.expand_all_documents(&mut errs);
+        let lints = Self::live_lint(&live_registry, &mut errs);
         //crate::profile_end!(dt);
         
         // lets evaluate all expressions in the main module
@@ -329,6 +359,89 @@ impl Cx {
             }
             println!("Error expanding live file {}", live_registry.live_error_to_live_file_error(err));
         }
+        Self::report_live_lint(&live_registry, lints);
+    }
+    
+    fn live_media(&self, window_size: DVec2, dpi_factor: f64) -> LiveMedia {
+        LiveMedia {
+            width: window_size.x,
+            height: window_size.y,
+            dpi_factor,
+            platform: match self.os_type() {
+                OsType::Windows => live_id!(windows),
+                OsType::Macos => live_id!(macos),
+                OsType::Ios => live_id!(ios),
+                OsType::Android(_) => live_id!(android),
+                OsType::OpenHarmony(_) => live_id!(openharmony),
+                OsType::LinuxWindow(_) | OsType::LinuxDirect | OsType::LinuxHeadless => live_id!(linux),
+                OsType::Web(_) => live_id!(web),
+                OsType::Unknown => live_id!(unknown),
+            }
+        }
+    }
+    
+    /// The `@media` conditions in the live documents follow the size of the main window. When
+    /// one of them changes outcome the documents are expanded again and everything is re-applied
+    /// with an `Event::LiveEdit`, like on a live edit.
+    pub (crate) fn handle_live_media_change(&mut self, e: &WindowGeomChangeEvent) {
+        if e.window_id.id() != 0 {
+            return
+        }
+        let media = self.live_media(e.new_geom.inner_size, e.new_geom.dpi_factor);
+        let mut errs = Vec::new();
+        if !self.live_registry.borrow_mut().update_media(media, &mut errs) {
+            return
+        }
+        for err in errs {
+            error!("handle_live_media_change: Error expanding live file {}", err);
+        }
+        self.draw_shaders.reset_for_live_reload();
+        self.call_event_handler(&Event::LiveEdit);
+        self.redraw_all();
+    }
+    
+    /// Lints the expanded documents in debug builds, skipping the spans that already have an
+    /// expand error in `errs`.
+    fn live_lint(live_registry: &LiveRegistry, errs: &mut Vec<LiveError>) -> Vec<LiveError> {
+        if !cfg!(debug_assertions) {
+            return Vec::new()
+        }
+        let expand_errs = errs.len();
+        live_registry.lint_all_documents(errs);
+        errs.split_off(expand_errs)
+    }
+    
+    /// Studio gets the lint warnings as one batch so the ones fixed by a live edit disappear,
+    /// otherwise they are printed.
+    fn report_live_lint(live_registry: &LiveRegistry, lints: Vec<LiveError>) {
+        let items: Vec<StudioLogItem> = lints.into_iter().map( | lint | {
+            let lint = live_registry.live_error_to_live_file_error(lint);
+            StudioLogItem {
+                file_name: lint.file,
+                line_start: lint.span.start.line,
+                line_end: lint.span.end.line,
+                column_start: lint.span.start.column,
+                column_end: lint.span.end.column,
+                message: lint.message,
+                explanation: None,
+                level: LogLevel::Warning
+            }
+        }).collect();
+        if Cx::has_studio_web_socket() {
+            Cx::send_studio_message(AppToStudio::LiveLint(items));
+            return
+        }
+        for item in items {
+            crate::log::log_with_level(
+                &item.file_name,
+                item.line_start,
+                item.column_start,
+                item.line_end,
+                item.column_end,
+                item.message,
+                item.level
+            );
+        }
     }
     
     pub fn live_scan_dependencies(&mut self) {
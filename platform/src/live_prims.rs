@@ -45,6 +45,7 @@ macro_rules!live_primitive {
                     module_id: LiveModuleId::from_str(&module_path!()).unwrap(),
                     live_type: LiveType::of::<Self>(),
                     fields: Vec::new(),
+                    variants: Vec::new(),
                     live_ignore: true,
                     type_name: LiveId::from_str_with_lut(stringify!( $ ty)).unwrap(),
                     //kind: LiveTypeKind::Primitive
@@ -26,6 +26,7 @@ use {
                     macos_window::MacosWindow
                 },
                 apple_classes::init_apple_classes_global,
+                apple_util::{nsstring_to_string, str_to_nsstring},
                 url_session::AppleHttpRequests,
             },
             metal_xpc::start_xpc_service,
@@ -615,6 +616,14 @@ impl CxOsApi for Cx {
         crate::error!("open_url not implemented on this platform");
     }
     
+    fn prefers_dark_mode(&self) -> Option<bool> {
+        unsafe {
+            let defaults: ObjcId = msg_send![class!(NSUserDefaults), standardUserDefaults];
+            let style: ObjcId = msg_send![defaults, stringForKey: str_to_nsstring("AppleInterfaceStyle")];
+            Some(style != nil && nsstring_to_string(style) == "Dark")
+        }
+    }
+    
     /*
     fn web_socket_open(&mut self, _url: String, _rec: WebSocketAutoReconnect) -> WebSocket {
         todo!()
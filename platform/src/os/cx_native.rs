@@ -3,10 +3,12 @@ use {
         io::prelude::*,
         fs::File,
         rc::Rc,
+        sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}},
         time::{SystemTime}
     },
     crate::{
         cx::{Cx},
+        thread::SignalToUI,
     }
 };
 
@@ -19,6 +21,36 @@ pub enum EventFlow{
 
 // lets start a websocket thread
 
+/// Caches the OS dark mode preference for platforms where reading it means running a process.
+/// `refresh` reads it again on a thread and raises a ui signal when it changed.
+#[derive(Clone, Default)]
+pub struct OsDarkMode {
+    prefers_dark: Arc<Mutex<Option<bool>>>,
+    is_querying: Arc<AtomicBool>,
+}
+
+impl OsDarkMode {
+    pub fn prefers_dark(&self) -> Option<bool> {
+        *self.prefers_dark.lock().unwrap()
+    }
+    
+    pub fn refresh(&self, query: fn() -> Option<bool>) {
+        if self.is_querying.swap(true, Ordering::SeqCst) {
+            return
+        }
+        let dark_mode = self.clone();
+        std::thread::spawn(move || {
+            let prefers_dark = query();
+            let mut cached = dark_mode.prefers_dark.lock().unwrap();
+            if *cached != prefers_dark {
+                *cached = prefers_dark;
+                SignalToUI::set_ui_signal();
+            }
+            dark_mode.is_querying.store(false, Ordering::SeqCst);
+        });
+    }
+}
+
 
 impl Cx {
     
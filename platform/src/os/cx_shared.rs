@@ -186,6 +186,9 @@ impl Cx {
         self.inner_key_focus_change();
         self.handle_triggers();
         self.handle_actions();
+        if let Event::WindowGeomChange(e) = event {
+            self.handle_live_media_change(e);
+        }
     }
 
     // helpers
@@ -0,0 +1,418 @@
+use {
+    std::rc::Rc,
+    std::time::{Instant, Duration},
+    std::cell::RefCell,
+    std::path::PathBuf,
+    self::super::software::SoftwareTarget,
+    self::super::super::{
+        select_timer::SelectTimers,
+        linux_media::CxLinuxMedia
+    },
+    crate::{
+        cx_api::{CxOsOp, CxOsApi, OpenUrlInPlace},
+        makepad_math::*,
+        thread::SignalToUI,
+        event::{
+            TimerEvent,
+            Event,
+            WindowGeom,
+        },
+        pass::CxPassParent,
+        cx::{Cx, OsType,},
+        gpu_info::GpuPerformance,
+        os::cx_native::EventFlow,
+        pass::PassId,
+        makepad_image_formats::png,
+    }
+};
+
+// Runs the normal event, draw and pass pipeline without a display or gpu.
+// Window passes are rasterized on the cpu into an in memory framebuffer,
+// which can be written out as png files for every painted frame.
+//   -size=1280x720   size of the window in logical pixels
+//   -scale=1.0       dpi factor
+//   -out=dir         write every painted frame to dir/frame_0000.png
+//   -frames=n        exit after n painted frames
+
+pub struct HeadlessApp {
+    timers: SelectTimers,
+    size: DVec2,
+    dpi_factor: f64,
+    out_dir: Option<PathBuf>,
+    max_frames: Option<usize>,
+    frame: usize,
+    target: SoftwareTarget,
+}
+
+enum HeadlessEvent {
+    Paint,
+    Timer(TimerEvent),
+}
+
+impl HeadlessApp {
+    fn new() -> Self {
+        let mut size = dvec2(1280.0, 720.0);
+        let mut dpi_factor = 1.0;
+        let mut out_dir = None;
+        let mut max_frames = None;
+        for arg in std::env::args() {
+            if arg.starts_with("-size=") {
+                let mut parts = arg.trim_start_matches("-size=").split('x');
+                if let (Some(Ok(w)), Some(Ok(h))) = (parts.next().map(|v| v.parse()), parts.next().map(|v| v.parse())) {
+                    size = dvec2(w, h);
+                }
+            }
+            if arg.starts_with("-scale=") {
+                match arg.trim_start_matches("-scale=").parse::<f64>() {
+                    Ok(scale) if scale > 0.0 => dpi_factor = scale,
+                    _ => crate::error!("invalid {}, using a scale of {}", arg, dpi_factor),
+                }
+            }
+            if arg.starts_with("-out=") {
+                out_dir = Some(PathBuf::from(arg.trim_start_matches("-out=")));
+            }
+            if arg.starts_with("-frames=") {
+                max_frames = arg.trim_start_matches("-frames=").parse().ok();
+            }
+        }
+        if let Some(out_dir) = &out_dir {
+            if let Err(err) = std::fs::create_dir_all(out_dir) {
+                crate::error!("cannot create output directory {}: {}", out_dir.display(), err);
+            }
+        }
+        Self {
+            timers: SelectTimers::new(),
+            size,
+            dpi_factor,
+            out_dir,
+            max_frames,
+            frame: 0,
+            target: SoftwareTarget::default(),
+        }
+    }
+
+    fn save_frame(&mut self) {
+        if let Some(out_dir) = &self.out_dir {
+            let path = out_dir.join(format!("frame_{:04}.png", self.frame));
+            match png::encode(&self.target.to_image_buffer()) {
+                Ok(data) => if let Err(err) = std::fs::write(&path, data) {
+                    crate::error!("cannot write frame {}: {}", path.display(), err);
+                }
+                Err(err) => crate::error!("cannot encode frame {}: {}", path.display(), err),
+            }
+        }
+        self.frame += 1;
+    }
+}
+
+impl Cx {
+    pub fn event_loop(cx: Rc<RefCell<Cx >>) {
+
+        let mut cx = cx.borrow_mut();
+
+        cx.os_type = OsType::LinuxHeadless;
+        cx.gpu_info.performance = GpuPerformance::Tier1;
+
+        cx.call_event_handler(&Event::Startup);
+        cx.redraw_all();
+
+        let mut headless_app = HeadlessApp::new();
+        headless_app.timers.start_timer(0, 0.008, true);
+        let mut event_flow = EventFlow::Poll;
+        let mut timer_ids = Vec::new();
+
+        while event_flow != EventFlow::Exit {
+            if event_flow == EventFlow::Wait {
+                // nothing to paint, sleep until our ui timer fires
+                std::thread::sleep(Duration::from_secs_f64(0.008));
+            }
+            headless_app.timers.update_timers(&mut timer_ids);
+            let time = headless_app.timers.time_now();
+            for timer_id in &timer_ids {
+                cx.headless_event_callback(
+                    &mut headless_app,
+                    HeadlessEvent::Timer(TimerEvent {
+                        timer_id: *timer_id,
+                        time:Some(time)
+                    })
+                );
+            }
+            event_flow = cx.headless_event_callback(&mut headless_app, HeadlessEvent::Paint);
+        }
+    }
+
+    fn headless_event_callback(
+        &mut self,
+        headless_app: &mut HeadlessApp,
+        event: HeadlessEvent,
+    ) -> EventFlow {
+        if let EventFlow::Exit = self.handle_platform_ops(headless_app) {
+            return EventFlow::Exit
+        }
+
+        match event {
+            HeadlessEvent::Paint => {
+                if !self.new_next_frames.is_empty() {
+                    self.call_next_frame_event(headless_app.timers.time_now());
+                }
+                if self.need_redrawing() {
+                    self.call_draw_event();
+                    self.software_compile_shaders();
+                }
+                self.handle_repaint(headless_app);
+                if let Some(max_frames) = headless_app.max_frames {
+                    if headless_app.frame >= max_frames {
+                        return EventFlow::Exit
+                    }
+                }
+            }
+            HeadlessEvent::Timer(e) => {
+                if e.timer_id == 0 {
+                    if SignalToUI::check_and_clear_ui_signal() {
+                        self.handle_media_signals();
+                        self.call_event_handler(&Event::Signal);
+                    }
+                    self.handle_action_receiver();
+                }
+                else {
+                    self.call_event_handler(&Event::Timer(e))
+                }
+
+                if self.handle_live_edit() {
+                    self.call_event_handler(&Event::LiveEdit);
+                    self.redraw_all();
+                }
+            }
+        }
+        if self.any_passes_dirty() || self.need_redrawing() || !self.new_next_frames.is_empty() {
+            EventFlow::Poll
+        } else {
+            EventFlow::Wait
+        }
+    }
+
+    pub fn draw_pass_to_headless_framebuffer(
+        &mut self,
+        pass_id: PassId,
+        headless_app: &mut HeadlessApp,
+    ) {
+        self.draw_pass_to_target(pass_id, &mut headless_app.target);
+        headless_app.save_frame();
+    }
+
+    pub (crate) fn handle_repaint(&mut self, headless_app: &mut HeadlessApp) {
+        let mut passes_todo = Vec::new();
+        self.compute_pass_repaint_order(&mut passes_todo);
+        self.repaint_id += 1;
+        for pass_id in &passes_todo {
+            self.passes[*pass_id].set_time(headless_app.timers.time_now() as f32);
+            match self.passes[*pass_id].parent.clone() {
+                CxPassParent::Window(_window_id) => {
+                    self.draw_pass_to_headless_framebuffer(*pass_id, headless_app);
+                }
+                CxPassParent::Pass(_) => {
+                    self.draw_pass_to_magic_texture(*pass_id);
+                },
+                CxPassParent::None => {
+                    self.draw_pass_to_magic_texture(*pass_id);
+                }
+            }
+        }
+    }
+
+    fn handle_platform_ops(&mut self, headless_app: &mut HeadlessApp) -> EventFlow {
+        while let Some(op) = self.platform_ops.pop() {
+            match op {
+                CxOsOp::CreateWindow(window_id) => {
+                    let window = &mut self.windows[window_id];
+                    window.window_geom = WindowGeom {
+                        dpi_factor: headless_app.dpi_factor,
+                        can_fullscreen: false,
+                        xr_is_presenting: false,
+                        is_fullscreen: true,
+                        is_topmost: true,
+                        position: dvec2(0.0, 0.0),
+                        inner_size: headless_app.size,
+                        outer_size: headless_app.size
+                    };
+                    window.is_created = true;
+                },
+                CxOsOp::Quit => {
+                    return EventFlow::Exit
+                },
+                CxOsOp::StartTimer {timer_id, interval, repeats} => {
+                    headless_app.timers.start_timer(timer_id, interval, repeats);
+                },
+                CxOsOp::StopTimer(timer_id) => {
+                    headless_app.timers.stop_timer(timer_id);
+                },
+                _ => ()
+            }
+        }
+        EventFlow::Poll
+    }
+}
+
+impl CxOsApi for Cx {
+    fn init_cx_os(&mut self) {
+        self.live_expand();
+        if !Self::has_studio_web_socket() {
+            self.start_disk_live_file_watcher(100);
+        }
+        self.live_scan_dependencies();
+        self.native_load_dependencies();
+    }
+
+    fn spawn_thread<F>(&mut self, f: F) where F: FnOnce() + Send + 'static {
+        std::thread::spawn(f);
+    }
+
+    fn open_url(&mut self, _url:&str, _in_place:OpenUrlInPlace){
+        crate::error!("open_url not implemented on this platform");
+    }
+
+    fn seconds_since_app_start(&self)->f64{
+        Instant::now().duration_since(self.os.start_time).as_secs_f64()
+    }
+
+}
+
+pub struct CxOs {
+    pub (crate) media: CxLinuxMedia,
+    pub (crate) start_time: Instant,
+}
+
+impl Default for CxOs {
+    fn default() -> Self {
+        Self {
+            start_time: Instant::now(),
+            media: Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            makepad_live_compiler::*,
+            makepad_live_id::*,
+            makepad_shader_compiler::{
+                shader_registry::{ShaderRegistry, DrawShaderQuery},
+                shader_ast::{DrawShaderPtr, ShaderTy},
+                interpret::ShaderInterpreter,
+            },
+            draw_shader::{DrawShaderInputs, DrawShaderInputPacking},
+            geometry::CxGeometry,
+        },
+    };
+
+    // covers the left half of the target
+    const DRAW_HALF_SOURCE: &str = r#"
+        DrawHalf = {{DrawHalf}} {
+            geometry: {{GeometryQuad2D}} {}
+
+            fn vertex(self) -> vec4 {
+                return vec4(self.geom_pos * self.rect_size * 2.0 - 1.0, 0.0, 1.0)
+            }
+
+            fn pixel(self) -> vec4 {
+                return #f00
+            }
+        }
+    "#;
+
+    struct DrawHalf;
+    struct GeometryQuad2D;
+
+    fn live_type_info<T: 'static>(type_name: LiveId, module_id: LiveModuleId) -> LiveTypeInfo {
+        LiveTypeInfo {
+            live_type: LiveType::of::<T>(),
+            type_name,
+            module_id,
+            live_ignore: false,
+            fields: Vec::new(),
+            variants: Vec::new()
+        }
+    }
+
+    #[test]
+    fn writes_rendered_frames_as_png() {
+        let module_id = LiveModuleId::from_str("test::draw_half").unwrap();
+        let mut live_registry = LiveRegistry::default();
+        if let Err(err) = live_registry.register_live_file(
+            "draw_half.rs",
+            "",
+            module_id,
+            DRAW_HALF_SOURCE.to_string(),
+            vec![
+                live_type_info::<DrawHalf>(live_id!(DrawHalf), module_id),
+                live_type_info::<GeometryQuad2D>(live_id!(GeometryQuad2D), module_id),
+            ],
+            TextPos::default()
+        ) {
+            panic!("{}", err);
+        }
+        let mut errors = Vec::new();
+        live_registry.expand_all_documents(&mut errors);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let mut shader_registry = ShaderRegistry::new(true);
+        let draw_shader_ptr = DrawShaderPtr(live_registry.module_id_and_name_to_ptr(module_id, live_id!(DrawHalf)).unwrap());
+        let result = shader_registry.analyse_draw_shader(&live_registry, draw_shader_ptr, | _, _, span, draw_shader_query, _, draw_shader_def | {
+            match draw_shader_query {
+                DrawShaderQuery::DrawShader => {
+                    draw_shader_def.add_instance(live_id!(rect_size), ShaderTy::Vec2, span, LiveFieldKind::Calc);
+                }
+                DrawShaderQuery::Geometry => {
+                    draw_shader_def.add_geometry(live_id!(geom_pos), ShaderTy::Vec2, span);
+                }
+            }
+        });
+        if let Err(err) = result {
+            panic!("{}", live_registry.live_error_to_live_file_error(err));
+        }
+        let draw_shader_def = shader_registry.draw_shader_defs.get(&draw_shader_ptr).unwrap();
+        let mut interpreter = ShaderInterpreter::new(&live_registry, &shader_registry, draw_shader_def);
+
+        let mut geometries = DrawShaderInputs::new(DrawShaderInputPacking::Attribute);
+        geometries.push(live_id!(geom_pos), ShaderTy::Vec2, None);
+        let mut instances = DrawShaderInputs::new(DrawShaderInputPacking::Attribute);
+        instances.push(live_id!(rect_size), ShaderTy::Vec2, None);
+        let geometry = CxGeometry {
+            indices: vec![0, 1, 2, 2, 3, 0],
+            vertices: vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0],
+            dirty: false,
+            os: Default::default(),
+        };
+
+        let out_dir = std::env::temp_dir().join(format!("makepad_headless_{}", std::process::id()));
+        std::fs::create_dir_all(&out_dir).unwrap();
+        let mut headless_app = HeadlessApp {
+            timers: SelectTimers::new(),
+            size: dvec2(4.0, 2.0),
+            dpi_factor: 1.0,
+            out_dir: Some(out_dir.clone()),
+            max_frames: None,
+            frame: 0,
+            target: SoftwareTarget::default(),
+        };
+        headless_app.target.resize(4, 2, false);
+        headless_app.target.clear_color(vec4(0.0, 0.0, 0.5, 0.5));
+        if let Err(err) = headless_app.target.draw(&mut interpreter, &geometries, &instances, &geometry, &[0.5, 1.0]) {
+            panic!("{}", live_registry.live_error_to_live_file_error(err));
+        }
+        headless_app.save_frame();
+        headless_app.save_frame();
+
+        let data = std::fs::read(out_dir.join("frame_0000.png")).unwrap();
+        assert!(out_dir.join("frame_0001.png").exists());
+        std::fs::remove_dir_all(&out_dir).unwrap();
+        let image = png::decode(&data).unwrap();
+        assert_eq!((image.width, image.height), (4, 2));
+        // the background is stored with straight alpha
+        let (red, blue) = (0xffff0000, 0x800000ff);
+        assert_eq!(image.data, vec![red, red, blue, blue, red, red, blue, blue]);
+    }
+}
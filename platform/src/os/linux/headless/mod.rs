@@ -0,0 +1,2 @@
+pub mod linux_headless;
+pub mod software;
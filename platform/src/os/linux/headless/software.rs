@@ -0,0 +1,576 @@
+use {
+    std::{
+        mem,
+        rc::Rc,
+    },
+    crate::{
+        makepad_shader_compiler::{
+            shader_ast::{Ident, DrawShaderFieldKind, DrawShaderPtr},
+            interpret::{ShaderInterpreter, CpuTexture},
+        },
+        makepad_live_compiler::LiveError,
+        cx::Cx,
+        texture::{Texture, TextureFormat, TexturePixel, TextureUpdated, CxTexture},
+        makepad_math::{DVec2, Vec4, vec4},
+        pass::{PassClearColor, PassClearDepth, PassId},
+        draw_list::DrawListId,
+        draw_shader::DrawShaderInputs,
+        geometry::CxGeometry,
+        makepad_image_formats::ImageBuffer,
+    },
+};
+
+// A software rasterizer that stands in for opengl.rs in the headless backend.
+// Draw shaders are not compiled, they run through the shader interpreter: the vertex
+// function once per geometry vertex and instance, the pixel function once per covered pixel.
+// Slow, but it needs nothing but the cpu, which is what screenshot tests on CI want.
+
+#[derive(Default, Clone)]
+pub struct SoftwareTarget {
+    pub color: CpuTexture,
+    pub depth: Option<CpuTexture>,
+}
+
+impl SoftwareTarget {
+    pub fn resize(&mut self, width: usize, height: usize, with_depth: bool) {
+        if self.color.width != width || self.color.height != height {
+            self.color = CpuTexture::new(width, height);
+            self.depth = None;
+        }
+        if with_depth && self.depth.is_none() {
+            self.depth = Some(CpuTexture::new(width, height));
+        }
+    }
+
+    pub fn clear_color(&mut self, color: Vec4) {
+        self.color.pixels.fill(color);
+    }
+
+    pub fn clear_depth(&mut self, depth: f32) {
+        if let Some(depth_texture) = &mut self.depth {
+            depth_texture.pixels.fill(vec4(depth, 0.0, 0.0, 0.0));
+        }
+    }
+
+    // straight alpha argb, which is what the image encoders want
+    pub fn to_image_buffer(&self) -> ImageBuffer {
+        let mut image = ImageBuffer::new(self.color.width, self.color.height);
+        for (out, pixel) in image.data.iter_mut().zip(&self.color.pixels) {
+            let alpha = pixel.w.clamp(0.0, 1.0);
+            let rgb = if alpha > 0.0 {[pixel.x / alpha, pixel.y / alpha, pixel.z / alpha]} else {[0.0; 3]};
+            let [r, g, b] = rgb.map( | c | (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u32);
+            *out = (((alpha * 255.0 + 0.5) as u32) << 24) | (r << 16) | (g << 8) | b;
+        }
+        image
+    }
+
+    pub (crate) fn draw(
+        &mut self,
+        interpreter: &mut ShaderInterpreter,
+        geometries: &DrawShaderInputs,
+        instances: &DrawShaderInputs,
+        geometry: &CxGeometry,
+        instance_data: &[f32],
+    ) -> Result<(), LiveError> {
+        // geometry attributes and varyings are what changes across a triangle
+        let interpolated: Vec<Ident> = interpreter.draw_shader_def.fields.iter().filter_map( | field | match field.kind {
+            DrawShaderFieldKind::Geometry {..} | DrawShaderFieldKind::Varying {..} => Some(field.ident),
+            _ => None
+        }).collect();
+        let vertex_count = geometry.vertices.len().checked_div(geometries.total_slots).unwrap_or(0);
+        let (width, height) = (self.color.width as f32, self.color.height as f32);
+
+        for instance in instance_data.chunks_exact(instances.total_slots) {
+            set_inputs(interpreter, instances, instance);
+
+            let mut vertices = Vec::with_capacity(vertex_count);
+            for vertex in geometry.vertices.chunks_exact(geometries.total_slots) {
+                set_inputs(interpreter, geometries, vertex);
+                let clip = interpreter.vertex() ?;
+                let values: Vec<Vec<f32>> = interpolated.iter().map( | ident | interpreter.fields[ident].to_floats()).collect();
+                vertices.push(RasterVertex {
+                    // clip space to pixels, y points down and row 0 is the top like metal and dx
+                    x: (clip.x / clip.w * 0.5 + 0.5) * width,
+                    y: (0.5 - clip.y / clip.w * 0.5) * height,
+                    z: clip.z / clip.w,
+                    inv_w: 1.0 / clip.w,
+                    values
+                });
+            }
+
+            for triangle in geometry.indices.chunks_exact(3) {
+                let mut tri = [
+                    &vertices[triangle[0] as usize],
+                    &vertices[triangle[1] as usize],
+                    &vertices[triangle[2] as usize]
+                ];
+                if tri.iter().any( | v | v.inv_w <= 0.0 || !v.inv_w.is_finite() || !v.x.is_finite() || !v.y.is_finite()) {
+                    continue;
+                }
+                let mut area = edge(tri[0], tri[1], tri[2].x, tri[2].y);
+                if area == 0.0 {
+                    continue;
+                }
+                // no culling, just flip the winding so inside is positive
+                if area < 0.0 {
+                    tri.swap(1, 2);
+                    area = -area;
+                }
+                let min_x = tri.iter().map( | v | v.x).fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+                let max_x = tri.iter().map( | v | v.x).fold(f32::MIN, f32::max).ceil().min(width) as usize;
+                let min_y = tri.iter().map( | v | v.y).fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+                let max_y = tri.iter().map( | v | v.y).fold(f32::MIN, f32::max).ceil().min(height) as usize;
+                let edges = [(tri[1], tri[2]), (tri[2], tri[0]), (tri[0], tri[1])];
+
+                for py in min_y..max_y {
+                    for px in min_x..max_x {
+                        let (sx, sy) = (px as f32 + 0.5, py as f32 + 0.5);
+                        let mut bary = [0.0f32; 3];
+                        let mut inside = true;
+                        for (i, (a, b)) in edges.iter().enumerate() {
+                            let e = edge(a, b, sx, sy);
+                            // top-left fill rule, so pixels on an edge shared by two triangles are drawn once
+                            if e < 0.0 || (e == 0.0 && !is_top_left(a, b)) {
+                                inside = false;
+                                break;
+                            }
+                            bary[i] = e / area;
+                        }
+                        if !inside {
+                            continue;
+                        }
+                        let index = py * self.color.width + px;
+                        let z = bary[0] * tri[0].z + bary[1] * tri[1].z + bary[2] * tri[2].z;
+                        if let Some(depth) = &self.depth {
+                            if z > depth.pixels[index].x {
+                                continue;
+                            }
+                        }
+                        // perspective correct weights
+                        let persp = [bary[0] * tri[0].inv_w, bary[1] * tri[1].inv_w, bary[2] * tri[2].inv_w];
+                        let sum = persp[0] + persp[1] + persp[2];
+                        for (i, ident) in interpolated.iter().enumerate() {
+                            let floats: Vec<f32> = (0..tri[0].values[i].len()).map( | c | {
+                                (persp[0] * tri[0].values[i][c] + persp[1] * tri[1].values[i][c] + persp[2] * tri[2].values[i][c]) / sum
+                            }).collect();
+                            let value = interpreter.fields[ident].with_floats(&floats);
+                            interpreter.fields.insert(*ident, value);
+                        }
+                        let src = interpreter.pixel() ?;
+                        let src = vec4(src.x.clamp(0.0, 1.0), src.y.clamp(0.0, 1.0), src.z.clamp(0.0, 1.0), src.w.clamp(0.0, 1.0));
+                        // premultiplied alpha, the same blend mode as the gpu backends
+                        let dst = self.color.pixels[index];
+                        self.color.pixels[index] = src + dst * (1.0 - src.w);
+                        if let Some(depth) = &mut self.depth {
+                            depth.pixels[index].x = z;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct RasterVertex {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+    values: Vec<Vec<f32>>,
+}
+
+fn edge(a: &RasterVertex, b: &RasterVertex, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+fn is_top_left(a: &RasterVertex, b: &RasterVertex) -> bool {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    dy < 0.0 || (dy == 0.0 && dx > 0.0)
+}
+
+// writes the inputs packed in a buffer into the interpreter fields of the same name
+fn set_inputs(interpreter: &mut ShaderInterpreter, inputs: &DrawShaderInputs, data: &[f32]) {
+    for input in &inputs.inputs {
+        if input.offset + input.slots > data.len() {
+            continue;
+        }
+        let ident = Ident(input.id);
+        if let Some(value) = interpreter.fields.get(&ident) {
+            let value = value.with_floats(&data[input.offset..input.offset + input.slots]);
+            interpreter.fields.insert(ident, value);
+        }
+    }
+}
+
+impl Cx {
+
+    pub (crate) fn render_view(
+        &mut self,
+        pass_id: PassId,
+        draw_list_id: DrawListId,
+        zbias: &mut f32,
+        zbias_step: f32,
+        target: &mut SoftwareTarget,
+    ) {
+        let draw_items_len = self.draw_lists[draw_list_id].draw_items.len();
+        self.draw_lists[draw_list_id].uniform_view_transform(&crate::makepad_math::Mat4::identity());
+
+        for draw_item_id in 0..draw_items_len {
+            if let Some(sub_list_id) = self.draw_lists[draw_list_id].draw_items[draw_item_id].kind.sub_list() {
+                self.render_view(
+                    pass_id,
+                    sub_list_id,
+                    zbias,
+                    zbias_step,
+                    target,
+                );
+                continue;
+            }
+
+            let draw_item = &mut self.draw_lists[draw_list_id].draw_items[draw_item_id];
+            let draw_call = if let Some(draw_call) = draw_item.kind.draw_call_mut() {
+                draw_call
+            }else {
+                continue;
+            };
+            let sh = &self.draw_shaders.shaders[draw_call.draw_shader.draw_shader_id];
+            if sh.os_shader_id.is_none() { // shader didnt compile somehow
+                continue;
+            }
+
+            // update the zbias uniform if we have it.
+            draw_call.instance_dirty = false;
+            draw_call.uniforms_dirty = false;
+            draw_call.draw_uniforms.set_zbias(*zbias);
+            *zbias += zbias_step;
+
+            let texture_ids: Vec<_> = sh.mapping.textures.iter().enumerate().map( | (i, _) | {
+                draw_call.texture_slots[i].as_ref().map( | texture | texture.texture_id())
+            }).collect();
+            if let Some(geometry_id) = draw_call.geometry_id {
+                let geometry = &mut self.geometries[geometry_id];
+                if geometry.dirty {
+                    geometry.os.free_resources();
+                    geometry.dirty = false;
+                }
+            }
+            for texture_id in texture_ids.iter().flatten() {
+                let cxtexture = &mut self.textures[*texture_id];
+                if cxtexture.format.is_vec() {
+                    cxtexture.update_vec_texture();
+                }
+            }
+
+            let draw_list = &self.draw_lists[draw_list_id];
+            let draw_item = &draw_list.draw_items[draw_item_id];
+            let draw_call = draw_item.kind.draw_call().unwrap();
+            let sh = &self.draw_shaders.shaders[draw_call.draw_shader.draw_shader_id];
+            let geometry = if let Some(geometry_id) = draw_call.geometry_id {
+                &self.geometries[geometry_id]
+            }
+            else {
+                continue;
+            };
+            let instances = draw_item.instances.as_ref().unwrap();
+            if sh.mapping.instances.total_slots == 0 || instances.len() < sh.mapping.instances.total_slots {
+                continue;
+            }
+            let draw_shader_def = if let Some(def) = self.shader_registry.draw_shader_defs.get(&draw_call.draw_shader.draw_shader_ptr) {
+                def
+            }
+            else {
+                continue;
+            };
+
+            let live_registry = self.live_registry.borrow();
+            let mut interpreter = ShaderInterpreter::new(&live_registry, &self.shader_registry, draw_shader_def);
+            set_inputs(&mut interpreter, &sh.mapping.pass_uniforms, self.passes[pass_id].pass_uniforms.as_slice());
+            set_inputs(&mut interpreter, &sh.mapping.view_uniforms, draw_list.draw_list_uniforms.as_slice());
+            set_inputs(&mut interpreter, &sh.mapping.draw_uniforms, draw_call.draw_uniforms.as_slice());
+            set_inputs(&mut interpreter, &sh.mapping.user_uniforms, &draw_call.user_uniforms);
+            set_inputs(&mut interpreter, &sh.mapping.live_uniforms, &sh.mapping.live_uniforms_buf);
+            for (texture_input, texture_id) in sh.mapping.textures.iter().zip(texture_ids.iter()) {
+                // an unbound texture samples as transparent black, like it does on the gpu
+                let pixels = texture_id.map_or_else( | | Rc::new(CpuTexture::default()), | id | self.textures[id].os.pixels.clone());
+                interpreter.set_texture(texture_input.id, pixels);
+            }
+
+            if let Err(err) = target.draw(&mut interpreter, &sh.mapping.geometries, &sh.mapping.instances, geometry, instances) {
+                crate::error!("software renderer: {}", live_registry.live_error_to_live_file_error(err));
+            }
+        }
+    }
+
+    pub fn setup_render_pass(&mut self, pass_id: PassId,) -> Option<DVec2> {
+
+        let dpi_factor = self.passes[pass_id].dpi_factor.unwrap();
+        let pass_rect = self.get_pass_rect(pass_id, dpi_factor).unwrap();
+        self.passes[pass_id].paint_dirty = false;
+
+        if pass_rect.size.x <0.5 || pass_rect.size.y < 0.5 {
+            return None
+        }
+
+        self.passes[pass_id].set_matrix(pass_rect.pos, pass_rect.size);
+        self.passes[pass_id].set_dpi_factor(dpi_factor);
+        Some(pass_rect.size)
+    }
+
+    // draws a window pass into a framebuffer owned by the platform layer
+    pub (crate) fn draw_pass_to_target(&mut self, pass_id: PassId, target: &mut SoftwareTarget) {
+        let draw_list_id = self.passes[pass_id].main_draw_list_id.unwrap();
+        let pass_size = if let Some(pass_size) = self.setup_render_pass(pass_id) {
+            pass_size
+        }
+        else {
+            return
+        };
+        let dpi_factor = self.passes[pass_id].dpi_factor.unwrap();
+        let size = pass_size * dpi_factor;
+        target.resize(size.x as usize, size.y as usize, true);
+
+        if !self.passes[pass_id].dont_clear {
+            let clear_color = if self.passes[pass_id].color_textures.is_empty() {
+                self.passes[pass_id].clear_color
+            }
+            else {
+                match self.passes[pass_id].color_textures[0].clear_color {
+                    PassClearColor::InitWith(color) => color,
+                    PassClearColor::ClearWith(color) => color
+                }
+            };
+            let clear_depth = match self.passes[pass_id].clear_depth {
+                PassClearDepth::InitWith(depth) => depth,
+                PassClearDepth::ClearWith(depth) => depth
+            };
+            target.clear_color(clear_color);
+            target.clear_depth(clear_depth);
+        }
+
+        let mut zbias = 0.0;
+        let zbias_step = self.passes[pass_id].zbias_step;
+        self.render_view(pass_id, draw_list_id, &mut zbias, zbias_step, target);
+    }
+
+    pub fn draw_pass_to_texture(&mut self, pass_id: PassId, texture: &Texture) {
+        self.draw_pass_to_texture_inner(pass_id, Some(texture))
+    }
+
+    pub fn draw_pass_to_magic_texture(&mut self, pass_id: PassId) {
+        self.draw_pass_to_texture_inner(pass_id, None)
+    }
+
+    fn draw_pass_to_texture_inner(
+        &mut self,
+        pass_id: PassId,
+        maybe_texture: Option<&Texture>,
+    ) {
+        let draw_list_id = self.passes[pass_id].main_draw_list_id.unwrap();
+
+        let pass_size = if let Some(pz) = self.setup_render_pass(pass_id) {
+            pz
+        }
+        else {
+            return
+        };
+
+        let dpi_factor = self.passes[pass_id].dpi_factor.unwrap();
+        let size = pass_size * dpi_factor;
+        let (width, height) = (size.x as usize, size.y as usize);
+
+        // we only rasterize into a single color attachment
+        let color_texture = if let Some(texture) = maybe_texture {
+            crate::pass::CxPassColorTexture {
+                clear_color: PassClearColor::ClearWith(self.passes[pass_id].clear_color),
+                texture: texture.clone(),
+            }
+        }
+        else if let Some(color_texture) = self.passes[pass_id].color_textures.first() {
+            color_texture.clone()
+        }
+        else {
+            return
+        };
+
+        let color_id = color_texture.texture.texture_id();
+        let cxtexture = &mut self.textures[color_id];
+        cxtexture.update_render_target(width, height);
+        let clear_color = match color_texture.clear_color {
+            PassClearColor::InitWith(clear_color) => if cxtexture.take_initial() {Some(clear_color)} else {None},
+            PassClearColor::ClearWith(clear_color) => Some(clear_color)
+        };
+        let mut target = SoftwareTarget {
+            color: Rc::unwrap_or_clone(mem::take(&mut cxtexture.os.pixels)),
+            depth: None
+        };
+        if let Some(clear_color) = clear_color {
+            target.clear_color(clear_color);
+        }
+
+        let depth_id = self.passes[pass_id].depth_texture.as_ref().map( | texture | texture.texture_id());
+        if let Some(depth_id) = depth_id {
+            let cxtexture = &mut self.textures[depth_id];
+            cxtexture.update_depth_stencil(width, height);
+            target.depth = Some(Rc::unwrap_or_clone(mem::take(&mut cxtexture.os.pixels)));
+            match self.passes[pass_id].clear_depth {
+                PassClearDepth::InitWith(clear_depth) => if self.textures[depth_id].take_initial() {
+                    target.clear_depth(clear_depth);
+                },
+                PassClearDepth::ClearWith(clear_depth) => target.clear_depth(clear_depth)
+            }
+        }
+
+        let mut zbias = 0.0;
+        let zbias_step = self.passes[pass_id].zbias_step;
+        self.render_view(pass_id, draw_list_id, &mut zbias, zbias_step, &mut target);
+
+        self.textures[color_id].os.pixels = Rc::new(target.color);
+        if let (Some(depth_id), Some(depth)) = (depth_id, target.depth) {
+            self.textures[depth_id].os.pixels = Rc::new(depth);
+        }
+    }
+
+    // there is nothing to compile, we only remember which draw shaders can be interpreted
+    pub fn software_compile_shaders(&mut self) {
+        for draw_shader_ptr in &self.draw_shaders.compile_set {
+            if let Some(item) = self.draw_shaders.ptr_to_item.get(draw_shader_ptr) {
+                let cx_shader = &mut self.draw_shaders.shaders[item.draw_shader_id];
+                if !self.shader_registry.draw_shader_defs.contains_key(draw_shader_ptr) {
+                    continue;
+                }
+                cx_shader.os_shader_id = Some(self.draw_shaders.os_shaders.len());
+                self.draw_shaders.os_shaders.push(CxOsDrawShader {
+                    draw_shader_ptr: *draw_shader_ptr
+                });
+            }
+        }
+        self.draw_shaders.compile_set.clear();
+    }
+}
+
+#[derive(Clone)]
+pub struct CxOsDrawShader {
+    pub draw_shader_ptr: DrawShaderPtr,
+}
+
+#[derive(Clone, Default)]
+pub struct CxOsGeometry {
+}
+
+impl CxOsGeometry{
+    pub fn free_resources(&mut self){
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CxOsView {
+}
+
+#[derive(Default, Clone)]
+pub struct CxOsDrawCall {
+}
+
+impl CxOsDrawCall {
+    pub fn free_resources(&mut self){
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CxOsTexture {
+    pub pixels: Rc<CpuTexture>,
+}
+
+impl CxTexture {
+
+    // converts the texture data to floats so the interpreter can sample it
+    pub fn update_vec_texture(&mut self) {
+        let realloc = self.alloc_vec();
+        let updated = self.take_updated();
+        if !realloc && updated.is_empty() {
+            return;
+        }
+        let (width, height) = self.format.vec_width_height().unwrap();
+        let rect = match updated {
+            TextureUpdated::Partial(rect) if !realloc && self.os.pixels.width == width && self.os.pixels.height == height => {
+                (rect.origin.x, rect.origin.y, rect.size.width, rect.size.height)
+            }
+            _ => {
+                self.os.pixels = Rc::new(CpuTexture::new(width, height));
+                (0, 0, width, height)
+            }
+        };
+        let pixels = Rc::make_mut(&mut self.os.pixels);
+        for y in rect.1..(rect.1 + rect.3).min(height) {
+            for x in rect.0..(rect.0 + rect.2).min(width) {
+                let index = y * width + x;
+                pixels.pixels[index] = match &self.format {
+                    TextureFormat::VecBGRAu8_32 {data, ..} | TextureFormat::VecMipBGRAu8_32 {data, ..} => {
+                        data.as_ref().and_then( | data | data.get(index)).map_or(Vec4::default(), | v | {
+                            vec4(
+                                ((v >> 16) & 0xff) as f32 / 255.0,
+                                ((v >> 8) & 0xff) as f32 / 255.0,
+                                (v & 0xff) as f32 / 255.0,
+                                ((v >> 24) & 0xff) as f32 / 255.0
+                            )
+                        })
+                    }
+                    TextureFormat::VecRGBAf32 {data, ..} => {
+                        data.as_ref().and_then( | data | data.get(index * 4..index * 4 + 4)).map_or(Vec4::default(), | v | {
+                            vec4(v[0], v[1], v[2], v[3])
+                        })
+                    }
+                    TextureFormat::VecRu8 {data, unpack_row_length, ..} => {
+                        let index = y * unpack_row_length.unwrap_or(width) + x;
+                        data.as_ref().and_then( | data | data.get(index)).map_or(Vec4::default(), | v | {
+                            vec4(*v as f32 / 255.0, 0.0, 0.0, 1.0)
+                        })
+                    }
+                    TextureFormat::VecRGu8 {data, unpack_row_length, ..} => {
+                        let index = y * unpack_row_length.unwrap_or(width) + x;
+                        data.as_ref().and_then( | data | data.get(index * 2..index * 2 + 2)).map_or(Vec4::default(), | v | {
+                            vec4(v[0] as f32 / 255.0, v[1] as f32 / 255.0, 0.0, 1.0)
+                        })
+                    }
+                    TextureFormat::VecRf32 {data, ..} => {
+                        data.as_ref().and_then( | data | data.get(index)).map_or(Vec4::default(), | v | {
+                            vec4(*v, 0.0, 0.0, 1.0)
+                        })
+                    }
+                    _ => Vec4::default()
+                };
+            }
+        }
+    }
+
+    pub fn update_render_target(&mut self, width: usize, height: usize) {
+        if self.alloc_render(width, height) {
+            let alloc = self.alloc.as_ref().unwrap();
+            match &alloc.pixel {
+                TexturePixel::BGRAu8 | TexturePixel::RGBAf16 | TexturePixel::RGBAf32 => {
+                    self.os.pixels = Rc::new(CpuTexture::new(width, height));
+                }
+                _ => panic!()
+            }
+        }
+    }
+
+    fn update_depth_stencil(&mut self, width: usize, height: usize) {
+        if self.alloc_depth(width, height) {
+            self.os.pixels = Rc::new(CpuTexture::new(width, height));
+        }
+    }
+
+    pub fn free_previous_resources(&mut self){
+        self.previous_platform_resource.take();
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct CxOsPass {
+}
+
+impl CxOsPass{
+    pub fn free_resources(&mut self){
+    }
+}
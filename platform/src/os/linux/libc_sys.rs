@@ -30,6 +30,9 @@ type size_t = usize;
 pub const FD_SETSIZE: usize = 1024;
 pub const EPIPE: c_int = 32;
 pub const O_RDWR: c_int = 2;
+pub const O_NONBLOCK: c_int = 0o4000;
+pub const F_GETFL: c_int = 3;
+pub const F_SETFL: c_int = 4;
 
 #[repr(C)]
 pub struct fd_set {
@@ -47,6 +50,7 @@ extern "C"{
     pub fn close(fd: c_int) -> c_int;
     pub fn free(arg1: *mut c_void);
     pub fn pipe(fds: *mut c_int) -> c_int;
+    pub fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
     pub fn select(
         nfds: c_int,
         readfds: *mut fd_set,
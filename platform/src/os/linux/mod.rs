@@ -1,15 +1,22 @@
-#[cfg(not(any(linux_direct, target_env="ohos", target_os="android")))]
+#[cfg(not(any(linux_direct, linux_headless, target_env="ohos", target_os="android")))]
 pub mod x11; 
 
+#[cfg(not(any(linux_direct, linux_headless, target_env="ohos", target_os="android")))]
+pub mod wayland;
+
 #[cfg(linux_direct)]
 pub mod direct;
 
+#[cfg(linux_headless)]
+pub mod headless;
+
 #[cfg(target_env="ohos")]
 pub mod open_harmony;
 
 pub mod egl_sys;
 pub mod gl_sys;
 pub mod libc_sys;
+#[cfg(not(linux_headless))]
 pub mod opengl;
 pub mod module_loader;
 
@@ -44,7 +51,7 @@ pub mod android;
 #[cfg(target_os="android")]
 pub(crate) use self::android::android::CxOs;
 
-#[cfg(not(any(linux_direct, target_os="android", target_env="ohos")))]
+#[cfg(not(any(linux_direct, linux_headless, target_os="android", target_env="ohos")))]
 pub(crate) use self::x11::linux_x11::*;
 
 #[cfg(target_env="ohos")]
@@ -53,6 +60,10 @@ pub(crate) use self::open_harmony::open_harmony::*;
 #[cfg(linux_direct)]
 pub(crate) use self::direct::linux_direct::*;
 
+#[cfg(linux_headless)]
+pub(crate) use self::headless::{linux_headless::*, software::*};
+
+#[cfg(not(linux_headless))]
 pub(crate) use self::opengl::*;
 
 #[cfg(not(any(target_os="android", target_env="ohos")))]
@@ -0,0 +1,333 @@
+use {
+    std::cell::RefCell,
+    std::rc::Rc,
+    self::super::{
+        opengl_wayland::OpenglWindow,
+        wayland_app::*,
+        wayland_event::*,
+        super::{
+            egl_sys,
+            x11::{opengl_x11::OpenglCx, linux_x11::query_prefers_dark_mode},
+        },
+    },
+    crate::{
+        cx_api::CxOsOp,
+        makepad_math::dvec2,
+        makepad_live_id::*,
+        thread::SignalToUI,
+        event::Event,
+        pass::CxPassParent,
+        cx::Cx,
+        os::cx_native::EventFlow,
+    }
+};
+
+impl Cx {
+    // runs the app on the wayland compositor from WAYLAND_DISPLAY,
+    // returns false without touching the cx if we can't connect to it
+    pub(crate) fn wayland_event_loop(cx: Rc<RefCell<Cx >>) -> bool {
+        let opengl_windows = Rc::new(RefCell::new(Vec::new()));
+        let connected = init_wayland_app_global(Box::new({
+            let cx = cx.clone();
+            move | wayland_app,
+            event | {
+                let mut cx = cx.borrow_mut();
+                let mut opengl_windows = opengl_windows.borrow_mut();
+                cx.wayland_event_callback(wayland_app, event, &mut opengl_windows)
+            }
+        }));
+        if !connected {
+            return false
+        }
+
+        cx.borrow_mut().os.opengl_cx = Some(unsafe {
+            OpenglCx::from_egl_platform_display(
+                egl_sys::EGL_PLATFORM_WAYLAND_KHR,
+                get_wayland_app_global().display,
+            )
+        });
+
+        cx.borrow_mut().call_event_handler(&Event::Startup);
+        cx.borrow_mut().redraw_all();
+        get_wayland_app_global().start_timer(0, 0.008, true);
+        get_wayland_app_global().event_loop();
+        true
+    }
+
+    fn wayland_event_callback(
+        &mut self,
+        wayland_app: &mut WaylandApp,
+        event: WaylandEvent,
+        opengl_windows: &mut Vec<OpenglWindow>
+    ) -> EventFlow {
+        if let EventFlow::Exit = self.wayland_handle_platform_ops(opengl_windows, wayland_app) {
+            return EventFlow::Exit
+        }
+
+        let mut paint_dirty = false;
+
+        match event {
+            WaylandEvent::AppGotFocus => {
+                for window in opengl_windows.iter_mut() {
+                    if let Some(main_pass_id) = self.windows[window.window_id].main_pass_id {
+                        self.repaint_pass(main_pass_id);
+                    }
+                }
+                paint_dirty = true;
+                self.os.dark_mode.refresh(query_prefers_dark_mode);
+                self.call_event_handler(&Event::AppGotFocus);
+            }
+            WaylandEvent::AppLostFocus => {
+                self.call_event_handler(&Event::AppLostFocus);
+            }
+            WaylandEvent::WindowGeomChange(mut re) => {
+                if let Some(window) = opengl_windows.iter_mut().find( | w | w.window_id == re.window_id) {
+                    if let Some(dpi_override) = self.windows[re.window_id].dpi_override {
+                        re.new_geom.inner_size *= re.new_geom.dpi_factor / dpi_override;
+                        re.new_geom.dpi_factor = dpi_override;
+                    }
+
+                    window.window_geom = re.new_geom.clone();
+                    self.windows[re.window_id].window_geom = re.new_geom.clone();
+                    if re.old_geom.inner_size != re.new_geom.inner_size || re.old_geom.dpi_factor != re.new_geom.dpi_factor {
+                        if let Some(main_pass_id) = self.windows[re.window_id].main_pass_id {
+                            self.redraw_pass_and_child_passes(main_pass_id);
+                        }
+                    }
+                }
+                self.call_event_handler(&Event::WindowGeomChange(re));
+            }
+            WaylandEvent::WindowClosed(wc) => {
+                let window_id = wc.window_id;
+                self.call_event_handler(&Event::WindowClosed(wc));
+                self.windows[window_id].is_created = false;
+                if let Some(index) = opengl_windows.iter().position( | w | w.window_id == window_id) {
+                    let opengl_window = opengl_windows.remove(index);
+                    unsafe {self.os.opengl_cx.as_ref().unwrap().destroy_surface(opengl_window.egl_surface)};
+                    wayland_app.close_window(window_id);
+                    if opengl_windows.is_empty() {
+                        wayland_app.terminate_event_loop();
+                        self.call_event_handler(&Event::Shutdown);
+                        return EventFlow::Exit
+                    }
+                }
+            }
+            WaylandEvent::Paint => {
+                if !self.new_next_frames.is_empty() {
+                    self.call_next_frame_event(wayland_app.time_now());
+                }
+                if self.need_redrawing() {
+                    self.call_draw_event();
+                    self.os.opengl_cx.as_ref().unwrap().make_current();
+                    self.opengl_compile_shaders();
+                }
+                self.wayland_handle_repaint(wayland_app, opengl_windows);
+            }
+            WaylandEvent::MouseDown(e) => {
+                self.fingers.process_tap_count(
+                    e.abs,
+                    e.time
+                );
+                self.fingers.mouse_down(e.button, e.window_id);
+                self.call_event_handler(&Event::MouseDown(e))
+            }
+            WaylandEvent::MouseMove(e) => {
+                self.call_event_handler(&Event::MouseMove(e));
+                self.fingers.cycle_hover_area(live_id!(mouse).into());
+                self.fingers.switch_captures();
+            }
+            WaylandEvent::MouseUp(e) => {
+                let button = e.button;
+                self.call_event_handler(&Event::MouseUp(e));
+                self.fingers.mouse_up(button);
+                self.fingers.cycle_hover_area(live_id!(mouse).into());
+            }
+            WaylandEvent::Scroll(e) => {
+                self.call_event_handler(&Event::Scroll(e))
+            }
+            WaylandEvent::TouchUpdate(e) => {
+                self.fingers.process_touch_update_start(e.time, &e.touches);
+                let e = Event::TouchUpdate(e);
+                self.call_event_handler(&e);
+                let e = if let Event::TouchUpdate(e) = e {e} else {panic!()};
+                self.fingers.process_touch_update_end(&e.touches);
+            }
+            WaylandEvent::WindowCloseRequested(e) => {
+                self.call_event_handler(&Event::WindowCloseRequested(e))
+            }
+            WaylandEvent::TextInput(e) => {
+                self.call_event_handler(&Event::TextInput(e))
+            }
+            WaylandEvent::KeyDown(e) => {
+                self.keyboard.process_key_down(e);
+                self.call_event_handler(&Event::KeyDown(e))
+            }
+            WaylandEvent::KeyUp(e) => {
+                self.keyboard.process_key_up(e);
+                self.call_event_handler(&Event::KeyUp(e))
+            }
+            WaylandEvent::TextCopy(e) => {
+                self.call_event_handler(&Event::TextCopy(e))
+            }
+            WaylandEvent::TextCut(e) => {
+                self.call_event_handler(&Event::TextCut(e))
+            }
+            WaylandEvent::Timer(e) => {
+                if e.timer_id == 0 {
+                    if SignalToUI::check_and_clear_ui_signal() {
+                        self.handle_media_signals();
+                        self.call_event_handler(&Event::Signal);
+                    }
+                    self.handle_action_receiver();
+                }
+                else {
+                    self.call_event_handler(&Event::Timer(e))
+                }
+
+                if self.handle_live_edit() {
+                    self.call_event_handler(&Event::LiveEdit);
+                    self.redraw_all();
+                }
+            }
+        }
+
+        if self.any_passes_dirty() || self.need_redrawing() || paint_dirty {
+            EventFlow::Poll
+        } else {
+            EventFlow::Wait
+        }
+    }
+
+    fn wayland_handle_repaint(&mut self, wayland_app: &mut WaylandApp, opengl_windows: &mut [OpenglWindow]) {
+        self.os.opengl_cx.as_ref().unwrap().make_current();
+        let mut passes_todo = Vec::new();
+        self.compute_pass_repaint_order(&mut passes_todo);
+        self.repaint_id += 1;
+        for pass_id in &passes_todo {
+            self.passes[*pass_id].set_time(wayland_app.time_now() as f32);
+            match self.passes[*pass_id].parent.clone() {
+                CxPassParent::Window(window_id) => {
+                    // attaching a buffer before the first configure is a protocol error,
+                    // the pass stays dirty and gets drawn once the configure arrived
+                    if !wayland_app.window_is_configured(window_id) {
+                        continue;
+                    }
+                    if let Some(window) = opengl_windows.iter_mut().find( | w | w.window_id == window_id) {
+                        window.resize_buffers();
+                        self.draw_pass_to_egl_surface(*pass_id, window.egl_surface, window.cal_size);
+                    }
+                }
+                CxPassParent::Pass(_) => {
+                    self.draw_pass_to_magic_texture(*pass_id);
+                },
+                CxPassParent::None => {
+                    self.draw_pass_to_magic_texture(*pass_id);
+                }
+            }
+        }
+    }
+
+    fn wayland_handle_platform_ops(&mut self, opengl_windows: &mut Vec<OpenglWindow>, wayland_app: &mut WaylandApp) -> EventFlow {
+        let mut ret = EventFlow::Poll;
+        while let Some(op) = self.platform_ops.pop() {
+            match op {
+                CxOsOp::CreateWindow(window_id) => {
+                    let window = &mut self.windows[window_id];
+                    let opengl_window = OpenglWindow::new(
+                        window_id,
+                        self.os.opengl_cx.as_ref().unwrap(),
+                        wayland_app,
+                        window.create_inner_size.unwrap_or(dvec2(800., 600.)),
+                        &window.create_title,
+                    );
+                    window.window_geom = opengl_window.window_geom.clone();
+                    opengl_windows.push(opengl_window);
+                    window.is_created = true;
+                },
+                CxOsOp::CloseWindow(window_id) => {
+                    if let Some(index) = opengl_windows.iter().position( | w | w.window_id == window_id) {
+                        self.windows[window_id].is_created = false;
+                        let opengl_window = opengl_windows.remove(index);
+                        unsafe {self.os.opengl_cx.as_ref().unwrap().destroy_surface(opengl_window.egl_surface)};
+                        wayland_app.close_window(window_id);
+                        if opengl_windows.is_empty() {
+                            ret = EventFlow::Exit
+                        }
+                    }
+                },
+                CxOsOp::Quit => {
+                    ret = EventFlow::Exit
+                }
+                CxOsOp::MinimizeWindow(window_id) => {
+                    wayland_app.minimize(window_id);
+                },
+                CxOsOp::MaximizeWindow(window_id) => {
+                    wayland_app.maximize(window_id);
+                },
+                CxOsOp::RestoreWindow(window_id) => {
+                    wayland_app.restore(window_id);
+                },
+                CxOsOp::FullscreenWindow(window_id) => {
+                    wayland_app.fullscreen(window_id);
+                },
+                CxOsOp::NormalizeWindow(window_id) => {
+                    wayland_app.normalize(window_id);
+                }
+                CxOsOp::ShowClipboardActions(_) => {
+                },
+                CxOsOp::CopyToClipboard(content) => {
+                    wayland_app.copy_to_clipboard(&content);
+                }
+                CxOsOp::SetTopmost(_window_id, _is_topmost) => {
+                    // xdg-shell has no notion of topmost windows
+                }
+                CxOsOp::XrStartPresenting => {
+                },
+                CxOsOp::XrStopPresenting => {
+                },
+                CxOsOp::ShowTextIME(_area, _pos) => {
+                }
+                CxOsOp::HideTextIME => {
+                },
+                CxOsOp::SetCursor(cursor) => {
+                    wayland_app.set_mouse_cursor(cursor);
+                },
+                CxOsOp::StartTimer {timer_id, interval, repeats} => {
+                    wayland_app.start_timer(timer_id, interval, repeats);
+                },
+                CxOsOp::StopTimer(timer_id) => {
+                    wayland_app.stop_timer(timer_id);
+                },
+                CxOsOp::StartDragging(_dragged_item) => {
+                },
+                CxOsOp::UpdateMacosMenu(_menu) => {
+                },
+                // there is no http client, video decoder or file chooser on this backend yet,
+                // so these are reported and dropped instead of taking the app down
+                CxOsOp::HttpRequest {request_id, request} => {
+                    crate::error!("http requests are not supported on wayland, dropping {:?} to {}", request_id, request.url);
+                },
+                CxOsOp::CancelHttpRequest {request_id: _} => {
+                }
+                CxOsOp::PrepareVideoPlayback(video_id, _, _, _, _) => {
+                    crate::error!("video playback is not supported on wayland, ignoring {:?}", video_id);
+                }
+                CxOsOp::BeginVideoPlayback(_) |
+                CxOsOp::PauseVideoPlayback(_) |
+                CxOsOp::ResumeVideoPlayback(_) |
+                CxOsOp::MuteVideoPlayback(_) |
+                CxOsOp::UnmuteVideoPlayback(_) |
+                CxOsOp::CleanupVideoPlaybackResources(_) |
+                CxOsOp::UpdateVideoSurfaceTexture(_) => {
+                }
+                CxOsOp::SaveFileDialog(_) |
+                CxOsOp::SelectFileDialog(_) |
+                CxOsOp::SaveFolderDialog(_) |
+                CxOsOp::SelectFolderDialog(_) => {
+                    crate::error!("file dialogs are not supported on wayland");
+                }
+            }
+        }
+        ret
+    }
+}
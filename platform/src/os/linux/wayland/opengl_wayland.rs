@@ -0,0 +1,55 @@
+use {
+    self::super::{
+        wayland_app::WaylandApp,
+        super::{
+            egl_sys,
+            x11::opengl_x11::OpenglCx,
+        },
+    },
+    crate::{
+        window::WindowId,
+        makepad_math::DVec2,
+        event::*,
+    },
+};
+
+pub struct OpenglWindow {
+    pub window_id: WindowId,
+    pub window_geom: WindowGeom,
+    pub cal_size: DVec2,
+    pub egl_surface: egl_sys::EGLSurface,
+}
+
+impl OpenglWindow {
+    pub fn new(
+        window_id: WindowId,
+        opengl_cx: &OpenglCx,
+        wayland_app: &mut WaylandApp,
+        inner_size: DVec2,
+        title: &str
+    ) -> OpenglWindow {
+        let window_geom = wayland_app.create_window(window_id, inner_size, title);
+        let egl_window = wayland_app.egl_window(window_id).unwrap();
+        let egl_surface = unsafe {opengl_cx.create_window_surface(egl_window as egl_sys::EGLNativeWindowType)};
+        OpenglWindow {
+            window_id,
+            window_geom,
+            cal_size: DVec2::default(),
+            egl_surface,
+        }
+    }
+    
+    pub fn resize_buffers(&mut self) -> bool {
+        let cal_size = DVec2 {
+            x: self.window_geom.inner_size.x * self.window_geom.dpi_factor,
+            y: self.window_geom.inner_size.y * self.window_geom.dpi_factor
+        };
+        if self.cal_size != cal_size {
+            self.cal_size = cal_size;
+            true
+        }
+        else {
+            false
+        }
+    }
+}
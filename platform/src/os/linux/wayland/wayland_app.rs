@@ -0,0 +1,1583 @@
+use {
+    std::{
+        cell::{Cell, RefCell},
+        ffi::{CStr, CString},
+        fs::File,
+        io::{ErrorKind, Read, Write},
+        os::{
+            fd::FromRawFd,
+            raw::{c_char, c_int, c_void},
+        },
+        ptr,
+        rc::Rc,
+    },
+    self::super::{
+        wayland_event::WaylandEvent,
+        wayland_protocols::*,
+        wayland_sys::*,
+        xkb_sys::*,
+        super::{
+            libc_sys,
+            select_timer::SelectTimers,
+            x11::xlib_app::keysym_to_keycode,
+        },
+    },
+    crate::{
+        makepad_math::{dvec2, DVec2},
+        event::*,
+        area::Area,
+        cursor::MouseCursor,
+        window::WindowId,
+        os::cx_native::EventFlow,
+    },
+};
+
+static mut WAYLAND_APP: *mut WaylandApp = ptr::null_mut();
+
+pub type WaylandEventCallback = Box<dyn FnMut(&mut WaylandApp, WaylandEvent) -> EventFlow>;
+
+pub fn get_wayland_app_global() -> &'static mut WaylandApp {
+    unsafe {
+        &mut *(WAYLAND_APP)
+    }
+}
+
+// connects to the compositor named by WAYLAND_DISPLAY, returns false if there is none
+// or it lacks the protocols we need, so the caller can fall back to X11
+pub fn init_wayland_app_global(event_callback: WaylandEventCallback) -> bool {
+    match unsafe {WaylandApp::try_new(event_callback)} {
+        Some(app) => unsafe {
+            WAYLAND_APP = Box::into_raw(Box::new(app));
+            if get_wayland_app_global().init_globals() {
+                return true
+            }
+            let app = Box::from_raw(WAYLAND_APP);
+            WAYLAND_APP = ptr::null_mut();
+            (app.lib.wl_display_disconnect)(app.display);
+            false
+        }
+        None => false
+    }
+}
+
+// the timer id the key repeat uses on our SelectTimers, user timer ids count up from 1
+const KEY_REPEAT_TIMER_ID: u64 = u64::MAX;
+// polls the pipes of clipboard transfers with other clients that are still in flight
+const CLIPBOARD_TIMER_ID: u64 = u64::MAX - 1;
+const CLIPBOARD_POLL_INTERVAL: f64 = 0.01;
+// a client that makes no progress on its end of a transfer for this long is given up on
+const CLIPBOARD_TIMEOUT: f64 = 1.0;
+
+// in order of preference, the first one an offer has is the one we receive
+const TEXT_MIME_TYPES: [&[u8]; 3] = [b"text/plain;charset=utf-8\0", b"UTF8_STRING\0", b"text/plain\0"];
+
+// data offers that have text, with the index in TEXT_MIME_TYPES of the best type they offer
+#[derive(Default)]
+struct TextOffers(Vec<(*mut wl_proxy, usize)>);
+
+impl TextOffers {
+    fn add_mime(&mut self, offer: *mut wl_proxy, mime: &[u8]) {
+        let Some(index) = TEXT_MIME_TYPES.iter().position( | m | *m == mime) else {
+            return
+        };
+        match self.0.iter_mut().find( | (o, _) | *o == offer) {
+            Some((_, best)) => *best = (*best).min(index),
+            None => self.0.push((offer, index)),
+        }
+    }
+
+    fn remove(&mut self, offer: *mut wl_proxy) {
+        self.0.retain( | (o, _) | *o != offer);
+    }
+
+    fn mime_for(&self, offer: *mut wl_proxy) -> Option<&'static [u8]> {
+        self.0.iter().find( | (o, _) | *o == offer).map( | (_, index) | TEXT_MIME_TYPES[*index])
+    }
+}
+
+// which object an event is for, passed to the dispatcher as its implementation pointer
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[repr(usize)]
+enum ProxyKind {
+    Registry = 1,
+    WmBase,
+    Seat,
+    Pointer,
+    Keyboard,
+    Touch,
+    Surface,
+    XdgSurface,
+    XdgToplevel,
+    FractionalScale,
+    DataDevice,
+    DataSource,
+    DataOffer,
+}
+
+impl ProxyKind {
+    fn from_raw(raw: usize) -> Option<Self> {
+        [
+            Self::Registry,
+            Self::WmBase,
+            Self::Seat,
+            Self::Pointer,
+            Self::Keyboard,
+            Self::Touch,
+            Self::Surface,
+            Self::XdgSurface,
+            Self::XdgToplevel,
+            Self::FractionalScale,
+            Self::DataDevice,
+            Self::DataSource,
+            Self::DataOffer,
+        ].into_iter().find( | kind | *kind as usize == raw)
+    }
+}
+
+unsafe extern "C" fn wayland_dispatcher(
+    implementation: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    _msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int {
+    if let Some(kind) = ProxyKind::from_raw(implementation as usize) {
+        get_wayland_app_global().handle_proxy_event(kind, target as *mut wl_proxy, opcode, args);
+    }
+    0
+}
+
+// reads the keymap the compositor shared with us, as a nul terminated string for xkb
+fn read_keymap(file: &mut impl Read, size: usize) -> Option<Vec<u8>> {
+    let mut keymap = vec![0u8; size];
+    file.read_exact(&mut keymap).ok()?;
+    // the keymap string is nul terminated within size
+    if keymap.last() != Some(&0) {
+        keymap.push(0);
+    }
+    Some(keymap)
+}
+
+// the text a key press types, shortcuts and control characters like backspace
+// and escape don't type anything, those only come through as key events
+fn typed_text(modifiers: &KeyModifiers, text: String) -> Option<String> {
+    if modifiers.control || modifiers.logo || modifiers.alt {
+        return None
+    }
+    if text.is_empty() || text.chars().any( | c | c.is_control()) {
+        return None
+    }
+    Some(text)
+}
+
+fn set_nonblocking(fd: c_int) {
+    unsafe {
+        let flags = libc_sys::fcntl(fd, libc_sys::F_GETFL);
+        libc_sys::fcntl(fd, libc_sys::F_SETFL, flags | libc_sys::O_NONBLOCK);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum TransferPoll {
+    Pending,
+    Done,
+    Failed(String),
+}
+
+// a paste read from another client's selection, the pipe is non blocking
+// and drained from the event loop so a slow owner can't freeze us
+struct PendingPaste {
+    file: File,
+    data: Vec<u8>,
+    last_progress: f64,
+}
+
+impl PendingPaste {
+    fn new(file: File, time: f64) -> Self {
+        Self {file, data: Vec::new(), last_progress: time}
+    }
+
+    // reads whatever the selection owner wrote so far, done once it closed the pipe
+    fn poll(&mut self, time: f64) -> TransferPoll {
+        let mut buf = [0u8; 4096];
+        loop {
+            match self.file.read(&mut buf) {
+                Ok(0) => return TransferPoll::Done,
+                Ok(n) => {
+                    self.data.extend_from_slice(&buf[..n]);
+                    self.last_progress = time;
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return TransferPoll::Failed(format!("reading the wayland clipboard failed: {}", err)),
+            }
+        }
+        if time - self.last_progress > CLIPBOARD_TIMEOUT {
+            return TransferPoll::Failed(format!("the wayland clipboard owner stopped sending the selection for {}s", CLIPBOARD_TIMEOUT))
+        }
+        TransferPoll::Pending
+    }
+
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.data).into_owned()
+    }
+}
+
+// our selection being written to another client, non blocking for the same reason,
+// a client that never reads its end would otherwise hang us in write
+struct PendingSend {
+    file: File,
+    data: Vec<u8>,
+    written: usize,
+    last_progress: f64,
+}
+
+impl PendingSend {
+    fn new(file: File, data: Vec<u8>, time: f64) -> Self {
+        Self {file, data, written: 0, last_progress: time}
+    }
+
+    fn poll(&mut self, time: f64) -> TransferPoll {
+        while self.written < self.data.len() {
+            match self.file.write(&self.data[self.written..]) {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.written += n;
+                    self.last_progress = time;
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return TransferPoll::Failed(format!("sending the wayland clipboard failed: {}", err)),
+            }
+        }
+        if self.written == self.data.len() {
+            return TransferPoll::Done
+        }
+        if time - self.last_progress > CLIPBOARD_TIMEOUT {
+            return TransferPoll::Failed(format!("a wayland client stopped reading the clipboard for {}s", CLIPBOARD_TIMEOUT))
+        }
+        TransferPoll::Pending
+    }
+}
+
+pub struct WaylandWindow {
+    pub window_id: WindowId,
+    pub surface: *mut wl_proxy,
+    pub xdg_surface: *mut wl_proxy,
+    pub xdg_toplevel: *mut wl_proxy,
+    pub decoration: *mut wl_proxy,
+    pub fractional_scale: *mut wl_proxy,
+    pub viewport: *mut wl_proxy,
+    pub egl_window: *mut wl_egl_window,
+    pub configured: bool,
+    pub inner_size: DVec2,
+    pub pending_size: Option<DVec2>,
+    pub pending_states: (bool, bool),
+    // the scale the compositor prefers, fractional if it supports that
+    pub preferred_scale: f64,
+    pub is_maximized: bool,
+    pub is_fullscreen: bool,
+    pub last_window_geom: WindowGeom,
+    pub last_mouse_pos: DVec2,
+}
+
+impl WaylandWindow {
+    // with a viewport we render at the exact fractional scale, otherwise we round up to
+    // an integer buffer scale and let the compositor scale down
+    pub fn dpi_factor(&self) -> f64 {
+        if !self.viewport.is_null() {
+            self.preferred_scale
+        }
+        else {
+            self.preferred_scale.ceil()
+        }
+    }
+
+    pub fn get_window_geom(&self) -> WindowGeom {
+        WindowGeom {
+            xr_is_presenting: false,
+            can_fullscreen: true,
+            is_topmost: false,
+            is_fullscreen: self.is_fullscreen,
+            inner_size: self.inner_size,
+            outer_size: self.inner_size,
+            dpi_factor: self.dpi_factor(),
+            position: DVec2::default(),
+        }
+    }
+}
+
+struct TouchSlot {
+    id: i32,
+    surface: *mut wl_proxy,
+    state: TouchState,
+    abs: DVec2,
+}
+
+pub struct WaylandApp {
+    pub lib: LibWaylandClient,
+    pub lib_egl: LibWaylandEgl,
+    xkb: Option<LibXkbCommon>,
+    xkb_context: *mut xkb_context,
+    xkb_keyboard: Option<XkbKeyboard>,
+
+    pub display: *mut wl_display,
+    pub display_fd: c_int,
+    registry: *mut wl_proxy,
+    compositor: *mut wl_proxy,
+    compositor_version: u32,
+    wm_base: *mut wl_proxy,
+    seat: *mut wl_proxy,
+    pointer: *mut wl_proxy,
+    keyboard: *mut wl_proxy,
+    touch: *mut wl_proxy,
+    data_device_manager: *mut wl_proxy,
+    data_device: *mut wl_proxy,
+    fractional_scale_manager: *mut wl_proxy,
+    viewporter: *mut wl_proxy,
+    decoration_manager: *mut wl_proxy,
+    cursor_shape_manager: *mut wl_proxy,
+    cursor_shape_device: *mut wl_proxy,
+
+    pub windows: Vec<Box<WaylandWindow >>,
+    pointer_focus: *mut wl_proxy,
+    pointer_serial: u32,
+    keyboard_focus: *mut wl_proxy,
+    keyboard_serial: u32,
+    modifiers: KeyModifiers,
+    pending_scroll: DVec2,
+    scroll_is_wheel: bool,
+    touches: Vec<TouchSlot>,
+
+    repeat_rate: i32,
+    repeat_delay: i32,
+    repeat_key: Option<u32>,
+
+    pub clipboard: String,
+    data_source: *mut wl_proxy,
+    owns_selection: bool,
+    selection_offer: *mut wl_proxy,
+    dnd_offer: *mut wl_proxy,
+    text_offers: TextOffers,
+    pending_paste: Option<PendingPaste>,
+    pending_sends: Vec<PendingSend>,
+
+    pub timers: SelectTimers,
+    pub event_callback: Option<WaylandEventCallback>,
+    pub event_flow: EventFlow,
+    event_loop_running: bool,
+    pub current_cursor: MouseCursor,
+}
+
+impl WaylandApp {
+    unsafe fn try_new(event_callback: WaylandEventCallback) -> Option<WaylandApp> {
+        let lib = LibWaylandClient::try_load()?;
+        let lib_egl = LibWaylandEgl::try_load()?;
+        let display = (lib.wl_display_connect)(ptr::null());
+        if display.is_null() {
+            return None
+        }
+        let display_fd = (lib.wl_display_get_fd)(display);
+        let xkb = LibXkbCommon::try_load();
+        let xkb_context = xkb.as_ref().map( | xkb | (xkb.xkb_context_new)(XKB_CONTEXT_NO_FLAGS)).unwrap_or(ptr::null_mut());
+        if xkb.is_none() {
+            crate::error!("libxkbcommon not found, wayland keyboard input is disabled");
+        }
+        Some(WaylandApp {
+            lib,
+            lib_egl,
+            xkb,
+            xkb_context,
+            xkb_keyboard: None,
+            display,
+            display_fd,
+            registry: ptr::null_mut(),
+            compositor: ptr::null_mut(),
+            compositor_version: 0,
+            wm_base: ptr::null_mut(),
+            seat: ptr::null_mut(),
+            pointer: ptr::null_mut(),
+            keyboard: ptr::null_mut(),
+            touch: ptr::null_mut(),
+            data_device_manager: ptr::null_mut(),
+            data_device: ptr::null_mut(),
+            fractional_scale_manager: ptr::null_mut(),
+            viewporter: ptr::null_mut(),
+            decoration_manager: ptr::null_mut(),
+            cursor_shape_manager: ptr::null_mut(),
+            cursor_shape_device: ptr::null_mut(),
+            windows: Vec::new(),
+            pointer_focus: ptr::null_mut(),
+            pointer_serial: 0,
+            keyboard_focus: ptr::null_mut(),
+            keyboard_serial: 0,
+            modifiers: KeyModifiers::default(),
+            pending_scroll: DVec2::default(),
+            scroll_is_wheel: true,
+            touches: Vec::new(),
+            repeat_rate: 25,
+            repeat_delay: 600,
+            repeat_key: None,
+            clipboard: String::new(),
+            data_source: ptr::null_mut(),
+            owns_selection: false,
+            selection_offer: ptr::null_mut(),
+            dnd_offer: ptr::null_mut(),
+            text_offers: TextOffers::default(),
+            pending_paste: None,
+            pending_sends: Vec::new(),
+            timers: SelectTimers::new(),
+            event_callback: Some(event_callback),
+            event_flow: EventFlow::Poll,
+            event_loop_running: true,
+            current_cursor: MouseCursor::Default,
+        })
+    }
+
+    unsafe fn init_globals(&mut self) -> bool {
+        self.registry = self.lib.marshal(
+            self.display,
+            WL_DISPLAY_GET_REGISTRY,
+            self.lib.wl_registry_interface,
+            &mut [wl_argument::new_id()]
+        );
+        self.add_dispatcher(self.registry, ProxyKind::Registry);
+        // the first roundtrip binds the globals, the second one gets us the seat capabilities
+        (self.lib.wl_display_roundtrip)(self.display);
+        if self.compositor.is_null() || self.wm_base.is_null() {
+            crate::error!("wayland compositor does not support xdg_wm_base");
+            return false
+        }
+        (self.lib.wl_display_roundtrip)(self.display);
+        true
+    }
+
+    unsafe fn add_dispatcher(&self, proxy: *mut wl_proxy, kind: ProxyKind) {
+        (self.lib.wl_proxy_add_dispatcher)(proxy, wayland_dispatcher, kind as usize as *const c_void, ptr::null_mut());
+    }
+
+    unsafe fn bind(&self, name: u32, interface: *const wl_interface, version: u32) -> *mut wl_proxy {
+        let version = version.min((*interface).version as u32);
+        self.lib.marshal_versioned(self.registry, WL_REGISTRY_BIND, interface, version, &mut [
+            wl_argument::uint(name),
+            wl_argument::string((*interface).name),
+            wl_argument::uint(version),
+            wl_argument::new_id(),
+        ])
+    }
+
+    unsafe fn handle_proxy_event(&mut self, kind: ProxyKind, proxy: *mut wl_proxy, opcode: u32, args: *mut wl_argument) {
+        let arg = | i: usize | *args.add(i);
+        match (kind, opcode) {
+            (ProxyKind::Registry, 0) => { // global
+                let name = arg(0).u;
+                let interface = CStr::from_ptr(arg(1).s).to_bytes();
+                let version = arg(2).u;
+                match interface {
+                    b"wl_compositor" => {
+                        self.compositor = self.bind(name, self.lib.wl_compositor_interface, version.min(6));
+                        self.compositor_version = (self.lib.wl_proxy_get_version)(self.compositor);
+                    }
+                    b"xdg_wm_base" => {
+                        self.wm_base = self.bind(name, &xdg_wm_base_interface, 1);
+                        self.add_dispatcher(self.wm_base, ProxyKind::WmBase);
+                    }
+                    b"wl_seat" if self.seat.is_null() => {
+                        self.seat = self.bind(name, self.lib.wl_seat_interface, version.min(5));
+                        self.add_dispatcher(self.seat, ProxyKind::Seat);
+                        self.init_data_device();
+                    }
+                    b"wl_data_device_manager" => {
+                        self.data_device_manager = self.bind(name, self.lib.wl_data_device_manager_interface, version.min(3));
+                        self.init_data_device();
+                    }
+                    b"wp_fractional_scale_manager_v1" => {
+                        self.fractional_scale_manager = self.bind(name, &wp_fractional_scale_manager_v1_interface, 1);
+                    }
+                    b"wp_viewporter" => {
+                        self.viewporter = self.bind(name, &wp_viewporter_interface, 1);
+                    }
+                    b"zxdg_decoration_manager_v1" => {
+                        self.decoration_manager = self.bind(name, &zxdg_decoration_manager_v1_interface, 1);
+                    }
+                    b"wp_cursor_shape_manager_v1" => {
+                        self.cursor_shape_manager = self.bind(name, &wp_cursor_shape_manager_v1_interface, 1);
+                    }
+                    _ => ()
+                }
+            }
+            (ProxyKind::Registry, _) => (), // global_remove
+            (ProxyKind::WmBase, 0) => { // ping
+                self.lib.marshal(self.wm_base, XDG_WM_BASE_PONG, ptr::null(), &mut [arg(0)]);
+            }
+            (ProxyKind::Seat, 0) => { // capabilities
+                self.update_seat_capabilities(arg(0).u);
+            }
+            (ProxyKind::Seat, _) => (), // name
+            (ProxyKind::Pointer, _) => self.handle_pointer_event(opcode, args),
+            (ProxyKind::Keyboard, _) => self.handle_keyboard_event(opcode, args),
+            (ProxyKind::Touch, _) => self.handle_touch_event(opcode, args),
+            (ProxyKind::Surface, 2) => { // preferred_buffer_scale
+                if let Some(window) = self.windows.iter_mut().find( | w | w.surface == proxy) {
+                    if window.fractional_scale.is_null() {
+                        window.preferred_scale = arg(0).i as f64;
+                        let window_id = window.window_id;
+                        self.apply_window_size(window_id);
+                    }
+                }
+            }
+            (ProxyKind::Surface, _) => (), // enter, leave, preferred_buffer_transform
+            (ProxyKind::XdgSurface, 0) => { // configure
+                self.lib.marshal(proxy, XDG_SURFACE_ACK_CONFIGURE, ptr::null(), &mut [arg(0)]);
+                if let Some(window) = self.windows.iter_mut().find( | w | w.xdg_surface == proxy) {
+                    window.configured = true;
+                    if let Some(size) = window.pending_size.take() {
+                        window.inner_size = size;
+                    }
+                    (window.is_maximized, window.is_fullscreen) = window.pending_states;
+                    let window_id = window.window_id;
+                    self.apply_window_size(window_id);
+                }
+            }
+            (ProxyKind::XdgToplevel, 0) => { // configure
+                if let Some(window) = self.windows.iter_mut().find( | w | w.xdg_toplevel == proxy) {
+                    let (width, height) = (arg(0).i, arg(1).i);
+                    // zero means we get to pick our own size
+                    if width > 0 && height > 0 {
+                        window.pending_size = Some(dvec2(width as f64, height as f64));
+                    }
+                    let states = &*arg(2).a;
+                    let states = std::slice::from_raw_parts(states.data as *const u32, states.size / 4);
+                    window.pending_states = (
+                        states.contains(&XDG_TOPLEVEL_STATE_MAXIMIZED),
+                        states.contains(&XDG_TOPLEVEL_STATE_FULLSCREEN)
+                    );
+                }
+            }
+            (ProxyKind::XdgToplevel, 1) => { // close
+                if let Some(window_id) = self.windows.iter().find( | w | w.xdg_toplevel == proxy).map( | w | w.window_id) {
+                    self.send_close_requested(window_id);
+                }
+            }
+            (ProxyKind::FractionalScale, 0) => { // preferred_scale
+                if let Some(window) = self.windows.iter_mut().find( | w | w.fractional_scale == proxy) {
+                    window.preferred_scale = arg(0).u as f64 / 120.0;
+                    let window_id = window.window_id;
+                    self.apply_window_size(window_id);
+                }
+            }
+            (ProxyKind::DataDevice, 0) => { // data_offer
+                let offer = arg(0).o;
+                self.add_dispatcher(offer, ProxyKind::DataOffer);
+            }
+            (ProxyKind::DataDevice, 1) => { // enter, we dont accept drops yet
+                self.destroy_offer(self.dnd_offer);
+                self.dnd_offer = arg(4).o;
+            }
+            (ProxyKind::DataDevice, 2) => { // leave
+                self.destroy_offer(self.dnd_offer);
+                self.dnd_offer = ptr::null_mut();
+            }
+            (ProxyKind::DataDevice, 5) => { // selection
+                let offer = arg(0).o;
+                if offer != self.selection_offer {
+                    self.destroy_offer(self.selection_offer);
+                    self.selection_offer = offer;
+                }
+            }
+            (ProxyKind::DataDevice, _) => (), // motion, drop
+            (ProxyKind::DataOffer, 0) => { // offer
+                let mime = CStr::from_ptr(arg(0).s).to_bytes_with_nul();
+                self.text_offers.add_mime(proxy, mime);
+            }
+            (ProxyKind::DataOffer, _) => (), // source_actions, action
+            (ProxyKind::DataSource, 1) => { // send
+                let fd = arg(1).h;
+                set_nonblocking(fd);
+                let time = self.time_now();
+                let mut send = PendingSend::new(File::from_raw_fd(fd), self.clipboard.as_bytes().to_vec(), time);
+                match send.poll(time) {
+                    TransferPoll::Done => (),
+                    TransferPoll::Failed(err) => crate::error!("{}", err),
+                    TransferPoll::Pending => {
+                        self.pending_sends.push(send);
+                        self.start_clipboard_timer();
+                    }
+                }
+            }
+            (ProxyKind::DataSource, 2) => { // cancelled
+                if proxy == self.data_source {
+                    self.data_source = ptr::null_mut();
+                    self.owns_selection = false;
+                }
+                self.lib.marshal_destroy(proxy, WL_DATA_SOURCE_DESTROY);
+            }
+            (ProxyKind::DataSource, _) => (), // target
+            _ => ()
+        }
+    }
+
+    unsafe fn init_data_device(&mut self) {
+        if self.seat.is_null() || self.data_device_manager.is_null() || !self.data_device.is_null() {
+            return
+        }
+        self.data_device = self.lib.marshal(
+            self.data_device_manager,
+            WL_DATA_DEVICE_MANAGER_GET_DATA_DEVICE,
+            self.lib.wl_data_device_interface,
+            &mut [wl_argument::new_id(), wl_argument::object(self.seat)]
+        );
+        self.add_dispatcher(self.data_device, ProxyKind::DataDevice);
+    }
+
+    unsafe fn update_seat_capabilities(&mut self, caps: u32) {
+        let seat_version = (self.lib.wl_proxy_get_version)(self.seat);
+        let has_pointer = caps & WL_SEAT_CAPABILITY_POINTER != 0;
+        if has_pointer && self.pointer.is_null() {
+            self.pointer = self.lib.marshal(self.seat, WL_SEAT_GET_POINTER, self.lib.wl_pointer_interface, &mut [wl_argument::new_id()]);
+            self.add_dispatcher(self.pointer, ProxyKind::Pointer);
+            if !self.cursor_shape_manager.is_null() {
+                self.cursor_shape_device = self.lib.marshal(
+                    self.cursor_shape_manager,
+                    WP_CURSOR_SHAPE_MANAGER_V1_GET_POINTER,
+                    &wp_cursor_shape_device_v1_interface,
+                    &mut [wl_argument::new_id(), wl_argument::object(self.pointer)]
+                );
+            }
+        }
+        else if !has_pointer && !self.pointer.is_null() {
+            if !self.cursor_shape_device.is_null() {
+                self.lib.marshal_destroy(self.cursor_shape_device, WP_CURSOR_SHAPE_DEVICE_V1_DESTROY);
+                self.cursor_shape_device = ptr::null_mut();
+            }
+            self.release_proxy(self.pointer, seat_version, WL_POINTER_RELEASE);
+            self.pointer = ptr::null_mut();
+        }
+        let has_keyboard = caps & WL_SEAT_CAPABILITY_KEYBOARD != 0;
+        if has_keyboard && self.keyboard.is_null() {
+            self.keyboard = self.lib.marshal(self.seat, WL_SEAT_GET_KEYBOARD, self.lib.wl_keyboard_interface, &mut [wl_argument::new_id()]);
+            self.add_dispatcher(self.keyboard, ProxyKind::Keyboard);
+        }
+        else if !has_keyboard && !self.keyboard.is_null() {
+            self.release_proxy(self.keyboard, seat_version, WL_KEYBOARD_RELEASE);
+            self.keyboard = ptr::null_mut();
+        }
+        let has_touch = caps & WL_SEAT_CAPABILITY_TOUCH != 0;
+        if has_touch && self.touch.is_null() {
+            self.touch = self.lib.marshal(self.seat, WL_SEAT_GET_TOUCH, self.lib.wl_touch_interface, &mut [wl_argument::new_id()]);
+            self.add_dispatcher(self.touch, ProxyKind::Touch);
+        }
+        else if !has_touch && !self.touch.is_null() {
+            self.release_proxy(self.touch, seat_version, WL_TOUCH_RELEASE);
+            self.touch = ptr::null_mut();
+        }
+    }
+
+    // the release requests only exist from wl_seat version 3 on
+    unsafe fn release_proxy(&self, proxy: *mut wl_proxy, seat_version: u32, release_opcode: u32) {
+        if seat_version >= 3 {
+            self.lib.marshal_destroy(proxy, release_opcode);
+        }
+        else {
+            (self.lib.wl_proxy_destroy)(proxy);
+        }
+    }
+
+    unsafe fn destroy_offer(&mut self, offer: *mut wl_proxy) {
+        if !offer.is_null() {
+            self.text_offers.remove(offer);
+            self.lib.marshal_destroy(offer, WL_DATA_OFFER_DESTROY);
+        }
+    }
+
+    fn window_for_surface(&self, surface: *mut wl_proxy) -> Option<&WaylandWindow> {
+        self.windows.iter().find( | w | w.surface == surface).map( | w | &**w)
+    }
+
+    unsafe fn handle_pointer_event(&mut self, opcode: u32, args: *mut wl_argument) {
+        let arg = | i: usize | *args.add(i);
+        match opcode {
+            0 => { // enter
+                self.pointer_serial = arg(0).u;
+                self.pointer_focus = arg(1).o;
+                let pos = dvec2(wl_fixed_to_f64(arg(2).f), wl_fixed_to_f64(arg(3).f));
+                let cursor = self.current_cursor;
+                self.set_mouse_cursor_(cursor);
+                self.send_mouse_move(pos);
+            }
+            1 => { // leave
+                self.pointer_focus = ptr::null_mut();
+            }
+            2 => { // motion
+                let pos = dvec2(wl_fixed_to_f64(arg(1).f), wl_fixed_to_f64(arg(2).f));
+                self.send_mouse_move(pos);
+            }
+            3 => { // button
+                let button = match arg(2).u {
+                    BTN_LEFT => 1,
+                    BTN_MIDDLE => 2,
+                    BTN_RIGHT => 3,
+                    other => other as usize,
+                };
+                let window = match self.window_for_surface(self.pointer_focus) {
+                    Some(window) => window,
+                    None => return
+                };
+                let (window_id, abs) = (window.window_id, window.last_mouse_pos);
+                if arg(3).u == WL_POINTER_BUTTON_STATE_PRESSED {
+                    self.do_callback(WaylandEvent::MouseDown(MouseDownEvent {
+                        button,
+                        modifiers: self.modifiers,
+                        window_id,
+                        abs,
+                        time: self.time_now(),
+                        handled: Cell::new(Area::Empty),
+                    }));
+                }
+                else {
+                    self.do_callback(WaylandEvent::MouseUp(MouseUpEvent {
+                        button,
+                        modifiers: self.modifiers,
+                        window_id,
+                        abs,
+                        time: self.time_now()
+                    }));
+                }
+            }
+            4 => { // axis
+                let value = wl_fixed_to_f64(arg(2).f);
+                match arg(1).u {
+                    WL_POINTER_AXIS_VERTICAL_SCROLL => self.pending_scroll.y += value,
+                    WL_POINTER_AXIS_HORIZONTAL_SCROLL => self.pending_scroll.x += value,
+                    _ => ()
+                }
+            }
+            5 if self.pending_scroll != DVec2::default() => { // frame
+                let scroll = std::mem::take(&mut self.pending_scroll);
+                let is_mouse = self.scroll_is_wheel;
+                self.scroll_is_wheel = true;
+                if let Some(window) = self.window_for_surface(self.pointer_focus) {
+                    // a wheel click is about 15 units, scale it up to what the other platforms send
+                    let scroll = if is_mouse {scroll * 8.0} else {scroll};
+                    let event = WaylandEvent::Scroll(ScrollEvent {
+                        window_id: window.window_id,
+                        scroll,
+                        abs: window.last_mouse_pos,
+                        modifiers: self.modifiers,
+                        is_mouse,
+                        handled_x: Cell::new(false),
+                        handled_y: Cell::new(false),
+                        time: self.time_now()
+                    });
+                    self.do_callback(event);
+                }
+            }
+            6 => { // axis_source
+                self.scroll_is_wheel = arg(0).u == 0;
+            }
+            _ => () // axis_stop, axis_discrete and newer
+        }
+    }
+
+    fn send_mouse_move(&mut self, pos: DVec2) {
+        let surface = self.pointer_focus;
+        if let Some(window) = self.windows.iter_mut().find( | w | w.surface == surface) {
+            window.last_mouse_pos = pos;
+            let window_id = window.window_id;
+            self.do_callback(WaylandEvent::MouseMove(MouseMoveEvent {
+                window_id,
+                abs: pos,
+                modifiers: self.modifiers,
+                time: self.time_now(),
+                handled: Cell::new(Area::Empty),
+            }));
+        }
+    }
+
+    unsafe fn handle_keyboard_event(&mut self, opcode: u32, args: *mut wl_argument) {
+        let arg = | i: usize | *args.add(i);
+        match opcode {
+            0 => { // keymap
+                let (format, fd, size) = (arg(0).u, arg(1).h, arg(2).u as usize);
+                let mut file = File::from_raw_fd(fd);
+                let xkb = match &self.xkb {
+                    Some(xkb) if format == WL_KEYBOARD_KEYMAP_FORMAT_XKB_V1 => xkb,
+                    _ => return
+                };
+                let Some(keymap) = read_keymap(&mut file, size) else {
+                    crate::error!("cannot read the wayland keymap");
+                    return
+                };
+                if let Some(old) = self.xkb_keyboard.take() {
+                    old.free(xkb);
+                }
+                self.xkb_keyboard = XkbKeyboard::from_keymap_string(xkb, self.xkb_context, keymap.as_ptr() as *const c_char);
+            }
+            1 => { // enter
+                self.keyboard_serial = arg(0).u;
+                self.keyboard_focus = arg(1).o;
+                self.do_callback(WaylandEvent::AppGotFocus);
+            }
+            2 => { // leave
+                self.keyboard_focus = ptr::null_mut();
+                self.stop_key_repeat();
+                self.do_callback(WaylandEvent::AppLostFocus);
+            }
+            3 => { // key
+                self.keyboard_serial = arg(0).u;
+                let key = arg(2).u + 8;
+                if arg(3).u == WL_KEYBOARD_KEY_STATE_PRESSED {
+                    self.send_key_down(key, false);
+                    let repeats = match (&self.xkb, &self.xkb_keyboard) {
+                        (Some(xkb), Some(keyboard)) => keyboard.key_repeats(xkb, key),
+                        _ => false
+                    };
+                    if repeats && self.repeat_rate > 0 {
+                        self.repeat_key = Some(key);
+                        self.timers.stop_timer(KEY_REPEAT_TIMER_ID);
+                        self.timers.start_timer(KEY_REPEAT_TIMER_ID, self.repeat_delay as f64 / 1000.0, false);
+                    }
+                }
+                else {
+                    if self.repeat_key == Some(key) {
+                        self.stop_key_repeat();
+                    }
+                    if let Some(key_code) = self.key_to_keycode(key) {
+                        self.do_callback(WaylandEvent::KeyUp(KeyEvent {
+                            key_code,
+                            is_repeat: false,
+                            modifiers: self.modifiers,
+                            time: self.time_now()
+                        }));
+                    }
+                }
+            }
+            4 => { // modifiers
+                if let (Some(xkb), Some(keyboard)) = (&self.xkb, &self.xkb_keyboard) {
+                    keyboard.update_mask(xkb, arg(1).u, arg(2).u, arg(3).u, arg(4).u);
+                    self.modifiers = KeyModifiers {
+                        shift: keyboard.mod_is_active(xkb, XKB_MOD_NAME_SHIFT),
+                        control: keyboard.mod_is_active(xkb, XKB_MOD_NAME_CTRL),
+                        alt: keyboard.mod_is_active(xkb, XKB_MOD_NAME_ALT),
+                        logo: keyboard.mod_is_active(xkb, XKB_MOD_NAME_LOGO),
+                    };
+                }
+            }
+            5 => { // repeat_info
+                self.repeat_rate = arg(0).i;
+                self.repeat_delay = arg(1).i;
+            }
+            _ => ()
+        }
+    }
+
+    fn key_to_keycode(&self, key: u32) -> Option<KeyCode> {
+        match (&self.xkb, &self.xkb_keyboard) {
+            (Some(xkb), Some(keyboard)) => Some(keysym_to_keycode(keyboard.key_get_one_sym(xkb, key))),
+            _ => None
+        }
+    }
+
+    fn stop_key_repeat(&mut self) {
+        self.repeat_key = None;
+        self.timers.stop_timer(KEY_REPEAT_TIMER_ID);
+    }
+
+    fn handle_key_repeat(&mut self) {
+        if let Some(key) = self.repeat_key {
+            self.send_key_down(key, true);
+            self.timers.start_timer(KEY_REPEAT_TIMER_ID, 1.0 / self.repeat_rate as f64, false);
+        }
+    }
+
+    fn send_key_down(&mut self, key: u32, is_repeat: bool) {
+        let key_code = match self.key_to_keycode(key) {
+            Some(key_code) => key_code,
+            None => return
+        };
+        let modifiers = self.modifiers;
+        if modifiers.control || modifiers.logo {
+            match key_code {
+                KeyCode::KeyV => { // paste
+                    self.read_selection();
+                }
+                KeyCode::KeyC | KeyCode::KeyX => {
+                    let response = Rc::new(RefCell::new(None));
+                    let event = TextClipboardEvent {
+                        response: response.clone()
+                    };
+                    self.do_callback(if key_code == KeyCode::KeyC {
+                        WaylandEvent::TextCopy(event)
+                    } else {
+                        WaylandEvent::TextCut(event)
+                    });
+                    let response = response.borrow();
+                    if let Some(response) = response.as_ref() {
+                        self.copy_to_clipboard(response);
+                    }
+                }
+                _ => ()
+            }
+        }
+        self.do_callback(WaylandEvent::KeyDown(KeyEvent {
+            key_code,
+            is_repeat,
+            modifiers,
+            time: self.time_now()
+        }));
+        let text = match (&self.xkb, &self.xkb_keyboard) {
+            (Some(xkb), Some(keyboard)) => keyboard.key_get_utf8(xkb, key),
+            _ => String::new()
+        };
+        if let Some(text) = typed_text(&modifiers, text) {
+            self.do_callback(WaylandEvent::TextInput(TextInputEvent {
+                input: text,
+                was_paste: false,
+                replace_last: false
+            }));
+        }
+    }
+
+    unsafe fn handle_touch_event(&mut self, opcode: u32, args: *mut wl_argument) {
+        let arg = | i: usize | *args.add(i);
+        match opcode {
+            0 => { // down
+                let (surface, id) = (arg(2).o, arg(3).i);
+                let abs = dvec2(wl_fixed_to_f64(arg(4).f), wl_fixed_to_f64(arg(5).f));
+                self.touches.retain( | t | t.id != id);
+                self.touches.push(TouchSlot {id, surface, state: TouchState::Start, abs});
+            }
+            1 => { // up
+                let id = arg(2).i;
+                if let Some(touch) = self.touches.iter_mut().find( | t | t.id == id) {
+                    touch.state = TouchState::Stop;
+                }
+            }
+            2 => { // motion
+                let id = arg(1).i;
+                if let Some(touch) = self.touches.iter_mut().find( | t | t.id == id) {
+                    touch.abs = dvec2(wl_fixed_to_f64(arg(2).f), wl_fixed_to_f64(arg(3).f));
+                    if !matches!(touch.state, TouchState::Start) {
+                        touch.state = TouchState::Move;
+                    }
+                }
+            }
+            3 => { // frame
+                self.send_touch_update();
+            }
+            4 => { // cancel
+                for touch in &mut self.touches {
+                    touch.state = TouchState::Stop;
+                }
+                self.send_touch_update();
+            }
+            _ => () // shape, orientation
+        }
+    }
+
+    fn send_touch_update(&mut self) {
+        let time = self.time_now();
+        for window_id in self.windows.iter().map( | w | w.window_id).collect::<Vec<_ >> () {
+            let surface = self.windows.iter().find( | w | w.window_id == window_id).unwrap().surface;
+            let touches: Vec<TouchPoint> = self.touches.iter().filter( | t | t.surface == surface).map( | t | TouchPoint {
+                state: t.state,
+                abs: t.abs,
+                time,
+                uid: t.id as u64,
+                rotation_angle: 0.0,
+                force: 0.0,
+                radius: dvec2(0.0, 0.0),
+                handled: Cell::new(Area::Empty),
+                sweep_lock: Cell::new(Area::Empty),
+            }).collect();
+            if !touches.is_empty() {
+                self.do_callback(WaylandEvent::TouchUpdate(TouchUpdateEvent {
+                    time,
+                    window_id,
+                    modifiers: self.modifiers,
+                    touches,
+                }));
+            }
+        }
+        self.touches.retain( | t | !matches!(t.state, TouchState::Stop));
+        for touch in &mut self.touches {
+            touch.state = TouchState::Stable;
+        }
+    }
+
+    pub fn create_window(&mut self, window_id: WindowId, inner_size: DVec2, title: &str) -> WindowGeom {
+        unsafe {
+            let surface = self.lib.marshal(self.compositor, WL_COMPOSITOR_CREATE_SURFACE, self.lib.wl_surface_interface, &mut [wl_argument::new_id()]);
+            self.add_dispatcher(surface, ProxyKind::Surface);
+            let xdg_surface = self.lib.marshal(
+                self.wm_base,
+                XDG_WM_BASE_GET_XDG_SURFACE,
+                &xdg_surface_interface,
+                &mut [wl_argument::new_id(), wl_argument::object(surface)]
+            );
+            self.add_dispatcher(xdg_surface, ProxyKind::XdgSurface);
+            let xdg_toplevel = self.lib.marshal(xdg_surface, XDG_SURFACE_GET_TOPLEVEL, &xdg_toplevel_interface, &mut [wl_argument::new_id()]);
+            self.add_dispatcher(xdg_toplevel, ProxyKind::XdgToplevel);
+
+            let title = CString::new(title).unwrap_or_default();
+            self.lib.marshal(xdg_toplevel, XDG_TOPLEVEL_SET_TITLE, ptr::null(), &mut [wl_argument::string(title.as_ptr())]);
+            self.lib.marshal(xdg_toplevel, XDG_TOPLEVEL_SET_APP_ID, ptr::null(), &mut [wl_argument::string(c"makepad".as_ptr())]);
+
+            let decoration = if !self.decoration_manager.is_null() {
+                let decoration = self.lib.marshal(
+                    self.decoration_manager,
+                    ZXDG_DECORATION_MANAGER_V1_GET_TOPLEVEL_DECORATION,
+                    &zxdg_toplevel_decoration_v1_interface,
+                    &mut [wl_argument::new_id(), wl_argument::object(xdg_toplevel)]
+                );
+                self.lib.marshal(decoration, ZXDG_TOPLEVEL_DECORATION_V1_SET_MODE, ptr::null(), &mut [
+                    wl_argument::uint(ZXDG_TOPLEVEL_DECORATION_V1_MODE_SERVER_SIDE)
+                ]);
+                decoration
+            } else {ptr::null_mut()};
+
+            // fractional scaling needs both the scale notifications and a viewport to present at
+            let (fractional_scale, viewport) = if !self.fractional_scale_manager.is_null() && !self.viewporter.is_null() {
+                let fractional_scale = self.lib.marshal(
+                    self.fractional_scale_manager,
+                    WP_FRACTIONAL_SCALE_MANAGER_V1_GET_FRACTIONAL_SCALE,
+                    &wp_fractional_scale_v1_interface,
+                    &mut [wl_argument::new_id(), wl_argument::object(surface)]
+                );
+                self.add_dispatcher(fractional_scale, ProxyKind::FractionalScale);
+                let viewport = self.lib.marshal(
+                    self.viewporter,
+                    WP_VIEWPORTER_GET_VIEWPORT,
+                    &wp_viewport_interface,
+                    &mut [wl_argument::new_id(), wl_argument::object(surface)]
+                );
+                (fractional_scale, viewport)
+            } else {(ptr::null_mut(), ptr::null_mut())};
+
+            // the initial commit without a buffer asks the compositor for a configure,
+            // we dont draw into the window until that has arrived
+            self.lib.marshal(surface, WL_SURFACE_COMMIT, ptr::null(), &mut []);
+
+            let egl_window = (self.lib_egl.wl_egl_window_create)(surface, inner_size.x as c_int, inner_size.y as c_int);
+
+            let window = Box::new(WaylandWindow {
+                window_id,
+                surface,
+                xdg_surface,
+                xdg_toplevel,
+                decoration,
+                fractional_scale,
+                viewport,
+                egl_window,
+                configured: false,
+                inner_size,
+                pending_size: None,
+                pending_states: (false, false),
+                preferred_scale: 1.0,
+                is_maximized: false,
+                is_fullscreen: false,
+                last_window_geom: WindowGeom::default(),
+                last_mouse_pos: DVec2::default(),
+            });
+            let geom = window.get_window_geom();
+            self.windows.push(window);
+            (self.lib.wl_display_flush)(self.display);
+            geom
+        }
+    }
+
+    // resizes the egl buffer to the window size and scale, then tells the cx about it
+    fn apply_window_size(&mut self, window_id: WindowId) {
+        let window = match self.windows.iter_mut().find( | w | w.window_id == window_id) {
+            Some(window) => window,
+            None => return
+        };
+        let dpi_factor = window.dpi_factor();
+        let pix_size = (window.inner_size * dpi_factor).floor();
+        unsafe {
+            (self.lib_egl.wl_egl_window_resize)(window.egl_window, pix_size.x as c_int, pix_size.y as c_int, 0, 0);
+            if !window.viewport.is_null() {
+                self.lib.marshal(window.viewport, WP_VIEWPORT_SET_DESTINATION, ptr::null(), &mut [
+                    wl_argument::int(window.inner_size.x as i32),
+                    wl_argument::int(window.inner_size.y as i32)
+                ]);
+            }
+            else if self.compositor_version >= 3 {
+                self.lib.marshal(window.surface, WL_SURFACE_SET_BUFFER_SCALE, ptr::null(), &mut [wl_argument::int(dpi_factor as i32)]);
+            }
+        }
+        let new_geom = window.get_window_geom();
+        let old_geom = std::mem::replace(&mut window.last_window_geom, new_geom.clone());
+        if old_geom != new_geom {
+            self.do_callback(WaylandEvent::WindowGeomChange(WindowGeomChangeEvent {
+                window_id,
+                old_geom,
+                new_geom
+            }));
+        }
+        self.do_callback(WaylandEvent::Paint);
+    }
+
+    pub fn window_is_configured(&self, window_id: WindowId) -> bool {
+        self.windows.iter().any( | w | w.window_id == window_id && w.configured)
+    }
+
+    pub fn egl_window(&self, window_id: WindowId) -> Option<*mut wl_egl_window> {
+        self.windows.iter().find( | w | w.window_id == window_id).map( | w | w.egl_window)
+    }
+
+    fn send_close_requested(&mut self, window_id: WindowId) {
+        let accept_close = Rc::new(Cell::new(true));
+        self.do_callback(WaylandEvent::WindowCloseRequested(WindowCloseRequestedEvent {
+            window_id,
+            accept_close: accept_close.clone()
+        }));
+        if accept_close.get() {
+            self.do_callback(WaylandEvent::WindowClosed(WindowClosedEvent {window_id}));
+        }
+    }
+
+    // only destroys the wayland objects, the egl surface on top has to be gone by now
+    pub fn close_window(&mut self, window_id: WindowId) {
+        if let Some(index) = self.windows.iter().position( | w | w.window_id == window_id) {
+            let window = self.windows.remove(index);
+            unsafe {
+                (self.lib_egl.wl_egl_window_destroy)(window.egl_window);
+                if !window.viewport.is_null() {
+                    self.lib.marshal_destroy(window.viewport, WP_VIEWPORT_DESTROY);
+                }
+                if !window.fractional_scale.is_null() {
+                    self.lib.marshal_destroy(window.fractional_scale, WP_FRACTIONAL_SCALE_V1_DESTROY);
+                }
+                if !window.decoration.is_null() {
+                    self.lib.marshal_destroy(window.decoration, ZXDG_TOPLEVEL_DECORATION_V1_DESTROY);
+                }
+                self.lib.marshal_destroy(window.xdg_toplevel, XDG_TOPLEVEL_DESTROY);
+                self.lib.marshal_destroy(window.xdg_surface, XDG_SURFACE_DESTROY);
+                self.lib.marshal_destroy(window.surface, WL_SURFACE_DESTROY);
+                (self.lib.wl_display_flush)(self.display);
+            }
+            if self.pointer_focus == window.surface {
+                self.pointer_focus = ptr::null_mut();
+            }
+            if self.keyboard_focus == window.surface {
+                self.keyboard_focus = ptr::null_mut();
+            }
+        }
+    }
+
+    fn toplevel_request(&self, window_id: WindowId, opcode: u32) {
+        if let Some(window) = self.windows.iter().find( | w | w.window_id == window_id) {
+            unsafe {
+                let mut args = if opcode == XDG_TOPLEVEL_SET_FULLSCREEN {
+                    vec![wl_argument::object(ptr::null_mut())]
+                } else {vec![]};
+                self.lib.marshal(window.xdg_toplevel, opcode, ptr::null(), &mut args);
+                (self.lib.wl_display_flush)(self.display);
+            }
+        }
+    }
+
+    pub fn minimize(&self, window_id: WindowId) {
+        self.toplevel_request(window_id, XDG_TOPLEVEL_SET_MINIMIZED);
+    }
+
+    pub fn maximize(&self, window_id: WindowId) {
+        self.toplevel_request(window_id, XDG_TOPLEVEL_SET_MAXIMIZED);
+    }
+
+    pub fn restore(&self, window_id: WindowId) {
+        self.toplevel_request(window_id, XDG_TOPLEVEL_UNSET_MAXIMIZED);
+    }
+
+    pub fn fullscreen(&self, window_id: WindowId) {
+        self.toplevel_request(window_id, XDG_TOPLEVEL_SET_FULLSCREEN);
+    }
+
+    pub fn normalize(&self, window_id: WindowId) {
+        self.toplevel_request(window_id, XDG_TOPLEVEL_UNSET_FULLSCREEN);
+    }
+
+    pub fn copy_to_clipboard(&mut self, text: &str) {
+        self.clipboard = text.to_string();
+        if self.data_device.is_null() {
+            return
+        }
+        unsafe {
+            if !self.data_source.is_null() {
+                self.lib.marshal_destroy(self.data_source, WL_DATA_SOURCE_DESTROY);
+            }
+            self.data_source = self.lib.marshal(
+                self.data_device_manager,
+                WL_DATA_DEVICE_MANAGER_CREATE_DATA_SOURCE,
+                self.lib.wl_data_source_interface,
+                &mut [wl_argument::new_id()]
+            );
+            self.add_dispatcher(self.data_source, ProxyKind::DataSource);
+            for mime in TEXT_MIME_TYPES {
+                self.lib.marshal(self.data_source, WL_DATA_SOURCE_OFFER, ptr::null(), &mut [wl_argument::string(mime.as_ptr() as *const c_char)]);
+            }
+            self.lib.marshal(self.data_device, WL_DATA_DEVICE_SET_SELECTION, ptr::null(), &mut [
+                wl_argument::object(self.data_source),
+                wl_argument::uint(self.keyboard_serial)
+            ]);
+            (self.lib.wl_display_flush)(self.display);
+        }
+        self.owns_selection = true;
+    }
+
+    fn read_selection(&mut self) {
+        // reading our own selection through the compositor would deadlock,
+        // we'd be waiting on the pipe while the send request waits in our queue
+        if self.owns_selection {
+            let text = self.clipboard.clone();
+            self.send_paste(text);
+            return
+        }
+        // a newer paste replaces one that is still in flight
+        self.pending_paste = None;
+        let Some(mime) = self.text_offers.mime_for(self.selection_offer) else {
+            return
+        };
+        let file = unsafe {
+            let mut fds = [0 as c_int; 2];
+            if libc_sys::pipe(fds.as_mut_ptr()) != 0 {
+                return
+            }
+            set_nonblocking(fds[0]);
+            self.lib.marshal(self.selection_offer, WL_DATA_OFFER_RECEIVE, ptr::null(), &mut [
+                wl_argument::string(mime.as_ptr() as *const c_char),
+                wl_argument::fd(fds[1])
+            ]);
+            (self.lib.wl_display_flush)(self.display);
+            libc_sys::close(fds[1]);
+            File::from_raw_fd(fds[0])
+        };
+        self.pending_paste = Some(PendingPaste::new(file, self.time_now()));
+        self.start_clipboard_timer();
+    }
+
+    fn start_clipboard_timer(&mut self) {
+        self.timers.stop_timer(CLIPBOARD_TIMER_ID);
+        self.timers.start_timer(CLIPBOARD_TIMER_ID, CLIPBOARD_POLL_INTERVAL, true);
+    }
+
+    fn poll_clipboard(&mut self) {
+        let time = self.time_now();
+        self.pending_sends.retain_mut( | send | match send.poll(time) {
+            TransferPoll::Pending => true,
+            TransferPoll::Done => false,
+            TransferPoll::Failed(err) => {
+                crate::error!("{}", err);
+                false
+            }
+        });
+        if let Some(paste) = &mut self.pending_paste {
+            match paste.poll(time) {
+                TransferPoll::Pending => (),
+                TransferPoll::Done => {
+                    let text = paste.text();
+                    self.pending_paste = None;
+                    self.send_paste(text);
+                }
+                TransferPoll::Failed(err) => {
+                    crate::error!("{}", err);
+                    self.pending_paste = None;
+                }
+            }
+        }
+        if self.pending_paste.is_none() && self.pending_sends.is_empty() {
+            self.timers.stop_timer(CLIPBOARD_TIMER_ID);
+        }
+    }
+
+    fn send_paste(&mut self, text: String) {
+        self.do_callback(WaylandEvent::TextInput(TextInputEvent {
+            input: text,
+            was_paste: true,
+            replace_last: false
+        }));
+    }
+
+    pub fn set_mouse_cursor(&mut self, cursor: MouseCursor) {
+        if self.current_cursor != cursor {
+            self.current_cursor = cursor;
+            self.set_mouse_cursor_(cursor);
+        }
+    }
+
+    fn set_mouse_cursor_(&mut self, cursor: MouseCursor) {
+        if self.pointer.is_null() || self.pointer_focus.is_null() {
+            return
+        }
+        unsafe {
+            if let MouseCursor::Hidden = cursor {
+                self.lib.marshal(self.pointer, 0, ptr::null(), &mut [
+                    wl_argument::uint(self.pointer_serial),
+                    wl_argument::object(ptr::null_mut()),
+                    wl_argument::int(0),
+                    wl_argument::int(0)
+                ]);
+                return
+            }
+            if self.cursor_shape_device.is_null() {
+                return
+            }
+            // shape values of the cursor-shape-v1 protocol
+            let shape = match cursor {
+                MouseCursor::Hidden | MouseCursor::Default | MouseCursor::Arrow => 1,
+                MouseCursor::Help => 3,
+                MouseCursor::Hand => 4,
+                MouseCursor::Wait => 6,
+                MouseCursor::Crosshair => 8,
+                MouseCursor::Text => 9,
+                MouseCursor::Move => 13,
+                MouseCursor::NotAllowed => 15,
+                MouseCursor::EResize => 18,
+                MouseCursor::NResize => 19,
+                MouseCursor::NeResize => 20,
+                MouseCursor::NwResize => 21,
+                MouseCursor::SResize => 22,
+                MouseCursor::SeResize => 23,
+                MouseCursor::SwResize => 24,
+                MouseCursor::WResize => 25,
+                MouseCursor::EwResize => 26,
+                MouseCursor::NsResize => 27,
+                MouseCursor::NeswResize => 28,
+                MouseCursor::NwseResize => 29,
+                MouseCursor::ColResize => 30,
+                MouseCursor::RowResize => 31,
+            };
+            self.lib.marshal(self.cursor_shape_device, WP_CURSOR_SHAPE_DEVICE_V1_SET_SHAPE, ptr::null(), &mut [
+                wl_argument::uint(self.pointer_serial),
+                wl_argument::uint(shape)
+            ]);
+        }
+    }
+
+    // reads whatever is waiting on the socket and dispatches it, optionally blocking
+    // until the compositor or one of our timers has something for us
+    unsafe fn read_and_dispatch(&mut self, block: bool) {
+        let display = self.display;
+        while (self.lib.wl_display_prepare_read)(display) != 0 {
+            (self.lib.wl_display_dispatch_pending)(display);
+        }
+        (self.lib.wl_display_flush)(display);
+        if block && self.event_flow == EventFlow::Wait {
+            self.timers.select(self.display_fd);
+        }
+        // the socket is read non blocking, so this is fine if nothing arrived
+        (self.lib.wl_display_read_events)(display);
+        if (self.lib.wl_display_dispatch_pending)(display) < 0 {
+            crate::error!("lost the connection to the wayland compositor");
+            self.terminate_event_loop();
+        }
+    }
+
+    fn fire_timers(&mut self, timer_ids: &mut Vec<u64>) {
+        let time = self.time_now();
+        self.timers.update_timers(timer_ids);
+        for timer_id in timer_ids.iter() {
+            if *timer_id == KEY_REPEAT_TIMER_ID {
+                self.handle_key_repeat();
+                continue;
+            }
+            if *timer_id == CLIPBOARD_TIMER_ID {
+                self.poll_clipboard();
+                continue;
+            }
+            self.do_callback(
+                WaylandEvent::Timer(TimerEvent {
+                    timer_id: *timer_id,
+                    time: Some(time)
+                })
+            );
+        }
+    }
+
+    pub fn event_loop(&mut self) {
+        unsafe {
+            self.do_callback(WaylandEvent::Paint);
+
+            let mut timer_ids = Vec::new();
+            while self.event_loop_running {
+                match self.event_flow {
+                    EventFlow::Exit => {
+                        break;
+                    }
+                    EventFlow::Wait => {
+                        self.fire_timers(&mut timer_ids);
+                        self.read_and_dispatch(true);
+                        self.event_flow = EventFlow::Poll;
+                    }
+                    EventFlow::Poll => {
+                        self.fire_timers(&mut timer_ids);
+                        self.read_and_dispatch(false);
+                        self.do_callback(WaylandEvent::Paint);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn do_callback(&mut self, event: WaylandEvent) {
+        if let Some(mut callback) = self.event_callback.take() {
+            self.event_flow = callback(self, event);
+            if let EventFlow::Exit = self.event_flow {
+                self.terminate_event_loop();
+            }
+            self.event_callback = Some(callback);
+        }
+    }
+
+    pub fn terminate_event_loop(&mut self) {
+        self.event_loop_running = false;
+        self.event_flow = EventFlow::Exit;
+    }
+
+    pub fn start_timer(&mut self, id: u64, timeout: f64, repeats: bool) {
+        self.timers.start_timer(id, timeout, repeats);
+    }
+
+    pub fn stop_timer(&mut self, id: u64) {
+        self.timers.stop_timer(id);
+    }
+
+    pub fn time_now(&self) -> f64 {
+        self.timers.time_now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(id: usize) -> *mut wl_proxy {
+        id as *mut wl_proxy
+    }
+
+    // a pipe with a non blocking end for the side we transfer on, like the compositor hands us
+    fn pipe(nonblocking_read: bool) -> (File, File) {
+        let mut fds = [0 as c_int; 2];
+        assert_eq!(unsafe {libc_sys::pipe(fds.as_mut_ptr())}, 0);
+        set_nonblocking(if nonblocking_read {fds[0]} else {fds[1]});
+        unsafe {(File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))}
+    }
+
+    #[test]
+    fn receives_the_preferred_text_mime_type_an_offer_has() {
+        let mut offers = TextOffers::default();
+        offers.add_mime(offer(1), b"text/plain\0");
+        offers.add_mime(offer(1), b"image/png\0");
+        offers.add_mime(offer(1), b"UTF8_STRING\0");
+        offers.add_mime(offer(2), b"UTF8_STRING\0");
+        offers.add_mime(offer(2), b"text/plain;charset=utf-8\0");
+        offers.add_mime(offer(2), b"text/plain\0");
+        offers.add_mime(offer(3), b"text/plain\0");
+        offers.add_mime(offer(4), b"image/png\0");
+        assert_eq!(offers.mime_for(offer(1)), Some(&b"UTF8_STRING\0"[..]));
+        assert_eq!(offers.mime_for(offer(2)), Some(&b"text/plain;charset=utf-8\0"[..]));
+        assert_eq!(offers.mime_for(offer(3)), Some(&b"text/plain\0"[..]));
+        assert_eq!(offers.mime_for(offer(4)), None);
+        assert_eq!(offers.mime_for(ptr::null_mut()), None);
+
+        offers.remove(offer(1));
+        assert_eq!(offers.mime_for(offer(1)), None);
+        assert_eq!(offers.mime_for(offer(3)), Some(&b"text/plain\0"[..]));
+    }
+
+    #[test]
+    fn reads_a_paste_without_blocking_until_the_owner_closes_the_pipe() {
+        let (reader, mut writer) = pipe(true);
+        let mut paste = PendingPaste::new(reader, 0.0);
+        assert_eq!(paste.poll(0.0), TransferPoll::Pending);
+        // splits the ö across two writes
+        writer.write_all(b"gr\xc3").unwrap();
+        assert_eq!(paste.poll(0.5), TransferPoll::Pending);
+        writer.write_all(b"\xb6\xc3\x9fe").unwrap();
+        // progress keeps a slow owner from timing out
+        assert_eq!(paste.poll(1.2), TransferPoll::Pending);
+        drop(writer);
+        assert_eq!(paste.poll(1.3), TransferPoll::Done);
+        assert_eq!(paste.text(), "größe");
+    }
+
+    #[test]
+    fn gives_up_on_a_paste_the_owner_stops_sending() {
+        let (reader, mut writer) = pipe(true);
+        let mut paste = PendingPaste::new(reader, 0.0);
+        writer.write_all(b"abc").unwrap();
+        assert_eq!(paste.poll(0.5), TransferPoll::Pending);
+        assert_eq!(paste.poll(1.4), TransferPoll::Pending);
+        assert!(matches!(paste.poll(1.6), TransferPoll::Failed(_)));
+    }
+
+    #[test]
+    fn sends_a_large_selection_as_the_reader_drains_the_pipe() {
+        let (mut reader, writer) = pipe(false);
+        let data: Vec<u8> = (0..1 << 20).map( | i | (i % 251) as u8).collect();
+        let mut send = PendingSend::new(writer, data.clone(), 0.0);
+        // a full pipe leaves the rest for later instead of blocking
+        assert_eq!(send.poll(0.0), TransferPoll::Pending);
+        assert!(send.written > 0 && send.written < data.len());
+
+        let mut received = Vec::new();
+        let mut buf = vec![0u8; 1 << 16];
+        let mut time = 0.0;
+        loop {
+            time += 0.01;
+            let n = reader.read(&mut buf).unwrap();
+            received.extend_from_slice(&buf[..n]);
+            match send.poll(time) {
+                TransferPoll::Pending => (),
+                TransferPoll::Done => break,
+                TransferPoll::Failed(err) => panic!("{}", err),
+            }
+        }
+        drop(send);
+        reader.read_to_end(&mut received).unwrap();
+        assert!(received == data);
+    }
+
+    #[test]
+    fn gives_up_on_a_send_the_reader_stops_reading_or_closes() {
+        let (reader, writer) = pipe(false);
+        let mut send = PendingSend::new(writer, vec![b'x'; 1 << 20], 0.0);
+        assert_eq!(send.poll(0.0), TransferPoll::Pending);
+        assert_eq!(send.poll(0.9), TransferPoll::Pending);
+        assert!(matches!(send.poll(1.1), TransferPoll::Failed(_)));
+
+        let mut send = PendingSend::new(send.file, vec![b'x'; 16], 0.0);
+        drop(reader);
+        assert!(matches!(send.poll(0.0), TransferPoll::Failed(_)));
+    }
+
+    #[test]
+    fn reads_the_keymap_as_a_nul_terminated_string() {
+        let keymap = b"xkb_keymap {};\0";
+        assert_eq!(read_keymap(&mut &keymap[..], keymap.len()).unwrap(), keymap);
+        // some compositors leave off the nul
+        assert_eq!(read_keymap(&mut &b"xkb_keymap {};"[..], 14).unwrap(), keymap);
+        assert_eq!(read_keymap(&mut &b"xkb_keymap {};\0\0\0"[..], 15).unwrap(), keymap);
+        assert_eq!(read_keymap(&mut &b"xkb_key"[..], 14), None);
+    }
+
+    #[test]
+    fn types_text_only_for_unmodified_printable_keys() {
+        let none = KeyModifiers::default();
+        let shift = KeyModifiers {shift: true, ..none};
+        assert_eq!(typed_text(&none, "a".to_string()), Some("a".to_string()));
+        assert_eq!(typed_text(&shift, "\u{c4}".to_string()), Some("\u{c4}".to_string()));
+        for modifiers in [
+            KeyModifiers {control: true, ..none},
+            KeyModifiers {logo: true, ..none},
+            KeyModifiers {alt: true, ..none},
+        ] {
+            assert_eq!(typed_text(&modifiers, "v".to_string()), None);
+        }
+        assert_eq!(typed_text(&none, String::new()), None);
+        for control in ["\u{8}", "\u{1b}", "\r", "\t", "\u{7f}"] {
+            assert_eq!(typed_text(&none, control.to_string()), None);
+        }
+    }
+}
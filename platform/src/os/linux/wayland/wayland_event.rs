@@ -0,0 +1,41 @@
+use {
+    crate::{
+        event::{
+            MouseDownEvent,
+            MouseUpEvent,
+            MouseMoveEvent,
+            ScrollEvent,
+            TouchUpdateEvent,
+            WindowGeomChangeEvent,
+            WindowCloseRequestedEvent,
+            WindowClosedEvent,
+            TextInputEvent,
+            KeyEvent,
+            TextClipboardEvent,
+            TimerEvent,
+        },
+    }
+};
+
+#[derive(Debug)]
+pub enum WaylandEvent {
+    AppGotFocus,
+    AppLostFocus,
+    WindowGeomChange(WindowGeomChangeEvent),
+    WindowClosed(WindowClosedEvent),
+    Paint,
+    
+    MouseDown(MouseDownEvent),
+    MouseUp(MouseUpEvent),
+    MouseMove(MouseMoveEvent),
+    Scroll(ScrollEvent),
+    TouchUpdate(TouchUpdateEvent),
+    
+    WindowCloseRequested(WindowCloseRequestedEvent),
+    TextInput(TextInputEvent),
+    KeyDown(KeyEvent),
+    KeyUp(KeyEvent),
+    TextCopy(TextClipboardEvent),
+    TextCut(TextClipboardEvent),
+    Timer(TimerEvent),
+}
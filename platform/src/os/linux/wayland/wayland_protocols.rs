@@ -0,0 +1,164 @@
+#![allow(non_upper_case_globals)]
+
+use {
+    std::{ptr, os::raw::c_char},
+    self::super::wayland_sys::{wl_interface, wl_message},
+};
+
+// Hand written interface tables for the non-core protocols we speak, equivalent to what
+// wayland-scanner generates. The object types of arguments are left null, libwayland
+// only needs them for new_id arguments of events and none of these protocols have any.
+
+const NULL_TYPES: [*const wl_interface; 4] = [ptr::null(); 4];
+
+macro_rules! message {
+    ($name:literal, $signature:literal) => {
+        wl_message {
+            name: concat!($name, "\0").as_ptr() as *const c_char,
+            signature: concat!($signature, "\0").as_ptr() as *const c_char,
+            types: &NULL_TYPES as *const _ as *const *const wl_interface,
+        }
+    }
+}
+
+macro_rules! interface {
+    ($name:ident, $wire_name:literal, $version:literal, [$($method:expr),*], [$($event:expr),*]) => {
+        pub static $name: wl_interface = {
+            const METHODS: &[wl_message] = &[$($method),*];
+            const EVENTS: &[wl_message] = &[$($event),*];
+            wl_interface {
+                name: concat!($wire_name, "\0").as_ptr() as *const c_char,
+                version: $version,
+                method_count: METHODS.len() as i32,
+                methods: METHODS.as_ptr(),
+                event_count: EVENTS.len() as i32,
+                events: EVENTS.as_ptr(),
+            }
+        };
+    }
+}
+
+// xdg-shell
+pub const XDG_WM_BASE_DESTROY: u32 = 0;
+pub const XDG_WM_BASE_GET_XDG_SURFACE: u32 = 2;
+pub const XDG_WM_BASE_PONG: u32 = 3;
+pub const XDG_SURFACE_DESTROY: u32 = 0;
+pub const XDG_SURFACE_GET_TOPLEVEL: u32 = 1;
+pub const XDG_SURFACE_ACK_CONFIGURE: u32 = 4;
+pub const XDG_TOPLEVEL_DESTROY: u32 = 0;
+pub const XDG_TOPLEVEL_SET_TITLE: u32 = 2;
+pub const XDG_TOPLEVEL_SET_APP_ID: u32 = 3;
+pub const XDG_TOPLEVEL_MOVE: u32 = 5;
+pub const XDG_TOPLEVEL_SET_MAXIMIZED: u32 = 9;
+pub const XDG_TOPLEVEL_UNSET_MAXIMIZED: u32 = 10;
+pub const XDG_TOPLEVEL_SET_FULLSCREEN: u32 = 11;
+pub const XDG_TOPLEVEL_UNSET_FULLSCREEN: u32 = 12;
+pub const XDG_TOPLEVEL_SET_MINIMIZED: u32 = 13;
+
+pub const XDG_TOPLEVEL_STATE_MAXIMIZED: u32 = 1;
+pub const XDG_TOPLEVEL_STATE_FULLSCREEN: u32 = 2;
+
+interface!(xdg_wm_base_interface, "xdg_wm_base", 1, [
+    message!("destroy", ""),
+    message!("create_positioner", "n"),
+    message!("get_xdg_surface", "no"),
+    message!("pong", "u")
+], [
+    message!("ping", "u")
+]);
+
+interface!(xdg_surface_interface, "xdg_surface", 1, [
+    message!("destroy", ""),
+    message!("get_toplevel", "n"),
+    message!("get_popup", "n?oo"),
+    message!("set_window_geometry", "iiii"),
+    message!("ack_configure", "u")
+], [
+    message!("configure", "u")
+]);
+
+interface!(xdg_toplevel_interface, "xdg_toplevel", 1, [
+    message!("destroy", ""),
+    message!("set_parent", "?o"),
+    message!("set_title", "s"),
+    message!("set_app_id", "s"),
+    message!("show_window_menu", "ouii"),
+    message!("move", "ou"),
+    message!("resize", "ouu"),
+    message!("set_max_size", "ii"),
+    message!("set_min_size", "ii"),
+    message!("set_maximized", ""),
+    message!("unset_maximized", ""),
+    message!("set_fullscreen", "?o"),
+    message!("unset_fullscreen", ""),
+    message!("set_minimized", "")
+], [
+    message!("configure", "iia"),
+    message!("close", "")
+]);
+
+// fractional-scale-v1
+pub const WP_FRACTIONAL_SCALE_MANAGER_V1_GET_FRACTIONAL_SCALE: u32 = 1;
+pub const WP_FRACTIONAL_SCALE_V1_DESTROY: u32 = 0;
+
+interface!(wp_fractional_scale_manager_v1_interface, "wp_fractional_scale_manager_v1", 1, [
+    message!("destroy", ""),
+    message!("get_fractional_scale", "no")
+], []);
+
+interface!(wp_fractional_scale_v1_interface, "wp_fractional_scale_v1", 1, [
+    message!("destroy", "")
+], [
+    message!("preferred_scale", "u")
+]);
+
+// viewporter
+pub const WP_VIEWPORTER_GET_VIEWPORT: u32 = 1;
+pub const WP_VIEWPORT_DESTROY: u32 = 0;
+pub const WP_VIEWPORT_SET_DESTINATION: u32 = 2;
+
+interface!(wp_viewporter_interface, "wp_viewporter", 1, [
+    message!("destroy", ""),
+    message!("get_viewport", "no")
+], []);
+
+interface!(wp_viewport_interface, "wp_viewport", 1, [
+    message!("destroy", ""),
+    message!("set_source", "ffff"),
+    message!("set_destination", "ii")
+], []);
+
+// xdg-decoration-unstable-v1
+pub const ZXDG_DECORATION_MANAGER_V1_GET_TOPLEVEL_DECORATION: u32 = 1;
+pub const ZXDG_TOPLEVEL_DECORATION_V1_DESTROY: u32 = 0;
+pub const ZXDG_TOPLEVEL_DECORATION_V1_SET_MODE: u32 = 1;
+pub const ZXDG_TOPLEVEL_DECORATION_V1_MODE_SERVER_SIDE: u32 = 2;
+
+interface!(zxdg_decoration_manager_v1_interface, "zxdg_decoration_manager_v1", 1, [
+    message!("destroy", ""),
+    message!("get_toplevel_decoration", "no")
+], []);
+
+interface!(zxdg_toplevel_decoration_v1_interface, "zxdg_toplevel_decoration_v1", 1, [
+    message!("destroy", ""),
+    message!("set_mode", "u"),
+    message!("unset_mode", "")
+], [
+    message!("configure", "u")
+]);
+
+// cursor-shape-v1
+pub const WP_CURSOR_SHAPE_MANAGER_V1_GET_POINTER: u32 = 1;
+pub const WP_CURSOR_SHAPE_DEVICE_V1_DESTROY: u32 = 0;
+pub const WP_CURSOR_SHAPE_DEVICE_V1_SET_SHAPE: u32 = 1;
+
+interface!(wp_cursor_shape_manager_v1_interface, "wp_cursor_shape_manager_v1", 1, [
+    message!("destroy", ""),
+    message!("get_pointer", "no"),
+    message!("get_tablet_tool_v2", "no")
+], []);
+
+interface!(wp_cursor_shape_device_v1_interface, "wp_cursor_shape_device_v1", 1, [
+    message!("destroy", ""),
+    message!("set_shape", "uu")
+], []);
@@ -0,0 +1,255 @@
+#![allow(non_camel_case_types)]
+#![allow(clippy::missing_safety_doc)]
+#![allow(non_upper_case_globals)]
+#![allow(dead_code)]
+
+use {
+    std::os::raw::{c_char, c_int, c_void},
+    self::super::super::module_loader::ModuleLoader,
+};
+
+// the subset of the libwayland-client abi we use. Everything is loaded at runtime
+// so a makepad binary still starts on machines without wayland installed.
+
+#[repr(C)]
+pub struct wl_proxy {
+    _private: [u8; 0],
+}
+
+pub type wl_display = wl_proxy;
+
+#[repr(C)]
+pub struct wl_message {
+    pub name: *const c_char,
+    pub signature: *const c_char,
+    pub types: *const *const wl_interface,
+}
+
+#[repr(C)]
+pub struct wl_interface {
+    pub name: *const c_char,
+    pub version: c_int,
+    pub method_count: c_int,
+    pub methods: *const wl_message,
+    pub event_count: c_int,
+    pub events: *const wl_message,
+}
+
+// the protocol tables are immutable statics shared with libwayland
+unsafe impl Sync for wl_message {}
+unsafe impl Sync for wl_interface {}
+
+#[repr(C)]
+pub struct wl_array {
+    pub size: usize,
+    pub alloc: usize,
+    pub data: *mut c_void,
+}
+
+pub type wl_fixed_t = i32;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union wl_argument {
+    pub i: i32,
+    pub u: u32,
+    pub f: wl_fixed_t,
+    pub s: *const c_char,
+    pub o: *mut wl_proxy,
+    pub n: u32,
+    pub a: *mut wl_array,
+    pub h: i32,
+}
+
+impl wl_argument {
+    pub fn int(i: i32) -> Self {Self {i}}
+    pub fn uint(u: u32) -> Self {Self {u}}
+    pub fn fixed(v: f64) -> Self {Self {f: (v * 256.0) as i32}}
+    pub fn string(s: *const c_char) -> Self {Self {s}}
+    pub fn object(o: *mut wl_proxy) -> Self {Self {o}}
+    pub fn new_id() -> Self {Self {o: std::ptr::null_mut()}}
+    pub fn fd(h: i32) -> Self {Self {h}}
+}
+
+pub fn wl_fixed_to_f64(f: wl_fixed_t) -> f64 {
+    f as f64 / 256.0
+}
+
+pub type wl_dispatcher_func_t = unsafe extern "C" fn(
+    user_data: *const c_void,
+    target: *mut c_void,
+    opcode: u32,
+    msg: *const wl_message,
+    args: *mut wl_argument,
+) -> c_int;
+
+pub const WL_MARSHAL_FLAG_DESTROY: u32 = 1;
+
+pub const WL_SEAT_CAPABILITY_POINTER: u32 = 1;
+pub const WL_SEAT_CAPABILITY_KEYBOARD: u32 = 2;
+pub const WL_SEAT_CAPABILITY_TOUCH: u32 = 4;
+
+pub const WL_POINTER_BUTTON_STATE_PRESSED: u32 = 1;
+pub const WL_POINTER_AXIS_VERTICAL_SCROLL: u32 = 0;
+pub const WL_POINTER_AXIS_HORIZONTAL_SCROLL: u32 = 1;
+
+pub const WL_KEYBOARD_KEYMAP_FORMAT_XKB_V1: u32 = 1;
+pub const WL_KEYBOARD_KEY_STATE_PRESSED: u32 = 1;
+
+// linux/input-event-codes.h
+pub const BTN_LEFT: u32 = 0x110;
+pub const BTN_RIGHT: u32 = 0x111;
+pub const BTN_MIDDLE: u32 = 0x112;
+
+// request opcodes of the core protocol
+pub const WL_DISPLAY_GET_REGISTRY: u32 = 1;
+pub const WL_REGISTRY_BIND: u32 = 0;
+pub const WL_COMPOSITOR_CREATE_SURFACE: u32 = 0;
+pub const WL_SURFACE_DESTROY: u32 = 0;
+pub const WL_SURFACE_COMMIT: u32 = 6;
+pub const WL_SURFACE_SET_BUFFER_SCALE: u32 = 8;
+pub const WL_SEAT_GET_POINTER: u32 = 0;
+pub const WL_SEAT_GET_KEYBOARD: u32 = 1;
+pub const WL_SEAT_GET_TOUCH: u32 = 2;
+pub const WL_POINTER_RELEASE: u32 = 1;
+pub const WL_KEYBOARD_RELEASE: u32 = 0;
+pub const WL_TOUCH_RELEASE: u32 = 0;
+pub const WL_DATA_DEVICE_MANAGER_CREATE_DATA_SOURCE: u32 = 0;
+pub const WL_DATA_DEVICE_MANAGER_GET_DATA_DEVICE: u32 = 1;
+pub const WL_DATA_SOURCE_OFFER: u32 = 0;
+pub const WL_DATA_SOURCE_DESTROY: u32 = 1;
+pub const WL_DATA_DEVICE_SET_SELECTION: u32 = 1;
+pub const WL_DATA_OFFER_RECEIVE: u32 = 1;
+pub const WL_DATA_OFFER_DESTROY: u32 = 2;
+
+pub struct LibWaylandClient {
+    pub wl_display_connect: unsafe extern "C" fn(name: *const c_char) -> *mut wl_display,
+    pub wl_display_disconnect: unsafe extern "C" fn(display: *mut wl_display),
+    pub wl_display_get_fd: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_dispatch_pending: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_flush: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_roundtrip: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_prepare_read: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_read_events: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_cancel_read: unsafe extern "C" fn(display: *mut wl_display),
+    pub wl_proxy_marshal_array_flags: unsafe extern "C" fn(
+        proxy: *mut wl_proxy,
+        opcode: u32,
+        interface: *const wl_interface,
+        version: u32,
+        flags: u32,
+        args: *mut wl_argument,
+    ) -> *mut wl_proxy,
+    pub wl_proxy_add_dispatcher: unsafe extern "C" fn(
+        proxy: *mut wl_proxy,
+        dispatcher: wl_dispatcher_func_t,
+        implementation: *const c_void,
+        data: *mut c_void,
+    ) -> c_int,
+    pub wl_proxy_get_version: unsafe extern "C" fn(proxy: *mut wl_proxy) -> u32,
+    pub wl_proxy_destroy: unsafe extern "C" fn(proxy: *mut wl_proxy),
+
+    pub wl_registry_interface: *const wl_interface,
+    pub wl_compositor_interface: *const wl_interface,
+    pub wl_surface_interface: *const wl_interface,
+    pub wl_seat_interface: *const wl_interface,
+    pub wl_pointer_interface: *const wl_interface,
+    pub wl_keyboard_interface: *const wl_interface,
+    pub wl_touch_interface: *const wl_interface,
+    pub wl_data_device_manager_interface: *const wl_interface,
+    pub wl_data_device_interface: *const wl_interface,
+    pub wl_data_source_interface: *const wl_interface,
+    pub wl_data_offer_interface: *const wl_interface,
+
+    _keep_module_alive: ModuleLoader,
+}
+
+impl LibWaylandClient {
+    pub fn try_load() -> Option<LibWaylandClient> {
+        let module = ModuleLoader::load("libwayland-client.so.0").or_else(|_| ModuleLoader::load("libwayland-client.so")).ok()?;
+        Some(LibWaylandClient {
+            wl_display_connect: module.get_symbol("wl_display_connect").ok()?,
+            wl_display_disconnect: module.get_symbol("wl_display_disconnect").ok()?,
+            wl_display_get_fd: module.get_symbol("wl_display_get_fd").ok()?,
+            wl_display_dispatch_pending: module.get_symbol("wl_display_dispatch_pending").ok()?,
+            wl_display_flush: module.get_symbol("wl_display_flush").ok()?,
+            wl_display_roundtrip: module.get_symbol("wl_display_roundtrip").ok()?,
+            wl_display_prepare_read: module.get_symbol("wl_display_prepare_read").ok()?,
+            wl_display_read_events: module.get_symbol("wl_display_read_events").ok()?,
+            wl_display_cancel_read: module.get_symbol("wl_display_cancel_read").ok()?,
+            wl_proxy_marshal_array_flags: module.get_symbol("wl_proxy_marshal_array_flags").ok()?,
+            wl_proxy_add_dispatcher: module.get_symbol("wl_proxy_add_dispatcher").ok()?,
+            wl_proxy_get_version: module.get_symbol("wl_proxy_get_version").ok()?,
+            wl_proxy_destroy: module.get_symbol("wl_proxy_destroy").ok()?,
+
+            // the core interfaces are exported as data symbols
+            wl_registry_interface: module.get_symbol("wl_registry_interface").ok()?,
+            wl_compositor_interface: module.get_symbol("wl_compositor_interface").ok()?,
+            wl_surface_interface: module.get_symbol("wl_surface_interface").ok()?,
+            wl_seat_interface: module.get_symbol("wl_seat_interface").ok()?,
+            wl_pointer_interface: module.get_symbol("wl_pointer_interface").ok()?,
+            wl_keyboard_interface: module.get_symbol("wl_keyboard_interface").ok()?,
+            wl_touch_interface: module.get_symbol("wl_touch_interface").ok()?,
+            wl_data_device_manager_interface: module.get_symbol("wl_data_device_manager_interface").ok()?,
+            wl_data_device_interface: module.get_symbol("wl_data_device_interface").ok()?,
+            wl_data_source_interface: module.get_symbol("wl_data_source_interface").ok()?,
+            wl_data_offer_interface: module.get_symbol("wl_data_offer_interface").ok()?,
+
+            _keep_module_alive: module,
+        })
+    }
+
+    // sends a request, returning the new proxy if the request creates one
+    pub unsafe fn marshal(
+        &self,
+        proxy: *mut wl_proxy,
+        opcode: u32,
+        interface: *const wl_interface,
+        args: &mut [wl_argument],
+    ) -> *mut wl_proxy {
+        let version = (self.wl_proxy_get_version)(proxy);
+        (self.wl_proxy_marshal_array_flags)(proxy, opcode, interface, version, 0, args.as_mut_ptr())
+    }
+
+    // like marshal, but binds the new proxy to an explicit version (wl_registry.bind)
+    pub unsafe fn marshal_versioned(
+        &self,
+        proxy: *mut wl_proxy,
+        opcode: u32,
+        interface: *const wl_interface,
+        version: u32,
+        args: &mut [wl_argument],
+    ) -> *mut wl_proxy {
+        (self.wl_proxy_marshal_array_flags)(proxy, opcode, interface, version, 0, args.as_mut_ptr())
+    }
+
+    // sends a destructor request and frees the proxy
+    pub unsafe fn marshal_destroy(&self, proxy: *mut wl_proxy, opcode: u32) {
+        let version = (self.wl_proxy_get_version)(proxy);
+        (self.wl_proxy_marshal_array_flags)(proxy, opcode, std::ptr::null(), version, WL_MARSHAL_FLAG_DESTROY, std::ptr::null_mut());
+    }
+}
+
+#[repr(C)]
+pub struct wl_egl_window {
+    _private: [u8; 0],
+}
+
+pub struct LibWaylandEgl {
+    pub wl_egl_window_create: unsafe extern "C" fn(surface: *mut wl_proxy, width: c_int, height: c_int) -> *mut wl_egl_window,
+    pub wl_egl_window_destroy: unsafe extern "C" fn(egl_window: *mut wl_egl_window),
+    pub wl_egl_window_resize: unsafe extern "C" fn(egl_window: *mut wl_egl_window, width: c_int, height: c_int, dx: c_int, dy: c_int),
+    _keep_module_alive: ModuleLoader,
+}
+
+impl LibWaylandEgl {
+    pub fn try_load() -> Option<LibWaylandEgl> {
+        let module = ModuleLoader::load("libwayland-egl.so.1").or_else(|_| ModuleLoader::load("libwayland-egl.so")).ok()?;
+        Some(LibWaylandEgl {
+            wl_egl_window_create: module.get_symbol("wl_egl_window_create").ok()?,
+            wl_egl_window_destroy: module.get_symbol("wl_egl_window_destroy").ok()?,
+            wl_egl_window_resize: module.get_symbol("wl_egl_window_resize").ok()?,
+            _keep_module_alive: module,
+        })
+    }
+}
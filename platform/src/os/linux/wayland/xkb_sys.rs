@@ -0,0 +1,157 @@
+#![allow(non_camel_case_types)]
+#![allow(clippy::missing_safety_doc)]
+
+use {
+    std::os::raw::{c_char, c_int},
+    self::super::super::module_loader::ModuleLoader,
+};
+
+#[repr(C)]
+pub struct xkb_context {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct xkb_keymap {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct xkb_state {
+    _private: [u8; 0],
+}
+
+pub type xkb_keycode_t = u32;
+pub type xkb_keysym_t = u32;
+pub type xkb_mod_mask_t = u32;
+pub type xkb_layout_index_t = u32;
+
+pub const XKB_CONTEXT_NO_FLAGS: c_int = 0;
+pub const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+pub const XKB_KEYMAP_COMPILE_NO_FLAGS: c_int = 0;
+pub const XKB_STATE_MODS_EFFECTIVE: c_int = 1 << 3;
+
+pub const XKB_MOD_NAME_SHIFT: &[u8] = b"Shift\0";
+pub const XKB_MOD_NAME_CTRL: &[u8] = b"Control\0";
+pub const XKB_MOD_NAME_ALT: &[u8] = b"Mod1\0";
+pub const XKB_MOD_NAME_LOGO: &[u8] = b"Mod4\0";
+
+pub struct LibXkbCommon {
+    pub xkb_context_new: unsafe extern "C" fn(flags: c_int) -> *mut xkb_context,
+    pub xkb_context_unref: unsafe extern "C" fn(context: *mut xkb_context),
+    pub xkb_keymap_new_from_string: unsafe extern "C" fn(
+        context: *mut xkb_context,
+        string: *const c_char,
+        format: c_int,
+        flags: c_int,
+    ) -> *mut xkb_keymap,
+    pub xkb_keymap_unref: unsafe extern "C" fn(keymap: *mut xkb_keymap),
+    pub xkb_keymap_key_repeats: unsafe extern "C" fn(keymap: *mut xkb_keymap, key: xkb_keycode_t) -> c_int,
+    pub xkb_state_new: unsafe extern "C" fn(keymap: *mut xkb_keymap) -> *mut xkb_state,
+    pub xkb_state_unref: unsafe extern "C" fn(state: *mut xkb_state),
+    pub xkb_state_update_mask: unsafe extern "C" fn(
+        state: *mut xkb_state,
+        depressed_mods: xkb_mod_mask_t,
+        latched_mods: xkb_mod_mask_t,
+        locked_mods: xkb_mod_mask_t,
+        depressed_layout: xkb_layout_index_t,
+        latched_layout: xkb_layout_index_t,
+        locked_layout: xkb_layout_index_t,
+    ) -> c_int,
+    pub xkb_state_key_get_one_sym: unsafe extern "C" fn(state: *mut xkb_state, key: xkb_keycode_t) -> xkb_keysym_t,
+    pub xkb_state_key_get_utf8: unsafe extern "C" fn(
+        state: *mut xkb_state,
+        key: xkb_keycode_t,
+        buffer: *mut c_char,
+        size: usize,
+    ) -> c_int,
+    pub xkb_state_mod_name_is_active: unsafe extern "C" fn(
+        state: *mut xkb_state,
+        name: *const c_char,
+        kind: c_int,
+    ) -> c_int,
+    _keep_module_alive: ModuleLoader,
+}
+
+impl LibXkbCommon {
+    pub fn try_load() -> Option<LibXkbCommon> {
+        let module = ModuleLoader::load("libxkbcommon.so.0").or_else(|_| ModuleLoader::load("libxkbcommon.so")).ok()?;
+        Some(LibXkbCommon {
+            xkb_context_new: module.get_symbol("xkb_context_new").ok()?,
+            xkb_context_unref: module.get_symbol("xkb_context_unref").ok()?,
+            xkb_keymap_new_from_string: module.get_symbol("xkb_keymap_new_from_string").ok()?,
+            xkb_keymap_unref: module.get_symbol("xkb_keymap_unref").ok()?,
+            xkb_keymap_key_repeats: module.get_symbol("xkb_keymap_key_repeats").ok()?,
+            xkb_state_new: module.get_symbol("xkb_state_new").ok()?,
+            xkb_state_unref: module.get_symbol("xkb_state_unref").ok()?,
+            xkb_state_update_mask: module.get_symbol("xkb_state_update_mask").ok()?,
+            xkb_state_key_get_one_sym: module.get_symbol("xkb_state_key_get_one_sym").ok()?,
+            xkb_state_key_get_utf8: module.get_symbol("xkb_state_key_get_utf8").ok()?,
+            xkb_state_mod_name_is_active: module.get_symbol("xkb_state_mod_name_is_active").ok()?,
+            _keep_module_alive: module,
+        })
+    }
+}
+
+// a compiled keymap together with its live modifier state
+pub struct XkbKeyboard {
+    keymap: *mut xkb_keymap,
+    state: *mut xkb_state,
+}
+
+impl XkbKeyboard {
+    pub unsafe fn from_keymap_string(lib: &LibXkbCommon, context: *mut xkb_context, keymap: *const c_char) -> Option<Self> {
+        let keymap = (lib.xkb_keymap_new_from_string)(context, keymap, XKB_KEYMAP_FORMAT_TEXT_V1, XKB_KEYMAP_COMPILE_NO_FLAGS);
+        if keymap.is_null() {
+            return None
+        }
+        let state = (lib.xkb_state_new)(keymap);
+        if state.is_null() {
+            (lib.xkb_keymap_unref)(keymap);
+            return None
+        }
+        Some(Self {keymap, state})
+    }
+
+    pub fn free(&self, lib: &LibXkbCommon) {
+        unsafe {
+            (lib.xkb_state_unref)(self.state);
+            (lib.xkb_keymap_unref)(self.keymap);
+        }
+    }
+
+    pub fn update_mask(&self, lib: &LibXkbCommon, depressed: u32, latched: u32, locked: u32, group: u32) {
+        unsafe {
+            (lib.xkb_state_update_mask)(self.state, depressed, latched, locked, 0, 0, group);
+        }
+    }
+
+    pub fn key_get_one_sym(&self, lib: &LibXkbCommon, key: xkb_keycode_t) -> xkb_keysym_t {
+        unsafe {
+            (lib.xkb_state_key_get_one_sym)(self.state, key)
+        }
+    }
+
+    pub fn key_repeats(&self, lib: &LibXkbCommon, key: xkb_keycode_t) -> bool {
+        unsafe {
+            (lib.xkb_keymap_key_repeats)(self.keymap, key) != 0
+        }
+    }
+
+    pub fn key_get_utf8(&self, lib: &LibXkbCommon, key: xkb_keycode_t) -> String {
+        unsafe {
+            let mut buffer = [0u8; 64];
+            let len = (lib.xkb_state_key_get_utf8)(self.state, key, buffer.as_mut_ptr() as *mut c_char, buffer.len());
+            if len <= 0 || len as usize >= buffer.len() {
+                return String::new()
+            }
+            std::str::from_utf8(&buffer[..len as usize]).unwrap_or("").to_string()
+        }
+    }
+
+    pub fn mod_is_active(&self, lib: &LibXkbCommon, name: &[u8]) -> bool {
+        unsafe {
+            (lib.xkb_state_mod_name_is_active)(self.state, name.as_ptr() as *const c_char, XKB_STATE_MODS_EFFECTIVE) > 0
+        }
+    }
+}
@@ -23,7 +23,7 @@ use {
         cx::{Cx, OsType,LinuxWindowParams}, 
         os::cx_stdin::{PollTimers},
         gpu_info::GpuPerformance,
-        os::cx_native::EventFlow,
+        os::cx_native::{EventFlow, OsDarkMode},
     }
 };
 
@@ -40,6 +40,12 @@ impl Cx {
         if is_stdin_loop {
             cx.borrow_mut().in_makepad_studio = true;
         }
+        // prefer a native wayland connection, and fall back to X11 (or XWayland) without one
+        if !is_stdin_loop && std::env::var("WAYLAND_DISPLAY").map_or(false, | v | !v.is_empty()) {
+            if Cx::wayland_event_loop(cx.clone()) {
+                return
+            }
+        }
         init_xlib_app_global(Box::new({
             let cx = cx.clone();
             move | xlib_app,
@@ -92,6 +98,7 @@ impl Cx {
                     }
                 }
                 paint_dirty = true;
+                self.os.dark_mode.refresh(query_prefers_dark_mode);
                 self.call_event_handler(&Event::AppGotFocus);
             }
             XlibEvent::AppLostFocus => { 
@@ -379,6 +386,7 @@ impl CxOsApi for Cx {
         }
         self.live_scan_dependencies();
         self.native_load_dependencies();
+        self.os.dark_mode.refresh(query_prefers_dark_mode);
     }
     
     fn spawn_thread<F>(&mut self, f: F) where F: FnOnce() + Send + 'static {
@@ -392,6 +400,22 @@ impl CxOsApi for Cx {
     fn open_url(&mut self, _url:&str, _in_place:OpenUrlInPlace){
         crate::error!("open_url not implemented on this platform");
     }
+    
+    fn prefers_dark_mode(&self) -> Option<bool> {
+        self.os.dark_mode.prefers_dark()
+    }
+}
+
+// GNOME, and the desktops following its settings, keep the preference in gsettings
+pub(crate) fn query_prefers_dark_mode() -> Option<bool> {
+    let output = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+        .output()
+        .ok() ?;
+    if !output.status.success() {
+        return None
+    }
+    Some(String::from_utf8_lossy(&output.stdout).contains("prefer-dark"))
 }
 
 #[derive(Default)]
@@ -399,7 +423,8 @@ pub struct CxOs {
     pub(crate) media: CxLinuxMedia,
     pub (crate) stdin_timers: PollTimers,
     pub (crate) start_time: Option<Instant>,
+    pub (crate) dark_mode: OsDarkMode,
     // HACK(eddyb) generalize this to EGL, properly.
-    pub(super) opengl_cx: Option<OpenglCx>,
+    pub(crate) opengl_cx: Option<OpenglCx>,
 }
 
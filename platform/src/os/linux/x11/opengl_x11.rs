@@ -30,21 +30,28 @@ impl Cx {
         &mut self,
         pass_id: PassId,
         opengl_window: &mut OpenglWindow,
+    ) {
+        let pix_size = opengl_window.window_geom.inner_size * opengl_window.window_geom.dpi_factor;
+        self.draw_pass_to_egl_surface(pass_id, opengl_window.egl_surface, pix_size);
+    }
+    
+    // shared by the X11 and wayland windows, which both present through an EGL window surface
+    pub(crate) fn draw_pass_to_egl_surface(
+        &mut self,
+        pass_id: PassId,
+        egl_surface: egl_sys::EGLSurface,
+        pix_size: DVec2,
     ) {
         let draw_list_id = self.passes[pass_id].main_draw_list_id.unwrap();
         
         self.setup_render_pass(pass_id);
         
-        let egl_surface = opengl_window.egl_surface;
-        
         self.passes[pass_id].paint_dirty = false;
 
-        let pix_width = opengl_window.window_geom.inner_size.x * opengl_window.window_geom.dpi_factor;
-        let pix_height = opengl_window.window_geom.inner_size.y * opengl_window.window_geom.dpi_factor;
         unsafe {
             let opengl_cx = self.os.opengl_cx.as_ref().unwrap();
             (opengl_cx.libegl.eglMakeCurrent.unwrap())(opengl_cx.egl_display, egl_surface, egl_surface, opengl_cx.egl_context);
-            gl_sys::Viewport(0, 0, pix_width.floor() as i32, pix_height.floor() as i32);
+            gl_sys::Viewport(0, 0, pix_size.x.floor() as i32, pix_size.y.floor() as i32);
         }
         
         let clear_color = if self.passes[pass_id].color_textures.len() == 0 {
@@ -391,6 +398,31 @@ impl OpenglCx {
             );
         }
     }
+    
+    // creates a surface on a native window of the platform this context was made for.
+    // Vsync is turned off, on wayland a blocking swap would wait on the compositors
+    // frame callback and stall the event loop of a hidden window.
+    pub unsafe fn create_window_surface(&self, native_window: egl_sys::EGLNativeWindowType) -> egl_sys::EGLSurface {
+        {
+            let egl_surface = (self.libegl.eglCreateWindowSurface.unwrap())(
+                self.egl_display,
+                self.egl_config,
+                native_window,
+                std::ptr::null(),
+            );
+            assert!(!egl_surface.is_null(), "eglCreateWindowSurface failed");
+            (self.libegl.eglMakeCurrent.unwrap())(self.egl_display, egl_surface, egl_surface, self.egl_context);
+            (self.libegl.eglSwapInterval.unwrap())(self.egl_display, 0);
+            egl_surface
+        }
+    }
+    
+    pub unsafe fn destroy_surface(&self, egl_surface: egl_sys::EGLSurface) {
+        {
+            self.make_current();
+            (self.libegl.eglDestroySurface.unwrap())(self.egl_display, egl_surface);
+        }
+    }
 }
 
 #[derive(Clone)]
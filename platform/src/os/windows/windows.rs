@@ -17,7 +17,7 @@ use {
                 win32_app::*,
                 win32_window::Win32Window,
             },
-            cx_native::EventFlow,
+            cx_native::{EventFlow, OsDarkMode},
         },
         makepad_math::*,
         pass::CxPassParent,
@@ -94,6 +94,7 @@ impl Cx {
                     }
                 }
                 paint_dirty = true;
+                self.os.dark_mode.refresh(query_prefers_dark_mode);
                 self.call_event_handler(&Event::AppGotFocus);
             }
             Win32Event::AppLostFocus => {
@@ -435,6 +436,7 @@ impl CxOsApi for Cx {
         }
         self.live_scan_dependencies();
         self.native_load_dependencies();
+        self.os.dark_mode.refresh(query_prefers_dark_mode);
     }
     
     fn spawn_thread<F>(&mut self, f: F) where F: FnOnce() + Send + 'static {
@@ -448,11 +450,30 @@ impl CxOsApi for Cx {
     fn open_url(&mut self, _url:&str, _in_place:OpenUrlInPlace){
         crate::error!("open_url not implemented on this platform");
     }
+    
+    fn prefers_dark_mode(&self) -> Option<bool> {
+        self.os.dark_mode.prefers_dark()
+    }
+}
+
+fn query_prefers_dark_mode() -> Option<bool> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let output = std::process::Command::new("reg")
+        .args(["query", r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize", "/v", "AppsUseLightTheme"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok() ?;
+    // the value line reads `AppsUseLightTheme    REG_DWORD    0x0`
+    let query = String::from_utf8_lossy(&output.stdout);
+    let value = query.lines().find( | line | line.contains("AppsUseLightTheme")) ?.split_whitespace().last() ?;
+    Some(value == "0x0")
 }
 
 #[derive(Default)]
 pub struct CxOs {
     pub (crate) start_time: Option<Instant>,
+    pub (crate) dark_mode: OsDarkMode,
     pub (crate) media: CxWindowsMedia,
     pub (crate) d3d11_device: Option<ID3D11Device>,
     pub (crate) network_response: NetworkResponseChannel,
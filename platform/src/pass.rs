@@ -88,6 +88,7 @@ impl LiveNew for Pass {
             module_id: LiveModuleId::from_str(&module_path!()).unwrap(),
             live_type: LiveType::of::<Self>(),
             fields: Vec::new(),
+            variants: Vec::new(),
             live_ignore: true,
             //kind: LiveTypeKind::Object,
             type_name: id_lut!(Pass)
@@ -79,6 +79,8 @@ pub struct EditFile{
 #[derive(SerBin, DeBin, Debug)]
 pub enum AppToStudio{
     LogItem(StudioLogItem),
+    /// All live_design lint warnings after an expand, replacing the previous set
+    LiveLint(Vec<StudioLogItem>),
     EventSample(EventSample),
     GPUSample(GPUSample),
     JumpToFile(JumpToFile),
@@ -118,6 +118,7 @@ impl LiveNew for WindowHandle {
             module_id: LiveModuleId::from_str(&module_path!()).unwrap(),
             live_type: LiveType::of::<Self>(),
             fields: Vec::new(),
+            variants: Vec::new(),
             live_ignore: true,
             type_name: id_lut!(Window)
         }
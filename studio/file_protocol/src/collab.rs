@@ -0,0 +1,159 @@
+use {
+    crate::delta::{Delta, OffsetSelection},
+    std::collections::{BTreeMap, HashMap},
+};
+
+/// Keeps the text of a client in sync with the collaboration session of a file on the server.
+///
+/// The client sends at most one delta at a time, and waits until the server acknowledges it before
+/// sending the next. Local changes made in the meantime are combined into a single buffered delta.
+/// Deltas from other participants are transformed against the deltas the server has not seen yet,
+/// so they can be applied to the local text.
+///
+/// Deltas from other participants are applied in order of revision. Because a notification can
+/// overtake the acknowledgement of the delta that came before it, deltas and selections that
+/// arrive early are held back until the revisions before them are known.
+#[derive(Clone, Debug, Default)]
+pub struct CollabClient {
+    revision: usize,
+    // The delta that was sent to the server but not acknowledged yet.
+    pending: Option<Delta>,
+    // The local changes since the pending delta was sent.
+    buffer: Option<Delta>,
+    // Deltas from other participants by revision, that are not applied yet.
+    remote: BTreeMap<usize, Delta>,
+    // The selections of other participants by participant, and the revision they are in.
+    selections: HashMap<u64, (usize, Vec<OffsetSelection>)>,
+}
+
+impl CollabClient {
+    /// Creates a client for a text at the given revision.
+    pub fn new(revision: usize) -> Self {
+        Self {
+            revision,
+            ..Self::default()
+        }
+    }
+
+    /// The last revision of the server that is part of the local text.
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    /// Returns whether the server has seen every local change, so that offsets in the local text
+    /// are offsets in the current revision.
+    pub fn is_synchronized(&self) -> bool {
+        self.pending.is_none() && self.buffer.is_none()
+    }
+
+    /// Records a local change. Returns the delta to send to the server, if the client is not
+    /// waiting for a previous delta to be acknowledged.
+    pub fn apply_local(&mut self, delta: Delta) -> Option<Delta> {
+        if delta.is_identity() {
+            return None;
+        }
+        if self.pending.is_none() {
+            self.pending = Some(delta.clone());
+            return Some(delta);
+        }
+        self.buffer = Some(match self.buffer.take() {
+            Some(buffer) => buffer.compose(&delta),
+            None => delta,
+        });
+        None
+    }
+
+    /// Records that the server applied the pending delta as the given revision. Returns the next
+    /// delta to send to the server, if any.
+    pub fn apply_ack(&mut self, revision: usize) -> Option<Delta> {
+        if let Some(pending) = &self.pending {
+            for (selection_revision, selections) in self.selections.values_mut() {
+                if *selection_revision == self.revision {
+                    *selection_revision = revision;
+                    for selection in selections {
+                        *selection = selection.transform(pending);
+                    }
+                }
+            }
+        }
+        self.revision = revision;
+        self.pending = self.buffer.take();
+        self.pending.clone()
+    }
+
+    /// Records a delta that another participant applied as the given revision. Use
+    /// `next_remote_delta` to get the deltas that can be applied to the local text.
+    pub fn receive_remote(&mut self, revision: usize, delta: Delta) {
+        if revision > self.revision {
+            self.remote.insert(revision, delta);
+        }
+    }
+
+    /// Returns the next delta from another participant, transformed so it applies to the local
+    /// text, if it can be applied yet.
+    pub fn next_remote_delta(&mut self) -> Option<Delta> {
+        let mut delta = self.remote.remove(&(self.revision + 1))?;
+        for (revision, selections) in self.selections.values_mut() {
+            if *revision == self.revision {
+                *revision += 1;
+                for selection in selections {
+                    *selection = selection.transform(&delta);
+                }
+            }
+        }
+        self.revision += 1;
+        if let Some(pending) = self.pending.take() {
+            let (pending, remote) = pending.transform(&delta);
+            self.pending = Some(pending);
+            delta = remote;
+        }
+        if let Some(buffer) = self.buffer.take() {
+            let (buffer, remote) = buffer.transform(&delta);
+            self.buffer = Some(buffer);
+            delta = remote;
+        }
+        Some(delta)
+    }
+
+    /// Records the selections of another participant in the given revision. Use
+    /// `remote_selections` to get them in the local text.
+    pub fn receive_remote_selections(
+        &mut self,
+        participant: u64,
+        revision: usize,
+        selections: Vec<OffsetSelection>,
+    ) {
+        if revision >= self.revision {
+            self.selections.insert(participant, (revision, selections));
+        }
+    }
+
+    /// Forgets the selections of a participant that left.
+    pub fn remove_participant(&mut self, participant: u64) {
+        self.selections.remove(&participant);
+    }
+
+    /// Returns the selections of the other participants in the local text, leaving out the ones
+    /// that are in a revision that is not applied yet.
+    pub fn remote_selections(&self) -> Vec<(u64, Vec<OffsetSelection>)> {
+        let mut remote_selections: Vec<_> = self
+            .selections
+            .iter()
+            .filter(|(_, (revision, _))| *revision == self.revision)
+            .map(|(participant, (_, selections))| {
+                let selections = selections
+                    .iter()
+                    .map(|selection| {
+                        self.pending
+                            .iter()
+                            .chain(self.buffer.iter())
+                            .fold(*selection, |selection, delta| selection.transform(delta))
+                    })
+                    .collect();
+                (*participant, selections)
+            })
+            .collect();
+        remote_selections.sort_by_key(|(participant, _)| *participant);
+        remote_selections
+    }
+}
@@ -0,0 +1,344 @@
+use crate::makepad_micro_serde::{DeBin, DeBinErr, SerBin};
+
+/// A change to a text, as a sequence of operations that together span the whole text.
+///
+/// Lengths and offsets are in bytes, with a line break counting as a single byte. A delta can be
+/// applied to any text that is exactly `base_len` bytes long, and turns it into a text that is
+/// `target_len` bytes long.
+#[derive(Clone, Debug, Default, PartialEq, SerBin, DeBin)]
+pub struct Delta {
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin)]
+pub enum Operation {
+    /// Keeps the given number of bytes.
+    Retain(usize),
+    /// Inserts the given string.
+    Insert(String),
+    /// Removes the given number of bytes.
+    Delete(usize),
+}
+
+impl Delta {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the smallest delta that turns `old` into `new`, which keeps their common prefix and
+    /// suffix and replaces everything in between.
+    pub fn diff(old: &str, new: &str) -> Self {
+        let mut prefix_len = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(old_byte, new_byte)| old_byte == new_byte)
+            .count();
+        while !old.is_char_boundary(prefix_len) || !new.is_char_boundary(prefix_len) {
+            prefix_len -= 1;
+        }
+        let max_suffix_len = old.len().min(new.len()) - prefix_len;
+        let mut suffix_len = old
+            .bytes()
+            .rev()
+            .zip(new.bytes().rev())
+            .take(max_suffix_len)
+            .take_while(|(old_byte, new_byte)| old_byte == new_byte)
+            .count();
+        while !old.is_char_boundary(old.len() - suffix_len)
+            || !new.is_char_boundary(new.len() - suffix_len)
+        {
+            suffix_len -= 1;
+        }
+        let mut delta = Self::new();
+        delta.retain(prefix_len);
+        delta.delete(old.len() - prefix_len - suffix_len);
+        delta.insert(&new[prefix_len..new.len() - suffix_len]);
+        delta.retain(suffix_len);
+        delta
+    }
+
+    /// Returns whether applying this delta leaves every text unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.operations
+            .iter()
+            .all(|operation| matches!(operation, Operation::Retain(_)))
+    }
+
+    /// The length of the texts this delta can be applied to.
+    pub fn base_len(&self) -> usize {
+        self.operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Retain(len) | Operation::Delete(len) => *len,
+                Operation::Insert(_) => 0,
+            })
+            .sum()
+    }
+
+    /// The length of the texts this delta produces.
+    pub fn target_len(&self) -> usize {
+        self.operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Retain(len) => *len,
+                Operation::Insert(string) => string.len(),
+                Operation::Delete(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Appends a retain, merging it with the last operation if possible.
+    pub fn retain(&mut self, len: usize) {
+        if len == 0 {
+            return;
+        }
+        if let Some(Operation::Retain(last_len)) = self.operations.last_mut() {
+            *last_len += len;
+            return;
+        }
+        self.operations.push(Operation::Retain(len));
+    }
+
+    /// Appends an insert, merging it with the last operation if possible.
+    ///
+    /// An insert right after a delete is put before it, so that equal deltas have equal operations.
+    pub fn insert(&mut self, string: &str) {
+        if string.is_empty() {
+            return;
+        }
+        let len = self.operations.len();
+        let index = match self.operations.last() {
+            Some(Operation::Delete(_)) => len - 1,
+            _ => len,
+        };
+        if let Some(Operation::Insert(last_string)) =
+            index.checked_sub(1).map(|index| &mut self.operations[index])
+        {
+            last_string.push_str(string);
+            return;
+        }
+        self.operations.insert(index, Operation::Insert(string.to_string()));
+    }
+
+    /// Appends a delete, merging it with the last operation if possible.
+    pub fn delete(&mut self, len: usize) {
+        if len == 0 {
+            return;
+        }
+        if let Some(Operation::Delete(last_len)) = self.operations.last_mut() {
+            *last_len += len;
+            return;
+        }
+        self.operations.push(Operation::Delete(len));
+    }
+
+    /// Applies this delta to `text`. Fails if `text` does not have the base length of this delta,
+    /// or if an operation would split a character.
+    pub fn apply(&self, text: &str) -> Result<String, String> {
+        if text.len() != self.base_len() {
+            return Err(format!(
+                "delta expects a text of {} bytes, but the text has {} bytes",
+                self.base_len(),
+                text.len()
+            ));
+        }
+        let mut new_text = String::with_capacity(self.target_len());
+        let mut index = 0;
+        for operation in &self.operations {
+            match operation {
+                Operation::Retain(len) | Operation::Delete(len) => {
+                    let end = index + len;
+                    if !text.is_char_boundary(end) {
+                        return Err(format!("delta splits a character at byte {}", end));
+                    }
+                    if let Operation::Retain(_) = operation {
+                        new_text.push_str(&text[index..end]);
+                    }
+                    index = end;
+                }
+                Operation::Insert(string) => new_text.push_str(string),
+            }
+        }
+        Ok(new_text)
+    }
+
+    /// Returns a delta that has the same effect as applying this delta followed by `other`.
+    ///
+    /// The target length of this delta must be the base length of `other`.
+    pub fn compose(&self, other: &Delta) -> Delta {
+        let mut delta = Delta::new();
+        let mut operations_0 = Operations::new(&self.operations);
+        let mut operations_1 = Operations::new(&other.operations);
+        loop {
+            match (operations_0.peek(), operations_1.peek()) {
+                (None, None) => break,
+                (Some(Operation::Delete(len)), _) => {
+                    delta.delete(len);
+                    operations_0.skip(len);
+                }
+                (_, Some(Operation::Insert(string))) => {
+                    delta.insert(&string);
+                    operations_1.next_insert();
+                }
+                (Some(operation_0), Some(operation_1)) => {
+                    let len = operation_0.len().min(operation_1.len());
+                    match (&operation_0, &operation_1) {
+                        (Operation::Retain(_), Operation::Retain(_)) => delta.retain(len),
+                        (Operation::Insert(string), Operation::Retain(_)) => {
+                            delta.insert(&string[..len])
+                        }
+                        (Operation::Retain(_), Operation::Delete(_)) => delta.delete(len),
+                        // Text that is inserted and then deleted again disappears altogether.
+                        _ => {}
+                    }
+                    operations_0.skip(len);
+                    operations_1.skip(len);
+                }
+                (Some(Operation::Insert(string)), None) => {
+                    delta.insert(&string);
+                    operations_0.next_insert();
+                }
+                (Some(operation), None) | (None, Some(operation)) => {
+                    panic!("cannot compose deltas of mismatched lengths at {:?}", operation)
+                }
+            }
+        }
+        delta
+    }
+
+    /// Transforms this delta and `other`, which both apply to the same text, into a pair of deltas
+    /// `(self', other')` such that applying `self` and then `other'` has the same effect as
+    /// applying `other` and then `self'`.
+    ///
+    /// When both deltas insert at the same offset, the insert of this delta comes first.
+    pub fn transform(&self, other: &Delta) -> (Delta, Delta) {
+        let mut delta_0 = Delta::new();
+        let mut delta_1 = Delta::new();
+        let mut operations_0 = Operations::new(&self.operations);
+        let mut operations_1 = Operations::new(&other.operations);
+        loop {
+            match (operations_0.peek(), operations_1.peek()) {
+                (None, None) => break,
+                (Some(Operation::Insert(string)), _) => {
+                    delta_0.insert(&string);
+                    delta_1.retain(string.len());
+                    operations_0.next_insert();
+                }
+                (_, Some(Operation::Insert(string))) => {
+                    delta_0.retain(string.len());
+                    delta_1.insert(&string);
+                    operations_1.next_insert();
+                }
+                (Some(operation_0), Some(operation_1)) => {
+                    let len = operation_0.len().min(operation_1.len());
+                    match (&operation_0, &operation_1) {
+                        (Operation::Retain(_), Operation::Retain(_)) => {
+                            delta_0.retain(len);
+                            delta_1.retain(len);
+                        }
+                        (Operation::Delete(_), Operation::Retain(_)) => delta_0.delete(len),
+                        (Operation::Retain(_), Operation::Delete(_)) => delta_1.delete(len),
+                        // Text that both deltas delete only needs to be deleted once.
+                        _ => {}
+                    }
+                    operations_0.skip(len);
+                    operations_1.skip(len);
+                }
+                (Some(operation), None) | (None, Some(operation)) => {
+                    panic!("cannot transform deltas of mismatched lengths at {:?}", operation)
+                }
+            }
+        }
+        (delta_0, delta_1)
+    }
+
+    /// Returns where the byte at `offset` ends up after applying this delta. An insert at `offset`
+    /// moves it along, and a delete that contains it moves it to the start of the delete.
+    pub fn transform_offset(&self, offset: usize) -> usize {
+        let mut remaining = offset;
+        let mut new_offset = offset;
+        for operation in &self.operations {
+            match operation {
+                Operation::Retain(len) => {
+                    if remaining < *len {
+                        break;
+                    }
+                    remaining -= len;
+                }
+                Operation::Insert(string) => new_offset += string.len(),
+                Operation::Delete(len) => {
+                    new_offset -= remaining.min(*len);
+                    if remaining < *len {
+                        break;
+                    }
+                    remaining -= len;
+                }
+            }
+        }
+        new_offset
+    }
+}
+
+// Iterates over the operations of a delta, splitting them up as needed.
+struct Operations<'a> {
+    operations: &'a [Operation],
+    // How much of the first operation was already consumed.
+    offset: usize,
+}
+
+impl<'a> Operations<'a> {
+    fn new(operations: &'a [Operation]) -> Self {
+        Self {
+            operations,
+            offset: 0,
+        }
+    }
+
+    // Returns what is left of the first operation.
+    fn peek(&self) -> Option<Operation> {
+        self.operations.first().map(|operation| match operation {
+            Operation::Retain(len) => Operation::Retain(len - self.offset),
+            Operation::Insert(string) => Operation::Insert(string[self.offset..].to_string()),
+            Operation::Delete(len) => Operation::Delete(len - self.offset),
+        })
+    }
+
+    fn next_insert(&mut self) {
+        self.operations = &self.operations[1..];
+        self.offset = 0;
+    }
+
+    fn skip(&mut self, len: usize) {
+        self.offset += len;
+        if self.offset == self.operations[0].len() {
+            self.operations = &self.operations[1..];
+            self.offset = 0;
+        }
+    }
+}
+
+impl Operation {
+    fn len(&self) -> usize {
+        match self {
+            Operation::Retain(len) | Operation::Delete(len) => *len,
+            Operation::Insert(string) => string.len(),
+        }
+    }
+}
+
+/// A selection in a text, as byte offsets.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, SerBin, DeBin)]
+pub struct OffsetSelection {
+    pub anchor: usize,
+    pub cursor: usize,
+}
+
+impl OffsetSelection {
+    /// Returns where this selection ends up after applying `delta`.
+    pub fn transform(self, delta: &Delta) -> Self {
+        Self {
+            anchor: delta.transform_offset(self.anchor),
+            cursor: delta.transform_offset(self.cursor),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use {
     crate::{
+        delta::{Delta, OffsetSelection},
         makepad_live_id::*,
         makepad_micro_serde::{SerBin, DeBin, DeBinErr},
     },
@@ -39,15 +40,67 @@ pub enum FileRequest {
         path: String, 
         id: u64
     },
-    /// Requests the collab server to apply the given delta to the given revision of the file with
-    /// the given id.
+    /// Requests the collab server to write the file with the given path to disk.
+    ///
+    /// While the file has a collaboration session, the text of the session is what gets written.
+    /// Participants that change the file with `ApplyDelta` only save with this request. For other
+    /// clients, the given data replaces the text of the session as if it was a delta.
     SaveFile{
         path: String,
         data: String,
         id: u64,
-        patch: bool
+        patch: bool,
+        /// The undo history of the file, which is stored next to it.
+        undo_history: Option<Vec<u8>>
+    },
+    /// Requests the collab server to search the files in its file tree. The matches are sent back
+    /// in `SearchResults` notifications. Starting a new search cancels the previous one.
+    Search{
+        pattern: String,
+        options: SearchOptions,
+        id: u64,
+    },
+    /// Requests the collab server to replace every match of the given search in the files with
+    /// the given paths.
+    ReplaceInFiles{
+        pattern: String,
+        options: SearchOptions,
+        replacement: String,
+        paths: Vec<String>,
+    },
+    /// Requests the collab server to apply the given delta to the given revision of the file with
+    /// the given path. The client must be a participant for the file.
+    ApplyDelta{
+        path: String,
+        revision: usize,
+        delta: Delta,
     },
+    /// Requests the collab server to show the given selections, which are in the given revision of
+    /// the file with the given path, to the other participants.
+    UpdateSelections{
+        path: String,
+        revision: usize,
+        selections: Vec<OffsetSelection>,
+    },
+    /// Requests the collab server to remove the client as a participant from the file with the
+    /// given path. If the client is the last participant for the file, this also causes the file
+    /// to be closed on the server.
+    CloseFile{
+        path: String,
+    },
+}
 
+/// The options of a `Search` request.
+#[derive(Clone, Debug, Default, PartialEq, SerBin, DeBin)]
+pub struct SearchOptions{
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub regex: bool,
+    /// Glob patterns for the paths to search, such as `*.rs` or `studio/**`. Empty means all
+    /// files.
+    pub include: Vec<String>,
+    /// Glob patterns for the paths to skip. Paths ignored by a `.gitignore` are always skipped.
+    pub exclude: Vec<String>,
 }
 
 /// A type for representing either a response or a notification from the collab server.
@@ -80,6 +133,17 @@ pub struct OpenFileResponse{
     pub path: String, 
     pub data: String, 
     pub id: u64, 
+    /// The undo history that was last saved with the file, if any.
+    pub undo_history: Option<Vec<u8>>,
+    /// The revision of the collaboration session that `data` is the text of.
+    pub revision: usize,
+}
+
+#[derive(Clone, Debug, SerBin, DeBin)]
+pub struct ApplyDeltaResponse{
+    pub path: String,
+    /// The revision the delta was applied as.
+    pub revision: usize,
 }
 
 
@@ -94,8 +158,34 @@ pub enum FileResponse {
     /// The result of requesting the collab server to apply a delta to a revision of the file with
     /// the given id.
     SaveFile(Result<SaveFileResponse, FileError>),
-    
-    // Existing variants...
+    /// The result of starting a search. This fails if the pattern or one of the globs is invalid.
+    Search(Result<u64, FileError>),
+    /// The files that were changed by replacing in them. Files that have a collaboration session
+    /// are changed with a delta instead, which is sent to every participant.
+    ReplaceInFiles(Result<Vec<SaveFileResponse>, FileError>),
+    /// The result of requesting the collab server to apply a delta to a revision of the file with
+    /// the given path.
+    ApplyDelta(Result<ApplyDeltaResponse, FileError>),
+    /// The result of requesting the collab server to show the selections of the client for the
+    /// file with the given path.
+    UpdateSelections(Result<String, FileError>),
+    /// The result of requesting the collab server to remove the client as a participant from the
+    /// file with the given path.
+    CloseFile(Result<String, FileError>),
+}
+
+/// A search stops after finding this many matching lines.
+pub const MAX_SEARCH_RESULTS: usize = 10000;
+
+/// A line that matches a search.
+#[derive(Clone, Debug, SerBin, DeBin)]
+pub struct SearchResult{
+    pub path: String,
+    /// The zero-based index of the line.
+    pub line: usize,
+    pub line_text: String,
+    /// The byte ranges of the matches in `line_text`.
+    pub ranges: Vec<(usize, usize)>,
 }
 
 /// A type for representing data about a file tree.
@@ -131,9 +221,34 @@ pub struct DirectoryEntry {
 #[derive(Clone, Debug, SerBin, DeBin)]
 pub enum FileNotification {
     FileChangedOnDisk(SaveFileResponse),
-    // Notifies the client that another client applied the given delta to the file with the given
-    // id. This is only sent for files for which the client is a participant.
-   // DeltaWasApplied(TextFileId),
+    /// The next batch of results for the search with the given id. The last batch is `done`.
+    SearchResults{
+        id: u64,
+        results: Vec<SearchResult>,
+        done: bool,
+    },
+    /// Notifies the client that another participant applied the given delta to the file with the
+    /// given path, as the given revision. The participant is `None` for changes that were made to
+    /// the file on disk. This is only sent for files for which the client is a participant.
+    DeltaWasApplied{
+        path: String,
+        revision: usize,
+        delta: Delta,
+        participant: Option<u64>,
+    },
+    /// Notifies the client that another participant changed its selections in the file with the
+    /// given path. The selections are in the given revision.
+    SelectionsChanged{
+        path: String,
+        revision: usize,
+        participant: u64,
+        selections: Vec<OffsetSelection>,
+    },
+    /// Notifies the client that another participant was removed from the file with the given path.
+    ParticipantLeft{
+        path: String,
+        participant: u64,
+    },
 }
 
 /// A type for representing errors from the collab server.
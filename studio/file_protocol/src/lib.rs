@@ -1,5 +1,9 @@
+pub mod collab;
+pub mod delta;
 pub mod file_protocol;
 
+pub use collab::CollabClient;
+pub use delta::{Delta, OffsetSelection, Operation};
 pub use file_protocol::*;
 pub use makepad_live_id;
 pub use makepad_micro_serde;
@@ -0,0 +1,145 @@
+use makepad_file_protocol::{CollabClient, Delta, OffsetSelection, Operation};
+
+fn delta(operations: &[Operation]) -> Delta {
+    let mut delta = Delta::new();
+    for operation in operations {
+        match operation {
+            Operation::Retain(len) => delta.retain(*len),
+            Operation::Insert(string) => delta.insert(string),
+            Operation::Delete(len) => delta.delete(*len),
+        }
+    }
+    delta
+}
+
+fn insert(offset: usize, string: &str, len: usize) -> Delta {
+    delta(&[
+        Operation::Retain(offset),
+        Operation::Insert(string.to_string()),
+        Operation::Retain(len - offset),
+    ])
+}
+
+fn delete(offset: usize, count: usize, len: usize) -> Delta {
+    delta(&[
+        Operation::Retain(offset),
+        Operation::Delete(count),
+        Operation::Retain(len - offset - count),
+    ])
+}
+
+#[test]
+fn apply_checks_the_length() {
+    let text = "hello\nworld";
+    assert_eq!(insert(5, "!", text.len()).apply(text).unwrap(), "hello!\nworld");
+    assert_eq!(delete(0, 6, text.len()).apply(text).unwrap(), "world");
+    assert!(insert(2, "!", 3).apply(text).is_err());
+}
+
+#[test]
+fn compose_is_the_same_as_applying_in_turn() {
+    let text = "fn main() {}";
+    let a = insert(11, "\n    println!();\n", text.len());
+    let after_a = a.apply(text).unwrap();
+    let b = delete(3, 4, after_a.len());
+    let after_b = b.apply(&after_a).unwrap();
+    assert_eq!(a.compose(&b).apply(text).unwrap(), after_b);
+}
+
+#[test]
+fn transform_converges() {
+    let text = "let x = 1;";
+    let cases = [
+        (insert(4, "mut ", text.len()), insert(8, "2", text.len())),
+        (insert(4, "a", text.len()), insert(4, "b", text.len())),
+        (delete(4, 3, text.len()), insert(5, "yz", text.len())),
+        (delete(2, 5, text.len()), delete(4, 6, text.len())),
+        (Delta::diff(text, "const x: u32 = 1;"), Delta::diff(text, "let x = 1; // one")),
+    ];
+    for (a, b) in cases {
+        let (a_prime, b_prime) = a.transform(&b);
+        let after_a = b_prime.apply(&a.apply(text).unwrap()).unwrap();
+        let after_b = a_prime.apply(&b.apply(text).unwrap()).unwrap();
+        assert_eq!(after_a, after_b, "{:?} and {:?}", a, b);
+    }
+    // On a tie, the insert of the delta that is transformed comes first.
+    let (_, b_prime) = insert(4, "a", text.len()).transform(&insert(4, "b", text.len()));
+    assert_eq!(b_prime.apply("let ax = 1;").unwrap(), "let abx = 1;");
+}
+
+#[test]
+fn diff_keeps_characters_whole() {
+    let old = "naïve café";
+    let new = "naive cafè";
+    let delta = Delta::diff(old, new);
+    assert_eq!(delta.apply(old).unwrap(), new);
+    assert!(Delta::diff(old, old).is_identity());
+}
+
+#[test]
+fn transform_offset_follows_the_text() {
+    let text = "abcdef";
+    let delta = delta(&[
+        Operation::Retain(1),
+        Operation::Insert("XY".to_string()),
+        Operation::Retain(2),
+        Operation::Delete(2),
+        Operation::Retain(1),
+    ]);
+    assert_eq!(delta.apply(text).unwrap(), "aXYbcf");
+    assert_eq!(delta.transform_offset(0), 0);
+    assert_eq!(delta.transform_offset(1), 3);
+    assert_eq!(delta.transform_offset(4), 5);
+    assert_eq!(delta.transform_offset(5), 5);
+    assert_eq!(delta.transform_offset(6), 6);
+    let selection = OffsetSelection { anchor: 2, cursor: 6 };
+    assert_eq!(selection.transform(&delta), OffsetSelection { anchor: 4, cursor: 6 });
+}
+
+#[test]
+fn client_buffers_and_transforms() {
+    let mut text = "abc".to_string();
+    let mut client = CollabClient::new(0);
+
+    let first = insert(3, "d", text.len());
+    text = first.apply(&text).unwrap();
+    assert_eq!(client.apply_local(first.clone()), Some(first));
+    let second = insert(0, "_", text.len());
+    text = second.apply(&text).unwrap();
+    assert_eq!(client.apply_local(second), None);
+    assert!(!client.is_synchronized());
+
+    // Another participant deleted "a", and its notification overtook the acknowledgement of our
+    // first delta, which the server applied as revision 1.
+    client.receive_remote(2, delete(0, 1, 4));
+    assert_eq!(client.next_remote_delta(), None);
+    let buffered = client.apply_ack(1).unwrap();
+    let remote = client.next_remote_delta().unwrap();
+    text = remote.apply(&text).unwrap();
+    assert_eq!(text, "_bcd");
+    assert_eq!(client.revision(), 2);
+
+    // The buffered delta was sent for revision 1, and so it applies to the server text at that
+    // revision, before the delete.
+    assert_eq!(buffered.apply("abcd").unwrap(), "_abcd");
+    assert_eq!(client.apply_ack(3), None);
+    assert!(client.is_synchronized());
+
+    // Selections in a revision that is not applied yet only show up once it is.
+    let selection = OffsetSelection { anchor: 1, cursor: 2 };
+    client.receive_remote_selections(7, 4, vec![selection]);
+    assert!(client.remote_selections().is_empty());
+    client.receive_remote(4, insert(0, "!", 4));
+    text = client.next_remote_delta().unwrap().apply(&text).unwrap();
+    assert_eq!(text, "!_bcd");
+    assert_eq!(client.remote_selections(), vec![(7, vec![selection])]);
+    client.receive_remote(5, delete(2, 1, 5));
+    text = client.next_remote_delta().unwrap().apply(&text).unwrap();
+    assert_eq!(text, "!_cd");
+    assert_eq!(
+        client.remote_selections(),
+        vec![(7, vec![OffsetSelection { anchor: 1, cursor: 2 }])]
+    );
+    client.remove_participant(7);
+    assert!(client.remote_selections().is_empty());
+}
@@ -10,15 +10,23 @@ use {
             FileResponse,
             SaveKind,
             SaveFileResponse,
-            OpenFileResponse
+            OpenFileResponse,
+            SearchOptions,
+            ApplyDeltaResponse,
+            Delta,
+            OffsetSelection,
+            MAX_SEARCH_RESULTS,
         },
+        search::{read_text_file, Searcher},
+        makepad_live_id::LiveId,
     },
     std::{
         thread,
         cmp::Ordering,
+        collections::{HashMap, hash_map::Entry},
         fmt,
         fs,
-        time::Duration,
+        time::{Duration, Instant},
         path::{Path, PathBuf},
         sync::{Arc, RwLock, Mutex},
     },
@@ -34,14 +42,23 @@ pub struct FileServer {
 impl FileServer {
     /// Creates a new collab server rooted at the given path.
     pub fn new<P: Into<PathBuf >> (root_path: P) -> FileServer {
+        let root_path = root_path.into();
         FileServer {
             next_connection_id: 0,
             shared: Arc::new(RwLock::new(Shared {
-                root_path: root_path.into(),
+                undo_history_dir: default_undo_history_dir(&root_path),
+                root_path,
+                sessions: Default::default(),
             })),
         }
     }
     
+    /// Sets the directory that the undo history of each file is stored in, by its path relative
+    /// to the root. Defaults to a directory for the root in the Studio cache directory.
+    pub fn set_undo_history_dir<P: Into<PathBuf >> (&mut self, undo_history_dir: P) {
+        self.shared.write().unwrap().undo_history_dir = undo_history_dir.into();
+    }
+    
     /// Creates a new connection to this collab server, and returns a handle for the connection.
     ///
     /// The given `notification_sender` is called whenever the server wants to send a notification
@@ -50,11 +67,12 @@ impl FileServer {
         let connection_id = ConnectionId(self.next_connection_id);
         self.next_connection_id += 1;
         FileServerConnection {
-            _connection_id:connection_id,
+            connection_id,
             shared: self.shared.clone(),
             _notification_sender: notification_sender,
             open_files: Default::default(),
-            stop_observation: Default::default()
+            stop_observation: Default::default(),
+            search_id: Default::default(),
         }
     }
 }
@@ -62,13 +80,15 @@ impl FileServer {
 /// A connection to a collab server.
 pub struct FileServerConnection {
     // The id for this connection.
-    _connection_id: ConnectionId,
+    connection_id: ConnectionId,
     // State is shared between every connection.
     shared: Arc<RwLock<Shared >>,
     // Used to send notifications for this connection.
     _notification_sender: Box<dyn NotificationSender>,
     open_files: Arc<Mutex<Vec<(String, u64, Vec<u8>)>>>,
     stop_observation: Arc<Mutex<bool>>,
+    // The id of the search that is running, if any. A search stops once this changes.
+    search_id: Arc<Mutex<Option<u64>>>,
 }
 
 impl FileServerConnection {
@@ -82,7 +102,12 @@ impl FileServerConnection {
         match request {
             FileRequest::LoadFileTree {with_data} => FileResponse::LoadFileTree(self.load_file_tree(with_data)),
             FileRequest::OpenFile{path,id} => FileResponse::OpenFile(self.open_file(path, id)),
-            FileRequest::SaveFile{path, data, id, patch} => FileResponse::SaveFile(self.save_file(path, data, id, patch)),
+            FileRequest::SaveFile{path, data, id, patch, undo_history} => FileResponse::SaveFile(self.save_file(path, data, id, patch, undo_history)),
+            FileRequest::Search{pattern, options, id} => FileResponse::Search(self.search(pattern, options, id)),
+            FileRequest::ReplaceInFiles{pattern, options, replacement, paths} => FileResponse::ReplaceInFiles(self.replace_in_files(pattern, options, replacement, paths)),
+            FileRequest::ApplyDelta{path, revision, delta} => FileResponse::ApplyDelta(self.apply_delta(path, revision, delta)),
+            FileRequest::UpdateSelections{path, revision, selections} => FileResponse::UpdateSelections(self.update_selections(path, revision, selections)),
+            FileRequest::CloseFile{path} => FileResponse::CloseFile(self.close_file(path)),
         }
     }
     
@@ -173,14 +198,21 @@ impl FileServerConnection {
         path
     }
     
+    // The undo history of a file is stored outside of the project, so it doesn't end up in the
+    // file tree or in version control.
+    fn make_undo_history_path(&self, child_path:&str)->PathBuf{
+        let mut path = self.shared.read().unwrap().undo_history_dir.clone();
+        path.push(format!("{}.undo", child_path));
+        path
+    }
+    
     fn start_observation(&self) {
         let open_files = self.open_files.clone();
         let shared = self.shared.clone();
         let notification_sender = self._notification_sender.clone();
         let stop_observation = self.stop_observation.clone();
         thread::spawn(move || {
-            let stop = *stop_observation.lock().unwrap();
-            while !stop{
+            while !*stop_observation.lock().unwrap(){
                 if let Ok(mut files) = open_files.lock(){
                     for (path, file_id, last_content) in files.iter_mut() {
                         let full_path = {
@@ -188,7 +220,25 @@ impl FileServerConnection {
                             shared.root_path.join(&path)
                         };
                         if let Ok(bytes) = fs::read(&full_path) {
-                            if bytes.len() > 0 && bytes != *last_content {
+                            if bytes.is_empty() || bytes == *last_content {
+                                continue;
+                            }
+                            let shared = shared.read().unwrap();
+                            let mut sessions = shared.sessions.lock().unwrap();
+                            if let Some(session) = sessions.get_mut(path.as_str()) {
+                                // The session owns the text of the file, so a change on disk
+                                // becomes a delta for every participant. The first connection
+                                // to notice the change applies it.
+                                if bytes != session.disk_data {
+                                    let new_data = String::from_utf8_lossy(&bytes).to_string();
+                                    let delta = Delta::diff(&session.text, &new_data);
+                                    let revision = session.revision();
+                                    let _ = session.apply_delta(path, None, revision, delta);
+                                    session.disk_data = bytes.clone();
+                                }
+                                *last_content = bytes;
+                            }
+                            else {
                                 let new_data = String::from_utf8_lossy(&bytes);
                                 let old_data = String::from_utf8_lossy(&last_content);
                                 // Send notification of external file change.
@@ -216,62 +266,168 @@ impl FileServerConnection {
     // Handles an `OpenFile` request.
     fn open_file(&self, child_path: String, id:u64) -> Result<OpenFileResponse, FileError> {
         let path = self.make_full_path(&child_path);
-        
-        let bytes = fs::read(&path).map_err(
-            | error | FileError::Unknown(error.to_string())
-        ) ?;
+        let undo_history_path = self.make_undo_history_path(&child_path);
         
         let mut open_files = self.open_files.lock().unwrap();
+        let shared = self.shared.read().unwrap();
+        let mut sessions = shared.sessions.lock().unwrap();
+        
+        let session = match sessions.entry(child_path.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let bytes = fs::read(&path).map_err(
+                    | error | FileError::Unknown(error.to_string())
+                ) ?;
+                // Converts the file contents to a `String`. This is necessarily a lossy
+                // conversion because the editor assumes everything is UTF-8 encoded, and this
+                // isn't always the case for files on disk (is this a problem?)
+                entry.insert(Session::new(String::from_utf8_lossy(&bytes).to_string(), bytes))
+            }
+        };
+        let revision = session.revision();
+        session.participants.insert(self.connection_id, Participant{
+            notification_sender: self._notification_sender.clone(),
+            revision,
+            applies_deltas: false,
+        });
         
         if open_files.iter().find(|(cp,_,_)| *cp == child_path).is_none(){
-            open_files.push((child_path.clone(), id, bytes.clone()));
+            open_files.push((child_path.clone(), id, session.disk_data.clone()));
         }
         
         if open_files.len() == 1 {
             self.start_observation();
         }
-        // Converts the file contents to a `Text`. This is necessarily a lossy conversion
-        // because `Text` assumes everything is UTF-8 encoded, and this isn't always the
-        // case for files on disk (is this a problem?)
-        /*let text: Text = Text::from_lines(String::from_utf8_lossy(&bytes)
-            .lines()
-            .map( | line | line.chars().collect::<Vec<_ >> ())
-            .collect::<Vec<_ >>());*/
         
-        let text = String::from_utf8_lossy(&bytes);
         Ok(OpenFileResponse{
             path: child_path,
-            data: text.to_string(),
-            id
+            data: session.text.clone(),
+            id,
+            undo_history: fs::read(undo_history_path).ok(),
+            revision,
         })
     }
     
+    // Handles a `CloseFile` request.
+    fn close_file(&self, child_path: String) -> Result<String, FileError> {
+        self.open_files.lock().unwrap().retain(|(cp,_,_)| *cp != child_path);
+        let shared = self.shared.read().unwrap();
+        let mut sessions = shared.sessions.lock().unwrap();
+        if let Some(session) = sessions.get_mut(&child_path) {
+            session.remove_participant(&child_path, self.connection_id);
+            if session.participants.is_empty() {
+                sessions.remove(&child_path);
+            }
+        }
+        Ok(child_path)
+    }
+    
+    // Handles an `ApplyDelta` request.
+    fn apply_delta(&self, child_path: String, revision: usize, delta: Delta) -> Result<ApplyDeltaResponse, FileError> {
+        let shared = self.shared.read().unwrap();
+        let mut sessions = shared.sessions.lock().unwrap();
+        let session = sessions.get_mut(&child_path)
+            .filter(|session| session.participants.contains_key(&self.connection_id))
+            .ok_or_else(|| FileError::Unknown(format!("not a participant for {}", child_path)))?;
+        let participant = session.participants.get_mut(&self.connection_id).unwrap();
+        participant.applies_deltas = true;
+        participant.revision = revision;
+        let revision = session.apply_delta(&child_path, Some(self.connection_id), revision, delta)?;
+        Ok(ApplyDeltaResponse{
+            path: child_path,
+            revision
+        })
+    }
+    
+    // Handles an `UpdateSelections` request.
+    fn update_selections(&self, child_path: String, revision: usize, selections: Vec<OffsetSelection>) -> Result<String, FileError> {
+        let shared = self.shared.read().unwrap();
+        let mut sessions = shared.sessions.lock().unwrap();
+        let session = sessions.get_mut(&child_path)
+            .filter(|session| session.participants.contains_key(&self.connection_id))
+            .ok_or_else(|| FileError::Unknown(format!("not a participant for {}", child_path)))?;
+        let deltas = session.deltas_since(revision)?;
+        let selections = selections.into_iter().map(|selection| {
+            deltas.iter().fold(selection, |selection, delta| selection.transform(delta))
+        }).collect::<Vec<_>>();
+        session.participants.get_mut(&self.connection_id).unwrap().revision = revision;
+        let current_revision = session.revision();
+        for (connection_id, participant) in &session.participants {
+            if *connection_id != self.connection_id {
+                participant.notification_sender.send_notification(FileNotification::SelectionsChanged{
+                    path: child_path.clone(),
+                    revision: current_revision,
+                    participant: self.connection_id.0 as u64,
+                    selections: selections.clone(),
+                });
+            }
+        }
+        session.prune_history();
+        Ok(child_path)
+    }
+    
     // Handles an `ApplyDelta` request.
     fn save_file(
         &self,
         child_path: String,
         new_data: String,
         id: u64,
-        patch: bool
+        patch: bool,
+        undo_history: Option<Vec<u8>>
     ) -> Result<SaveFileResponse, FileError> {
         let mut open_files = self.open_files.lock().unwrap();
                 
-        if let Some(of) = open_files.iter_mut().find(|(cp,_,_)| *cp == child_path){
-            of.2 =  new_data.as_bytes().to_vec();
-        }
-        else{
+        if open_files.iter().find(|(cp,_,_)| *cp == child_path).is_none(){
             open_files.push((child_path.clone(), id, new_data.as_bytes().to_vec()));
         }
         
         let path = self.make_full_path(&child_path);
+        let undo_history_path = self.make_undo_history_path(&child_path);
         
         let old_data = String::from_utf8_lossy(&fs::read(&path).map_err(
             | error | FileError::Unknown(error.to_string())
         ) ?).to_string();
+        
+        let shared = self.shared.read().unwrap();
+        let mut sessions = shared.sessions.lock().unwrap();
+        let new_data = match sessions.get_mut(&child_path) {
+            Some(session) => {
+                let revision = session.revision();
+                match session.participants.get_mut(&self.connection_id) {
+                    // Participants that apply deltas already changed the session, and their data
+                    // may be behind it.
+                    Some(participant) if participant.applies_deltas => (),
+                    Some(participant) => {
+                        participant.revision = revision;
+                        let delta = Delta::diff(&session.text, &new_data);
+                        session.apply_delta(&child_path, Some(self.connection_id), revision, delta)?;
+                    }
+                    None => {
+                        let delta = Delta::diff(&session.text, &new_data);
+                        session.apply_delta(&child_path, None, revision, delta)?;
+                    }
+                }
+                session.disk_data = session.text.as_bytes().to_vec();
+                session.text.clone()
+            }
+            None => new_data
+        };
+        if let Some(of) = open_files.iter_mut().find(|(cp,_,_)| *cp == child_path){
+            of.2 = new_data.as_bytes().to_vec();
+        }
 
         fs::write(&path, &new_data).map_err(
             | error | FileError::Unknown(error.to_string())
         ) ?;
+        drop(sessions);
+        
+        if let Some(undo_history) = undo_history{
+            // Losing the undo history is not worth failing the save over.
+            if let Some(dir) = undo_history_path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            let _ = fs::write(undo_history_path, undo_history);
+        }
         
         Ok(SaveFileResponse{
             path: child_path, 
@@ -281,6 +437,118 @@ impl FileServerConnection {
             kind: if patch{SaveKind::Patch}else{SaveKind::Save}
         })
     }
+    
+    // Handles a `Search` request. The search itself runs on its own thread, which sends the
+    // results in batches so the client can show them as they come in.
+    fn search(&self, pattern: String, options: SearchOptions, id: u64) -> Result<u64, FileError> {
+        let searcher = Searcher::new(&pattern, &options).map_err(FileError::Unknown)?;
+        *self.search_id.lock().unwrap() = Some(id);
+        let root_path = self.shared.read().unwrap().root_path.clone();
+        let notification_sender = self._notification_sender.clone();
+        let search_id = self.search_id.clone();
+        thread::spawn(move || {
+            let mut results = Vec::new();
+            let mut result_count = 0;
+            let mut last_send = Instant::now();
+            let mut is_cancelled = false;
+            searcher.walk(&root_path, &mut |full_path, path| {
+                if *search_id.lock().unwrap() != Some(id) {
+                    is_cancelled = true;
+                    return false;
+                }
+                for result in searcher.search_file(full_path, path) {
+                    results.push(result);
+                    result_count += 1;
+                    if result_count == MAX_SEARCH_RESULTS {
+                        return false;
+                    }
+                }
+                if !results.is_empty() && last_send.elapsed() > Duration::from_millis(50) {
+                    notification_sender.send_notification(FileNotification::SearchResults{
+                        id,
+                        results: std::mem::take(&mut results),
+                        done: false
+                    });
+                    last_send = Instant::now();
+                }
+                true
+            });
+            if !is_cancelled {
+                notification_sender.send_notification(FileNotification::SearchResults{
+                    id,
+                    results,
+                    done: true
+                });
+            }
+        });
+        Ok(id)
+    }
+    
+    // Handles a `ReplaceInFiles` request.
+    fn replace_in_files(
+        &self,
+        pattern: String,
+        options: SearchOptions,
+        replacement: String,
+        paths: Vec<String>
+    ) -> Result<Vec<SaveFileResponse>, FileError> {
+        let searcher = Searcher::new(&pattern, &options).map_err(FileError::Unknown)?;
+        let mut responses = Vec::new();
+        for child_path in paths {
+            let path = self.make_full_path(&child_path);
+            let Some(old_data) = read_text_file(&path) else {
+                continue;
+            };
+            let shared = self.shared.read().unwrap();
+            let mut sessions = shared.sessions.lock().unwrap();
+            if let Some(session) = sessions.get_mut(&child_path) {
+                // Every participant, including this client, gets the change as a delta.
+                let Some(new_data) = searcher.replace(&session.text, &replacement) else {
+                    continue;
+                };
+                let delta = Delta::diff(&session.text, &new_data);
+                let revision = session.revision();
+                session.apply_delta(&child_path, None, revision, delta)?;
+                fs::write(&path, &new_data).map_err(
+                    | error | FileError::Unknown(error.to_string())
+                ) ?;
+                session.disk_data = new_data.into_bytes();
+                continue;
+            }
+            drop(sessions);
+            let Some(new_data) = searcher.replace(&old_data, &replacement) else {
+                continue;
+            };
+            fs::write(&path, &new_data).map_err(
+                | error | FileError::Unknown(error.to_string())
+            ) ?;
+            // The client updates open documents from the response, so the observation thread
+            // should not report this change again.
+            if let Some(of) = self.open_files.lock().unwrap().iter_mut().find(|(cp,_,_)| *cp == child_path){
+                of.2 = new_data.as_bytes().to_vec();
+            }
+            responses.push(SaveFileResponse{
+                path: child_path,
+                old_data,
+                new_data,
+                id: 0,
+                kind: SaveKind::Save
+            });
+        }
+        Ok(responses)
+    }
+}
+
+impl Drop for FileServerConnection {
+    fn drop(&mut self) {
+        *self.stop_observation.lock().unwrap() = true;
+        let shared = self.shared.read().unwrap();
+        let mut sessions = shared.sessions.lock().unwrap();
+        sessions.retain(|path, session| {
+            session.remove_participant(path, self.connection_id);
+            !session.participants.is_empty()
+        });
+    }
 }
 
 /// A trait for sending notifications over a connection.
@@ -314,10 +582,155 @@ impl fmt::Debug for dyn NotificationSender {
     }
 }
 
+// Where Studio keeps data it can recreate or do without, following the conventions of the
+// platform.
+fn studio_cache_dir() -> PathBuf {
+    let var = | name | std::env::var_os(name).filter( | value | !value.is_empty()).map(PathBuf::from);
+    let base_dir = if cfg!(target_os = "windows") {
+        var("LOCALAPPDATA")
+    }
+    else if cfg!(target_os = "macos") {
+        var("HOME").map( | home | home.join("Library/Caches"))
+    }
+    else {
+        var("XDG_CACHE_HOME").or_else( || var("HOME").map( | home | home.join(".cache")))
+    };
+    base_dir.unwrap_or_else(std::env::temp_dir).join("makepad-studio")
+}
+
+// The undo histories of each project get their own directory, named after the project and a
+// hash of its full path so projects with the same name don't share one.
+fn default_undo_history_dir(root_path: &Path) -> PathBuf {
+    let root_path = root_path.canonicalize().unwrap_or_else( | _ | root_path.to_path_buf());
+    let name = root_path.file_name().unwrap_or_default().to_string_lossy();
+    let hash = LiveId::from_str(&root_path.to_string_lossy());
+    studio_cache_dir().join("undo_history").join(format!("{}_{:016x}", name, hash.0))
+}
+
 // State that is shared between every connection.
 #[derive(Debug)]
 struct Shared {
     root_path: PathBuf,
+    // The directory that the undo history of each file is stored in, by its path.
+    undo_history_dir: PathBuf,
+    // The collaboration session of every open file, by path.
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+// The collaboration session of an open file.
+#[derive(Debug)]
+struct Session {
+    // The text at the newest revision.
+    text: String,
+    // The contents of the file when it was last read or written.
+    disk_data: Vec<u8>,
+    // The revision that the first delta in `history` applies to.
+    history_start: usize,
+    history: Vec<Delta>,
+    participants: HashMap<ConnectionId, Participant>,
+}
+
+#[derive(Debug)]
+struct Participant {
+    notification_sender: Box<dyn NotificationSender>,
+    // The newest revision the participant is known to have seen.
+    revision: usize,
+    // Whether the participant changes the file with `ApplyDelta` rather than `SaveFile`.
+    applies_deltas: bool,
+}
+
+impl Session {
+    fn new(text: String, disk_data: Vec<u8>) -> Self {
+        Self {
+            text,
+            disk_data,
+            history_start: 0,
+            history: Vec::new(),
+            participants: HashMap::new(),
+        }
+    }
+    
+    fn revision(&self) -> usize {
+        self.history_start + self.history.len()
+    }
+    
+    // Returns the deltas that were applied after the given revision.
+    fn deltas_since(&self, revision: usize) -> Result<&[Delta], FileError> {
+        if revision < self.history_start || revision > self.revision() {
+            return Err(FileError::Unknown(format!(
+                "revision {} is not in the history of revisions {} to {}",
+                revision,
+                self.history_start,
+                self.revision()
+            )));
+        }
+        Ok(&self.history[revision - self.history_start..])
+    }
+    
+    // Transforms a delta for the given revision so it applies to the newest revision, applies it,
+    // and notifies every participant except the one it came from. Returns the new revision.
+    fn apply_delta(
+        &mut self,
+        path: &str,
+        from: Option<ConnectionId>,
+        revision: usize,
+        mut delta: Delta
+    ) -> Result<usize, FileError> {
+        let concurrent_deltas = self.deltas_since(revision)?;
+        // Transforming panics on deltas of different lengths, so a delta that doesn't fit its
+        // revision has to be rejected before that.
+        let len = concurrent_deltas.first().map_or(self.text.len(), |delta| delta.base_len());
+        if delta.base_len() != len {
+            return Err(FileError::Unknown(format!(
+                "delta expects a text of {} bytes, but revision {} has {} bytes",
+                delta.base_len(),
+                revision,
+                len
+            )));
+        }
+        for concurrent_delta in concurrent_deltas {
+            delta = delta.transform(concurrent_delta).0;
+        }
+        self.text = delta.apply(&self.text).map_err(FileError::Unknown)?;
+        self.history.push(delta.clone());
+        let revision = self.revision();
+        for (connection_id, participant) in &self.participants {
+            if Some(*connection_id) != from {
+                participant.notification_sender.send_notification(FileNotification::DeltaWasApplied{
+                    path: path.to_string(),
+                    revision,
+                    delta: delta.clone(),
+                    participant: from.map(|from| from.0 as u64),
+                });
+            }
+        }
+        self.prune_history();
+        Ok(revision)
+    }
+    
+    fn remove_participant(&mut self, path: &str, connection_id: ConnectionId) {
+        if self.participants.remove(&connection_id).is_none() {
+            return;
+        }
+        for participant in self.participants.values() {
+            participant.notification_sender.send_notification(FileNotification::ParticipantLeft{
+                path: path.to_string(),
+                participant: connection_id.0 as u64,
+            });
+        }
+        self.prune_history();
+    }
+    
+    // Removes the deltas that every participant has seen.
+    fn prune_history(&mut self) {
+        let revision = self.participants.values()
+            .map(|participant| participant.revision)
+            .min()
+            .unwrap_or(self.revision());
+        let count = revision.saturating_sub(self.history_start);
+        self.history.drain(..count);
+        self.history_start += count;
+    }
 }
 
 /// An identifier for a connection.
@@ -3,6 +3,8 @@
 pub mod file_server;
 #[cfg(not(target_arch = "wasm32"))]
 pub use file_server::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod search;
 
 pub use makepad_micro_serde;
 pub use makepad_live_id;
@@ -0,0 +1,588 @@
+use {
+    crate::makepad_file_protocol::{SearchOptions, SearchResult},
+    makepad_regex::{self, SearchQuery},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+// Files larger than this are not searched.
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// A glob pattern for paths relative to the root.
+///
+/// `*` matches anything but `/`, `**` matches anything including `/`, `?` matches a single
+/// character, and `[...]` matches a character class. A pattern without a `/` matches the name of a
+/// file or directory anywhere in the tree, otherwise it matches the path from the root. A trailing
+/// `/` only matches directories.
+#[derive(Clone, Debug)]
+pub struct Glob {
+    chars: Vec<char>,
+    is_anchored: bool,
+    is_dir_only: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut pattern = pattern.trim();
+        let is_dir_only = pattern.ends_with('/');
+        pattern = pattern.trim_end_matches('/');
+        let is_anchored = pattern.contains('/');
+        pattern = pattern.trim_start_matches('/');
+        if pattern.is_empty() {
+            return Err("empty glob".to_string());
+        }
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut index = 0;
+        while index < chars.len() {
+            if chars[index] == '[' {
+                match chars[index..].iter().position(|&char| char == ']') {
+                    Some(end) => index += end,
+                    None => return Err(format!("unclosed `[` in glob `{}`", pattern)),
+                }
+            }
+            index += 1;
+        }
+        Ok(Self {
+            chars,
+            is_anchored,
+            is_dir_only,
+        })
+    }
+
+    /// Returns whether the glob matches the file or directory at `path` itself.
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.is_dir_only && !is_dir {
+            return false;
+        }
+        let path: Vec<char> = if self.is_anchored {
+            path.chars().collect()
+        } else {
+            path.rsplit('/').next().unwrap_or(path).chars().collect()
+        };
+        match_glob(&self.chars, &path)
+    }
+
+    /// Returns whether the glob matches the file or directory at `path`, or one of the directories
+    /// that contain it.
+    pub fn matches_path(&self, path: &str, is_dir: bool) -> bool {
+        path.match_indices('/')
+            .any(|(index, _)| self.matches(&path[..index], true))
+            || self.matches(path, is_dir)
+    }
+}
+
+fn match_glob(pattern: &[char], path: &[char]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            // `**/` also matches no directories at all
+            if rest.first() == Some(&'/') && match_glob(&rest[1..], path) {
+                return true;
+            }
+            (0..=path.len()).any(|start| match_glob(rest, &path[start..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for start in 0..=path.len() {
+                if match_glob(rest, &path[start..]) {
+                    return true;
+                }
+                if path.get(start) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => match path.first() {
+            Some(&char) if char != '/' => match_glob(&pattern[1..], &path[1..]),
+            _ => false,
+        },
+        Some('[') => {
+            let end = pattern.iter().position(|&char| char == ']').unwrap();
+            match path.first() {
+                Some(&char) if char != '/' && match_class(&pattern[1..end], char) => {
+                    match_glob(&pattern[end + 1..], &path[1..])
+                }
+                _ => false,
+            }
+        }
+        Some('\\') if pattern.len() > 1 => {
+            path.first() == Some(&pattern[1]) && match_glob(&pattern[2..], &path[1..])
+        }
+        Some(&char) => path.first() == Some(&char) && match_glob(&pattern[1..], &path[1..]),
+    }
+}
+
+fn match_class(class: &[char], char: char) -> bool {
+    let (is_negated, class) = match class.first() {
+        Some('!') | Some('^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut is_match = false;
+    let mut index = 0;
+    while index < class.len() {
+        if index + 2 < class.len() && class[index + 1] == '-' {
+            is_match |= (class[index]..=class[index + 2]).contains(&char);
+            index += 3;
+        } else {
+            is_match |= class[index] == char;
+            index += 1;
+        }
+    }
+    is_match != is_negated
+}
+
+// The rules of a single `.gitignore` file.
+struct IgnoreFile {
+    // The directory the file is in, relative to the top of the repository and ending in a `/`.
+    base: String,
+    rules: Vec<(Glob, bool)>,
+}
+
+impl IgnoreFile {
+    fn load(dir: &Path, base: String) -> Option<Self> {
+        let data = fs::read_to_string(dir.join(".gitignore")).ok()?;
+        let rules = data
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim_end();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let (line, is_negated) = match line.strip_prefix('!') {
+                    Some(line) => (line, true),
+                    None => (line.strip_prefix('\\').unwrap_or(line), false),
+                };
+                Some((Glob::new(line).ok()?, is_negated))
+            })
+            .collect();
+        Some(Self { base, rules })
+    }
+}
+
+/// The `.gitignore` rules that apply to a directory, including the ones in the directories above it
+/// up to the top of the repository.
+pub struct Gitignore {
+    // The root of the search, relative to the top of the repository.
+    root_prefix: String,
+    files: Vec<IgnoreFile>,
+}
+
+impl Gitignore {
+    pub fn new(root_path: &Path) -> Self {
+        let root_path = root_path.canonicalize().unwrap_or_else(|_| root_path.to_path_buf());
+        let mut dirs = Vec::new();
+        let mut dir = Some(root_path.as_path());
+        while let Some(current) = dir {
+            dirs.push(current);
+            if current.join(".git").exists() {
+                break;
+            }
+            dir = current.parent();
+        }
+        if !dirs.last().is_some_and(|dir| dir.join(".git").exists()) {
+            // Not in a repository, so only the files below the root count.
+            dirs.truncate(1);
+        }
+        let mut root_prefix = String::new();
+        let mut files = Vec::new();
+        for (index, dir) in dirs.iter().enumerate().rev() {
+            files.extend(IgnoreFile::load(dir, root_prefix.clone()));
+            if index > 0 {
+                let name = dirs[index - 1].file_name().unwrap_or_default();
+                root_prefix.push_str(&name.to_string_lossy());
+                root_prefix.push('/');
+            }
+        }
+        Self { root_prefix, files }
+    }
+
+    /// Adds the rules of the `.gitignore` in the directory at `path`, relative to the root.
+    pub fn push_dir(&mut self, dir: &Path, path: &str) -> bool {
+        match IgnoreFile::load(dir, format!("{}{}/", self.root_prefix, path)) {
+            Some(file) => {
+                self.files.push(file);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn pop_dir(&mut self) {
+        self.files.pop();
+    }
+
+    /// Returns whether the file or directory at `path`, relative to the root, is ignored. Rules in
+    /// deeper `.gitignore` files and later lines take precedence.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let path = format!("{}{}", self.root_prefix, path);
+        for file in self.files.iter().rev() {
+            let Some(path) = path.strip_prefix(&file.base) else {
+                continue;
+            };
+            if let Some((_, is_negated)) = file
+                .rules
+                .iter()
+                .rev()
+                .find(|(glob, _)| glob.matches(path, is_dir))
+            {
+                return !is_negated;
+            }
+        }
+        false
+    }
+}
+
+/// A compiled `Search` request.
+pub struct Searcher {
+    query: SearchQuery,
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+}
+
+impl Searcher {
+    pub fn new(pattern: &str, options: &SearchOptions) -> Result<Self, String> {
+        let query = SearchQuery::new(
+            pattern,
+            makepad_regex::SearchOptions {
+                case_sensitive: options.case_sensitive,
+                whole_word: options.whole_word,
+                regex: options.regex,
+            },
+        )
+        .map_err(|error| error.to_string())?;
+        let compile = |globs: &[String]| {
+            globs
+                .iter()
+                .filter(|glob| !glob.trim().is_empty())
+                .map(|glob| Glob::new(glob))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            query,
+            include: compile(&options.include)?,
+            exclude: compile(&options.exclude)?,
+        })
+    }
+
+    /// Calls `f` with the path of every file below `root_path` that should be searched, relative
+    /// to `root_path`, until it returns `false`.
+    ///
+    /// This skips the same entries as the file tree does, as well as everything ignored by a
+    /// `.gitignore`.
+    pub fn walk(&self, root_path: &Path, f: &mut dyn FnMut(&Path, &str) -> bool) {
+        fn walk_dir(
+            searcher: &Searcher,
+            dir: &Path,
+            path: &str,
+            gitignore: &mut Gitignore,
+            f: &mut dyn FnMut(&Path, &str) -> bool,
+        ) -> bool {
+            let Ok(entries) = fs::read_dir(dir) else {
+                return true;
+            };
+            let mut entries: Vec<(String, PathBuf)> = entries
+                .filter_map(|entry| {
+                    let entry = entry.ok()?;
+                    Some((entry.file_name().into_string().ok()?, entry.path()))
+                })
+                .collect();
+            entries.sort();
+            for (name, entry_path) in entries {
+                let is_dir = entry_path.is_dir();
+                if name.starts_with('.') || is_dir && name == "target" {
+                    continue;
+                }
+                let child_path = if path.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", path, name)
+                };
+                if gitignore.is_ignored(&child_path, is_dir)
+                    || searcher
+                        .exclude
+                        .iter()
+                        .any(|glob| glob.matches_path(&child_path, is_dir))
+                {
+                    continue;
+                }
+                if is_dir {
+                    let pushed = gitignore.push_dir(&entry_path, &child_path);
+                    let keep_going = walk_dir(searcher, &entry_path, &child_path, gitignore, f);
+                    if pushed {
+                        gitignore.pop_dir();
+                    }
+                    if !keep_going {
+                        return false;
+                    }
+                } else if entry_path.is_file()
+                    && (searcher.include.is_empty()
+                        || searcher
+                            .include
+                            .iter()
+                            .any(|glob| glob.matches_path(&child_path, false)))
+                    && !f(&entry_path, &child_path)
+                {
+                    return false;
+                }
+            }
+            true
+        }
+
+        let mut gitignore = Gitignore::new(root_path);
+        walk_dir(self, root_path, "", &mut gitignore, f);
+    }
+
+    /// Returns the matching lines of the file at `full_path`, or nothing if it is not a text file.
+    pub fn search_file(&self, full_path: &Path, path: &str) -> Vec<SearchResult> {
+        let Some(data) = read_text_file(full_path) else {
+            return Vec::new();
+        };
+        let mut results = Vec::new();
+        for (line, line_text) in data.lines().enumerate() {
+            let ranges = self.query.find_in_line(line_text);
+            if ranges.is_empty() {
+                continue;
+            }
+            results.push(SearchResult {
+                path: path.to_string(),
+                line,
+                line_text: line_text.to_string(),
+                ranges: ranges.into_iter().map(|range| (range.start, range.end)).collect(),
+            });
+        }
+        results
+    }
+
+    /// Replaces every match in `data`, keeping its line endings. Returns `None` if nothing matched.
+    pub fn replace(&self, data: &str, replacement: &str) -> Option<String> {
+        let mut new_data = String::with_capacity(data.len());
+        let mut count = 0;
+        for line in data.split_inclusive('\n') {
+            let (line, ending) = match line.strip_suffix("\r\n") {
+                Some(line) => (line, "\r\n"),
+                None => match line.strip_suffix('\n') {
+                    Some(line) => (line, "\n"),
+                    None => (line, ""),
+                },
+            };
+            let (new_line, line_count) = self.query.replace_in_line(line, replacement);
+            new_data.push_str(&new_line);
+            new_data.push_str(ending);
+            count += line_count;
+        }
+        if count == 0 {
+            return None;
+        }
+        Some(new_data)
+    }
+}
+
+/// Reads a file as UTF-8 text. Returns `None` for files that are too large, binary or not UTF-8.
+pub fn read_text_file(path: &Path) -> Option<String> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if bytes[..bytes.len().min(8000)].contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A directory tree that is removed again when the test is done.
+    struct TempDir {
+        path: PathBuf,
+    }
+
+    impl TempDir {
+        fn new(name: &str, files: &[(&str, &[u8])]) -> Self {
+            let path = std::env::temp_dir().join(format!("makepad_search_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            for (file_path, data) in files {
+                let file_path = path.join(file_path);
+                fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+                fs::write(file_path, data).unwrap();
+            }
+            Self { path }
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    fn searcher(pattern: &str, include: &[&str], exclude: &[&str]) -> Searcher {
+        let options = SearchOptions {
+            include: include.iter().map(|glob| glob.to_string()).collect(),
+            exclude: exclude.iter().map(|glob| glob.to_string()).collect(),
+            ..SearchOptions::default()
+        };
+        Searcher::new(pattern, &options).unwrap()
+    }
+
+    fn walk(searcher: &Searcher, root_path: &Path) -> Vec<String> {
+        let mut paths = Vec::new();
+        searcher.walk(root_path, &mut |_, path| {
+            paths.push(path.to_string());
+            true
+        });
+        paths
+    }
+
+    fn glob(pattern: &str) -> Glob {
+        Glob::new(pattern).unwrap()
+    }
+
+    #[test]
+    fn unanchored_globs_match_names_anywhere() {
+        assert!(glob("*.rs").matches("main.rs", false));
+        assert!(glob("*.rs").matches("src/bin/main.rs", false));
+        assert!(!glob("*.rs").matches("main.rsx", false));
+        assert!(glob("?.rs").matches("src/a.rs", false));
+        assert!(!glob("?.rs").matches("ab.rs", false));
+        assert!(glob("[a-c].rs").matches("b.rs", false));
+        assert!(!glob("[!a-c].rs").matches("b.rs", false));
+        assert!(glob("[^a-c].rs").matches("d.rs", false));
+        assert!(glob("\\*.rs").matches("*.rs", false));
+        assert!(!glob("\\*.rs").matches("a.rs", false));
+    }
+
+    #[test]
+    fn anchored_globs_match_from_the_root() {
+        assert!(glob("src/*.rs").matches("src/main.rs", false));
+        assert!(!glob("src/*.rs").matches("src/bin/main.rs", false));
+        assert!(!glob("src/*.rs").matches("lib/src/main.rs", false));
+        assert!(glob("/target").matches("target", true));
+        assert!(!glob("/target").matches("lib/target", true));
+        assert!(glob("target").matches("lib/target", true));
+    }
+
+    #[test]
+    fn double_stars_match_any_number_of_directories() {
+        assert!(glob("**/test").matches("test", true));
+        assert!(glob("**/test").matches("a/b/test", true));
+        assert!(glob("a/**/b").matches("a/b", true));
+        assert!(glob("a/**/b").matches("a/x/y/b", true));
+        assert!(!glob("a/**/b").matches("x/a/b", true));
+        assert!(glob("studio/**").matches("studio/src/app.rs", false));
+        assert!(glob("**/*.rs").matches("a/b/c.rs", false));
+        assert!(!glob("a/*/b").matches("a/x/y/b", true));
+    }
+
+    #[test]
+    fn trailing_slashes_only_match_directories() {
+        assert!(glob("build/").matches("build", true));
+        assert!(!glob("build/").matches("build", false));
+        assert!(glob("build/").matches_path("build/out/main.o", false));
+        assert!(glob("*.log").matches_path("logs/today.log", false));
+        assert!(!glob("*.log").matches_path("logs/today.txt", false));
+    }
+
+    #[test]
+    fn rejects_invalid_globs() {
+        assert!(Glob::new("").is_err());
+        assert!(Glob::new("/").is_err());
+        assert!(Glob::new("[abc").is_err());
+    }
+
+    fn gitignore_tree(name: &str) -> TempDir {
+        TempDir::new(
+            name,
+            &[
+                (".git/HEAD", b"ref: refs/heads/main\n"),
+                (".gitignore", b"# logs\n*.log\n!keep.log\nbuild/\n/top.txt\n"),
+                ("a.log", b""),
+                ("keep.log", b""),
+                ("top.txt", b""),
+                ("secret.txt", b""),
+                ("build/main.o", b""),
+                ("target/debug/main", b""),
+                ("src/main.rs", b""),
+                ("sub/.gitignore", b"!debug.log\nsecret.txt\n"),
+                ("sub/build", b""),
+                ("sub/debug.log", b""),
+                ("sub/other.log", b""),
+                ("sub/secret.txt", b""),
+                ("sub/top.txt", b""),
+                ("sub/deep/secret.txt", b""),
+            ],
+        )
+    }
+
+    #[test]
+    fn deeper_gitignores_take_precedence() {
+        let dir = gitignore_tree("gitignore");
+        assert_eq!(
+            walk(&searcher("x", &[], &[]), &dir.path),
+            [
+                "keep.log",
+                "secret.txt",
+                "src/main.rs",
+                "sub/build",
+                "sub/debug.log",
+                "sub/top.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn gitignores_above_the_root_apply() {
+        let dir = gitignore_tree("gitignore_above");
+        let gitignore = Gitignore::new(&dir.path.join("sub"));
+        assert!(gitignore.is_ignored("other.log", false));
+        assert!(!gitignore.is_ignored("debug.log", false));
+        assert!(!gitignore.is_ignored("top.txt", false));
+        assert!(gitignore.is_ignored("secret.txt", false));
+    }
+
+    #[test]
+    fn walks_included_and_not_excluded_files() {
+        let dir = gitignore_tree("include");
+        assert_eq!(walk(&searcher("x", &["*.txt"], &[]), &dir.path), ["secret.txt", "sub/top.txt"]);
+        assert_eq!(walk(&searcher("x", &["sub/**"], &["*.log"]), &dir.path), ["sub/build", "sub/top.txt"]);
+        assert_eq!(walk(&searcher("x", &[], &["sub", "src/"]), &dir.path), ["keep.log", "secret.txt"]);
+    }
+
+    #[test]
+    fn skips_binary_and_oversized_files() {
+        let mut large = b"needle\n".to_vec();
+        large.resize(MAX_FILE_SIZE as usize + 1, b'x');
+        let dir = TempDir::new(
+            "binary",
+            &[
+                ("binary.dat", b"needle\0needle\n"),
+                ("large.txt", &large),
+                ("latin1.txt", b"needle caf\xe9\n"),
+                ("text.txt", b"one\r\nneedle needle\r\n"),
+            ],
+        );
+        let searcher = searcher("needle", &[], &[]);
+        let results: Vec<_> = walk(&searcher, &dir.path)
+            .into_iter()
+            .flat_map(|path| searcher.search_file(&dir.path.join(&path), &path))
+            .collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "text.txt");
+        assert_eq!(results[0].line, 1);
+        assert_eq!(results[0].line_text, "needle needle");
+        assert_eq!(results[0].ranges, [(0, 6), (7, 13)]);
+        assert_eq!(read_text_file(&dir.path.join("large.txt")), None);
+    }
+
+    #[test]
+    fn replaces_and_keeps_line_endings() {
+        let searcher = searcher("a", &[], &[]);
+        assert_eq!(searcher.replace("a\r\nb\nab", "x").as_deref(), Some("x\r\nb\nxb"));
+        assert_eq!(searcher.replace("b\n", "x"), None);
+    }
+}
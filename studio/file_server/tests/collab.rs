@@ -0,0 +1,399 @@
+use {
+    makepad_file_server::{
+        CollabClient, Delta, FileNotification, FileRequest, FileResponse, FileServer,
+        FileServerConnection, OffsetSelection, SearchOptions,
+    },
+    std::{
+        fs,
+        path::PathBuf,
+        sync::mpsc::{self, Receiver},
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+// A directory with a single file in it, which is removed again when the test is done.
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(name: &str, text: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("makepad_collab_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("main.rs"), text).unwrap();
+        Self { path }
+    }
+
+    fn read(&self) -> String {
+        fs::read_to_string(self.path.join("main.rs")).unwrap()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+// A participant for `main.rs`. Deltas are only sent to the server when `send` is called, and
+// responses and notifications are only handled when `receive` is called, so tests can control how
+// the participants interleave.
+struct Client {
+    connection: FileServerConnection,
+    notifications: Receiver<FileNotification>,
+    text: String,
+    collab: CollabClient,
+    // The delta to send next, and the revision it is for.
+    outgoing: Option<(usize, Delta)>,
+    // The revisions of the deltas that the server acknowledged.
+    acks: Vec<usize>,
+}
+
+impl Client {
+    fn connect(server: &mut FileServer) -> Self {
+        let (sender, notifications) = mpsc::channel();
+        let connection = server.connect(Box::new(move |notification| {
+            let _ = sender.send(notification);
+        }));
+        let FileResponse::OpenFile(Ok(response)) = connection.handle_request(FileRequest::OpenFile {
+            path: "main.rs".to_string(),
+            id: 0,
+        }) else {
+            panic!("cannot open file")
+        };
+        Self {
+            connection,
+            notifications,
+            text: response.data,
+            collab: CollabClient::new(response.revision),
+            outgoing: None,
+            acks: Vec::new(),
+        }
+    }
+
+    fn edit(&mut self, new_text: &str) {
+        let delta = Delta::diff(&self.text, new_text);
+        self.text = new_text.to_string();
+        if let Some(delta) = self.collab.apply_local(delta) {
+            self.outgoing = Some((self.collab.revision(), delta));
+        }
+    }
+
+    fn insert(&mut self, offset: usize, string: &str) {
+        let mut text = self.text.clone();
+        text.insert_str(offset, string);
+        self.edit(&text);
+    }
+
+    fn send(&mut self) {
+        if let Some((revision, delta)) = self.outgoing.take() {
+            match self.connection.handle_request(FileRequest::ApplyDelta {
+                path: "main.rs".to_string(),
+                revision,
+                delta,
+            }) {
+                FileResponse::ApplyDelta(Ok(response)) => self.acks.push(response.revision),
+                response => panic!("unexpected response {:?}", response),
+            }
+        }
+    }
+
+    fn receive(&mut self) {
+        while let Ok(notification) = self.notifications.try_recv() {
+            match notification {
+                FileNotification::DeltaWasApplied { revision, delta, .. } => {
+                    self.collab.receive_remote(revision, delta);
+                    self.apply_remote_deltas();
+                }
+                FileNotification::SelectionsChanged { revision, participant, selections, .. } => {
+                    self.collab.receive_remote_selections(participant, revision, selections);
+                }
+                FileNotification::ParticipantLeft { participant, .. } => {
+                    self.collab.remove_participant(participant);
+                }
+                _ => {}
+            }
+        }
+        for revision in std::mem::take(&mut self.acks) {
+            if let Some(delta) = self.collab.apply_ack(revision) {
+                self.outgoing = Some((self.collab.revision(), delta));
+            }
+            self.apply_remote_deltas();
+        }
+    }
+
+    fn apply_remote_deltas(&mut self) {
+        while let Some(delta) = self.collab.next_remote_delta() {
+            self.text = delta.apply(&self.text).unwrap();
+        }
+    }
+
+    fn select(&mut self, offset: usize) {
+        assert!(self.collab.is_synchronized());
+        self.connection.handle_request(FileRequest::UpdateSelections {
+            path: "main.rs".to_string(),
+            revision: self.collab.revision(),
+            selections: vec![OffsetSelection { anchor: offset, cursor: offset }],
+        });
+    }
+
+    fn save(&self, data: &str) -> String {
+        match self.connection.handle_request(FileRequest::SaveFile {
+            path: "main.rs".to_string(),
+            data: data.to_string(),
+            id: 0,
+            patch: false,
+            undo_history: None,
+        }) {
+            FileResponse::SaveFile(Ok(response)) => response.new_data,
+            response => panic!("unexpected response {:?}", response),
+        }
+    }
+}
+
+// Sends and receives until every client has seen every delta.
+fn sync(clients: &mut [Client]) {
+    for _ in 0..10 {
+        for client in clients.iter_mut() {
+            client.send();
+        }
+        for client in clients.iter_mut() {
+            client.receive();
+        }
+    }
+    for client in clients.iter() {
+        assert!(client.collab.is_synchronized());
+        assert_eq!(client.text, clients[0].text);
+    }
+}
+
+#[test]
+fn concurrent_edits_converge() {
+    let dir = TempDir::new("converge", "fn main() {\n}\n");
+    let mut server = FileServer::new(&dir.path);
+    let mut a = Client::connect(&mut server);
+    let mut b = Client::connect(&mut server);
+
+    a.insert(12, "    let a = 1;\n");
+    b.insert(12, "    let b = 2;\n");
+    b.insert(0, "// b\n");
+    a.send();
+    b.send();
+    a.insert(0, "// a\n");
+    let mut clients = [a, b];
+    sync(&mut clients);
+    assert_eq!(
+        clients[0].text,
+        "// b\n// a\nfn main() {\n    let b = 2;\n    let a = 1;\n}\n"
+    );
+
+    // A client that joins later gets the text of the session rather than the file on disk.
+    let c = Client::connect(&mut server);
+    assert_eq!(c.text, clients[0].text);
+    assert_eq!(c.collab.revision(), clients[0].collab.revision());
+    assert_eq!(clients[1].save(""), clients[0].text);
+    assert_eq!(dir.read(), clients[0].text);
+}
+
+#[test]
+fn interleaved_edits_of_many_clients_converge() {
+    let dir = TempDir::new("interleaved", "0123456789\n");
+    let mut server = FileServer::new(&dir.path);
+    let mut clients: Vec<Client> = (0..3).map(|_| Client::connect(&mut server)).collect();
+    let mut seed = 7u32;
+    let mut random = move |max: usize| {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        (seed >> 16) as usize % max.max(1)
+    };
+    for round in 0..200 {
+        let index = random(clients.len());
+        let client = &mut clients[index];
+        match random(4) {
+            0 if !client.text.is_empty() => {
+                let start = random(client.text.len());
+                let end = (start + random(4)).min(client.text.len());
+                let mut text = client.text.clone();
+                text.replace_range(start..end, "");
+                client.edit(&text);
+            }
+            1 => client.send(),
+            2 => client.receive(),
+            _ => {
+                let offset = random(client.text.len() + 1);
+                client.insert(offset, &format!("<{}:{}>", index, round));
+            }
+        }
+    }
+    sync(&mut clients);
+}
+
+#[test]
+fn selections_follow_concurrent_edits() {
+    let dir = TempDir::new("selections", "abcdef");
+    let mut server = FileServer::new(&dir.path);
+    let mut a = Client::connect(&mut server);
+    let mut b = Client::connect(&mut server);
+
+    // B selects in revision 0, after A already inserted in front of it.
+    a.insert(0, "XYZ");
+    a.send();
+    b.select(4);
+    a.receive();
+    assert_eq!(a.text, "XYZabcdef");
+    let selection = OffsetSelection { anchor: 7, cursor: 7 };
+    assert_eq!(a.collab.remote_selections(), vec![(1, vec![selection])]);
+
+    // Selections move along with the edits that come in after them.
+    b.receive();
+    b.insert(0, "__");
+    b.send();
+    a.receive();
+    assert_eq!(a.text, "__XYZabcdef");
+    let selection = OffsetSelection { anchor: 9, cursor: 9 };
+    assert_eq!(a.collab.remote_selections(), vec![(1, vec![selection])]);
+
+    drop(b);
+    a.receive();
+    assert!(a.collab.remote_selections().is_empty());
+}
+
+#[test]
+fn saving_without_deltas_becomes_a_delta() {
+    let dir = TempDir::new("save", "one\n");
+    let mut server = FileServer::new(&dir.path);
+    let a = Client::connect(&mut server);
+    let mut b = Client::connect(&mut server);
+
+    assert_eq!(a.save("one\ntwo\n"), "one\ntwo\n");
+    b.receive();
+    assert_eq!(b.text, "one\ntwo\n");
+    assert_eq!(dir.read(), "one\ntwo\n");
+}
+
+#[test]
+fn changes_on_disk_become_a_delta() {
+    let dir = TempDir::new("disk", "one\n");
+    let mut server = FileServer::new(&dir.path);
+    let mut a = Client::connect(&mut server);
+    let mut b = Client::connect(&mut server);
+    a.insert(0, "zero\n");
+    let mut clients = [a, b];
+    sync(&mut clients);
+    [a, b] = clients;
+
+    fs::write(dir.path.join("main.rs"), "one\ntwo\n").unwrap();
+    let start = Instant::now();
+    while b.text != "one\ntwo\n" && start.elapsed() < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(10));
+        a.receive();
+        b.receive();
+    }
+    assert_eq!(a.text, "one\ntwo\n");
+    assert_eq!(b.text, "one\ntwo\n");
+}
+
+#[test]
+fn deltas_of_the_wrong_length_are_rejected() {
+    let dir = TempDir::new("wrong_length", "one\n");
+    let mut server = FileServer::new(&dir.path);
+    let mut a = Client::connect(&mut server);
+    let mut b = Client::connect(&mut server);
+    a.insert(0, "zero\n");
+    a.send();
+
+    // A delta for the first revision, that is too long for it, next to a concurrent delta.
+    let mut delta = Delta::new();
+    delta.retain(10);
+    delta.insert("x");
+    for revision in [0, 1] {
+        match b.connection.handle_request(FileRequest::ApplyDelta {
+            path: "main.rs".to_string(),
+            revision,
+            delta: delta.clone(),
+        }) {
+            FileResponse::ApplyDelta(Err(_)) => {}
+            response => panic!("unexpected response {:?}", response),
+        }
+    }
+
+    // The session is still usable afterwards.
+    b.insert(3, "!");
+    let mut clients = [a, b];
+    sync(&mut clients);
+    assert_eq!(clients[0].text, "zero\none!\n");
+}
+
+#[test]
+fn replacing_in_a_file_with_a_session_becomes_a_delta() {
+    let dir = TempDir::new("replace", "let a = 1;\nlet b = a;\n");
+    fs::write(dir.path.join("other.rs"), "a\n").unwrap();
+    let mut server = FileServer::new(&dir.path);
+    let a = Client::connect(&mut server);
+    let mut b = Client::connect(&mut server);
+    // An edit the server has not seen yet.
+    b.insert(0, "// b\n");
+
+    let FileResponse::ReplaceInFiles(Ok(responses)) =
+        a.connection.handle_request(FileRequest::ReplaceInFiles {
+            pattern: "a".to_string(),
+            options: SearchOptions {
+                whole_word: true,
+                ..SearchOptions::default()
+            },
+            replacement: "x".to_string(),
+            paths: vec!["main.rs".to_string(), "other.rs".to_string()],
+        })
+    else {
+        panic!("cannot replace in files")
+    };
+    // Files with a session are updated through the session, not through a response.
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0].path, "other.rs");
+    assert_eq!(responses[0].new_data, "x\n");
+    assert_eq!(dir.read(), "let x = 1;\nlet b = x;\n");
+
+    let mut clients = [a, b];
+    sync(&mut clients);
+    assert_eq!(clients[0].text, "// b\nlet x = 1;\nlet b = x;\n");
+}
+
+#[test]
+fn undo_history_is_stored_outside_the_project() {
+    let dir = TempDir::new("undo_history", "fn main() {\n}\n");
+    let undo_dir = TempDir::new("undo_history_cache", "");
+    fs::create_dir_all(dir.path.join("src")).unwrap();
+    fs::write(dir.path.join("src/lib.rs"), "").unwrap();
+    let mut server = FileServer::new(&dir.path);
+    server.set_undo_history_dir(&undo_dir.path);
+    let connection = server.connect(Box::new(|_| {}));
+
+    for (path, undo_history) in [("main.rs", vec![1, 2, 3]), ("src/lib.rs", vec![4, 5])] {
+        let response = connection.handle_request(FileRequest::SaveFile {
+            path: path.to_string(),
+            data: "fn main() {}\n".to_string(),
+            id: 0,
+            patch: false,
+            undo_history: Some(undo_history.clone()),
+        });
+        assert!(matches!(response, FileResponse::SaveFile(Ok(_))), "{:?}", response);
+        assert_eq!(fs::read(undo_dir.path.join(format!("{}.undo", path))).unwrap(), undo_history);
+
+        let FileResponse::OpenFile(Ok(response)) = connection.handle_request(FileRequest::OpenFile {
+            path: path.to_string(),
+            id: 0,
+        }) else {
+            panic!("cannot open {}", path)
+        };
+        assert_eq!(response.undo_history, Some(undo_history));
+    }
+
+    let mut project_files: Vec<_> = fs::read_dir(&dir.path)
+        .unwrap()
+        .chain(fs::read_dir(dir.path.join("src")).unwrap())
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    project_files.sort();
+    assert_eq!(project_files, ["lib.rs", "main.rs", "src"]);
+}
@@ -0,0 +1,246 @@
+use std::ops::Range;
+
+/// One hunk of a unified diff. The line numbers are the ones from its `@@ -a,b +c,d @@` header:
+/// they start at 1, and on a side without lines they are the line after which the change happens.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    /// The lines of the hunk with their ` `, `+` or `-` prefix, including any
+    /// `\ No newline at end of file` markers.
+    pub lines: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HunkKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl Hunk {
+    pub fn kind(&self) -> HunkKind {
+        let adds = self.lines.iter().any(|line| line.starts_with('+'));
+        let deletes = self.lines.iter().any(|line| line.starts_with('-'));
+        match (adds, deletes) {
+            (true, false) => HunkKind::Added,
+            (false, true) => HunkKind::Deleted,
+            _ => HunkKind::Modified,
+        }
+    }
+
+    /// The zero based range of lines the hunk covers in the new version. For a hunk that only
+    /// deletes lines, the range is empty and starts at the line that follows them.
+    pub fn new_line_range(&self) -> Range<usize> {
+        if self.new_count == 0 {
+            self.new_start..self.new_start
+        } else {
+            self.new_start - 1..self.new_start - 1 + self.new_count
+        }
+    }
+
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{} +{} @@",
+            format_range(self.old_start, self.old_count),
+            format_range(self.new_start, self.new_count)
+        )
+    }
+}
+
+/// The diff of a single file: the `diff --git`, `index`, `---` and `+++` lines, followed by
+/// the hunks.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FileDiff {
+    pub header: Vec<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FileDiff {
+    /// Parses the output of `git diff`, which may contain any number of files.
+    pub fn parse(diff: &str) -> Vec<FileDiff> {
+        let mut file_diffs: Vec<FileDiff> = Vec::new();
+        for line in diff.lines() {
+            if line.starts_with("diff ") {
+                file_diffs.push(FileDiff {
+                    header: vec![line.to_string()],
+                    hunks: Vec::new(),
+                });
+                continue;
+            }
+            let Some(file_diff) = file_diffs.last_mut() else {
+                continue;
+            };
+            if line.starts_with("@@") {
+                if let Some(hunk) = parse_hunk_header(line) {
+                    file_diff.hunks.push(hunk);
+                    continue;
+                }
+            }
+            match file_diff.hunks.last_mut() {
+                Some(hunk) => hunk.lines.push(line.to_string()),
+                None => file_diff.header.push(line.to_string()),
+            }
+        }
+        file_diffs
+    }
+
+    /// Returns a patch that only contains the hunk at `index`, for `git apply`.
+    pub fn hunk_patch(&self, index: usize) -> String {
+        let mut patch = String::new();
+        for line in &self.header {
+            patch.push_str(line);
+            patch.push('\n');
+        }
+        let hunk = &self.hunks[index];
+        patch.push_str(&hunk.header());
+        patch.push('\n');
+        for line in &hunk.lines {
+            patch.push_str(line);
+            patch.push('\n');
+        }
+        patch
+    }
+}
+
+/// Compares two versions of a text line by line, and returns the changes as hunks without any
+/// context, the way `git diff -U0` would.
+pub fn diff_lines(old: &str, new: &str) -> Vec<Hunk> {
+    let old: Vec<&str> = old.split('\n').collect();
+    let new: Vec<&str> = new.split('\n').collect();
+    let mut prefix = 0;
+    while prefix < old.len() && prefix < new.len() && old[prefix] == new[prefix] {
+        prefix += 1;
+    }
+    let mut suffix = 0;
+    while suffix < old.len() - prefix
+        && suffix < new.len() - prefix
+        && old[old.len() - 1 - suffix] == new[new.len() - 1 - suffix]
+    {
+        suffix += 1;
+    }
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+    // Texts that are too different to diff in reasonable time are one big change.
+    let matches = find_matches(old, new, MAX_EDIT_COUNT).unwrap_or_default();
+    let mut hunks = Vec::new();
+    let mut old_index = 0;
+    let mut new_index = 0;
+    for (old_match, new_match) in matches
+        .into_iter()
+        .chain(Some((old.len(), new.len())))
+    {
+        if old_index < old_match || new_index < new_match {
+            let old_count = old_match - old_index;
+            let new_count = new_match - new_index;
+            hunks.push(Hunk {
+                old_start: prefix + old_index + (old_count > 0) as usize,
+                old_count,
+                new_start: prefix + new_index + (new_count > 0) as usize,
+                new_count,
+                lines: old[old_index..old_match]
+                    .iter()
+                    .map(|line| format!("-{}", line))
+                    .chain(new[new_index..new_match].iter().map(|line| format!("+{}", line)))
+                    .collect(),
+            });
+        }
+        old_index = old_match + 1;
+        new_index = new_match + 1;
+    }
+    hunks
+}
+
+const MAX_EDIT_COUNT: usize = 1000;
+
+// Finds the pairs of lines that stay the same with Myers' algorithm, in order. Returns `None` if
+// it takes more than `max_edit_count` insertions and deletions to get from `old` to `new`.
+fn find_matches(old: &[&str], new: &[&str], max_edit_count: usize) -> Option<Vec<(usize, usize)>> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max_edit_count = max_edit_count.min(old.len() + new.len()) as isize;
+    // `v[k + offset]` is the furthest x reached on diagonal k.
+    let offset = max_edit_count + 1;
+    let mut v = vec![0isize; 2 * max_edit_count as usize + 3];
+    let mut trace = Vec::new();
+    for d in 0..=max_edit_count {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, offset, n, m));
+            }
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], offset: isize, n: isize, m: isize) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    let mut x = n;
+    let mut y = m;
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            matches.push((x as usize, y as usize));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    matches.reverse();
+    matches
+}
+
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let ranges = line.strip_prefix("@@ ")?;
+    let ranges = &ranges[..ranges.find(" @@")?];
+    let (old, new) = ranges.split_once(' ')?;
+    let (old_start, old_count) = parse_range(old.strip_prefix('-')?)?;
+    let (new_start, new_count) = parse_range(new.strip_prefix('+')?)?;
+    Some(Hunk {
+        old_start,
+        old_count,
+        new_start,
+        new_count,
+        lines: Vec::new(),
+    })
+}
+
+// A count of 1 is left out of hunk headers.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn format_range(start: usize, count: usize) -> String {
+    if count == 1 {
+        format!("{}", start)
+    } else {
+        format!("{},{}", start, count)
+    }
+}
@@ -0,0 +1,168 @@
+use {
+    crate::diff::FileDiff,
+    makepad_shell::{shell_env_cap_split, shell_env_cap_split_stdin},
+    std::path::{Path, PathBuf},
+};
+
+/// A git working tree, operated on by running the `git` command line tool.
+///
+/// Paths passed to and returned from a `GitRepo` are relative to the top of the working tree,
+/// which is not necessarily the directory it was opened from. `repo_path` and `local_path`
+/// convert between the two.
+#[derive(Clone, Debug)]
+pub struct GitRepo {
+    root: PathBuf,
+    // The directory the repo was opened from, relative to `root`, with a trailing slash.
+    prefix: String,
+}
+
+/// The state of a changed file, as reported by `git status`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileStatus {
+    pub path: String,
+    /// For renames and copies, the path the file had before.
+    pub orig_path: Option<String>,
+    /// The status in the index, compared to HEAD, as one of git's status letters.
+    pub index: char,
+    /// The status in the working tree, compared to the index.
+    pub worktree: char,
+}
+
+impl FileStatus {
+    pub fn is_untracked(&self) -> bool {
+        self.index == '?'
+    }
+
+    pub fn is_staged(&self) -> bool {
+        !matches!(self.index, ' ' | '?' | '!')
+    }
+
+    pub fn is_changed(&self) -> bool {
+        !matches!(self.worktree, ' ' | '?' | '!')
+    }
+}
+
+impl GitRepo {
+    /// Opens the working tree that `dir` is in, if it is in one.
+    pub fn open(dir: &Path) -> Option<Self> {
+        let (stdout, _, success) =
+            shell_env_cap_split(&[], dir, "git", &["rev-parse", "--show-toplevel", "--show-prefix"]);
+        if !success {
+            return None;
+        }
+        let mut lines = stdout.lines();
+        let root = PathBuf::from(lines.next()?);
+        let prefix = lines.next().unwrap_or("").to_string();
+        Some(Self { root, prefix })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Converts a path relative to the directory the repo was opened from to one relative to
+    /// the top of the working tree.
+    pub fn repo_path(&self, local_path: &str) -> String {
+        format!("{}{}", self.prefix, local_path)
+    }
+
+    /// The inverse of `repo_path`. Returns `None` for files outside of the directory the repo
+    /// was opened from.
+    pub fn local_path<'a>(&self, repo_path: &'a str) -> Option<&'a str> {
+        repo_path.strip_prefix(self.prefix.as_str())
+    }
+
+    /// Returns the changed, staged and untracked files.
+    pub fn status(&self) -> Result<Vec<FileStatus>, String> {
+        let stdout = self.git(&["status", "--porcelain=v1", "-z", "--untracked-files=all"])?;
+        let mut entries = stdout.split('\0');
+        let mut statuses = Vec::new();
+        while let Some(entry) = entries.next() {
+            if entry.len() < 4 {
+                continue;
+            }
+            let mut chars = entry.chars();
+            let index = chars.next().unwrap();
+            let worktree = chars.next().unwrap();
+            // renames and copies are followed by the path they came from
+            let orig_path = if matches!(index, 'R' | 'C') {
+                entries.next().map(|path| path.to_string())
+            } else {
+                None
+            };
+            statuses.push(FileStatus {
+                path: entry[3..].to_string(),
+                orig_path,
+                index,
+                worktree,
+            });
+        }
+        Ok(statuses)
+    }
+
+    /// Returns the contents of a file in HEAD, or `None` if it isn't in there.
+    pub fn head_text(&self, path: &str) -> Option<String> {
+        self.git(&["show", &format!("HEAD:{}", path)]).ok()
+    }
+
+    /// Returns the diff of a file between the working tree and the index, or between the index
+    /// and HEAD if `staged` is set.
+    pub fn diff_text(&self, path: &str, staged: bool) -> Result<String, String> {
+        if staged {
+            self.git(&["diff", "--no-color", "--no-ext-diff", "--cached", "--", path])
+        } else {
+            self.git(&["diff", "--no-color", "--no-ext-diff", "--", path])
+        }
+    }
+
+    pub fn diff(&self, path: &str, staged: bool) -> Result<FileDiff, String> {
+        let diff = self.diff_text(path, staged)?;
+        Ok(FileDiff::parse(&diff).into_iter().next().unwrap_or_default())
+    }
+
+    pub fn stage_file(&self, path: &str) -> Result<(), String> {
+        self.git(&["add", "--", path]).map(|_| ())
+    }
+
+    pub fn unstage_file(&self, path: &str) -> Result<(), String> {
+        if self.has_head() {
+            self.git(&["reset", "-q", "HEAD", "--", path]).map(|_| ())
+        } else {
+            self.git(&["rm", "-q", "--cached", "--", path]).map(|_| ())
+        }
+    }
+
+    /// Stages a single hunk of the unstaged diff of a file.
+    pub fn stage_hunk(&self, diff: &FileDiff, index: usize) -> Result<(), String> {
+        self.git_stdin(&["apply", "--cached", "-"], &diff.hunk_patch(index))
+    }
+
+    /// Unstages a single hunk of the staged diff of a file.
+    pub fn unstage_hunk(&self, diff: &FileDiff, index: usize) -> Result<(), String> {
+        self.git_stdin(&["apply", "--cached", "--reverse", "-"], &diff.hunk_patch(index))
+    }
+
+    pub fn commit(&self, message: &str) -> Result<(), String> {
+        self.git(&["commit", "-q", "-m", message]).map(|_| ())
+    }
+
+    fn has_head(&self) -> bool {
+        self.git(&["rev-parse", "--verify", "-q", "HEAD"]).is_ok()
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, String> {
+        let (stdout, stderr, success) = shell_env_cap_split(&[], &self.root, "git", args);
+        if !success {
+            return Err(stderr);
+        }
+        Ok(stdout)
+    }
+
+    fn git_stdin(&self, args: &[&str], stdin: &str) -> Result<(), String> {
+        let (_, stderr, success) = shell_env_cap_split_stdin(&[], &self.root, "git", args, stdin);
+        if !success {
+            return Err(stderr);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod git_repo;
+#[cfg(not(target_arch = "wasm32"))]
+pub use git_repo::*;
+
+pub mod diff;
+pub use diff::*;
+
+pub use makepad_shell;
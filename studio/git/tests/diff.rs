@@ -0,0 +1,106 @@
+use makepad_git::{diff_lines, FileDiff, HunkKind};
+
+#[test]
+fn diff_lines_reports_added_modified_and_deleted_lines() {
+    let old = "a\nb\nc\nd\ne\n";
+    let new = "a\nB\nc\ne\nf\n";
+    let hunks = diff_lines(old, new);
+    let summary: Vec<_> = hunks
+        .iter()
+        .map(|hunk| (hunk.kind(), hunk.new_line_range()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (HunkKind::Modified, 1..2),
+            (HunkKind::Deleted, 3..3),
+            (HunkKind::Added, 4..5),
+        ]
+    );
+    assert_eq!(hunks[0].lines, ["-b", "+B"]);
+    assert_eq!(hunks[0].header(), "@@ -2 +2 @@");
+    assert_eq!(hunks[1].header(), "@@ -4 +3,0 @@");
+    assert_eq!(hunks[2].header(), "@@ -5,0 +5 @@");
+}
+
+#[test]
+fn diff_lines_of_equal_and_empty_texts() {
+    assert!(diff_lines("a\nb\n", "a\nb\n").is_empty());
+    let hunks = diff_lines("", "a\nb\n");
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(hunks[0].kind(), HunkKind::Added);
+    assert_eq!(hunks[0].new_line_range(), 0..2);
+    let hunks = diff_lines("a\nb\n", "");
+    assert_eq!(hunks[0].kind(), HunkKind::Deleted);
+    assert_eq!(hunks[0].new_line_range(), 0..0);
+}
+
+#[test]
+fn diff_lines_finds_the_fewest_changes() {
+    let old = "x\na\nb\nc\ny\n";
+    let new = "a\nb\nz\nc\n";
+    let hunks = diff_lines(old, new);
+    let removed: Vec<&str> = hunks
+        .iter()
+        .flat_map(|hunk| hunk.lines.iter())
+        .filter(|line| line.starts_with('-'))
+        .map(|line| line.as_str())
+        .collect();
+    let added: Vec<&str> = hunks
+        .iter()
+        .flat_map(|hunk| hunk.lines.iter())
+        .filter(|line| line.starts_with('+'))
+        .map(|line| line.as_str())
+        .collect();
+    assert_eq!(removed, ["-x", "-y"]);
+    assert_eq!(added, ["+z"]);
+}
+
+#[test]
+fn parse_and_rebuild_a_hunk_patch() {
+    let diff = "\
+diff --git a/file.txt b/file.txt
+index 1111111..2222222 100644
+--- a/file.txt
++++ b/file.txt
+@@ -1,3 +1,3 @@
+ one
+-two
++TWO
+ three
+@@ -10 +10,2 @@ fn context()
+ ten
++eleven
+\\ No newline at end of file
+diff --git a/other.txt b/other.txt
+new file mode 100644
+--- /dev/null
++++ b/other.txt
+@@ -0,0 +1 @@
++other
+";
+    let file_diffs = FileDiff::parse(diff);
+    assert_eq!(file_diffs.len(), 2);
+    let file_diff = &file_diffs[0];
+    assert_eq!(file_diff.header.len(), 4);
+    assert_eq!(file_diff.hunks.len(), 2);
+    let hunk = &file_diff.hunks[1];
+    assert_eq!((hunk.old_start, hunk.old_count, hunk.new_start, hunk.new_count), (10, 1, 10, 2));
+    assert_eq!(hunk.lines.len(), 3);
+    assert_eq!(
+        file_diff.hunk_patch(0),
+        "\
+diff --git a/file.txt b/file.txt
+index 1111111..2222222 100644
+--- a/file.txt
++++ b/file.txt
+@@ -1,3 +1,3 @@
+ one
+-two
++TWO
+ three
+"
+    );
+    assert_eq!(file_diffs[1].hunks[0].header(), "@@ -0,0 +1 @@");
+    assert_eq!(file_diffs[1].hunks[0].kind(), HunkKind::Added);
+}
@@ -0,0 +1,173 @@
+use {
+    makepad_git::{GitRepo, HunkKind},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+// A repository in a temporary directory, which is removed again when the test is done.
+struct TempRepo {
+    path: PathBuf,
+}
+
+impl TempRepo {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("makepad_git_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        let repo = Self { path };
+        repo.git(&["init", "-q"]);
+        repo.git(&["config", "user.name", "Test"]);
+        repo.git(&["config", "user.email", "test@example.com"]);
+        repo.git(&["config", "commit.gpgsign", "false"]);
+        repo
+    }
+
+    fn write(&self, path: &str, text: &str) {
+        let path = self.path.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git").args(args).current_dir(&self.path).output().unwrap();
+        assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn open(&self) -> GitRepo {
+        GitRepo::open(&self.path).unwrap()
+    }
+}
+
+impl Drop for TempRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn numbered_lines(count: usize) -> String {
+    (1..=count).map(|line| format!("line {}\n", line)).collect()
+}
+
+#[test]
+fn status_lists_staged_changed_and_untracked_files() {
+    let temp = TempRepo::new("status");
+    temp.write("a.txt", "a\n");
+    temp.write("b.txt", "b\n");
+    temp.git(&["add", "."]);
+    temp.git(&["commit", "-q", "-m", "initial"]);
+    temp.write("a.txt", "a changed\n");
+    temp.write("b.txt", "b changed\n");
+    temp.git(&["add", "b.txt"]);
+    temp.write("dir/c.txt", "c\n");
+
+    let mut status = temp.open().status().unwrap();
+    status.sort_by(|a, b| a.path.cmp(&b.path));
+    let paths: Vec<&str> = status.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, ["a.txt", "b.txt", "dir/c.txt"]);
+    assert!(status[0].is_changed() && !status[0].is_staged());
+    assert!(status[1].is_staged() && !status[1].is_changed());
+    assert!(status[2].is_untracked() && !status[2].is_staged());
+}
+
+#[test]
+fn status_reports_the_original_path_of_renames() {
+    let temp = TempRepo::new("rename");
+    temp.write("old.txt", "text\n");
+    temp.git(&["add", "."]);
+    temp.git(&["commit", "-q", "-m", "initial"]);
+    temp.git(&["mv", "old.txt", "new.txt"]);
+
+    let status = temp.open().status().unwrap();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].index, 'R');
+    assert_eq!(status[0].path, "new.txt");
+    assert_eq!(status[0].orig_path.as_deref(), Some("old.txt"));
+}
+
+#[test]
+fn stage_and_unstage_a_single_hunk() {
+    let temp = TempRepo::new("hunks");
+    temp.write("file.txt", &numbered_lines(20));
+    temp.git(&["add", "."]);
+    temp.git(&["commit", "-q", "-m", "initial"]);
+    temp.write(
+        "file.txt",
+        &numbered_lines(20)
+            .replace("line 2\n", "line two\n")
+            .replace("line 18\n", "line 18\nline 18.5\n"),
+    );
+    let repo = temp.open();
+
+    let diff = repo.diff("file.txt", false).unwrap();
+    assert_eq!(diff.hunks.len(), 2);
+    assert_eq!(diff.hunks[0].kind(), HunkKind::Modified);
+    assert_eq!(diff.hunks[1].kind(), HunkKind::Added);
+
+    repo.stage_hunk(&diff, 1).unwrap();
+    let staged = repo.diff("file.txt", true).unwrap();
+    assert_eq!(staged.hunks.len(), 1);
+    assert!(staged.hunks[0].lines.contains(&"+line 18.5".to_string()));
+    let unstaged = repo.diff("file.txt", false).unwrap();
+    assert_eq!(unstaged.hunks.len(), 1);
+    assert!(unstaged.hunks[0].lines.contains(&"+line two".to_string()));
+
+    repo.unstage_hunk(&staged, 0).unwrap();
+    assert!(repo.diff("file.txt", true).unwrap().hunks.is_empty());
+    assert_eq!(repo.diff("file.txt", false).unwrap().hunks.len(), 2);
+}
+
+#[test]
+fn stage_unstage_and_commit_files() {
+    let temp = TempRepo::new("commit");
+    temp.write("file.txt", "first\n");
+    let repo = temp.open();
+
+    // there is no HEAD to reset to before the first commit
+    repo.stage_file("file.txt").unwrap();
+    assert!(repo.status().unwrap()[0].is_staged());
+    repo.unstage_file("file.txt").unwrap();
+    assert!(repo.status().unwrap()[0].is_untracked());
+
+    repo.stage_file("file.txt").unwrap();
+    repo.commit("first").unwrap();
+    assert!(repo.status().unwrap().is_empty());
+    assert_eq!(repo.head_text("file.txt").as_deref(), Some("first\n"));
+    assert_eq!(temp.git(&["log", "--format=%s"]), "first\n");
+
+    temp.write("file.txt", "second\n");
+    repo.stage_file("file.txt").unwrap();
+    repo.unstage_file("file.txt").unwrap();
+    let status = repo.status().unwrap();
+    assert!(status[0].is_changed() && !status[0].is_staged());
+    assert_eq!(repo.head_text("missing.txt"), None);
+}
+
+#[test]
+fn paths_are_relative_to_the_directory_the_repo_was_opened_from() {
+    let temp = TempRepo::new("prefix");
+    temp.write("sub/file.txt", "text\n");
+    let repo = GitRepo::open(&temp.path.join("sub")).unwrap();
+    assert_eq!(
+        repo.root().canonicalize().unwrap(),
+        temp.path.canonicalize().unwrap()
+    );
+    assert_eq!(repo.repo_path("file.txt"), "sub/file.txt");
+    assert_eq!(repo.local_path("sub/file.txt"), Some("file.txt"));
+    assert_eq!(repo.local_path("other.txt"), None);
+}
+
+#[test]
+fn open_outside_of_a_repo_fails() {
+    let path = std::env::temp_dir().join(format!("makepad_git_none_{}", std::process::id()));
+    fs::create_dir_all(&path).unwrap();
+    // GIT_CEILING_DIRECTORIES can't be passed through GitRepo, so only check this when the
+    // temporary directory isn't inside a repository itself
+    if !Path::new(&std::env::temp_dir()).join(".git").exists() {
+        assert!(GitRepo::open(&path).is_none());
+    }
+    let _ = fs::remove_dir_all(&path);
+}
@@ -1,5 +1,7 @@
 use crate::{
     makepad_code_editor::code_editor::*,
+    makepad_code_editor::{EditorCommand, Keymap},
+    makepad_code_editor::command::EDITOR_COMMANDS,
     makepad_code_editor::selection::Affinity,
     makepad_code_editor::session::SelectionMode,
     makepad_code_editor::history::NewGroup,
@@ -12,10 +14,17 @@ use crate::{
     run_view::*,
     makepad_platform::studio::{JumpToFile,EditFile, PatchFile},
     log_list::*,
+    commands::{StudioCommand, STUDIO_COMMANDS, STUDIO_KEYMAP, USER_KEYMAP_PATH},
+    command_palette::{CommandPaletteWidgetRefExt, PaletteCommand},
+    terminal::TerminalWidgetRefExt,
     makepad_code_editor::text::{Position},
+    makepad_file_protocol::SearchOptions,
     ai_chat::ai_chat_manager::AiChatManager,
+    lsp::lsp_manager::{LspManager, LspAction},
+    git::git_manager::GitManager,
+    makepad_live_compiler::{format_live_design_in_rust, LiveFormatOptions},
     build_manager::{
-        build_protocol::BuildProcess,
+        build_protocol::{BuildProcess, LogItem, LogItemLocation},
         build_manager::{
             BuildManager,
             BuildManagerAction
@@ -37,6 +46,9 @@ live_design!{
 pub struct App {
     #[live] pub ui: WidgetRef,
     #[rust] pub data: AppData,
+    // The editor that had key focus when the command palette was opened, which runs the editor
+    // commands from the palette.
+    #[rust] command_palette_tab: Option<LiveId>,
 }
 
 impl LiveRegister for App{
@@ -45,12 +57,17 @@ impl LiveRegister for App{
         crate::makepad_code_editor::live_design(cx);
         crate::run_list::live_design(cx);
         crate::log_list::live_design(cx);
+        crate::search_list::live_design(cx);
+        crate::command_palette::live_design(cx);
         crate::profiler::live_design(cx);
+        crate::terminal::live_design(cx);
         crate::run_view::live_design(cx);
         crate::studio_editor::live_design(cx);
         crate::studio_file_tree::live_design(cx);
         crate::app_ui::live_design(cx);
         crate::ai_chat::ai_chat_view::live_design(cx);
+        crate::git::git_panel::live_design(cx);
+        crate::git::git_diff_view::live_design(cx);
         // for macos
         cx.start_stdin_service();
     }
@@ -112,6 +129,152 @@ impl App {
         }
     }
     
+    // Starts a search across all files with what is in the search panel.
+    fn start_search(&mut self, cx:&mut Cx){
+        let globs = |id:&[LiveId]|->Vec<String>{
+            self.ui.text_input(id).text().split(',').map(|glob| glob.trim().to_string()).filter(|glob| !glob.is_empty()).collect()
+        };
+        let options = SearchOptions{
+            case_sensitive: self.ui.check_box(id!(search_case)).selected(cx),
+            whole_word: self.ui.check_box(id!(search_whole_word)).selected(cx),
+            regex: self.ui.check_box(id!(search_regex)).selected(cx),
+            include: globs(id!(search_include)),
+            exclude: globs(id!(search_exclude)),
+        };
+        let pattern = self.ui.text_input(id!(search_input)).text();
+        self.data.file_system.request_search(cx, &pattern, options);
+    }
+    
+    fn handle_code_editor_action(&mut self, tab_id: LiveId, action: CodeEditorAction){
+        match action{
+            CodeEditorAction::TextDidChange => {
+                // lets write the file
+                self.data.file_system.request_save_file_for_tab_id(tab_id, false)
+            }
+            CodeEditorAction::Hover(pos)=>{
+                self.data.lsp_manager.hover(&self.data.file_system, tab_id, pos)
+            }
+            CodeEditorAction::GoToDefinition(pos)=>{
+                self.data.lsp_manager.go_to_definition(&self.data.file_system, tab_id, pos)
+            }
+            CodeEditorAction::RequestCompletion(pos)=>{
+                self.data.lsp_manager.complete(&self.data.file_system, tab_id, pos)
+            }
+            CodeEditorAction::SearchDidChange | CodeEditorAction::None=>{}
+        }
+    }
+    
+    // Returns the tab of the code editor that has key focus, if any.
+    fn focused_code_tab(&self, cx: &Cx)->Option<LiveId>{
+        let dock = self.ui.dock(id!(dock));
+        self.data.file_system.tab_id_to_session.iter().find_map(|(tab_id, session)|{
+            let EditSession::Code(_) = session else {return None};
+            let editor = dock.item(*tab_id).studio_code_editor(id!(editor));
+            let has_key_focus = editor.borrow().is_some_and(|editor| editor.editor.has_key_focus(cx));
+            has_key_focus.then_some(*tab_id)
+        })
+    }
+    
+    fn open_command_palette(&mut self, cx: &mut Cx){
+        self.command_palette_tab = self.focused_code_tab(cx);
+        let keymap = cx.global::<Keymap>();
+        let mut commands: Vec<_> = STUDIO_COMMANDS.iter().map(|(_, info)| *info).collect();
+        if self.command_palette_tab.is_some(){
+            commands.extend(EDITOR_COMMANDS.iter().map(|(_, info)| *info));
+        }
+        let commands = commands.into_iter()
+            .filter(|info| StudioCommand::from_id(info.id) != Some(StudioCommand::CommandPalette))
+            .map(|info| PaletteCommand{
+                id: info.id.to_string(),
+                title: info.title.to_string(),
+                keys: keymap.sequence_for_command(info.id),
+            })
+            .collect();
+        self.ui.modal(id!(command_palette_modal)).open(cx);
+        self.ui.command_palette(id!(command_palette)).open(cx, commands);
+    }
+    
+    // Closes the command palette, and gives key focus back to the editor that had it before.
+    fn close_command_palette(&mut self, cx: &mut Cx)->Option<LiveId>{
+        self.ui.modal(id!(command_palette_modal)).close(cx);
+        let tab_id = self.command_palette_tab.take()?;
+        if let Some(mut editor) = self.ui.dock(id!(dock)).item(tab_id).studio_code_editor(id!(editor)).borrow_mut() {
+            editor.editor.set_key_focus(cx);
+        }
+        Some(tab_id)
+    }
+    
+    // Runs a command from the command palette. Editor commands run in the editor of `tab_id`.
+    fn run_command(&mut self, cx: &mut Cx, id: &str, tab_id: Option<LiveId>){
+        if let Some(command) = StudioCommand::from_id(id){
+            self.run_studio_command(cx, command, tab_id);
+        }
+        else if let Some(command) = EditorCommand::from_id(id){
+            let Some(tab_id) = tab_id else {return};
+            let dock = self.ui.dock(id!(dock));
+            let mut actions = Vec::new();
+            if let Some(mut editor) = dock.item(tab_id).studio_code_editor(id!(editor)).borrow_mut() {
+                if let Some(EditSession::Code(session)) = self.data.file_system.get_session_mut(tab_id) {
+                    actions = editor.editor.run_command(cx, session, command);
+                }
+            }
+            self.data.file_system.handle_sessions();
+            for action in actions{
+                self.handle_code_editor_action(tab_id, action);
+            }
+        }
+    }
+    
+    // Runs a studio command. Commands on a document, such as formatting, run on the one in `tab_id`.
+    fn run_studio_command(&mut self, cx: &mut Cx, command: StudioCommand, tab_id: Option<LiveId>){
+        match command{
+            StudioCommand::CommandPalette=>self.open_command_palette(cx),
+            StudioCommand::Recompile=>{
+                cx.action(AppAction::ClearLog);
+                cx.action(AppAction::RecompileStarted);
+                cx.action(AppAction::StartRecompile);
+            }
+            StudioCommand::ClearLog=>cx.action(AppAction::ClearLog),
+            StudioCommand::ReloadFileTree=>cx.action(AppAction::ReloadFileTree),
+            StudioCommand::GitRefresh=>self.data.git_manager.refresh(cx),
+            StudioCommand::FormatLiveDesign=>if let Some(tab_id) = tab_id{
+                self.format_live_design(cx, tab_id)
+            }
+        }
+    }
+    
+    // Formats the live_design! blocks in the document of `tab_id`. If that fails, the log says
+    // where, and the document is left as it is.
+    fn format_live_design(&mut self, cx: &mut Cx, tab_id: LiveId){
+        let file_system = &mut self.data.file_system;
+        let Some(file_id) = file_system.tab_id_to_file_node_id.get(&tab_id).copied() else {return};
+        let Some(text) = file_system.file_id_as_string(file_id) else {return};
+        match format_live_design_in_rust(&text, &LiveFormatOptions::default()){
+            Ok(formatted)=>if formatted != text{
+                file_system.replace_code_document(file_id, &formatted);
+                file_system.request_save_file_for_file_node_id(file_id, false);
+                file_system.handle_sessions();
+                cx.action(AppAction::RedrawFile(file_id));
+            }
+            Err(error)=>{
+                let Some(path) = file_system.file_node_id_to_path(file_id) else {return};
+                let pos = Position{
+                    line_index: error.pos.line as usize,
+                    byte_index: error.pos.column as usize
+                };
+                self.data.build_manager.log.push((LiveId(0), LogItem::Location(LogItemLocation{
+                    level: LogLevel::Error,
+                    file_name: path.to_string(),
+                    start: pos,
+                    end: pos,
+                    message: format!("Cannot format live_design!: {}", error.message),
+                    explanation: None
+                })));
+                cx.action(AppAction::RedrawLog);
+            }
+        }
+    }
+    
     fn save_state(&self, slot:usize){
         let dock = self.ui.dock(id!(dock));
         let dock_items = dock.clone_state().unwrap();
@@ -140,6 +303,8 @@ pub struct AppData{
     pub build_manager: BuildManager,
     pub file_system: FileSystem,
     pub ai_chat_manager: AiChatManager,
+    pub lsp_manager: LspManager,
+    pub git_manager: GitManager,
 }
 
 // all global app commands coming in from keybindings, and UI components
@@ -150,6 +315,9 @@ pub enum AppAction{
     RedrawLog,
     RedrawProfiler,
     RedrawFile(LiveId),
+    RedrawGit,
+    ShowGitDiff,
+    GitCommitted,
     FocusDesign(LiveId),
     EditFile(EditFile),
     PatchFile(PatchFile),
@@ -163,6 +331,8 @@ pub enum AppAction{
     RedrawAiChat{chat_id:LiveId},
     RunAiChat{chat_id:LiveId, history_slot:usize, item_id:usize},
     DestroyRunViews{run_view_id:LiveId},
+    RunCommand(String),
+    CloseCommandPalette,
     None
 }
 
@@ -179,7 +349,18 @@ impl MatchEvent for App{
                 
         self.data.file_system.init(cx, &root_path);
         self.data.build_manager.init(cx, &root_path);
+        self.data.lsp_manager.init(cx, &root_path);
+        self.data.git_manager.init(cx, &root_path);
         
+        let keymap = cx.global::<Keymap>();
+        keymap.load_toml(STUDIO_KEYMAP).unwrap();
+        if let Ok(toml) = std::fs::read_to_string(USER_KEYMAP_PATH){
+            if let Err(errors) = keymap.load_toml(&toml){
+                for error in errors{
+                    error!("{}: {}", USER_KEYMAP_PATH, error);
+                }
+            }
+        }
                 
         //self.data.build_manager.discover_external_ip(cx);
         self.data.build_manager.start_http_server();
@@ -211,6 +392,7 @@ impl MatchEvent for App{
                         // lets open the editor
                         let tab_id = dock.unique_tab_id(file_id.0);
                         self.data.file_system.request_open_file(tab_id, file_id);
+                        self.data.file_system.tab_id_to_pending_jump.insert(tab_id, pos);
                         // lets add a file tab 'somewhere'
                         let (tab_bar, pos) = dock.find_tab_bar_of_tab(live_id!(edit_first)).unwrap();
                         let template = FileSystem::get_editor_template_from_path(&jt.file_name);
@@ -284,6 +466,22 @@ impl MatchEvent for App{
             AppAction::RedrawFile(file_id)=>{
                 self.data.file_system.redraw_view_by_file_id(cx, file_id, &dock);
             }
+            AppAction::RedrawGit=>{
+                self.ui.widget(id!(git_panel)).redraw(cx);
+                dock.item(live_id!(git_diff)).redraw(cx);
+            }
+            AppAction::GitCommitted=>{
+                self.ui.text_input(id!(git_commit_message)).set_text_and_redraw(cx, "");
+            }
+            AppAction::ShowGitDiff=>{
+                if let Some(selected) = &self.data.git_manager.selected{
+                    let name = selected.path.rsplit('/').next().unwrap_or("").to_string();
+                    let (tab_bar, pos) = dock.find_tab_bar_of_tab(live_id!(edit_first)).unwrap();
+                    dock.create_and_select_tab(cx, tab_bar, live_id!(git_diff), live_id!(GitDiff), "".to_string(), live_id!(CloseableTab), Some(pos));
+                    dock.set_tab_title(cx, live_id!(git_diff), format!("Diff {}", name));
+                    dock.item(live_id!(git_diff)).redraw(cx);
+                }
+            }
             AppAction::ClearLog=>{
                 self.data.build_manager.clear_log(cx, &dock, &mut self.data.file_system);
                 log_list.reset_scroll(cx);
@@ -347,6 +545,13 @@ impl MatchEvent for App{
             AppAction::RunAiChat{chat_id, history_slot, item_id}=>{
                 self.data.ai_chat_manager.run_ai_chat(cx, chat_id, history_slot, item_id, &mut self.data.file_system);
             }
+            AppAction::RunCommand(id)=>{
+                let tab_id = self.close_command_palette(cx);
+                self.run_command(cx, &id, tab_id);
+            }
+            AppAction::CloseCommandPalette=>{
+                self.close_command_palette(cx);
+            }
             AppAction::DestroyRunViews{run_view_id} => {
                 dock.close_tab(cx, run_view_id);
                 dock.close_tab(cx, run_view_id.add(1));
@@ -439,17 +644,33 @@ impl MatchEvent for App{
             }
             FileSystemAction::FileChangedOnDisk(_res)=>{
                 
+            }
+            FileSystemAction::SearchResultsChanged=>{
+                self.ui.widget(id!(search_list)).redraw(cx);
             }
             FileSystemAction::None=>()
         }
         
+        match action.cast(){
+            LspAction::ShowHover{tab_id, position, text}=>{
+                if let Some(mut editor) = dock.item(tab_id).studio_code_editor(id!(editor)).borrow_mut() {
+                    editor.editor.show_hover(cx, position, &text);
+                }
+            }
+            LspAction::ShowCompletions{tab_id, position, items}=>{
+                if let Some(mut editor) = dock.item(tab_id).studio_code_editor(id!(editor)).borrow_mut() {
+                    if let Some(EditSession::Code(session)) = self.data.file_system.get_session_mut(tab_id) {
+                        editor.editor.show_completions(cx, session, position, items);
+                    }
+                }
+            }
+            LspAction::None=>()
+        }
+        
         if let Some(action) = action.as_widget_action(){
             match action.cast(){
-                CodeEditorAction::TextDidChange => {
-                    // lets write the file
-                    self.data.file_system.request_save_file_for_tab_id(action.path.from_end(1), false)
-                }
                 CodeEditorAction::None=>{}
+                code_editor_action=>self.handle_code_editor_action(action.path.from_end(1), code_editor_action)
             }
             
             match action.cast(){
@@ -507,23 +728,15 @@ impl MatchEvent for App{
     }        
         
     fn handle_key_down(&mut self, cx: &mut Cx, event: &KeyEvent){
-        let KeyEvent {
-            key_code,
-            modifiers: KeyModifiers {logo, control, ..},
-            ..
-        } = event;
-        if *control || *logo {
-            if let KeyCode::Backtick = key_code {
-                cx.action(AppAction::ClearLog);
-                cx.action(AppAction::RecompileStarted);
-                cx.action(AppAction::StartRecompile);
-            }
-            else if let KeyCode::KeyK = key_code {
-                cx.action(AppAction::ClearLog);
-            }
-            else if let KeyCode::KeyR = key_code{
-                cx.action(AppAction::ReloadFileTree);
+        // Editor commands are run by the editor that has key focus.
+        if let Some(command) = cx.global::<Keymap>().resolve(event).and_then(|id| StudioCommand::from_id(&id)){
+            // The shell in the terminal gets keys such as Ctrl+R, so only the command palette is
+            // shown while the terminal has key focus.
+            if command != StudioCommand::CommandPalette && self.ui.terminal(id!(terminal)).has_key_focus(cx){
+                return
             }
+            let tab_id = self.focused_code_tab(cx);
+            self.run_studio_command(cx, command, tab_id);
         }
     }
     
@@ -553,6 +766,23 @@ impl MatchEvent for App{
             }
         }
             
+        if self.ui.button(id!(git_commit)).clicked(actions){
+            let message = self.ui.text_input(id!(git_commit_message)).text();
+            self.data.git_manager.commit(cx, &message);
+        }
+        if self.ui.button(id!(git_refresh)).clicked(actions){
+            self.data.git_manager.refresh(cx);
+        }
+        
+        if [*id!(search_input), *id!(search_include), *id!(search_exclude)].iter().any(|id| self.ui.text_input(id).changed(actions).is_some())
+            || [*id!(search_case), *id!(search_whole_word), *id!(search_regex)].iter().any(|id| self.ui.check_box(id).changed(actions).is_some()){
+            self.start_search(cx);
+        }
+        if self.ui.button(id!(replace_all)).clicked(actions){
+            let replacement = self.ui.text_input(id!(replace_input)).text();
+            self.data.file_system.request_replace_in_files(&replacement);
+        }
+            
         if let Some(file_id) = file_tree.file_clicked(&actions) {
             println!("FILE CLICKED");
             // ok lets open the file
@@ -591,6 +821,8 @@ impl AppMain for App {
         self.data.file_system.handle_event(cx, event, &self.ui);
         self.data.build_manager.handle_event(cx, event, &mut self.data.file_system); 
         self.data.ai_chat_manager.handle_event(cx, event, &mut self.data.file_system);
+        self.data.lsp_manager.handle_event(cx, event, &mut self.data.file_system);
+        self.data.git_manager.handle_event(cx, event, &mut self.data.file_system);
         if self.ui.dock(id!(dock)).check_and_clear_need_save(){
             self.save_state(0);
         }
@@ -9,8 +9,13 @@ live_design!{
     import makepad_studio::studio_file_tree::StudioFileTree;
     import makepad_studio::run_view::RunView;
     import makepad_studio::log_list::LogList;
+    import makepad_studio::search_list::SearchList;
+    import makepad_studio::command_palette::CommandPalette;
     import makepad_studio::run_list::RunList;
     import makepad_studio::profiler::Profiler;
+    import makepad_studio::terminal::Terminal;
+    import makepad_studio::git::git_panel::GitPanel;
+    import makepad_studio::git::git_diff_view::GitDiffView;
 
     ICO_SEARCH = dep("crate://self/resources/icons/Icon_Search.svg")
 
@@ -273,6 +278,17 @@ live_design!{
                         svg_file: dep("crate://self/resources/icons/icon_profiler.svg"),
                     }
                 }
+                GitTab = <IconTab> {
+                    spacing: (THEME_SPACE_2)
+                    icon_walk: {
+                        width: 10.,
+                        margin: { top: 4. }
+                    }
+                    draw_icon: {
+                        color: (STUDIO_PALETTE_6)
+                        svg_file: dep("crate://self/resources/icons/icon_git.svg"),
+                    }
+                }
                 SearchFirstTab = <IconTab> {
                     spacing: (THEME_SPACE_2)
                     icon_walk: {
@@ -326,7 +342,7 @@ live_design!{
             }*/
 
             file_tree_tabs = Tabs {
-                tabs: [file_tree_tab, run_list_tab, search],
+                tabs: [file_tree_tab, run_list_tab, search, git_tab],
                 selected: 0
             }
 
@@ -346,7 +362,7 @@ live_design!{
             }
             
             log_tabs = Tabs {
-                tabs: [log_list_tab, profiler],
+                tabs: [log_list_tab, profiler, terminal_tab],
                 selected: 0
             }
 
@@ -400,6 +416,12 @@ live_design!{
                 kind: OutlineFirst
             }
 
+            git_tab = Tab {
+                name: "Git"
+                template: GitTab,
+                kind: Git
+            }
+
             run_list_tab = Tab {
                 name: "Run"
                 template: RunListTab,
@@ -418,6 +440,12 @@ live_design!{
                 kind: Profiler
             }
 
+            terminal_tab = Tab {
+                name: "Terminal",
+                template: LogTab,
+                kind: Terminal
+            }
+
             CodeEditor = <View> {
                 flow: Down,
                 <DockToolbar> {
@@ -528,18 +556,38 @@ live_design!{
                 }
                 <RunList> {}
             }
+            Git = <View> {
+                flow: Down,
+                <DockToolbar> {
+                    content = {
+                        spacing: (THEME_SPACE_2)
+                        align: { y: 0.5 }
+                        git_commit_message = <TextInput> {
+                            width: Fill,
+                            empty_message: "Commit message",
+                        }
+                        git_commit = <ButtonFlat> { width: Fit, text: "Commit" }
+                        git_refresh = <ButtonFlat> { width: Fit, text: "Refresh" }
+                    }
+                }
+                git_panel = <GitPanel> {}
+            }
+            GitDiff = <View> {
+                flow: Down,
+                git_diff_view = <GitDiffView> {}
+            }
             Search = <RectView> {
             flow: Down,
                 <DockToolbar> {
                     content = {
                         spacing: (THEME_SPACE_2)
                         align: { y: 0.5 }
-                        <TextInput> {
+                        search_input = <TextInput> {
                             width: Fill,
                             empty_message: "Search",
                         }
 
-                        <CheckBoxCustom> {
+                        search_case = <CheckBoxCustom> {
                             padding: 0.
                             text: ""
                             draw_check: { check_type: None }
@@ -550,7 +598,7 @@ live_design!{
                                 svg_file: dep("crate://self/resources/icons/icon_search_case_sensitive.svg"),
                             }
                         }
-                        <CheckBoxCustom> {
+                        search_whole_word = <CheckBoxCustom> {
                             padding: 0.
                             text:""
                             draw_check: { check_type: None }
@@ -561,7 +609,7 @@ live_design!{
                                 svg_file: dep("crate://self/resources/icons/icon_search_full_word.svg"),
                             }
                         }
-                        <CheckBoxCustom> {
+                        search_regex = <CheckBoxCustom> {
                             padding: 0.
                             text:""
                             draw_check: { check_type: None }
@@ -574,11 +622,32 @@ live_design!{
                         }
                     }
                 }
-                <View> {
-                    flow: Down
-                    margin: <THEME_MSPACE_2> {}
-                    <P> { text: "this does not work yet." }
+                <DockToolbar> {
+                    content = {
+                        spacing: (THEME_SPACE_2)
+                        align: { y: 0.5 }
+                        replace_input = <TextInput> {
+                            width: Fill,
+                            empty_message: "Replace",
+                        }
+                        replace_all = <ButtonFlat> { width: Fit, text: "Replace All" }
+                    }
+                }
+                <DockToolbar> {
+                    content = {
+                        spacing: (THEME_SPACE_2)
+                        align: { y: 0.5 }
+                        search_include = <TextInput> {
+                            width: Fill,
+                            empty_message: "Files to include",
+                        }
+                        search_exclude = <TextInput> {
+                            width: Fill,
+                            empty_message: "Files to exclude",
+                        }
+                    }
                 }
+                search_list = <SearchList> {}
             }
             RunView = <RunView> {}
             StudioFileTree = <View> {
@@ -746,6 +815,17 @@ live_design!{
                 }
                 <Profiler> {}
             }
+            Terminal = <View> {
+                flow: Down,
+                terminal = <Terminal> {}
+            }
+        }
+        command_palette_modal = <Modal> {
+            align: { x: 0.5, y: 0.15 }
+            content: {
+                width: Fit, height: Fit,
+                command_palette = <CommandPalette> {}
+            }
         }}
     }
-}
\ No newline at end of file
+}
@@ -28,6 +28,7 @@ use {
         fs::File,
         io::prelude::*,
         net::{SocketAddr, UdpSocket},
+        ops::Range,
         path::Path,
         path::PathBuf,
         sync::mpsc,
@@ -38,6 +39,8 @@ use {
 };
 
 pub const MAX_SWAPCHAIN_HISTORY: usize = 4;
+/// Decorations for the live_design lint warnings a running app sends, replaced as a set
+pub const LIVE_LINT_DECORATION_ID: usize = 2;
 pub struct ActiveBuild {
     pub log_index: String,
     pub process: BuildProcess,
@@ -121,6 +124,8 @@ pub struct BuildManager {
     http_port: usize,
     pub clients: Vec<BuildClient>,
     pub log: Vec<(LiveId, LogItem)>,
+    // where the live lint warnings of each build are in the log, they are replaced on every pass
+    live_lint_log: HashMap<LiveId, Range<usize>>,
     pub profile: HashMap<LiveId, ProfileSampleStore>,
     recompile_timeout: f64,
     recompile_timer: Timer,
@@ -218,6 +223,10 @@ fn get_local_ip() -> String {
 }
 
 impl BuildManager {
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
     pub fn init(&mut self, cx: &mut Cx, path: &Path) {
         self.http_port = if std::option_env!("MAKEPAD_STUDIO_HTTP").is_some() {
             8002
@@ -282,6 +291,7 @@ impl BuildManager {
         }
         if len != self.active.builds.len() {
             self.log.clear();
+            self.live_lint_log.clear();
             true
         } else {
             false
@@ -316,6 +326,7 @@ impl BuildManager {
         file_system.clear_all_decorations();
         file_system.redraw_all_views(cx, dock);
         self.log.clear();
+        self.live_lint_log.clear();
         self.profile.clear();
     }
 
@@ -419,6 +430,7 @@ impl BuildManager {
 
         if let Event::Signal = event {
             let log = &mut self.log;
+            let live_lint_log = &mut self.live_lint_log;
             let active = &mut self.active;
 
             if let Ok(mut addr) = self.recv_external_ip.try_recv() {
@@ -472,6 +484,50 @@ impl BuildManager {
                             ));
                             cx.action(AppAction::RedrawLog)
                         }
+                        AppToStudio::LiveLint(items) => {
+                            for file_id in file_system.clear_all_decorations_with_id(LIVE_LINT_DECORATION_ID) {
+                                cx.action(AppAction::RedrawFile(file_id))
+                            }
+                            if let Some(old) = live_lint_log.remove(&build_id) {
+                                log.drain(old.clone());
+                                for range in live_lint_log.values_mut() {
+                                    if range.start >= old.end {
+                                        *range = range.start - old.len()..range.end - old.len();
+                                    }
+                                }
+                            }
+                            let log_start = log.len();
+                            for item in items {
+                                let start = text::Position {
+                                    line_index: item.line_start as usize,
+                                    byte_index: item.column_start as usize,
+                                };
+                                let end = text::Position {
+                                    line_index: item.line_end as usize,
+                                    byte_index: item.column_end as usize,
+                                };
+                                if let Some(file_id) = file_system.path_to_file_node_id(&item.file_name) {
+                                    file_system.add_decoration(
+                                        file_id,
+                                        Decoration::new(LIVE_LINT_DECORATION_ID, start, end, DecorationType::Warning),
+                                    );
+                                    cx.action(AppAction::RedrawFile(file_id))
+                                }
+                                log.push((
+                                    build_id,
+                                    LogItem::Location(LogItemLocation {
+                                        level: item.level,
+                                        file_name: item.file_name,
+                                        start,
+                                        end,
+                                        message: item.message,
+                                        explanation: item.explanation
+                                    }),
+                                ));
+                            }
+                            live_lint_log.insert(build_id, log_start..log.len());
+                            cx.action(AppAction::RedrawLog)
+                        }
                         AppToStudio::EventSample(sample) => {
                             // ok lets push this profile sample into the profiles
                             let values = self.profile.entry(build_id).or_default();
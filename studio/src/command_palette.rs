@@ -0,0 +1,222 @@
+use {
+    crate::{
+        app::AppAction,
+        makepad_widgets::*,
+    },
+};
+
+live_design!{
+    import makepad_draw::shader::std::*;
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    CommandPalette = {{CommandPalette}}{
+        width: 500, height: Fit,
+        flow: Down,
+        padding: (THEME_SPACE_2)
+        spacing: (THEME_SPACE_2)
+        show_bg: true,
+        draw_bg: { color: (THEME_COLOR_BG_CONTAINER) }
+
+        query = <TextInput> {
+            width: Fill,
+            empty_message: "Type a command"
+        }
+
+        list = <PortalList> {
+            capture_overload: false,
+            grab_key_focus: false
+            drag_scrolling: false
+            height: 350, width: Fill,
+            flow: Down
+
+            Command = <View> {
+                width: Fill, height: Fit,
+                flow: Right,
+                align: { y: 0.5 }
+                padding: { left: (THEME_SPACE_2), right: (THEME_SPACE_2), top: (THEME_SPACE_1), bottom: (THEME_SPACE_1) }
+                cursor: Hand
+                show_bg: true,
+                draw_bg: {
+                    instance is_selected: 0.0
+                    fn pixel(self) -> vec4 {
+                        return mix(
+                            THEME_COLOR_BG_CONTAINER,
+                            THEME_COLOR_CTRL_SELECTED,
+                            self.is_selected
+                        );
+                    }
+                }
+                title = <P> { width: Fill, margin: 0., padding: 0. }
+                keys = <P> {
+                    width: Fit, margin: 0., padding: 0.
+                    draw_text: { color: (THEME_COLOR_TEXT_META) }
+                }
+            }
+        }
+    }
+}
+
+/// A command as the palette shows it.
+#[derive(Clone, Debug)]
+pub struct PaletteCommand {
+    pub id: String,
+    pub title: String,
+    /// The keys the command is bound to, if any.
+    pub keys: Option<String>,
+}
+
+/// Lists commands that fuzzy match what is typed, and runs the selected one with
+/// `AppAction::RunCommand`.
+#[derive(Live, LiveHook, Widget)]
+pub struct CommandPalette{
+    #[deref] view: View,
+    #[rust] commands: Vec<PaletteCommand>,
+    // The indices of the commands that match the query, best match first.
+    #[rust] matches: Vec<usize>,
+    #[rust] selected: usize,
+}
+
+impl CommandPalette{
+    pub fn open(&mut self, cx: &mut Cx, commands: Vec<PaletteCommand>){
+        self.commands = commands;
+        let query = self.view.text_input(id!(query));
+        query.set_text("");
+        query.set_key_focus(cx);
+        self.update_matches("");
+        self.view.redraw(cx);
+    }
+
+    fn update_matches(&mut self, query: &str){
+        let mut scored: Vec<_> = self.commands.iter().enumerate().filter_map(|(index, command)|{
+            let score = fuzzy_score(query, &command.title).max(fuzzy_score(query, &command.id))?;
+            Some((index, score))
+        }).collect();
+        // The sort is stable, so commands that score the same stay in the order they were given.
+        scored.sort_by(|(_, a), (_, b)| b.cmp(a));
+        self.matches = scored.into_iter().map(|(index, _)| index).collect();
+        self.selected = 0;
+        self.view.portal_list(id!(list)).set_first_id(0);
+    }
+
+    fn move_selection(&mut self, cx: &mut Cx, down: bool){
+        if self.matches.is_empty(){
+            return
+        }
+        self.selected = if down{
+            (self.selected + 1) % self.matches.len()
+        }
+        else{
+            self.selected.checked_sub(1).unwrap_or(self.matches.len() - 1)
+        };
+        let list = self.view.portal_list(id!(list));
+        let first_id = list.first_id();
+        let visible_items = list.visible_items().max(2);
+        if self.selected < first_id{
+            list.set_first_id(self.selected);
+        }
+        else if self.selected + 2 > first_id + visible_items{
+            list.set_first_id(self.selected + 2 - visible_items);
+        }
+        self.view.redraw(cx);
+    }
+
+    fn run_command(&self, cx: &mut Cx, index: usize){
+        if let Some(command) = self.matches.get(index).and_then(|index| self.commands.get(*index)){
+            cx.action(AppAction::RunCommand(command.id.clone()));
+        }
+    }
+}
+
+impl Widget for CommandPalette {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope:&mut Scope, walk:Walk)->DrawStep{
+        while let Some(step) = self.view.draw_walk(cx, scope, walk).step(){
+            if let Some(mut list) = step.as_portal_list().borrow_mut(){
+                list.set_item_range(cx, 0, self.matches.len());
+                while let Some(item_id) = list.next_visible_item(cx) {
+                    let Some(command) = self.matches.get(item_id).map(|index| &self.commands[*index]) else {
+                        continue
+                    };
+                    let item = list.item(cx, item_id, live_id!(Command)).as_view();
+                    item.apply_over(cx, live!{
+                        draw_bg: {is_selected: (if item_id == self.selected {1.0} else {0.0})}
+                        title = {text: (&command.title)}
+                        keys = {text: (command.keys.as_deref().unwrap_or(""))}
+                    });
+                    item.draw_all(cx, &mut Scope::empty());
+                }
+            }
+        }
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope){
+        // The query keeps key focus, so the selection moves with the arrow keys before the query
+        // gets to use them.
+        if let Event::KeyDown(KeyEvent{key_code: key_code @ (KeyCode::ArrowUp | KeyCode::ArrowDown), ..}) = event{
+            self.move_selection(cx, *key_code == KeyCode::ArrowDown);
+            return
+        }
+        self.view.handle_event(cx, event, scope);
+        if let Event::Actions(actions) = event{
+            let query = self.view.text_input(id!(query));
+            if let Some(text) = query.changed(actions){
+                self.update_matches(&text);
+                self.view.redraw(cx);
+            }
+            if query.returned(actions).is_some(){
+                self.run_command(cx, self.selected);
+            }
+            if query.escape(actions){
+                cx.action(AppAction::CloseCommandPalette);
+            }
+            let list = self.view.portal_list(id!(list));
+            for (item_id, item) in list.items_with_actions(actions){
+                if item.as_view().finger_up(actions).is_some_and(|fe| fe.is_over){
+                    self.run_command(cx, item_id);
+                }
+            }
+        }
+    }
+}
+
+impl CommandPaletteRef{
+    pub fn open(&self, cx: &mut Cx, commands: Vec<PaletteCommand>){
+        if let Some(mut inner) = self.borrow_mut(){
+            inner.open(cx, commands);
+        }
+    }
+}
+
+/// Scores how well `query` matches `text`, ignoring case. Every character of the query has to
+/// appear in the text in the same order, and characters that start a word or follow the previous
+/// match score higher. Returns `None` if the query does not match.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut text_chars = text.char_indices();
+    let mut prev_char: Option<char> = None;
+    let mut prev_match_end = None;
+    for query_char in query.chars().filter(|c| !c.is_whitespace()) {
+        loop {
+            let (index, char) = text_chars.next()?;
+            let is_word_start = match prev_char {
+                None => true,
+                Some(prev_char) => !prev_char.is_alphanumeric() || prev_char.is_lowercase() && char.is_uppercase(),
+            };
+            prev_char = Some(char);
+            if !char.to_lowercase().eq(query_char.to_lowercase()) {
+                continue
+            }
+            score += 1;
+            if is_word_start {
+                score += 3;
+            }
+            if prev_match_end == Some(index) {
+                score += 2;
+            }
+            prev_match_end = Some(index + char.len_utf8());
+            break
+        }
+    }
+    Some(score)
+}
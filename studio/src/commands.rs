@@ -0,0 +1,48 @@
+use crate::makepad_code_editor::command::CommandInfo;
+
+/// The bindings of the studio commands, which go on top of the bindings of the code editor.
+pub const STUDIO_KEYMAP: &str = r#"
+[keys]
+"ctrl+shift+p" = "studio.command_palette"
+"cmd+shift+p" = "studio.command_palette"
+"ctrl+`" = "studio.recompile"
+"cmd+`" = "studio.recompile"
+"ctrl+k" = "studio.clear_log"
+"cmd+k" = "studio.clear_log"
+"ctrl+r" = "studio.reload_file_tree"
+"cmd+r" = "studio.reload_file_tree"
+"alt+shift+f" = "studio.format_live_design"
+"#;
+
+/// The file in the working directory with the keymap of the user, in the same format as
+/// `STUDIO_KEYMAP`.
+pub const USER_KEYMAP_PATH: &str = "makepad_keymap.toml";
+
+/// A command that runs in the studio itself rather than in an editor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StudioCommand {
+    CommandPalette,
+    Recompile,
+    ClearLog,
+    ReloadFileTree,
+    GitRefresh,
+    FormatLiveDesign,
+}
+
+impl StudioCommand {
+    pub fn from_id(id: &str) -> Option<Self> {
+        STUDIO_COMMANDS
+            .iter()
+            .find(|(_, info)| info.id == id)
+            .map(|(command, _)| *command)
+    }
+}
+
+pub const STUDIO_COMMANDS: &[(StudioCommand, CommandInfo)] = &[
+    (StudioCommand::CommandPalette, CommandInfo {id: "studio.command_palette", title: "Show Command Palette"}),
+    (StudioCommand::Recompile, CommandInfo {id: "studio.recompile", title: "Recompile"}),
+    (StudioCommand::ClearLog, CommandInfo {id: "studio.clear_log", title: "Clear Log"}),
+    (StudioCommand::ReloadFileTree, CommandInfo {id: "studio.reload_file_tree", title: "Reload File Tree"}),
+    (StudioCommand::GitRefresh, CommandInfo {id: "studio.git_refresh", title: "Git: Refresh"}),
+    (StudioCommand::FormatLiveDesign, CommandInfo {id: "studio.format_live_design", title: "Format live_design! Blocks"}),
+];
@@ -0,0 +1,183 @@
+use {
+    crate::{
+        makepad_code_editor::{
+            decoration::RemoteSelection,
+            selection::Selection,
+            text::{Change, Drift, Edit, Position, Text},
+            CodeDocument,
+        },
+        makepad_file_protocol::{CollabClient, Delta, OffsetSelection, Operation},
+    },
+    std::sync::mpsc::Receiver,
+};
+
+/// Keeps an open code document in sync with the collaboration session of its file on the server.
+///
+/// The document itself works with positions, while the server works with byte offsets, so this
+/// keeps a copy of the text that the edits of the document apply to.
+pub struct DocumentCollab {
+    pub client: CollabClient,
+    // The text of the document as of the last edit that was turned into a delta.
+    text: Text,
+    edits: Receiver<Vec<Edit>>,
+    // The selections that were last sent to the other participants.
+    sent_selections: Vec<OffsetSelection>,
+}
+
+impl DocumentCollab {
+    pub fn new(document: &CodeDocument, revision: usize) -> Self {
+        Self {
+            client: CollabClient::new(revision),
+            text: document.as_text().clone(),
+            edits: document.add_edit_listener(),
+            sent_selections: Vec::new(),
+        }
+    }
+
+    /// Turns the edits made to the document since the last call into a delta. Returns the delta
+    /// to send to the server, if any.
+    pub fn take_local_delta(&mut self) -> Option<Delta> {
+        let mut delta: Option<Delta> = None;
+        while let Ok(edits) = self.edits.try_recv() {
+            for edit in edits {
+                let edit_delta = change_to_delta(&self.text, &edit.change);
+                self.text.apply_change(edit.change);
+                delta = Some(match delta {
+                    Some(delta) => delta.compose(&edit_delta),
+                    None => edit_delta,
+                });
+            }
+        }
+        self.client.apply_local(delta?)
+    }
+
+    /// Applies the deltas of other participants that can be applied yet. Local edits have to be
+    /// taken first. Returns whether the document changed.
+    pub fn apply_remote_deltas(&mut self, document: &CodeDocument) -> bool {
+        let mut is_changed = false;
+        while let Some(delta) = self.client.next_remote_delta() {
+            let edits = self.delta_to_edits(&delta);
+            document.apply_remote_edits(&edits);
+            // The document reports these edits back, but the text already has them.
+            while self.edits.try_recv().is_ok() {}
+            is_changed = true;
+        }
+        is_changed
+    }
+
+    /// Shows the selections of the other participants in the document.
+    pub fn update_remote_selections(&self, document: &CodeDocument) {
+        let len = text_len(&self.text);
+        let selections = self.client.remote_selections();
+        document.set_remote_selections(selections.into_iter().flat_map(
+            |(participant, selections)| {
+                selections.into_iter().map(move |selection| RemoteSelection {
+                    participant,
+                    anchor: offset_to_position(&self.text, selection.anchor.min(len)),
+                    cursor: offset_to_position(&self.text, selection.cursor.min(len)),
+                })
+            },
+        ));
+    }
+
+    /// Returns the selections to send to the other participants, if they changed. Selections are
+    /// only sent once the server has seen every local edit, so they are in its newest revision.
+    pub fn take_selections(&mut self, selections: &[Selection]) -> Option<Vec<OffsetSelection>> {
+        if !self.client.is_synchronized() {
+            return None;
+        }
+        let selections: Vec<_> = selections
+            .iter()
+            .map(|selection| OffsetSelection {
+                anchor: position_to_offset(&self.text, selection.anchor),
+                cursor: position_to_offset(&self.text, selection.cursor.position),
+            })
+            .collect();
+        if selections == self.sent_selections {
+            return None;
+        }
+        self.sent_selections = selections.clone();
+        Some(selections)
+    }
+
+    // Turns a delta into edits for the document, and applies them to the text.
+    fn delta_to_edits(&mut self, delta: &Delta) -> Vec<Edit> {
+        let mut edits = Vec::new();
+        let mut offset = 0;
+        for operation in &delta.operations {
+            let change = match operation {
+                Operation::Retain(len) => {
+                    offset += len;
+                    continue;
+                }
+                Operation::Insert(string) => {
+                    let position = offset_to_position(&self.text, offset);
+                    offset += string.len();
+                    Change::Insert(position, string.as_str().into())
+                }
+                Operation::Delete(len) => {
+                    let start = offset_to_position(&self.text, offset);
+                    let end = offset_to_position(&self.text, offset + len);
+                    Change::Delete(start, end - start)
+                }
+            };
+            self.text.apply_change(change.clone());
+            edits.push(Edit {
+                change,
+                drift: Drift::Before,
+            });
+        }
+        edits
+    }
+}
+
+fn text_len(text: &Text) -> usize {
+    text.as_lines().iter().map(|line| line.len() + 1).sum::<usize>() - 1
+}
+
+fn position_to_offset(text: &Text, position: Position) -> usize {
+    text.as_lines()[..position.line_index]
+        .iter()
+        .map(|line| line.len() + 1)
+        .sum::<usize>()
+        + position.byte_index
+}
+
+fn offset_to_position(text: &Text, offset: usize) -> Position {
+    let mut byte_index = offset;
+    for (line_index, line) in text.as_lines().iter().enumerate() {
+        if byte_index <= line.len() {
+            return Position {
+                line_index,
+                byte_index,
+            };
+        }
+        byte_index -= line.len() + 1;
+    }
+    let line_index = text.as_lines().len() - 1;
+    Position {
+        line_index,
+        byte_index: text.as_lines()[line_index].len(),
+    }
+}
+
+fn change_to_delta(text: &Text, change: &Change) -> Delta {
+    let len = text_len(text);
+    let mut delta = Delta::new();
+    match change {
+        Change::Insert(position, inserted_text) => {
+            let offset = position_to_offset(text, *position);
+            delta.retain(offset);
+            delta.insert(&inserted_text.to_string());
+            delta.retain(len - offset);
+        }
+        Change::Delete(start, length) => {
+            let start_offset = position_to_offset(text, *start);
+            let end_offset = position_to_offset(text, *start + *length);
+            delta.retain(start_offset);
+            delta.delete(end_offset - start_offset);
+            delta.retain(len - end_offset);
+        }
+    }
+    delta
+}
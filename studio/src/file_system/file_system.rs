@@ -2,11 +2,11 @@ use {
     std::collections::{HashMap, hash_map},
     std::path::Path,
     crate::{
-        makepad_code_editor::{CodeDocument, decoration::{Decoration, DecorationSet}, CodeSession},
+        makepad_code_editor::{CodeDocument, decoration::{Decoration, DecorationSet}, grammar::Language, history::HistoryMode, text::Position, CodeSession},
         makepad_platform::makepad_live_compiler::LiveFileChange,
         makepad_widgets::*,
         makepad_widgets::file_tree::*,
-        file_system::FileClient,
+        file_system::{FileClient, collab::DocumentCollab},
         ai_chat::ai_chat_manager::AiChatDocument,
         makepad_file_protocol::{
             FileRequest,
@@ -17,7 +17,10 @@ use {
             FileNodeData,
             FileTreeData,
             SaveKind,
-            SaveFileResponse
+            SaveFileResponse,
+            SearchOptions,
+            SearchResult,
+            Delta,
         },
     },
 };
@@ -30,7 +33,38 @@ pub struct FileSystem {
     pub path_to_file_node_id: HashMap<String, LiveId>,
     pub tab_id_to_file_node_id: HashMap<LiveId, LiveId>,
     pub tab_id_to_session: HashMap<LiveId, EditSession>,
-    pub open_documents: HashMap<LiveId, OpenDocument>
+    /// Where to put the cursor in tabs whose document is still loading.
+    pub tab_id_to_pending_jump: HashMap<LiveId, Position>,
+    pub open_documents: HashMap<LiveId, OpenDocument>,
+    /// The collaboration state of every open code document.
+    pub collab: HashMap<LiveId, DocumentCollab>,
+    /// Deltas from other participants that came in while the document was still loading.
+    pub early_remote_deltas: HashMap<LiveId, Vec<(usize, Delta)>>,
+    pub search: ProjectSearch,
+}
+
+/// The state of the last search across all files.
+#[derive(Default)]
+pub struct ProjectSearch {
+    pub id: u64,
+    pub pattern: String,
+    pub options: SearchOptions,
+    pub results: Vec<SearchResult>,
+    pub is_done: bool,
+    pub error: Option<String>,
+}
+
+impl ProjectSearch {
+    /// The paths of the files with results, in the order they were found.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = Vec::new();
+        for result in &self.results {
+            if paths.last() != Some(&result.path) {
+                paths.push(result.path.clone());
+            }
+        }
+        paths
+    }
 }
 
 pub enum EditSession {
@@ -71,6 +105,7 @@ pub enum FileSystemAction {
     RecompileNeeded,
     LiveReloadNeeded(LiveFileChange),
     FileChangedOnDisk(SaveFileResponse),
+    SearchResultsChanged,
     None
 }
 
@@ -119,6 +154,7 @@ impl FileSystem {
     pub fn remove_tab(&mut self, tab_id: LiveId) {
         self.tab_id_to_file_node_id.remove(&tab_id);
         self.tab_id_to_session.remove(&tab_id);
+        self.tab_id_to_pending_jump.remove(&tab_id);
     }
     
     pub fn path_to_file_node_id(&self, path: &str) -> Option<LiveId> {
@@ -193,7 +229,19 @@ impl FileSystem {
                                     match self.open_documents.get(&file_id){
                                         Some(OpenDocument::CodeLoading(dec))=>{
                                             let dec = dec.clone();
-                                            self.open_documents.insert(file_id, OpenDocument::Code(CodeDocument::new(response.data.into(), dec)));
+                                            let language = Language::for_path(&response.path);
+                                            let document = CodeDocument::new_with_language(response.data.into(), dec, language);
+                                            document.set_history_mode(HistoryMode::Tree);
+                                            if let Some(undo_history) = &response.undo_history{
+                                                document.restore_history(undo_history);
+                                            }
+                                            let mut collab = DocumentCollab::new(&document, response.revision);
+                                            for (revision, delta) in self.early_remote_deltas.remove(&file_id).unwrap_or_default(){
+                                                collab.client.receive_remote(revision, delta);
+                                            }
+                                            collab.apply_remote_deltas(&document);
+                                            self.collab.insert(file_id, collab);
+                                            self.open_documents.insert(file_id, OpenDocument::Code(document));
                                         }
                                         Some(OpenDocument::Code(_))=>{
                                         }
@@ -224,6 +272,61 @@ impl FileSystem {
                             // to see if we need a recompile
                             
                         }
+                        FileResponse::Search(result) => {
+                            if let Err(FileError::Unknown(err) | FileError::CannotOpen(err)) = result {
+                                self.search.error = Some(err);
+                                self.search.is_done = true;
+                                cx.action(FileSystemAction::SearchResultsChanged);
+                            }
+                        }
+                        FileResponse::ReplaceInFiles(result) => match result {
+                            Ok(responses) => {
+                                for response in responses {
+                                    if let Some(file_id) = self.path_to_file_node_id.get(&response.path){
+                                        if let Some(OpenDocument::Code(doc)) = self.open_documents.get_mut(file_id){
+                                            doc.replace(response.new_data.clone().into());
+                                        }
+                                    }
+                                    self.process_save_response(cx, response);
+                                }
+                                ui.redraw(cx);
+                                // the old results point at text that is gone now
+                                let pattern = self.search.pattern.clone();
+                                let options = self.search.options.clone();
+                                self.request_search(cx, &pattern, options);
+                            }
+                            Err(FileError::Unknown(err) | FileError::CannotOpen(err)) => {
+                                self.search.error = Some(err);
+                                cx.action(FileSystemAction::SearchResultsChanged);
+                            }
+                        }
+                        FileResponse::ApplyDelta(result) => match result {
+                            Ok(response) => {
+                                if let Some(file_id) = self.path_to_file_node_id(&response.path){
+                                    if let Some(collab) = self.collab.get_mut(&file_id){
+                                        if let Some(delta) = collab.client.apply_ack(response.revision){
+                                            let revision = collab.client.revision();
+                                            self.file_client.send_request(FileRequest::ApplyDelta{
+                                                path: response.path.clone(),
+                                                revision,
+                                                delta
+                                            });
+                                            self.request_save_file_for_file_node_id(file_id, false);
+                                        }
+                                    }
+                                    // deltas that overtook the acknowledgement can be applied now
+                                    self.apply_remote_deltas(cx, file_id, ui);
+                                }
+                            }
+                            Err(FileError::Unknown(err) | FileError::CannotOpen(err)) => {
+                                log!("Cannot apply delta {}", err);
+                            }
+                        }
+                        FileResponse::UpdateSelections(result) | FileResponse::CloseFile(result) => {
+                            if let Err(FileError::Unknown(err) | FileError::CannotOpen(err)) = result {
+                                log!("Collaboration error {}", err);
+                            }
+                        }
                     },
                     FileClientMessage::Notification(notification) => {
                         match notification{
@@ -241,6 +344,49 @@ impl FileSystem {
                                 // we should chuck this into the load comparison
                                 cx.action( FileSystemAction::FileChangedOnDisk(response));
                             }
+                            FileNotification::SearchResults{id, results, done}=>{
+                                if id == self.search.id {
+                                    self.search.results.extend(results);
+                                    self.search.is_done = done;
+                                    cx.action(FileSystemAction::SearchResultsChanged);
+                                }
+                            }
+                            FileNotification::DeltaWasApplied{path, revision, delta, participant: _}=>{
+                                if let Some(file_id) = self.path_to_file_node_id(&path){
+                                    match self.open_documents.get(&file_id){
+                                        Some(OpenDocument::CodeLoading(_))=>{
+                                            self.early_remote_deltas.entry(file_id).or_default().push((revision, delta));
+                                        }
+                                        Some(OpenDocument::Code(_))=>{
+                                            // local edits have to reach the server first
+                                            self.sync_collab();
+                                            if let Some(collab) = self.collab.get_mut(&file_id){
+                                                collab.client.receive_remote(revision, delta);
+                                            }
+                                            self.apply_remote_deltas(cx, file_id, ui);
+                                        }
+                                        _=>()
+                                    }
+                                }
+                            }
+                            FileNotification::SelectionsChanged{path, revision, participant, selections}=>{
+                                if let Some(file_id) = self.path_to_file_node_id(&path){
+                                    if let (Some(collab), Some(OpenDocument::Code(doc))) = (self.collab.get_mut(&file_id), self.open_documents.get(&file_id)){
+                                        collab.client.receive_remote_selections(participant, revision, selections);
+                                        collab.update_remote_selections(doc);
+                                        ui.redraw(cx);
+                                    }
+                                }
+                            }
+                            FileNotification::ParticipantLeft{path, participant}=>{
+                                if let Some(file_id) = self.path_to_file_node_id(&path){
+                                    if let (Some(collab), Some(OpenDocument::Code(doc))) = (self.collab.get_mut(&file_id), self.open_documents.get(&file_id)){
+                                        collab.client.remove_participant(participant);
+                                        collab.update_remote_selections(doc);
+                                        ui.redraw(cx);
+                                    }
+                                }
+                            }
                         }
                         //self.editors.handle_collab_notification(cx, &mut state.editor_state, notification)
                     }
@@ -348,6 +494,59 @@ impl FileSystem {
                 }
             }
         }
+        self.sync_collab();
+    }
+    
+    /// Sends the local edits and selections of every open code document to the other
+    /// participants of its collaboration session.
+    pub fn sync_collab(&mut self) {
+        let file_ids: Vec<LiveId> = self.collab.keys().cloned().collect();
+        for file_id in file_ids {
+            let path = self.file_node_path(file_id);
+            let collab = self.collab.get_mut(&file_id).unwrap();
+            if let Some(delta) = collab.take_local_delta() {
+                self.file_client.send_request(FileRequest::ApplyDelta{
+                    path: path.clone(),
+                    revision: collab.client.revision(),
+                    delta
+                });
+            }
+            let session = self.tab_id_to_file_node_id.iter()
+                .filter(|(_, id)| **id == file_id)
+                .find_map(|(tab_id, _)| match self.tab_id_to_session.get(tab_id){
+                    Some(EditSession::Code(session)) => Some(session),
+                    _ => None
+                });
+            if let Some(session) = session {
+                if let Some(selections) = collab.take_selections(&session.selections()) {
+                    self.file_client.send_request(FileRequest::UpdateSelections{
+                        path,
+                        revision: collab.client.revision(),
+                        selections
+                    });
+                }
+            }
+        }
+    }
+    
+    // Applies the deltas from other participants that can be applied to the document with the
+    // given id, and live reloads what changed.
+    fn apply_remote_deltas(&mut self, cx: &mut Cx, file_id: LiveId, ui: &WidgetRef) {
+        let (Some(collab), Some(OpenDocument::Code(doc))) = (self.collab.get_mut(&file_id), self.open_documents.get(&file_id)) else {
+            return
+        };
+        let old_data = doc.as_text().to_string();
+        if !collab.apply_remote_deltas(doc) {
+            return
+        }
+        collab.update_remote_selections(doc);
+        let new_data = doc.as_text().to_string();
+        self.handle_sessions();
+        ui.redraw(cx);
+        let path = self.file_node_path(file_id);
+        if Self::get_editor_template_from_path(&path) == live_id!(CodeEditor) {
+            self.process_possible_live_reload(cx, &path, &old_data, &new_data, true);
+        }
     }
     
     pub fn request_open_file(&mut self, tab_id: LiveId, file_id: LiveId) {
@@ -387,6 +586,38 @@ impl FileSystem {
         self.file_client.send_request(FileRequest::OpenFile{path, id: file_id.0});
     }
     
+    /// Starts searching all files, which cancels the search that is running. The results come
+    /// in over time, each batch followed by a `SearchResultsChanged` action.
+    pub fn request_search(&mut self, cx: &mut Cx, pattern: &str, options: SearchOptions) {
+        self.search.id += 1;
+        self.search.pattern = pattern.to_string();
+        self.search.options = options.clone();
+        self.search.results.clear();
+        self.search.error = None;
+        self.search.is_done = pattern.is_empty();
+        if !pattern.is_empty() {
+            self.file_client.send_request(FileRequest::Search{
+                pattern: pattern.to_string(),
+                options,
+                id: self.search.id,
+            });
+        }
+        cx.action(FileSystemAction::SearchResultsChanged);
+    }
+    
+    /// Replaces every result of the last search in the files it was found in.
+    pub fn request_replace_in_files(&mut self, replacement: &str) {
+        if self.search.results.is_empty() {
+            return
+        }
+        self.file_client.send_request(FileRequest::ReplaceInFiles{
+            pattern: self.search.pattern.clone(),
+            options: self.search.options.clone(),
+            replacement: replacement.to_string(),
+            paths: self.search.paths(),
+        });
+    }
+    
     pub fn request_save_file_for_tab_id(&mut self, tab_id: LiveId, was_patch:bool) {
         // ok lets see if we have a document
         // ifnot, we create a new one
@@ -430,13 +661,20 @@ impl FileSystem {
     }
     
     pub fn request_save_file_for_file_node_id(&mut self, file_id: LiveId, patch:bool) {
+        // the server saves the text of the collaboration session, so it needs the edits first
+        self.sync_collab();
         if let Some(text) = self.file_id_as_string(file_id){
             let path = self.file_node_path(file_id);
+            let undo_history = match self.open_documents.get(&file_id){
+                Some(OpenDocument::Code(doc))=>Some(doc.history_to_bin()),
+                _=>None
+            };
             self.file_client.send_request(FileRequest::SaveFile{
                 path: path.clone(), 
                 data: text, 
                 id: file_id.0,
-                patch
+                patch,
+                undo_history
             });
         }
     }
@@ -451,6 +689,28 @@ impl FileSystem {
         };
     }
     
+    pub fn clear_decorations_with_id(&mut self, file_node_id: &LiveId, id: usize) {
+        match self.open_documents.get_mut(file_node_id) {
+            Some(OpenDocument::CodeLoading(dec)) => dec.remove_decorations_with_id(id),
+            Some(OpenDocument::Code(doc)) => doc.remove_decorations_with_id(id),
+            Some(_) | None=>()
+        };
+    }
+    
+    /// Removes the decorations with `id` from every open document and returns their files
+    pub fn clear_all_decorations_with_id(&mut self, id: usize) -> Vec<LiveId> {
+        let mut file_ids = Vec::new();
+        for (file_id, document) in &mut self.open_documents {
+            match document {
+                OpenDocument::CodeLoading(dec) => dec.remove_decorations_with_id(id),
+                OpenDocument::Code(doc) => doc.remove_decorations_with_id(id),
+                _=>continue
+            }
+            file_ids.push(*file_id);
+        }
+        file_ids
+    }
+    
     pub fn clear_all_decorations(&mut self) {
         // ok lets see if we have a document
         // ifnot, we create a new one
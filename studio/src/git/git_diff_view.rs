@@ -0,0 +1,44 @@
+use {
+    crate::{
+        app::AppData,
+        makepad_widgets::*,
+        makepad_code_editor::CodeEditor,
+    },
+};
+
+live_design!{
+    import makepad_code_editor::code_editor::CodeEditor;
+
+    GitDiffView = {{GitDiffView}}{
+        editor: <CodeEditor>{
+            read_only: true
+        }
+    }
+}
+
+/// Shows the diff that is selected in the Git panel.
+#[derive(Live, LiveHook, Widget)]
+pub struct GitDiffView{
+    #[wrap] #[live] pub editor: CodeEditor
+}
+
+impl Widget for GitDiffView {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope:&mut Scope, walk:Walk)->DrawStep{
+        let data = scope.data.get_mut::<AppData>().unwrap();
+        if let Some(session) = &mut data.git_manager.diff_session{
+            self.editor.draw_walk_editor(cx, session, walk);
+        }
+        else{
+            self.editor.draw_empty_editor(cx, walk);
+        }
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope){
+        let data = scope.data.get_mut::<AppData>().unwrap();
+        if let Some(session) = &mut data.git_manager.diff_session{
+            self.editor.handle_event(cx, event, &mut Scope::empty(), session);
+            session.handle_changes();
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use {
+    crate::{
+        app::AppAction,
+        file_system::file_system::{FileSystem, OpenDocument},
+        makepad_code_editor::{
+            decoration::{DecorationSet, LineMarker, LineMarkerType},
+            grammar,
+            text::Edit,
+            CodeDocument, CodeSession,
+        },
+        makepad_git::{diff_lines, FileDiff, FileStatus, GitRepo, HunkKind},
+        makepad_widgets::*,
+    },
+    std::{
+        collections::HashMap,
+        path::Path,
+        sync::mpsc::{self, Receiver, Sender},
+        thread,
+    },
+};
+
+// Line markers for the differences with HEAD carry this id.
+pub const GIT_LINE_MARKER_ID: usize = 0;
+
+// How long to wait after the last edit before asking git for the status again. Studio saves on
+// every edit, so this is what keeps the panel up to date while typing.
+const REFRESH_DELAY: f64 = 0.5;
+
+#[derive(Default)]
+pub struct GitManager {
+    repo: Option<GitRepo>,
+    // git runs on a worker thread so a slow repository can't stall the editor, it handles the
+    // requests in order and sends back what it found
+    request_sender: Option<Sender<GitRequest>>,
+    response_receiver: ToUIReceiver<GitResponse>,
+    pub files: Vec<FileStatus>,
+    pub selected: Option<SelectedDiff>,
+    /// The read-only session the diff view shows the selected diff in.
+    pub diff_session: Option<CodeSession>,
+    pub error: Option<String>,
+    documents: HashMap<LiveId, TrackedDocument>,
+    refresh_timer: Timer,
+}
+
+pub struct SelectedDiff {
+    /// Relative to the top of the repository.
+    pub path: String,
+    /// Whether this is the diff between HEAD and the index, rather than the one between the
+    /// index and the working tree.
+    pub staged: bool,
+    pub diff: FileDiff,
+}
+
+struct TrackedDocument {
+    // The text in HEAD the line markers compare against. `None` for files git doesn't track,
+    // and until the worker read it.
+    base_text: Option<String>,
+    edit_receiver: Receiver<Vec<Edit>>,
+}
+
+type GitOperation = Box<dyn FnOnce(&GitRepo) -> Result<(), String> + Send>;
+
+enum GitRequest {
+    Refresh {
+        selected: Option<(String, bool)>,
+    },
+    Select {
+        path: String,
+        staged: bool,
+        untracked: bool,
+        show: bool,
+    },
+    // Changes the index or HEAD, and is followed by a refresh.
+    Run {
+        operation: GitOperation,
+        is_commit: bool,
+    },
+    BaseText {
+        file_id: LiveId,
+        path: String,
+        is_new: bool,
+    },
+}
+
+enum GitResponse {
+    Status(Result<Vec<FileStatus>, String>),
+    Diff {
+        path: String,
+        staged: bool,
+        show: bool,
+        result: Result<(String, FileDiff), String>,
+    },
+    Ran {
+        result: Result<(), String>,
+        is_commit: bool,
+    },
+    BaseText {
+        file_id: LiveId,
+        base_text: Option<String>,
+    },
+}
+
+impl GitManager {
+    pub fn init(&mut self, cx: &mut Cx, root_path: &Path) {
+        self.repo = GitRepo::open(root_path);
+        if let Some(repo) = self.repo.clone() {
+            let (request_sender, request_receiver) = mpsc::channel();
+            let response_sender = self.response_receiver.sender();
+            thread::spawn(move || {
+                while let Ok(request) = request_receiver.recv() {
+                    for response in handle_request(&repo, request) {
+                        if response_sender.send(response).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+            self.request_sender = Some(request_sender);
+        }
+        self.refresh(cx);
+    }
+
+    pub fn has_repo(&self) -> bool {
+        self.repo.is_some()
+    }
+
+    pub fn staged_files(&self) -> impl Iterator<Item = &FileStatus> {
+        self.files.iter().filter(|file| file.is_staged())
+    }
+
+    pub fn changed_files(&self) -> impl Iterator<Item = &FileStatus> {
+        self.files.iter().filter(|file| file.is_changed())
+    }
+
+    pub fn untracked_files(&self) -> impl Iterator<Item = &FileStatus> {
+        self.files.iter().filter(|file| file.is_untracked())
+    }
+
+    /// Reads the status and the selected diff from git again.
+    pub fn refresh(&mut self, _cx: &mut Cx) {
+        let selected = self
+            .selected
+            .as_ref()
+            .map(|selected| (selected.path.clone(), selected.staged));
+        self.send(GitRequest::Refresh { selected });
+    }
+
+    /// Shows the diff of a file in the diff view, once git produced it.
+    pub fn select(&mut self, _cx: &mut Cx, path: &str, staged: bool) {
+        let untracked = self
+            .files
+            .iter()
+            .any(|file| file.path == path && file.is_untracked());
+        self.send(GitRequest::Select {
+            path: path.to_string(),
+            staged,
+            untracked,
+            show: true,
+        });
+    }
+
+    pub fn stage_file(&mut self, cx: &mut Cx, path: &str) {
+        let path = path.to_string();
+        self.run(cx, false, move |repo| repo.stage_file(&path));
+    }
+
+    pub fn unstage_file(&mut self, cx: &mut Cx, path: &str) {
+        let path = path.to_string();
+        self.run(cx, false, move |repo| repo.unstage_file(&path));
+    }
+
+    /// Stages or unstages a hunk of the selected diff, depending on which diff that is.
+    pub fn toggle_hunk(&mut self, cx: &mut Cx, index: usize) {
+        let Some(selected) = &self.selected else {
+            return;
+        };
+        if index >= selected.diff.hunks.len() {
+            return;
+        }
+        let diff = selected.diff.clone();
+        let staged = selected.staged;
+        self.run(cx, false, move |repo| {
+            if staged {
+                repo.unstage_hunk(&diff, index)
+            } else {
+                repo.stage_hunk(&diff, index)
+            }
+        });
+    }
+
+    /// Commits the staged changes. Returns `false` if there is nothing to commit with. Once the
+    /// commit went through, `AppAction::GitCommitted` is sent.
+    pub fn commit(&mut self, cx: &mut Cx, message: &str) -> bool {
+        if message.trim().is_empty() {
+            self.set_error("Enter a commit message first".to_string());
+            cx.action(AppAction::RedrawGit);
+            return false;
+        }
+        let message = message.to_string();
+        self.run(cx, true, move |repo| repo.commit(&message))
+    }
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &Event, file_system: &mut FileSystem) {
+        if self.refresh_timer.is_event(event).is_some() {
+            self.refresh(cx);
+        }
+        if let Event::Signal = event {
+            while let Ok(response) = self.response_receiver.try_recv() {
+                self.handle_response(cx, file_system, response);
+            }
+        }
+        self.sync_documents(cx, file_system);
+    }
+
+    fn handle_response(&mut self, cx: &mut Cx, file_system: &FileSystem, response: GitResponse) {
+        match response {
+            GitResponse::Status(Ok(files)) => {
+                self.files = files;
+                cx.action(AppAction::RedrawGit);
+            }
+            GitResponse::Status(Err(err)) => {
+                self.set_error(err);
+                cx.action(AppAction::RedrawGit);
+            }
+            GitResponse::Diff { path, staged, show, result } => {
+                match result {
+                    Ok((text, diff)) => {
+                        let document = CodeDocument::new_with_language(
+                            text.into(),
+                            DecorationSet::new(),
+                            grammar::DIFF,
+                        );
+                        self.diff_session = Some(CodeSession::new(document));
+                        self.selected = Some(SelectedDiff { path, staged, diff });
+                        if show {
+                            cx.action(AppAction::ShowGitDiff);
+                        }
+                    }
+                    Err(err) => self.set_error(err),
+                }
+                cx.action(AppAction::RedrawGit);
+            }
+            GitResponse::Ran { result, is_commit } => {
+                match result {
+                    Ok(()) => {
+                        self.error = None;
+                        if is_commit {
+                            // HEAD moved, so every line marker needs a new base
+                            self.documents.clear();
+                            cx.action(AppAction::GitCommitted);
+                        }
+                    }
+                    Err(err) => self.set_error(err),
+                }
+                cx.action(AppAction::RedrawGit);
+            }
+            GitResponse::BaseText { file_id, base_text } => {
+                let Some(tracked) = self.documents.get_mut(&file_id) else {
+                    return;
+                };
+                let Some(OpenDocument::Code(document)) = file_system.open_documents.get(&file_id) else {
+                    return;
+                };
+                tracked.base_text = base_text;
+                update_line_markers(document, tracked.base_text.as_deref());
+                cx.action(AppAction::RedrawFile(file_id));
+            }
+        }
+    }
+
+    // Starts tracking newly opened documents, and updates the line markers of the ones that
+    // were edited since the last call.
+    fn sync_documents(&mut self, cx: &mut Cx, file_system: &FileSystem) {
+        let Some(repo) = &self.repo else {
+            return;
+        };
+        self.documents.retain(|file_id, _| {
+            matches!(file_system.open_documents.get(file_id), Some(OpenDocument::Code(_)))
+        });
+        let mut was_edited = false;
+        let mut base_text_requests = Vec::new();
+        for (file_id, document) in &file_system.open_documents {
+            let OpenDocument::Code(document) = document else {
+                continue;
+            };
+            if file_system.file_nodes.get(file_id).is_none() {
+                continue;
+            }
+            match self.documents.get(file_id) {
+                Some(tracked) => {
+                    if tracked.edit_receiver.try_iter().count() == 0 {
+                        continue;
+                    }
+                    was_edited = true;
+                    update_line_markers(document, tracked.base_text.as_deref());
+                }
+                None => {
+                    // the line markers show up once the worker read the text in HEAD
+                    let path = repo.repo_path(&file_system.file_node_path(*file_id));
+                    let is_new = self
+                        .files
+                        .iter()
+                        .any(|file| file.path == path && (file.is_untracked() || file.index == 'A'));
+                    base_text_requests.push(GitRequest::BaseText {
+                        file_id: *file_id,
+                        path,
+                        is_new,
+                    });
+                    self.documents.insert(
+                        *file_id,
+                        TrackedDocument {
+                            base_text: None,
+                            edit_receiver: document.add_edit_listener(),
+                        },
+                    );
+                }
+            }
+        }
+        for request in base_text_requests {
+            self.send(request);
+        }
+        if was_edited {
+            cx.stop_timer(self.refresh_timer);
+            self.refresh_timer = cx.start_timeout(REFRESH_DELAY);
+        }
+    }
+
+    // Queues a git operation that changes the index or HEAD, the worker refreshes afterwards.
+    // Returns `false` if there is no repository to run it in.
+    fn run(
+        &mut self,
+        cx: &mut Cx,
+        is_commit: bool,
+        operation: impl FnOnce(&GitRepo) -> Result<(), String> + Send + 'static,
+    ) -> bool {
+        if self.repo.is_none() {
+            return false;
+        }
+        self.send(GitRequest::Run {
+            operation: Box::new(operation),
+            is_commit,
+        });
+        self.refresh(cx);
+        true
+    }
+
+    fn send(&self, request: GitRequest) {
+        if let Some(request_sender) = &self.request_sender {
+            let _ = request_sender.send(request);
+        }
+    }
+
+    fn set_error(&mut self, err: String) {
+        log!("Git: {}", err.trim());
+        self.error = Some(err.trim().to_string());
+    }
+}
+
+// Runs on the worker thread.
+fn handle_request(repo: &GitRepo, request: GitRequest) -> Vec<GitResponse> {
+    match request {
+        GitRequest::Refresh { selected } => {
+            let status = repo.status().map(|mut files| {
+                files.sort_by(|a, b| a.path.cmp(&b.path));
+                files
+            });
+            let untracked = |path: &str| match &status {
+                Ok(files) => files.iter().any(|file| file.path == path && file.is_untracked()),
+                Err(_) => false,
+            };
+            let diff = selected.map(|(path, staged)| {
+                let untracked = untracked(&path);
+                diff_response(repo, path, staged, untracked, false)
+            });
+            std::iter::once(GitResponse::Status(status)).chain(diff).collect()
+        }
+        GitRequest::Select { path, staged, untracked, show } => {
+            vec![diff_response(repo, path, staged, untracked, show)]
+        }
+        GitRequest::Run { operation, is_commit } => vec![GitResponse::Ran {
+            result: operation(repo),
+            is_commit,
+        }],
+        GitRequest::BaseText { file_id, path, is_new } => vec![GitResponse::BaseText {
+            file_id,
+            base_text: repo.head_text(&path).or_else(|| is_new.then(String::new)),
+        }],
+    }
+}
+
+fn diff_response(repo: &GitRepo, path: String, staged: bool, untracked: bool, show: bool) -> GitResponse {
+    let result = if untracked {
+        // git has nothing to compare an untracked file with, so show it as added in full
+        let data = std::fs::read_to_string(repo.root().join(&path)).unwrap_or_default();
+        Ok((untracked_diff_text(&path, &data), FileDiff::default()))
+    } else {
+        repo.diff_text(&path, staged).map(|text| {
+            let diff = FileDiff::parse(&text).into_iter().next().unwrap_or_default();
+            (text, diff)
+        })
+    };
+    GitResponse::Diff { path, staged, show, result }
+}
+
+fn update_line_markers(document: &CodeDocument, base_text: Option<&str>) {
+    let hunks = match base_text {
+        Some(base_text) => diff_lines(base_text, &document.as_text().to_string()),
+        None => Vec::new(),
+    };
+    document.set_line_markers(
+        GIT_LINE_MARKER_ID,
+        hunks.iter().map(|hunk| {
+            let lines = hunk.new_line_range();
+            let ty = match hunk.kind() {
+                HunkKind::Added => LineMarkerType::Added,
+                HunkKind::Modified => LineMarkerType::Modified,
+                HunkKind::Deleted => LineMarkerType::Deleted,
+            };
+            LineMarker::new(GIT_LINE_MARKER_ID, lines.start, lines.end, ty)
+        }),
+    );
+}
+
+fn untracked_diff_text(path: &str, data: &str) -> String {
+    let mut text = format!("--- /dev/null\n+++ b/{}\n", path);
+    for hunk in diff_lines("", data) {
+        text.push_str(&hunk.header());
+        text.push('\n');
+        for line in &hunk.lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    text
+}
@@ -0,0 +1,243 @@
+use {
+    crate::{
+        app::AppData,
+        git::git_manager::GitManager,
+        makepad_widgets::*,
+    },
+};
+
+live_design!{
+    import makepad_draw::shader::std::*;
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    GitItem = <View> {
+        width: Fill, height: Fit,
+        flow: Right,
+        align: { y: 0.5 }
+        padding: { left: (THEME_SPACE_2), right: (THEME_SPACE_2) }
+        show_bg: true,
+
+        draw_bg: {
+            instance is_even: 0.0
+            instance selected: 0.0
+            fn pixel(self) -> vec4 {
+                return mix(
+                    mix(
+                        THEME_COLOR_BG_EVEN,
+                        THEME_COLOR_BG_ODD,
+                        self.is_even
+                    ),
+                    THEME_COLOR_CTRL_SELECTED,
+                    self.selected
+                );
+            }
+        }
+    }
+
+    GitItemButton = <ButtonFlatter> {
+        margin: 0.,
+        padding: <THEME_MSPACE_1> {}
+    }
+
+    GitPanel = {{GitPanel}}{
+        width: Fill, height: Fill,
+
+        list = <FlatList> {
+            height: Fill, width: Fill,
+            flow: Down,
+            grab_key_focus: true,
+            drag_scrolling: false,
+
+            Section = <GitItem> {
+                label = <Pbold> { width: Fill, margin: 0., padding: <THEME_MSPACE_V_2> {} }
+            }
+
+            File = <GitItem> {
+                status = <P> {
+                    width: 15., margin: 0., padding: <THEME_MSPACE_V_1> {}
+                    draw_text: { color: (THEME_COLOR_TEXT_META) }
+                }
+                name = <GitItemButton> { width: Fill, align: { x: 0.0 } }
+                toggle = <GitItemButton> {}
+            }
+
+            Hunk = <GitItem> {
+                padding: { left: 25., right: (THEME_SPACE_2) }
+                name = <GitItemButton> {
+                    width: Fill, align: { x: 0.0 }
+                    draw_text: { text_style: <THEME_FONT_CODE> {} }
+                }
+                toggle = <GitItemButton> {}
+            }
+
+            Message = <GitItem> {
+                label = <P> { width: Fill, margin: 0., padding: <THEME_MSPACE_V_2> {} }
+            }
+
+            Empty = <GitItem> {
+                height: Fit, width: Fill,
+                cursor: Default
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, DefaultNone)]
+enum ActionData{
+    Select{path: String, staged: bool},
+    StageFile{path: String},
+    UnstageFile{path: String},
+    ToggleHunk{index: usize},
+    None
+}
+
+/// Lists the staged, changed and untracked files, and the hunks of the file whose diff is shown.
+#[derive(Live, LiveHook, Widget)]
+pub struct GitPanel{
+    #[deref] view: View
+}
+
+impl GitPanel{
+    fn draw_git_panel(&mut self, cx: &mut Cx2d, list: &mut FlatList, git_manager: &GitManager){
+        fn draw_message(cx: &mut Cx2d, list: &mut FlatList, counter: &mut u32, text: &str){
+            let item_id = LiveId::from_str("message").bytes_append(&counter.to_be_bytes());
+            let item = list.item(cx, item_id, live_id!(Message)).unwrap().as_view();
+            item.apply_over(cx, live!{
+                draw_bg: {is_even: (if *counter & 1 == 0 {1.0} else {0.0})}
+                label = {text: (text)}
+            });
+            item.draw_all(cx, &mut Scope::empty());
+            *counter += 1;
+        }
+        let mut counter = 0u32;
+        if !git_manager.has_repo(){
+            draw_message(cx, list, &mut counter, "Not a git repository");
+        }
+        if let Some(error) = &git_manager.error{
+            draw_message(cx, list, &mut counter, error);
+        }
+
+        let sections = [
+            ("Staged Changes", git_manager.staged_files().collect::<Vec<_>>(), true),
+            ("Changes", git_manager.changed_files().collect::<Vec<_>>(), false),
+            ("Untracked", git_manager.untracked_files().collect::<Vec<_>>(), false),
+        ];
+        for (section, files, staged) in sections{
+            if files.is_empty(){
+                continue;
+            }
+            let is_even = counter & 1 == 0;
+            let section_id = LiveId::from_str(section);
+            let item = list.item(cx, section_id, live_id!(Section)).unwrap().as_view();
+            item.apply_over(cx, live!{
+                draw_bg: {is_even: (if is_even {1.0} else {0.0})}
+                label = {text: (format!("{} ({})", section, files.len()))}
+            });
+            item.draw_all(cx, &mut Scope::empty());
+            counter += 1;
+
+            for file in files{
+                let is_even = counter & 1 == 0;
+                let is_selected = git_manager.selected.as_ref().map_or(false, |selected|{
+                    selected.path == file.path && selected.staged == staged
+                });
+                let status = if staged {file.index} else if file.is_untracked() {'U'} else {file.worktree};
+                let item_id = section_id.str_append(&file.path);
+                let item = list.item(cx, item_id, live_id!(File)).unwrap().as_view();
+                item.apply_over(cx, live!{
+                    draw_bg: {
+                        is_even: (if is_even {1.0} else {0.0})
+                        selected: (if is_selected {1.0} else {0.0})
+                    }
+                    status = {text: (status.to_string())}
+                    name = {text: (&file.path)}
+                    toggle = {text: (if staged {"−"} else {"+"})}
+                });
+                item.button(id!(name)).set_action_data(ActionData::Select{path: file.path.clone(), staged});
+                item.button(id!(toggle)).set_action_data(if staged{
+                    ActionData::UnstageFile{path: file.path.clone()}
+                }
+                else{
+                    ActionData::StageFile{path: file.path.clone()}
+                });
+                item.draw_all(cx, &mut Scope::empty());
+                counter += 1;
+
+                if !is_selected{
+                    continue;
+                }
+                let selected = git_manager.selected.as_ref().unwrap();
+                for (index, hunk) in selected.diff.hunks.iter().enumerate(){
+                    let is_even = counter & 1 == 0;
+                    let item_id = item_id.bytes_append(&index.to_be_bytes());
+                    let item = list.item(cx, item_id, live_id!(Hunk)).unwrap().as_view();
+                    item.apply_over(cx, live!{
+                        draw_bg: {is_even: (if is_even {1.0} else {0.0})}
+                        name = {text: (hunk.header())}
+                        toggle = {text: (if staged {"−"} else {"+"})}
+                    });
+                    item.button(id!(name)).set_action_data(ActionData::Select{path: file.path.clone(), staged});
+                    item.button(id!(toggle)).set_action_data(ActionData::ToggleHunk{index});
+                    item.draw_all(cx, &mut Scope::empty());
+                    counter += 1;
+                }
+            }
+        }
+        if git_manager.has_repo() && git_manager.files.is_empty(){
+            draw_message(cx, list, &mut counter, "No changes");
+        }
+
+        while list.space_left(cx)>0.0 {
+            let is_even = counter & 1 == 0;
+            let item_id = LiveId::from_str("empty").bytes_append(&counter.to_be_bytes());
+            let item = list.item(cx, item_id, live_id!(Empty)).unwrap().as_view();
+            let height = list.space_left(cx).min(20.0);
+            item.apply_over(cx, live!{
+                height: (height)
+                draw_bg: {is_even: (if is_even {1.0} else {0.0})}
+            });
+            item.draw_all(cx, &mut Scope::empty());
+            counter += 1;
+        }
+    }
+}
+
+impl WidgetMatchEvent for GitPanel{
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope){
+        let git_manager = &mut scope.data.get_mut::<AppData>().unwrap().git_manager;
+        let list = self.view.flat_list(id!(list));
+        for (_item_id, item) in list.items_with_actions(&actions) {
+            for button in [item.button(id!(name)), item.button(id!(toggle))]{
+                if !button.clicked(&actions){
+                    continue;
+                }
+                match button.action_data().cast_ref(){
+                    ActionData::Select{path, staged}=>{
+                        git_manager.select(cx, path, *staged);
+                    }
+                    ActionData::StageFile{path}=>git_manager.stage_file(cx, path),
+                    ActionData::UnstageFile{path}=>git_manager.unstage_file(cx, path),
+                    ActionData::ToggleHunk{index}=>git_manager.toggle_hunk(cx, *index),
+                    ActionData::None=>()
+                }
+            }
+        }
+    }
+}
+
+impl Widget for GitPanel {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope:&mut Scope, walk:Walk)->DrawStep{
+        while let Some(item) = self.view.draw_walk(cx, scope, walk).step(){
+            if let Some(mut list) = item.as_flat_list().borrow_mut(){
+                self.draw_git_panel(cx, &mut *list, &scope.data.get::<AppData>().unwrap().git_manager)
+            }
+        }
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope){
+        self.widget_match_event(cx, event, scope);
+        self.view.handle_event(cx, event, scope);
+    }
+}
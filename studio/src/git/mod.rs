@@ -0,0 +1,3 @@
+pub mod git_diff_view;
+pub mod git_manager;
+pub mod git_panel;
@@ -7,11 +7,17 @@ pub mod file_system;
 pub mod studio_editor;
 pub mod studio_file_tree;
 pub mod log_list;
+pub mod search_list;
+pub mod commands;
+pub mod command_palette;
 pub mod run_list;
 pub mod run_view;
 pub mod profiler;
 pub mod integration;
 pub mod ai_chat;
+pub mod lsp;
+pub mod git;
+pub mod terminal;
 
 //pub use makepad_code_editor;
 
@@ -29,6 +35,8 @@ pub use makepad_platform::makepad_math;
 //pub use makepad_code_editor;
 pub use makepad_file_protocol;
 pub use makepad_file_server;
+pub use makepad_git;
+pub use makepad_terminal;
 pub use makepad_widgets;
 pub use makepad_code_editor;
 pub use makepad_shell;
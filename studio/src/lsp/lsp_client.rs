@@ -0,0 +1,601 @@
+use {
+    crate::{
+        lsp::{lsp_protocol::*, lsp_transport::*},
+        makepad_code_editor::{
+            code_editor::CompletionItem,
+            decoration::DecorationType,
+            text::{Change, Edit, Position, Text},
+        },
+        makepad_micro_serde::*,
+        makepad_platform::*,
+    },
+    std::{
+        collections::HashMap,
+        io,
+        path::{Path, PathBuf},
+    },
+};
+
+// Keeps one language server in sync with the documents open in studio. Every document has a
+// shadow copy of its text, so the byte positions of the editor can be turned into the UTF-16
+// positions of the protocol, and back.
+
+pub enum LspEvent {
+    Hover {
+        request_id: u64,
+        text: String,
+    },
+    Definition {
+        request_id: u64,
+        path: PathBuf,
+        position: Position,
+    },
+    Completion {
+        request_id: u64,
+        items: Vec<CompletionItem>,
+    },
+    Diagnostics {
+        path: PathBuf,
+        diagnostics: Vec<(Position, Position, DecorationType)>,
+    },
+    Exited,
+}
+
+enum LspRequest {
+    Initialize,
+    Hover,
+    Definition,
+    Completion,
+    Shutdown,
+}
+
+struct LspDocument {
+    version: i64,
+    text: Text,
+}
+
+pub struct LspClient {
+    transport: LspTransport,
+    next_request_id: u64,
+    is_initialized: bool,
+    queued_messages: Vec<String>,
+    pending_requests: HashMap<u64, LspRequest>,
+    documents: HashMap<String, LspDocument>,
+}
+
+impl LspClient {
+    pub fn start(cmd: &str, args: &[String], root_path: &Path) -> Result<Self, io::Error> {
+        let transport = LspTransport::start(cmd, args, root_path)?;
+        Ok(Self::with_transport(transport, root_path))
+    }
+
+    fn with_transport(transport: LspTransport, root_path: &Path) -> Self {
+        let mut client = Self {
+            transport,
+            next_request_id: 1,
+            is_initialized: false,
+            queued_messages: Vec::new(),
+            pending_requests: HashMap::new(),
+            documents: HashMap::new(),
+        };
+        let root_uri = path_to_uri(root_path);
+        let root_name = root_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let id = client.next_request_id();
+        client.pending_requests.insert(id, LspRequest::Initialize);
+        client.transport.send(request(id, "initialize", |w| {
+            write_initialize_params(w, &root_uri, &root_name)
+        }));
+        client
+    }
+
+    pub fn is_open(&self, path: &Path) -> bool {
+        self.documents.contains_key(&path_to_uri(path))
+    }
+
+    pub fn open_document(&mut self, path: &Path, text: &Text) {
+        let uri = path_to_uri(path);
+        let language_id = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("rs") => "rust",
+            Some("toml") => "toml",
+            Some(ext) => ext,
+            None => "plaintext",
+        };
+        let message = notification("textDocument/didOpen", |w| {
+            write_did_open_params(w, &uri, language_id, 0, &text.to_string())
+        });
+        self.send(message);
+        self.documents.insert(
+            uri,
+            LspDocument {
+                version: 0,
+                text: text.clone(),
+            },
+        );
+    }
+
+    pub fn close_document(&mut self, path: &Path) {
+        let uri = path_to_uri(path);
+        if self.documents.remove(&uri).is_some() {
+            self.send(notification("textDocument/didClose", |w| {
+                write_did_close_params(w, &uri)
+            }));
+        }
+    }
+
+    // Sends the edits as incremental changes. The protocol applies the changes of a single
+    // notification one after the other, just like the edits themselves.
+    pub fn change_document(&mut self, path: &Path, edits: &[Edit]) {
+        let uri = path_to_uri(path);
+        let Some(document) = self.documents.get_mut(&uri) else {
+            return;
+        };
+        if edits.is_empty() {
+            return;
+        }
+        let changes = edits_to_changes(&mut document.text, edits);
+        document.version += 1;
+        let version = document.version;
+        self.send(notification("textDocument/didChange", |w| {
+            write_did_change_params(w, &uri, version, &changes)
+        }));
+    }
+
+    pub fn hover(&mut self, path: &Path, position: Position) -> Option<u64> {
+        self.text_document_request(path, position, "textDocument/hover", LspRequest::Hover)
+    }
+
+    pub fn definition(&mut self, path: &Path, position: Position) -> Option<u64> {
+        self.text_document_request(path, position, "textDocument/definition", LspRequest::Definition)
+    }
+
+    pub fn completion(&mut self, path: &Path, position: Position) -> Option<u64> {
+        self.text_document_request(path, position, "textDocument/completion", LspRequest::Completion)
+    }
+
+    pub fn shutdown(mut self) {
+        let id = self.next_request_id();
+        self.pending_requests.insert(id, LspRequest::Shutdown);
+        self.send(request_without_params(id, "shutdown"));
+        self.send(notification_without_params("exit"));
+        self.transport.close();
+    }
+
+    pub fn handle_messages(&mut self) -> Vec<LspEvent> {
+        let mut events = Vec::new();
+        while let Ok(msg) = self.transport.msg_receiver.try_recv() {
+            match msg {
+                LspTransportMessage::Message(value) => self.handle_message(value, &mut events),
+                LspTransportMessage::Error(err) => log!("Language server error: {}", err),
+                LspTransportMessage::Exited => events.push(LspEvent::Exited),
+            }
+        }
+        events
+    }
+
+    fn handle_message(&mut self, value: JsonValue, events: &mut Vec<LspEvent>) {
+        match (value.key("id"), value.key("method")) {
+            // a request from the server, which we answer as well as we can
+            (Some(id), Some(method)) => {
+                let method = method.string().map(|s| s.as_str()).unwrap_or("");
+                let message = if method == "workspace/configuration" {
+                    let count = value
+                        .key("params")
+                        .and_then(|params| params.key("items"))
+                        .and_then(as_array)
+                        .map_or(0, |items| items.len());
+                    response(id, |w| {
+                        w.begin_array();
+                        for _ in 0..count {
+                            w.null();
+                        }
+                        w.end_array();
+                    })
+                } else {
+                    response(id, |w| w.null())
+                };
+                self.transport.send(message);
+            }
+            (None, Some(method)) => {
+                if method.string().map(|s| s.as_str()) == Some("textDocument/publishDiagnostics") {
+                    if let Some(event) = value
+                        .key("params")
+                        .and_then(parse_diagnostics)
+                        .and_then(|(uri, diagnostics)| self.diagnostics_event(&uri, diagnostics))
+                    {
+                        events.push(event);
+                    }
+                }
+            }
+            (Some(id), None) => {
+                let Some(request_id) = as_u64(id) else {
+                    return;
+                };
+                let Some(request) = self.pending_requests.remove(&request_id) else {
+                    return;
+                };
+                if let Some(error) = value.key("error") {
+                    let message = error.key("message").and_then(|m| m.string());
+                    log!("Language server request failed: {}", message.map_or("", |m| m.as_str()));
+                    return;
+                }
+                let result = value.key("result").unwrap_or(&JsonValue::Null);
+                self.handle_response(request_id, request, result, events);
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn handle_response(
+        &mut self,
+        request_id: u64,
+        request: LspRequest,
+        result: &JsonValue,
+        events: &mut Vec<LspEvent>,
+    ) {
+        match request {
+            LspRequest::Initialize => {
+                self.is_initialized = true;
+                self.transport
+                    .send(notification("initialized", write_empty_object));
+                for message in std::mem::take(&mut self.queued_messages) {
+                    self.transport.send(message);
+                }
+            }
+            LspRequest::Hover => {
+                if let Some(text) = parse_hover(result) {
+                    events.push(LspEvent::Hover { request_id, text });
+                }
+            }
+            LspRequest::Definition => {
+                if let Some(location) = parse_locations(result).into_iter().next() {
+                    if let Some(path) = uri_to_path(&location.uri) {
+                        let position = self.from_lsp_position(&location.uri, &path, location.range.start);
+                        events.push(LspEvent::Definition {
+                            request_id,
+                            path,
+                            position,
+                        });
+                    }
+                }
+            }
+            LspRequest::Completion => {
+                let items = parse_completions(result)
+                    .into_iter()
+                    .map(|item| CompletionItem {
+                        label: item.label,
+                        detail: item.detail,
+                        insert_text: item.insert_text,
+                    })
+                    .collect();
+                events.push(LspEvent::Completion { request_id, items });
+            }
+            LspRequest::Shutdown => {}
+        }
+    }
+
+    fn diagnostics_event(&self, uri: &str, diagnostics: Vec<LspDiagnostic>) -> Option<LspEvent> {
+        let path = uri_to_path(uri)?;
+        let text = self.text_for(uri, &path);
+        let diagnostics = diagnostics
+            .into_iter()
+            .filter_map(|diagnostic| {
+                let ty = match diagnostic.severity {
+                    LspSeverity::Error => DecorationType::Error,
+                    LspSeverity::Warning => DecorationType::Warning,
+                    LspSeverity::Information | LspSeverity::Hint => return None,
+                };
+                let start = from_lsp_position(text.as_ref(), diagnostic.range.start);
+                let end = from_lsp_position(text.as_ref(), diagnostic.range.end);
+                Some((start, end, ty))
+            })
+            .collect();
+        Some(LspEvent::Diagnostics { path, diagnostics })
+    }
+
+    fn from_lsp_position(&self, uri: &str, path: &Path, position: LspPosition) -> Position {
+        from_lsp_position(self.text_for(uri, path).as_ref(), position)
+    }
+
+    // Positions in files we don't track are converted with the text on disk.
+    fn text_for(&self, uri: &str, path: &Path) -> Option<Text> {
+        match self.documents.get(uri) {
+            Some(document) => Some(document.text.clone()),
+            None => std::fs::read_to_string(path).ok().map(Text::from),
+        }
+    }
+
+    fn text_document_request(
+        &mut self,
+        path: &Path,
+        position: Position,
+        method: &str,
+        request_kind: LspRequest,
+    ) -> Option<u64> {
+        let uri = path_to_uri(path);
+        let position = to_lsp_position(&self.documents.get(&uri)?.text, position);
+        let id = self.next_request_id();
+        self.pending_requests.insert(id, request_kind);
+        self.send(request(id, method, |w| {
+            write_text_document_position(w, &uri, position)
+        }));
+        Some(id)
+    }
+
+    fn next_request_id(&mut self) -> u64 {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        id
+    }
+
+    // Nothing but the initialize request may be sent before the server has answered it.
+    fn send(&mut self, message: String) {
+        if self.is_initialized {
+            self.transport.send(message);
+        } else {
+            self.queued_messages.push(message);
+        }
+    }
+}
+
+// Turns edits into the ranges and texts of `didChange`, applying each edit to `text` so the next
+// one is converted against the text it applies to.
+fn edits_to_changes(text: &mut Text, edits: &[Edit]) -> Vec<(LspRange, String)> {
+    let mut changes = Vec::new();
+    for edit in edits {
+        let (start, end, string) = match &edit.change {
+            Change::Insert(position, text) => (*position, *position, text.to_string()),
+            Change::Delete(start, length) => (*start, *start + *length, String::new()),
+        };
+        let range = LspRange {
+            start: to_lsp_position(text, start),
+            end: to_lsp_position(text, end),
+        };
+        changes.push((range, string));
+        text.apply_change(edit.change.clone());
+    }
+    changes
+}
+
+fn to_lsp_position(text: &Text, position: Position) -> LspPosition {
+    let character = match text.as_lines().get(position.line_index) {
+        Some(line) => byte_to_utf16_index(line, position.byte_index),
+        None => position.byte_index,
+    };
+    LspPosition {
+        line: position.line_index,
+        character,
+    }
+}
+
+fn from_lsp_position(text: Option<&Text>, position: LspPosition) -> Position {
+    let byte_index = match text.and_then(|text| text.as_lines().get(position.line)) {
+        Some(line) => utf16_to_byte_index(line, position.character),
+        None => position.character,
+    };
+    Position {
+        line_index: position.line,
+        byte_index,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::makepad_code_editor::text::{Drift, Length},
+        std::{
+            io::{BufReader, Read, Write},
+            thread,
+            time::{Duration, Instant},
+        },
+    };
+
+    fn position(line: usize, character: usize) -> LspPosition {
+        LspPosition { line, character }
+    }
+
+    #[test]
+    fn edits_become_utf16_ranges_against_the_text_before_each_edit() {
+        let mut text = Text::from("aé\n😀b");
+        let edits = [
+            Change::Insert(
+                Position {
+                    line_index: 1,
+                    byte_index: 4,
+                },
+                Text::from("x"),
+            ),
+            Change::Delete(
+                Position {
+                    line_index: 0,
+                    byte_index: 1,
+                },
+                Length {
+                    line_count: 1,
+                    byte_count: 4,
+                },
+            ),
+        ]
+        .map(|change| Edit {
+            change,
+            drift: Drift::Before,
+        });
+        let changes = edits_to_changes(&mut text, &edits);
+        assert_eq!(
+            changes,
+            [
+                (
+                    LspRange {
+                        start: position(1, 2),
+                        end: position(1, 2)
+                    },
+                    "x".to_string()
+                ),
+                (
+                    LspRange {
+                        start: position(0, 1),
+                        end: position(1, 2)
+                    },
+                    String::new()
+                ),
+            ]
+        );
+        assert_eq!(text.to_string(), "axb");
+    }
+
+    // Answers the requests of a client the way a language server would, and returns the methods
+    // it received.
+    fn fake_server(reader: impl Read, mut writer: impl Write) -> Vec<String> {
+        let mut reader = BufReader::new(reader);
+        let mut send = |body: String| {
+            write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+            writer.flush().unwrap();
+        };
+        let mut methods = Vec::new();
+        while let Some(body) = read_message(&mut reader).unwrap() {
+            let value = JsonReader::new(&body).read_value().unwrap();
+            let Some(method) = value.key("method").and_then(|method| method.string()) else {
+                // The answer to our configuration request.
+                assert_eq!(as_array(value.key("result").unwrap()).unwrap().len(), 2);
+                methods.push("response".to_string());
+                continue;
+            };
+            methods.push(method.clone());
+            let params = value.key("params");
+            match method.as_str() {
+                "initialize" => send(response(value.key("id").unwrap(), |w| {
+                    w.begin_object();
+                    w.key("capabilities");
+                    write_empty_object(w);
+                    w.end_object();
+                })),
+                "initialized" => send(request(99, "workspace/configuration", |w| {
+                    w.begin_object();
+                    w.key("items");
+                    w.begin_array();
+                    write_empty_object(w);
+                    write_empty_object(w);
+                    w.end_array();
+                    w.end_object();
+                })),
+                "textDocument/didOpen" => {
+                    let uri = params.unwrap().key("textDocument").unwrap().key("uri").unwrap();
+                    let uri = uri.string().unwrap().clone();
+                    send(notification("textDocument/publishDiagnostics", |w| {
+                        w.begin_object();
+                        w.key("uri");
+                        w.string(&uri);
+                        w.key("diagnostics");
+                        w.begin_array();
+                        w.begin_object();
+                        w.key("range");
+                        write_range(w, LspRange {
+                            start: position(0, 3),
+                            end: position(0, 4),
+                        });
+                        w.key("severity");
+                        w.u64(2);
+                        w.key("message");
+                        w.string("non-ASCII name");
+                        w.end_object();
+                        w.end_array();
+                        w.end_object();
+                    }));
+                }
+                "textDocument/hover" => {
+                    let position = parse_position(params.unwrap().key("position").unwrap());
+                    assert_eq!(position, Some(LspPosition { line: 0, character: 5 }));
+                    send(response(value.key("id").unwrap(), |w| {
+                        w.begin_object();
+                        w.key("contents");
+                        w.begin_object();
+                        w.key("kind");
+                        w.string("markdown");
+                        w.key("value");
+                        w.string("```rust\nfn é()\n```");
+                        w.end_object();
+                        w.end_object();
+                    }));
+                }
+                "shutdown" => send(response(value.key("id").unwrap(), |w| w.null())),
+                _ => {}
+            }
+        }
+        methods
+    }
+
+    #[test]
+    fn round_trip_with_a_language_server() {
+        let (client_reader, server_writer) = std::io::pipe().unwrap();
+        let (server_reader, client_writer) = std::io::pipe().unwrap();
+        let server = thread::spawn(move || fake_server(server_reader, server_writer));
+
+        let root_path = std::env::temp_dir();
+        let path = root_path.join("main.rs");
+        let transport = LspTransport::from_streams(client_writer, client_reader);
+        let mut client = LspClient::with_transport(transport, &root_path);
+        client.open_document(&path, &Text::from("fn é() {}\n"));
+        let hover_id = client
+            .hover(
+                &path,
+                Position {
+                    line_index: 0,
+                    byte_index: 6,
+                },
+            )
+            .unwrap();
+
+        let mut hover = None;
+        let mut diagnostics = None;
+        let start = Instant::now();
+        while (hover.is_none() || diagnostics.is_none()) && start.elapsed() < Duration::from_secs(5) {
+            for event in client.handle_messages() {
+                match event {
+                    LspEvent::Hover { request_id, text } => hover = Some((request_id, text)),
+                    LspEvent::Diagnostics {
+                        path,
+                        diagnostics: events,
+                    } => diagnostics = Some((path, events)),
+                    _ => panic!("unexpected event"),
+                }
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(hover, Some((hover_id, "fn é()".to_string())));
+        let (diagnostics_path, diagnostics) = diagnostics.unwrap();
+        assert_eq!(diagnostics_path, path);
+        assert_eq!(
+            diagnostics,
+            [(
+                Position {
+                    line_index: 0,
+                    byte_index: 3
+                },
+                Position {
+                    line_index: 0,
+                    byte_index: 5
+                },
+                DecorationType::Warning
+            )]
+        );
+
+        client.shutdown();
+        let mut methods = server.join().unwrap();
+        // The answer to the configuration request races with the queued messages.
+        methods.retain(|method| method != "response");
+        assert_eq!(
+            methods,
+            [
+                "initialize",
+                "initialized",
+                "textDocument/didOpen",
+                "textDocument/hover",
+                "shutdown",
+                "exit"
+            ]
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use {
+    crate::{
+        app::AppAction,
+        file_system::file_system::{FileSystem, OpenDocument},
+        lsp::lsp_client::{LspClient, LspEvent},
+        makepad_code_editor::{
+            code_editor::CompletionItem,
+            decoration::Decoration,
+            text::{Edit, Position},
+        },
+        makepad_platform::studio::JumpToFile,
+        makepad_widgets::*,
+    },
+    std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        sync::mpsc::Receiver,
+    },
+};
+
+// Decorations from the language server carry this id, so they can be replaced without touching
+// the ones the build produced.
+pub const LSP_DECORATION_ID: usize = 1;
+
+#[derive(DefaultNone, Debug, Clone)]
+pub enum LspAction {
+    ShowHover {
+        tab_id: LiveId,
+        position: Position,
+        text: String,
+    },
+    ShowCompletions {
+        tab_id: LiveId,
+        position: Position,
+        items: Vec<CompletionItem>,
+    },
+    None,
+}
+
+#[derive(Default)]
+pub struct LspManager {
+    client: Option<LspClient>,
+    root_path: PathBuf,
+    edit_receivers: HashMap<LiveId, Receiver<Vec<Edit>>>,
+    requests: HashMap<u64, (LiveId, Position)>,
+}
+
+impl LspManager {
+    // The server is rust-analyzer, unless another one is passed with --lsp="cmd args". An empty
+    // --lsp= turns the language server off.
+    pub fn init(&mut self, _cx: &mut Cx, root_path: &Path) {
+        let mut command = "rust-analyzer".to_string();
+        for arg in std::env::args() {
+            if let Some(prefix) = arg.strip_prefix("--lsp=") {
+                command = prefix.to_string();
+                break;
+            }
+        }
+        let mut parts = command.split_whitespace().map(|s| s.to_string());
+        let Some(cmd) = parts.next() else {
+            return;
+        };
+        let args: Vec<String> = parts.collect();
+        self.root_path = root_path
+            .canonicalize()
+            .unwrap_or_else(|_| root_path.to_path_buf());
+        match LspClient::start(&cmd, &args, &self.root_path) {
+            Ok(client) => self.client = Some(client),
+            Err(err) => log!("Cannot start language server {}: {}", cmd, err),
+        }
+    }
+
+    // Opens newly loaded documents on the server, closes the ones that went away, and sends the
+    // edits made since the last call.
+    pub fn sync_documents(&mut self, file_system: &FileSystem) {
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        self.edit_receivers.retain(|file_id, _| {
+            let is_open = matches!(file_system.open_documents.get(file_id), Some(OpenDocument::Code(_)));
+            if !is_open {
+                if let Some(path) = document_path(&self.root_path, file_system, *file_id) {
+                    client.close_document(&path);
+                }
+            }
+            is_open
+        });
+        for (file_id, document) in &file_system.open_documents {
+            let OpenDocument::Code(document) = document else {
+                continue;
+            };
+            let Some(path) = document_path(&self.root_path, file_system, *file_id) else {
+                continue;
+            };
+            if !self.edit_receivers.contains_key(file_id) {
+                if !is_supported_file(&path) {
+                    continue;
+                }
+                client.open_document(&path, &document.as_text());
+                self.edit_receivers.insert(*file_id, document.add_edit_listener());
+                continue;
+            }
+            let edits: Vec<Edit> = self.edit_receivers[file_id].try_iter().flatten().collect();
+            client.change_document(&path, &edits);
+        }
+    }
+
+    pub fn hover(&mut self, file_system: &FileSystem, tab_id: LiveId, position: Position) {
+        self.request(file_system, tab_id, position, LspClient::hover)
+    }
+
+    pub fn go_to_definition(&mut self, file_system: &FileSystem, tab_id: LiveId, position: Position) {
+        self.request(file_system, tab_id, position, LspClient::definition)
+    }
+
+    pub fn complete(&mut self, file_system: &FileSystem, tab_id: LiveId, position: Position) {
+        self.request(file_system, tab_id, position, LspClient::completion)
+    }
+
+    fn request(
+        &mut self,
+        file_system: &FileSystem,
+        tab_id: LiveId,
+        position: Position,
+        send: impl FnOnce(&mut LspClient, &Path, Position) -> Option<u64>,
+    ) {
+        self.sync_documents(file_system);
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        let Some(path) = file_system
+            .tab_id_to_file_node_id
+            .get(&tab_id)
+            .and_then(|file_id| document_path(&self.root_path, file_system, *file_id))
+        else {
+            return;
+        };
+        if let Some(request_id) = send(client, &path, position) {
+            self.requests.insert(request_id, (tab_id, position));
+        }
+    }
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &Event, file_system: &mut FileSystem) {
+        self.sync_documents(file_system);
+        if let Event::Signal = event {
+            let Some(client) = &mut self.client else {
+                return;
+            };
+            for event in client.handle_messages() {
+                match event {
+                    LspEvent::Hover { request_id, text } => {
+                        if let Some((tab_id, position)) = self.requests.remove(&request_id) {
+                            cx.action(LspAction::ShowHover {
+                                tab_id,
+                                position,
+                                text,
+                            });
+                        }
+                    }
+                    LspEvent::Completion { request_id, items } => {
+                        if let Some((tab_id, position)) = self.requests.remove(&request_id) {
+                            cx.action(LspAction::ShowCompletions {
+                                tab_id,
+                                position,
+                                items,
+                            });
+                        }
+                    }
+                    LspEvent::Definition {
+                        request_id,
+                        path,
+                        position,
+                    } => {
+                        if self.requests.remove(&request_id).is_none() {
+                            continue;
+                        }
+                        if let Some(file_name) = self.relative_path(&path) {
+                            cx.action(AppAction::JumpTo(JumpToFile {
+                                file_name,
+                                line: position.line_index as u32,
+                                column: position.byte_index as u32,
+                            }));
+                        }
+                    }
+                    LspEvent::Diagnostics { path, diagnostics } => {
+                        let Some(file_id) = self
+                            .relative_path(&path)
+                            .and_then(|path| file_system.path_to_file_node_id(&path))
+                        else {
+                            continue;
+                        };
+                        file_system.clear_decorations_with_id(&file_id, LSP_DECORATION_ID);
+                        for (start, end, ty) in diagnostics {
+                            file_system.add_decoration(
+                                file_id,
+                                Decoration::new(LSP_DECORATION_ID, start, end, ty),
+                            );
+                        }
+                        cx.action(AppAction::RedrawFile(file_id));
+                    }
+                    LspEvent::Exited => {
+                        log!("Language server exited");
+                        self.client = None;
+                        self.edit_receivers.clear();
+                        self.requests.clear();
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let path = path.strip_prefix(&self.root_path).ok()?;
+        Some(
+            path.components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+}
+
+fn document_path(root_path: &Path, file_system: &FileSystem, file_id: LiveId) -> Option<PathBuf> {
+    file_system.file_nodes.get(&file_id)?;
+    Some(root_path.join(file_system.file_node_path(file_id)))
+}
+
+fn is_supported_file(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext == "rs")
+}
@@ -0,0 +1,510 @@
+use {
+    crate::makepad_micro_serde::*,
+    std::path::{Path, PathBuf},
+};
+
+// The small subset of the language server protocol that studio speaks. Outgoing messages are
+// built with a JsonWriter, incoming ones are picked apart from a JsonValue.
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LspPosition {
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LspRange {
+    pub start: LspPosition,
+    pub end: LspPosition,
+}
+
+#[derive(Clone, Debug)]
+pub struct LspLocation {
+    pub uri: String,
+    pub range: LspRange,
+}
+
+#[derive(Clone, Debug)]
+pub struct LspCompletionItem {
+    pub label: String,
+    pub detail: Option<String>,
+    pub insert_text: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LspSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+#[derive(Clone, Debug)]
+pub struct LspDiagnostic {
+    pub range: LspRange,
+    pub severity: LspSeverity,
+    pub message: String,
+}
+
+pub fn request(id: u64, method: &str, params: impl FnOnce(&mut JsonWriter)) -> String {
+    let mut w = JsonWriter::new();
+    w.begin_object();
+    w.key("jsonrpc");
+    w.string("2.0");
+    w.key("id");
+    w.u64(id);
+    w.key("method");
+    w.string(method);
+    w.key("params");
+    params(&mut w);
+    w.end_object();
+    w.take()
+}
+
+pub fn request_without_params(id: u64, method: &str) -> String {
+    let mut w = JsonWriter::new();
+    w.begin_object();
+    w.key("jsonrpc");
+    w.string("2.0");
+    w.key("id");
+    w.u64(id);
+    w.key("method");
+    w.string(method);
+    w.end_object();
+    w.take()
+}
+
+pub fn notification(method: &str, params: impl FnOnce(&mut JsonWriter)) -> String {
+    let mut w = JsonWriter::new();
+    w.begin_object();
+    w.key("jsonrpc");
+    w.string("2.0");
+    w.key("method");
+    w.string(method);
+    w.key("params");
+    params(&mut w);
+    w.end_object();
+    w.take()
+}
+
+pub fn notification_without_params(method: &str) -> String {
+    let mut w = JsonWriter::new();
+    w.begin_object();
+    w.key("jsonrpc");
+    w.string("2.0");
+    w.key("method");
+    w.string(method);
+    w.end_object();
+    w.take()
+}
+
+pub fn response(id: &JsonValue, result: impl FnOnce(&mut JsonWriter)) -> String {
+    let mut w = JsonWriter::new();
+    w.begin_object();
+    w.key("jsonrpc");
+    w.string("2.0");
+    w.key("id");
+    w.value(id);
+    w.key("result");
+    result(&mut w);
+    w.end_object();
+    w.take()
+}
+
+pub fn write_initialize_params(w: &mut JsonWriter, root_uri: &str, root_name: &str) {
+    w.begin_object();
+    w.key("processId");
+    w.u64(std::process::id() as u64);
+    w.key("rootUri");
+    w.string(root_uri);
+    w.key("workspaceFolders");
+    w.begin_array();
+    w.begin_object();
+    w.key("uri");
+    w.string(root_uri);
+    w.key("name");
+    w.string(root_name);
+    w.end_object();
+    w.end_array();
+    w.key("capabilities");
+    w.begin_object();
+    w.key("textDocument");
+    w.begin_object();
+    w.key("synchronization");
+    w.begin_object();
+    w.key("didSave");
+    w.bool(false);
+    w.end_object();
+    w.key("hover");
+    w.begin_object();
+    w.key("contentFormat");
+    w.begin_array();
+    w.string("plaintext");
+    w.string("markdown");
+    w.end_array();
+    w.end_object();
+    w.key("definition");
+    w.begin_object();
+    w.key("linkSupport");
+    w.bool(true);
+    w.end_object();
+    w.key("completion");
+    w.begin_object();
+    w.key("completionItem");
+    w.begin_object();
+    w.key("snippetSupport");
+    w.bool(false);
+    w.end_object();
+    w.end_object();
+    w.key("publishDiagnostics");
+    w.begin_object();
+    w.key("relatedInformation");
+    w.bool(false);
+    w.end_object();
+    w.end_object();
+    w.key("general");
+    w.begin_object();
+    w.key("positionEncodings");
+    w.begin_array();
+    w.string("utf-16");
+    w.end_array();
+    w.end_object();
+    w.end_object();
+    w.end_object();
+}
+
+pub fn write_empty_object(w: &mut JsonWriter) {
+    w.begin_object();
+    w.end_object();
+}
+
+pub fn write_position(w: &mut JsonWriter, position: LspPosition) {
+    w.begin_object();
+    w.key("line");
+    w.u64(position.line as u64);
+    w.key("character");
+    w.u64(position.character as u64);
+    w.end_object();
+}
+
+pub fn write_range(w: &mut JsonWriter, range: LspRange) {
+    w.begin_object();
+    w.key("start");
+    write_position(w, range.start);
+    w.key("end");
+    write_position(w, range.end);
+    w.end_object();
+}
+
+pub fn write_text_document_position(w: &mut JsonWriter, uri: &str, position: LspPosition) {
+    w.begin_object();
+    w.key("textDocument");
+    w.begin_object();
+    w.key("uri");
+    w.string(uri);
+    w.end_object();
+    w.key("position");
+    write_position(w, position);
+    w.end_object();
+}
+
+pub fn write_did_open_params(w: &mut JsonWriter, uri: &str, language_id: &str, version: i64, text: &str) {
+    w.begin_object();
+    w.key("textDocument");
+    w.begin_object();
+    w.key("uri");
+    w.string(uri);
+    w.key("languageId");
+    w.string(language_id);
+    w.key("version");
+    w.i64(version);
+    w.key("text");
+    w.string(text);
+    w.end_object();
+    w.end_object();
+}
+
+pub fn write_did_change_params(w: &mut JsonWriter, uri: &str, version: i64, changes: &[(LspRange, String)]) {
+    w.begin_object();
+    w.key("textDocument");
+    w.begin_object();
+    w.key("uri");
+    w.string(uri);
+    w.key("version");
+    w.i64(version);
+    w.end_object();
+    w.key("contentChanges");
+    w.begin_array();
+    for (range, text) in changes {
+        w.begin_object();
+        w.key("range");
+        write_range(w, *range);
+        w.key("text");
+        w.string(text);
+        w.end_object();
+    }
+    w.end_array();
+    w.end_object();
+}
+
+pub fn write_did_close_params(w: &mut JsonWriter, uri: &str) {
+    w.begin_object();
+    w.key("textDocument");
+    w.begin_object();
+    w.key("uri");
+    w.string(uri);
+    w.end_object();
+    w.end_object();
+}
+
+pub fn as_u64(value: &JsonValue) -> Option<u64> {
+    match value {
+        JsonValue::U64(v) => Some(*v),
+        JsonValue::I64(v) if *v >= 0 => Some(*v as u64),
+        JsonValue::F64(v) if *v >= 0.0 => Some(*v as u64),
+        _ => None,
+    }
+}
+
+pub fn as_array(value: &JsonValue) -> Option<&[JsonValue]> {
+    if let JsonValue::Array(array) = value {
+        return Some(array);
+    }
+    None
+}
+
+pub fn parse_position(value: &JsonValue) -> Option<LspPosition> {
+    Some(LspPosition {
+        line: as_u64(value.key("line")?)? as usize,
+        character: as_u64(value.key("character")?)? as usize,
+    })
+}
+
+pub fn parse_range(value: &JsonValue) -> Option<LspRange> {
+    Some(LspRange {
+        start: parse_position(value.key("start")?)?,
+        end: parse_position(value.key("end")?)?,
+    })
+}
+
+// Hover contents are either MarkupContent, a MarkedString, or an array of MarkedStrings.
+pub fn parse_hover(result: &JsonValue) -> Option<String> {
+    fn marked_string(value: &JsonValue) -> Option<String> {
+        if let Some(s) = value.string() {
+            return Some(s.clone());
+        }
+        value.key("value")?.string().cloned()
+    }
+    let contents = result.key("contents")?;
+    let text = if let Some(array) = as_array(contents) {
+        array
+            .iter()
+            .filter_map(marked_string)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    } else {
+        marked_string(contents)?
+    };
+    let text = strip_markdown_fences(&text);
+    if text.trim().is_empty() {
+        return None;
+    }
+    Some(text)
+}
+
+// The popup draws plain text, so the code fences around signatures are just noise.
+fn strip_markdown_fences(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+// A definition result is a Location, an array of Locations, or an array of LocationLinks.
+pub fn parse_locations(result: &JsonValue) -> Vec<LspLocation> {
+    fn location(value: &JsonValue) -> Option<LspLocation> {
+        if let Some(uri) = value.key("targetUri") {
+            return Some(LspLocation {
+                uri: uri.string()?.clone(),
+                range: parse_range(value.key("targetSelectionRange")?)?,
+            });
+        }
+        Some(LspLocation {
+            uri: value.key("uri")?.string()?.clone(),
+            range: parse_range(value.key("range")?)?,
+        })
+    }
+    match as_array(result) {
+        Some(array) => array.iter().filter_map(location).collect(),
+        None => location(result).into_iter().collect(),
+    }
+}
+
+// A completion result is either an array of CompletionItems or a CompletionList.
+pub fn parse_completions(result: &JsonValue) -> Vec<LspCompletionItem> {
+    fn item(value: &JsonValue) -> Option<LspCompletionItem> {
+        let label = value.key("label")?.string()?.clone();
+        let detail = value.key("detail").and_then(|v| v.string()).cloned();
+        let insert_text = value
+            .key("textEdit")
+            .and_then(|edit| edit.key("newText"))
+            .or_else(|| value.key("insertText"))
+            .and_then(|v| v.string())
+            .cloned()
+            .unwrap_or_else(|| label.clone());
+        Some(LspCompletionItem {
+            label,
+            detail,
+            insert_text,
+        })
+    }
+    let items = match as_array(result) {
+        Some(array) => array,
+        None => match result.key("items").and_then(as_array) {
+            Some(array) => array,
+            None => return Vec::new(),
+        },
+    };
+    items.iter().filter_map(item).collect()
+}
+
+pub fn parse_diagnostics(params: &JsonValue) -> Option<(String, Vec<LspDiagnostic>)> {
+    let uri = params.key("uri")?.string()?.clone();
+    let diagnostics = as_array(params.key("diagnostics")?)?
+        .iter()
+        .filter_map(|value| {
+            Some(LspDiagnostic {
+                range: parse_range(value.key("range")?)?,
+                severity: match value.key("severity").and_then(as_u64) {
+                    Some(1) | None => LspSeverity::Error,
+                    Some(2) => LspSeverity::Warning,
+                    Some(3) => LspSeverity::Information,
+                    _ => LspSeverity::Hint,
+                },
+                message: value
+                    .key("message")
+                    .and_then(|v| v.string())
+                    .cloned()
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+    Some((uri, diagnostics))
+}
+
+// Positions in the protocol count UTF-16 code units, the editor counts bytes.
+pub fn utf16_to_byte_index(line: &str, character: usize) -> usize {
+    let mut utf16_index = 0;
+    for (byte_index, char) in line.char_indices() {
+        if utf16_index >= character {
+            return byte_index;
+        }
+        utf16_index += char.len_utf16();
+    }
+    line.len()
+}
+
+pub fn byte_to_utf16_index(line: &str, byte_index: usize) -> usize {
+    line[..byte_index.min(line.len())]
+        .chars()
+        .map(|char| char.len_utf16())
+        .sum()
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut iter = path.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // windows paths come in as /C:/dir/file.rs
+    if cfg!(windows) {
+        if let Some(stripped) = path.strip_prefix('/') {
+            if stripped.as_bytes().get(1) == Some(&b':') {
+                return Some(PathBuf::from(stripped));
+            }
+        }
+    }
+    Some(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 'é' is two bytes and one UTF-16 unit, '😀' is four bytes and two UTF-16 units.
+    const LINE: &str = "aé😀b";
+
+    #[test]
+    fn converts_byte_indices_to_utf16() {
+        let expected = [(0, 0), (1, 1), (3, 2), (7, 4), (8, 5), (100, 5)];
+        for (byte_index, utf16_index) in expected {
+            assert_eq!(byte_to_utf16_index(LINE, byte_index), utf16_index);
+        }
+    }
+
+    #[test]
+    fn converts_utf16_indices_to_bytes() {
+        // An index in the middle of a surrogate pair ends up after the char.
+        let expected = [(0, 0), (1, 1), (2, 3), (3, 7), (4, 7), (5, 8), (100, 8)];
+        for (utf16_index, byte_index) in expected {
+            assert_eq!(utf16_to_byte_index(LINE, utf16_index), byte_index);
+        }
+        for (byte_index, _) in LINE.char_indices() {
+            assert_eq!(
+                utf16_to_byte_index(LINE, byte_to_utf16_index(LINE, byte_index)),
+                byte_index
+            );
+        }
+    }
+
+    #[test]
+    fn writes_did_change_params() {
+        let range = LspRange {
+            start: LspPosition { line: 1, character: 2 },
+            end: LspPosition { line: 3, character: 4 },
+        };
+        let mut w = JsonWriter::new();
+        write_did_change_params(&mut w, "file:///a.rs", 7, &[(range, "é\n".to_string())]);
+        let value = JsonReader::new(&w.take()).read_value().unwrap();
+        assert_eq!(as_u64(value.key("textDocument").unwrap().key("version").unwrap()), Some(7));
+        let changes = as_array(value.key("contentChanges").unwrap()).unwrap();
+        assert_eq!(parse_range(changes[0].key("range").unwrap()), Some(range));
+        assert_eq!(changes[0].key("text").unwrap().string().unwrap(), "é\n");
+    }
+
+    #[test]
+    fn converts_paths_to_uris_and_back() {
+        let path = Path::new("/home/user/my project/é.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///home/user/my%20project/%C3%A9.rs");
+        assert_eq!(uri_to_path(&uri).unwrap(), path);
+        assert_eq!(uri_to_path("https://example.com"), None);
+    }
+}
@@ -0,0 +1,248 @@
+use {
+    crate::{
+        makepad_micro_serde::*,
+        makepad_platform::*,
+    },
+    std::{
+        io::{self, prelude::*, BufReader},
+        path::Path,
+        process::{Child, Command, Stdio},
+        sync::mpsc::{self, Sender},
+        thread,
+    },
+};
+
+// Runs a language server as a child process and frames JSON-RPC messages over its stdio. Every
+// message is preceded by a `Content-Length` header, so we can't use the line based ChildProcess.
+
+pub enum LspTransportMessage {
+    Message(JsonValue),
+    Error(String),
+    Exited,
+}
+
+pub struct LspTransport {
+    child: Option<Child>,
+    stdin_sender: Sender<Option<String>>,
+    pub msg_receiver: ToUIReceiver<LspTransportMessage>,
+}
+
+impl LspTransport {
+    pub fn start(cmd: &str, args: &[String], current_dir: &Path) -> Result<Self, io::Error> {
+        let mut child = Command::new(cmd)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .current_dir(current_dir)
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin cannot be taken!");
+        let stdout = child.stdout.take().expect("stdout cannot be taken!");
+        let stderr = child.stderr.take().expect("stderr cannot be taken!");
+
+        // language servers log to stderr, which blocks them once the pipe is full
+        let _stderr_thread = thread::spawn(move || {
+            let mut reader = BufReader::new(stderr);
+            let mut line = String::new();
+            while let Ok(len) = reader.read_line(&mut line) {
+                if len == 0 {
+                    break;
+                }
+                line.clear();
+            }
+        });
+
+        let mut transport = Self::from_streams(stdin, stdout);
+        transport.child = Some(child);
+        Ok(transport)
+    }
+
+    // Frames messages over the given streams without a child process, which is how the tests run
+    // a language server in process.
+    pub fn from_streams(
+        mut stdin: impl Write + Send + 'static,
+        stdout: impl Read + Send + 'static,
+    ) -> Self {
+        let msg_receiver = ToUIReceiver::default();
+        let (stdin_sender, stdin_receiver) = mpsc::channel::<Option<String>>();
+
+        let _stdout_thread = {
+            let msg_sender = msg_receiver.sender();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stdout);
+                loop {
+                    match read_message(&mut reader) {
+                        Ok(Some(body)) => {
+                            let msg = match JsonReader::new(&body).read_value() {
+                                Ok(value) => LspTransportMessage::Message(value),
+                                Err(err) => LspTransportMessage::Error(format!(
+                                    "Cannot parse language server message {:?}",
+                                    err
+                                )),
+                            };
+                            if msg_sender.send(msg).is_err() {
+                                break;
+                            }
+                        }
+                        Ok(None) => {
+                            let _ = msg_sender.send(LspTransportMessage::Exited);
+                            break;
+                        }
+                        Err(err) => {
+                            let _ = msg_sender.send(LspTransportMessage::Error(err.to_string()));
+                            let _ = msg_sender.send(LspTransportMessage::Exited);
+                            break;
+                        }
+                    }
+                }
+            })
+        };
+
+        let _stdin_thread = thread::spawn(move || {
+            while let Ok(Some(body)) = stdin_receiver.recv() {
+                let header = format!("Content-Length: {}\r\n\r\n", body.len());
+                if stdin.write_all(header.as_bytes()).is_err()
+                    || stdin.write_all(body.as_bytes()).is_err()
+                    || stdin.flush().is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            child: None,
+            stdin_sender,
+            msg_receiver,
+        }
+    }
+
+    pub fn send(&self, body: String) {
+        let _ = self.stdin_sender.send(Some(body));
+    }
+
+    // Closes stdin once the queued messages are written, and reaps the server when it exits.
+    pub fn close(self) {
+        let _ = self.stdin_sender.send(None);
+        if let Some(mut child) = self.child {
+            thread::spawn(move || {
+                let _ = child.wait();
+            });
+        }
+    }
+}
+
+// Reads one framed message, or returns None when the stream is closed.
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<String>, io::Error> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; content_length.unwrap()];
+    reader.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::time::Duration,
+    };
+
+    // Hands out at most `chunk_len` bytes per read, like a pipe that is written in small pieces.
+    struct Chunked<'a> {
+        bytes: &'a [u8],
+        chunk_len: usize,
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.chunk_len).min(self.bytes.len());
+            buf[..len].copy_from_slice(&self.bytes[..len]);
+            self.bytes = &self.bytes[len..];
+            Ok(len)
+        }
+    }
+
+    fn framed(body: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    #[test]
+    fn reads_consecutive_messages() {
+        let stream = format!(
+            "{}content-length: 10\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{{\"b\":\"é\"}}{}",
+            framed("{\"a\":1}"),
+            framed("[]")
+        );
+        let mut reader = stream.as_bytes();
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("{\"a\":1}"));
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("{\"b\":\"é\"}"));
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("[]"));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn reads_messages_split_across_reads() {
+        let stream = format!("{}{}", framed("{\"id\":1}"), framed("{\"id\":22}"));
+        for chunk_len in 1..8 {
+            let mut reader = BufReader::with_capacity(
+                3,
+                Chunked {
+                    bytes: stream.as_bytes(),
+                    chunk_len,
+                },
+            );
+            assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("{\"id\":1}"));
+            assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("{\"id\":22}"));
+            assert_eq!(read_message(&mut reader).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn waits_for_partial_headers_on_a_pipe() {
+        let (reader, mut writer) = io::pipe().unwrap();
+        let writer_thread = thread::spawn(move || {
+            for piece in ["Content-Le", "ngth: 5\r", "\n\r", "\nhel", "lo"] {
+                writer.write_all(piece.as_bytes()).unwrap();
+                writer.flush().unwrap();
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+        let mut reader = BufReader::new(reader);
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("hello"));
+        writer_thread.join().unwrap();
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn reports_broken_streams() {
+        // A stream that closes in the headers is a stream that closed.
+        assert_eq!(read_message(&mut "Content-Length: 5\r\n".as_bytes()).unwrap(), None);
+        assert_eq!(read_message(&mut "".as_bytes()).unwrap(), None);
+        let err = read_message(&mut "Content-Length: 5\r\n\r\nhel".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = read_message(&mut &b"Content-Length: 2\r\n\r\n\xff\xfe"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod lsp_client;
+pub mod lsp_manager;
+pub mod lsp_protocol;
+pub mod lsp_transport;
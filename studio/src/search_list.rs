@@ -0,0 +1,210 @@
+use {
+    crate::{
+        app::{AppAction, AppData},
+        file_system::file_system::ProjectSearch,
+        makepad_platform::studio::JumpToFile,
+        makepad_file_protocol::MAX_SEARCH_RESULTS,
+        makepad_widgets::*,
+    },
+};
+
+live_design!{
+    import makepad_draw::shader::std::*;
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    SearchItem = <View> {
+        width: Fill, height: Fit,
+        flow: Right,
+        align: { y: 0.5 }
+        padding: { left: (THEME_SPACE_2), right: (THEME_SPACE_2), top: (THEME_SPACE_1), bottom: (THEME_SPACE_1) }
+        spacing: (THEME_SPACE_2)
+        show_bg: true,
+
+        draw_bg: {
+            instance is_even: 0.0
+            fn pixel(self) -> vec4 {
+                return mix(
+                    THEME_COLOR_BG_EVEN,
+                    THEME_COLOR_BG_ODD,
+                    self.is_even
+                );
+            }
+        }
+    }
+
+    SearchList = {{SearchList}}{
+        height: Fill, width: Fill,
+        list = <PortalList> {
+            capture_overload: false,
+            grab_key_focus: false
+            drag_scrolling: false
+            height: Fill, width: Fill,
+            flow: Down
+
+            File = <SearchItem> {
+                label = <Pbold> { width: Fill, margin: 0., padding: 0. }
+                count = <P> {
+                    width: Fit, margin: 0., padding: 0.
+                    draw_text: { color: (THEME_COLOR_TEXT_META) }
+                }
+            }
+
+            Match = <SearchItem> {
+                cursor: Hand
+                padding: { left: 25., right: (THEME_SPACE_2), top: (THEME_SPACE_1), bottom: (THEME_SPACE_1) }
+                line = <P> {
+                    width: 40., margin: 0., padding: 0.
+                    draw_text: { color: (THEME_COLOR_TEXT_META) }
+                }
+                flow = <TextFlow> { width: Fill, height: Fit }
+            }
+
+            Message = <SearchItem> {
+                label = <P> { width: Fill, margin: 0., padding: 0. }
+            }
+
+            Empty = <SearchItem> {
+                cursor: Default
+                height: 25,
+            }
+        }
+    }
+}
+
+// A row of the list, which is either the header of a file or one of its matching lines.
+enum SearchRow {
+    Message(String),
+    File { path: String, count: usize },
+    Match(usize),
+}
+
+/// Shows the results of the search across all files, grouped by file.
+#[derive(Live, LiveHook, Widget)]
+pub struct SearchList{
+    #[deref] view: View,
+    #[rust] rows: Vec<SearchRow>,
+}
+
+impl SearchList{
+    fn update_rows(&mut self, search: &ProjectSearch){
+        self.rows.clear();
+        if let Some(error) = &search.error{
+            self.rows.push(SearchRow::Message(error.clone()));
+        }
+        else if search.is_done && !search.pattern.is_empty(){
+            let file_count = search.paths().len();
+            self.rows.push(SearchRow::Message(if search.results.len() >= MAX_SEARCH_RESULTS{
+                format!("Showing the first {} results in {} files", search.results.len(), file_count)
+            }
+            else{
+                format!("{} results in {} files", search.results.len(), file_count)
+            }));
+        }
+        else if !search.is_done{
+            self.rows.push(SearchRow::Message("Searching...".to_string()));
+        }
+        for (index, result) in search.results.iter().enumerate(){
+            let is_new_file = match index.checked_sub(1){
+                Some(prev) => search.results[prev].path != result.path,
+                None => true
+            };
+            if is_new_file{
+                let count = search.results[index..].iter().take_while(|other| other.path == result.path).count();
+                self.rows.push(SearchRow::File{path: result.path.clone(), count});
+            }
+            self.rows.push(SearchRow::Match(index));
+        }
+    }
+
+    fn draw_search(&mut self, cx: &mut Cx2d, list: &mut PortalList, search: &ProjectSearch){
+        self.update_rows(search);
+        list.set_item_range(cx, 0, self.rows.len());
+        while let Some(item_id) = list.next_visible_item(cx) {
+            let is_even = item_id & 1 == 0;
+            match self.rows.get(item_id){
+                Some(SearchRow::Message(message)) => {
+                    let item = list.item(cx, item_id, live_id!(Message)).as_view();
+                    item.apply_over(cx, live!{
+                        draw_bg: {is_even: (if is_even {1.0} else {0.0})}
+                        label = {text: (message)}
+                    });
+                    item.draw_all(cx, &mut Scope::empty());
+                }
+                Some(SearchRow::File{path, count}) => {
+                    let item = list.item(cx, item_id, live_id!(File)).as_view();
+                    item.apply_over(cx, live!{
+                        draw_bg: {is_even: (if is_even {1.0} else {0.0})}
+                        label = {text: (path)}
+                        count = {text: (count.to_string())}
+                    });
+                    item.draw_all(cx, &mut Scope::empty());
+                }
+                Some(SearchRow::Match(index)) => {
+                    let result = &search.results[*index];
+                    let mut item = list.item(cx, item_id, live_id!(Match)).as_view();
+                    item.apply_over(cx, live!{
+                        draw_bg: {is_even: (if is_even {1.0} else {0.0})}
+                        line = {text: ((result.line + 1).to_string())}
+                    });
+                    while let Some(step) = item.draw(cx, &mut Scope::empty()).step(){
+                        if let Some(mut tf) = step.as_text_flow().borrow_mut(){
+                            // leave out the indentation, and show the matches in bold
+                            let line_text = result.line_text.trim_start();
+                            let offset = result.line_text.len() - line_text.len();
+                            let mut start = 0;
+                            for &(match_start, match_end) in &result.ranges{
+                                let match_start = match_start.saturating_sub(offset).max(start);
+                                let match_end = match_end.saturating_sub(offset).max(match_start);
+                                tf.draw_text(cx, &line_text[start..match_start]);
+                                tf.bold.push();
+                                tf.draw_text(cx, &line_text[match_start..match_end]);
+                                tf.bold.pop();
+                                start = match_end;
+                            }
+                            tf.draw_text(cx, &line_text[start..]);
+                        }
+                    }
+                }
+                None => {
+                    let item = list.item(cx, item_id, live_id!(Empty)).as_view();
+                    item.apply_over(cx, live!{draw_bg: {is_even: (if is_even {1.0} else {0.0})}});
+                    item.draw_all(cx, &mut Scope::empty());
+                }
+            }
+        }
+    }
+}
+
+impl Widget for SearchList {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope:&mut Scope, walk:Walk)->DrawStep{
+        while let Some(step) = self.view.draw_walk(cx, scope, walk).step(){
+            if let Some(mut list) = step.as_portal_list().borrow_mut(){
+                self.draw_search(cx, &mut *list, &scope.data.get::<AppData>().unwrap().file_system.search)
+            }
+        }
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope){
+        let list = self.view.portal_list(id!(list));
+        self.view.handle_event(cx, event, scope);
+        let search = &scope.data.get::<AppData>().unwrap().file_system.search;
+        if let Event::Actions(actions) = event{
+            for (item_id, item) in list.items_with_actions(actions){
+                if !item.as_view().finger_up(actions).is_some_and(|fe| fe.is_over){
+                    continue;
+                }
+                if let Some(SearchRow::Match(index)) = self.rows.get(item_id){
+                    if let Some(result) = search.results.get(*index){
+                        cx.action(AppAction::JumpTo(JumpToFile{
+                            file_name: result.path.clone(),
+                            line: result.line as u32,
+                            column: result.ranges.first().map_or(0, |range| range.0) as u32
+                        }));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -30,10 +30,18 @@ impl Widget for StudioCodeEditor {
         // alright we have a scope, and an id, so now we can properly draw the editor.
         let session_id = scope.path.from_end(1);
         let app_scope = scope.data.get_mut::<AppData>().unwrap();
+        let pending_jump = app_scope.file_system.tab_id_to_pending_jump.remove(&session_id);
         if let Some(EditSession::Code(session)) = app_scope.file_system.get_session_mut(session_id){
+            if let Some(pos) = pending_jump{
+                self.editor.set_cursor_and_scroll(cx, pos, session);
+            }
             self.editor.draw_walk_editor(cx, session, walk);
         }
         else{
+            if let Some(pos) = pending_jump{
+                // the document is still loading
+                app_scope.file_system.tab_id_to_pending_jump.insert(session_id, pos);
+            }
             self.editor.draw_empty_editor(cx, walk);
         }
         DrawStep::done()
@@ -0,0 +1,460 @@
+use {
+    crate::{
+        app::AppData,
+        makepad_widgets::*,
+        makepad_terminal::{Color as CellColor, Pty, Screen, Style as CellStyle},
+    },
+    std::{
+        env,
+        io::Read,
+        path::Path,
+        thread,
+    },
+};
+
+live_design!{
+    import makepad_draw::shader::std::*;
+    import makepad_widgets::base::*;
+    import makepad_widgets::theme_desktop_dark::*;
+
+    Terminal = {{Terminal}}{
+        height: Fill, width: Fill,
+        pad_left_top: vec2(4.0, 4.0)
+        scroll_bars: <ScrollBars> {show_scroll_x: false}
+        default_fg: (THEME_COLOR_TEXT_DEFAULT)
+        draw_bg: { color: (THEME_COLOR_BG_CONTAINER) }
+        draw_selection: { color: #5a5a5a80 }
+        draw_cursor: { color: #c0c0c0a0 }
+        draw_text: {
+            text_style: <THEME_FONT_CODE> {}
+        }
+    }
+}
+
+// The 16 ANSI colours, as xterm has them.
+const ANSI_COLORS: [u32; 16] = [
+    0x000000ff, 0xcd3131ff, 0x0dbc79ff, 0xe5e510ff, 0x2472c8ff, 0xbc3fbcff, 0x11a8cdff, 0xe5e5e5ff,
+    0x666666ff, 0xf14c4cff, 0x23d18bff, 0xf5f543ff, 0x3b8eeaff, 0xd670d6ff, 0x29b8dbff, 0xffffffff,
+];
+
+enum TerminalOutput {
+    Bytes(Vec<u8>),
+    Exited,
+}
+
+/// Runs the shell of the user in a pseudo terminal, and shows what it writes.
+#[derive(Live, LiveHook, Widget)]
+pub struct Terminal{
+    #[walk] walk: Walk,
+    #[redraw] #[live] scroll_bars: ScrollBars,
+    #[live] pad_left_top: DVec2,
+    #[live] default_fg: Vec4,
+    #[live] draw_bg: DrawColor,
+    #[live] draw_cell_bg: DrawColor,
+    #[live] draw_selection: DrawColor,
+    #[live] draw_cursor: DrawColor,
+    #[live] draw_text: DrawText,
+    #[rust] screen: Option<Screen>,
+    #[rust] pty: Option<Pty>,
+    #[rust] output: ToUIReceiver<TerminalOutput>,
+    #[rust] exited: bool,
+    #[rust] has_key_focus: bool,
+    #[rust] cell_size: DVec2,
+    #[rust] view_height: f64,
+    #[rust] content_height: f64,
+    #[rust(true)] stick_to_bottom: bool,
+    // The start and end of the selection, as a line index and a column of the screen.
+    #[rust] selection: Option<((usize, usize), (usize, usize))>,
+    // Ctrl+C copied the selection, so it should not interrupt the program as well.
+    #[rust] copied_selection: bool,
+    #[rust] tmp_text: String,
+}
+
+impl Terminal{
+    fn start_shell(&mut self, root_path: &Path){
+        let screen = self.screen.as_mut().unwrap();
+        let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
+        let pty = match Pty::spawn(&shell, &[], root_path, screen.cols(), screen.rows()){
+            Ok(pty) => pty,
+            Err(err) => {
+                screen.process(format!("Cannot start {}: {}\r\n", shell, err).as_bytes());
+                self.exited = true;
+                return
+            }
+        };
+        let Ok(mut reader) = pty.reader() else {
+            return
+        };
+        self.output = ToUIReceiver::default();
+        let sender = self.output.sender();
+        thread::spawn(move || {
+            let mut buf = [0; 4096];
+            // Reading fails rather than returning 0 once the shell has exited.
+            while let Ok(len) = reader.read(&mut buf){
+                if len == 0 || sender.send(TerminalOutput::Bytes(buf[..len].to_vec())).is_err(){
+                    break
+                }
+            }
+            let _ = sender.send(TerminalOutput::Exited);
+        });
+        self.pty = Some(pty);
+        self.exited = false;
+    }
+
+    fn handle_output(&mut self, cx: &mut Cx){
+        let Some(screen) = &mut self.screen else {
+            return
+        };
+        let mut changed = false;
+        while let Ok(output) = self.output.try_recv(){
+            match output{
+                TerminalOutput::Bytes(bytes) => screen.process(&bytes),
+                TerminalOutput::Exited => {
+                    self.pty = None;
+                    self.exited = true;
+                    screen.process(b"\r\n[Process exited, press Enter to restart]\r\n");
+                }
+            }
+            changed = true;
+        }
+        let responses = screen.take_responses();
+        if !responses.is_empty(){
+            self.write(&responses);
+        }
+        if changed{
+            self.scroll_bars.redraw(cx);
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]){
+        if let Some(pty) = &mut self.pty{
+            let _ = pty.write(bytes);
+        }
+    }
+
+    // Writes what the user typed, and scrolls down to the cursor.
+    fn write_input(&mut self, cx: &mut Cx, bytes: &[u8]){
+        self.write(bytes);
+        self.selection = None;
+        self.stick_to_bottom = true;
+        self.scroll_bars.redraw(cx);
+    }
+
+    // Returns the line index and column of the cell boundary nearest to `abs`.
+    fn pick(&self, cx: &Cx, abs: DVec2) -> (usize, usize){
+        let rect = self.scroll_bars.area().rect(cx);
+        let pos = abs - rect.pos + self.scroll_bars.get_scroll_pos() - self.pad_left_top;
+        let line = (pos.y / self.cell_size.y).floor().max(0.0) as usize;
+        let col = (pos.x / self.cell_size.x).round().max(0.0) as usize;
+        let Some(screen) = &self.screen else {
+            return (0, 0)
+        };
+        (line.min(screen.line_count() - 1), col.min(screen.cols()))
+    }
+
+    fn selected_text(&self) -> Option<String>{
+        let (start, end) = self.selection?;
+        if start == end{
+            return None
+        }
+        Some(self.screen.as_ref()?.text_in_range(start, end))
+    }
+
+    fn palette_color(&self, color: CellColor) -> Vec4{
+        match color{
+            CellColor::Default => self.default_fg,
+            CellColor::Indexed(index @ 0..=15) => Vec4::from_u32(ANSI_COLORS[index as usize]),
+            // A 6x6x6 colour cube
+            CellColor::Indexed(index @ 16..=231) => {
+                let index = index - 16;
+                let level = |value: u8| if value == 0 {0} else {55 + value as u32 * 40};
+                Vec4::from_u32(level(index / 36) << 24 | level(index / 6 % 6) << 16 | level(index % 6) << 8 | 0xff)
+            }
+            // A ramp of greys
+            CellColor::Indexed(index) => {
+                let level = 8 + (index - 232) as u32 * 10;
+                Vec4::from_u32(level << 24 | level << 16 | level << 8 | 0xff)
+            }
+            CellColor::Rgb(r, g, b) => Vec4::from_u32((r as u32) << 24 | (g as u32) << 16 | (b as u32) << 8 | 0xff),
+        }
+    }
+
+    // Returns the colours to draw a cell with, where the background is none if it is the default.
+    fn cell_colors(&self, style: CellStyle) -> (Vec4, Option<Vec4>){
+        let fg = match style.fg{
+            // Bold text is drawn in the bright colours, as there is no bold code font.
+            CellColor::Indexed(index @ 0..=7) if style.bold => self.palette_color(CellColor::Indexed(index + 8)),
+            fg => self.palette_color(fg),
+        };
+        let bg = match style.bg{
+            CellColor::Default => None,
+            bg => Some(self.palette_color(bg)),
+        };
+        if style.inverse{
+            (bg.unwrap_or(self.draw_bg.color), Some(fg))
+        }
+        else{
+            (fg, bg)
+        }
+    }
+
+    fn draw_lines(&mut self, cx: &mut Cx2d, origin: DVec2){
+        let Some(screen) = self.screen.take() else {
+            return
+        };
+        let scroll_y = self.scroll_bars.get_scroll_pos().y;
+        let first_line = ((scroll_y / self.cell_size.y).floor().max(0.0) as usize).min(screen.line_count());
+        let last_line = (first_line + screen.rows() + 2).min(screen.line_count());
+        let selection = self.selection.map(|(start, end)| if start <= end {(start, end)} else {(end, start)});
+        for index in first_line..last_line{
+            let line = screen.line(index);
+            let y = origin.y + index as f64 * self.cell_size.y;
+            // Draws runs of cells that have the same style in one go.
+            let mut start = 0;
+            while start < line.cells.len(){
+                let style = line.cells[start].style;
+                let end = line.cells[start..].iter().position(|cell| cell.style != style).map_or(line.cells.len(), |len| start + len);
+                let pos = dvec2(origin.x + start as f64 * self.cell_size.x, y);
+                let size = dvec2((end - start) as f64 * self.cell_size.x, self.cell_size.y);
+                let (fg, bg) = self.cell_colors(style);
+                if let Some(bg) = bg{
+                    self.draw_cell_bg.color = bg;
+                    self.draw_cell_bg.draw_abs(cx, Rect{pos, size});
+                }
+                if style.underline{
+                    self.draw_cell_bg.color = fg;
+                    self.draw_cell_bg.draw_abs(cx, Rect{pos: pos + dvec2(0.0, size.y - 1.0), size: dvec2(size.x, 1.0)});
+                }
+                self.tmp_text.clear();
+                self.tmp_text.extend(line.cells[start..end].iter().map(|cell| cell.char));
+                if !self.tmp_text.trim_end().is_empty(){
+                    self.draw_text.color = fg;
+                    self.draw_text.draw_abs(cx, pos, self.tmp_text.trim_end());
+                }
+                start = end;
+            }
+            if let Some((sel_start, sel_end)) = selection{
+                if (sel_start.0..=sel_end.0).contains(&index) && sel_start != sel_end{
+                    let from = if index == sel_start.0 {sel_start.1} else {0};
+                    let to = if index == sel_end.0 {sel_end.1} else {screen.cols()};
+                    if to > from{
+                        self.draw_selection.draw_abs(cx, Rect{
+                            pos: dvec2(origin.x + from as f64 * self.cell_size.x, y),
+                            size: dvec2((to - from) as f64 * self.cell_size.x, self.cell_size.y),
+                        });
+                    }
+                }
+            }
+        }
+        if screen.cursor_visible() && !self.exited{
+            let (x, y) = screen.cursor();
+            let pos = origin + dvec2(x as f64, (screen.scrollback_len() + y) as f64) * self.cell_size;
+            // Without key focus, the cursor is drawn as a bar.
+            let width = if self.has_key_focus {self.cell_size.x} else {2.0};
+            self.draw_cursor.draw_abs(cx, Rect{pos, size: dvec2(width, self.cell_size.y)});
+        }
+        self.screen = Some(screen);
+    }
+
+    pub fn has_key_focus(&self, cx: &Cx) -> bool{
+        cx.has_key_focus(self.scroll_bars.area())
+    }
+}
+
+impl Widget for Terminal {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope:&mut Scope, walk:Walk)->DrawStep{
+        self.cell_size = self.draw_text.text_style.font_size * self.draw_text.get_monospace_base(cx);
+        if self.stick_to_bottom{
+            let bottom = (self.content_height - self.view_height).max(0.0);
+            self.scroll_bars.set_scroll_pos_no_clip(cx, dvec2(0.0, bottom));
+        }
+        self.scroll_bars.begin(cx, walk, Layout::default());
+        let rect = cx.turtle().rect();
+        self.draw_bg.draw_abs(cx, cx.turtle().unscrolled_rect());
+
+        let size = rect.size - self.pad_left_top * 2.0;
+        let cols = (size.x / self.cell_size.x).floor().max(1.0) as usize;
+        let rows = (size.y / self.cell_size.y).floor().max(1.0) as usize;
+        match &mut self.screen{
+            None => {
+                self.screen = Some(Screen::new(cols, rows));
+                if let Some(data) = scope.data.get::<AppData>(){
+                    let root_path = data.build_manager.root_path().to_path_buf();
+                    self.start_shell(&root_path);
+                }
+            }
+            Some(screen) => if screen.cols() != cols || screen.rows() != rows{
+                screen.resize(cols, rows);
+                self.selection = None;
+                if let Some(pty) = &self.pty{
+                    let _ = pty.resize(cols, rows);
+                }
+            }
+        }
+
+        self.draw_lines(cx, rect.pos + self.pad_left_top);
+        let line_count = self.screen.as_ref().map_or(0, |screen| screen.line_count());
+        self.content_height = line_count as f64 * self.cell_size.y + self.pad_left_top.y * 2.0;
+        self.view_height = rect.size.y;
+        cx.turtle_mut().set_used(rect.size.x, self.content_height);
+        self.scroll_bars.end(cx);
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope){
+        if let Event::Signal = event{
+            self.handle_output(cx);
+        }
+        if !self.scroll_bars.handle_event(cx, event, scope).is_empty(){
+            let bottom = (self.content_height - self.view_height).max(0.0);
+            self.stick_to_bottom = self.scroll_bars.get_scroll_pos().y >= bottom - 1.0;
+            self.scroll_bars.redraw(cx);
+        }
+        let application_cursor_keys = self.screen.as_ref().is_some_and(|screen| screen.application_cursor_keys());
+        match event.hits(cx, self.scroll_bars.area()){
+            Hit::KeyFocus(_) => {
+                self.has_key_focus = true;
+                self.scroll_bars.redraw(cx);
+            }
+            Hit::KeyFocusLost(_) => {
+                self.has_key_focus = false;
+                self.scroll_bars.redraw(cx);
+            }
+            Hit::KeyDown(KeyEvent{key_code: KeyCode::ReturnKey, ..}) if self.exited => {
+                self.screen = None;
+                self.scroll_bars.redraw(cx);
+            }
+            Hit::KeyDown(KeyEvent{key_code: KeyCode::KeyC, modifiers: KeyModifiers{control: true, ..}, ..}) if self.copied_selection => {
+                self.copied_selection = false;
+            }
+            Hit::KeyDown(key_event) => {
+                if let Some(bytes) = key_event_bytes(&key_event, application_cursor_keys){
+                    self.write_input(cx, &bytes);
+                }
+            }
+            Hit::TextInput(TextInputEvent{input, was_paste, ..}) if !input.is_empty() => {
+                if was_paste && self.screen.as_ref().is_some_and(|screen| screen.bracketed_paste()){
+                    let input = format!("\x1b[200~{}\x1b[201~", input);
+                    self.write_input(cx, input.as_bytes());
+                }
+                else{
+                    self.write_input(cx, input.as_bytes());
+                }
+            }
+            Hit::TextCopy(ce) => {
+                if let Some(text) = self.selected_text(){
+                    *ce.response.borrow_mut() = Some(text);
+                    self.copied_selection = true;
+                }
+            }
+            Hit::FingerHoverIn(_) | Hit::FingerHoverOver(_) => {
+                cx.set_cursor(MouseCursor::Text);
+            }
+            Hit::FingerDown(fe) => {
+                cx.set_key_focus(self.scroll_bars.area());
+                let pos = self.pick(cx, fe.abs);
+                self.selection = Some((pos, pos));
+                self.scroll_bars.redraw(cx);
+            }
+            Hit::FingerMove(fe) => {
+                let pos = self.pick(cx, fe.abs);
+                if let Some((_, end)) = &mut self.selection{
+                    *end = pos;
+                    self.scroll_bars.redraw(cx);
+                }
+            }
+            _ => ()
+        }
+    }
+}
+
+impl TerminalRef{
+    pub fn has_key_focus(&self, cx: &Cx) -> bool{
+        self.borrow().is_some_and(|inner| inner.has_key_focus(cx))
+    }
+}
+
+// Returns the bytes that a key sends to the program, as xterm sends them. Keys that type text
+// come as text input instead, unless a modifier is held.
+fn key_event_bytes(event: &KeyEvent, application_cursor_keys: bool) -> Option<Vec<u8>>{
+    let modifiers = event.modifiers;
+    if modifiers.logo{
+        return None
+    }
+    // The modifiers are encoded as a parameter, such as the 5 in `ESC [ 1 ; 5 A` for Ctrl+Up.
+    let modifier_param = 1 + modifiers.shift as u8 + modifiers.alt as u8 * 2 + modifiers.control as u8 * 4;
+    let cursor_key = |final_byte: char| -> Vec<u8>{
+        if modifier_param > 1{
+            format!("\x1b[1;{}{}", modifier_param, final_byte).into_bytes()
+        }
+        else if application_cursor_keys{
+            format!("\x1bO{}", final_byte).into_bytes()
+        }
+        else{
+            format!("\x1b[{}", final_byte).into_bytes()
+        }
+    };
+    let tilde_key = |code: u8| -> Vec<u8>{
+        if modifier_param > 1{
+            format!("\x1b[{};{}~", code, modifier_param).into_bytes()
+        }
+        else{
+            format!("\x1b[{}~", code).into_bytes()
+        }
+    };
+    let bytes = match event.key_code{
+        KeyCode::ReturnKey | KeyCode::NumpadEnter => b"\r".to_vec(),
+        KeyCode::Tab if modifiers.shift => b"\x1b[Z".to_vec(),
+        KeyCode::Tab => b"\t".to_vec(),
+        KeyCode::Backspace if modifiers.control => vec![0x08],
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Escape => vec![0x1b],
+        KeyCode::ArrowUp => cursor_key('A'),
+        KeyCode::ArrowDown => cursor_key('B'),
+        KeyCode::ArrowRight => cursor_key('C'),
+        KeyCode::ArrowLeft => cursor_key('D'),
+        KeyCode::Home => cursor_key('H'),
+        KeyCode::End => cursor_key('F'),
+        KeyCode::Insert => tilde_key(2),
+        KeyCode::Delete => tilde_key(3),
+        KeyCode::PageUp => tilde_key(5),
+        KeyCode::PageDown => tilde_key(6),
+        KeyCode::F1 => b"\x1bOP".to_vec(),
+        KeyCode::F2 => b"\x1bOQ".to_vec(),
+        KeyCode::F3 => b"\x1bOR".to_vec(),
+        KeyCode::F4 => b"\x1bOS".to_vec(),
+        KeyCode::F5 => tilde_key(15),
+        KeyCode::F6 => tilde_key(17),
+        KeyCode::F7 => tilde_key(18),
+        KeyCode::F8 => tilde_key(19),
+        KeyCode::F9 => tilde_key(20),
+        KeyCode::F10 => tilde_key(21),
+        KeyCode::F11 => tilde_key(23),
+        KeyCode::F12 => tilde_key(24),
+        // Ctrl+V pastes, which comes as text input.
+        KeyCode::KeyV if modifiers.control => return None,
+        key_code if modifiers.control => {
+            let byte = match key_code{
+                KeyCode::Space | KeyCode::Key2 => 0x00,
+                KeyCode::LBracket => 0x1b,
+                KeyCode::Backslash => 0x1c,
+                KeyCode::RBracket => 0x1d,
+                KeyCode::Slash => 0x1f,
+                key_code => {
+                    let char = key_code.to_char(false).filter(|char| char.is_ascii_lowercase())?;
+                    char as u8 & 0x1f
+                }
+            };
+            if modifiers.alt {vec![0x1b, byte]} else {vec![byte]}
+        }
+        // Alt with a key sends escape before the key, as text input does not come while alt is held.
+        key_code if modifiers.alt => {
+            let char = key_code.to_char(modifiers.shift)?;
+            let mut bytes = vec![0x1b];
+            bytes.extend_from_slice(char.to_string().as_bytes());
+            bytes
+        }
+        _ => return None,
+    };
+    Some(bytes)
+}
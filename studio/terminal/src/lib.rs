@@ -0,0 +1,20 @@
+pub mod parser;
+pub use parser::*;
+
+pub mod screen;
+pub use screen::*;
+
+// pty.rs uses the ioctl numbers and open flags of the architectures that share the generic
+// Linux definitions
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64")
+))]
+pub mod pty;
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64", target_arch = "riscv64")
+)))]
+#[path = "pty_unsupported.rs"]
+pub mod pty;
+pub use pty::*;
@@ -0,0 +1,234 @@
+/// The most parameters a control sequence keeps. Any further parameters are dropped.
+const MAX_PARAMS: usize = 32;
+
+/// The most bytes an operating system command keeps. Any further bytes are dropped.
+const MAX_OSC_LEN: usize = 4096;
+
+/// Something a terminal has to do, as decoded by the `Parser` from the bytes a program writes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Action {
+    /// Prints a character at the cursor.
+    Print(char),
+    /// Executes a C0 control character, such as a line feed or a backspace.
+    Control(u8),
+    /// Runs a control sequence, which starts with `ESC [`.
+    Csi(Csi),
+    /// Runs an escape sequence, such as `ESC 7` or `ESC ( 0`.
+    Esc {
+        intermediate: Option<u8>,
+        final_byte: u8,
+    },
+    /// Runs an operating system command, which starts with `ESC ]`, such as setting the title.
+    Osc(String),
+}
+
+/// A control sequence such as `ESC [ ? 1049 h`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Csi {
+    /// The private marker, one of `<`, `=`, `>` or `?`, that comes before the parameters.
+    pub private: Option<u8>,
+    /// The parameters, where an empty parameter is 0.
+    pub params: Vec<u16>,
+    pub intermediate: Option<u8>,
+    pub final_byte: u8,
+}
+
+impl Csi {
+    /// Returns the parameter at `index`, or `default` if it is missing or 0, as most sequences
+    /// treat 0 the same as a missing parameter.
+    pub fn param(&self, index: usize, default: u16) -> u16 {
+        match self.params.get(index) {
+            Some(param) if *param != 0 => *param,
+            _ => default,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    CsiParam,
+    CsiIgnore,
+    OscString,
+    // Device control strings and the like, which we do not support, up to the string terminator.
+    IgnoreString,
+}
+
+/// Turns the bytes a program writes to a terminal into `Action`s, following the state machine of
+/// the DEC VT500 series that xterm and most other terminals use. Text is decoded as UTF-8.
+///
+/// Sequences can be split over any number of calls to `advance`.
+#[derive(Clone, Debug, Default)]
+pub struct Parser {
+    state: State,
+    csi: Csi,
+    param: Option<u16>,
+    osc: Vec<u8>,
+    utf8_char: u32,
+    utf8_remaining: usize,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&mut self, bytes: &[u8], mut f: impl FnMut(Action)) {
+        for byte in bytes {
+            self.advance_byte(*byte, &mut f);
+        }
+    }
+
+    fn advance_byte(&mut self, byte: u8, f: &mut impl FnMut(Action)) {
+        if self.utf8_remaining > 0 {
+            if byte & 0xC0 == 0x80 {
+                self.utf8_char = self.utf8_char << 6 | (byte & 0x3F) as u32;
+                self.utf8_remaining -= 1;
+                if self.utf8_remaining == 0 {
+                    f(Action::Print(
+                        char::from_u32(self.utf8_char).unwrap_or(char::REPLACEMENT_CHARACTER),
+                    ));
+                }
+                return;
+            }
+            // The sequence was cut short, so what we have so far is not a character.
+            self.utf8_remaining = 0;
+            f(Action::Print(char::REPLACEMENT_CHARACTER));
+        }
+        match byte {
+            // These abort any sequence, wherever they come.
+            0x18 | 0x1A => {
+                self.state = State::Ground;
+                return;
+            }
+            0x1B => {
+                if self.state == State::OscString {
+                    self.dispatch_osc(f);
+                }
+                self.state = State::Escape;
+                return;
+            }
+            _ => {}
+        }
+        match self.state {
+            State::Ground => self.ground(byte, f),
+            State::Escape => match byte {
+                0x00..=0x1F => f(Action::Control(byte)),
+                0x20..=0x2F => {
+                    self.csi.intermediate = Some(byte);
+                    self.state = State::EscapeIntermediate;
+                }
+                b'[' => {
+                    self.csi = Csi::default();
+                    self.param = None;
+                    self.state = State::CsiParam;
+                }
+                b']' => {
+                    self.osc.clear();
+                    self.state = State::OscString;
+                }
+                b'P' | b'X' | b'^' | b'_' => self.state = State::IgnoreString,
+                0x30..=0x7E => {
+                    f(Action::Esc {
+                        intermediate: None,
+                        final_byte: byte,
+                    });
+                    self.state = State::Ground;
+                }
+                _ => {}
+            },
+            State::EscapeIntermediate => match byte {
+                0x00..=0x1F => f(Action::Control(byte)),
+                0x30..=0x7E => {
+                    f(Action::Esc {
+                        intermediate: self.csi.intermediate.take(),
+                        final_byte: byte,
+                    });
+                    self.state = State::Ground;
+                }
+                _ => {}
+            },
+            State::CsiParam => match byte {
+                0x00..=0x1F => f(Action::Control(byte)),
+                // Parameters can not come after an intermediate byte.
+                b'0'..=b';' if self.csi.intermediate.is_some() => self.state = State::CsiIgnore,
+                b'0'..=b'9' => {
+                    let digit = (byte - b'0') as u16;
+                    let param = self.param.unwrap_or(0);
+                    self.param = Some(param.saturating_mul(10).saturating_add(digit));
+                }
+                // We do not support sub-parameters, so they are taken as parameters of their own.
+                b':' | b';' => {
+                    self.push_param();
+                    self.param = Some(0);
+                }
+                b'<'..=b'?' => {
+                    if self.csi.private.is_none() && self.csi.params.is_empty() && self.param.is_none() {
+                        self.csi.private = Some(byte);
+                    } else {
+                        self.state = State::CsiIgnore;
+                    }
+                }
+                0x20..=0x2F => self.csi.intermediate = Some(byte),
+                0x40..=0x7E => {
+                    if self.param.is_some() {
+                        self.push_param();
+                    }
+                    self.csi.final_byte = byte;
+                    f(Action::Csi(std::mem::take(&mut self.csi)));
+                    self.state = State::Ground;
+                }
+                _ => {}
+            },
+            State::CsiIgnore => match byte {
+                0x00..=0x1F => f(Action::Control(byte)),
+                0x40..=0x7E => self.state = State::Ground,
+                _ => {}
+            },
+            State::OscString => match byte {
+                0x07 => {
+                    self.dispatch_osc(f);
+                    self.state = State::Ground;
+                }
+                0x00..=0x1F => {}
+                _ => {
+                    if self.osc.len() < MAX_OSC_LEN {
+                        self.osc.push(byte);
+                    }
+                }
+            },
+            State::IgnoreString => {}
+        }
+    }
+
+    fn ground(&mut self, byte: u8, f: &mut impl FnMut(Action)) {
+        match byte {
+            0x00..=0x1F => f(Action::Control(byte)),
+            0x20..=0x7E => f(Action::Print(byte as char)),
+            0x7F => {}
+            0xC2..=0xDF => self.start_utf8_char(byte & 0x1F, 1),
+            0xE0..=0xEF => self.start_utf8_char(byte & 0x0F, 2),
+            0xF0..=0xF4 => self.start_utf8_char(byte & 0x07, 3),
+            _ => f(Action::Print(char::REPLACEMENT_CHARACTER)),
+        }
+    }
+
+    fn start_utf8_char(&mut self, bits: u8, remaining: usize) {
+        self.utf8_char = bits as u32;
+        self.utf8_remaining = remaining;
+    }
+
+    fn push_param(&mut self) {
+        if self.csi.params.len() < MAX_PARAMS {
+            self.csi.params.push(self.param.unwrap_or(0));
+        }
+    }
+
+    fn dispatch_osc(&mut self, f: &mut impl FnMut(Action)) {
+        f(Action::Osc(String::from_utf8_lossy(&self.osc).into_owned()));
+        self.osc.clear();
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    ffi::CStr,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::{
+        raw::{c_char, c_int, c_ulong},
+        unix::{
+            fs::OpenOptionsExt,
+            io::AsRawFd,
+            process::CommandExt,
+        },
+    },
+    path::Path,
+    process::{Child, Command, ExitStatus, Stdio},
+};
+
+// From the generic Linux headers (asm-generic/fcntl.h and asm-generic/ioctls.h). Mips, powerpc,
+// sparc and alpha define their own, which is why lib.rs only builds this file for the
+// architectures that use these.
+const O_NOCTTY: c_int = 0o400;
+const TIOCSWINSZ: c_ulong = 0x5414;
+const TIOCSCTTY: c_ulong = 0x540E;
+
+#[repr(C)]
+struct WinSize {
+    ws_row: u16,
+    ws_col: u16,
+    ws_xpixel: u16,
+    ws_ypixel: u16,
+}
+
+extern "C" {
+    fn grantpt(fd: c_int) -> c_int;
+    fn unlockpt(fd: c_int) -> c_int;
+    fn ptsname_r(fd: c_int, buf: *mut c_char, buflen: usize) -> c_int;
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    fn setsid() -> c_int;
+}
+
+/// A program that runs in a pseudo terminal, so it behaves as if it runs in a terminal. Whatever
+/// it writes can be read from `reader`, and whatever is written to it is what the user typed.
+///
+/// The program is killed when the `Pty` is dropped.
+pub struct Pty {
+    master: File,
+    child: Child,
+}
+
+impl Pty {
+    pub fn spawn(
+        program: &str,
+        args: &[&str],
+        current_dir: &Path,
+        cols: usize,
+        rows: usize,
+    ) -> io::Result<Self> {
+        let master = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(O_NOCTTY)
+            .open("/dev/ptmx")?;
+        let fd = master.as_raw_fd();
+        let mut name = [0 as c_char; 128];
+        unsafe {
+            if grantpt(fd) != 0 || unlockpt(fd) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let error = ptsname_r(fd, name.as_mut_ptr(), name.len());
+            if error != 0 {
+                return Err(io::Error::from_raw_os_error(error));
+            }
+        }
+        let slave_path = unsafe { CStr::from_ptr(name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+        let slave = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(O_NOCTTY)
+            .open(slave_path)?;
+        set_size(fd, cols, rows)?;
+
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .current_dir(current_dir)
+            .env("TERM", "xterm-256color")
+            .env("COLORTERM", "truecolor")
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+        unsafe {
+            // Starts a new session with the pseudo terminal as its controlling terminal, so the
+            // program gets job control and signals such as the one for Ctrl+C.
+            command.pre_exec(|| {
+                if setsid() < 0 || ioctl(0, TIOCSCTTY, 0 as c_int) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+        Ok(Self { master, child })
+    }
+
+    /// Returns a handle to read what the program writes from. Reads block until there is output,
+    /// and fail or return 0 once the program has exited.
+    pub fn reader(&self) -> io::Result<File> {
+        self.master.try_clone()
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.master.write_all(bytes)?;
+        self.master.flush()
+    }
+
+    /// Tells the program that the terminal now has a different size.
+    pub fn resize(&self, cols: usize, rows: usize) -> io::Result<()> {
+        set_size(self.master.as_raw_fd(), cols, rows)
+    }
+
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.child.try_wait()
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn set_size(fd: c_int, cols: usize, rows: usize) -> io::Result<()> {
+    let size = WinSize {
+        ws_row: rows.min(u16::MAX as usize) as u16,
+        ws_col: cols.min(u16::MAX as usize) as u16,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    if unsafe { ioctl(fd, TIOCSWINSZ, &size as *const WinSize) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::{
+    fs::File,
+    io,
+    path::Path,
+    process::ExitStatus,
+};
+
+/// Pseudo terminals are only supported on Linux on the common architectures, so elsewhere `spawn`
+/// fails.
+pub struct Pty {
+    _private: (),
+}
+
+impl Pty {
+    pub fn spawn(
+        _program: &str,
+        _args: &[&str],
+        _current_dir: &Path,
+        _cols: usize,
+        _rows: usize,
+    ) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "pseudo terminals are not supported on this platform",
+        ))
+    }
+
+    pub fn reader(&self) -> io::Result<File> {
+        unreachable!()
+    }
+
+    pub fn write(&mut self, _bytes: &[u8]) -> io::Result<()> {
+        unreachable!()
+    }
+
+    pub fn resize(&self, _cols: usize, _rows: usize) -> io::Result<()> {
+        unreachable!()
+    }
+
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        unreachable!()
+    }
+}
@@ -0,0 +1,834 @@
+use {
+    crate::parser::{Action, Csi, Parser},
+    std::{collections::VecDeque, mem},
+};
+
+/// The number of lines a `Screen` keeps in its scrollback unless told otherwise.
+pub const DEFAULT_MAX_SCROLLBACK: usize = 10000;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Color {
+    /// The default foreground or background colour of the terminal.
+    #[default]
+    Default,
+    /// One of the 256 colours of the xterm palette, the first 16 of which are the ANSI colours.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    /// Swaps the foreground and background colours.
+    pub inverse: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cell {
+    pub char: char,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            char: ' ',
+            style: Style::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Line {
+    pub cells: Vec<Cell>,
+    /// Whether the text on this line goes on on the next line, because it was too long to fit.
+    pub wrapped: bool,
+}
+
+impl Line {
+    fn new(cols: usize, cell: Cell) -> Self {
+        Self {
+            cells: vec![cell; cols],
+            wrapped: false,
+        }
+    }
+
+    /// Returns the text on this line, without trailing spaces.
+    pub fn text(&self) -> String {
+        let text: String = self.cells.iter().map(|cell| cell.char).collect();
+        text.trim_end().to_string()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Cursor {
+    x: usize,
+    y: usize,
+    style: Style,
+    // The last column was written to, so the next character goes on the next line.
+    pending_wrap: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct SavedCursor {
+    cursor: Cursor,
+    origin_mode: bool,
+    charsets: [Charset; 2],
+    shift_out: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum Charset {
+    #[default]
+    Ascii,
+    // The DEC special graphics set, which has the line drawing characters.
+    LineDrawing,
+}
+
+/// The state of a terminal: a grid of cells with a cursor, the lines that scrolled off the top of
+/// it, and the modes programs set with escape sequences. Feed it the output of a program with
+/// `process`, and send what `take_responses` returns back to the program.
+///
+/// Every character takes up one cell; double width characters are not supported.
+#[derive(Clone, Debug)]
+pub struct Screen {
+    cols: usize,
+    rows: usize,
+    lines: Vec<Line>,
+    scrollback: VecDeque<Line>,
+    max_scrollback: usize,
+    // The lines of the main screen, while the alternate screen is shown.
+    main_lines: Option<Vec<Line>>,
+    cursor: Cursor,
+    saved_cursor: SavedCursor,
+    // The rows that scroll, where the bottom row is exclusive.
+    scroll_top: usize,
+    scroll_bottom: usize,
+    tab_stops: Vec<bool>,
+    charsets: [Charset; 2],
+    shift_out: bool,
+    autowrap: bool,
+    origin_mode: bool,
+    insert_mode: bool,
+    newline_mode: bool,
+    cursor_visible: bool,
+    application_cursor_keys: bool,
+    bracketed_paste: bool,
+    title: String,
+    responses: Vec<u8>,
+    parser: Parser,
+}
+
+impl Screen {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        Self {
+            cols,
+            rows,
+            lines: vec![Line::new(cols, Cell::default()); rows],
+            scrollback: VecDeque::new(),
+            max_scrollback: DEFAULT_MAX_SCROLLBACK,
+            main_lines: None,
+            cursor: Cursor::default(),
+            saved_cursor: SavedCursor::default(),
+            scroll_top: 0,
+            scroll_bottom: rows,
+            tab_stops: default_tab_stops(cols),
+            charsets: [Charset::Ascii; 2],
+            shift_out: false,
+            autowrap: true,
+            origin_mode: false,
+            insert_mode: false,
+            newline_mode: false,
+            cursor_visible: true,
+            application_cursor_keys: false,
+            bracketed_paste: false,
+            title: String::new(),
+            responses: Vec::new(),
+            parser: Parser::new(),
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the column and row of the cursor on the screen.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor.x, self.cursor.y)
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Whether the arrow keys should send `ESC O A` rather than `ESC [ A` and so on.
+    pub fn application_cursor_keys(&self) -> bool {
+        self.application_cursor_keys
+    }
+
+    /// Whether pasted text should be put between `ESC [ 200 ~` and `ESC [ 201 ~`.
+    pub fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste
+    }
+
+    pub fn is_alternate_screen(&self) -> bool {
+        self.main_lines.is_some()
+    }
+
+    /// Returns the title the program set, if any.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn set_max_scrollback(&mut self, max_scrollback: usize) {
+        self.max_scrollback = max_scrollback;
+        self.trim_scrollback();
+    }
+
+    pub fn scrollback_len(&self) -> usize {
+        self.scrollback.len()
+    }
+
+    pub fn clear_scrollback(&mut self) {
+        self.scrollback.clear();
+    }
+
+    /// Returns the number of lines in the scrollback and on the screen together.
+    pub fn line_count(&self) -> usize {
+        self.scrollback.len() + self.rows
+    }
+
+    /// Returns a line, where the lines in the scrollback come first, oldest first, and the rows of
+    /// the screen after them. Lines in the scrollback keep the width they had when they scrolled
+    /// off, so they can be shorter or longer than the screen is wide.
+    pub fn line(&self, index: usize) -> &Line {
+        if index < self.scrollback.len() {
+            &self.scrollback[index]
+        } else {
+            &self.lines[index - self.scrollback.len()]
+        }
+    }
+
+    /// Returns the text on the screen, one line per row, without trailing spaces.
+    pub fn visible_text(&self) -> String {
+        let lines: Vec<_> = self.lines.iter().map(|line| line.text()).collect();
+        lines.join("\n")
+    }
+
+    /// Returns the text from `start` up to `end`, both given as a line index as for `line` and a
+    /// column. Lines are separated by newlines, except where text was wrapped.
+    pub fn text_in_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let (start, end) = if start <= end { (start, end) } else { (end, start) };
+        let mut text = String::new();
+        for index in start.0..=end.0.min(self.line_count().saturating_sub(1)) {
+            let line = self.line(index);
+            let from = if index == start.0 { start.1 } else { 0 };
+            let to = if index == end.0 { end.1 } else { line.cells.len() };
+            let cells = &line.cells[from.min(line.cells.len())..to.min(line.cells.len())];
+            let part: String = cells.iter().map(|cell| cell.char).collect();
+            if index == end.0 || line.wrapped {
+                text.push_str(&part);
+            } else {
+                text.push_str(part.trim_end());
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// Returns what the terminal answered to the queries of the program, such as the cursor
+    /// position, which has to be written back to the program.
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        mem::take(&mut self.responses)
+    }
+
+    /// Resizes the screen. Lines are cut off or padded rather than rewrapped. If the screen
+    /// gets shorter the top lines go to the scrollback, and if it gets taller they come back.
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        if cols == self.cols && rows == self.rows {
+            return;
+        }
+        let is_main_screen = self.main_lines.is_none();
+        if rows < self.rows {
+            // Keep the cursor on the screen by dropping lines above it.
+            let removed = (self.cursor.y + 1).saturating_sub(rows);
+            for line in self.lines.drain(..removed) {
+                if is_main_screen {
+                    self.scrollback.push_back(line);
+                }
+            }
+            self.cursor.y -= removed;
+            self.lines.truncate(rows);
+        } else if is_main_screen {
+            while self.lines.len() < rows {
+                let Some(line) = self.scrollback.pop_back() else {
+                    break;
+                };
+                self.lines.insert(0, line);
+                self.cursor.y += 1;
+            }
+        }
+        self.lines.resize(rows, Line::new(cols, Cell::default()));
+        if let Some(main_lines) = &mut self.main_lines {
+            main_lines.resize(rows, Line::new(cols, Cell::default()));
+            for line in main_lines {
+                line.cells.resize(cols, Cell::default());
+            }
+        }
+        for line in &mut self.lines {
+            line.cells.resize(cols, Cell::default());
+        }
+        self.trim_scrollback();
+        self.cols = cols;
+        self.rows = rows;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows;
+        self.tab_stops = default_tab_stops(cols);
+        self.cursor.x = self.cursor.x.min(cols - 1);
+        self.cursor.y = self.cursor.y.min(rows - 1);
+        self.cursor.pending_wrap = false;
+    }
+
+    /// Processes the output of a program.
+    pub fn process(&mut self, bytes: &[u8]) {
+        let mut parser = mem::take(&mut self.parser);
+        parser.advance(bytes, |action| self.perform(action));
+        self.parser = parser;
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Print(char) => self.print(char),
+            Action::Control(byte) => self.control(byte),
+            Action::Csi(csi) => self.csi(&csi),
+            Action::Esc {
+                intermediate,
+                final_byte,
+            } => self.esc(intermediate, final_byte),
+            Action::Osc(osc) => {
+                if let Some(("0" | "2", title)) = osc.split_once(';') {
+                    self.title = title.to_string();
+                }
+            }
+        }
+    }
+
+    fn print(&mut self, char: char) {
+        let charset = self.charsets[self.shift_out as usize];
+        let char = if charset == Charset::LineDrawing {
+            line_drawing_char(char)
+        } else {
+            char
+        };
+        if self.cursor.pending_wrap {
+            self.lines[self.cursor.y].wrapped = true;
+            self.cursor.x = 0;
+            self.index();
+        }
+        let cell = Cell {
+            char,
+            style: self.cursor.style,
+        };
+        let cells = &mut self.lines[self.cursor.y].cells;
+        if self.insert_mode {
+            cells.insert(self.cursor.x, cell);
+            cells.truncate(self.cols);
+        } else {
+            cells[self.cursor.x] = cell;
+        }
+        if self.cursor.x + 1 < self.cols {
+            self.cursor.x += 1;
+        } else {
+            self.cursor.pending_wrap = self.autowrap;
+        }
+    }
+
+    fn control(&mut self, byte: u8) {
+        match byte {
+            // Backspace
+            0x08 => {
+                self.cursor.x = self.cursor.x.saturating_sub(1);
+                self.cursor.pending_wrap = false;
+            }
+            // Horizontal tab
+            0x09 => {
+                let x = (self.cursor.x + 1..self.cols)
+                    .find(|x| self.tab_stops[*x])
+                    .unwrap_or(self.cols - 1);
+                self.cursor.x = x;
+                self.cursor.pending_wrap = false;
+            }
+            // Line feed, vertical tab and form feed
+            0x0A..=0x0C => {
+                if self.newline_mode {
+                    self.cursor.x = 0;
+                }
+                self.index();
+            }
+            // Carriage return
+            0x0D => {
+                self.cursor.x = 0;
+                self.cursor.pending_wrap = false;
+            }
+            // Shift out and shift in
+            0x0E => self.shift_out = true,
+            0x0F => self.shift_out = false,
+            _ => {}
+        }
+    }
+
+    fn esc(&mut self, intermediate: Option<u8>, final_byte: u8) {
+        match (intermediate, final_byte) {
+            (None, b'7') => self.save_cursor(),
+            (None, b'8') => self.restore_cursor(),
+            (None, b'D') => self.index(),
+            (None, b'E') => {
+                self.cursor.x = 0;
+                self.index();
+            }
+            (None, b'H') => self.tab_stops[self.cursor.x] = true,
+            (None, b'M') => self.reverse_index(),
+            (None, b'c') => self.reset(),
+            (Some(designator @ (b'(' | b')')), charset) => {
+                self.charsets[(designator == b')') as usize] = if charset == b'0' {
+                    Charset::LineDrawing
+                } else {
+                    Charset::Ascii
+                };
+            }
+            // Fills the screen with E's, to test the alignment
+            (Some(b'#'), b'8') => {
+                for line in &mut self.lines {
+                    line.cells.fill(Cell {
+                        char: 'E',
+                        style: Style::default(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, csi: &Csi) {
+        let n = csi.param(0, 1) as usize;
+        match (csi.private, csi.intermediate, csi.final_byte) {
+            (None, None, b'A') => {
+                let top = if self.cursor.y >= self.scroll_top { self.scroll_top } else { 0 };
+                self.set_cursor(self.cursor.x, self.cursor.y.saturating_sub(n).max(top));
+            }
+            (None, None, b'B' | b'e') => self.cursor_down(n),
+            (None, None, b'C' | b'a') => self.set_cursor(self.cursor.x + n, self.cursor.y),
+            (None, None, b'D') => self.set_cursor(self.cursor.x.saturating_sub(n), self.cursor.y),
+            (None, None, b'E') => {
+                self.cursor_down(n);
+                self.cursor.x = 0;
+            }
+            (None, None, b'F') => self.set_cursor(0, self.cursor.y.saturating_sub(n)),
+            (None, None, b'G' | b'`') => self.set_cursor(n - 1, self.cursor.y),
+            (None, None, b'H' | b'f') => {
+                let row = csi.param(0, 1) as usize - 1;
+                let col = csi.param(1, 1) as usize - 1;
+                self.set_cursor_origin(col, row);
+            }
+            (None, None, b'd') => self.set_cursor_origin(self.cursor.x, n - 1),
+            (None, None, b'J') => self.erase_in_display(csi.param(0, 0)),
+            (None, None, b'K') => self.erase_in_line(csi.param(0, 0)),
+            (None, None, b'@') => {
+                let blank = self.blank_cell();
+                let x = self.cursor.x;
+                let cells = &mut self.lines[self.cursor.y].cells;
+                for _ in 0..n.min(self.cols - x) {
+                    cells.insert(x, blank);
+                }
+                cells.truncate(self.cols);
+                self.cursor.pending_wrap = false;
+            }
+            (None, None, b'P') => {
+                let blank = self.blank_cell();
+                let x = self.cursor.x;
+                let cells = &mut self.lines[self.cursor.y].cells;
+                cells.drain(x..(x + n).min(self.cols));
+                cells.resize(self.cols, blank);
+                self.cursor.pending_wrap = false;
+            }
+            (None, None, b'X') => {
+                let blank = self.blank_cell();
+                let x = self.cursor.x;
+                self.lines[self.cursor.y].cells[x..(x + n).min(self.cols)].fill(blank);
+                self.cursor.pending_wrap = false;
+            }
+            (None, None, b'L') if (self.scroll_top..self.scroll_bottom).contains(&self.cursor.y) => {
+                self.scroll_down_from(self.cursor.y, n);
+                self.cursor.x = 0;
+            }
+            (None, None, b'M') if (self.scroll_top..self.scroll_bottom).contains(&self.cursor.y) => {
+                self.scroll_up_from(self.cursor.y, n);
+                self.cursor.x = 0;
+            }
+            (None, None, b'S') => self.scroll_up_from(self.scroll_top, n),
+            (None, None, b'T') => self.scroll_down_from(self.scroll_top, n),
+            (None, None, b'r') => {
+                let top = csi.param(0, 1) as usize - 1;
+                let bottom = (csi.param(1, self.rows as u16) as usize).min(self.rows);
+                if top + 1 < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.set_cursor_origin(0, 0);
+                }
+            }
+            (None, None, b'm') => self.select_graphic_rendition(&csi.params),
+            (None, None, b's') => self.save_cursor(),
+            (None, None, b'u') => self.restore_cursor(),
+            (None, None, b'n') => match csi.param(0, 0) {
+                5 => self.responses.extend_from_slice(b"\x1b[0n"),
+                6 => {
+                    let row = if self.origin_mode {
+                        self.cursor.y.saturating_sub(self.scroll_top)
+                    } else {
+                        self.cursor.y
+                    };
+                    let response = format!("\x1b[{};{}R", row + 1, self.cursor.x + 1);
+                    self.responses.extend_from_slice(response.as_bytes());
+                }
+                _ => {}
+            },
+            (None, None, b'c') if csi.param(0, 0) == 0 => {
+                // A VT220 with ANSI colours
+                self.responses.extend_from_slice(b"\x1b[?62;22c");
+            }
+            (Some(b'>'), None, b'c') if csi.param(0, 0) == 0 => {
+                self.responses.extend_from_slice(b"\x1b[>1;10;0c");
+            }
+            (None, None, b'g') => match csi.param(0, 0) {
+                0 => self.tab_stops[self.cursor.x] = false,
+                3 => self.tab_stops.fill(false),
+                _ => {}
+            },
+            (None, None, final_byte @ (b'h' | b'l')) => {
+                for mode in &csi.params {
+                    self.set_mode(*mode, final_byte == b'h');
+                }
+            }
+            (Some(b'?'), None, final_byte @ (b'h' | b'l')) => {
+                for mode in &csi.params {
+                    self.set_private_mode(*mode, final_byte == b'h');
+                }
+            }
+            (None, Some(b'!'), b'p') => self.soft_reset(),
+            _ => {}
+        }
+    }
+
+    fn set_mode(&mut self, mode: u16, enabled: bool) {
+        match mode {
+            4 => self.insert_mode = enabled,
+            20 => self.newline_mode = enabled,
+            _ => {}
+        }
+    }
+
+    fn set_private_mode(&mut self, mode: u16, enabled: bool) {
+        match mode {
+            1 => self.application_cursor_keys = enabled,
+            6 => {
+                self.origin_mode = enabled;
+                self.set_cursor_origin(0, 0);
+            }
+            7 => self.autowrap = enabled,
+            25 => self.cursor_visible = enabled,
+            47 | 1047 => self.set_alternate_screen(enabled),
+            1048 => {
+                if enabled {
+                    self.save_cursor();
+                } else {
+                    self.restore_cursor();
+                }
+            }
+            1049 => {
+                if enabled {
+                    self.save_cursor();
+                    self.set_alternate_screen(true);
+                } else {
+                    self.set_alternate_screen(false);
+                    self.restore_cursor();
+                }
+            }
+            2004 => self.bracketed_paste = enabled,
+            _ => {}
+        }
+    }
+
+    fn set_alternate_screen(&mut self, enabled: bool) {
+        if enabled && self.main_lines.is_none() {
+            let alternate_lines = vec![Line::new(self.cols, self.blank_cell()); self.rows];
+            self.main_lines = Some(mem::replace(&mut self.lines, alternate_lines));
+        } else if !enabled {
+            if let Some(main_lines) = self.main_lines.take() {
+                self.lines = main_lines;
+            }
+        }
+        self.cursor.pending_wrap = false;
+    }
+
+    fn select_graphic_rendition(&mut self, params: &[u16]) {
+        let style = &mut self.cursor.style;
+        if params.is_empty() {
+            *style = Style::default();
+            return;
+        }
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            match param {
+                0 => *style = Style::default(),
+                1 => style.bold = true,
+                3 => style.italic = true,
+                4 => style.underline = true,
+                7 => style.inverse = true,
+                22 => style.bold = false,
+                23 => style.italic = false,
+                24 => style.underline = false,
+                27 => style.inverse = false,
+                30..=37 => style.fg = Color::Indexed((param - 30) as u8),
+                38 => style.fg = extended_color(&mut params).unwrap_or(style.fg),
+                39 => style.fg = Color::Default,
+                40..=47 => style.bg = Color::Indexed((param - 40) as u8),
+                48 => style.bg = extended_color(&mut params).unwrap_or(style.bg),
+                49 => style.bg = Color::Default,
+                90..=97 => style.fg = Color::Indexed((param - 90 + 8) as u8),
+                100..=107 => style.bg = Color::Indexed((param - 100 + 8) as u8),
+                _ => {}
+            }
+        }
+    }
+
+    fn erase_in_display(&mut self, mode: u16) {
+        let blank = self.blank_cell();
+        let (x, y) = (self.cursor.x, self.cursor.y);
+        match mode {
+            0 => {
+                self.lines[y].cells[x..].fill(blank);
+                for line in &mut self.lines[y + 1..] {
+                    *line = Line::new(self.cols, blank);
+                }
+            }
+            1 => {
+                for line in &mut self.lines[..y] {
+                    *line = Line::new(self.cols, blank);
+                }
+                self.lines[y].cells[..=x].fill(blank);
+            }
+            2 => {
+                for line in &mut self.lines {
+                    *line = Line::new(self.cols, blank);
+                }
+            }
+            3 => self.scrollback.clear(),
+            _ => {}
+        }
+        self.cursor.pending_wrap = false;
+    }
+
+    fn erase_in_line(&mut self, mode: u16) {
+        let blank = self.blank_cell();
+        let x = self.cursor.x;
+        let line = &mut self.lines[self.cursor.y];
+        match mode {
+            0 => {
+                line.cells[x..].fill(blank);
+                line.wrapped = false;
+            }
+            1 => line.cells[..=x].fill(blank),
+            2 => *line = Line::new(self.cols, blank),
+            _ => {}
+        }
+        self.cursor.pending_wrap = false;
+    }
+
+    // Moves the cursor down, scrolling if it is on the bottom row of the scroll region.
+    fn index(&mut self) {
+        self.cursor.pending_wrap = false;
+        if self.cursor.y + 1 == self.scroll_bottom {
+            self.scroll_up_from(self.scroll_top, 1);
+        } else if self.cursor.y + 1 < self.rows {
+            self.cursor.y += 1;
+        }
+    }
+
+    // Moves the cursor up, scrolling if it is on the top row of the scroll region.
+    fn reverse_index(&mut self) {
+        self.cursor.pending_wrap = false;
+        if self.cursor.y == self.scroll_top {
+            self.scroll_down_from(self.scroll_top, 1);
+        } else if self.cursor.y > 0 {
+            self.cursor.y -= 1;
+        }
+    }
+
+    // Scrolls the rows from `top` to the bottom of the scroll region up. Rows that scroll off the
+    // top of the main screen go to the scrollback.
+    fn scroll_up_from(&mut self, top: usize, count: usize) {
+        let blank = self.blank_cell();
+        for _ in 0..count.min(self.scroll_bottom - top) {
+            let line = self.lines.remove(top);
+            if top == 0 && self.main_lines.is_none() {
+                self.scrollback.push_back(line);
+            }
+            self.lines.insert(self.scroll_bottom - 1, Line::new(self.cols, blank));
+        }
+        self.trim_scrollback();
+    }
+
+    // Scrolls the rows from `top` to the bottom of the scroll region down.
+    fn scroll_down_from(&mut self, top: usize, count: usize) {
+        let blank = self.blank_cell();
+        for _ in 0..count.min(self.scroll_bottom - top) {
+            self.lines.remove(self.scroll_bottom - 1);
+            self.lines.insert(top, Line::new(self.cols, blank));
+        }
+    }
+
+    fn trim_scrollback(&mut self) {
+        while self.scrollback.len() > self.max_scrollback {
+            self.scrollback.pop_front();
+        }
+    }
+
+    // Moves the cursor down, but not past the bottom of the scroll region if it is in it.
+    fn cursor_down(&mut self, count: usize) {
+        let bottom = if self.cursor.y < self.scroll_bottom {
+            self.scroll_bottom
+        } else {
+            self.rows
+        };
+        self.set_cursor(self.cursor.x, (self.cursor.y + count).min(bottom - 1));
+    }
+
+    fn set_cursor(&mut self, x: usize, y: usize) {
+        self.cursor.x = x.min(self.cols - 1);
+        self.cursor.y = y.min(self.rows - 1);
+        self.cursor.pending_wrap = false;
+    }
+
+    // Moves the cursor to a position that is relative to the scroll region in origin mode.
+    fn set_cursor_origin(&mut self, x: usize, y: usize) {
+        if self.origin_mode {
+            let y = (self.scroll_top + y).min(self.scroll_bottom - 1);
+            self.set_cursor(x, y);
+        } else {
+            self.set_cursor(x, y);
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = SavedCursor {
+            cursor: self.cursor,
+            origin_mode: self.origin_mode,
+            charsets: self.charsets,
+            shift_out: self.shift_out,
+        };
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = self.saved_cursor;
+        self.cursor = saved.cursor;
+        self.cursor.x = self.cursor.x.min(self.cols - 1);
+        self.cursor.y = self.cursor.y.min(self.rows - 1);
+        self.origin_mode = saved.origin_mode;
+        self.charsets = saved.charsets;
+        self.shift_out = saved.shift_out;
+    }
+
+    fn soft_reset(&mut self) {
+        self.cursor.style = Style::default();
+        self.cursor.pending_wrap = false;
+        self.saved_cursor = SavedCursor::default();
+        self.scroll_top = 0;
+        self.scroll_bottom = self.rows;
+        self.charsets = [Charset::Ascii; 2];
+        self.shift_out = false;
+        self.autowrap = true;
+        self.origin_mode = false;
+        self.insert_mode = false;
+        self.cursor_visible = true;
+        self.application_cursor_keys = false;
+    }
+
+    fn reset(&mut self) {
+        let mut screen = Self::new(self.cols, self.rows);
+        screen.max_scrollback = self.max_scrollback;
+        screen.scrollback = mem::take(&mut self.scrollback);
+        *self = screen;
+    }
+
+    // Erased cells keep the current background colour.
+    fn blank_cell(&self) -> Cell {
+        Cell {
+            char: ' ',
+            style: Style {
+                bg: self.cursor.style.bg,
+                ..Style::default()
+            },
+        }
+    }
+}
+
+fn default_tab_stops(cols: usize) -> Vec<bool> {
+    (0..cols).map(|x| x > 0 && x % 8 == 0).collect()
+}
+
+// Reads the colour after a 38 or 48, which is either 5 and an index or 2 and three components.
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match params.next()? {
+        5 => Some(Color::Indexed(params.next()?.min(255) as u8)),
+        2 => {
+            let r = params.next()?.min(255) as u8;
+            let g = params.next()?.min(255) as u8;
+            let b = params.next()?.min(255) as u8;
+            Some(Color::Rgb(r, g, b))
+        }
+        _ => None,
+    }
+}
+
+fn line_drawing_char(char: char) -> char {
+    match char {
+        '`' => '◆',
+        'a' => '▒',
+        'f' => '°',
+        'g' => '±',
+        'j' => '┘',
+        'k' => '┐',
+        'l' => '┌',
+        'm' => '└',
+        'n' => '┼',
+        'o' => '⎺',
+        'p' => '⎻',
+        'q' => '─',
+        'r' => '⎼',
+        's' => '⎽',
+        't' => '├',
+        'u' => '┤',
+        'v' => '┴',
+        'w' => '┬',
+        'x' => '│',
+        'y' => '≤',
+        'z' => '≥',
+        '{' => 'π',
+        '|' => '≠',
+        '}' => '£',
+        '~' => '·',
+        _ => char,
+    }
+}
@@ -0,0 +1,125 @@
+use makepad_terminal::{Action, Csi, Parser};
+
+fn parse(bytes: &[u8]) -> Vec<Action> {
+    let mut actions = Vec::new();
+    Parser::new().advance(bytes, |action| actions.push(action));
+    actions
+}
+
+fn csi(private: Option<u8>, params: &[u16], final_byte: u8) -> Action {
+    Action::Csi(Csi {
+        private,
+        params: params.to_vec(),
+        intermediate: None,
+        final_byte,
+    })
+}
+
+#[test]
+fn prints_text_and_executes_controls() {
+    assert_eq!(
+        parse(b"ab\r\n\x07"),
+        [
+            Action::Print('a'),
+            Action::Print('b'),
+            Action::Control(b'\r'),
+            Action::Control(b'\n'),
+            Action::Control(0x07),
+        ]
+    );
+}
+
+#[test]
+fn decodes_utf8_split_over_calls() {
+    let mut parser = Parser::new();
+    let mut actions = Vec::new();
+    let bytes = "é─".as_bytes();
+    for byte in bytes {
+        parser.advance(&[*byte], |action| actions.push(action));
+    }
+    assert_eq!(actions, [Action::Print('é'), Action::Print('─')]);
+    assert_eq!(
+        parse(b"\xE2\x94a\xFF"),
+        [
+            Action::Print(char::REPLACEMENT_CHARACTER),
+            Action::Print('a'),
+            Action::Print(char::REPLACEMENT_CHARACTER),
+        ]
+    );
+}
+
+#[test]
+fn parses_control_sequences() {
+    // Colours from `ls --color`, a cursor position and the alternate screen of `less`.
+    assert_eq!(
+        parse(b"\x1b[01;34m\x1b[H\x1b[;5H\x1b[?1049h\x1b[38:2:1:2:3m"),
+        [
+            csi(None, &[1, 34], b'm'),
+            csi(None, &[], b'H'),
+            csi(None, &[0, 5], b'H'),
+            csi(Some(b'?'), &[1049], b'h'),
+            csi(None, &[38, 2, 1, 2, 3], b'm'),
+        ]
+    );
+    let Action::Csi(csi) = &parse(b"\x1b[;5H")[0] else {
+        panic!()
+    };
+    assert_eq!((csi.param(0, 1), csi.param(1, 1), csi.param(2, 1)), (1, 5, 1));
+    assert_eq!(
+        parse(b"\x1b[!p\x1b[2 q"),
+        [
+            Action::Csi(Csi {
+                private: None,
+                params: vec![],
+                intermediate: Some(b'!'),
+                final_byte: b'p',
+            }),
+            Action::Csi(Csi {
+                private: None,
+                params: vec![2],
+                intermediate: Some(b' '),
+                final_byte: b'q',
+            }),
+        ]
+    );
+}
+
+#[test]
+fn executes_controls_inside_sequences_and_aborts_on_cancel() {
+    assert_eq!(
+        parse(b"\x1b[1\n;2H\x1b[12\x18x"),
+        [
+            Action::Control(b'\n'),
+            csi(None, &[1, 2], b'H'),
+            Action::Print('x'),
+        ]
+    );
+}
+
+#[test]
+fn parses_escape_sequences_and_strings() {
+    assert_eq!(
+        parse(b"\x1b7\x1b(0\x1b]0;title\x07\x1b]2;other\x1b\\\x1bPq#0\x1b\\z"),
+        [
+            Action::Esc {
+                intermediate: None,
+                final_byte: b'7',
+            },
+            Action::Esc {
+                intermediate: Some(b'('),
+                final_byte: b'0',
+            },
+            Action::Osc("0;title".to_string()),
+            Action::Osc("2;other".to_string()),
+            Action::Esc {
+                intermediate: None,
+                final_byte: b'\\',
+            },
+            Action::Esc {
+                intermediate: None,
+                final_byte: b'\\',
+            },
+            Action::Print('z'),
+        ]
+    );
+}
@@ -0,0 +1,32 @@
+#![cfg(target_os = "linux")]
+
+use {
+    makepad_terminal::{Pty, Screen},
+    std::{io::Read, path::Path},
+};
+
+#[test]
+fn runs_a_program_in_a_pseudo_terminal() {
+    let pty = Pty::spawn(
+        "sh",
+        &["-c", "stty size; printf '\\033[1;32mok\\033[0m'"],
+        Path::new("."),
+        40,
+        5,
+    )
+    .unwrap();
+    let mut reader = pty.reader().unwrap();
+    let mut output = Vec::new();
+    let mut buf = [0; 1024];
+    // Reading fails rather than returning 0 once the program has exited.
+    while let Ok(len) = reader.read(&mut buf) {
+        if len == 0 {
+            break;
+        }
+        output.extend_from_slice(&buf[..len]);
+    }
+    let mut screen = Screen::new(40, 5);
+    screen.process(&output);
+    assert_eq!(screen.visible_text(), "5 40\nok\n\n\n");
+    assert!(screen.line(1).cells[0].style.bold);
+}
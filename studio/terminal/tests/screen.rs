@@ -0,0 +1,124 @@
+use makepad_terminal::{Color, Screen};
+
+fn run(cols: usize, rows: usize, bytes: &[u8]) -> Screen {
+    let mut screen = Screen::new(cols, rows);
+    screen.process(bytes);
+    screen
+}
+
+#[test]
+fn wraps_and_scrolls_into_the_scrollback() {
+    let screen = run(4, 2, b"abcdef\r\nghi\r\njk");
+    assert_eq!(screen.visible_text(), "ghi\njk");
+    assert_eq!(screen.scrollback_len(), 2);
+    assert_eq!(screen.line(0).text(), "abcd");
+    assert!(screen.line(0).wrapped);
+    assert_eq!(screen.text_in_range((0, 1), (2, 2)), "bcdef\ngh");
+    assert_eq!(screen.cursor(), (2, 1));
+}
+
+#[test]
+fn writing_the_last_column_does_not_wrap_until_the_next_character() {
+    let screen = run(3, 2, b"abc\r\nd");
+    assert_eq!(screen.visible_text(), "abc\nd");
+    assert!(!screen.line(0).wrapped);
+}
+
+#[test]
+fn moves_the_cursor_and_erases() {
+    let screen = run(6, 3, b"aaaaaa\r\nbbbbbb\r\ncccccc\x1b[2;3H\x1b[K\x1b[1A\x1b[2P\x1b[3;1H\x1b[1K");
+    assert_eq!(screen.visible_text(), "aaaa\nbb\n ccccc");
+    let screen = run(3, 3, b"abc\r\ndef\x1b[2J\x1b[3;1Hx");
+    assert_eq!(screen.visible_text(), "\n\nx");
+}
+
+#[test]
+fn inserts_and_deletes_characters_and_lines() {
+    let screen = run(5, 3, b"abcde\r\nfghij\r\nklmno\x1b[1;2H\x1b[2@\x1b[2;1H\x1b[L");
+    assert_eq!(screen.visible_text(), "a  bc\n\nfghij");
+    let screen = run(5, 3, b"1\r\n2\r\n3\x1b[1;1H\x1b[M");
+    assert_eq!(screen.visible_text(), "2\n3\n");
+}
+
+#[test]
+fn scrolls_inside_the_scroll_region() {
+    // Sets the region to rows 2 to 3 and feeds lines at the bottom of it, as a pager does.
+    let screen = run(4, 4, b"top\r\n\r\n\r\nbot\x1b[2;3r\x1b[3;1Ha\r\nb\r\nc");
+    assert_eq!(screen.visible_text(), "top\nb\nc\nbot");
+    assert_eq!(screen.scrollback_len(), 0);
+    // Reverse index at the top of the region scrolls it down.
+    let screen = run(4, 4, b"top\r\nx\r\ny\r\nbot\x1b[2;3r\x1b[2;1H\x1bMz");
+    assert_eq!(screen.visible_text(), "top\nz\nx\nbot");
+}
+
+#[test]
+fn switches_to_the_alternate_screen_and_back() {
+    let mut screen = run(5, 2, b"shell\r\n$ ");
+    screen.process(b"\x1b[?1049h\x1b[H\x1b[2Jvim\r\n\r\n\r\n");
+    assert!(screen.is_alternate_screen());
+    assert_eq!(screen.visible_text(), "\n");
+    assert_eq!(screen.scrollback_len(), 0);
+    screen.process(b"\x1b[?1049l");
+    assert!(!screen.is_alternate_screen());
+    assert_eq!(screen.visible_text(), "shell\n$");
+    assert_eq!(screen.cursor(), (2, 1));
+}
+
+#[test]
+fn applies_colours_and_attributes() {
+    let screen = run(8, 1, b"\x1b[1;31ma\x1b[38;5;208;48;2;1;2;3mb\x1b[0;94mc\x1b[7md\x1b[mE");
+    let cells = &screen.line(0).cells;
+    assert!(cells[0].style.bold);
+    assert_eq!(cells[0].style.fg, Color::Indexed(1));
+    assert_eq!(cells[1].style.fg, Color::Indexed(208));
+    assert_eq!(cells[1].style.bg, Color::Rgb(1, 2, 3));
+    assert!(!cells[2].style.bold);
+    assert_eq!(cells[2].style.fg, Color::Indexed(12));
+    assert!(cells[3].style.inverse);
+    assert_eq!(cells[4].style, Default::default());
+}
+
+#[test]
+fn erases_with_the_background_colour() {
+    let screen = run(3, 1, b"abc\x1b[44m\x1b[1;2H\x1b[K");
+    let cells = &screen.line(0).cells;
+    assert_eq!(cells[0].style.bg, Color::Default);
+    assert_eq!(cells[1].style.bg, Color::Indexed(4));
+    assert_eq!(cells[2].style.bg, Color::Indexed(4));
+}
+
+#[test]
+fn answers_queries_and_sets_modes() {
+    let mut screen = run(10, 5, b"\x1b[3;4H\x1b[6n\x1b[c\x1b[?1h\x1b[?2004h\x1b[?25l\x1b]0;make\x07");
+    assert_eq!(screen.take_responses(), b"\x1b[3;4R\x1b[?62;22c");
+    assert!(screen.take_responses().is_empty());
+    assert!(screen.application_cursor_keys());
+    assert!(screen.bracketed_paste());
+    assert!(!screen.cursor_visible());
+    assert_eq!(screen.title(), "make");
+}
+
+#[test]
+fn draws_lines_with_the_line_drawing_charset() {
+    let screen = run(4, 1, b"\x1b(0lqk\x1b(Bx");
+    assert_eq!(screen.visible_text(), "┌─┐x");
+}
+
+#[test]
+fn saves_and_restores_the_cursor_and_moves_to_tab_stops() {
+    let screen = run(20, 2, b"ab\x1b7\r\n\tc\x1b8d");
+    assert_eq!(screen.visible_text(), "abd\n        c");
+}
+
+#[test]
+fn resizing_moves_lines_between_the_screen_and_the_scrollback() {
+    let mut screen = run(4, 3, b"1\r\n2\r\n3");
+    screen.resize(4, 2);
+    assert_eq!(screen.visible_text(), "2\n3");
+    assert_eq!(screen.scrollback_len(), 1);
+    assert_eq!(screen.cursor(), (1, 1));
+    screen.resize(2, 3);
+    assert_eq!(screen.visible_text(), "1\n2\n3");
+    assert_eq!(screen.scrollback_len(), 0);
+    assert_eq!(screen.cursor(), (1, 2));
+}
@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+use makepad_live_compiler::{format_live_design_in_rust, LiveFormatOptions};
+
+pub fn handle_fmt(args: &[String]) -> Result<(), String> {
+    let mut check = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_ref() {
+            "--check" => check = true,
+            arg if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            arg => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        return Err("No files or directories to format".to_string())
+    }
+    let mut files = Vec::new();
+    for path in &paths {
+        collect_rust_files(path, &mut files)?;
+    }
+    let options = LiveFormatOptions::default();
+    let mut unformatted = 0;
+    let mut errors = 0;
+    for file in &files {
+        let source = std::fs::read_to_string(file).map_err(|e| format!("Cant read {}: {}", file.display(), e))?;
+        if !source.contains("live_design!") {
+            continue;
+        }
+        let formatted = match format_live_design_in_rust(&source, &options) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{}:{}", file.display(), e);
+                errors += 1;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        unformatted += 1;
+        if check {
+            println!("Not formatted: {}", file.display());
+        }
+        else {
+            std::fs::write(file, formatted).map_err(|e| format!("Cant write {}: {}", file.display(), e))?;
+            println!("Formatted: {}", file.display());
+        }
+    }
+    if errors > 0 {
+        return Err(format!("{} files could not be formatted", errors))
+    }
+    if check && unformatted > 0 {
+        return Err(format!("{} files have live_design! blocks that are not formatted", unformatted))
+    }
+    Ok(())
+}
+
+fn collect_rust_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(())
+    }
+    let entries = std::fs::read_dir(path).map_err(|e| format!("Cant read {}: {}", path.display(), e))?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') || name == "target" {
+            continue;
+        }
+        if path.is_dir() {
+            collect_rust_files(&path, files)?;
+        }
+        else if name.ends_with(".rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -3,10 +3,12 @@ mod wasm;
 mod utils;
 mod apple;
 mod check;
+mod fmt;
 use android::*;
 use wasm::*;
 use apple::*;
 use check::*;
+use fmt::*;
 pub use makepad_shell;
 pub use makepad_http;
 pub use makepad_wasm_strip;
@@ -76,6 +78,11 @@ println!("    wasm [options] run <cargo args>              Build and run a wasm
     println!("    android [options] expand-sdk");
     println!("    android [options] remove-sdk-sources");
     println!();
+    println!("Formatting commands:");
+    println!();
+    println!("    fmt [--check] <files or directories>         Format the live_design! blocks in rust files");
+    println!("                                                 --check only lists the files that are not formatted, and fails if there are any");
+    println!();
     println!("Linux commands:");
     println!();
     println!("    linux apt-get-install-makepad-deps           Call apt-get install with all dependencies needed for makepad.");
@@ -114,6 +121,10 @@ fn main() {
         "check" => if let Err(e) = handle_check(&args[1..]){
             println!("Got error: {}", e);
         }
+        "fmt" => if let Err(e) = handle_fmt(&args[1..]){
+            println!("Got error: {}", e);
+            std::process::exit(1);
+        }
         _=> show_help("not implemented yet")
     }
 }
\ No newline at end of file
@@ -0,0 +1,51 @@
+use crate::makepad_platform::*;
+
+/// The color scheme of the desktop theme. `Dark` is the theme_desktop_dark values as written,
+/// `Light` rebinds the values of theme_desktop_light onto them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DesktopTheme {
+    #[default]
+    Dark,
+    Light
+}
+
+impl DesktopTheme {
+    fn theme_module_id(module: &str) -> LiveModuleId {
+        LiveModuleId::from_str(&format!("{}::{}", env!("CARGO_CRATE_NAME"), module)).unwrap()
+    }
+}
+
+pub trait DesktopThemeCxExt {
+    /// Switches the desktop theme at runtime. All widgets are re-applied and redrawn
+    /// on the next live edit pass, so no restart is needed.
+    fn set_desktop_theme(&mut self, theme: DesktopTheme);
+    fn desktop_theme(&mut self) -> DesktopTheme;
+    /// Picks the theme matching the OS dark mode setting, on platforms that report one
+    fn follow_os_desktop_theme(&mut self);
+}
+
+impl DesktopThemeCxExt for Cx {
+    fn set_desktop_theme(&mut self, theme: DesktopTheme) {
+        if *self.global::<DesktopTheme>() == theme {
+            return
+        }
+        *self.global::<DesktopTheme>() = theme;
+        let with_module_id = match theme {
+            DesktopTheme::Dark => None,
+            DesktopTheme::Light => Some(DesktopTheme::theme_module_id("theme_desktop_light"))
+        };
+        self.rebind_live_module(DesktopTheme::theme_module_id("theme_desktop_dark"), with_module_id);
+    }
+
+    fn desktop_theme(&mut self) -> DesktopTheme {
+        *self.global::<DesktopTheme>()
+    }
+
+    fn follow_os_desktop_theme(&mut self) {
+        match self.prefers_dark_mode() {
+            Some(true) => self.set_desktop_theme(DesktopTheme::Dark),
+            Some(false) => self.set_desktop_theme(DesktopTheme::Light),
+            None => ()
+        }
+    }
+}
@@ -73,6 +73,8 @@ pub mod data_binding;
 
 pub mod base;
 pub mod theme_desktop_dark;
+pub mod theme_desktop_light;
+pub mod desktop_theme;
 pub mod image_cache;
 pub mod bare_step;
 pub mod turtle_step;
@@ -95,6 +97,7 @@ pub use crate::{
     label::*,
     slider::*,
     root::*,
+    desktop_theme::*,
     text_flow::*,
     markdown::*,
     html::*,
@@ -157,6 +160,7 @@ pub fn live_design(cx: &mut Cx) {
     crate::splitter::live_design(cx);
     crate::base::live_design(cx);
     crate::theme_desktop_dark::live_design(cx);
+    crate::theme_desktop_light::live_design(cx);
     crate::slider::live_design(cx);
     crate::label::live_design(cx);
     crate::nav_control::live_design(cx);
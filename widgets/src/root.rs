@@ -2,6 +2,7 @@
 use {
     crate::{
         widget::*,
+        desktop_theme::*,
         makepad_derive_widget::*,
         makepad_draw::*,
     }
@@ -13,11 +14,19 @@ live_design!{
 
 #[derive(Live, LiveRegisterWidget, WidgetRef)]
 pub struct Root {
+    /// Switch between the dark and light desktop theme with the OS dark mode setting
+    #[live] follow_os_theme: bool,
     #[rust] draw_state: DrawStateWrap<DrawState>,
     #[rust] windows: ComponentMap<LiveId, WidgetRef>,
 }
  
 impl LiveHook for Root {
+    fn after_new_from_doc(&mut self, cx: &mut Cx) {
+        if self.follow_os_theme {
+            cx.follow_os_desktop_theme();
+        }
+    }
+    
     fn apply_value_instance(&mut self, cx: &mut Cx, apply: &mut Apply, index: usize, nodes: &[LiveNode]) -> usize {
         let id = nodes[index].id;
         match apply.from {
@@ -75,6 +84,12 @@ impl WidgetNode for Root{
 impl Widget for Root {
     
     fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if self.follow_os_theme {
+            // platforms that read the preference off-thread signal when it changed
+            if let Event::AppGotFocus | Event::Signal = event {
+                cx.follow_os_desktop_theme();
+            }
+        }
         for window in self.windows.values_mut() {
             window.handle_event(cx, event, scope);
         }
@@ -14,6 +14,12 @@ live_design! {
     THEME_FONT_SIZE_BASE = 7.5
     THEME_FONT_SIZE_CONTRAST = 2.5// Greater values = greater font-size steps between font-formats (i.e. from H3 to H2)
 
+    // HELPERS
+    // Sizes relative to the base font size, like CSS rem units: `font_size: (rem(1.5))`
+    fn rem(x: float) -> float {
+        return x * THEME_FONT_SIZE_BASE
+    }
+
     // DIMENSIONS
     THEME_SPACE_1 = (0.5 * (THEME_SPACE_FACTOR))
     THEME_SPACE_2 = (1.0 * (THEME_SPACE_FACTOR))
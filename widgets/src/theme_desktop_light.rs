@@ -0,0 +1,28 @@
+use crate::makepad_platform::*;
+
+// The light desktop theme only holds values. Switching to it with `cx.set_desktop_theme`
+// rebinds these onto the same names in theme_desktop_dark, and everything derived from
+// them there (the U_* and D_* scales, backgrounds, text colors) is recomputed from these.
+live_design! {
+    // COLOR PALETTE
+    THEME_COLOR_W = #000000FF
+    THEME_COLOR_W_H = #00000000
+    THEME_COLOR_B = #FFFFFFFF
+    THEME_COLOR_B_H = #FFFFFF00
+
+    THEME_COLOR_FLOATING_BG = #E8E8E8FF // Elements that live on top of the UI like dialogs, popovers, and context menus.
+
+    // Progress bars, slider amounts etc.
+    THEME_COLOR_AMOUNT_DEFAULT_BIG = #5
+
+    // WIDGET SPECIFIC COLORS
+    THEME_COLOR_SLIDER_BIG_NUB_TOP = #F
+    THEME_COLOR_SLIDER_BIG_NUB_TOP_HOVER = #F
+    THEME_COLOR_SLIDER_BIG_NUB_BOTTOM = #C
+    THEME_COLOR_SLIDER_BIG_NUB_BOTTOM_HOVER = #D
+
+    // TODO: THESE ARE APPLICATION SPECIFIC COLORS THAT SHOULD BE MOVED FROM THE GENERAL THEME TO THE GIVEN PROJECT
+    THEME_COLOR_HIGH = #B00
+    THEME_COLOR_MID = #D80
+    THEME_COLOR_LOW = #580
+}
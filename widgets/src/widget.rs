@@ -700,6 +700,7 @@ impl LiveNew for WidgetRef {
             module_id: LiveModuleId::from_str(&module_path!()).unwrap(),
             live_type: LiveType::of::<dyn Widget>(),
             fields: Vec::new(),
+            variants: Vec::new(),
             live_ignore: true,
             type_name: LiveId(0),
         }
@@ -100,6 +100,7 @@ impl LiveHook for Window {
             }
             OsType::LinuxWindow(_) |
             OsType::LinuxDirect |
+            OsType::LinuxHeadless |
             OsType::Android(_) => {
                 //self.frame.get_view(id!(caption_bar)).set_visible(false);
             }